@@ -243,6 +243,7 @@ class ManagementService(
                             _mutableCurrentAccessMethod.update { event.newAccessMethod.toDomain() }
                         }
                         ManagementInterface.DaemonEvent.EventCase.REMOVE_DEVICE -> {}
+                        ManagementInterface.DaemonEvent.EventCase.LEAK_DETECTED -> {}
                         ManagementInterface.DaemonEvent.EventCase.EVENT_NOT_SET -> {}
                     }
                 }
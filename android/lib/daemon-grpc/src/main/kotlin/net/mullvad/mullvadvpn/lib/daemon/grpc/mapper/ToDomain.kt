@@ -208,6 +208,8 @@ internal fun ManagementInterface.ObfuscationEndpoint.ObfuscationType.toDomain():
         ManagementInterface.ObfuscationEndpoint.ObfuscationType.UDP2TCP -> ObfuscationType.Udp2Tcp
         ManagementInterface.ObfuscationEndpoint.ObfuscationType.SHADOWSOCKS ->
             ObfuscationType.Shadowsocks
+        ManagementInterface.ObfuscationEndpoint.ObfuscationType.QUIC ->
+            throw IllegalArgumentException("Unsupported obfuscation type")
         ManagementInterface.ObfuscationEndpoint.ObfuscationType.UNRECOGNIZED ->
             throw IllegalArgumentException("Unrecognized obfuscation type")
     }
@@ -408,6 +410,8 @@ internal fun ManagementInterface.ObfuscationSettings.SelectedObfuscation.toDomai
             ObfuscationMode.Udp2Tcp
         ManagementInterface.ObfuscationSettings.SelectedObfuscation.SHADOWSOCKS ->
             ObfuscationMode.Shadowsocks
+        ManagementInterface.ObfuscationSettings.SelectedObfuscation.QUIC ->
+            throw IllegalArgumentException("Unsupported selected obfuscation")
         ManagementInterface.ObfuscationSettings.SelectedObfuscation.UNRECOGNIZED ->
             throw IllegalArgumentException("Unrecognized selected obfuscation")
     }
@@ -645,6 +649,7 @@ internal fun ManagementInterface.CustomProxy.toDomain(): ApiAccessMethod.CustomP
         hasShadowsocks() -> shadowsocks.toDomain()
         hasSocks5Remote() -> socks5Remote.toDomain()
         hasSocks5Local() -> error("Socks5 local not supported")
+        hasHttpConnect() -> error("HTTP CONNECT proxy not supported")
         else -> error("Custom proxy not found")
     }
 
@@ -693,6 +698,8 @@ internal fun ManagementInterface.FeatureIndicator.toDomain() =
         ManagementInterface.FeatureIndicator.LOCKDOWN_MODE,
         ManagementInterface.FeatureIndicator.BRIDGE_MODE,
         ManagementInterface.FeatureIndicator.CUSTOM_MSS_FIX,
+        ManagementInterface.FeatureIndicator.TRUSTED_NETWORKS,
+        ManagementInterface.FeatureIndicator.QUIC,
         ManagementInterface.FeatureIndicator.UNRECOGNIZED ->
             error("Feature not supported ${this.name}")
     }
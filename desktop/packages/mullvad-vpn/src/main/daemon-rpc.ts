@@ -40,9 +40,12 @@ import {
   convertToApiAccessMethodSetting,
   convertToCustomList,
   convertToCustomProxy,
+  convertToEncryptedDnsUpstream,
   convertToNewApiAccessMethodSetting,
   convertToNormalBridgeSettings,
   convertToRelayConstraints,
+  convertToLocalBlocklistOptions,
+  convertToSplitDnsRoute,
   ensureExists,
 } from './grpc-type-convertions';
 
@@ -305,6 +308,11 @@ export class DaemonRpc extends GrpcClient {
           grpcTypes.ObfuscationSettings.SelectedObfuscation.UDP2TCP,
         );
         break;
+      case ObfuscationType.quic:
+        grpcObfuscationSettings.setSelectedObfuscation(
+          grpcTypes.ObfuscationSettings.SelectedObfuscation.QUIC,
+        );
+        break;
     }
 
     if (obfuscationSettings.udp2tcpSettings) {
@@ -312,6 +320,12 @@ export class DaemonRpc extends GrpcClient {
       if (obfuscationSettings.udp2tcpSettings.port !== 'any') {
         grpcUdp2tcpSettings.setPort(obfuscationSettings.udp2tcpSettings.port.only);
       }
+      if (obfuscationSettings.udp2tcpSettings.websocket) {
+        const websocket = new grpcTypes.WebSocketSettings();
+        websocket.setServerName(obfuscationSettings.udp2tcpSettings.websocket.serverName);
+        websocket.setPath(obfuscationSettings.udp2tcpSettings.websocket.path);
+        grpcUdp2tcpSettings.setWebsocket(websocket);
+      }
       grpcObfuscationSettings.setUdp2tcp(grpcUdp2tcpSettings);
     }
 
@@ -323,6 +337,17 @@ export class DaemonRpc extends GrpcClient {
       grpcObfuscationSettings.setShadowsocks(shadowsocksSettings);
     }
 
+    if (obfuscationSettings.customShadowsocks) {
+      const customShadowsocks = new grpcTypes.Shadowsocks();
+      customShadowsocks.setIp(obfuscationSettings.customShadowsocks.ip);
+      customShadowsocks.setPort(obfuscationSettings.customShadowsocks.port);
+      customShadowsocks.setPassword(obfuscationSettings.customShadowsocks.password);
+      customShadowsocks.setCipher(obfuscationSettings.customShadowsocks.cipher);
+      grpcObfuscationSettings.setCustomShadowsocks(customShadowsocks);
+    }
+
+    grpcObfuscationSettings.setLearnPerNetwork(obfuscationSettings.learnPerNetwork ?? false);
+
     await this.call<grpcTypes.ObfuscationSettings, Empty>(
       this.client.setObfuscationSettings,
       grpcObfuscationSettings,
@@ -410,7 +435,12 @@ export class DaemonRpc extends GrpcClient {
 
     const customOptions = new grpcTypes.CustomDnsOptions();
     customOptions.setAddressesList(dns.customOptions.addresses);
+    customOptions.setEncryptedUpstreamsList(
+      dns.customOptions.encryptedUpstreams.map(convertToEncryptedDnsUpstream),
+    );
     dnsOptions.setCustomOptions(customOptions);
+    dnsOptions.setSplitDnsList(dns.splitDns.map(convertToSplitDnsRoute));
+    dnsOptions.setLocalBlocklists(convertToLocalBlocklistOptions(dns.localBlocklists));
 
     if (dns.state === 'custom') {
       dnsOptions.setState(grpcTypes.DnsOptions.DnsState.CUSTOM);
@@ -80,6 +80,12 @@ export function getDefaultSettings(): ISettings {
         },
         customOptions: {
           addresses: [],
+          encryptedUpstreams: [],
+        },
+        splitDns: [],
+        localBlocklists: {
+          sources: [],
+          allowlist: [],
         },
       },
     },
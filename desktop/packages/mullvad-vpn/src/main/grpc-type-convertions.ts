@@ -6,6 +6,7 @@ import {
   AfterDisconnect,
   ApiAccessMethodSettings,
   AuthFailedError,
+  BlocklistSource,
   BridgeSettings,
   BridgesMethod,
   BridgeState,
@@ -28,6 +29,9 @@ import {
   IBridgeConstraints,
   ICustomList,
   IDevice,
+  IEncryptedDnsUpstream,
+  ILeakReport,
+  ILocalBlocklistOptions,
   IObfuscationEndpoint,
   IOpenVpnConstraints,
   IProxyEndpoint,
@@ -37,6 +41,7 @@ import {
   IRelayListWithEndpointData,
   IRelaySettingsNormal,
   ISettings,
+  ISplitDnsRoute,
   ITunnelOptions,
   ITunnelStateRelayInfo,
   IWireguardConstraints,
@@ -52,6 +57,7 @@ import {
   RelayLocation,
   RelayLocationGeographical,
   RelayProtocol,
+  RelaySelectionMode,
   RelaySettings,
   SocksAuth,
   TunnelParameterError,
@@ -384,6 +390,10 @@ function convertFromFeatureIndicator(
       return FeatureIndicator.daita;
     case grpcTypes.FeatureIndicator.SHADOWSOCKS:
       return FeatureIndicator.shadowsocks;
+    case grpcTypes.FeatureIndicator.QUIC:
+      return FeatureIndicator.quic;
+    case grpcTypes.FeatureIndicator.TRUSTED_NETWORKS:
+      return FeatureIndicator.trustedNetworks;
   }
 }
 
@@ -420,6 +430,9 @@ function convertFromObfuscationEndpoint(
     case grpcTypes.ObfuscationEndpoint.ObfuscationType.SHADOWSOCKS:
       obfuscationType = 'shadowsocks';
       break;
+    case grpcTypes.ObfuscationEndpoint.ObfuscationType.QUIC:
+      obfuscationType = 'quic';
+      break;
     default:
       throw new Error('unsupported obfuscation protocol');
   }
@@ -501,6 +514,7 @@ function convertFromRelaySettings(
         const tunnelProtocol = convertFromTunnelType(normal.getTunnelType());
         const providers = normal.getProvidersList();
         const ownership = convertFromOwnership(normal.getOwnership());
+        const selectionMode = convertFromRelaySelectionMode(normal.getSelectionMode());
         const openvpnConstraints = convertFromOpenVpnConstraints(normal.getOpenvpnConstraints()!);
         const wireguardConstraints = convertFromWireguardConstraints(
           normal.getWireguardConstraints()!,
@@ -514,6 +528,7 @@ function convertFromRelaySettings(
             ownership,
             wireguardConstraints,
             openvpnConstraints,
+            selectionMode,
           },
         };
       }
@@ -656,11 +671,78 @@ function convertFromTunnelOptions(tunnelOptions: grpcTypes.TunnelOptions.AsObjec
       },
       customOptions: {
         addresses: tunnelOptions.dnsOptions?.customOptions?.addressesList ?? [],
+        encryptedUpstreams: (
+          tunnelOptions.dnsOptions?.customOptions?.encryptedUpstreamsList ?? []
+        ).map(convertFromEncryptedDnsUpstream),
+      },
+      splitDns: (tunnelOptions.dnsOptions?.splitDnsList ?? []).map((route) => ({
+        domain: route.domain,
+        resolvers: route.resolversList,
+      })),
+      localBlocklists: {
+        sources: (tunnelOptions.dnsOptions?.localBlocklists?.sourcesList ?? []).map(
+          convertFromBlocklistSource,
+        ),
+        allowlist: tunnelOptions.dnsOptions?.localBlocklists?.allowlistList ?? [],
       },
     },
   };
 }
 
+function convertFromBlocklistSource(source: grpcTypes.BlocklistSource.AsObject): BlocklistSource {
+  return source.url ? { url: source.url } : { file: source.file };
+}
+
+function convertFromEncryptedDnsUpstream(
+  upstream: grpcTypes.EncryptedDnsUpstream.AsObject,
+): IEncryptedDnsUpstream {
+  return {
+    protocol: upstream.protocol === grpcTypes.EncryptedDnsUpstream.Protocol.TLS ? 'tls' : 'https',
+    address: upstream.address,
+    hostname: upstream.hostname,
+  };
+}
+
+export function convertToEncryptedDnsUpstream(
+  upstream: IEncryptedDnsUpstream,
+): grpcTypes.EncryptedDnsUpstream {
+  const grpcUpstream = new grpcTypes.EncryptedDnsUpstream();
+  grpcUpstream.setProtocol(
+    upstream.protocol === 'tls'
+      ? grpcTypes.EncryptedDnsUpstream.Protocol.TLS
+      : grpcTypes.EncryptedDnsUpstream.Protocol.HTTPS,
+  );
+  grpcUpstream.setAddress(upstream.address);
+  grpcUpstream.setHostname(upstream.hostname);
+  return grpcUpstream;
+}
+
+export function convertToSplitDnsRoute(route: ISplitDnsRoute): grpcTypes.SplitDnsRoute {
+  const grpcRoute = new grpcTypes.SplitDnsRoute();
+  grpcRoute.setDomain(route.domain);
+  grpcRoute.setResolversList(route.resolvers);
+  return grpcRoute;
+}
+
+export function convertToLocalBlocklistOptions(
+  options: ILocalBlocklistOptions,
+): grpcTypes.LocalBlocklistOptions {
+  const grpcOptions = new grpcTypes.LocalBlocklistOptions();
+  grpcOptions.setSourcesList(
+    options.sources.map((source) => {
+      const grpcSource = new grpcTypes.BlocklistSource();
+      if ('url' in source) {
+        grpcSource.setUrl(source.url);
+      } else {
+        grpcSource.setFile(source.file);
+      }
+      return grpcSource;
+    }),
+  );
+  grpcOptions.setAllowlistList(options.allowlist);
+  return grpcOptions;
+}
+
 function convertFromQuantumResistantState(
   state?: grpcTypes.QuantumResistantState.State,
 ): boolean | undefined {
@@ -687,16 +769,26 @@ function convertFromObfuscationSettings(
     case grpcTypes.ObfuscationSettings.SelectedObfuscation.SHADOWSOCKS:
       selectedObfuscationType = ObfuscationType.shadowsocks;
       break;
+    case grpcTypes.ObfuscationSettings.SelectedObfuscation.QUIC:
+      selectedObfuscationType = ObfuscationType.quic;
+      break;
   }
 
   return {
     selectedObfuscation: selectedObfuscationType,
     udp2tcpSettings: obfuscationSettings?.udp2tcp
-      ? { port: convertFromConstraint(obfuscationSettings.udp2tcp.port) }
+      ? {
+          port: convertFromConstraint(obfuscationSettings.udp2tcp.port),
+          websocket: obfuscationSettings.udp2tcp.websocket,
+        }
       : { port: 'any' },
     shadowsocksSettings: obfuscationSettings?.shadowsocks
       ? { port: convertFromConstraint(obfuscationSettings.shadowsocks.port) }
       : { port: 'any' },
+    customShadowsocks: obfuscationSettings?.customShadowsocks
+      ? { type: 'shadowsocks', ...obfuscationSettings.customShadowsocks }
+      : undefined,
+    learnPerNetwork: obfuscationSettings?.learnPerNetwork ?? false,
   };
 }
 
@@ -736,6 +828,11 @@ export function convertFromDaemonEvent(data: grpcTypes.DaemonEvent): DaemonEvent
     return { accessMethodSetting: convertFromApiAccessMethodSetting(newAccessMethod) };
   }
 
+  const leakDetected = data.getLeakDetected();
+  if (leakDetected !== undefined) {
+    return { leakDetected: convertFromLeakReport(leakDetected) };
+  }
+
   // Handle unknown daemon events
   const keys = Object.entries(data.toObject())
     .filter(([, value]) => value !== undefined)
@@ -743,6 +840,19 @@ export function convertFromDaemonEvent(data: grpcTypes.DaemonEvent): DaemonEvent
   throw new Error(`Unknown daemon event received containing ${keys}`);
 }
 
+function convertFromLeakReport(report: grpcTypes.LeakReport): ILeakReport {
+  const info = report.getInfo();
+  const nodeReachable = info?.getNodeReachableOnInterface();
+  const dnsReachable = info?.getDnsReachable();
+  return {
+    detectedAt: report.getDetectedAt()?.toDate() ?? new Date(),
+    interface: nodeReachable?.getInterface() ?? dnsReachable?.getInterface(),
+    reachableNodes: nodeReachable?.getReachableNodesList() ?? [],
+    amIMullvadIp: info?.getAmIMullvad()?.getIp(),
+    dnsResolvers: dnsReachable?.getResolversList() ?? [],
+  };
+}
+
 function convertFromOwnership(ownership: grpcTypes.Ownership): Ownership {
   switch (ownership) {
     case grpcTypes.Ownership.ANY:
@@ -765,6 +875,29 @@ function convertToOwnership(ownership: Ownership): grpcTypes.Ownership {
   }
 }
 
+function convertFromRelaySelectionMode(
+  selectionMode: grpcTypes.RelaySelectionMode,
+): RelaySelectionMode {
+  switch (selectionMode) {
+    case grpcTypes.RelaySelectionMode.RANDOM:
+      return 'random';
+    case grpcTypes.RelaySelectionMode.FASTEST:
+      return 'fastest';
+  }
+}
+
+function convertToRelaySelectionMode(
+  selectionMode?: RelaySelectionMode,
+): grpcTypes.RelaySelectionMode {
+  switch (selectionMode) {
+    case 'fastest':
+      return grpcTypes.RelaySelectionMode.FASTEST;
+    case 'random':
+    case undefined:
+      return grpcTypes.RelaySelectionMode.RANDOM;
+  }
+}
+
 function convertFromOpenVpnConstraints(
   constraints: grpcTypes.OpenvpnConstraints,
 ): IOpenVpnConstraints {
@@ -844,6 +977,7 @@ export function convertToRelayConstraints(
   );
   relayConstraints.setProvidersList(constraints.providers);
   relayConstraints.setOwnership(convertToOwnership(constraints.ownership));
+  relayConstraints.setSelectionMode(convertToRelaySelectionMode(constraints.selectionMode));
 
   return relayConstraints;
 }
@@ -1124,6 +1258,22 @@ export function convertToCustomProxy(proxy: CustomProxy): grpcTypes.CustomProxy
       customProxy.setShadowsocks(shadowsocks);
       break;
     }
+    case 'http-connect': {
+      const httpConnect = new grpcTypes.HttpConnect();
+      httpConnect.setIp(proxy.ip);
+      httpConnect.setPort(proxy.port);
+      if (proxy.authentication !== undefined) {
+        const auth = new grpcTypes.HttpAuth();
+        auth.setUsername(proxy.authentication.username);
+        auth.setPassword(proxy.authentication.password);
+        httpConnect.setAuth(auth);
+      }
+      if (proxy.tlsServerName !== undefined) {
+        httpConnect.setTlsServerName(proxy.tlsServerName);
+      }
+      customProxy.setHttpConnect(httpConnect);
+      break;
+    }
   }
 
   return customProxy;
@@ -1240,6 +1390,20 @@ function convertFromCustomProxy(proxy: grpcTypes.CustomProxy): CustomProxy {
         cipher: shadowsocks.getCipher(),
       };
     }
+    case grpcTypes.CustomProxy.ProxyMethodCase.HTTP_CONNECT: {
+      const httpConnect = proxy.getHttpConnect()!;
+      const auth = httpConnect.getAuth();
+      return {
+        type: 'http-connect',
+        ip: httpConnect.getIp(),
+        port: httpConnect.getPort(),
+        authentication:
+          auth === undefined
+            ? undefined
+            : { username: auth.getUsername(), password: auth.getPassword() },
+        tlsServerName: httpConnect.hasTlsServerName() ? httpConnect.getTlsServerName() : undefined,
+      };
+    }
     case grpcTypes.CustomProxy.ProxyMethodCase.PROXY_METHOD_NOT_SET:
       throw new Error('Custom method not set, which should always be set');
   }
@@ -726,6 +726,8 @@ class ApplicationMain
           IpcMainEventChannel.settings.notifyApiAccessMethodSettingChange?.(
             daemonEvent.accessMethodSetting,
           );
+        } else if ('leakDetected' in daemonEvent) {
+          log.warn('Network leak detected', daemonEvent.leakDetected);
         }
       },
       (error: Error) => {
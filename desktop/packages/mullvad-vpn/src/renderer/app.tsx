@@ -633,6 +633,7 @@ export default class AppRenderer {
         tunnelProtocol,
         providers,
         ownership,
+        selectionMode,
       } = relaySettings.normal;
 
       actions.settings.updateRelay({
@@ -640,6 +641,7 @@ export default class AppRenderer {
           location: liftConstraint(location),
           providers,
           ownership,
+          selectionMode,
           openvpn: {
             port: liftConstraint(openvpnConstraints.port),
             protocol: liftConstraint(openvpnConstraints.protocol),
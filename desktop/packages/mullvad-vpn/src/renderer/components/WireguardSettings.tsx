@@ -215,6 +215,10 @@ function ObfuscationSettings() {
           ariaLabel: messages.pgettext('accessibility', 'UDP-over-TCP settings'),
         },
       },
+      {
+        label: messages.pgettext('wireguard-settings-view', 'QUIC'),
+        value: ObfuscationType.quic,
+      },
       {
         label: messages.gettext('Off'),
         value: ObfuscationType.off,
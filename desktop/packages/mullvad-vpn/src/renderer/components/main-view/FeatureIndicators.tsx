@@ -243,6 +243,7 @@ function getFeatureIndicatorLabel(indicator: FeatureIndicator) {
       return strings.daita;
     case FeatureIndicator.udp2tcp:
     case FeatureIndicator.shadowsocks:
+    case FeatureIndicator.quic:
       return messages.pgettext('wireguard-settings-view', 'Obfuscation');
     case FeatureIndicator.multihop:
       // TRANSLATORS: This refers to the multihop setting in the VPN settings view. This is
@@ -272,5 +273,9 @@ function getFeatureIndicatorLabel(indicator: FeatureIndicator) {
       return messages.gettext('Quantum resistance');
     case FeatureIndicator.dnsContentBlockers:
       return messages.pgettext('vpn-settings-view', 'DNS content blockers');
+    case FeatureIndicator.trustedNetworks:
+      // TRANSLATORS: This refers to the trusted networks setting, which disconnects the VPN on
+      // TRANSLATORS: networks trusted by the user. This is displayed when the feature is on.
+      return messages.gettext('Trusted networks');
   }
 }
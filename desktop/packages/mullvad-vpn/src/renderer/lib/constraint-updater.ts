@@ -34,6 +34,7 @@ export function wrapRelaySettingsOrDefault(
     return {
       providers: [...relaySettings.providers],
       ownership: relaySettings.ownership,
+      selectionMode: relaySettings.selectionMode,
       tunnelProtocol,
       openvpnConstraints: {
         port: openvpnPort,
@@ -19,6 +19,7 @@ import {
   RelayLocation,
   RelayOverride,
   RelayProtocol,
+  RelaySelectionMode,
   TunnelProtocol,
 } from '../../../shared/daemon-rpc-types';
 import { IGuiSettingsState } from '../../../shared/gui-settings-state';
@@ -29,6 +30,9 @@ export type NormalRelaySettingsRedux = {
   location: LiftedConstraint<RelayLocation>;
   providers: string[];
   ownership: Ownership;
+  // Only configurable from the CLI, but kept so that it is not reset when the relay settings
+  // are changed from the GUI.
+  selectionMode?: RelaySelectionMode;
   openvpn: {
     port: LiftedConstraint<number>;
     protocol: LiftedConstraint<RelayProtocol>;
@@ -179,6 +183,12 @@ const initialState: ISettingsReduxState = {
     },
     customOptions: {
       addresses: [],
+      encryptedUpstreams: [],
+    },
+    splitDns: [],
+    localBlocklists: {
+      sources: [],
+      allowlist: [],
     },
   },
   splitTunneling: false,
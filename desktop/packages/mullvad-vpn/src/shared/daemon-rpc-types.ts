@@ -110,7 +110,7 @@ export function tunnelTypeToString(tunnel: TunnelType): string {
 }
 
 export type RelayProtocol = 'tcp' | 'udp';
-export type EndpointObfuscationType = 'udp2tcp' | 'shadowsocks';
+export type EndpointObfuscationType = 'udp2tcp' | 'shadowsocks' | 'quic';
 
 export type Constraint<T> = 'any' | { only: T };
 export type LiftedConstraint<T> = 'any' | T;
@@ -171,7 +171,18 @@ export type DaemonEvent =
   | { appVersionInfo: IAppVersionInfo }
   | { device: DeviceEvent }
   | { deviceRemoval: Array<IDevice> }
-  | { accessMethodSetting: AccessMethodSetting };
+  | { accessMethodSetting: AccessMethodSetting }
+  | { leakDetected: ILeakReport };
+
+export interface ILeakReport {
+  detectedAt: Date;
+  // Set when other network nodes or DNS resolvers were reachable on this physical interface.
+  interface?: string;
+  reachableNodes: Array<string>;
+  // Set when am.i.mullvad.net saw traffic from this non-Mullvad IP.
+  amIMullvadIp?: string;
+  dnsResolvers: Array<string>;
+}
 
 export interface ITunnelStateRelayInfo {
   endpoint: ITunnelEndpoint;
@@ -188,12 +199,14 @@ export enum FeatureIndicator {
   lockdownMode,
   udp2tcp,
   shadowsocks,
+  quic,
   lanSharing,
   dnsContentBlockers,
   customDns,
   serverIpOverride,
   customMtu,
   customMssFix,
+  trustedNetworks,
 }
 
 export type DisconnectedState = { state: 'disconnected'; location?: Partial<ILocation> };
@@ -267,8 +280,12 @@ export interface IRelaySettingsNormal<OpenVpn, Wireguard> {
   ownership: Ownership;
   openvpnConstraints: OpenVpn;
   wireguardConstraints: Wireguard;
+  // Only configurable from the CLI.
+  selectionMode?: RelaySelectionMode;
 }
 
+export type RelaySelectionMode = 'random' | 'fastest';
+
 export type ConnectionConfig =
   | {
       openvpn: {
@@ -366,10 +383,29 @@ export interface ITunnelOptions {
   dns: IDnsOptions;
 }
 
+export interface IEncryptedDnsUpstream {
+  protocol: 'https' | 'tls';
+  address: string;
+  hostname: string;
+}
+
+export interface ISplitDnsRoute {
+  domain: string;
+  resolvers: string[];
+}
+
+export type BlocklistSource = { file: string } | { url: string };
+
+export interface ILocalBlocklistOptions {
+  sources: BlocklistSource[];
+  allowlist: string[];
+}
+
 export interface IDnsOptions {
   state: 'custom' | 'default';
   customOptions: {
     addresses: string[];
+    encryptedUpstreams: IEncryptedDnsUpstream[];
   };
   defaultOptions: {
     blockAds: boolean;
@@ -379,6 +415,8 @@ export interface IDnsOptions {
     blockGambling: boolean;
     blockSocialMedia: boolean;
   };
+  splitDns: ISplitDnsRoute[];
+  localBlocklists: ILocalBlocklistOptions;
 }
 
 export interface IAppVersionInfo {
@@ -447,6 +485,13 @@ export type SplitTunnelSettings = {
 
 export type Udp2TcpObfuscationSettings = {
   port: Constraint<number>;
+  // WebSocket over TLS framing. Only configurable from the CLI.
+  websocket?: WebSocketSettings;
+};
+
+export type WebSocketSettings = {
+  serverName: string;
+  path: string;
 };
 
 export type ShadowsocksSettings = {
@@ -458,12 +503,17 @@ export enum ObfuscationType {
   off,
   udp2tcp,
   shadowsocks,
+  quic,
 }
 
 export type ObfuscationSettings = {
   selectedObfuscation: ObfuscationType;
   udp2tcpSettings: Udp2TcpObfuscationSettings;
   shadowsocksSettings: ShadowsocksSettings;
+  // Self-hosted server used with custom WireGuard endpoints. Only configurable from the CLI.
+  customShadowsocks?: ShadowsocksCustomProxy;
+  // Only configurable from the CLI, and only supported on Linux and macOS.
+  learnPerNetwork?: boolean;
 };
 
 export interface IBridgeConstraints {
@@ -517,7 +567,19 @@ export type ShadowsocksCustomProxy = {
   cipher: string;
 };
 
-export type CustomProxy = Socks5LocalCustomProxy | Socks5RemoteCustomProxy | ShadowsocksCustomProxy;
+export type HttpConnectCustomProxy = {
+  type: 'http-connect';
+  ip: string;
+  port: number;
+  authentication?: SocksAuth;
+  tlsServerName?: string;
+};
+
+export type CustomProxy =
+  | Socks5LocalCustomProxy
+  | Socks5RemoteCustomProxy
+  | ShadowsocksCustomProxy
+  | HttpConnectCustomProxy;
 export type NamedCustomProxy = CustomProxy & { name: string };
 
 export type DirectMethod = { type: 'direct' };
@@ -1,8 +1,23 @@
-//! This module keeps track of the last known good API IP address and reads and stores it on disk.
+//! This module keeps track of known API IP addresses and how reliable they have been, and reads
+//! and stores them on disk.
+//!
+//! The address with the fewest consecutive connection failures is used. Ties are broken by the
+//! success rate of each address, and then by the order returned by the API. Since failing
+//! addresses are demoted, the cache will rotate through all known addresses if they keep failing.
+//!
+//! The selected address is stored on its own in the address file, which is the format that older
+//! versions read. The history of all known addresses is stored in a separate JSON file next to it.
+//! It is only written when the ranking of the addresses changes.
 
 use crate::{ApiEndpoint, DnsResolver};
 use async_trait::async_trait;
-use std::{io, net::SocketAddr, path::Path, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::{
     fs,
     io::{AsyncReadExt, AsyncWriteExt},
@@ -20,6 +35,9 @@ pub enum Error {
     #[error("Failed to parse the address cache file")]
     Parse,
 
+    #[error("Failed to serialize the address cache")]
+    Serialize(#[source] serde_json::Error),
+
     #[error("Failed to update the address cache file")]
     Write(#[source] io::Error),
 }
@@ -33,6 +51,18 @@ impl DnsResolver for AddressCache {
             .map(|addr| vec![addr])
             .ok_or(io::Error::other("host does not match API host"))
     }
+
+    async fn report_success(&self, address: SocketAddr) {
+        if let Err(error) = self.record_result(address, true).await {
+            log::error!("Failed to update API address cache: {error}");
+        }
+    }
+
+    async fn report_failure(&self, address: SocketAddr) {
+        if let Err(error) = self.record_result(address, false).await {
+            log::error!("Failed to update API address cache: {error}");
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -45,23 +75,41 @@ pub struct AddressCache {
 impl AddressCache {
     /// Initialize cache using the hardcoded address, and write changes to `write_path`.
     pub fn new(endpoint: &ApiEndpoint, write_path: Option<Box<Path>>) -> Self {
-        Self::new_inner(endpoint.address(), endpoint.host().to_owned(), write_path)
+        let cache = AddressCacheInner::from_addresses(&[endpoint.address()]);
+        Self::new_inner(cache, endpoint.host().to_owned(), write_path)
     }
 
-    /// Initialize cache using `read_path`, and write changes to `write_path`.
+    /// Initialize cache using `read_path`, and write changes to `write_path`. The history of the
+    /// addresses is read from the file next to `read_path`, if there is one.
     pub async fn from_file(
         read_path: &Path,
         write_path: Option<Box<Path>>,
         hostname: String,
     ) -> Result<Self, Error> {
-        log::debug!("Loading API addresses from {}", read_path.display());
+        log::debug!("Loading API address from {}", read_path.display());
         let address = read_address_file(read_path).await?;
-        Ok(Self::new_inner(address, hostname, write_path))
+        let history_path = history_path(read_path);
+        let cache = match read_history_file(&history_path).await {
+            Ok(mut cache) => {
+                cache.insert_address(address);
+                cache
+            }
+            Err(error) => {
+                if history_path.exists() {
+                    log::warn!("Ignoring API address history: {error}");
+                }
+                AddressCacheInner::from_addresses(&[address])
+            }
+        };
+        Ok(Self::new_inner(cache, hostname, write_path))
     }
 
-    fn new_inner(address: SocketAddr, hostname: String, write_path: Option<Box<Path>>) -> Self {
-        let cache = AddressCacheInner::from_address(address);
-        log::debug!("Using API address: {}", cache.address);
+    fn new_inner(
+        cache: AddressCacheInner,
+        hostname: String,
+        write_path: Option<Box<Path>>,
+    ) -> Self {
+        log::debug!("Using API address: {}", cache.address());
 
         Self {
             inner: Arc::new(Mutex::new(cache)),
@@ -79,54 +127,323 @@ impl AddressCache {
         }
     }
 
-    /// Returns the currently selected address.
+    /// Returns the currently selected address, i.e. the healthiest known address.
     pub async fn get_address(&self) -> SocketAddr {
-        self.inner.lock().await.address
+        self.inner.lock().await.address()
     }
 
-    pub async fn set_address(&self, address: SocketAddr) -> Result<(), Error> {
+    /// Replace the known addresses with `addresses`, in order of preference. The history of
+    /// addresses that were already known is kept.
+    pub async fn set_addresses(&self, addresses: &[SocketAddr]) -> Result<(), Error> {
+        if addresses.is_empty() {
+            return Ok(());
+        }
         let mut inner = self.inner.lock().await;
-        if address != inner.address {
-            self.save_to_disk(&address).await?;
-            inner.address = address;
+        let mut new_inner = inner.clone();
+        new_inner.set_addresses(addresses);
+        if new_inner != *inner {
+            self.save_to_disk(&new_inner).await?;
+            *inner = new_inner;
         }
         Ok(())
     }
 
-    async fn save_to_disk(&self, address: &SocketAddr) -> Result<(), Error> {
+    /// Record whether connecting to `address` succeeded. Unknown addresses are ignored. The
+    /// cache is only saved if this changes the ranking of the addresses.
+    async fn record_result(&self, address: SocketAddr, success: bool) -> Result<(), Error> {
+        let mut inner = self.inner.lock().await;
+        let previous_ranking = inner.ranking();
+        if !inner.record_result(address, success) {
+            return Ok(());
+        }
+        let new_ranking = inner.ranking();
+        if new_ranking == previous_ranking {
+            return Ok(());
+        }
+        if new_ranking[0] != previous_ranking[0] {
+            log::debug!(
+                "Switching API address from {} to {}",
+                previous_ranking[0],
+                new_ranking[0]
+            );
+        }
+        self.save_to_disk(&inner).await
+    }
+
+    async fn save_to_disk(&self, cache: &AddressCacheInner) -> Result<(), Error> {
         let write_path = match self.write_path.as_ref() {
             Some(write_path) => write_path,
             None => return Ok(()),
         };
 
-        let mut file = mullvad_fs::AtomicFile::new(&**write_path)
-            .await
-            .map_err(Error::Open)?;
-        let mut contents = address.to_string();
-        contents += "\n";
-        file.write_all(contents.as_bytes())
-            .await
-            .map_err(Error::Write)?;
-        file.finalize().await.map_err(Error::Write)
+        let mut history = serde_json::to_string_pretty(cache).map_err(Error::Serialize)?;
+        history += "\n";
+        write_file(&history_path(write_path), &history).await?;
+
+        let mut address = cache.address().to_string();
+        address += "\n";
+        write_file(write_path, &address).await
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+async fn write_file(path: &Path, contents: &str) -> Result<(), Error> {
+    let mut file = mullvad_fs::AtomicFile::new(path)
+        .await
+        .map_err(Error::Open)?;
+    file.write_all(contents.as_bytes())
+        .await
+        .map_err(Error::Write)?;
+    file.finalize().await.map_err(Error::Write)
+}
+
+/// Returns the path of the file that stores the history of all known addresses, given the path of
+/// the address file.
+fn history_path(address_path: &Path) -> PathBuf {
+    address_path.with_extension("json")
+}
+
+/// Known API addresses, in the order returned by the API. There is always at least one address.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 struct AddressCacheInner {
+    addresses: Vec<AddressHealth>,
+}
+
+/// Connection history of a single API address.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct AddressHealth {
     address: SocketAddr,
+    #[serde(default)]
+    successes: u32,
+    #[serde(default)]
+    failures: u32,
+    /// Number of failures since the last successful connection.
+    #[serde(default)]
+    consecutive_failures: u32,
+}
+
+impl AddressHealth {
+    fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            successes: 0,
+            failures: 0,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Ratio of successful connections, in per mille. Untried addresses are given the benefit of
+    /// the doubt.
+    fn success_rate(&self) -> u64 {
+        let attempts = u64::from(self.successes) + u64::from(self.failures);
+        if attempts == 0 {
+            return 1000;
+        }
+        u64::from(self.successes) * 1000 / attempts
+    }
 }
 
 impl AddressCacheInner {
-    fn from_address(address: SocketAddr) -> Self {
-        Self { address }
+    fn from_addresses(addresses: &[SocketAddr]) -> Self {
+        Self {
+            addresses: addresses.iter().copied().map(AddressHealth::new).collect(),
+        }
+    }
+
+    /// Returns the healthiest address.
+    fn address(&self) -> SocketAddr {
+        self.ranking()[0]
+    }
+
+    /// Returns all addresses, from the healthiest to the least healthy.
+    fn ranking(&self) -> Vec<SocketAddr> {
+        let mut addresses: Vec<_> = self.addresses.iter().enumerate().collect();
+        addresses.sort_by_key(|(index, health)| {
+            (
+                health.consecutive_failures,
+                std::cmp::Reverse(health.success_rate()),
+                *index,
+            )
+        });
+        addresses
+            .into_iter()
+            .map(|(_, health)| health.address)
+            .collect()
+    }
+
+    /// Add `address` as the most preferred address, unless it is already known.
+    fn insert_address(&mut self, address: SocketAddr) {
+        if !self
+            .addresses
+            .iter()
+            .any(|health| health.address == address)
+        {
+            self.addresses.insert(0, AddressHealth::new(address));
+        }
+    }
+
+    fn set_addresses(&mut self, addresses: &[SocketAddr]) {
+        let mut new_addresses = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            if new_addresses
+                .iter()
+                .any(|health: &AddressHealth| health.address == *address)
+            {
+                continue;
+            }
+            let health = self
+                .addresses
+                .iter()
+                .find(|health| health.address == *address)
+                .cloned()
+                .unwrap_or_else(|| AddressHealth::new(*address));
+            new_addresses.push(health);
+        }
+        self.addresses = new_addresses;
+    }
+
+    /// Update the history of `address`. Returns whether the address is known.
+    fn record_result(&mut self, address: SocketAddr, success: bool) -> bool {
+        let Some(health) = self
+            .addresses
+            .iter_mut()
+            .find(|health| health.address == address)
+        else {
+            return false;
+        };
+        if success {
+            health.successes = health.successes.saturating_add(1);
+            health.consecutive_failures = 0;
+        } else {
+            health.failures = health.failures.saturating_add(1);
+            health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        }
+        true
     }
 }
 
 async fn read_address_file(path: &Path) -> Result<SocketAddr, Error> {
+    read_file(path)
+        .await?
+        .trim()
+        .parse()
+        .map_err(|_| Error::Parse)
+}
+
+async fn read_history_file(path: &Path) -> Result<AddressCacheInner, Error> {
+    parse_history_file(&read_file(path).await?)
+}
+
+async fn read_file(path: &Path) -> Result<String, Error> {
     let mut file = fs::File::open(path).await.map_err(Error::Open)?;
-    let mut address = String::new();
-    file.read_to_string(&mut address)
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
         .await
         .map_err(Error::Read)?;
-    address.trim().parse().map_err(|_| Error::Parse)
+    Ok(contents)
+}
+
+fn parse_history_file(contents: &str) -> Result<AddressCacheInner, Error> {
+    let cache: AddressCacheInner = serde_json::from_str(contents).map_err(|_| Error::Parse)?;
+    if cache.addresses.is_empty() {
+        return Err(Error::Parse);
+    }
+    Ok(cache)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ADDR_A: &str = "1.1.1.1:443";
+    const ADDR_B: &str = "2.2.2.2:443";
+    const ADDR_C: &str = "3.3.3.3:443";
+
+    fn addr(address: &str) -> SocketAddr {
+        address.parse().unwrap()
+    }
+
+    /// Failing addresses should be demoted, rotating through all addresses if they all fail
+    #[test]
+    fn test_rotate_on_failure() {
+        let mut cache = AddressCacheInner::from_addresses(&[addr(ADDR_A), addr(ADDR_B)]);
+        assert_eq!(cache.address(), addr(ADDR_A));
+
+        cache.record_result(addr(ADDR_A), false);
+        assert_eq!(cache.address(), addr(ADDR_B));
+        cache.record_result(addr(ADDR_B), false);
+        assert_eq!(cache.address(), addr(ADDR_A));
+
+        // A single success is enough to prefer an address again
+        cache.record_result(addr(ADDR_B), true);
+        assert_eq!(cache.address(), addr(ADDR_B));
+
+        // Equally healthy addresses are picked in the order returned by the API
+        cache.record_result(addr(ADDR_A), true);
+        assert_eq!(cache.address(), addr(ADDR_A));
+    }
+
+    /// Replacing the address list should keep the history of known addresses
+    #[test]
+    fn test_set_addresses_keeps_history() {
+        let mut cache = AddressCacheInner::from_addresses(&[addr(ADDR_A), addr(ADDR_B)]);
+        cache.record_result(addr(ADDR_A), false);
+
+        cache.set_addresses(&[addr(ADDR_C), addr(ADDR_A), addr(ADDR_C)]);
+        assert_eq!(cache.addresses.len(), 2);
+        assert_eq!(cache.addresses[1].consecutive_failures, 1);
+        assert_eq!(cache.address(), addr(ADDR_C));
+    }
+
+    #[test]
+    fn test_parse_history_file() {
+        let mut cache = AddressCacheInner::from_addresses(&[addr(ADDR_A), addr(ADDR_B)]);
+        cache.record_result(addr(ADDR_A), false);
+        let contents = serde_json::to_string_pretty(&cache).unwrap();
+        assert_eq!(parse_history_file(&contents).unwrap(), cache);
+
+        assert!(parse_history_file(r#"{"addresses":[]}"#).is_err());
+    }
+
+    /// Results that do not change the ranking should not be saved
+    #[tokio::test]
+    async fn test_save_on_ranking_change() {
+        let dir = std::env::temp_dir().join(format!("address-cache-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir(&dir).await.unwrap();
+        let path = dir.join(crate::API_IP_CACHE_FILENAME);
+        let cache = AddressCache::new_inner(
+            AddressCacheInner::from_addresses(&[addr(ADDR_A), addr(ADDR_B)]),
+            "api.test".to_owned(),
+            Some(path.clone().into_boxed_path()),
+        );
+
+        cache.record_result(addr(ADDR_A), true).await.unwrap();
+        assert!(!path.exists());
+
+        cache.record_result(addr(ADDR_A), false).await.unwrap();
+        assert_eq!(read_address_file(&path).await.unwrap(), addr(ADDR_B));
+
+        // Older versions only read the address file. The history is read back if it exists.
+        let loaded = AddressCache::from_file(&path, None, "api.test".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(*loaded.inner.lock().await, *cache.inner.lock().await);
+
+        tokio::fs::remove_file(history_path(&path)).await.unwrap();
+        let loaded = AddressCache::from_file(&path, None, "api.test".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(loaded.get_address().await, addr(ADDR_B));
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    /// An address written by an older version should be known after upgrading
+    #[test]
+    fn test_insert_address() {
+        let mut cache = AddressCacheInner::from_addresses(&[addr(ADDR_A)]);
+        cache.insert_address(addr(ADDR_A));
+        assert_eq!(cache.addresses.len(), 1);
+        cache.insert_address(addr(ADDR_B));
+        assert_eq!(cache.address(), addr(ADDR_B));
+    }
 }
@@ -124,7 +124,8 @@ impl ApiAvailability {
         self.get_state().is_offline()
     }
 
-    fn get_state(&self) -> State {
+    /// Return the current availability state
+    pub fn get_state(&self) -> State {
         self.acquire().state
     }
 
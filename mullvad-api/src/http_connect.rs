@@ -0,0 +1,166 @@
+//! Tunnel a TCP connection through an HTTP proxy using the `CONNECT` method.
+use std::{io, net::SocketAddr};
+use talpid_types::net::proxy::HttpAuth;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Upper bound on the size of the status line and headers returned by the proxy.
+const MAX_RESPONSE_HEAD_SIZE: usize = 8 * 1024;
+
+/// Ask the HTTP proxy at the other end of `stream` to open a tunnel to `target`.
+///
+/// On success, the returned stream is connected to `target`.
+pub async fn connect<S>(
+    mut stream: S,
+    target: &SocketAddr,
+    auth: Option<&HttpAuth>,
+) -> io::Result<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(auth) = auth {
+        request.push_str(&format!(
+            "Proxy-Authorization: {}\r\n",
+            auth.to_header_value()
+        ));
+    }
+    request.push_str("\r\n");
+
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    let head = read_response_head(&mut stream).await?;
+    match parse_status_code(&head)? {
+        200..=299 => Ok(stream),
+        407 => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "HTTP proxy authentication failed",
+        )),
+        status => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("HTTP proxy refused to connect: status {status}"),
+        )),
+    }
+}
+
+/// Read the response up to and including the empty line that terminates the headers.
+///
+/// This reads one byte at a time so that nothing sent by the target after the
+/// headers is consumed.
+async fn read_response_head<S>(stream: &mut S) -> io::Result<Vec<u8>>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HTTP proxy response is too large",
+            ));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    Ok(head)
+}
+
+fn parse_status_code(head: &[u8]) -> io::Result<u16> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP proxy response");
+
+    let status_line = head.split(|&b| b == b'\n').next().ok_or_else(invalid)?;
+    let status_line = std::str::from_utf8(status_line).map_err(|_| invalid())?;
+    let mut parts = status_line.split_whitespace();
+
+    match parts.next() {
+        Some(version) if version.starts_with("HTTP/1.") => (),
+        _ => return Err(invalid()),
+    }
+    parts
+        .next()
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{duplex, AsyncBufReadExt, BufReader};
+
+    const TARGET: &str = "[2001:db8::1]:443";
+
+    /// Accept a single `CONNECT` request and reply with `response`.
+    /// Returns the request head received by the proxy.
+    async fn mock_proxy<S>(stream: S, response: &'static str) -> String
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut stream = BufReader::new(stream);
+        let mut request = String::new();
+        while !request.ends_with("\r\n\r\n") {
+            stream.read_line(&mut request).await.unwrap();
+        }
+        stream.write_all(response.as_bytes()).await.unwrap();
+        request
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        let (client, server) = duplex(1024);
+        let proxy = tokio::spawn(mock_proxy(
+            server,
+            "HTTP/1.1 200 Connection established\r\n\r\nhello",
+        ));
+
+        let mut stream = connect(client, &TARGET.parse().unwrap(), None)
+            .await
+            .unwrap();
+        let request = proxy.await.unwrap();
+        assert_eq!(
+            request,
+            format!("CONNECT {TARGET} HTTP/1.1\r\nHost: {TARGET}\r\n\r\n")
+        );
+
+        // Data following the response head must be left for the caller
+        let mut data = [0u8; 5];
+        stream.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_auth() {
+        let (client, server) = duplex(1024);
+        let proxy = tokio::spawn(mock_proxy(server, "HTTP/1.0 200 OK\r\n\r\n"));
+
+        let auth = HttpAuth::new("user".to_string(), "pass".to_string()).unwrap();
+        connect(client, &TARGET.parse().unwrap(), Some(&auth))
+            .await
+            .unwrap();
+        let request = proxy.await.unwrap();
+        assert!(request.contains("\r\nProxy-Authorization: Basic dXNlcjpwYXNz\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_auth_required() {
+        let (client, server) = duplex(1024);
+        tokio::spawn(mock_proxy(
+            server,
+            "HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: Basic\r\n\r\n",
+        ));
+
+        let error = connect(client, &TARGET.parse().unwrap(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_connect_invalid_response() {
+        let (client, server) = duplex(1024);
+        tokio::spawn(mock_proxy(server, "SSH-2.0-OpenSSH\r\n\r\n"));
+
+        let error = connect(client, &TARGET.parse().unwrap(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
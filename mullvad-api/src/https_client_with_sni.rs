@@ -1,5 +1,6 @@
 use crate::{
     abortable_stream::{AbortableStream, AbortableStreamHandle},
+    http_connect,
     proxy::{ApiConnection, ApiConnectionMode, ProxyConfig},
     tls_stream::TlsStream,
     DnsResolver,
@@ -78,6 +79,8 @@ enum InnerConnectionMode {
     Shadowsocks(ShadowsocksConfig),
     /// Connect to the destination via a Socks proxy.
     Socks5(SocksConfig),
+    /// Connect to the destination via an HTTP(S) proxy, using the `CONNECT` method.
+    HttpConnect(proxy::HttpConnect),
     /// Connect to the destination via Mullvad Encrypted DNS proxy.
     /// See [`mullvad-encrypted-dns-proxy`] for how the proxy works.
     EncryptedDnsProxy(EncryptedDNSConfig),
@@ -163,6 +166,29 @@ impl InnerConnectionMode {
                 )
                 .await
             }
+            // Set up a tunnel through an HTTP proxy, optionally connecting to the proxy using TLS.
+            InnerConnectionMode::HttpConnect(http) => {
+                let first_hop = http.endpoint;
+                let make_proxy_stream = |tcp_stream: TcpStream| async move {
+                    let stream: Box<dyn ProxyStream> = match &http.tls_server_name {
+                        Some(server_name) => {
+                            Box::new(TlsStream::connect_proxy(tcp_stream, server_name).await?)
+                        }
+                        None => Box::new(tcp_stream),
+                    };
+                    http_connect::connect(stream, addr, http.auth.as_ref()).await
+                };
+                Self::connect_proxied(
+                    first_hop,
+                    hostname,
+                    make_proxy_stream,
+                    #[cfg(target_os = "android")]
+                    socket_bypass_tx,
+                    #[cfg(any(feature = "api-override", test))]
+                    disable_tls,
+                )
+                .await
+            }
             InnerConnectionMode::EncryptedDnsProxy(proxy_config) => {
                 let first_hop = SocketAddr::V4(proxy_config.addr);
                 let make_proxy_stream = |tcp_stream| async {
@@ -226,6 +252,11 @@ impl InnerConnectionMode {
     }
 }
 
+/// A stream which can be used as the transport for a proxy protocol.
+trait ProxyStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ProxyStream for T {}
+
 #[derive(Clone)]
 struct ShadowsocksConfig {
     proxy_context: SharedContext,
@@ -255,6 +286,8 @@ struct SocksConfig {
 enum ProxyConfigError {
     #[error("Unrecognized cipher selected: {0}")]
     InvalidCipher(String),
+    #[error("Invalid Shadowsocks configuration")]
+    InvalidShadowsocksConfig(#[source] proxy::Error),
 }
 
 impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
@@ -266,6 +299,10 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
             ApiConnectionMode::Direct => InnerConnectionMode::Direct,
             ApiConnectionMode::Proxied(proxy_settings) => match proxy_settings {
                 ProxyConfig::Shadowsocks(config) => {
+                    // `ServerConfig::new` panics if the key is invalid for the cipher
+                    config
+                        .validate()
+                        .map_err(ProxyConfigError::InvalidShadowsocksConfig)?;
                     InnerConnectionMode::Shadowsocks(ShadowsocksConfig {
                         params: ParsedShadowsocksConfig {
                             peer: config.endpoint,
@@ -284,6 +321,7 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
                     peer: config.endpoint,
                     authentication: config.auth,
                 }),
+                ProxyConfig::HttpConnect(config) => InnerConnectionMode::HttpConnect(config),
                 ProxyConfig::EncryptedDnsProxy(config) => {
                     InnerConnectionMode::EncryptedDnsProxy(config)
                 }
@@ -470,9 +508,10 @@ impl Service<Uri> for HttpsConnectorWithSni {
 
             // Loop until we have established a connection. This starts over if a new endpoint
             // is selected while connecting.
-            let stream = loop {
+            let (stream, direct) = loop {
                 let notify = abort_notify.notified();
                 let proxy_config = { inner.lock().unwrap().proxy_config.clone() };
+                let direct = matches!(proxy_config, InnerConnectionMode::Direct);
                 let stream_fut = proxy_config.connect(
                     &hostname,
                     &addr,
@@ -488,10 +527,19 @@ impl Service<Uri> for HttpsConnectorWithSni {
                 // Wait for connection. Abort and retry if we switched to a different server.
                 if let future::Either::Left((stream, _)) = future::select(stream_fut, notify).await
                 {
-                    break stream?;
+                    break (stream, direct);
                 }
             };
 
+            // Failures when using a proxy may not be caused by the API address
+            if direct {
+                match &stream {
+                    Ok(_) => dns_resolver.report_success(addr).await,
+                    Err(_) => dns_resolver.report_failure(addr).await,
+                }
+            }
+            let stream = stream?;
+
             let (stream, socket_handle) = AbortableStream::new(stream);
 
             {
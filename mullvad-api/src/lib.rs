@@ -25,9 +25,11 @@ use availability::ApiAvailability;
 pub mod rest;
 
 mod abortable_stream;
+mod http_connect;
 mod https_client_with_sni;
 pub mod proxy;
 mod tls_stream;
+pub mod web_client;
 #[cfg(target_os = "android")]
 pub use crate::https_client_with_sni::SocketBypassRequest;
 
@@ -296,6 +298,12 @@ impl ApiEndpoint {
 #[async_trait]
 pub trait DnsResolver: 'static + Send + Sync {
     async fn resolve(&self, host: String) -> io::Result<Vec<SocketAddr>>;
+
+    /// Called when a direct connection to a resolved address was established.
+    async fn report_success(&self, _address: SocketAddr) {}
+
+    /// Called when a direct connection to a resolved address could not be established.
+    async fn report_failure(&self, _address: SocketAddr) {}
 }
 
 /// DNS resolver that relies on `ToSocketAddrs` (`getaddrinfo`).
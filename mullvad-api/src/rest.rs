@@ -31,7 +31,7 @@ use talpid_types::ErrorExt;
 
 pub use hyper::StatusCode;
 
-const USER_AGENT: &str = "mullvad-app";
+pub(crate) const USER_AGENT: &str = "mullvad-app";
 
 pub type Result<T> = std::result::Result<T, Error>;
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
@@ -73,6 +73,12 @@ pub enum Error {
 
     #[error("Set account number on factory with no access token store")]
     NoAccessTokenStore,
+
+    #[error("Response body is larger than {0} bytes")]
+    BodyTooLarge(usize),
+
+    #[error("Only HTTPS URLs are supported")]
+    UnsupportedScheme,
 }
 
 impl From<Infallible> for Error {
@@ -497,7 +503,7 @@ impl<B: Body> Response<B>
 where
     Error: From<<B as Body>::Error>,
 {
-    fn new(response: hyper::Response<B>) -> Self {
+    pub(crate) fn new(response: hyper::Response<B>) -> Self {
         Self { response }
     }
 
@@ -518,6 +524,23 @@ where
     }
 }
 
+impl Response<Incoming> {
+    /// Like [Response::body], but fails if the body is larger than `limit` bytes.
+    pub async fn body_with_limit(self, limit: usize) -> Result<Vec<u8>> {
+        let mut body = self.response.into_body();
+        let mut data = Vec::new();
+        while let Some(frame) = body.frame().await {
+            if let Ok(chunk) = frame.map_err(Arc::new)?.into_data() {
+                if data.len() + chunk.len() > limit {
+                    return Err(Error::BodyTooLarge(limit));
+                }
+                data.extend_from_slice(&chunk);
+            }
+        }
+        Ok(data)
+    }
+}
+
 #[derive(serde::Deserialize)]
 struct OldErrorResponse {
     pub code: String,
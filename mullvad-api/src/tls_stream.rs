@@ -1,4 +1,5 @@
-//! Provides a TLS 1.3 stream with SNI and LE root cert only.
+//! Provides a TLS 1.3 stream with SNI and LE root cert only, as well as a stream
+//! trusting the usual web PKI roots for connecting to third-party proxy servers.
 use std::{
     io::{self, ErrorKind},
     pin::Pin,
@@ -36,7 +37,36 @@ where
             Arc::new(config)
         });
 
-        let connector = TlsConnector::from(TLS_CONFIG.clone());
+        Self::connect(TLS_CONFIG.clone(), stream, domain).await
+    }
+
+    /// Connect to a TLS server whose certificate is issued by any of the publicly
+    /// trusted root CAs. This is used for connecting to HTTPS proxies and other servers
+    /// which are not operated by Mullvad.
+    pub async fn connect_proxy(stream: S, domain: &str) -> io::Result<TlsStream<S>> {
+        static TLS_CONFIG: LazyLock<Arc<ClientConfig>> = LazyLock::new(|| {
+            let root_store = rustls::RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            let config = ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .expect("ring crypt-prover should support TLS 1.2 and 1.3")
+            .with_root_certificates(root_store)
+            .with_no_client_auth();
+            Arc::new(config)
+        });
+
+        Self::connect(TLS_CONFIG.clone(), stream, domain).await
+    }
+
+    async fn connect(
+        config: Arc<ClientConfig>,
+        stream: S,
+        domain: &str,
+    ) -> io::Result<TlsStream<S>> {
+        let connector = TlsConnector::from(config);
 
         let host = match ServerName::try_from(domain.to_owned()) {
             Ok(n) => n,
@@ -0,0 +1,102 @@
+//! A minimal HTTPS client for downloading resources from servers not operated by Mullvad, such as
+//! DNS blocklists. Unlike the API client, it trusts the usual web PKI roots and does not use the
+//! API access methods. Connections follow the routing table, so they go through the tunnel while
+//! it is up. Only `https` URLs are supported.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use http::uri::Scheme;
+use http_body_util::Empty;
+use hyper::{
+    body::Bytes,
+    header::{self, HeaderValue},
+    Uri,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use tokio::net::TcpStream;
+use tower::Service;
+
+use crate::{
+    rest::{self, Error, Response},
+    tls_stream::TlsStream,
+};
+
+const DEFAULT_PORT: u16 = 443;
+
+/// Download the resource at `url`. Fails if the response body is larger than `limit` bytes, or if
+/// the download takes longer than `timeout`.
+pub async fn get(
+    url: &str,
+    accept: &str,
+    timeout: Duration,
+    limit: usize,
+) -> rest::Result<Vec<u8>> {
+    let uri = Uri::from_str(url)?;
+    if uri.scheme() != Some(&Scheme::HTTPS) {
+        return Err(Error::UnsupportedScheme);
+    }
+    let request = hyper::Request::get(uri)
+        .header(
+            header::USER_AGENT,
+            HeaderValue::from_static(rest::USER_AGENT),
+        )
+        .header(
+            header::ACCEPT,
+            HeaderValue::from_str(accept).map_err(|_| Error::InvalidHeaderError)?,
+        )
+        .body(Empty::<Bytes>::new())?;
+
+    let client =
+        hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build(WebPkiConnector);
+    let download = async move {
+        let response = client.request(request).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::ApiError(status, String::new()));
+        }
+        Response::new(response).body_with_limit(limit).await
+    };
+    tokio::time::timeout(timeout, download)
+        .await
+        .map_err(|_| Error::TimeoutError)?
+}
+
+/// Connects to HTTPS servers whose certificates are issued by any of the publicly trusted root CAs.
+#[derive(Clone)]
+struct WebPkiConnector;
+
+impl Service<Uri> for WebPkiConnector {
+    type Response = TokioIo<TlsStream<TcpStream>>;
+    type Error = io::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(async move {
+            let Some(hostname) = uri.host() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid url, missing host",
+                ));
+            };
+            // IPv6 addresses are enclosed in brackets in URLs
+            let hostname = hostname.trim_start_matches('[').trim_end_matches(']');
+            let port = uri.port_u16().unwrap_or(DEFAULT_PORT);
+
+            let stream = TcpStream::connect((hostname, port)).await?;
+            let stream = TlsStream::connect_proxy(stream, hostname).await?;
+            Ok(TokioIo::new(stream))
+        })
+    }
+}
@@ -2,12 +2,26 @@ use anyhow::{anyhow, Result};
 use clap::Subcommand;
 use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
-use mullvad_types::{account::AccountNumber, device::DeviceState};
+use mullvad_types::{
+    account::{AccountData, AccountNumber},
+    device::DeviceState,
+};
+use serde::Serialize;
 use std::io::{self, Write};
 
+use crate::format;
+
 const NOT_LOGGED_IN_MESSAGE: &str = "Not logged in on any account";
 const REVOKED_MESSAGE: &str = "The current device has been revoked";
 
+/// Output of `account get` when the output is JSON.
+#[derive(Serialize)]
+struct JsonAccount {
+    device: DeviceState,
+    /// Account data, if logged in
+    account: Option<AccountData>,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Account {
     /// Create and log in on a new account
@@ -83,19 +97,21 @@ impl Account {
 
     async fn create(rpc: &mut MullvadProxyClient) -> Result<()> {
         rpc.create_new_account().await?;
-        println!("New account created!");
+        if !format::json_output() {
+            println!("New account created!");
+        }
         Self::get(rpc, false).await
     }
 
     async fn login(rpc: &mut MullvadProxyClient, account_number: AccountNumber) -> Result<()> {
         rpc.login_account(account_number.clone()).await?;
-        println!("Mullvad account \"{account_number}\" set");
+        format::print_message(format!("Mullvad account \"{account_number}\" set"));
         Ok(())
     }
 
     async fn logout(rpc: &mut MullvadProxyClient) -> Result<()> {
         rpc.logout_account().await?;
-        println!("Removed device from Mullvad account");
+        format::print_message("Removed device from Mullvad account");
         Ok(())
     }
 
@@ -104,6 +120,19 @@ impl Account {
 
         let state = rpc.get_device().await?;
 
+        if format::json_output() {
+            let account = match &state {
+                DeviceState::LoggedIn(device) => {
+                    Some(rpc.get_account_data(device.account_number.clone()).await?)
+                }
+                DeviceState::LoggedOut | DeviceState::Revoked => None,
+            };
+            return format::print_json(&JsonAccount {
+                device: state,
+                account,
+            });
+        }
+
         match state {
             DeviceState::LoggedIn(device) => {
                 println!("{:<20}{}", "Mullvad account:", device.account_number);
@@ -146,9 +175,13 @@ impl Account {
     ) -> Result<()> {
         let account_number = account_else_current(rpc, account).await?;
         let mut device_list = rpc.list_devices(account_number).await?;
+        device_list.sort_unstable_by_key(|dev| dev.created.timestamp());
+
+        if format::json_output() {
+            return format::print_json(&device_list);
+        }
 
         println!("Devices on the account:");
-        device_list.sort_unstable_by_key(|dev| dev.created.timestamp());
         for device in device_list {
             if verbose {
                 println!();
@@ -184,7 +217,7 @@ impl Account {
             .ok_or(mullvad_management_interface::Error::DeviceNotFound)?;
 
         rpc.remove_device(account_number, device_id).await?;
-        println!("Removed device");
+        format::print_message("Removed device");
         Ok(())
     }
 
@@ -192,6 +225,9 @@ impl Account {
         voucher.retain(|c| c.is_alphanumeric());
 
         let submission = rpc.submit_voucher(voucher).await?;
+        if format::json_output() {
+            return format::print_json(&submission);
+        }
         println!(
             "Added {} to the account",
             format_duration(submission.time_added)
@@ -232,10 +268,15 @@ async fn unwrap_or_from_stdin(val: Option<String>, prompt_str: &'static str) ->
 
 fn from_stdin(prompt_str: &'static str) -> String {
     let mut val = String::new();
-    io::stdout()
-        .write_all(prompt_str.as_bytes())
-        .expect("Failed to write to STDOUT");
-    let _ = io::stdout().flush();
+    // Keep standard output parsable when printing JSON
+    if format::json_output() {
+        eprint!("{prompt_str}");
+    } else {
+        io::stdout()
+            .write_all(prompt_str.as_bytes())
+            .expect("Failed to write to STDOUT");
+        let _ = io::stdout().flush();
+    }
     io::stdin()
         .read_line(&mut val)
         .expect("Failed to read from STDIN");
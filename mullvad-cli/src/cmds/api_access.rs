@@ -5,7 +5,10 @@ use talpid_types::net::proxy::CustomProxy;
 
 use clap::{Args, Subcommand};
 
-use super::proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd};
+use super::proxies::{
+    HttpConnectAdd, ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd,
+};
+use crate::format;
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum ApiAccess {
@@ -71,7 +74,11 @@ impl ApiAccess {
     /// Show all API access methods.
     async fn list() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        for (index, api_access_method) in rpc.get_api_access_methods().await?.iter().enumerate() {
+        let access_methods = rpc.get_api_access_methods().await?;
+        if format::json_output() {
+            return format::print_json(&access_methods);
+        }
+        for (index, api_access_method) in access_methods.iter().enumerate() {
             println!(
                 "{}. {}",
                 index + 1,
@@ -153,6 +160,9 @@ impl ApiAccess {
                         }
                     })
                 }
+                CustomProxy::HttpConnect(http) => {
+                    AccessMethod::from(cmd.params.merge_http_connect(&http)?)
+                }
             },
         };
 
@@ -189,10 +199,10 @@ impl ApiAccess {
         let mut rpc = MullvadProxyClient::new().await?;
         let access_method = Self::get_access_method(&mut rpc, &item).await?;
 
-        println!("Testing access method \"{}\"", access_method.name);
+        format::print_message(format!("Testing access method \"{}\"", access_method.name));
         match rpc.test_api_access_method(access_method.get_id()).await {
             Ok(true) => {
-                println!("Success!");
+                format::print_message("Success!");
                 Ok(())
             }
             Ok(false) | Err(_) => Err(anyhow!("Could not reach the Mullvad API.")),
@@ -225,13 +235,19 @@ impl ApiAccess {
             ;
         // If the test succeeded, the new access method should be used from now on.
         rpc.set_access_method(new_access_method.get_id()).await?;
-        println!("Using access method \"{}\"", new_access_method.get_name());
+        format::print_message(format!(
+            "Using access method \"{}\"",
+            new_access_method.get_name()
+        ));
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let current = rpc.get_current_api_access_method().await?;
+        if format::json_output() {
+            return format::print_json(&current);
+        }
         let mut access_method_formatter = pp::ApiAccessMethodFormatter::new(&current);
         access_method_formatter.settings.write_enabled = false;
         println!("{}", access_method_formatter);
@@ -266,6 +282,18 @@ pub enum AddCustomCommands {
         #[clap(flatten)]
         add: ShadowsocksAdd,
     },
+    /// Configure an HTTP proxy which supports the CONNECT method, optionally
+    /// reached over TLS
+    HttpConnect {
+        /// An easy to remember name for this custom proxy
+        name: String,
+        /// Disable the use of this custom access method. It has to be manually
+        /// enabled at a later stage to be used when accessing the Mullvad API.
+        #[arg(default_value_t = false, short, long)]
+        disabled: bool,
+        #[clap(flatten)]
+        add: HttpConnectAdd,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -298,6 +326,7 @@ impl AddCustomCommands {
     fn name(&self) -> &str {
         match self {
             AddCustomCommands::Shadowsocks { name, .. }
+            | AddCustomCommands::HttpConnect { name, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { name, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { name, .. }) => name,
         }
@@ -306,6 +335,7 @@ impl AddCustomCommands {
     fn enabled(&self) -> bool {
         match self {
             AddCustomCommands::Shadowsocks { disabled, .. }
+            | AddCustomCommands::HttpConnect { disabled, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { disabled, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { disabled, .. }) => !disabled,
         }
@@ -401,6 +431,9 @@ mod conversions {
                         add.password,
                     ),
                 )),
+                AddCustomCommands::HttpConnect { add, .. } => {
+                    talpid_types::HttpConnect::try_from(add).map(daemon_types::AccessMethod::from)
+                }
             }
         }
     }
@@ -1,8 +1,19 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Subcommand;
+use ipnetwork::IpNetwork;
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::{
+    auto_connect::{
+        AutoConnectAction, AutoConnectRule, AutoConnectRules, MacAddress, RuleConditions,
+        TimeWindow, TrustedNetwork,
+    },
+    constraints::Constraint,
+    relay_constraints::{LocationConstraint, RelayConstraints, RelaySettings},
+};
+use std::net::IpAddr;
 
 use super::BooleanOption;
+use crate::format;
 
 #[derive(Subcommand, Debug)]
 pub enum AutoConnect {
@@ -10,6 +21,104 @@ pub enum AutoConnect {
     Get,
     /// Change auto-connect setting
     Set { policy: BooleanOption },
+    /// Manage rules that connect or disconnect automatically depending on the current network
+    /// and time of day. Rules are evaluated in order, and the first matching rule is applied
+    /// when it starts to match.
+    #[clap(subcommand)]
+    Rule(RuleCommands),
+    /// Manage trusted networks. The tunnel is disconnected when the device moves onto a trusted
+    /// network, and reconnected when it leaves, unless lockdown mode is enabled. Only supported
+    /// on Linux and macOS.
+    #[clap(subcommand)]
+    TrustedNetwork(TrustedNetworkCommands),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TrustedNetworkCommands {
+    /// List all trusted networks
+    List,
+    /// Add a trusted network
+    #[clap(subcommand)]
+    Add(TrustedNetworkArg),
+    /// Remove a trusted network
+    #[clap(subcommand)]
+    Remove(TrustedNetworkArg),
+    /// Remove all trusted networks
+    Clear,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum TrustedNetworkArg {
+    /// A network whose default gateway has this MAC address
+    GatewayMac { mac: MacAddress },
+    /// A Wi-Fi network with this SSID. Only supported on Linux, using NetworkManager
+    Ssid { ssid: String },
+    /// A network whose default gateway is in this subnet
+    Subnet { subnet: IpNetwork },
+}
+
+impl From<TrustedNetworkArg> for TrustedNetwork {
+    fn from(network: TrustedNetworkArg) -> Self {
+        match network {
+            TrustedNetworkArg::GatewayMac { mac } => TrustedNetwork::GatewayMac(mac),
+            TrustedNetworkArg::Ssid { ssid } => TrustedNetwork::Ssid(ssid),
+            TrustedNetworkArg::Subnet { subnet } => TrustedNetwork::Subnet(subnet),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RuleCommands {
+    /// List all auto-connect rules
+    List,
+
+    /// Add a rule to the end of the list. A rule without conditions always matches
+    Add {
+        /// A unique name for the rule
+        name: String,
+
+        /// Whether to connect or disconnect when the rule matches
+        #[arg(value_parser = BooleanOption::custom_parser("connect", "disconnect"))]
+        action: BooleanOption,
+
+        /// Only match when the default route uses this interface. Network conditions are only
+        /// supported on Linux and macOS
+        #[arg(long)]
+        interface: Option<String>,
+
+        /// Only match when the default gateway has this IP address
+        #[arg(long)]
+        gateway_ip: Option<IpAddr>,
+
+        /// Only match when the default gateway has this MAC address
+        #[arg(long)]
+        gateway_mac: Option<MacAddress>,
+
+        /// Only match when the network provides this DNS search domain
+        #[arg(long)]
+        dns_suffix: Option<String>,
+
+        /// Only match during this local time window, e.g. 08:00-17:00
+        #[arg(long)]
+        time: Option<TimeWindow>,
+
+        /// Connect to a relay in this custom list when the rule matches
+        #[arg(long, conflicts_with = "custom_relay")]
+        custom_list: Option<String>,
+
+        /// Connect to this saved custom relay when the rule matches
+        #[arg(long)]
+        custom_relay: Option<String>,
+    },
+
+    /// Remove a rule
+    Remove {
+        /// Name of the rule
+        name: String,
+    },
+
+    /// Remove all rules
+    Clear,
 }
 
 impl AutoConnect {
@@ -17,20 +126,175 @@ impl AutoConnect {
         match self {
             AutoConnect::Get => Self::get().await,
             AutoConnect::Set { policy } => Self::set(policy).await,
+            AutoConnect::Rule(cmd) => Self::rule(cmd).await,
+            AutoConnect::TrustedNetwork(cmd) => Self::trusted_network(cmd).await,
         }
     }
 
     async fn set(policy: BooleanOption) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_auto_connect(*policy).await?;
-        println!("Changed auto-connect setting");
+        format::print_message("Changed auto-connect setting");
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let auto_connect = BooleanOption::from(rpc.get_settings().await?.auto_connect);
+        let auto_connect = rpc.get_settings().await?.auto_connect;
+        if format::json_output() {
+            return format::print_json(&serde_json::json!({ "auto_connect": auto_connect }));
+        }
+        let auto_connect = BooleanOption::from(auto_connect);
         println!("Autoconnect: {auto_connect}");
         Ok(())
     }
+
+    async fn rule(cmd: RuleCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut rules: Vec<_> = rpc
+            .get_settings()
+            .await?
+            .auto_connect_rules
+            .into_iter()
+            .collect();
+
+        match cmd {
+            RuleCommands::List => {
+                if format::json_output() {
+                    return format::print_json(&rules);
+                }
+                if rules.is_empty() {
+                    println!("No auto-connect rules");
+                }
+                for rule in &rules {
+                    print_rule(rule);
+                }
+                return Ok(());
+            }
+            RuleCommands::Add {
+                name,
+                action,
+                interface,
+                gateway_ip,
+                gateway_mac,
+                dns_suffix,
+                time,
+                custom_list,
+                custom_relay,
+            } => {
+                let action = if let Some(custom_list) = custom_list {
+                    if !*action {
+                        return Err(anyhow!("A custom list can only be used with 'connect'"));
+                    }
+                    let list =
+                        super::custom_list::find_list_by_name(&mut rpc, &custom_list).await?;
+                    let mut constraints = match rpc.get_settings().await?.get_relay_settings() {
+                        RelaySettings::Normal(constraints) => constraints,
+                        _ => RelayConstraints::default(),
+                    };
+                    constraints.location =
+                        Constraint::Only(LocationConstraint::CustomList { list_id: list.id });
+                    AutoConnectAction::ConnectUsing(RelaySettings::Normal(constraints))
+                } else if let Some(custom_relay) = custom_relay {
+                    if !*action {
+                        return Err(anyhow!("A custom relay can only be used with 'connect'"));
+                    }
+                    let endpoint =
+                        super::relay::find_custom_endpoint_by_name(&mut rpc, &custom_relay).await?;
+                    AutoConnectAction::ConnectUsing(RelaySettings::SavedCustomEndpoints(vec![
+                        endpoint.id,
+                    ]))
+                } else if *action {
+                    AutoConnectAction::Connect
+                } else {
+                    AutoConnectAction::Disconnect
+                };
+                rules.push(AutoConnectRule {
+                    name,
+                    conditions: RuleConditions {
+                        interface,
+                        gateway_ip,
+                        gateway_mac,
+                        dns_suffix,
+                        time_window: time,
+                    },
+                    action,
+                });
+            }
+            RuleCommands::Remove { name } => {
+                let index = rules
+                    .iter()
+                    .position(|rule| rule.name == name)
+                    .ok_or(anyhow!("Rule not found"))?;
+                rules.remove(index);
+            }
+            RuleCommands::Clear => rules.clear(),
+        }
+
+        rpc.set_auto_connect_rules(AutoConnectRules::new(rules)?)
+            .await?;
+        format::print_message("Updated auto-connect rules");
+        Ok(())
+    }
+
+    async fn trusted_network(cmd: TrustedNetworkCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut networks = rpc.get_settings().await?.trusted_networks;
+
+        match cmd {
+            TrustedNetworkCommands::List => {
+                if format::json_output() {
+                    return format::print_json(&networks);
+                }
+                if networks.is_empty() {
+                    println!("No trusted networks");
+                }
+                for network in &networks {
+                    println!("{network}");
+                }
+                return Ok(());
+            }
+            TrustedNetworkCommands::Add(network) => {
+                let network = TrustedNetwork::from(network);
+                if networks.contains(&network) {
+                    return Err(anyhow!("Network is already trusted"));
+                }
+                networks.push(network);
+            }
+            TrustedNetworkCommands::Remove(network) => {
+                let network = TrustedNetwork::from(network);
+                let index = networks
+                    .iter()
+                    .position(|trusted| *trusted == network)
+                    .ok_or(anyhow!("Network is not trusted"))?;
+                networks.remove(index);
+            }
+            TrustedNetworkCommands::Clear => networks.clear(),
+        }
+
+        rpc.set_trusted_networks(networks).await?;
+        format::print_message("Updated trusted networks");
+        Ok(())
+    }
+}
+
+fn print_rule(rule: &AutoConnectRule) {
+    println!("{}", rule.name);
+    let conditions = &rule.conditions;
+    if let Some(interface) = &conditions.interface {
+        println!("\tInterface: {interface}");
+    }
+    if let Some(gateway_ip) = conditions.gateway_ip {
+        println!("\tGateway IP: {gateway_ip}");
+    }
+    if let Some(gateway_mac) = conditions.gateway_mac {
+        println!("\tGateway MAC: {gateway_mac}");
+    }
+    if let Some(dns_suffix) = &conditions.dns_suffix {
+        println!("\tDNS suffix: {dns_suffix}");
+    }
+    if let Some(time_window) = conditions.time_window {
+        println!("\tTime: {time_window}");
+    }
+    println!("\tAction: {}", rule.action);
 }
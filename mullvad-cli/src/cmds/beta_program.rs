@@ -3,6 +3,7 @@ use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
 use super::BooleanOption;
+use crate::format;
 
 #[derive(Subcommand, Debug)]
 pub enum BetaProgram {
@@ -30,13 +31,19 @@ impl BetaProgram {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_show_beta_releases(*state).await?;
 
-        println!("Beta program: {state}");
+        format::print_message(format!("Beta program: {state}"));
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let opt = BooleanOption::from(rpc.get_settings().await?.show_beta_releases);
+        let show_beta_releases = rpc.get_settings().await?.show_beta_releases;
+        if format::json_output() {
+            return format::print_json(&serde_json::json!({
+                "show_beta_releases": show_beta_releases
+            }));
+        }
+        let opt = BooleanOption::from(show_beta_releases);
         println!("Beta program: {opt}");
         Ok(())
     }
@@ -11,7 +11,7 @@ use mullvad_types::{
 };
 use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
 
-use crate::cmds::proxies::pp::CustomProxyFormatter;
+use crate::{cmds::proxies::pp::CustomProxyFormatter, format};
 
 use super::{
     proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd},
@@ -138,7 +138,7 @@ impl Bridge {
         match subcmd {
             SetCommands::State { policy } => {
                 rpc.set_bridge_state(policy).await?;
-                println!("Updated bridge state");
+                format::print_message("Updated bridge state");
                 Ok(())
             }
             SetCommands::Location(location_constraint_args) => {
@@ -176,6 +176,12 @@ impl Bridge {
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let settings = rpc.get_settings().await?;
+        if format::json_output() {
+            return format::print_json(&serde_json::json!({
+                "bridge_state": settings.bridge_state,
+                "bridge_settings": settings.bridge_settings,
+            }));
+        }
         println!("Bridge state: {}", settings.bridge_state);
         println!(
             "Active bridge type: {}",
@@ -226,6 +232,10 @@ impl Bridge {
             }
         }
 
+        if format::json_output() {
+            return format::print_json(&countries);
+        }
+
         countries.sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
         for mut country in countries {
             country
@@ -277,7 +287,7 @@ impl Bridge {
 
         rpc.set_bridge_settings(settings).await?;
 
-        println!("Updated bridge settings");
+        format::print_message("Updated bridge settings");
 
         Ok(())
     }
@@ -305,6 +315,7 @@ impl Bridge {
             CustomProxy::Shadowsocks(ss) => *ss = edit.merge_shadowsocks(ss),
             CustomProxy::Socks5Local(local) => *local = edit.merge_socks_local(local),
             CustomProxy::Socks5Remote(remote) => *remote = edit.merge_socks_remote(remote)?,
+            CustomProxy::HttpConnect(http) => *http = edit.merge_http_connect(http)?,
         };
 
         rpc.set_bridge_settings(settings.bridge_settings)
@@ -6,6 +6,8 @@ use mullvad_types::{
     constraints::Constraint, relay_constraints::GeographicLocationConstraint, relay_list::RelayList,
 };
 
+use crate::format;
+
 /// Custom list length, expressed as a number of UTF8 codepoints (i.e. chars).
 pub const CUSTOM_LIST_MAX_LEN: usize = 30;
 
@@ -86,8 +88,12 @@ impl CustomList {
     /// Print all custom lists.
     async fn list() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
+        let custom_lists = rpc.get_settings().await?.custom_lists;
+        if format::json_output() {
+            return format::print_json(&custom_lists.into_iter().collect::<Vec<_>>());
+        }
         let cache = rpc.get_relay_locations().await?;
-        for custom_list in rpc.get_settings().await?.custom_lists {
+        for custom_list in custom_lists {
             Self::print_custom_list(&custom_list, &cache)
         }
         Ok(())
@@ -98,6 +104,9 @@ impl CustomList {
     async fn get(name: String) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let custom_list = find_list_by_name(&mut rpc, &name).await?;
+        if format::json_output() {
+            return format::print_json(&custom_list);
+        }
         let cache = rpc.get_relay_locations().await?;
         Self::print_custom_list_content(&custom_list, &cache);
         Ok(())
@@ -123,7 +132,7 @@ impl CustomList {
                 let mut list = find_list_by_name(&mut rpc, &name).await?;
                 if list.locations.insert(location) {
                     rpc.update_custom_list(list).await?;
-                    println!("Location added to custom-list")
+                    format::print_message("Location added to custom-list")
                 } else {
                     bail!("Provided location is already present in custom-list")
                 };
@@ -147,7 +156,7 @@ impl CustomList {
                 let mut list = find_list_by_name(&mut rpc, &name).await?;
                 if list.locations.remove(&location) {
                     rpc.update_custom_list(list).await?;
-                    println!("Location removed from custom-list")
+                    format::print_message("Location removed from custom-list")
                 } else {
                     bail!("Provided location was not present in custom-list")
                 };
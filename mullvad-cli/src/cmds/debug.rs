@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
@@ -9,6 +9,9 @@ use mullvad_types::{
 pub enum DebugCommands {
     /// Block all internet connection by setting an invalid relay constraint.
     BlockConnection,
+    /// Run the traceroute, am.i.mullvad.net and DNS leak tests against the current tunnel. Exits
+    /// with an error if any leak is found.
+    LeakTest,
 }
 
 impl DebugCommands {
@@ -21,8 +24,9 @@ impl DebugCommands {
                 let relay_settings = settings.get_relay_settings();
                 let mut constraints = match relay_settings {
                     RelaySettings::Normal(normal) => normal,
-                    RelaySettings::CustomTunnelEndpoint(_custom) => {
-                        println!("Removing custom relay settings");
+                    RelaySettings::CustomTunnelEndpoint(_)
+                    | RelaySettings::SavedCustomEndpoints(_) => {
+                        crate::format::print_message("Removing custom relay settings");
                         RelayConstraints::default()
                     }
                 };
@@ -41,6 +45,24 @@ impl DebugCommands {
                 eprintln!("WARNING: ENTERED BLOCKED MODE");
                 Ok(())
             }
+            DebugCommands::LeakTest => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let report = rpc.run_leak_test().await?;
+
+                if crate::format::json_output() {
+                    crate::format::print_json(&report)?;
+                } else {
+                    println!("Tunnel endpoint: {}", report.tunnel_endpoint);
+                    println!("Traceroute: {}", report.traceroute);
+                    println!("am.i.mullvad.net: {}", report.am_i_mullvad);
+                    println!("DNS: {}", report.dns);
+                }
+
+                if report.leaks().next().is_some() {
+                    return Err(anyhow!("Leak detected"));
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -1,8 +1,15 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
-use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
-use std::net::IpAddr;
+use mullvad_types::settings::{
+    BlocklistSource, CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState,
+};
+use std::{net::IpAddr, path::Path};
+use talpid_types::net::dns::{
+    normalize_domain, EncryptedDnsProtocol, EncryptedDnsUpstream, EncryptedDnsUpstreamParseError,
+};
+
+use crate::format;
 
 #[derive(Subcommand, Debug)]
 pub enum Dns {
@@ -14,6 +21,76 @@ pub enum Dns {
         #[clap(subcommand)]
         cmd: DnsSet,
     },
+
+    /// Resolve specific domains using resolvers on the local network
+    Split {
+        #[clap(subcommand)]
+        cmd: DnsSplit,
+    },
+
+    /// Block domains using local blocklists, regardless of which DNS servers are used
+    Blocklist {
+        #[clap(subcommand)]
+        cmd: DnsBlocklist,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DnsBlocklist {
+    /// Block the domains in a list in hosts, adblock or plain format. Subdomains are blocked as
+    /// well
+    Add {
+        /// Path to a file, or an HTTPS URL. Files must be owned by root and not be writable by
+        /// other users. URLs are downloaded through the tunnel while connected
+        #[arg(value_parser = parse_blocklist_source)]
+        source: BlocklistSource,
+    },
+
+    /// Stop using a list
+    Remove {
+        /// Path or URL of the list to remove
+        #[arg(value_parser = parse_blocklist_source)]
+        source: BlocklistSource,
+    },
+
+    /// Never block a domain or its subdomains, even if they are in a list
+    Allow {
+        /// Domain to allow, such as cdn.example
+        #[arg(value_parser = normalize_domain)]
+        domain: String,
+    },
+
+    /// Remove a domain from the allowlist
+    Unallow {
+        /// Domain to remove from the allowlist
+        #[arg(value_parser = normalize_domain)]
+        domain: String,
+    },
+
+    /// Show how many domains each list contains and how many queries it has blocked
+    Stats,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DnsSplit {
+    /// Send queries for a domain and its subdomains to the given resolvers. Replaces any
+    /// resolvers already set for the domain
+    Add {
+        /// Domain to resolve using the resolvers, such as corp.example
+        #[arg(value_parser = normalize_domain)]
+        domain: String,
+
+        /// One or more IP addresses of resolvers on the local network
+        #[arg(required = true, num_args = 1..)]
+        resolvers: Vec<IpAddr>,
+    },
+
+    /// Stop sending queries for a domain to separate resolvers
+    Remove {
+        /// Domain to remove
+        #[arg(value_parser = normalize_domain)]
+        domain: String,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -49,11 +126,44 @@ pub enum DnsSet {
     /// Set a list of custom DNS servers
     Custom {
         /// One or more IP addresses pointing to DNS resolvers
-        #[arg(required(true), num_args = 1..)]
+        #[arg(
+            required_unless_present_any = ["doh", "dot"],
+            conflicts_with_all = ["doh", "dot"],
+            num_args = 1..
+        )]
         servers: Vec<IpAddr>,
+
+        /// Use a DNS-over-HTTPS resolver, given as ADDRESS[:PORT]#HOSTNAME. Queries are sent
+        /// to it through a local forwarder. May be given more than once
+        #[arg(long, value_parser = parse_doh)]
+        doh: Vec<EncryptedDnsUpstream>,
+
+        /// Use a DNS-over-TLS resolver, given as ADDRESS[:PORT]#HOSTNAME. Queries are sent
+        /// to it through a local forwarder. May be given more than once
+        #[arg(long, value_parser = parse_dot)]
+        dot: Vec<EncryptedDnsUpstream>,
     },
 }
 
+fn parse_blocklist_source(s: &str) -> Result<BlocklistSource> {
+    if s.starts_with("https://") {
+        return Ok(BlocklistSource::Url(s.to_owned()));
+    }
+    if s.starts_with("http://") {
+        return Err(anyhow!("Blocklist URLs must use HTTPS"));
+    }
+    // The daemon does not share our working directory
+    Ok(BlocklistSource::File(std::path::absolute(Path::new(s))?))
+}
+
+fn parse_doh(s: &str) -> Result<EncryptedDnsUpstream, EncryptedDnsUpstreamParseError> {
+    EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Https, s)
+}
+
+fn parse_dot(s: &str) -> Result<EncryptedDnsUpstream, EncryptedDnsUpstreamParseError> {
+    EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Tls, s)
+}
+
 impl Dns {
     pub async fn handle(self) -> Result<()> {
         match self {
@@ -80,14 +190,38 @@ impl Dns {
                 .await
             }
             Dns::Set {
-                cmd: DnsSet::Custom { servers },
-            } => Self::set_custom(servers).await,
+                cmd: DnsSet::Custom { servers, doh, dot },
+            } => Self::set_custom(servers, doh.into_iter().chain(dot).collect()).await,
+            Dns::Split {
+                cmd: DnsSplit::Add { domain, resolvers },
+            } => Self::add_split_route(domain, resolvers).await,
+            Dns::Split {
+                cmd: DnsSplit::Remove { domain },
+            } => Self::remove_split_route(domain).await,
+            Dns::Blocklist {
+                cmd: DnsBlocklist::Add { source },
+            } => Self::add_blocklist(source).await,
+            Dns::Blocklist {
+                cmd: DnsBlocklist::Remove { source },
+            } => Self::remove_blocklist(source).await,
+            Dns::Blocklist {
+                cmd: DnsBlocklist::Allow { domain },
+            } => Self::allow_domain(domain).await,
+            Dns::Blocklist {
+                cmd: DnsBlocklist::Unallow { domain },
+            } => Self::unallow_domain(domain).await,
+            Dns::Blocklist {
+                cmd: DnsBlocklist::Stats,
+            } => Self::blocklist_stats().await,
         }
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let options = rpc.get_settings().await?.tunnel_options.dns_options;
+        if format::json_output() {
+            return format::print_json(&options);
+        }
 
         match options.state {
             DnsState::Default => {
@@ -110,6 +244,34 @@ impl Dns {
                 for server in &options.custom_options.addresses {
                     println!("{server}");
                 }
+                if !options.custom_options.encrypted_upstreams.is_empty() {
+                    println!("Encrypted servers:");
+                    for upstream in &options.custom_options.encrypted_upstreams {
+                        println!("{upstream}");
+                    }
+                }
+            }
+        }
+
+        if !options.split_dns.is_empty() {
+            println!("Split DNS:");
+            for (domain, resolvers) in &options.split_dns {
+                let resolvers: Vec<_> = resolvers.iter().map(ToString::to_string).collect();
+                println!("{domain}: {}", resolvers.join(", "));
+            }
+        }
+
+        let blocklists = &options.local_blocklists;
+        if !blocklists.sources.is_empty() {
+            println!("Blocklists:");
+            for source in &blocklists.sources {
+                println!("{source}");
+            }
+        }
+        if !blocklists.allowlist.is_empty() {
+            println!("Allowlist:");
+            for domain in &blocklists.allowlist {
+                println!("{domain}");
             }
         }
 
@@ -139,20 +301,119 @@ impl Dns {
             ..settings.tunnel_options.dns_options
         })
         .await?;
-        println!("Updated DNS settings");
+        format::print_message("Updated DNS settings");
         Ok(())
     }
 
-    async fn set_custom(servers: Vec<IpAddr>) -> Result<()> {
+    async fn set_custom(
+        servers: Vec<IpAddr>,
+        encrypted_upstreams: Vec<EncryptedDnsUpstream>,
+    ) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let settings = rpc.get_settings().await?;
         rpc.set_dns_options(DnsOptions {
             state: DnsState::Custom,
-            custom_options: CustomDnsOptions { addresses: servers },
+            custom_options: CustomDnsOptions {
+                addresses: servers,
+                encrypted_upstreams,
+            },
             ..settings.tunnel_options.dns_options
         })
         .await?;
-        println!("Updated DNS settings");
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn add_split_route(domain: String, resolvers: Vec<IpAddr>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        options.split_dns.insert(domain, resolvers);
+        rpc.set_dns_options(options).await?;
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn remove_split_route(domain: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        options
+            .split_dns
+            .remove(&domain)
+            .ok_or_else(|| anyhow!("No split DNS route for {domain}"))?;
+        rpc.set_dns_options(options).await?;
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn add_blocklist(source: BlocklistSource) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        if options.local_blocklists.sources.contains(&source) {
+            return Err(anyhow!("{source} is already used"));
+        }
+        options.local_blocklists.sources.push(source);
+        rpc.set_dns_options(options).await?;
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn remove_blocklist(source: BlocklistSource) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        let sources = &mut options.local_blocklists.sources;
+        let index = sources
+            .iter()
+            .position(|existing| existing == &source)
+            .ok_or_else(|| anyhow!("{source} is not used"))?;
+        sources.remove(index);
+        rpc.set_dns_options(options).await?;
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn allow_domain(domain: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        if !options.local_blocklists.allowlist.contains(&domain) {
+            options.local_blocklists.allowlist.push(domain);
+        }
+        rpc.set_dns_options(options).await?;
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn unallow_domain(domain: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        let allowlist = &mut options.local_blocklists.allowlist;
+        let index = allowlist
+            .iter()
+            .position(|existing| existing == &domain)
+            .ok_or_else(|| anyhow!("{domain} is not in the allowlist"))?;
+        allowlist.remove(index);
+        rpc.set_dns_options(options).await?;
+        format::print_message("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn blocklist_stats() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let stats = rpc.get_dns_blocklist_stats().await?;
+        if format::json_output() {
+            return format::print_json(&stats);
+        }
+        for source in &stats.sources {
+            println!("{}", source.source);
+            match &source.error {
+                Some(error) => println!("\tError: {error}"),
+                None => {
+                    println!("\tDomains: {}", source.domains);
+                    println!("\tBlocked queries: {}", source.blocked_queries);
+                }
+            }
+        }
+        println!("Blocked queries: {}", stats.blocked_queries());
+        println!("Allowlisted queries: {}", stats.allowlisted_queries);
         Ok(())
     }
 }
@@ -1,8 +1,11 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Subcommand;
+use ipnetwork::IpNetwork;
 use mullvad_management_interface::MullvadProxyClient;
+use talpid_types::net::AllowedLanNets;
 
 use super::BooleanOption;
+use crate::format;
 
 #[derive(Subcommand, Debug)]
 pub enum Lan {
@@ -14,6 +17,33 @@ pub enum Lan {
         #[arg(value_parser = BooleanOption::custom_parser("allow", "block"))]
         policy: BooleanOption,
     },
+
+    /// Manage the networks that are reachable when local network sharing is allowed
+    #[clap(subcommand)]
+    Networks(Networks),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Networks {
+    /// Allow a network in addition to the existing ones
+    Add {
+        /// Network to allow. Must be within a private, shared, link-local or unique local network,
+        /// e.g. 100.64.0.0/10 to reach devices behind carrier-grade NAT or on a Tailscale network
+        network: IpNetwork,
+    },
+
+    /// Remove a previously added network
+    Remove {
+        /// Network to remove
+        network: IpNetwork,
+    },
+
+    /// Remove all added networks and restore the default private networks
+    Clear,
+
+    /// Whether the added networks should replace the default private networks
+    /// (10.0.0.0/8, 172.16.0.0/12, 192.168.0.0/16, etc.)
+    ReplaceDefault { policy: BooleanOption },
 }
 
 impl Lan {
@@ -21,21 +51,67 @@ impl Lan {
         match self {
             Lan::Get => Self::get().await,
             Lan::Set { policy } => Self::set(policy).await,
+            Lan::Networks(cmd) => cmd.handle().await,
         }
     }
 
     async fn set(policy: BooleanOption) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_allow_lan(*policy).await?;
-        println!("Changed local network sharing setting");
+        format::print_message("Changed local network sharing setting");
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let allow_lan =
-            BooleanOption::with_labels(rpc.get_settings().await?.allow_lan, "allow", "block");
+        let settings = rpc.get_settings().await?;
+        if format::json_output() {
+            return format::print_json(&serde_json::json!({
+                "allow_lan": settings.allow_lan,
+                "allowed_lan_nets": settings.allowed_lan_nets,
+            }));
+        }
+        let allow_lan = BooleanOption::with_labels(settings.allow_lan, "allow", "block");
         println!("Local network sharing setting: {allow_lan}");
+        println!("Local networks: {}", settings.allowed_lan_nets);
+        Ok(())
+    }
+}
+
+impl Networks {
+    async fn handle(self) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let allowed_lan_nets = rpc.get_settings().await?.allowed_lan_nets;
+        let mut custom_nets = allowed_lan_nets.custom_nets().to_vec();
+        let mut replace_default = allowed_lan_nets.replace_default();
+
+        match self {
+            Networks::Add { network } => {
+                if custom_nets.contains(&network) {
+                    return Err(anyhow!("{network} is already allowed"));
+                }
+                custom_nets.push(network);
+            }
+            Networks::Remove { network } => {
+                let len = custom_nets.len();
+                custom_nets.retain(|net| *net != network);
+                if custom_nets.len() == len {
+                    return Err(anyhow!("{network} has not been added"));
+                }
+            }
+            Networks::Clear => {
+                custom_nets.clear();
+                replace_default = false;
+            }
+            Networks::ReplaceDefault { policy } => replace_default = *policy,
+        }
+        let allowed_lan_nets = AllowedLanNets::new(custom_nets, replace_default)?;
+
+        rpc.set_allowed_lan_nets(&allowed_lan_nets).await?;
+        if format::json_output() {
+            return format::print_json(&allowed_lan_nets);
+        }
+        println!("Local networks: {allowed_lan_nets}");
         Ok(())
     }
 }
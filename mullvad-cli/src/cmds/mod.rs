@@ -90,7 +90,12 @@ async fn receive_confirmation(msg: &'static str, default: bool) -> bool {
         false => "[y/N]",
     };
 
-    println!("{msg} {helper_str}");
+    // Keep standard output parsable when printing JSON
+    if crate::format::json_output() {
+        eprintln!("{msg} {helper_str}");
+    } else {
+        println!("{msg} {helper_str}");
+    }
 
     tokio::task::spawn_blocking(move || loop {
         let mut buf = String::new();
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{ensure, Context, Result};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
@@ -7,6 +7,10 @@ use mullvad_types::{
         ObfuscationSettings, SelectedObfuscation, ShadowsocksSettings, Udp2TcpObfuscationSettings,
     },
 };
+use talpid_types::net::{obfuscation::WebSocketSettings, proxy::Shadowsocks};
+
+use super::{proxies::ShadowsocksAdd, BooleanOption};
+use crate::format;
 
 #[derive(Subcommand, Debug)]
 pub enum Obfuscation {
@@ -16,6 +20,11 @@ pub enum Obfuscation {
     /// Set obfuscation settings
     #[clap(subcommand)]
     Set(SetCommands),
+
+    /// Learn which obfuscation works on each network. When enabled, the obfuscation that most
+    /// recently worked on a network is tried first when connecting on that network again.
+    #[clap(subcommand)]
+    Learned(LearnedCommands),
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -27,7 +36,20 @@ pub enum SetCommands {
     Udp2tcp {
         /// Port to use, or 'any'
         #[arg(long, short = 'p')]
-        port: Constraint<u16>,
+        port: Option<Constraint<u16>>,
+
+        /// Send datagrams as WebSocket messages over TLS, using this server name for SNI and
+        /// to verify the certificate of the server
+        #[arg(long, requires = "websocket_path")]
+        websocket_server_name: Option<String>,
+
+        /// Path of the WebSocket endpoint, such as /ws
+        #[arg(long, requires = "websocket_server_name")]
+        websocket_path: Option<String>,
+
+        /// Stop using WebSocket over TLS, and write datagrams directly to the TCP stream
+        #[arg(long, conflicts_with_all = ["websocket_server_name", "websocket_path"])]
+        no_websocket: bool,
     },
 
     /// Configure Shadowsocks obfuscation.
@@ -36,6 +58,31 @@ pub enum SetCommands {
         #[arg(long, short = 'p')]
         port: Constraint<u16>,
     },
+
+    /// Configure a self-hosted Shadowsocks server. It is used instead of Mullvad's servers when
+    /// connecting to a custom WireGuard endpoint with Shadowsocks obfuscation, and must forward
+    /// traffic to the endpoint of the custom peer.
+    #[clap(subcommand)]
+    CustomShadowsocks(CustomShadowsocksCommands),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum LearnedCommands {
+    /// Enable or disable learning which obfuscation works on each network. Only supported on
+    /// Linux and macOS
+    Set { policy: BooleanOption },
+    /// List the obfuscation that most recently worked on each network, least recently used first
+    List,
+    /// Forget the obfuscation that worked on each network
+    Clear,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CustomShadowsocksCommands {
+    /// Use a self-hosted Shadowsocks server
+    Set(ShadowsocksAdd),
+    /// Stop using the self-hosted Shadowsocks server
+    Unset,
 }
 
 impl Obfuscation {
@@ -44,16 +91,67 @@ impl Obfuscation {
             Obfuscation::Get => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let obfuscation_settings = rpc.get_settings().await?.obfuscation_settings;
+                if format::json_output() {
+                    return format::print_json(&obfuscation_settings);
+                }
                 println!(
                     "Obfuscation mode: {}",
                     obfuscation_settings.selected_obfuscation
                 );
                 println!("udp2tcp settings: {}", obfuscation_settings.udp2tcp);
                 println!("Shadowsocks settings: {}", obfuscation_settings.shadowsocks);
+                if let Some(server) = &obfuscation_settings.custom_shadowsocks {
+                    println!(
+                        "Self-hosted Shadowsocks server: {} ({})",
+                        server.endpoint, server.cipher
+                    );
+                }
+                println!(
+                    "Learn obfuscation per network: {}",
+                    BooleanOption::from(obfuscation_settings.learn_per_network)
+                );
                 Ok(())
             }
             Obfuscation::Set(subcmd) => Self::set(subcmd).await,
+            Obfuscation::Learned(subcmd) => Self::learned(subcmd).await,
+        }
+    }
+
+    async fn learned(subcmd: LearnedCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        match subcmd {
+            LearnedCommands::Set { policy } => {
+                let current_settings = rpc.get_settings().await?.obfuscation_settings;
+                rpc.set_obfuscation_settings(ObfuscationSettings {
+                    learn_per_network: *policy,
+                    ..current_settings
+                })
+                .await?;
+                format::print_message("Updated obfuscation settings");
+            }
+            LearnedCommands::List => {
+                let entries = rpc.get_learned_obfuscation().await?;
+                if format::json_output() {
+                    return format::print_json(&entries);
+                }
+                if entries.is_empty() {
+                    println!("No networks have been learned");
+                }
+                for entry in entries {
+                    println!(
+                        "{}: {} (last used {})",
+                        entry.network,
+                        entry.obfuscation,
+                        entry.last_used.with_timezone(&chrono::Local)
+                    );
+                }
+            }
+            LearnedCommands::Clear => {
+                rpc.clear_learned_obfuscation().await?;
+                format::print_message("Forgot the obfuscation learned for all networks");
+            }
         }
+        Ok(())
     }
 
     async fn set(subcmd: SetCommands) -> Result<()> {
@@ -68,9 +166,29 @@ impl Obfuscation {
                 })
                 .await?;
             }
-            SetCommands::Udp2tcp { port } => {
+            SetCommands::Udp2tcp {
+                port,
+                websocket_server_name,
+                websocket_path,
+                no_websocket,
+            } => {
+                let websocket = match (websocket_server_name, websocket_path) {
+                    (Some(server_name), Some(path)) => {
+                        Some(WebSocketSettings { server_name, path })
+                    }
+                    _ if no_websocket => None,
+                    _ => current_settings.udp2tcp.websocket.clone(),
+                };
+                if let Some(websocket) = &websocket {
+                    ensure!(
+                        websocket.is_valid(),
+                        "The WebSocket server name must be a valid DNS name or IP address, and \
+                         the path must start with '/'"
+                    );
+                }
+                let port = port.unwrap_or(current_settings.udp2tcp.port);
                 rpc.set_obfuscation_settings(ObfuscationSettings {
-                    udp2tcp: Udp2TcpObfuscationSettings { port },
+                    udp2tcp: Udp2TcpObfuscationSettings { port, websocket },
                     ..current_settings
                 })
                 .await?;
@@ -82,9 +200,27 @@ impl Obfuscation {
                 })
                 .await?;
             }
+            SetCommands::CustomShadowsocks(CustomShadowsocksCommands::Set(add)) => {
+                let server = Shadowsocks::from(add);
+                server
+                    .validate_for_obfuscation()
+                    .context("Invalid Shadowsocks server settings")?;
+                rpc.set_obfuscation_settings(ObfuscationSettings {
+                    custom_shadowsocks: Some(server),
+                    ..current_settings
+                })
+                .await?;
+            }
+            SetCommands::CustomShadowsocks(CustomShadowsocksCommands::Unset) => {
+                rpc.set_obfuscation_settings(ObfuscationSettings {
+                    custom_shadowsocks: None,
+                    ..current_settings
+                })
+                .await?;
+            }
         }
 
-        println!("Updated obfuscation settings");
+        format::print_message("Updated obfuscation settings");
 
         Ok(())
     }
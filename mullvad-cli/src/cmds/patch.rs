@@ -24,7 +24,7 @@ pub async fn import(source: String) -> Result<()> {
         .await
         .context("Error applying patch")?;
 
-    println!("Settings applied");
+    crate::format::print_message("Settings applied");
 
     Ok(())
 }
@@ -1,7 +1,10 @@
 use clap::Args;
 use std::net::{IpAddr, SocketAddr};
 use talpid_types::net::{
-    proxy::{Shadowsocks, Socks5Local, Socks5Remote, SocksAuth, SHADOWSOCKS_CIPHERS},
+    proxy::{
+        HttpAuth, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
+        SHADOWSOCKS_CIPHERS,
+    },
     Endpoint, TransportProtocol,
 };
 
@@ -89,6 +92,36 @@ impl From<ShadowsocksAdd> for Shadowsocks {
     }
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct HttpConnectAdd {
+    /// The IP of the remote HTTP proxy
+    pub remote_ip: IpAddr,
+    /// The port of the remote HTTP proxy
+    pub remote_port: u16,
+
+    #[clap(flatten)]
+    pub authentication: Option<HttpAuthentication>,
+
+    /// Connect to the proxy using TLS. The certificate presented by the proxy
+    /// is verified against this server name.
+    #[arg(long)]
+    pub tls_server_name: Option<String>,
+}
+
+impl TryFrom<HttpConnectAdd> for HttpConnect {
+    type Error = Error;
+    fn try_from(add: HttpConnectAdd) -> Result<Self, Self::Error> {
+        Ok(Self {
+            endpoint: SocketAddr::new(add.remote_ip, add.remote_port),
+            auth: add
+                .authentication
+                .map(|auth| HttpAuth::new(auth.username, auth.password))
+                .transpose()?,
+            tls_server_name: add.tls_server_name,
+        })
+    }
+}
+
 #[derive(Args, Debug, Clone)]
 #[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
 pub struct SocksAuthentication {
@@ -100,21 +133,32 @@ pub struct SocksAuthentication {
     pub password: String,
 }
 
+#[derive(Args, Debug, Clone)]
+#[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
+pub struct HttpAuthentication {
+    /// Username for basic authentication against a remote HTTP proxy
+    #[arg(short, long, required = false)]
+    pub username: String,
+    /// Password for basic authentication against a remote HTTP proxy
+    #[arg(short, long, required = false)]
+    pub password: String,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct ProxyEditParams {
-    /// Username for authentication \[Socks5 (Remote proxy)\]
+    /// Username for authentication \[Socks5 (Remote proxy), HTTP CONNECT\]
     #[arg(long)]
     pub username: Option<String>,
-    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks\]
+    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks, HTTP CONNECT\]
     #[arg(long)]
     pub password: Option<String>,
     /// Cipher to use \[Shadowsocks\]
     #[arg(value_parser = SHADOWSOCKS_CIPHERS, long)]
     pub cipher: Option<String>,
-    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP CONNECT\]
     #[arg(long)]
     pub ip: Option<IpAddr>,
-    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP CONNECT\]
     #[arg(long)]
     pub port: Option<u16>,
     /// The port that the server on localhost is listening on \[Socks5 (Local proxy)\]
@@ -123,6 +167,12 @@ pub struct ProxyEditParams {
     /// The transport protocol used by the remote proxy \[Socks5 (Local proxy)\]
     #[arg(long)]
     pub transport_protocol: Option<TransportProtocol>,
+    /// Connect to the proxy using TLS, verifying it against this server name \[HTTP CONNECT\]
+    #[arg(long)]
+    pub tls_server_name: Option<String>,
+    /// Connect to the proxy without TLS \[HTTP CONNECT\]
+    #[arg(long, conflicts_with = "tls_server_name")]
+    pub no_tls: bool,
 }
 
 impl ProxyEditParams {
@@ -151,7 +201,7 @@ impl ProxyEditParams {
                 }
                 (None, None) => Socks5Remote::new((ip, port)),
                 _ => {
-                    println!("Remote SOCKS5 proxy does not have a username and password set already, so you must provide both or neither when you edit.");
+                    eprintln!("Remote SOCKS5 proxy does not have a username and password set already, so you must provide both or neither when you edit.");
                     Socks5Remote::new((ip, port))
                 }
             },
@@ -165,6 +215,36 @@ impl ProxyEditParams {
         Ok(config)
     }
 
+    pub fn merge_http_connect(self, http: &HttpConnect) -> Result<HttpConnect, Error> {
+        let ip = self.ip.unwrap_or(http.endpoint.ip());
+        let port = self.port.unwrap_or(http.endpoint.port());
+        let auth = match &http.auth {
+            None => match (self.username, self.password) {
+                (Some(username), Some(password)) => Some(HttpAuth::new(username, password)?),
+                (None, None) => None,
+                _ => {
+                    eprintln!("HTTP proxy does not have a username and password set already, so you must provide both or neither when you edit.");
+                    None
+                }
+            },
+            Some(credentials) => {
+                let username = self.username.unwrap_or(credentials.username().to_string());
+                let password = self.password.unwrap_or(credentials.password().to_string());
+                Some(HttpAuth::new(username, password)?)
+            }
+        };
+        let tls_server_name = if self.no_tls {
+            None
+        } else {
+            self.tls_server_name.or(http.tls_server_name.clone())
+        };
+        Ok(HttpConnect {
+            endpoint: SocketAddr::new(ip, port),
+            auth,
+            tls_server_name,
+        })
+    }
+
     pub fn merge_shadowsocks(self, shadowsocks: &Shadowsocks) -> Shadowsocks {
         let ip = self.ip.unwrap_or(shadowsocks.endpoint.ip());
         let port = self.port.unwrap_or(shadowsocks.endpoint.port());
@@ -212,6 +292,18 @@ pub mod pp {
                     print_option!("Local port", local.local_port);
                     Ok(())
                 }
+                CustomProxy::HttpConnect(http) => {
+                    print_option!("Protocol", "HTTP CONNECT");
+                    print_option!("Peer", http.endpoint);
+                    if let Some(server_name) = &http.tls_server_name {
+                        print_option!("TLS server name", server_name);
+                    }
+                    if let Some(credentials) = &http.auth {
+                        print_option!("Username", credentials.username());
+                        print_option!("Password", credentials.password());
+                    }
+                    Ok(())
+                }
             }
         }
     }
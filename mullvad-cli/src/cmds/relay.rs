@@ -4,18 +4,22 @@ use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::{Constraint, Match},
+    custom_endpoint::NamedCustomEndpoint,
     location::CountryCode,
     relay_constraints::{
         GeographicLocationConstraint, LocationConstraint, LocationConstraintFormatter,
         OpenVpnConstraints, Ownership, Provider, Providers, RelayConstraints, RelayOverride,
-        RelaySettings, TransportPort, WireguardConstraints,
+        RelaySettings, SelectionMode, TransportPort, WireguardConstraints,
     },
     relay_list::{RelayEndpointData, RelayListCountry},
+    settings::{CustomDnsOptions, DnsOptions, DnsState},
+    wg_quick::WgQuickConfig,
     ConnectionConfig, CustomTunnelEndpoint,
 };
 use std::{
     collections::HashMap,
-    io::BufRead,
+    fs::{File, OpenOptions},
+    io::{read_to_string, stdin, BufRead, BufReader, Write},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
 };
 use talpid_types::net::{
@@ -23,7 +27,7 @@ use talpid_types::net::{
 };
 
 use super::{relay_constraints::LocationArgs, BooleanOption};
-use crate::{cmds::receive_confirmation, print_option};
+use crate::{cmds::receive_confirmation, format, print_option};
 
 #[derive(Subcommand, Debug)]
 pub enum Relay {
@@ -43,6 +47,19 @@ pub enum Relay {
     /// Override options for individual relays/servers
     #[clap(subcommand)]
     Override(OverrideCommands),
+
+    /// Manage saved custom relays. A group of saved relays can be used in
+    /// failover order
+    #[clap(subcommand)]
+    Custom(CustomCommands),
+
+    /// Export the custom WireGuard relay as a wg-quick configuration file.
+    /// The output contains the private key.
+    ExportWireguardConfig {
+        /// File to write the configuration to, or "-" for standard output
+        #[arg(default_value = "-")]
+        file: String,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -92,6 +109,11 @@ pub enum SetCommands {
         ownership: Constraint<Ownership>,
     },
 
+    /// Set how a relay is picked among the matching relays. 'fastest'
+    /// measures the latency to the relays before connecting, and only picks
+    /// among the fastest ones. With multihop, this applies to the entry relay.
+    SelectionMode { mode: SelectionMode },
+
     /// Set tunnel protocol specific constraints
     #[clap(subcommand)]
     Tunnel(SetTunnelCommands),
@@ -212,6 +234,60 @@ pub enum SetCustomCommands {
         #[arg(long)]
         v6_gateway: Option<Ipv6Addr>,
     },
+
+    /// Use a custom WireGuard relay described by a wg-quick configuration file.
+    /// DNS and MTU in the file are only applied if --apply-dns-and-mtu is given.
+    #[clap(arg_required_else_help = true)]
+    WireguardConfig {
+        /// File to read the configuration from, or "-" for standard input
+        file: String,
+        /// Also apply DNS and MTU in the file to the global DNS and tunnel
+        /// settings. These affect all relays, not only this one
+        #[arg(long)]
+        apply_dns_and_mtu: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CustomCommands {
+    /// List saved custom relays
+    List,
+
+    /// Save a custom relay under a name
+    Add {
+        /// A name for the custom relay
+        name: String,
+        #[clap(subcommand)]
+        endpoint: SetCustomCommands,
+    },
+
+    /// Rename a saved custom relay
+    Rename {
+        /// Current name of the custom relay
+        name: String,
+        /// A new name for the custom relay
+        new_name: String,
+    },
+
+    /// Remove a saved custom relay. Relays that are currently in use cannot
+    /// be removed
+    Remove {
+        /// A saved custom relay
+        name: String,
+    },
+
+    /// Remove all saved custom relays. Fails if saved custom relays are
+    /// currently in use
+    Clear,
+
+    /// Connect using one or more saved custom relays. If connecting to a
+    /// relay fails, the next one is tried
+    #[clap(arg_required_else_help = true)]
+    Use {
+        /// Saved custom relays, in the order they should be tried
+        #[arg(required = true, num_args = 1..)]
+        names: Vec<String>,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -266,6 +342,8 @@ impl Relay {
             Relay::Update => Self::update().await,
             Relay::Set(subcmd) => Self::set(subcmd).await,
             Relay::Override(subcmd) => Self::r#override(subcmd).await,
+            Relay::Custom(subcmd) => Self::custom(subcmd).await,
+            Relay::ExportWireguardConfig { file } => Self::export_wireguard_config(file).await,
         }
     }
 
@@ -274,10 +352,23 @@ impl Relay {
         let settings = rpc.get_settings().await?;
         let relay_settings = settings.relay_settings;
 
+        if format::json_output() {
+            return format::print_json(&relay_settings);
+        }
+
         match relay_settings {
             RelaySettings::CustomTunnelEndpoint(endpoint) => {
                 println!("Custom endpoint: {endpoint}")
             }
+            RelaySettings::SavedCustomEndpoints(ids) => {
+                println!("Saved custom endpoints, in failover order");
+                for id in &ids {
+                    match settings.custom_endpoints.get(id) {
+                        Some(endpoint) => print_option!(endpoint.name, endpoint.endpoint,),
+                        None => print_option!("Unknown endpoint", id.to_string(),),
+                    }
+                }
+            }
 
             RelaySettings::Normal(constraints) => {
                 println!("Generic constraints");
@@ -297,6 +388,7 @@ impl Relay {
 
                 print_option!("Provider(s)", constraints.providers,);
                 print_option!("Ownership", constraints.ownership,);
+                print_option!("Selection mode", constraints.selection_mode,);
 
                 println!("OpenVPN constraints");
 
@@ -343,6 +435,14 @@ impl Relay {
     }
 
     async fn list() -> Result<()> {
+        if format::json_output() {
+            let relay_list = MullvadProxyClient::new()
+                .await?
+                .get_relay_locations()
+                .await?;
+            return format::print_json(&relay_list);
+        }
+
         let mut countries = get_active_relays().await?;
         countries.sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
         for mut country in countries {
@@ -391,7 +491,7 @@ impl Relay {
             .await?
             .update_relay_locations()
             .await?;
-        println!("Updating relay list in the background...");
+        format::print_message("Updating relay list in the background...");
         Ok(())
     }
 
@@ -403,15 +503,15 @@ impl Relay {
         let relay_settings = settings.get_relay_settings();
         let mut constraints = match relay_settings {
             RelaySettings::Normal(normal) => normal,
-            RelaySettings::CustomTunnelEndpoint(_custom) => {
-                println!("Removing custom relay settings");
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints(_) => {
+                format::print_message("Removing custom relay settings");
                 RelayConstraints::default()
             }
         };
         update_fn(&mut constraints);
         rpc.set_relay_settings(RelaySettings::Normal(constraints))
             .await?;
-        println!("Relay constraints updated");
+        format::print_message("Relay constraints updated");
         Ok(())
     }
 
@@ -424,6 +524,7 @@ impl Relay {
             }
             SetCommands::Provider { providers } => Self::set_providers(providers).await,
             SetCommands::Ownership { ownership } => Self::set_ownership(ownership).await,
+            SetCommands::SelectionMode { mode } => Self::set_selection_mode(mode).await,
             SetCommands::Tunnel(subcmd) => Self::set_tunnel(subcmd).await,
             SetCommands::TunnelProtocol { protocol } => Self::set_tunnel_protocol(protocol).await,
         }
@@ -448,16 +549,36 @@ impl Relay {
     }
 
     async fn set_custom(subcmd: SetCustomCommands) -> Result<()> {
-        let custom_endpoint = match subcmd {
+        if let SetCustomCommands::WireguardConfig {
+            file,
+            apply_dns_and_mtu,
+        } = subcmd
+        {
+            return Self::import_wireguard_config(file, apply_dns_and_mtu).await;
+        }
+        let custom_endpoint = Self::read_custom_endpoint(subcmd).await?;
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_relay_settings(RelaySettings::CustomTunnelEndpoint(custom_endpoint))
+            .await?;
+        format::print_message("Relay constraints updated");
+        Ok(())
+    }
+
+    async fn read_custom_endpoint(subcmd: SetCustomCommands) -> Result<CustomTunnelEndpoint> {
+        match subcmd {
             SetCustomCommands::Openvpn {
                 host,
                 port,
                 username,
                 password,
                 transport_protocol,
-            } => {
-                Self::read_custom_openvpn_relay(host, port, username, password, transport_protocol)
-            }
+            } => Ok(Self::read_custom_openvpn_relay(
+                host,
+                port,
+                username,
+                password,
+                transport_protocol,
+            )),
             SetCustomCommands::Wireguard {
                 host,
                 port,
@@ -474,16 +595,157 @@ impl Relay {
                     v4_gateway,
                     v6_gateway,
                 )
-                .await?
+                .await
             }
-        };
+            SetCustomCommands::WireguardConfig {
+                file,
+                apply_dns_and_mtu,
+            } => {
+                if apply_dns_and_mtu {
+                    bail!("--apply-dns-and-mtu is only supported by 'mullvad relay set custom'");
+                }
+                Ok(Self::read_wireguard_config(file)
+                    .await?
+                    .custom_tunnel_endpoint())
+            }
+        }
+    }
+
+    async fn custom(subcmd: CustomCommands) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        rpc.set_relay_settings(RelaySettings::CustomTunnelEndpoint(custom_endpoint))
+        match subcmd {
+            CustomCommands::List => {
+                let settings = rpc.get_settings().await?;
+                if format::json_output() {
+                    return format::print_json(
+                        &settings.custom_endpoints.iter().collect::<Vec<_>>(),
+                    );
+                }
+                for endpoint in settings.custom_endpoints.iter() {
+                    println!("{}: {}", endpoint.name, endpoint.endpoint);
+                }
+            }
+            CustomCommands::Add { name, endpoint } => {
+                let endpoint = Self::read_custom_endpoint(endpoint).await?;
+                rpc.create_custom_endpoint(name, endpoint).await?;
+                format::print_message("Saved custom relay");
+            }
+            CustomCommands::Rename { name, new_name } => {
+                let mut endpoint = find_custom_endpoint_by_name(&mut rpc, &name).await?;
+                endpoint.name = new_name;
+                rpc.update_custom_endpoint(endpoint).await?;
+                format::print_message("Renamed custom relay");
+            }
+            CustomCommands::Remove { name } => {
+                let endpoint = find_custom_endpoint_by_name(&mut rpc, &name).await?;
+                rpc.delete_custom_endpoint(endpoint.id.to_string()).await?;
+                format::print_message("Removed custom relay");
+            }
+            CustomCommands::Clear => {
+                rpc.clear_custom_endpoints().await?;
+                format::print_message("Removed all saved custom relays");
+            }
+            CustomCommands::Use { names } => {
+                let mut ids = Vec::with_capacity(names.len());
+                for name in names {
+                    ids.push(find_custom_endpoint_by_name(&mut rpc, &name).await?.id);
+                }
+                rpc.set_relay_settings(RelaySettings::SavedCustomEndpoints(ids))
+                    .await?;
+                format::print_message("Relay constraints updated");
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_wireguard_config(source: String) -> Result<WgQuickConfig> {
+        let config = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => read_to_string(File::open(&source)?)
+                .context(format!("Failed to read from path: {source}")),
+        })
+        .await
+        .unwrap()?;
+        let config: WgQuickConfig = config.parse().context("Invalid WireGuard configuration")?;
+        for key in &config.ignored_keys {
+            eprintln!("Warning: Ignoring unsupported key \"{key}\"");
+        }
+        Ok(config)
+    }
+
+    async fn import_wireguard_config(source: String, apply_dns_and_mtu: bool) -> Result<()> {
+        let config = Self::read_wireguard_config(source).await?;
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_relay_settings(RelaySettings::CustomTunnelEndpoint(
+            config.custom_tunnel_endpoint(),
+        ))
+        .await?;
+        format::print_message("Relay constraints updated");
+
+        if !apply_dns_and_mtu {
+            if config.mtu.is_some() || config.custom_dns_servers().is_some() {
+                format::print_message(
+                    "DNS and MTU in the configuration were not applied. Use --apply-dns-and-mtu \
+                     to apply them to the global settings",
+                );
+            }
+            return Ok(());
+        }
+        if let Some(mtu) = config.mtu {
+            rpc.set_wireguard_mtu(Some(mtu)).await?;
+            format::print_message(format!("WireGuard MTU set to {mtu}"));
+        }
+        if let Some(servers) = config.custom_dns_servers() {
+            let settings = rpc.get_settings().await?;
+            rpc.set_dns_options(DnsOptions {
+                state: DnsState::Custom,
+                custom_options: CustomDnsOptions {
+                    addresses: servers.to_vec(),
+                    encrypted_upstreams: vec![],
+                },
+                ..settings.tunnel_options.dns_options
+            })
             .await?;
-        println!("Relay constraints updated");
+            format::print_message(format!("Custom DNS set to {}", servers.iter().join(", ")));
+        }
         Ok(())
     }
 
+    async fn export_wireguard_config(dest: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let settings = rpc.get_settings().await?;
+        let RelaySettings::CustomTunnelEndpoint(endpoint) = settings.relay_settings else {
+            bail!("No custom relay is configured");
+        };
+        let dns_options = &settings.tunnel_options.dns_options;
+        let dns_servers = match dns_options.state {
+            DnsState::Custom => dns_options.custom_options.addresses.clone(),
+            DnsState::Default => vec![],
+        };
+        let config = WgQuickConfig::from_endpoint(
+            endpoint,
+            dns_servers,
+            settings.tunnel_options.wireguard.mtu,
+        )?;
+
+        match dest.as_str() {
+            "-" => {
+                print!("{config}");
+                Ok(())
+            }
+            _ => {
+                let contents = config.to_string();
+                tokio::task::spawn_blocking(move || {
+                    write_secret_file(&dest, &contents)
+                        .context(format!("Failed to write to path {dest}"))
+                })
+                .await
+                .unwrap()
+            }
+        }
+    }
+
     fn read_custom_openvpn_relay(
         host: String,
         port: u16,
@@ -501,6 +763,7 @@ impl Relay {
                 username,
                 password,
             }),
+            wireguard_psk: None,
         }
     }
 
@@ -512,7 +775,12 @@ impl Relay {
         ipv4_gateway: Ipv4Addr,
         ipv6_gateway: Option<Ipv6Addr>,
     ) -> Result<CustomTunnelEndpoint> {
-        println!("Reading private key from standard input");
+        // Keep standard output parsable when printing JSON
+        if format::json_output() {
+            eprintln!("Reading private key from standard input");
+        } else {
+            println!("Reading private key from standard input");
+        }
 
         let private_key_str = tokio::task::spawn_blocking(|| {
             let mut private_key_str = String::new();
@@ -550,6 +818,7 @@ impl Relay {
                 #[cfg(target_os = "linux")]
                 fwmark: None,
             }),
+            wireguard_psk: None,
         })
     }
 
@@ -558,7 +827,7 @@ impl Relay {
         let relay_settings = rpc.get_settings().await?.get_relay_settings();
         let constraints = match relay_settings {
             RelaySettings::Normal(constraints) => constraints,
-            RelaySettings::CustomTunnelEndpoint(_custom) => {
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints(_) => {
                 bail!("Cannot change location while custom endpoint is set")
             }
         };
@@ -615,6 +884,13 @@ impl Relay {
         .await
     }
 
+    async fn set_selection_mode(mode: SelectionMode) -> Result<()> {
+        Self::update_constraints(|constraints| {
+            constraints.selection_mode = mode;
+        })
+        .await
+    }
+
     async fn set_openvpn_constraints(
         port: Option<Constraint<u16>>,
         protocol: Option<Constraint<TransportProtocol>>,
@@ -634,8 +910,8 @@ impl Relay {
     async fn get_openvpn_constraints(rpc: &mut MullvadProxyClient) -> Result<OpenVpnConstraints> {
         match rpc.get_settings().await?.relay_settings {
             RelaySettings::Normal(settings) => Ok(settings.openvpn_constraints),
-            RelaySettings::CustomTunnelEndpoint(_settings) => {
-                println!("Clearing custom tunnel constraints");
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints(_) => {
+                format::print_message("Clearing custom tunnel constraints");
                 Ok(OpenVpnConstraints::default())
             }
         }
@@ -705,8 +981,8 @@ impl Relay {
     ) -> Result<WireguardConstraints> {
         match rpc.get_settings().await?.relay_settings {
             RelaySettings::Normal(settings) => Ok(settings.wireguard_constraints),
-            RelaySettings::CustomTunnelEndpoint(_settings) => {
-                println!("Clearing custom tunnel constraints");
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints(_) => {
+                format::print_message("Clearing custom tunnel constraints");
                 Ok(WireguardConstraints::default())
             }
         }
@@ -747,7 +1023,7 @@ impl Relay {
         update_fn(&mut element);
 
         rpc.set_relay_override(element).await?;
-        println!("Updated override options for {hostname}");
+        format::print_message(format!("Updated override options for {hostname}"));
         Ok(())
     }
 
@@ -756,6 +1032,9 @@ impl Relay {
             OverrideCommands::Get => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?;
+                if format::json_output() {
+                    return format::print_json(&settings.relay_overrides);
+                }
 
                 let mut overrides = HashMap::new();
                 for relay_override in settings.relay_overrides {
@@ -874,7 +1153,7 @@ impl Relay {
                 {
                     let mut rpc = MullvadProxyClient::new().await?;
                     rpc.clear_all_relay_overrides().await?;
-                    println!("All overrides unset");
+                    format::print_message("All overrides unset");
                 }
             }
         }
@@ -900,7 +1179,7 @@ fn parse_transport_port(
     match (port, protocol) {
         (port, Constraint::Any) => {
             if port.is_only() {
-                println!("The port constraint was set to 'any'");
+                format::print_message("The port constraint was set to 'any'");
             }
             Constraint::Any
         }
@@ -967,6 +1246,18 @@ pub async fn resolve_location_constraint(
     }
 }
 
+pub async fn find_custom_endpoint_by_name(
+    rpc: &mut MullvadProxyClient,
+    name: &str,
+) -> Result<NamedCustomEndpoint> {
+    rpc.get_settings()
+        .await?
+        .custom_endpoints
+        .into_iter()
+        .find(|endpoint| endpoint.name == name)
+        .ok_or(anyhow!("Custom relay not found"))
+}
+
 /// Return a list of all relays that are active and not bridges
 pub async fn get_active_relays() -> Result<Vec<RelayListCountry>> {
     let mut rpc = MullvadProxyClient::new().await?;
@@ -997,3 +1288,12 @@ pub async fn get_active_relays() -> Result<Vec<RelayListCountry>> {
         })
         .collect_vec())
 }
+
+/// Write a file that is only readable by the current user, since it contains secrets.
+fn write_secret_file(path: &str, contents: &str) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(contents.as_bytes())
+}
@@ -9,6 +9,8 @@ pub async fn handle() -> Result<()> {
     let mut rpc = MullvadProxyClient::new().await?;
     rpc.factory_reset().await?;
     #[cfg(target_os = "linux")]
-    println!("If you're running systemd, to remove all logs, you must use journalctl");
+    crate::format::print_message(
+        "If you're running systemd, to remove all logs, you must use journalctl",
+    );
     Ok(())
 }
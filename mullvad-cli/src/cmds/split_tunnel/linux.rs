@@ -1,6 +1,10 @@
 use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use std::path::PathBuf;
+
+use super::super::BooleanOption;
+use crate::format;
 
 /// Manage split tunneling. To launch applications outside the tunnel, use the program
 /// 'mullvad-exclude' instead of this command
@@ -14,6 +18,24 @@ pub enum SplitTunnel {
     Delete { pid: i32 },
     /// Stop excluding all processes from the tunnel
     Clear,
+
+    /// Display the split tunnel status and apps
+    Get,
+
+    /// Enable or disable excluding apps from the tunnel
+    Set { policy: BooleanOption },
+
+    /// Manage applications to always exclude from the tunnel. Any process that runs one of these
+    /// executables is excluded automatically, along with its child processes
+    #[clap(subcommand)]
+    App(App),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum App {
+    Add { path: PathBuf },
+    Remove { path: PathBuf },
+    Clear,
 }
 
 impl SplitTunnel {
@@ -25,6 +47,10 @@ impl SplitTunnel {
                     .get_split_tunnel_processes()
                     .await?;
 
+                if format::json_output() {
+                    return format::print_json(&pids);
+                }
+
                 println!("Excluded PIDs:");
                 for pid in &pids {
                     println!("{pid}");
@@ -37,7 +63,7 @@ impl SplitTunnel {
                     .await?
                     .add_split_tunnel_process(pid)
                     .await?;
-                println!("Excluding process");
+                format::print_message("Excluding process");
                 Ok(())
             }
             SplitTunnel::Delete { pid } => {
@@ -45,7 +71,7 @@ impl SplitTunnel {
                     .await?
                     .remove_split_tunnel_process(pid)
                     .await?;
-                println!("Stopped excluding process");
+                format::print_message("Stopped excluding process");
                 Ok(())
             }
             SplitTunnel::Clear => {
@@ -53,7 +79,61 @@ impl SplitTunnel {
                     .await?
                     .clear_split_tunnel_processes()
                     .await?;
-                println!("Stopped excluding all processes");
+                format::print_message("Stopped excluding all processes");
+                Ok(())
+            }
+            SplitTunnel::Get => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let settings = rpc.get_settings().await?.split_tunnel;
+                if format::json_output() {
+                    return format::print_json(&settings);
+                }
+
+                let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
+
+                println!("Split tunneling state: {enable_exclusions}");
+
+                println!("Excluded applications:");
+                for path in &settings.apps {
+                    println!("{}", path.display());
+                }
+
+                Ok(())
+            }
+            SplitTunnel::Set { policy } => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.set_split_tunnel_state(*policy).await?;
+                format::print_message(format!("Split tunnel policy: {policy}"));
+                Ok(())
+            }
+            SplitTunnel::App(subcmd) => Self::app(subcmd).await,
+        }
+    }
+
+    async fn app(subcmd: App) -> Result<()> {
+        match subcmd {
+            App::Add { path } => {
+                MullvadProxyClient::new()
+                    .await?
+                    .add_split_tunnel_app(path)
+                    .await?;
+                format::print_message("Added path to excluded apps list");
+                Ok(())
+            }
+            App::Remove { path } => {
+                MullvadProxyClient::new()
+                    .await?
+                    .remove_split_tunnel_app(path)
+                    .await?;
+                format::print_message("Stopped excluding app from tunnel");
+                Ok(())
+            }
+            App::Clear => {
+                MullvadProxyClient::new()
+                    .await?
+                    .clear_split_tunnel_apps()
+                    .await?;
+                format::print_message("Stopped excluding all apps");
                 Ok(())
             }
         }
@@ -5,6 +5,7 @@ use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
 use super::super::BooleanOption;
+use crate::format;
 
 /// Set options for applications to exclude from the tunnel.
 #[derive(Subcommand, Debug)]
@@ -33,6 +34,9 @@ impl SplitTunnel {
             SplitTunnel::Get => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?.split_tunnel;
+                if format::json_output() {
+                    return format::print_json(&settings);
+                }
 
                 let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
 
@@ -48,7 +52,7 @@ impl SplitTunnel {
             SplitTunnel::Set { policy } => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.set_split_tunnel_state(*policy).await?;
-                println!("Split tunnel policy: {policy}");
+                format::print_message(format!("Split tunnel policy: {policy}"));
                 Ok(())
             }
             SplitTunnel::App(subcmd) => Self::app(subcmd).await,
@@ -62,7 +66,7 @@ impl SplitTunnel {
                     .await?
                     .add_split_tunnel_app(path)
                     .await?;
-                println!("Added path to excluded apps list");
+                format::print_message("Added path to excluded apps list");
                 Ok(())
             }
             App::Remove { path } => {
@@ -70,7 +74,7 @@ impl SplitTunnel {
                     .await?
                     .remove_split_tunnel_app(path)
                     .await?;
-                println!("Stopped excluding app from tunnel");
+                format::print_message("Stopped excluding app from tunnel");
                 Ok(())
             }
             App::Clear => {
@@ -78,7 +82,7 @@ impl SplitTunnel {
                     .await?
                     .clear_split_tunnel_apps()
                     .await?;
-                println!("Stopped excluding all apps");
+                format::print_message("Stopped excluding all apps");
                 Ok(())
             }
         }
@@ -8,6 +8,7 @@ use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
 use super::super::BooleanOption;
+use crate::format;
 
 /// Set options for applications to exclude from the tunnel.
 #[derive(Subcommand, Debug)]
@@ -16,7 +17,7 @@ pub enum SplitTunnel {
     Get {
         /// List processes that are currently being excluded, as well as whether they are
         /// excluded because of their executable paths or because they're subprocesses of
-        /// such processes
+        /// such processes. With --json, they are printed on a separate line after the settings
         #[arg(long)]
         list_processes: bool,
     },
@@ -43,6 +44,14 @@ impl SplitTunnel {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?.split_tunnel;
 
+                if format::json_output() {
+                    format::print_json(&settings)?;
+                    if list_processes {
+                        format::print_json(&rpc.get_excluded_processes().await?)?;
+                    }
+                    return Ok(());
+                }
+
                 let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
 
                 println!("Split tunneling state: {enable_exclusions}");
@@ -72,7 +81,7 @@ impl SplitTunnel {
             SplitTunnel::Set { policy } => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.set_split_tunnel_state(*policy).await?;
-                println!("Split tunnel policy: {policy}");
+                format::print_message(format!("Split tunnel policy: {policy}"));
                 Ok(())
             }
             SplitTunnel::App(subcmd) => Self::app(subcmd).await,
@@ -86,7 +95,7 @@ impl SplitTunnel {
                     .await?
                     .add_split_tunnel_app(path)
                     .await?;
-                println!("Added path to excluded apps list");
+                format::print_message("Added path to excluded apps list");
                 Ok(())
             }
             App::Remove { path } => {
@@ -94,7 +103,7 @@ impl SplitTunnel {
                     .await?
                     .remove_split_tunnel_app(path)
                     .await?;
-                println!("Stopped excluding app from tunnel");
+                format::print_message("Stopped excluding app from tunnel");
                 Ok(())
             }
             App::Clear => {
@@ -102,7 +111,7 @@ impl SplitTunnel {
                     .await?
                     .clear_split_tunnel_apps()
                     .await?;
-                println!("Stopped excluding all apps");
+                format::print_message("Stopped excluding all apps");
                 Ok(())
             }
         }
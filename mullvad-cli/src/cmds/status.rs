@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::{Args, Subcommand};
 use futures::StreamExt;
 use mullvad_management_interface::{client::DaemonEvent, MullvadProxyClient};
@@ -12,6 +12,8 @@ use crate::format;
 pub enum Status {
     /// Listen for tunnel state changes
     Listen,
+    /// Show the most recently detected network leaks
+    Leaks,
 }
 
 #[derive(Args, Debug)]
@@ -21,12 +23,13 @@ pub struct StatusArgs {
     verbose: bool,
 
     /// Enable debug output
-    #[arg(long, short = 'd', conflicts_with_all = ["verbose", "json"])]
+    #[arg(long, short = 'd', conflicts_with_all = ["verbose"])]
     debug: bool,
 
-    /// Format output as JSON
-    #[arg(long, short = 'j', conflicts_with_all = ["verbose", "debug"])]
-    json: bool,
+    /// Show traffic and connection statistics for the current tunnel. With --json, they are
+    /// printed on a separate line after the tunnel state
+    #[arg(long)]
+    stats: bool,
 }
 
 impl Status {
@@ -41,10 +44,8 @@ impl Status {
                 DaemonEvent::TunnelState(new_state) => {
                     if args.debug {
                         println!("New tunnel state: {new_state:#?}");
-                    } else if args.json {
-                        let json = serde_json::to_string(&new_state)
-                            .context("Failed to format output as JSON")?;
-                        println!("{json}");
+                    } else if format::json_output() {
+                        format::print_json(&new_state)?;
                     } else {
                         format::print_state(&new_state, Some(&previous_tunnel_state), args.verbose);
                         previous_tunnel_state = new_state;
@@ -68,6 +69,30 @@ impl Status {
                 DaemonEvent::NewAccessMethod(access_method) => {
                     print_debug_or_json(&args, "New access method", &access_method)?;
                 }
+                DaemonEvent::LeakDetected(report) => {
+                    print_debug_or_json(&args, "Leak detected", &report)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn leaks(mut rpc: MullvadProxyClient, args: StatusArgs) -> Result<()> {
+        let reports = rpc.get_leak_history().await?;
+        if args.debug {
+            println!("Leak history: {reports:#?}");
+        } else if format::json_output() {
+            format::print_json(&reports)?;
+        } else if reports.is_empty() {
+            println!("No leaks detected");
+        } else {
+            for report in reports {
+                println!(
+                    "{}: {}",
+                    report.detected_at.with_timezone(&chrono::Local),
+                    report.info
+                );
+                println!("\tTunnel endpoint: {}", report.tunnel_endpoint);
             }
         }
         Ok(())
@@ -76,20 +101,27 @@ impl Status {
 
 pub async fn handle(cmd: Option<Status>, args: StatusArgs) -> Result<()> {
     let mut rpc = MullvadProxyClient::new().await?;
+    if cmd == Some(Status::Leaks) {
+        return Status::leaks(rpc, args).await;
+    }
+
     let state = rpc.get_tunnel_state().await?;
     let device = rpc.get_device().await?;
 
-    print_account_logged_out(&state, &device);
-
     if args.debug {
+        print_account_logged_out(&state, &device);
         println!("Tunnel state: {state:#?}");
-    } else if args.json {
-        let json = serde_json::to_string(&state).context("Failed to format output as JSON")?;
-        println!("{json}");
+    } else if format::json_output() {
+        format::print_json(&state)?;
     } else {
+        print_account_logged_out(&state, &device);
         format::print_state(&state, None, args.verbose);
     }
 
+    if args.stats {
+        print_tunnel_statistics(&mut rpc, &args).await?;
+    }
+
     if cmd == Some(Status::Listen) {
         Status::listen(rpc, args, state).await?;
     }
@@ -111,6 +143,21 @@ fn print_account_logged_out(state: &TunnelState, device: &DeviceState) {
     }
 }
 
+async fn print_tunnel_statistics(rpc: &mut MullvadProxyClient, args: &StatusArgs) -> Result<()> {
+    let statistics = rpc.get_tunnel_statistics().await?;
+    if args.debug {
+        println!("Tunnel statistics: {statistics:#?}");
+    } else if format::json_output() {
+        format::print_json(&statistics)?;
+    } else {
+        match statistics {
+            Some(statistics) => format::print_tunnel_statistics(&statistics),
+            None => println!("No tunnel statistics available. Connect to a WireGuard relay first."),
+        }
+    }
+    Ok(())
+}
+
 fn print_debug_or_json<T: Debug + Serialize>(
     args: &StatusArgs,
     debug_message: &str,
@@ -118,9 +165,8 @@ fn print_debug_or_json<T: Debug + Serialize>(
 ) -> Result<()> {
     if args.debug {
         println!("{debug_message}: {t:#?}");
-    } else if args.json {
-        let json = serde_json::to_string(&t).context("Failed to format output as JSON")?;
-        println!("{json}");
+    } else if format::json_output() {
+        format::print_json(t)?;
     }
 
     Ok(())
@@ -7,7 +7,7 @@ use mullvad_types::{
 };
 
 use super::BooleanOption;
-use crate::print_option;
+use crate::{format, print_option};
 
 #[derive(Subcommand, Debug)]
 pub enum Tunnel {
@@ -75,6 +75,10 @@ impl Tunnel {
         let mut rpc = MullvadProxyClient::new().await?;
         let tunnel_options = rpc.get_settings().await?.tunnel_options;
 
+        if format::json_output() {
+            return format::print_json(&tunnel_options);
+        }
+
         println!("OpenVPN options");
 
         print_option!(
@@ -159,7 +163,7 @@ impl Tunnel {
     async fn handle_ipv6(state: BooleanOption) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_enable_ipv6(*state).await?;
-        println!("IPv6: {state}");
+        format::print_message(format!("IPv6: {state}"));
         Ok(())
     }
 
@@ -168,7 +172,7 @@ impl Tunnel {
 
         if let Some(mssfix) = mssfix {
             rpc.set_openvpn_mssfix(mssfix.option()).await?;
-            println!("mssfix parameter has been updated");
+            format::print_message("mssfix parameter has been updated");
         }
 
         Ok(())
@@ -186,44 +190,44 @@ impl Tunnel {
 
         if let Some(mtu) = mtu {
             rpc.set_wireguard_mtu(mtu.option()).await?;
-            println!("MTU parameter has been updated");
+            format::print_message("MTU parameter has been updated");
         }
 
         if let Some(quantum_resistant) = quantum_resistant {
             rpc.set_quantum_resistant_tunnel(quantum_resistant).await?;
-            println!("Quantum resistant setting has been updated");
+            format::print_message("Quantum resistant setting has been updated");
         }
 
         if let Some(enable_daita) = daita {
             rpc.set_enable_daita(*enable_daita).await?;
-            println!("DAITA setting has been updated");
-            println!("Direct only setting has been updated");
+            format::print_message("DAITA setting has been updated");
+            format::print_message("Direct only setting has been updated");
         }
 
         if let Some(daita_direct_only) = daita_direct_only {
             rpc.set_daita_direct_only(*daita_direct_only).await?;
-            println!("Direct only setting has been updated");
+            format::print_message("Direct only setting has been updated");
         }
 
         if let Some(interval) = rotation_interval {
             match interval {
                 Constraint::Only(interval) => {
                     rpc.set_wireguard_rotation_interval(interval).await?;
-                    println!("Set key rotation interval to {}", interval);
+                    format::print_message(format!("Set key rotation interval to {interval}"));
                 }
                 Constraint::Any => {
                     rpc.reset_wireguard_rotation_interval().await?;
-                    println!(
+                    format::print_message(format!(
                         "Reset key rotation interval to {}",
                         RotationInterval::new(DEFAULT_ROTATION_INTERVAL).unwrap()
-                    );
+                    ));
                 }
             }
         }
 
         if matches!(rotate_key, Some(RotateKey::RotateKey)) {
             rpc.rotate_wireguard_key().await?;
-            println!("Rotated WireGuard key");
+            format::print_message("Rotated WireGuard key");
         }
 
         Ok(())
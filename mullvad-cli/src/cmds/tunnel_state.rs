@@ -81,7 +81,11 @@ async fn wait_for_tunnel_state(
 ) -> Result<()> {
     while let Some(state) = event_stream.next().await {
         if let DaemonEvent::TunnelState(new_state) = state? {
-            format::print_state(&new_state, None, false);
+            if format::json_output() {
+                format::print_json(&new_state)?;
+            } else {
+                format::print_state(&new_state, None, false);
+            }
             if matches_event(&new_state)? {
                 return Ok(());
             }
@@ -98,12 +102,18 @@ async fn wait_for_tunnel_state(
 /// to their account. In this case, we at least want to issue a warning to guide
 /// the user when they inevitably will go troubleshooting.
 fn print_account_loggedout(state: &DeviceState) {
-    match state {
-        DeviceState::LoggedOut => println!("Warning: You are not logged in to an account."),
-        DeviceState::Revoked => println!("Warning: This device has been revoked"),
+    let warning = match state {
+        DeviceState::LoggedOut => "Warning: You are not logged in to an account.",
+        DeviceState::Revoked => "Warning: This device has been revoked",
         DeviceState::LoggedIn(_) => return, // Normal case, do nothing.
     };
 
+    // Keep standard output parsable when printing JSON
+    if format::json_output() {
+        eprintln!("{warning}");
+        return;
+    }
+    println!("{warning}");
     println!(
         "Mullvad is blocking all network traffic until you perform one of the following actions:
 
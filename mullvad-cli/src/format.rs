@@ -1,15 +1,52 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
 
+use anyhow::{Context, Result};
 use itertools::Itertools;
 use mullvad_types::{
     auth_failed::AuthFailed, features::FeatureIndicators, location::GeoIpLocation,
     states::TunnelState,
 };
+use serde::Serialize;
 use talpid_types::{
-    net::{Endpoint, TunnelEndpoint},
+    net::{wireguard::TunnelStatistics, Endpoint, TunnelEndpoint},
     tunnel::{ActionAfterDisconnect, ErrorState},
 };
 
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Make commands that display information print it as JSON, using [`print_json`].
+pub fn enable_json_output() {
+    JSON_OUTPUT.store(true, Ordering::Relaxed);
+}
+
+/// Whether output should be printed as JSON rather than formatted for humans.
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Print `value` as a single line of JSON. The schema is given by the serde implementation of
+/// the type.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string(value).context("Failed to format output as JSON")?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Print a message that describes what a command did. With JSON output, it is printed as
+/// `{"message": "..."}`.
+pub fn print_message(message: impl fmt::Display) {
+    if json_output() {
+        println!("{}", serde_json::json!({ "message": message.to_string() }));
+    } else {
+        println!("{message}");
+    }
+}
+
 #[macro_export]
 macro_rules! print_option {
     ($value:expr_2021 $(,)?) => {{
@@ -210,6 +247,49 @@ fn print_connection_info(
     }
 }
 
+pub fn print_tunnel_statistics(statistics: &TunnelStatistics) {
+    println!("Tunnel statistics");
+    print_option!("Uptime", format_duration(statistics.uptime));
+    print_option!("MTU", statistics.mtu);
+    if let Some(tx_packets) = statistics.tx_packets {
+        print_option!("Packets sent", tx_packets);
+    }
+    if let Some(rx_packets) = statistics.rx_packets {
+        print_option!("Packets received", rx_packets);
+    }
+    if !statistics.ping_rtts.is_empty() {
+        let rtts = statistics
+            .ping_rtts
+            .iter()
+            .map(|rtt| format!("{} ms", rtt.as_millis()))
+            .join(", ");
+        print_option!("Ping round-trip times", rtts);
+    }
+    for peer in &statistics.peers {
+        println!("Peer {}", peer.public_key);
+        print_option!("Bytes sent", peer.tx_bytes);
+        print_option!("Bytes received", peer.rx_bytes);
+        let last_handshake = peer
+            .last_handshake
+            .map(|time| chrono::DateTime::<chrono::Local>::from(time).to_string())
+            .unwrap_or_else(|| "Never".to_owned());
+        print_option!("Latest handshake", last_handshake);
+        print_option!("Handshakes", peer.handshakes);
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m {secs}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {secs}s")
+    } else {
+        format!("{secs}s")
+    }
+}
+
 pub fn format_location(location: &GeoIpLocation) -> String {
     let mut formatted_location = location.country.to_string();
     if let Some(city) = &location.city {
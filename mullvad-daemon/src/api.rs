@@ -3,6 +3,7 @@
 //! converting [`AccessMethodSetting`]s to connection details as encoded by
 //! [`ApiConnectionMode`], which in turn is used by `mullvad-api` for
 //! establishing connections when performing API requests.
+use crate::relay_latency::LatencyMeasurer;
 #[cfg(target_os = "android")]
 use crate::DaemonCommand;
 use crate::DaemonEventSender;
@@ -682,26 +683,33 @@ pub fn allowed_clients(connection_mode: &ApiConnectionMode) -> AllowedClients {
     }
 }
 
-/// Forwards the received values from `offline_state_rx` to the [`ApiAvailability`].
+/// Forwards the received values from `offline_state_rx` to the [`ApiAvailability`] and the
+/// auto-connect rules.
 pub(crate) fn forward_offline_state(
     api_availability: ApiAvailability,
+    auto_connect_rules: AutoConnectRulesHandle,
+    latency_measurer: LatencyMeasurer,
     mut offline_state_rx: mpsc::UnboundedReceiver<Connectivity>,
 ) {
     tokio::spawn(async move {
-        let is_offline = offline_state_rx
+        let state = offline_state_rx
             .next()
             .await
-            .expect("missing initial offline state")
-            .is_offline();
+            .expect("missing initial offline state");
+        let is_offline = state.is_offline();
         log::info!(
             "Initial offline state - {state}",
             state = if is_offline { "offline" } else { "online" },
         );
         api_availability.set_offline(is_offline);
+        auto_connect_rules.set_connectivity(state);
 
         while let Some(state) = offline_state_rx.next().await {
             log::info!("Detecting changes to offline state - {state:?}");
             api_availability.set_offline(state.is_offline());
+            auto_connect_rules.set_connectivity(state);
+            // Latencies measured before connectivity changed may no longer apply
+            latency_measurer.reset();
         }
     });
 }
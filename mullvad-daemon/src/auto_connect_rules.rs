@@ -0,0 +1,510 @@
+//! Evaluates the auto-connect rules and trusted networks whenever connectivity changes, and
+//! periodically to catch time windows starting or ending. When a different rule starts to match,
+//! or when moving onto or off a trusted network, the daemon is notified. Events are only sent when
+//! something changes, so that the user can still connect or disconnect manually.
+//!
+//! If the daemon has asked to identify networks, it is also notified when the device moves onto a
+//! different network, so that it can use the obfuscation that last worked on that network.
+//!
+//! Network details are only read when something needs them. Only time windows can be evaluated on
+//! platforms other than Linux and macOS, where networks cannot be identified, so rules with other
+//! conditions are rejected there.
+
+use crate::DaemonEventSender;
+use chrono::NaiveTime;
+use futures::{
+    channel::{mpsc, oneshot},
+    FutureExt, StreamExt,
+};
+use mullvad_types::{
+    auto_connect::{AutoConnectRule, AutoConnectRules, NetworkDetails, TrustedNetwork},
+    learned_obfuscation::NetworkId,
+};
+use std::time::Duration;
+use talpid_core::mpsc::Sender;
+#[cfg(target_os = "macos")]
+use talpid_routing::RouteManagerHandle;
+use talpid_types::net::Connectivity;
+
+/// How often to evaluate the rules when nothing else triggers it.
+const EVALUATION_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// A different rule started to match. `None` if no rule matches anymore.
+    RuleMatched(Option<AutoConnectRule>),
+    /// The device moved onto (`true`) or off (`false`) a trusted network
+    TrustedNetwork(bool),
+    /// The device moved onto a different network. `None` if the network cannot be identified.
+    NetworkChanged(Option<NetworkId>),
+}
+
+enum Command {
+    SetRules(AutoConnectRules),
+    SetTrustedNetworks(Vec<TrustedNetwork>),
+    SetIdentifyNetworks(bool),
+    SetConnectivity(Connectivity),
+    GetNetwork(oneshot::Sender<Option<NetworkId>>),
+}
+
+#[derive(Clone)]
+pub struct AutoConnectRulesHandle {
+    tx: mpsc::UnboundedSender<Command>,
+}
+
+impl AutoConnectRulesHandle {
+    pub fn set_rules(&self, rules: AutoConnectRules) {
+        let _ = self.tx.unbounded_send(Command::SetRules(rules));
+    }
+
+    pub fn set_trusted_networks(&self, networks: Vec<TrustedNetwork>) {
+        let _ = self
+            .tx
+            .unbounded_send(Command::SetTrustedNetworks(networks));
+    }
+
+    /// Set whether to send [`Event::NetworkChanged`] when the device moves onto a different
+    /// network.
+    pub fn set_identify_networks(&self, identify: bool) {
+        let _ = self
+            .tx
+            .unbounded_send(Command::SetIdentifyNetworks(identify));
+    }
+
+    pub fn set_connectivity(&self, connectivity: Connectivity) {
+        let _ = self
+            .tx
+            .unbounded_send(Command::SetConnectivity(connectivity));
+    }
+
+    /// Read the network details right away and identify the network that the device is on.
+    /// Returns `None` if the device is offline or the network cannot be identified.
+    pub async fn current_network(&self) -> Option<NetworkId> {
+        let (tx, rx) = oneshot::channel();
+        self.tx.unbounded_send(Command::GetNetwork(tx)).ok()?;
+        rx.await.ok().flatten()
+    }
+}
+
+pub(crate) fn spawn(
+    rules: AutoConnectRules,
+    trusted_networks: Vec<TrustedNetwork>,
+    identify_networks: bool,
+    event_tx: DaemonEventSender<Event>,
+    #[cfg(target_os = "macos")] route_manager: RouteManagerHandle,
+) -> AutoConnectRulesHandle {
+    let (tx, rx) = mpsc::unbounded();
+    tokio::spawn(run(
+        rules,
+        trusted_networks,
+        identify_networks,
+        rx,
+        event_tx,
+        #[cfg(target_os = "macos")]
+        route_manager,
+    ));
+    AutoConnectRulesHandle { tx }
+}
+
+async fn run(
+    mut rules: AutoConnectRules,
+    mut trusted_networks: Vec<TrustedNetwork>,
+    mut identify_networks: bool,
+    mut rx: mpsc::UnboundedReceiver<Command>,
+    event_tx: DaemonEventSender<Event>,
+    #[cfg(target_os = "macos")] route_manager: RouteManagerHandle,
+) {
+    let mut connectivity = None;
+    let mut rule_state = RuleState::default();
+    let mut current_network = None;
+
+    loop {
+        let mut network_reply = None;
+        futures::select! {
+            command = rx.next() => match command {
+                Some(Command::SetRules(new_rules)) => {
+                    if new_rules == rules {
+                        continue;
+                    }
+                    rules = new_rules;
+                }
+                Some(Command::SetTrustedNetworks(new_networks)) => {
+                    if new_networks == trusted_networks {
+                        continue;
+                    }
+                    trusted_networks = new_networks;
+                }
+                Some(Command::SetIdentifyNetworks(identify)) => {
+                    if identify == identify_networks {
+                        continue;
+                    }
+                    identify_networks = identify;
+                    // Report the network again if identification is turned back on
+                    current_network = None;
+                }
+                Some(Command::SetConnectivity(new_connectivity)) => {
+                    connectivity = Some(new_connectivity);
+                }
+                Some(Command::GetNetwork(tx)) => network_reply = Some(tx),
+                None => break,
+            },
+            _ = talpid_time::sleep(EVALUATION_INTERVAL).fuse() => (),
+        }
+
+        // Keep the current state until we know which network we're on
+        if !connectivity.as_ref().is_some_and(Connectivity::is_online) {
+            if let Some(tx) = network_reply {
+                let _ = tx.send(None);
+            }
+            continue;
+        }
+
+        let network = if rules.is_empty()
+            && trusted_networks.is_empty()
+            && !identify_networks
+            && network_reply.is_none()
+        {
+            NetworkDetails::default()
+        } else {
+            network_details(
+                #[cfg(target_os = "macos")]
+                &route_manager,
+            )
+            .await
+        };
+
+        let network_id = NetworkId::from_details(&network);
+        if let Some(tx) = network_reply {
+            let _ = tx.send(network_id.clone());
+        }
+        if identify_networks && network_id != current_network {
+            log::debug!("Network changed: {network:?}");
+            if event_tx
+                .send(Event::NetworkChanged(network_id.clone()))
+                .is_err()
+            {
+                break;
+            }
+            current_network = network_id;
+        }
+
+        let now = chrono::Local::now().time();
+        let events = rule_state.evaluate(&rules, &trusted_networks, &network, now);
+        if events
+            .into_iter()
+            .any(|event| event_tx.send(event).is_err())
+        {
+            break;
+        }
+    }
+}
+
+/// Remembers which rule matched and whether the device was on a trusted network, so that events
+/// are only sent when either changes.
+#[derive(Default)]
+struct RuleState {
+    current_rule: Option<AutoConnectRule>,
+    on_trusted_network: bool,
+}
+
+impl RuleState {
+    /// Evaluate `rules` and `trusted_networks` against `network` at the local time `now`, and
+    /// return the events to send. Trusted networks are evaluated last, so that they take
+    /// precedence over rules.
+    fn evaluate(
+        &mut self,
+        rules: &AutoConnectRules,
+        trusted_networks: &[TrustedNetwork],
+        network: &NetworkDetails,
+        now: NaiveTime,
+    ) -> Vec<Event> {
+        let mut events = vec![];
+
+        let matching_rule = rules.first_match(network, now).cloned();
+        if matching_rule != self.current_rule {
+            match &matching_rule {
+                Some(rule) => {
+                    log::debug!("Auto-connect rule \"{}\" matches {network:?}", rule.name)
+                }
+                None => log::debug!("No auto-connect rule matches {network:?}"),
+            }
+            events.push(Event::RuleMatched(matching_rule.clone()));
+            self.current_rule = matching_rule;
+        }
+
+        let trusted = trusted_networks
+            .iter()
+            .any(|trusted_network| trusted_network.matches(network));
+        if trusted != self.on_trusted_network {
+            log::debug!("Trusted network: {trusted}, {network:?}");
+            events.push(Event::TrustedNetwork(trusted));
+            self.on_trusted_network = trusted;
+        }
+
+        events
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn network_details() -> NetworkDetails {
+    tokio::task::spawn_blocking(linux::network_details)
+        .await
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+async fn network_details(route_manager: &RouteManagerHandle) -> NetworkDetails {
+    use mullvad_types::auto_connect::MacAddress;
+
+    let mut network = NetworkDetails::default();
+    if let Ok((Some(route), _)) = route_manager.get_default_routes().await {
+        network.interface = Some(route.interface);
+        network.gateway_ip = Some(route.router_ip);
+    }
+    if let Ok((Some(gateway), _)) = route_manager.get_default_gateway().await {
+        network.gateway_mac = Some(MacAddress(gateway.mac_address.0));
+    }
+    network.dns_suffixes = tokio::fs::read_to_string(RESOLV_CONF_PATH)
+        .await
+        .map(|contents| parse_dns_suffixes(&contents))
+        .unwrap_or_default();
+    network
+}
+
+/// Only time windows can be evaluated on this platform.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn network_details() -> NetworkDetails {
+    NetworkDetails::default()
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// Return the search domains in a `resolv.conf` file.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn parse_dns_suffixes(resolv_conf: &str) -> Vec<String> {
+    resolv_conf
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("search" | "domain") => Some(words.map(str::to_owned)),
+                _ => None,
+            }
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{parse_dns_suffixes, RESOLV_CONF_PATH};
+    use mullvad_types::auto_connect::{MacAddress, NetworkDetails};
+    use std::{fs, net::Ipv4Addr, sync::Mutex};
+    use talpid_dbus::network_manager::NetworkManager;
+    use talpid_types::ErrorExt;
+
+    const RTF_UP: u16 = 0x1;
+    const RTF_GATEWAY: u16 = 0x2;
+
+    pub fn network_details() -> NetworkDetails {
+        let mut network = NetworkDetails::default();
+        if let Some((interface, gateway)) = fs::read_to_string("/proc/net/route")
+            .ok()
+            .and_then(|routes| parse_default_route(&routes))
+        {
+            network.gateway_mac = fs::read_to_string("/proc/net/arp")
+                .ok()
+                .and_then(|arp| parse_gateway_mac(&arp, gateway, &interface));
+            network.gateway_ip = Some(gateway.into());
+            network.ssid = wireless_ssid(&interface);
+            network.interface = Some(interface);
+        }
+        network.dns_suffixes = fs::read_to_string(RESOLV_CONF_PATH)
+            .map(|contents| parse_dns_suffixes(&contents))
+            .unwrap_or_default();
+        network
+    }
+
+    /// NetworkManager client, which is created when first needed and then reused.
+    static NETWORK_MANAGER: Mutex<Option<NetworkManager>> = Mutex::new(None);
+
+    /// Ask NetworkManager for the SSID of the Wi-Fi network that `interface` is connected to.
+    fn wireless_ssid(interface: &str) -> Option<String> {
+        let mut network_manager = NETWORK_MANAGER.lock().unwrap();
+        if network_manager.is_none() {
+            *network_manager = NetworkManager::new().ok();
+        }
+        match network_manager.as_ref()?.get_wireless_ssid(interface) {
+            Ok(ssid) => ssid.map(|ssid| String::from_utf8_lossy(&ssid).into_owned()),
+            Err(error) => {
+                log::trace!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to obtain SSID from NetworkManager")
+                );
+                None
+            }
+        }
+    }
+
+    /// Return the interface and gateway of the IPv4 default route with the lowest metric, given
+    /// the contents of `/proc/net/route`.
+    fn parse_default_route(routes: &str) -> Option<(String, Ipv4Addr)> {
+        routes
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                let [interface, destination, gateway, flags, _refcnt, _use, metric, mask, ..] =
+                    fields[..]
+                else {
+                    return None;
+                };
+                let flags = u16::from_str_radix(flags, 16).ok()?;
+                if destination != "00000000"
+                    || mask != "00000000"
+                    || flags & (RTF_UP | RTF_GATEWAY) != RTF_UP | RTF_GATEWAY
+                {
+                    return None;
+                }
+                // The address is printed as a number in host byte order
+                let gateway = u32::from_str_radix(gateway, 16).ok()?;
+                let metric: u32 = metric.parse().ok()?;
+                Some((
+                    metric,
+                    interface.to_owned(),
+                    Ipv4Addr::from(gateway.to_ne_bytes()),
+                ))
+            })
+            .min_by_key(|(metric, ..)| *metric)
+            .map(|(_, interface, gateway)| (interface, gateway))
+    }
+
+    /// Find the MAC address of `gateway` in the contents of `/proc/net/arp`.
+    fn parse_gateway_mac(arp: &str, gateway: Ipv4Addr, interface: &str) -> Option<MacAddress> {
+        arp.lines().skip(1).find_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let [ip, _hw_type, _flags, mac, _mask, device] = fields[..] else {
+                return None;
+            };
+            if ip.parse::<Ipv4Addr>().ok() != Some(gateway) || device != interface {
+                return None;
+            }
+            mac.parse().ok()
+        })
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_parse_default_route() {
+            let gateway = u32::from_ne_bytes([192, 168, 1, 1]);
+            let other_gateway = u32::from_ne_bytes([10, 0, 0, 1]);
+            let routes = format!(
+                "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n\
+                 eth0\t00000000\t{other_gateway:08X}\t0003\t0\t0\t600\t00000000\t0\t0\t0\n\
+                 wlan0\t00000000\t{gateway:08X}\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+                 wlan0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n"
+            );
+            assert_eq!(
+                parse_default_route(&routes),
+                Some(("wlan0".to_owned(), Ipv4Addr::new(192, 168, 1, 1)))
+            );
+
+            let arp = "IP address       HW type     Flags       HW address            Mask     Device\n\
+                       192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:ff     *        wlan0\n";
+            assert_eq!(
+                parse_gateway_mac(arp, Ipv4Addr::new(192, 168, 1, 1), "wlan0"),
+                Some("aa:bb:cc:dd:ee:ff".parse().unwrap())
+            );
+        }
+
+        #[test]
+        fn test_parse_dns_suffixes() {
+            let resolv_conf = "# comment\nnameserver 127.0.0.53\nsearch corp.example.com lan\n";
+            assert_eq!(parse_dns_suffixes(resolv_conf), ["corp.example.com", "lan"]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::auto_connect::{AutoConnectAction, RuleConditions};
+
+    fn time(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    fn rule(name: &str, conditions: RuleConditions) -> AutoConnectRule {
+        AutoConnectRule {
+            name: name.to_owned(),
+            conditions,
+            action: AutoConnectAction::Connect,
+        }
+    }
+
+    /// Events should only be emitted when the matching rule changes
+    #[test]
+    fn test_rule_events() {
+        let office_hours = rule(
+            "office hours",
+            RuleConditions {
+                time_window: Some("08:00-17:00".parse().unwrap()),
+                ..Default::default()
+            },
+        );
+        let rules = AutoConnectRules::new(vec![office_hours.clone()]).unwrap();
+        let network = NetworkDetails::default();
+        let mut state = RuleState::default();
+
+        assert_eq!(
+            state.evaluate(&rules, &[], &network, time("09:00")),
+            [Event::RuleMatched(Some(office_hours))]
+        );
+        assert!(state
+            .evaluate(&rules, &[], &network, time("12:00"))
+            .is_empty());
+        assert_eq!(
+            state.evaluate(&rules, &[], &network, time("18:00")),
+            [Event::RuleMatched(None)]
+        );
+        assert!(state
+            .evaluate(&rules, &[], &network, time("19:00"))
+            .is_empty());
+    }
+
+    /// Moving between trusted and untrusted networks should be reported once, after any rule
+    #[test]
+    fn test_trusted_network_events() {
+        let always = rule("always", RuleConditions::default());
+        let rules = AutoConnectRules::new(vec![always.clone()]).unwrap();
+        let trusted_networks = [TrustedNetwork::Subnet("192.168.1.0/24".parse().unwrap())];
+        let home = NetworkDetails {
+            gateway_ip: Some("192.168.1.1".parse().unwrap()),
+            ..Default::default()
+        };
+        let elsewhere = NetworkDetails {
+            gateway_ip: Some("10.0.0.1".parse().unwrap()),
+            ..Default::default()
+        };
+        let mut state = RuleState::default();
+
+        assert_eq!(
+            state.evaluate(&rules, &trusted_networks, &home, time("09:00")),
+            [
+                Event::RuleMatched(Some(always)),
+                Event::TrustedNetwork(true)
+            ]
+        );
+        assert!(state
+            .evaluate(&rules, &trusted_networks, &home, time("09:01"))
+            .is_empty());
+        assert_eq!(
+            state.evaluate(&rules, &trusted_networks, &elsewhere, time("09:02")),
+            [Event::TrustedNetwork(false)]
+        );
+    }
+}
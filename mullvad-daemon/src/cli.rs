@@ -13,6 +13,9 @@ static ENV_DESC: LazyLock<String> = LazyLock::new(|| {
     MULLVAD_RPC_SOCKET_PATH    Location of the management interface device.
                                It refers to Unix domain socket on Unix based platforms, and named pipe on Windows.
                                [Default: {}]
+    MULLVAD_METRICS_ADDRESS    Serve OpenMetrics on this loopback address, e.g. 127.0.0.1:9531.
+                               On Unix based platforms, unix:PATH serves them on a Unix domain socket.
+                               [Default: disabled]
 
 ",
         mullvad_paths::get_default_resource_dir().display(),
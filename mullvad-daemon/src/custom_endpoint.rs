@@ -0,0 +1,119 @@
+use crate::{Daemon, Error};
+use mullvad_relay_selector::SelectorConfig;
+use mullvad_types::{
+    custom_endpoint::{self, Id, NamedCustomEndpoint},
+    relay_constraints::RelaySettings,
+    CustomTunnelEndpoint,
+};
+
+impl Daemon {
+    /// Save a new custom endpoint.
+    ///
+    /// Returns an error if the name is not unique.
+    pub async fn create_custom_endpoint(
+        &mut self,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) -> Result<Id, Error> {
+        let new_endpoint =
+            NamedCustomEndpoint::new(name, endpoint).map_err(Error::CustomEndpointError)?;
+        let id = new_endpoint.id;
+
+        self.settings
+            .try_update(|settings| settings.custom_endpoints.add(new_endpoint))
+            .await
+            .map_err(Error::SettingsError)?;
+
+        Ok(id)
+    }
+
+    /// Delete a saved custom endpoint.
+    ///
+    /// Returns an error if the endpoint doesn't exist, or if it is selected in the relay settings.
+    pub async fn delete_custom_endpoint(&mut self, id: Id) -> Result<(), Error> {
+        let settings_changed = self
+            .settings
+            .try_update(|settings| {
+                if let RelaySettings::SavedCustomEndpoints(selected) = &settings.relay_settings {
+                    if selected.contains(&id) {
+                        return Err(custom_endpoint::Error::EndpointInUse);
+                    }
+                }
+                settings.custom_endpoints.remove(&id)
+            })
+            .await
+            .map_err(Error::SettingsError);
+
+        self.on_custom_endpoints_changed(settings_changed.as_ref().ok(), Some(id));
+
+        settings_changed?;
+        Ok(())
+    }
+
+    /// Update a saved custom endpoint.
+    ///
+    /// Returns an error if...
+    /// - there is no existing endpoint with the same ID,
+    /// - or another endpoint has the same name.
+    pub async fn update_custom_endpoint(
+        &mut self,
+        new_endpoint: NamedCustomEndpoint,
+    ) -> Result<(), Error> {
+        let id = new_endpoint.id;
+        let settings_changed = self
+            .settings
+            .try_update(|settings| settings.custom_endpoints.update(new_endpoint))
+            .await
+            .map_err(Error::SettingsError);
+
+        self.on_custom_endpoints_changed(settings_changed.as_ref().ok(), Some(id));
+
+        settings_changed?;
+        Ok(())
+    }
+
+    /// Remove all saved custom endpoints.
+    ///
+    /// Returns an error if saved custom endpoints are selected in the relay settings.
+    pub async fn clear_custom_endpoints(&mut self) -> Result<(), Error> {
+        let settings_changed = self
+            .settings
+            .try_update(|settings| {
+                if let RelaySettings::SavedCustomEndpoints(_) = settings.relay_settings {
+                    return Err(custom_endpoint::Error::EndpointInUse);
+                }
+                settings.custom_endpoints.clear();
+                Ok(())
+            })
+            .await
+            .map_err(Error::SettingsError);
+
+        self.on_custom_endpoints_changed(settings_changed.as_ref().ok(), None);
+
+        settings_changed?;
+        Ok(())
+    }
+
+    fn on_custom_endpoints_changed(&mut self, settings_changed: Option<&bool>, id: Option<Id>) {
+        if settings_changed != Some(&true) {
+            return;
+        }
+        self.relay_selector
+            .set_config(SelectorConfig::from_settings(&self.settings));
+
+        if self.change_should_cause_reconnect_to_endpoint(id) {
+            log::info!("Initiating tunnel restart because a selected custom endpoint changed");
+            self.reconnect_tunnel();
+        }
+    }
+
+    /// Check whether we need to reconnect after changing saved custom endpoints.
+    ///
+    /// If `id` is `Some`, only changes to that endpoint will trigger a reconnect.
+    fn change_should_cause_reconnect_to_endpoint(&self, id: Option<Id>) -> bool {
+        let RelaySettings::SavedCustomEndpoints(selected) = &self.settings.relay_settings else {
+            return false;
+        };
+        id.map(|id| selected.contains(&id)).unwrap_or(true)
+    }
+}
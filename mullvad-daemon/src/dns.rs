@@ -1,6 +1,7 @@
 use mullvad_types::settings::{DnsOptions, DnsState};
 use std::net::{IpAddr, Ipv4Addr};
 use talpid_core::{dns::DnsConfig, firewall::is_local_address};
+use talpid_types::net::dns::SplitDnsRoutes;
 
 /// When we want to block certain contents with the help of DNS server side,
 /// we compute the resolver IP to use based on these constants. The last
@@ -15,6 +16,11 @@ const DNS_SOCIAL_MEDIA_BLOCKING_IP_BIT: u8 = 1 << 5; // 0b00100000
 
 /// Return the DNS resolvers to use
 pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
+    default_addresses_from_options(options).with_split_dns(split_dns_from_options(options))
+}
+
+/// Return the DNS resolvers to use for names that match no split DNS route
+fn default_addresses_from_options(options: &DnsOptions) -> DnsConfig {
     match options.state {
         DnsState::Default => {
             // Check if we should use a custom blocking DNS resolver.
@@ -48,6 +54,10 @@ pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
                 DnsConfig::default()
             }
         }
+        #[cfg(not(target_os = "android"))]
+        DnsState::Custom if !options.custom_options.encrypted_upstreams.is_empty() => {
+            DnsConfig::from_encrypted_upstreams(&options.custom_options.encrypted_upstreams)
+        }
         DnsState::Custom if options.custom_options.addresses.is_empty() => DnsConfig::default(),
         DnsState::Custom => {
             let (non_tunnel_config, tunnel_config): (Vec<_>, Vec<_>) = options
@@ -61,11 +71,31 @@ pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
     }
 }
 
+/// Return the split DNS routes to use. Resolvers that are not on the local network are ignored,
+/// since queries sent to them outside the tunnel would leak.
+fn split_dns_from_options(options: &DnsOptions) -> SplitDnsRoutes {
+    options
+        .split_dns
+        .iter()
+        .filter_map(|(domain, resolvers)| {
+            let (resolvers, ignored): (Vec<_>, Vec<_>) =
+                resolvers.iter().partition(|&addr| is_local_address(addr));
+            if !ignored.is_empty() {
+                log::warn!("Ignoring non-local split DNS resolvers for {domain}: {ignored:?}");
+            }
+            (!resolvers.is_empty()).then(|| (domain.clone(), resolvers))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use crate::dns::addresses_from_options;
-    use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
+    use mullvad_types::settings::{
+        CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState, LocalBlocklistOptions,
+    };
     use talpid_core::dns::DnsConfig;
+    use talpid_types::net::dns::SplitDnsRoutes;
 
     #[test]
     fn test_default_dns() {
@@ -73,6 +103,8 @@ mod test {
             state: DnsState::Default,
             custom_options: CustomDnsOptions::default(),
             default_options: DefaultDnsOptions::default(),
+            split_dns: SplitDnsRoutes::new(),
+            local_blocklists: LocalBlocklistOptions::default(),
         };
 
         assert_eq!(addresses_from_options(&public_cfg), DnsConfig::default());
@@ -87,6 +119,8 @@ mod test {
                 block_ads: true,
                 ..DefaultDnsOptions::default()
             },
+            split_dns: SplitDnsRoutes::new(),
+            local_blocklists: LocalBlocklistOptions::default(),
         };
 
         assert_eq!(
@@ -104,8 +138,11 @@ mod test {
             state: DnsState::Custom,
             custom_options: CustomDnsOptions {
                 addresses: vec![public_ip, private_ip],
+                encrypted_upstreams: vec![],
             },
             default_options: DefaultDnsOptions::default(),
+            split_dns: SplitDnsRoutes::new(),
+            local_blocklists: LocalBlocklistOptions::default(),
         };
 
         assert_eq!(
@@ -113,4 +150,51 @@ mod test {
             DnsConfig::from_addresses(&[public_ip], &[private_ip],)
         );
     }
+
+    // Encrypted resolvers should replace plain ones
+    #[cfg(not(target_os = "android"))]
+    #[test]
+    fn test_encrypted_dns() {
+        use talpid_types::net::dns::{EncryptedDnsProtocol, EncryptedDnsUpstream};
+
+        let upstream =
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Https, "192.0.2.1#dns.test").unwrap();
+        let cfg = DnsOptions {
+            state: DnsState::Custom,
+            custom_options: CustomDnsOptions {
+                addresses: vec!["1.2.3.4".parse().unwrap()],
+                encrypted_upstreams: vec![upstream.clone()],
+            },
+            default_options: DefaultDnsOptions::default(),
+            split_dns: SplitDnsRoutes::new(),
+            local_blocklists: LocalBlocklistOptions::default(),
+        };
+
+        assert_eq!(
+            addresses_from_options(&cfg),
+            DnsConfig::from_encrypted_upstreams(&[upstream])
+        );
+    }
+
+    // Split DNS routes should only use local resolvers
+    #[test]
+    fn test_split_dns() {
+        let local_ip = "192.168.1.53".parse().unwrap();
+        let public_ip = "1.2.3.4".parse().unwrap();
+        let cfg = DnsOptions {
+            split_dns: SplitDnsRoutes::from([
+                ("corp.example".to_owned(), vec![local_ip, public_ip]),
+                ("public.example".to_owned(), vec![public_ip]),
+            ]),
+            ..DnsOptions::default()
+        };
+
+        assert_eq!(
+            addresses_from_options(&cfg),
+            DnsConfig::default().with_split_dns(SplitDnsRoutes::from([(
+                "corp.example".to_owned(),
+                vec![local_ip]
+            )]))
+        );
+    }
 }
@@ -0,0 +1,324 @@
+//! Loads the local DNS blocklists configured in the settings, and keeps them up to date.
+//!
+//! Lists are read from root-owned files, or downloaded over HTTPS through the tunnel. The compiled
+//! [DomainBlocklist] is passed on to the tunnel state machine, which makes the local DNS
+//! forwarder answer queries for blocked domains with NXDOMAIN.
+
+use std::{io, path::Path, sync::Arc, time::Duration};
+
+use mullvad_api::{rest, web_client};
+use mullvad_types::{
+    dns_blocklist::{BlocklistSourceStats, DnsBlocklistStats},
+    settings::{BlocklistSource, LocalBlocklistOptions},
+};
+use talpid_core::{
+    dns::blocklist::{self, DomainBlocklist},
+    mpsc::Sender,
+};
+use talpid_types::ErrorExt;
+use tokio::task::JoinHandle;
+
+use crate::DaemonEventSender;
+
+/// Largest list that is loaded, in bytes.
+const MAX_LIST_SIZE: usize = 64 * 1024 * 1024;
+/// How long to wait for a list to be downloaded.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often the lists are reloaded.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How soon to reload the lists if any of them could not be loaded.
+const RETRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(thiserror::Error, Debug)]
+enum LoadError {
+    #[error("Failed to read file")]
+    Read(#[source] io::Error),
+
+    #[cfg(unix)]
+    #[error("The file is not owned by root, or may be replaced by other users")]
+    NotRootOwned,
+
+    #[cfg(not(unix))]
+    #[error("Files are not supported as blocklists on this platform")]
+    FileUnsupported,
+
+    #[error("Waiting for the tunnel to connect")]
+    NotConnected,
+
+    #[error("Failed to download list")]
+    Download(#[source] rest::Error),
+
+    #[error("The list is larger than {MAX_LIST_SIZE} bytes")]
+    TooLarge,
+
+    #[error("The list is not valid UTF-8")]
+    InvalidEncoding,
+}
+
+pub(crate) enum Event {
+    /// The lists have been loaded.
+    Loaded(LoadedBlocklist),
+    /// It is time to reload the lists.
+    RefreshDue { generation: u64 },
+}
+
+pub(crate) struct LoadedBlocklist {
+    generation: u64,
+    /// Whether the lists were reloaded without the settings having changed
+    refresh: bool,
+    sources: Vec<BlocklistSource>,
+    blocklist: Arc<DomainBlocklist>,
+    /// Why each list could not be loaded, if it could not be
+    errors: Vec<Option<String>>,
+}
+
+/// Keeps track of the local DNS blocklists.
+pub(crate) struct DnsBlocklistHandler {
+    options: LocalBlocklistOptions,
+    loaded: Option<LoadedBlocklist>,
+    /// Incremented whenever the lists are reloaded, to ignore results from stale tasks
+    generation: u64,
+    task: Option<JoinHandle<()>>,
+    event_tx: DaemonEventSender<Event>,
+}
+
+impl DnsBlocklistHandler {
+    pub fn new(event_tx: DaemonEventSender<Event>) -> Self {
+        DnsBlocklistHandler {
+            options: LocalBlocklistOptions::default(),
+            loaded: None,
+            generation: 0,
+            task: None,
+            event_tx,
+        }
+    }
+
+    /// The options that the current lists were loaded for.
+    pub fn options(&self) -> &LocalBlocklistOptions {
+        &self.options
+    }
+
+    /// The blocklist to enforce, if any.
+    pub fn blocklist(&self) -> Option<Arc<DomainBlocklist>> {
+        self.loaded.as_ref().map(|loaded| loaded.blocklist.clone())
+    }
+
+    /// Whether `generation` belongs to the most recent load.
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation == generation
+    }
+
+    /// Whether some of the lists have not been loaded successfully yet.
+    pub fn needs_retry(&self) -> bool {
+        if self.options.sources.is_empty() {
+            return false;
+        }
+        self.loaded
+            .as_ref()
+            .is_none_or(|loaded| loaded.errors.iter().any(Option::is_some))
+    }
+
+    /// Load the lists in `options`. The current blocklist is kept until the new lists have been
+    /// loaded, unless `options` contains no lists. Returns whether the blocklist was removed.
+    ///
+    /// URLs are only downloaded if `connected` is true, so that they are fetched through the
+    /// tunnel. Otherwise, they are retried once [`Self::refresh`] is called while connected.
+    pub fn set_options(&mut self, options: LocalBlocklistOptions, connected: bool) -> bool {
+        self.options = options;
+        if self.options.sources.is_empty() {
+            self.stop();
+            return self.loaded.take().is_some();
+        }
+        self.start_loading(connected, false);
+        false
+    }
+
+    /// Reload the current lists. If any list cannot be loaded, the previous blocklist is kept.
+    pub fn refresh(&mut self, connected: bool) {
+        self.start_loading(connected, true);
+    }
+
+    /// Handle lists that finished loading. Returns whether the blocklist changed.
+    pub fn on_loaded(&mut self, loaded: LoadedBlocklist) -> bool {
+        if !self.is_current(loaded.generation) {
+            return false;
+        }
+
+        let failed = loaded.errors.iter().any(Option::is_some);
+        let interval = if failed {
+            RETRY_INTERVAL
+        } else {
+            REFRESH_INTERVAL
+        };
+        let generation = self.generation;
+        let event_tx = self.event_tx.clone();
+        self.task = Some(tokio::spawn(async move {
+            tokio::time::sleep(interval).await;
+            let _ = event_tx.send(Event::RefreshDue { generation });
+        }));
+
+        match &mut self.loaded {
+            Some(current) if loaded.refresh && failed => {
+                log::warn!("Keeping previous DNS blocklists since some could not be reloaded");
+                current.errors = loaded.errors;
+                false
+            }
+            _ => {
+                log::info!("Loaded DNS blocklists: {:?}", loaded.blocklist);
+                self.loaded = Some(loaded);
+                true
+            }
+        }
+    }
+
+    /// Return the status and counters of each list.
+    pub fn stats(&self) -> DnsBlocklistStats {
+        let Some(loaded) = &self.loaded else {
+            return DnsBlocklistStats {
+                allowlisted_queries: 0,
+                sources: self
+                    .options
+                    .sources
+                    .iter()
+                    .map(|source| BlocklistSourceStats {
+                        source: source.clone(),
+                        domains: 0,
+                        blocked_queries: 0,
+                        error: None,
+                    })
+                    .collect(),
+            };
+        };
+
+        let stats = loaded.blocklist.stats();
+        DnsBlocklistStats {
+            allowlisted_queries: stats.allowlisted_queries,
+            sources: loaded
+                .sources
+                .iter()
+                .zip(stats.lists)
+                .zip(&loaded.errors)
+                .map(|((source, list), error)| BlocklistSourceStats {
+                    source: source.clone(),
+                    domains: list.domains,
+                    blocked_queries: list.blocked_queries,
+                    error: error.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn start_loading(&mut self, connected: bool, refresh: bool) {
+        self.stop();
+        self.generation = self.generation.wrapping_add(1);
+
+        let generation = self.generation;
+        let options = self.options.clone();
+        let event_tx = self.event_tx.clone();
+        self.task = Some(tokio::spawn(async move {
+            let mut contents = Vec::with_capacity(options.sources.len());
+            let mut errors = Vec::with_capacity(options.sources.len());
+            for source in &options.sources {
+                match load_source(source, connected).await {
+                    Ok(list) => {
+                        contents.push(list);
+                        errors.push(None);
+                    }
+                    Err(error) => {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg(&format!(
+                                "Failed to load DNS blocklist {source}"
+                            ))
+                        );
+                        contents.push(String::new());
+                        errors.push(Some(error.display_chain()));
+                    }
+                }
+            }
+
+            // Parsing and compiling large lists takes a while
+            let allowlist = options.allowlist;
+            let compile = move || {
+                let lists: Vec<_> = contents.iter().map(|list| blocklist::parse(list)).collect();
+                DomainBlocklist::new(&lists, &allowlist)
+            };
+            let Ok(blocklist) = tokio::task::spawn_blocking(compile).await else {
+                return;
+            };
+
+            let _ = event_tx.send(Event::Loaded(LoadedBlocklist {
+                generation,
+                refresh,
+                sources: options.sources,
+                blocklist: Arc::new(blocklist),
+                errors,
+            }));
+        }));
+    }
+
+    fn stop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for DnsBlocklistHandler {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+async fn load_source(source: &BlocklistSource, connected: bool) -> Result<String, LoadError> {
+    let contents = match source {
+        BlocklistSource::File(path) => read_file_source(path).await?,
+        BlocklistSource::Url(_) if !connected => return Err(LoadError::NotConnected),
+        BlocklistSource::Url(url) => {
+            web_client::get(url, "text/plain, */*", DOWNLOAD_TIMEOUT, MAX_LIST_SIZE)
+                .await
+                .map_err(|error| match error {
+                    rest::Error::BodyTooLarge(_) => LoadError::TooLarge,
+                    error => LoadError::Download(error),
+                })?
+        }
+    };
+    String::from_utf8(contents).map_err(|_| LoadError::InvalidEncoding)
+}
+
+/// Read a list from a file. The daemon runs as root and settings may be changed by other users,
+/// so only regular files put in place by root are read: the file and all of its parent directories
+/// must be owned by root and must not be writable by anyone else.
+#[cfg(unix)]
+async fn read_file_source(path: &Path) -> Result<Vec<u8>, LoadError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .map_err(LoadError::Read)?;
+    let metadata = tokio::fs::metadata(&path).await.map_err(LoadError::Read)?;
+    if !metadata.is_file() {
+        return Err(LoadError::Read(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a regular file",
+        )));
+    }
+    if metadata.len() > MAX_LIST_SIZE as u64 {
+        return Err(LoadError::TooLarge);
+    }
+    for ancestor in path.ancestors() {
+        let metadata = tokio::fs::metadata(ancestor)
+            .await
+            .map_err(LoadError::Read)?;
+        if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+            return Err(LoadError::NotRootOwned);
+        }
+    }
+
+    tokio::fs::read(&path).await.map_err(LoadError::Read)
+}
+
+#[cfg(not(unix))]
+async fn read_file_source(_path: &Path) -> Result<Vec<u8>, LoadError> {
+    Err(LoadError::FileUnsupported)
+}
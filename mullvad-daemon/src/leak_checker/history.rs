@@ -0,0 +1,122 @@
+//! Persisted history of recently detected leaks.
+
+use mullvad_types::leak::LeakReport;
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+use talpid_types::ErrorExt;
+use tokio::{fs, io};
+
+const LEAK_HISTORY_FILE: &str = "leak-history.json";
+/// Maximum number of leaks to remember. The oldest leak is forgotten first.
+const MAX_HISTORY_LEN: usize = 50;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize leak history")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Failed to write leak history file")]
+    Write(#[source] io::Error),
+}
+
+/// The most recently detected leaks, oldest first.
+pub struct LeakHistory {
+    path: PathBuf,
+    reports: VecDeque<LeakReport>,
+}
+
+impl LeakHistory {
+    /// Load the history from `cache_dir`. If it cannot be read, the history starts out empty.
+    pub async fn load(cache_dir: &Path) -> Self {
+        let path = cache_dir.join(LEAK_HISTORY_FILE);
+        let reports = match fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse leak history")
+                );
+                VecDeque::new()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read leak history")
+                );
+                VecDeque::new()
+            }
+        };
+        LeakHistory { path, reports }
+    }
+
+    pub fn reports(&self) -> Vec<LeakReport> {
+        self.reports.iter().cloned().collect()
+    }
+
+    /// Add a leak to the history, forgetting the oldest leak if the history is full.
+    pub async fn push(&mut self, report: LeakReport) -> Result<(), Error> {
+        Self::push_inner(&mut self.reports, report);
+        self.save().await
+    }
+
+    fn push_inner(reports: &mut VecDeque<LeakReport>, report: LeakReport) {
+        if reports.len() >= MAX_HISTORY_LEN {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+    }
+
+    async fn save(&self) -> Result<(), Error> {
+        let contents = serde_json::to_string_pretty(&self.reports).map_err(Error::Serialize)?;
+        let mut file = mullvad_fs::AtomicFile::new(&self.path)
+            .await
+            .map_err(Error::Write)?;
+        io::AsyncWriteExt::write_all(&mut file, contents.as_bytes())
+            .await
+            .map_err(Error::Write)?;
+        file.finalize().await.map_err(Error::Write)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::leak::LeakInfo;
+    use talpid_types::net::{Endpoint, TransportProtocol, TunnelEndpoint, TunnelType};
+
+    fn report(ip: [u8; 4]) -> LeakReport {
+        LeakReport {
+            detected_at: chrono::Utc::now(),
+            tunnel_endpoint: TunnelEndpoint {
+                endpoint: Endpoint::new([10, 0, 0, 1], 51820, TransportProtocol::Udp),
+                tunnel_type: TunnelType::Wireguard,
+                quantum_resistant: false,
+                proxy: None,
+                obfuscation: None,
+                entry_endpoint: None,
+                tunnel_interface: None,
+                #[cfg(daita)]
+                daita: false,
+            },
+            info: LeakInfo::AmIMullvad { ip: ip.into() },
+        }
+    }
+
+    /// The oldest leak should be forgotten when the history is full
+    #[test]
+    fn test_ring_buffer() {
+        let mut reports = VecDeque::new();
+        for i in 0..=MAX_HISTORY_LEN {
+            LeakHistory::push_inner(&mut reports, report([192, 168, 0, i as u8]));
+        }
+        assert_eq!(reports.len(), MAX_HISTORY_LEN);
+        assert_eq!(
+            reports.front().unwrap().info,
+            LeakInfo::AmIMullvad {
+                ip: [192, 168, 0, 1].into()
+            }
+        );
+    }
+}
@@ -1,13 +1,28 @@
-use futures::{select, FutureExt};
-pub use mullvad_leak_checker::LeakInfo;
-use std::time::Duration;
+use futures::{select, Future, FutureExt};
+use mullvad_leak_checker::{LeakInfo, LeakStatus};
+use mullvad_types::leak::{self, LeakReport, LeakTestReport, LeakTestResult};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
 use talpid_routing::RouteManagerHandle;
-use talpid_types::{net::Endpoint, tunnel::TunnelStateTransition};
+use talpid_types::{
+    net::{Endpoint, TunnelEndpoint},
+    tunnel::TunnelStateTransition,
+};
 use tokio::sync::mpsc;
 
+mod history;
+
+pub use history::LeakHistory;
+
+/// How long to wait for answers from DNS resolvers during the DNS leak test.
+const DNS_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// An actor that tries to leak traffic outside the tunnel while we are connected.
 pub struct LeakChecker {
     task_event_tx: mpsc::UnboundedSender<TaskEvent>,
+    route_manager: RouteManagerHandle,
 }
 
 /// [LeakChecker] internal task state.
@@ -32,7 +47,7 @@ pub enum CallbackResult {
 }
 
 pub trait LeakCheckerCallback: Send + 'static {
-    fn on_leak(&mut self, info: LeakInfo) -> CallbackResult;
+    fn on_leak(&mut self, report: LeakReport) -> CallbackResult;
 }
 
 impl LeakChecker {
@@ -41,13 +56,16 @@ impl LeakChecker {
 
         let task = Task {
             events_rx,
-            route_manager,
+            route_manager: route_manager.clone(),
             callbacks: vec![],
         };
 
         tokio::task::spawn(task.run());
 
-        LeakChecker { task_event_tx }
+        LeakChecker {
+            task_event_tx,
+            route_manager,
+        }
     }
 
     /// Call when we transition to a new tunnel state.
@@ -60,6 +78,17 @@ impl LeakChecker {
         self.send(TaskEvent::AddCallback(Box::new(callback)))
     }
 
+    /// Run the traceroute, am.i.mullvad.net and DNS leak tests against `tunnel`, independently of
+    /// the automatic leak test. Besides well-known public resolvers, the DNS test also queries
+    /// `non_tunnel_dns`, which must not include resolvers that the firewall allows.
+    pub fn run_leak_test(
+        &self,
+        tunnel: TunnelEndpoint,
+        non_tunnel_dns: Vec<IpAddr>,
+    ) -> impl Future<Output = LeakTestReport> + Send + 'static {
+        run_leak_test(self.route_manager.clone(), tunnel, non_tunnel_dns)
+    }
+
     /// Send a [TaskEvent] to the running [Task];
     fn send(&mut self, event: TaskEvent) {
         if self.task_event_tx.send(event).is_err() {
@@ -93,6 +122,7 @@ impl Task {
             };
 
             let ping_destination = tunnel.endpoint;
+            let tunnel_endpoint = tunnel.clone();
             let route_manager = self.route_manager.clone();
             let leak_test = async {
                 // Give the connection a little time to settle before starting the test.
@@ -144,14 +174,49 @@ impl Task {
 
             log::debug!("Leak detected: {leak_info:?}");
 
+            let report = LeakReport {
+                detected_at: chrono::Utc::now(),
+                tunnel_endpoint,
+                info: leak_info_to_report(leak_info),
+            };
             self.callbacks
-                .retain_mut(|callback| callback.on_leak(leak_info.clone()) == CallbackResult::Ok);
+                .retain_mut(|callback| callback.on_leak(report.clone()) == CallbackResult::Ok);
 
             break 'leak_test;
         }
     }
 }
 
+/// Convert [`LeakInfo`] into a serializable [`leak::LeakInfo`].
+fn leak_info_to_report(info: LeakInfo) -> leak::LeakInfo {
+    match info {
+        LeakInfo::NodeReachableOnInterface {
+            reachable_nodes,
+            interface,
+        } => leak::LeakInfo::NodeReachableOnInterface {
+            interface: interface_name(&interface),
+            reachable_nodes,
+        },
+        LeakInfo::AmIMullvad { ip } => leak::LeakInfo::AmIMullvad { ip },
+        LeakInfo::DnsReachable {
+            reachable_resolvers,
+            interface,
+        } => leak::LeakInfo::DnsReachable {
+            interface: interface.as_ref().map(interface_name).unwrap_or_default(),
+            resolvers: reachable_resolvers
+                .iter()
+                .map(|resolver| resolver.ip())
+                .collect(),
+        },
+    }
+}
+
+fn interface_name(interface: &mullvad_leak_checker::Interface) -> String {
+    interface
+        .name()
+        .unwrap_or_else(|_| format!("{interface:?}"))
+}
+
 #[cfg(target_os = "android")]
 #[allow(clippy::unused_async)]
 async fn check_for_leaks(
@@ -167,8 +232,138 @@ async fn check_for_leaks(
     route_manager: &RouteManagerHandle,
     destination: Endpoint,
 ) -> anyhow::Result<Option<LeakInfo>> {
-    use anyhow::{anyhow, Context};
-    use mullvad_leak_checker::{traceroute::TracerouteOpt, LeakStatus};
+    let interface = physical_interface(route_manager, destination).await?;
+    traceroute(interface, destination).await
+}
+
+/// Run all leak tests against `tunnel`.
+async fn run_leak_test(
+    route_manager: RouteManagerHandle,
+    tunnel: TunnelEndpoint,
+    non_tunnel_dns: Vec<IpAddr>,
+) -> LeakTestReport {
+    let destination = tunnel.endpoint;
+    let interface = physical_interface(&route_manager, destination)
+        .await
+        .map_err(|error| format!("{error:#}"));
+
+    let traceroute_test = async {
+        let interface = interface.clone().map_err(anyhow::Error::msg)?;
+        let leak = traceroute(interface, destination).await?;
+        anyhow::Ok(leak.map(leak_info_to_report))
+    };
+    let am_i_mullvad_test = async {
+        let leak = am_i_mullvad().await?;
+        anyhow::Ok(leak.map(leak_info_to_report))
+    };
+    let dns_test = async {
+        let interface = interface.clone().map_err(anyhow::Error::msg)?;
+        let leak = dns(interface, non_tunnel_dns).await?;
+        anyhow::Ok(leak.map(leak_info_to_report))
+    };
+    let (traceroute, am_i_mullvad, dns) =
+        futures::join!(traceroute_test, am_i_mullvad_test, dns_test);
+
+    LeakTestReport {
+        tested_at: chrono::Utc::now(),
+        tunnel_endpoint: tunnel,
+        traceroute: to_test_result(traceroute),
+        am_i_mullvad: to_test_result(am_i_mullvad),
+        dns: to_test_result(dns),
+    }
+}
+
+fn to_test_result(result: anyhow::Result<Option<leak::LeakInfo>>) -> LeakTestResult {
+    match result {
+        Ok(None) => LeakTestResult::NoLeak,
+        Ok(Some(info)) => LeakTestResult::Leak(info),
+        Err(error) => LeakTestResult::Error(format!("{error:#}")),
+    }
+}
+
+fn leak_from_status(status: LeakStatus) -> Option<LeakInfo> {
+    match status {
+        LeakStatus::NoLeak => None,
+        LeakStatus::LeakDetected(info) => Some(info),
+    }
+}
+
+/// Ask <https://am.i.mullvad.net> whether traffic comes from a Mullvad exit IP.
+async fn am_i_mullvad() -> anyhow::Result<Option<LeakInfo>> {
+    use mullvad_leak_checker::am_i_mullvad::{try_run_leak_test, AmIMullvadOpt};
+
+    try_run_leak_test(&AmIMullvadOpt { interface: None })
+        .await
+        .map(leak_from_status)
+}
+
+/// Send DNS queries on `interface` to well-known public resolvers and to `non_tunnel_dns`.
+async fn dns(
+    interface: mullvad_leak_checker::Interface,
+    non_tunnel_dns: Vec<IpAddr>,
+) -> anyhow::Result<Option<LeakInfo>> {
+    use mullvad_leak_checker::dns::{try_run_leak_test, DnsOpt, DNS_PORT, WELL_KNOWN_RESOLVERS};
+
+    let resolvers = WELL_KNOWN_RESOLVERS
+        .into_iter()
+        .chain(non_tunnel_dns)
+        .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        .collect();
+
+    try_run_leak_test(&DnsOpt {
+        interface: Some(interface),
+        resolvers,
+        timeout_ms: DNS_TIMEOUT.as_millis() as u64,
+    })
+    .await
+    .map(leak_from_status)
+}
+
+/// Try to reach other network nodes on `interface`, using low TTL packets sent towards
+/// `destination`.
+async fn traceroute(
+    interface: mullvad_leak_checker::Interface,
+    destination: Endpoint,
+) -> anyhow::Result<Option<LeakInfo>> {
+    use anyhow::anyhow;
+    use mullvad_leak_checker::traceroute::TracerouteOpt;
+
+    log::debug!("Attempting to leak traffic on interface {interface:?} to {destination}");
+
+    mullvad_leak_checker::traceroute::try_run_leak_test(&TracerouteOpt {
+        interface,
+        destination: destination.address.ip(),
+
+        #[cfg(unix)]
+        port: None,
+        #[cfg(unix)]
+        exclude_port: None,
+        #[cfg(unix)]
+        icmp: true,
+    })
+    .await
+    .map_err(|e| anyhow!("{e:#}"))
+    .map(leak_from_status)
+}
+
+#[cfg(target_os = "android")]
+#[allow(clippy::unused_async)]
+async fn physical_interface(
+    _route_manager: &RouteManagerHandle,
+    _destination: Endpoint,
+) -> anyhow::Result<mullvad_leak_checker::Interface> {
+    Err(anyhow::anyhow!(
+        "Cannot determine the non-tunnel interface on Android"
+    ))
+}
+
+/// Returns the non-tunnel interface that traffic to `destination` would be routed through.
+#[cfg(not(target_os = "android"))]
+async fn physical_interface(
+    route_manager: &RouteManagerHandle,
+    destination: Endpoint,
+) -> anyhow::Result<mullvad_leak_checker::Interface> {
+    use anyhow::Context;
 
     #[cfg(target_os = "linux")]
     let interface = {
@@ -177,7 +372,7 @@ async fn check_for_leaks(
             .get_destination_route(destination.address.ip(), Some(mullvad_types::TUNNEL_FWMARK))
             .await
             .context("Failed to get route to relay")?
-            .ok_or(anyhow!("No route to relay"))?;
+            .ok_or(anyhow::anyhow!("No route to relay"))?;
 
         route
             .get_node()
@@ -220,38 +415,20 @@ async fn check_for_leaks(
 
         let route = talpid_routing::get_best_default_route(family)
             .context("Failed to get best default route")?
-            .ok_or_else(|| anyhow!("No default route found"))?;
+            .ok_or_else(|| anyhow::anyhow!("No default route found"))?;
 
         mullvad_leak_checker::Interface::Luid(route.iface)
     };
 
-    log::debug!("Attempting to leak traffic on interface {interface:?} to {destination}");
-
-    mullvad_leak_checker::traceroute::try_run_leak_test(&TracerouteOpt {
-        interface,
-        destination: destination.address.ip(),
-
-        #[cfg(unix)]
-        port: None,
-        #[cfg(unix)]
-        exclude_port: None,
-        #[cfg(unix)]
-        icmp: true,
-    })
-    .await
-    .map_err(|e| anyhow!("{e:#}"))
-    .map(|status| match status {
-        LeakStatus::NoLeak => None,
-        LeakStatus::LeakDetected(info) => Some(info),
-    })
+    Ok(interface)
 }
 
 impl<T> LeakCheckerCallback for T
 where
-    T: FnMut(LeakInfo) -> bool + Send + 'static,
+    T: FnMut(LeakReport) -> bool + Send + 'static,
 {
-    fn on_leak(&mut self, info: LeakInfo) -> CallbackResult {
-        if self(info) {
+    fn on_leak(&mut self, report: LeakReport) -> CallbackResult {
+        if self(report) {
             CallbackResult::Ok
         } else {
             CallbackResult::Drop
@@ -0,0 +1,141 @@
+//! Persisted table of the obfuscation that most recently worked on each network.
+
+use mullvad_types::learned_obfuscation::{LearnedObfuscation, NetworkId, WorkingObfuscation};
+use std::path::{Path, PathBuf};
+use talpid_types::ErrorExt;
+use tokio::{fs, io};
+
+const LEARNED_OBFUSCATION_FILE: &str = "learned-obfuscation.json";
+/// Maximum number of networks to remember. The least recently used network is forgotten first.
+const MAX_NETWORKS: usize = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize learned obfuscation")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Failed to write learned obfuscation file")]
+    Write(#[source] io::Error),
+}
+
+/// The obfuscation that most recently worked on each network, least recently used first.
+pub struct LearnedObfuscationTable {
+    path: PathBuf,
+    entries: Vec<LearnedObfuscation>,
+}
+
+impl LearnedObfuscationTable {
+    /// Load the table from `cache_dir`. If it cannot be read, the table starts out empty.
+    pub async fn load(cache_dir: &Path) -> Self {
+        let path = cache_dir.join(LEARNED_OBFUSCATION_FILE);
+        let entries = match fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse learned obfuscation")
+                );
+                Vec::new()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read learned obfuscation")
+                );
+                Vec::new()
+            }
+        };
+        LearnedObfuscationTable { path, entries }
+    }
+
+    pub fn entries(&self) -> Vec<LearnedObfuscation> {
+        self.entries.clone()
+    }
+
+    /// Return the obfuscation that most recently worked on `network`.
+    pub fn get(&self, network: &NetworkId) -> Option<WorkingObfuscation> {
+        self.entries
+            .iter()
+            .find(|entry| entry.network == *network)
+            .map(|entry| entry.obfuscation)
+    }
+
+    /// Remember that `obfuscation` worked on `network`, replacing any earlier entry for it. The
+    /// least recently used network is forgotten if the table is full.
+    pub async fn insert(
+        &mut self,
+        network: NetworkId,
+        obfuscation: WorkingObfuscation,
+    ) -> Result<(), Error> {
+        Self::insert_inner(
+            &mut self.entries,
+            LearnedObfuscation {
+                network,
+                obfuscation,
+                last_used: chrono::Utc::now(),
+            },
+        );
+        self.save().await
+    }
+
+    fn insert_inner(entries: &mut Vec<LearnedObfuscation>, entry: LearnedObfuscation) {
+        entries.retain(|existing| existing.network != entry.network);
+        if entries.len() >= MAX_NETWORKS {
+            entries.remove(0);
+        }
+        entries.push(entry);
+    }
+
+    /// Forget all networks.
+    pub async fn clear(&mut self) -> Result<(), Error> {
+        self.entries.clear();
+        self.save().await
+    }
+
+    async fn save(&self) -> Result<(), Error> {
+        let contents = serde_json::to_string_pretty(&self.entries).map_err(Error::Serialize)?;
+        let mut file = mullvad_fs::AtomicFile::new(&self.path)
+            .await
+            .map_err(Error::Write)?;
+        io::AsyncWriteExt::write_all(&mut file, contents.as_bytes())
+            .await
+            .map_err(Error::Write)?;
+        file.finalize().await.map_err(Error::Write)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::{IpVersion, ObfuscationType};
+
+    fn entry(ssid: &str, port: u16) -> LearnedObfuscation {
+        LearnedObfuscation {
+            network: NetworkId::Ssid(ssid.to_owned()),
+            obfuscation: WorkingObfuscation {
+                obfuscation: Some(ObfuscationType::Udp2Tcp),
+                port,
+                ip_version: IpVersion::V4,
+            },
+            last_used: chrono::Utc::now(),
+        }
+    }
+
+    /// A network should only have a single entry, and the least recently used network should be
+    /// forgotten when the table is full
+    #[test]
+    fn test_insert() {
+        let mut entries = Vec::new();
+        LearnedObfuscationTable::insert_inner(&mut entries, entry("first", 80));
+        for i in 1..MAX_NETWORKS {
+            LearnedObfuscationTable::insert_inner(&mut entries, entry(&i.to_string(), 80));
+        }
+        LearnedObfuscationTable::insert_inner(&mut entries, entry("first", 443));
+        assert_eq!(entries.len(), MAX_NETWORKS);
+        assert_eq!(entries.last().unwrap().obfuscation.port, 443);
+
+        LearnedObfuscationTable::insert_inner(&mut entries, entry("last", 80));
+        assert_eq!(entries.len(), MAX_NETWORKS);
+        assert_eq!(entries[0].network, NetworkId::Ssid("2".to_owned()));
+    }
+}
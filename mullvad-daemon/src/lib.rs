@@ -6,19 +6,27 @@ pub mod account_history;
 mod android_dns;
 mod api;
 mod api_address_updater;
+mod auto_connect_rules;
 #[cfg(not(target_os = "android"))]
 mod cleanup;
+mod custom_endpoint;
 mod custom_list;
 pub mod device;
 mod dns;
+#[cfg(not(target_os = "android"))]
+mod dns_blocklist;
 pub mod exception_logging;
 mod geoip;
 mod leak_checker;
+mod learned_obfuscation;
 pub mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
+mod management_access;
 pub mod management_interface;
+mod metrics;
 mod migrations;
+mod relay_latency;
 mod relay_list;
 #[cfg(not(target_os = "android"))]
 pub mod rpc_uniqueness_check;
@@ -39,13 +47,15 @@ use futures::{
     StreamExt,
 };
 use geoip::GeoIpHandler;
-use leak_checker::{LeakChecker, LeakInfo};
+use leak_checker::{LeakChecker, LeakHistory};
+use learned_obfuscation::LearnedObfuscationTable;
 use management_interface::ManagementInterfaceServer;
 use mullvad_api::ApiEndpoint;
-use mullvad_relay_selector::{RelaySelector, SelectorConfig};
+use mullvad_relay_selector::{latency::LatencyRanker, RelaySelector, SelectorConfig};
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayPurchase, PlayPurchasePaymentToken};
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
+#[cfg(not(target_os = "android"))]
+use mullvad_types::dns_blocklist::DnsBlocklistStats;
 use mullvad_types::settings::SplitApp;
 #[cfg(daita)]
 use mullvad_types::wireguard::DaitaSettings;
@@ -53,9 +63,13 @@ use mullvad_types::{
     access_method::{AccessMethod, AccessMethodSetting},
     account::{AccountData, AccountNumber, VoucherSubmission},
     auth_failed::AuthFailed,
+    auto_connect::{AutoConnectAction, AutoConnectRule, AutoConnectRules, TrustedNetwork},
+    custom_endpoint::NamedCustomEndpoint,
     custom_list::CustomList,
     device::{Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceState, RemoveDeviceEvent},
     features::{compute_feature_indicators, FeatureIndicator, FeatureIndicators},
+    leak::{LeakReport, LeakTestReport},
+    learned_obfuscation::{LearnedObfuscation, NetworkId, WorkingObfuscation},
     location::{GeoIpLocation, LocationEventData},
     relay_constraints::{
         BridgeSettings, BridgeState, BridgeType, ObfuscationSettings, RelayOverride, RelaySettings,
@@ -65,10 +79,10 @@ use mullvad_types::{
     states::{Secured, TargetState, TargetStateStrict, TunnelState},
     version::{AppVersion, AppVersionInfo},
     wireguard::{PublicKey, QuantumResistantState, RotationInterval},
+    CustomTunnelEndpoint,
 };
 use relay_list::{RelayListUpdater, RelayListUpdaterHandle, RELAYS_FILENAME};
 use settings::SettingsPersister;
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use std::collections::HashSet;
 #[cfg(target_os = "android")]
 use std::os::unix::io::RawFd;
@@ -80,6 +94,7 @@ use std::{
     time::Duration,
 };
 use talpid_core::{
+    dns::DnsConfig,
     mpsc::Sender,
     split_tunnel,
     tunnel_state_machine::{self, TunnelCommand, TunnelStateMachineHandle},
@@ -87,10 +102,12 @@ use talpid_core::{
 use talpid_routing::RouteManagerHandle;
 #[cfg(target_os = "android")]
 use talpid_types::android::AndroidContext;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::AllowedLanNets;
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 use talpid_types::{
-    net::{IpVersion, TunnelType},
+    net::{wireguard::TunnelStatistics, IpVersion, TunnelEndpoint, TunnelType},
     tunnel::{ErrorStateCause, TunnelStateTransition},
     ErrorExt,
 };
@@ -158,7 +175,6 @@ pub enum Error {
     #[error("Unable to initialize split tunneling")]
     InitSplitTunneling(#[source] split_tunnel::Error),
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     #[error("Split tunneling error")]
     SplitTunnelError(#[source] split_tunnel::Error),
 
@@ -177,6 +193,9 @@ pub enum Error {
     #[error("Account history error")]
     AccountHistory(#[source] account_history::Error),
 
+    #[error("Learned obfuscation error")]
+    LearnedObfuscation(#[source] learned_obfuscation::Error),
+
     #[cfg(not(target_os = "android"))]
     #[error("Factory reset partially failed: {0}")]
     FactoryResetError(&'static str),
@@ -192,6 +211,9 @@ pub enum Error {
     #[error("Custom list error: {0}")]
     CustomListError(#[source] mullvad_types::custom_list::Error),
 
+    #[error("Custom endpoint error: {0}")]
+    CustomEndpointError(#[source] mullvad_types::custom_endpoint::Error),
+
     #[error("Access method error")]
     AccessMethodError(#[source] access_method::Error),
 
@@ -200,6 +222,9 @@ pub enum Error {
     #[error("No custom bridge has been specified")]
     NoCustomProxySaved,
 
+    #[error("Cannot run a leak test unless the tunnel is connected")]
+    LeakTestNotConnected,
+
     #[cfg(target_os = "macos")]
     #[error("Failed to set exclusion group")]
     GroupIdError(#[source] io::Error),
@@ -256,6 +281,9 @@ pub enum DaemonCommand {
     SetRelaySettings(ResponseTx<(), settings::Error>, RelaySettings),
     /// Set the allow LAN setting.
     SetAllowLan(ResponseTx<(), settings::Error>, bool),
+    /// Set the networks that are allowed when LAN access is enabled.
+    #[cfg(not(target_os = "android"))]
+    SetAllowedLanNets(ResponseTx<(), settings::Error>, AllowedLanNets),
     /// Set the beta program setting.
     SetShowBetaReleases(ResponseTx<(), settings::Error>, bool),
     /// Set the block_when_disconnected setting.
@@ -263,6 +291,10 @@ pub enum DaemonCommand {
     SetBlockWhenDisconnected(ResponseTx<(), settings::Error>, bool),
     /// Set the auto-connect setting.
     SetAutoConnect(ResponseTx<(), settings::Error>, bool),
+    /// Replace the auto-connect rules.
+    SetAutoConnectRules(ResponseTx<(), settings::Error>, AutoConnectRules),
+    /// Replace the list of trusted networks.
+    SetTrustedNetworks(ResponseTx<(), settings::Error>, Vec<TrustedNetwork>),
     /// Set the mssfix argument for OpenVPN
     SetOpenVpnMssfix(ResponseTx<(), settings::Error>, Option<u16>),
     /// Set proxy details for OpenVPN
@@ -307,6 +339,18 @@ pub enum DaemonCommand {
     UpdateCustomList(ResponseTx<(), Error>, CustomList),
     /// Remove all custom lists
     ClearCustomLists(ResponseTx<(), Error>),
+    /// Save a custom tunnel endpoint
+    CreateCustomEndpoint(
+        ResponseTx<mullvad_types::custom_endpoint::Id, Error>,
+        String,
+        CustomTunnelEndpoint,
+    ),
+    /// Delete a saved custom tunnel endpoint
+    DeleteCustomEndpoint(ResponseTx<(), Error>, mullvad_types::custom_endpoint::Id),
+    /// Update a saved custom tunnel endpoint with a given id
+    UpdateCustomEndpoint(ResponseTx<(), Error>, NamedCustomEndpoint),
+    /// Remove all saved custom tunnel endpoints
+    ClearCustomEndpoints(ResponseTx<(), Error>),
     /// Add API access methods
     AddApiAccessMethod(
         ResponseTx<mullvad_types::access_method::Id, Error>,
@@ -353,16 +397,12 @@ pub enum DaemonCommand {
     #[cfg(target_os = "linux")]
     ClearSplitTunnelProcesses(ResponseTx<(), split_tunnel::Error>),
     /// Exclude traffic of an application from the tunnel
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     AddSplitTunnelApp(ResponseTx<(), Error>, SplitApp),
     /// Remove application from list of apps to exclude from the tunnel
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     RemoveSplitTunnelApp(ResponseTx<(), Error>, SplitApp),
     /// Clear list of apps to exclude from the tunnel
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     ClearSplitTunnelApps(ResponseTx<(), Error>),
     /// Enable or disable split tunneling
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     SetSplitTunnelState(ResponseTx<(), Error>, bool),
     /// Returns all processes currently being excluded from the tunnel
     #[cfg(windows)]
@@ -391,6 +431,22 @@ pub enum DaemonCommand {
     ExportJsonSettings(ResponseTx<String, settings::patch::Error>),
     /// Request the current feature indicators.
     GetFeatureIndicators(oneshot::Sender<FeatureIndicators>),
+    /// Request the most recently detected leaks.
+    GetLeakHistory(oneshot::Sender<Vec<LeakReport>>),
+    /// Run a leak test against the current tunnel.
+    RunLeakTest(ResponseTx<LeakTestReport, Error>),
+    /// Request the obfuscation that most recently worked on each network.
+    GetLearnedObfuscation(oneshot::Sender<Vec<LearnedObfuscation>>),
+    /// Forget the obfuscation that worked on each network.
+    ClearLearnedObfuscation(ResponseTx<(), Error>),
+    /// Request the status and counters of the local DNS blocklists.
+    #[cfg(not(target_os = "android"))]
+    GetDnsBlocklistStats(oneshot::Sender<DnsBlocklistStats>),
+    /// Request statistics about the current tunnel. `None` is returned unless connected to a
+    /// WireGuard relay.
+    GetTunnelStatistics(oneshot::Sender<Option<TunnelStatistics>>),
+    /// Request a snapshot of the metrics served by the metrics exporter.
+    GetMetrics(oneshot::Sender<metrics::Metrics>),
 }
 
 /// All events that can happen in the daemon. Sent from various threads and exposed interfaces.
@@ -418,13 +474,21 @@ pub(crate) enum InternalDaemonEvent {
     /// A generic event for when any settings change.
     SettingsChanged,
     /// The split tunnel paths or state were updated.
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     ExcludedPathsEvent(ExcludedPathsUpdate, oneshot::Sender<Result<(), Error>>),
     /// A network leak was detected.
-    LeakDetected(LeakInfo),
+    LeakDetected(LeakReport),
+    /// A different auto-connect rule started to match the current network, or none matches
+    /// anymore.
+    AutoConnectRuleMatched(Option<AutoConnectRule>),
+    /// The device moved onto (`true`) or off (`false`) a trusted network.
+    TrustedNetworkChanged(bool),
+    /// The device moved onto a different network.
+    NetworkChanged(Option<NetworkId>),
+    /// The local DNS blocklists were loaded, or should be reloaded.
+    #[cfg(not(target_os = "android"))]
+    DnsBlocklistEvent(dns_blocklist::Event),
 }
 
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
 pub(crate) enum ExcludedPathsUpdate {
     SetState(bool),
     SetPaths(HashSet<SplitApp>),
@@ -454,6 +518,29 @@ impl From<AccountEvent> for InternalDaemonEvent {
     }
 }
 
+impl From<auto_connect_rules::Event> for InternalDaemonEvent {
+    fn from(event: auto_connect_rules::Event) -> Self {
+        match event {
+            auto_connect_rules::Event::RuleMatched(rule) => {
+                InternalDaemonEvent::AutoConnectRuleMatched(rule)
+            }
+            auto_connect_rules::Event::TrustedNetwork(trusted) => {
+                InternalDaemonEvent::TrustedNetworkChanged(trusted)
+            }
+            auto_connect_rules::Event::NetworkChanged(network) => {
+                InternalDaemonEvent::NetworkChanged(network)
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl From<dns_blocklist::Event> for InternalDaemonEvent {
+    fn from(event: dns_blocklist::Event) -> Self {
+        InternalDaemonEvent::DnsBlocklistEvent(event)
+    }
+}
+
 impl From<(AccessMethodEvent, oneshot::Sender<()>)> for InternalDaemonEvent {
     fn from(event: (AccessMethodEvent, oneshot::Sender<()>)) -> Self {
         InternalDaemonEvent::AccessMethodEvent {
@@ -574,6 +661,10 @@ pub struct Daemon {
     target_state: PersistentTargetState,
     #[cfg(target_os = "linux")]
     exclude_pids: split_tunnel::PidManager,
+    /// Excludes split tunnel apps automatically. This is `None` if the process events connector
+    /// is unavailable, in which case only manually excluded processes are excluded.
+    #[cfg(target_os = "linux")]
+    exclude_apps: Option<split_tunnel::AppMonitor>,
     rx: mpsc::UnboundedReceiver<InternalDaemonEvent>,
     tx: DaemonEventSender,
     reconnection_job: Option<AbortHandle>,
@@ -588,6 +679,8 @@ pub struct Daemon {
     api_handle: mullvad_api::rest::MullvadRestHandle,
     version_updater_handle: version_check::VersionUpdaterHandle,
     relay_selector: RelaySelector,
+    /// Measures relay latencies in the background while relays are reachable.
+    latency_measurer: relay_latency::LatencyMeasurer,
     relay_list_updater: RelayListUpdaterHandle,
     parameters_generator: tunnel::ParametersGenerator,
     shutdown_tasks: Vec<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
@@ -596,6 +689,12 @@ pub struct Daemon {
     volume_update_tx: mpsc::UnboundedSender<()>,
     location_handler: GeoIpHandler,
     leak_checker: LeakChecker,
+    leak_history: LeakHistory,
+    learned_obfuscation: LearnedObfuscationTable,
+    auto_connect_rules: auto_connect_rules::AutoConnectRulesHandle,
+    metrics: metrics::Counters,
+    #[cfg(not(target_os = "android"))]
+    dns_blocklist: dns_blocklist::DnsBlocklistHandler,
 }
 pub struct DaemonConfig {
     pub log_dir: Option<PathBuf>,
@@ -617,8 +716,10 @@ impl Daemon {
         macos::bump_filehandle_limit();
 
         let command_sender = daemon_command_channel.sender();
+        metrics::start_exporter(command_sender.clone()).await;
+        let access_policy = management_access::AccessPolicy::load(&config.settings_dir).await;
         let management_interface =
-            ManagementInterfaceServer::start(command_sender, config.rpc_socket_path)
+            ManagementInterfaceServer::start(command_sender, config.rpc_socket_path, access_policy)
                 .map_err(Error::ManagementInterfaceError)?;
 
         let (internal_event_tx, internal_event_rx) = daemon_command_channel.destructure();
@@ -670,13 +771,20 @@ impl Daemon {
             config.resource_dir.join(RELAYS_FILENAME),
             config.cache_dir.join(RELAYS_FILENAME),
         );
+        relay_selector.set_latency_ranker(Arc::new(LatencyRanker::new(
+            relay_latency::TcpHandshakeProber,
+        )));
+        let latency_measurer = relay_latency::LatencyMeasurer::new(relay_selector.clone());
 
         let settings_relay_selector = relay_selector.clone();
+        let settings_latency_measurer = latency_measurer.clone();
         settings.register_change_listener(move |settings| {
             // Notify relay selector of changes to the settings/selector config
             settings_relay_selector
                 .clone()
                 .set_config(SelectorConfig::from_settings(settings));
+            // The relays that may be selected might have changed
+            settings_latency_measurer.restart();
         });
 
         let (access_mode_handler, access_mode_provider) = api::AccessModeSelector::spawn(
@@ -747,8 +855,7 @@ impl Daemon {
             PersistentTargetState::new(&config.cache_dir).await
         };
 
-        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-        let exclude_paths = if settings.split_tunnel.enable_exclusions {
+        let exclude_paths: Vec<_> = if settings.split_tunnel.enable_exclusions {
             settings
                 .split_tunnel
                 .apps
@@ -760,6 +867,30 @@ impl Daemon {
             vec![]
         };
 
+        #[cfg(target_os = "linux")]
+        let exclude_pids = split_tunnel::PidManager::new().map_err(Error::InitSplitTunneling)?;
+        #[cfg(target_os = "linux")]
+        let exclude_apps = match split_tunnel::AppMonitor::spawn(exclude_pids.clone()) {
+            Ok(monitor) => {
+                if let Err(error) = monitor.set_paths(&exclude_paths) {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to exclude split tunnel apps")
+                    );
+                }
+                Some(monitor)
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg(
+                        "Failed to start split tunnel app monitor. Apps will not be excluded"
+                    )
+                );
+                None
+            }
+        };
+
         let parameters_generator = tunnel::ParametersGenerator::new(
             account_manager.clone(),
             relay_selector.clone(),
@@ -795,6 +926,22 @@ impl Daemon {
         .await
         .map_err(Error::RouteManager)?;
 
+        let auto_connect_rules = auto_connect_rules::spawn(
+            settings.auto_connect_rules.clone(),
+            settings.trusted_networks.clone(),
+            settings.obfuscation_settings.learn_per_network,
+            internal_event_tx.to_specialized_sender(),
+            #[cfg(target_os = "macos")]
+            route_manager.clone(),
+        );
+        let auto_connect_rules_listener = auto_connect_rules.clone();
+        settings.register_change_listener(move |settings| {
+            auto_connect_rules_listener.set_rules(settings.auto_connect_rules.clone());
+            auto_connect_rules_listener.set_trusted_networks(settings.trusted_networks.clone());
+            auto_connect_rules_listener
+                .set_identify_networks(settings.obfuscation_settings.learn_per_network);
+        });
+
         let (offline_state_tx, offline_state_rx) = mpsc::unbounded();
         #[cfg(target_os = "windows")]
         let (volume_update_tx, volume_update_rx) = mpsc::unbounded();
@@ -802,6 +949,10 @@ impl Daemon {
             tunnel_state_machine::InitialTunnelState {
                 allow_lan: settings.allow_lan,
                 #[cfg(not(target_os = "android"))]
+                allowed_lan_nets: settings.allowed_lan_nets.clone(),
+                #[cfg(target_os = "android")]
+                allowed_lan_nets: Default::default(),
+                #[cfg(not(target_os = "android"))]
                 block_when_disconnected: settings.block_when_disconnected,
                 dns_config: dns::addresses_from_options(&settings.tunnel_options.dns_options),
                 allowed_endpoint: access_mode_handler
@@ -812,6 +963,8 @@ impl Daemon {
                 reset_firewall: *target_state != TargetState::Secured,
                 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
                 exclude_paths,
+                #[cfg(target_os = "linux")]
+                split_tunnel_cgroup: exclude_pids.version(),
             },
             parameters_generator.clone(),
             config.log_dir,
@@ -834,7 +987,12 @@ impl Daemon {
         .await
         .map_err(Error::TunnelError)?;
 
-        api::forward_offline_state(api_availability.clone(), offline_state_rx);
+        api::forward_offline_state(
+            api_availability.clone(),
+            auto_connect_rules.clone(),
+            latency_measurer.clone(),
+            offline_state_rx,
+        );
 
         let relay_list_listener = management_interface.notifier().clone();
         let on_relay_list_update = move |relay_list: &RelayList| {
@@ -873,13 +1031,19 @@ impl Daemon {
         let leak_checker = {
             let mut leak_checker = LeakChecker::new(route_manager);
             let internal_event_tx = internal_event_tx.clone();
-            leak_checker.add_leak_callback(move |info| {
+            leak_checker.add_leak_callback(move |report| {
                 internal_event_tx
-                    .send(InternalDaemonEvent::LeakDetected(info))
+                    .send(InternalDaemonEvent::LeakDetected(report))
                     .is_ok()
             });
             leak_checker
         };
+        let leak_history = LeakHistory::load(&config.cache_dir).await;
+        let learned_obfuscation = LearnedObfuscationTable::load(&config.cache_dir).await;
+
+        #[cfg(not(target_os = "android"))]
+        let dns_blocklist =
+            dns_blocklist::DnsBlocklistHandler::new(internal_event_tx.to_specialized_sender());
 
         let daemon = Daemon {
             tunnel_state: TunnelState::Disconnected {
@@ -889,7 +1053,9 @@ impl Daemon {
             },
             target_state,
             #[cfg(target_os = "linux")]
-            exclude_pids: split_tunnel::PidManager::new().map_err(Error::InitSplitTunneling)?,
+            exclude_pids,
+            #[cfg(target_os = "linux")]
+            exclude_apps,
             rx: internal_event_rx,
             tx: internal_event_tx,
             reconnection_job: None,
@@ -904,6 +1070,7 @@ impl Daemon {
             api_handle,
             version_updater_handle,
             relay_selector,
+            latency_measurer,
             relay_list_updater,
             parameters_generator,
             shutdown_tasks: vec![],
@@ -912,6 +1079,12 @@ impl Daemon {
             volume_update_tx,
             location_handler,
             leak_checker,
+            leak_history,
+            learned_obfuscation,
+            auto_connect_rules,
+            metrics: metrics::Counters::default(),
+            #[cfg(not(target_os = "android"))]
+            dns_blocklist,
         };
 
         api_availability.unsuspend();
@@ -922,6 +1095,8 @@ impl Daemon {
     /// Consume the `Daemon` and run the main event loop. Blocks until an error happens or a
     /// shutdown event is received.
     pub async fn run(mut self) -> Result<(), Error> {
+        #[cfg(not(target_os = "android"))]
+        self.update_dns_blocklists();
         self.handle_initial_target_state();
         self.handle_events().await;
         self.disconnect_tunnel_and_wait().await;
@@ -938,7 +1113,8 @@ impl Daemon {
                 // Fetching GeoIpLocation is automatically done when connecting.
                 // If TargetState is Unsecured we will not connect on lauch and
                 // so we have to explicitly fetch this information.
-                self.fetch_am_i_mullvad()
+                self.fetch_am_i_mullvad();
+                self.update_latency_measurer();
             }
         }
     }
@@ -1032,17 +1208,190 @@ impl Daemon {
             LocationEvent(location_data) => self.handle_location_event(location_data),
             SettingsChanged => {
                 self.update_feature_indicators_on_settings_changed();
+                #[cfg(not(target_os = "android"))]
+                self.update_dns_blocklists();
             }
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             ExcludedPathsEvent(update, tx) => self.handle_new_excluded_paths(update, tx).await,
-            LeakDetected(leak_info) => {
-                log::warn!("Network leak detected! Please contact Mullvad support.");
-                log::warn!("{leak_info:?}")
-            }
+            LeakDetected(report) => self.handle_leak_detected(report).await,
+            AutoConnectRuleMatched(rule) => self.handle_auto_connect_rule(rule).await,
+            TrustedNetworkChanged(trusted) => self.handle_trusted_network_changed(trusted),
+            NetworkChanged(network) => self.handle_network_changed(network),
+            #[cfg(not(target_os = "android"))]
+            DnsBlocklistEvent(event) => self.handle_dns_blocklist_event(event),
         }
         should_stop
     }
 
+    async fn handle_leak_detected(&mut self, report: LeakReport) {
+        log::warn!("Network leak detected! Please contact Mullvad support.");
+        log::warn!("{report:?}");
+
+        self.metrics.on_leak_detected();
+        self.management_interface
+            .notifier()
+            .notify_leak_detected(report.clone());
+        if let Err(error) = self.leak_history.push(report).await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to save leak history")
+            );
+        }
+    }
+
+    fn handle_network_changed(&mut self, network: Option<NetworkId>) {
+        // Latencies measured on the previous network no longer apply
+        self.latency_measurer.reset();
+
+        if !self.settings.obfuscation_settings.learn_per_network {
+            return;
+        }
+        let learned = network
+            .as_ref()
+            .and_then(|network| self.learned_obfuscation.get(network));
+        if let (Some(network), Some(learned)) = (&network, learned) {
+            log::info!("Trying {learned} first, since it worked on {network} before");
+        }
+        self.relay_selector.set_learned_obfuscation(learned);
+    }
+
+    /// Remember the obfuscation used to reach `endpoint` for the network that the device is on.
+    async fn learn_working_obfuscation(&mut self, endpoint: &TunnelEndpoint) {
+        if !self.settings.obfuscation_settings.learn_per_network {
+            return;
+        }
+        if self.parameters_generator.last_relay_was_custom().await {
+            return;
+        }
+        let Some(working) = WorkingObfuscation::from_endpoint(endpoint) else {
+            return;
+        };
+        // The device may have changed networks since the tunnel started connecting
+        let Some(network) = self.auto_connect_rules.current_network().await else {
+            return;
+        };
+        if self.learned_obfuscation.get(&network) != Some(working) {
+            log::debug!("Learned that {working} works on {network}");
+        }
+        self.relay_selector.set_learned_obfuscation(Some(working));
+        if let Err(error) = self.learned_obfuscation.insert(network, working).await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to save learned obfuscation")
+            );
+        }
+    }
+
+    /// Disconnect while the device is on a trusted network. This overrides the target state
+    /// without persisting it, so that the tunnel is secured again after a restart.
+    fn handle_trusted_network_changed(&mut self, on_trusted_network: bool) {
+        if !on_trusted_network {
+            if self.target_state.clear_override() && *self.target_state == TargetState::Secured {
+                log::info!("Reconnecting since the device left a trusted network");
+                self.connect_tunnel();
+            }
+            return;
+        }
+
+        #[cfg(not(target_os = "android"))]
+        if self.settings.block_when_disconnected {
+            log::debug!("Ignoring trusted network since lockdown mode is enabled");
+            return;
+        }
+        if *self.target_state == TargetState::Secured {
+            log::info!("Disconnecting since the device is on a trusted network");
+            self.target_state.set_override(TargetState::Unsecured);
+            self.disconnect_tunnel();
+        }
+    }
+
+    /// Reload the local DNS blocklists if their settings changed.
+    #[cfg(not(target_os = "android"))]
+    fn update_dns_blocklists(&mut self) {
+        let options = &self.settings.tunnel_options.dns_options.local_blocklists;
+        if options == self.dns_blocklist.options() {
+            return;
+        }
+        let options = options.clone();
+        let connected = self.tunnel_state.is_connected();
+        if self.dns_blocklist.set_options(options, connected) {
+            log::info!("Removed DNS blocklists");
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::Dns(self.dns_config(), tx));
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn handle_dns_blocklist_event(&mut self, event: dns_blocklist::Event) {
+        match event {
+            dns_blocklist::Event::Loaded(loaded) => {
+                if self.dns_blocklist.on_loaded(loaded) {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::Dns(self.dns_config(), tx));
+                }
+            }
+            dns_blocklist::Event::RefreshDue { generation } => {
+                if self.dns_blocklist.is_current(generation) {
+                    self.dns_blocklist.refresh(self.tunnel_state.is_connected());
+                }
+            }
+        }
+    }
+
+    /// Return the DNS config for the tunnel state machine.
+    #[cfg(not(target_os = "android"))]
+    fn dns_config(&self) -> DnsConfig {
+        dns::addresses_from_options(&self.settings.tunnel_options.dns_options)
+            .with_blocklist(self.dns_blocklist.blocklist())
+    }
+
+    /// Return the DNS config for the tunnel state machine.
+    #[cfg(target_os = "android")]
+    fn dns_config(&self) -> DnsConfig {
+        dns::addresses_from_options(&self.settings.tunnel_options.dns_options)
+    }
+
+    async fn handle_auto_connect_rule(&mut self, rule: Option<AutoConnectRule>) {
+        let Some(rule) = rule else {
+            // The relay settings of a rule only apply for as long as it matches
+            if self.relay_selector.set_relay_settings_override(None) {
+                log::info!("Initiating tunnel restart because the relay settings changed");
+                self.reconnect_tunnel();
+            }
+            return;
+        };
+        log::info!(
+            "Applying auto-connect rule \"{}\": {}",
+            rule.name,
+            rule.action
+        );
+        let (new_state, relay_settings) = match rule.action {
+            AutoConnectAction::Connect => (TargetState::Secured, None),
+            AutoConnectAction::Disconnect => (TargetState::Unsecured, None),
+            AutoConnectAction::ConnectUsing(relay_settings) => {
+                if let RelaySettings::SavedCustomEndpoints(ids) = &relay_settings {
+                    if let Err(error) = self.settings.custom_endpoints.validate_selection(ids) {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to apply auto-connect rule")
+                        );
+                        return;
+                    }
+                }
+                (TargetState::Secured, Some(relay_settings))
+            }
+        };
+        // The relay settings are not saved, so that the saved ones are used again once a
+        // different rule matches
+        let relay_settings_changed = self
+            .relay_selector
+            .set_relay_settings_override(relay_settings);
+        if relay_settings_changed && new_state == TargetState::Secured {
+            log::info!("Initiating tunnel restart because the relay settings changed");
+            self.reconnect_tunnel();
+        }
+        self.set_target_state(new_state).await;
+    }
+
     async fn handle_tunnel_state_transition(
         &mut self,
         tunnel_state_transition: TunnelStateTransition,
@@ -1119,6 +1468,9 @@ impl Daemon {
             TunnelState::Connecting { .. } => {
                 log::debug!("Settings: {}", self.settings.summary());
             }
+            TunnelState::Connected { endpoint, .. } => {
+                self.learn_working_obfuscation(endpoint).await;
+            }
             TunnelState::Error(error_state) => {
                 if error_state.is_blocking() {
                     log::info!(
@@ -1141,11 +1493,33 @@ impl Daemon {
             _ => {}
         }
 
+        self.metrics.on_tunnel_state(&tunnel_state);
         self.tunnel_state = tunnel_state.clone();
         self.management_interface
             .notifier()
             .notify_new_state(tunnel_state);
         self.fetch_am_i_mullvad();
+        self.update_latency_measurer();
+
+        // Lists are only downloaded through the tunnel
+        #[cfg(not(target_os = "android"))]
+        if self.tunnel_state.is_connected() && self.dns_blocklist.needs_retry() {
+            self.dns_blocklist.refresh(true);
+        }
+    }
+
+    /// Measure relay latencies in the background, but only while the firewall lets traffic
+    /// through to all relays.
+    fn update_latency_measurer(&self) {
+        match self.tunnel_state {
+            #[cfg(not(target_os = "android"))]
+            TunnelState::Disconnected {
+                locked_down: false, ..
+            } => self.latency_measurer.start(),
+            #[cfg(target_os = "android")]
+            TunnelState::Disconnected { .. } => self.latency_measurer.start(),
+            _ => self.latency_measurer.stop(),
+        }
     }
 
     /// Get the geographical location from am.i.mullvad.net. When it arrives,
@@ -1323,6 +1697,10 @@ impl Daemon {
             ClearAccountHistory(tx) => self.on_clear_account_history(tx).await,
             SetRelaySettings(tx, update) => self.on_set_relay_settings(tx, update).await,
             SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan).await,
+            #[cfg(not(target_os = "android"))]
+            SetAllowedLanNets(tx, allowed_lan_nets) => {
+                self.on_set_allowed_lan_nets(tx, allowed_lan_nets).await
+            }
             SetShowBetaReleases(tx, enabled) => self.on_set_show_beta_releases(tx, enabled).await,
             #[cfg(not(target_os = "android"))]
             SetBlockWhenDisconnected(tx, block_when_disconnected) => {
@@ -1330,6 +1708,8 @@ impl Daemon {
                     .await
             }
             SetAutoConnect(tx, auto_connect) => self.on_set_auto_connect(tx, auto_connect).await,
+            SetAutoConnectRules(tx, rules) => self.on_set_auto_connect_rules(tx, rules).await,
+            SetTrustedNetworks(tx, networks) => self.on_set_trusted_networks(tx, networks).await,
             SetOpenVpnMssfix(tx, mssfix_arg) => self.on_set_openvpn_mssfix(tx, mssfix_arg).await,
             SetBridgeSettings(tx, bridge_settings) => {
                 self.on_set_bridge_settings(tx, bridge_settings).await
@@ -1367,6 +1747,12 @@ impl Daemon {
             DeleteCustomList(tx, id) => self.on_delete_custom_list(tx, id).await,
             UpdateCustomList(tx, update) => self.on_update_custom_list(tx, update).await,
             ClearCustomLists(tx) => self.on_clear_custom_lists(tx).await,
+            CreateCustomEndpoint(tx, name, endpoint) => {
+                self.on_create_custom_endpoint(tx, name, endpoint).await
+            }
+            DeleteCustomEndpoint(tx, id) => self.on_delete_custom_endpoint(tx, id).await,
+            UpdateCustomEndpoint(tx, update) => self.on_update_custom_endpoint(tx, update).await,
+            ClearCustomEndpoints(tx) => self.on_clear_custom_endpoints(tx).await,
             GetVersionInfo(tx) => self.on_get_version_info(tx),
             AddApiAccessMethod(tx, name, enabled, access_method) => {
                 self.on_add_access_method(tx, name, enabled, access_method)
@@ -1391,13 +1777,9 @@ impl Daemon {
             RemoveSplitTunnelProcess(tx, pid) => self.on_remove_split_tunnel_process(tx, pid),
             #[cfg(target_os = "linux")]
             ClearSplitTunnelProcesses(tx) => self.on_clear_split_tunnel_processes(tx),
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             AddSplitTunnelApp(tx, app) => self.on_add_split_tunnel_app(tx, app),
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             RemoveSplitTunnelApp(tx, path) => self.on_remove_split_tunnel_app(tx, path),
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             ClearSplitTunnelApps(tx) => self.on_clear_split_tunnel_apps(tx),
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             SetSplitTunnelState(tx, enabled) => self.on_set_split_tunnel_state(tx, enabled),
             #[cfg(windows)]
             GetSplitTunnelProcesses(tx) => self.on_get_split_tunnel_processes(tx),
@@ -1418,6 +1800,14 @@ impl Daemon {
             ApplyJsonSettings(tx, blob) => self.on_apply_json_settings(tx, blob).await,
             ExportJsonSettings(tx) => self.on_export_json_settings(tx),
             GetFeatureIndicators(tx) => self.on_get_feature_indicators(tx),
+            GetLeakHistory(tx) => self.on_get_leak_history(tx),
+            RunLeakTest(tx) => self.on_run_leak_test(tx),
+            GetLearnedObfuscation(tx) => self.on_get_learned_obfuscation(tx),
+            ClearLearnedObfuscation(tx) => self.on_clear_learned_obfuscation(tx).await,
+            #[cfg(not(target_os = "android"))]
+            GetDnsBlocklistStats(tx) => self.on_get_dns_blocklist_stats(tx),
+            GetTunnelStatistics(tx) => self.on_get_tunnel_statistics(tx),
+            GetMetrics(tx) => self.on_get_metrics(tx).await,
         }
     }
 
@@ -1576,7 +1966,6 @@ impl Daemon {
         });
     }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn handle_new_excluded_paths(
         &mut self,
         update: ExcludedPathsUpdate,
@@ -2040,6 +2429,52 @@ impl Daemon {
         }
     }
 
+    /// Update the split app paths in both the settings and the app monitor
+    #[cfg(target_os = "linux")]
+    fn set_split_tunnel_paths(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        response_msg: &'static str,
+        settings: Settings,
+        update: ExcludedPathsUpdate,
+    ) {
+        let excluded_paths: Vec<_> = match update {
+            ExcludedPathsUpdate::SetPaths(ref paths) if settings.split_tunnel.enable_exclusions => {
+                paths
+                    .iter()
+                    .cloned()
+                    .map(SplitApp::to_tunnel_command_repr)
+                    .collect()
+            }
+            ExcludedPathsUpdate::SetPaths(_) => vec![],
+            ExcludedPathsUpdate::SetState(true) => settings
+                .split_tunnel
+                .apps
+                .iter()
+                .cloned()
+                .map(SplitApp::to_tunnel_command_repr)
+                .collect(),
+            ExcludedPathsUpdate::SetState(false) => vec![],
+        };
+
+        match &self.exclude_apps {
+            Some(exclude_apps) => {
+                if let Err(error) = exclude_apps.set_paths(&excluded_paths) {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to set excluded apps list")
+                    );
+                    Self::oneshot_send(tx, Err(Error::SplitTunnelError(error)), response_msg);
+                    return;
+                }
+            }
+            None => log::warn!("Split tunnel apps cannot be excluded automatically on this system"),
+        }
+        let _ = self
+            .tx
+            .send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+    }
+
     /// Update the split app paths in both the settings and tunnel
     #[cfg(target_os = "macos")]
     fn set_split_tunnel_paths(
@@ -2090,7 +2525,6 @@ impl Daemon {
         });
     }
 
-    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
     fn on_add_split_tunnel_app(&mut self, tx: ResponseTx<(), Error>, app: SplitApp) {
         let settings = self.settings.to_settings();
 
@@ -2108,7 +2542,6 @@ impl Daemon {
         );
     }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     fn on_remove_split_tunnel_app(&mut self, tx: ResponseTx<(), Error>, app: impl Into<SplitApp>) {
         let settings = self.settings.to_settings();
 
@@ -2126,7 +2559,6 @@ impl Daemon {
         );
     }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     fn on_clear_split_tunnel_apps(&mut self, tx: ResponseTx<(), Error>) {
         let settings = self.settings.to_settings();
         let new_list = HashSet::new();
@@ -2138,7 +2570,6 @@ impl Daemon {
         );
     }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     fn on_set_split_tunnel_state(&mut self, tx: ResponseTx<(), Error>, state: bool) {
         let settings = self.settings.to_settings();
         self.set_split_tunnel_paths(
@@ -2177,12 +2608,20 @@ impl Daemon {
     ) {
         match self
             .settings
-            .update(move |settings| settings.set_relay_settings(update))
+            .try_update(move |settings| {
+                if let RelaySettings::SavedCustomEndpoints(ids) = &update {
+                    settings.custom_endpoints.validate_selection(ids)?;
+                }
+                settings.set_relay_settings(update);
+                Ok::<_, mullvad_types::custom_endpoint::Error>(())
+            })
             .await
         {
             Ok(settings_changed) => {
                 Self::oneshot_send(tx, Ok(()), "set_relay_settings response");
-                if settings_changed {
+                // Relay settings chosen by the user replace those of any auto-connect rule
+                let override_cleared = self.relay_selector.set_relay_settings_override(None);
+                if settings_changed || override_cleared {
                     log::info!("Initiating tunnel restart because the relay settings changed");
                     self.reconnect_tunnel();
                 }
@@ -2219,6 +2658,37 @@ impl Daemon {
         }
     }
 
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_allowed_lan_nets(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        allowed_lan_nets: AllowedLanNets,
+    ) {
+        let nets = allowed_lan_nets.clone();
+        match self
+            .settings
+            .update(move |settings| settings.allowed_lan_nets = nets)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::AllowedLanNets(
+                        allowed_lan_nets,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_allowed_lan_nets response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_allowed_lan_nets response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_allowed_lan_nets response");
+            }
+        }
+    }
+
     async fn on_set_show_beta_releases(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -2293,6 +2763,46 @@ impl Daemon {
         }
     }
 
+    async fn on_set_auto_connect_rules(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        rules: AutoConnectRules,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.auto_connect_rules = rules)
+            .await
+        {
+            Ok(_settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set auto-connect rules response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set auto-connect rules response");
+            }
+        }
+    }
+
+    async fn on_set_trusted_networks(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        networks: Vec<TrustedNetwork>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.trusted_networks = networks)
+            .await
+        {
+            Ok(_settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set trusted networks response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set trusted networks response");
+            }
+        }
+    }
+
     async fn on_set_openvpn_mssfix(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -2374,6 +2884,9 @@ impl Daemon {
         {
             Ok(settings_changed) => {
                 if settings_changed {
+                    if !self.settings.obfuscation_settings.learn_per_network {
+                        self.relay_selector.set_learned_obfuscation(None);
+                    }
                     self.reconnect_tunnel();
                 }
                 Self::oneshot_send(tx, Ok(()), "set_obfuscation_settings");
@@ -2570,11 +3083,8 @@ impl Daemon {
         {
             Ok(settings_changed) => {
                 if settings_changed {
-                    let settings = self.settings.settings();
-                    let resolvers =
-                        dns::addresses_from_options(&settings.tunnel_options.dns_options);
                     self.send_tunnel_command(TunnelCommand::Dns(
-                        resolvers,
+                        self.dns_config(),
                         oneshot_map(tx, |tx, ()| {
                             Self::oneshot_send(tx, Ok(()), "set_dns_options response");
                         }),
@@ -2740,6 +3250,39 @@ impl Daemon {
         Self::oneshot_send(tx, result, "clear_custom_lists response");
     }
 
+    async fn on_create_custom_endpoint(
+        &mut self,
+        tx: ResponseTx<mullvad_types::custom_endpoint::Id, Error>,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) {
+        let result = self.create_custom_endpoint(name, endpoint).await;
+        Self::oneshot_send(tx, result, "create_custom_endpoint response");
+    }
+
+    async fn on_delete_custom_endpoint(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        id: mullvad_types::custom_endpoint::Id,
+    ) {
+        let result = self.delete_custom_endpoint(id).await;
+        Self::oneshot_send(tx, result, "delete_custom_endpoint response");
+    }
+
+    async fn on_update_custom_endpoint(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        new_endpoint: NamedCustomEndpoint,
+    ) {
+        let result = self.update_custom_endpoint(new_endpoint).await;
+        Self::oneshot_send(tx, result, "update_custom_endpoint response");
+    }
+
+    async fn on_clear_custom_endpoints(&mut self, tx: ResponseTx<(), Error>) {
+        let result = self.clear_custom_endpoints().await;
+        Self::oneshot_send(tx, result, "clear_custom_endpoints response");
+    }
+
     async fn on_add_access_method(
         &mut self,
         tx: ResponseTx<mullvad_types::access_method::Id, Error>,
@@ -2920,6 +3463,17 @@ impl Daemon {
             let (tx, _rx) = oneshot::channel();
             self.send_tunnel_command(TunnelCommand::SetExcludedApps(tx, vec![]));
         }
+        #[cfg(target_os = "linux")]
+        if let Some(Err(error)) = self
+            .exclude_apps
+            .as_ref()
+            .map(|exclude_apps| exclude_apps.set_paths(&[]))
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to clear excluded apps list")
+            );
+        }
 
         #[cfg(not(target_os = "android"))]
         {
@@ -2933,9 +3487,17 @@ impl Daemon {
         let (tx, _rx) = oneshot::channel();
         self.send_tunnel_command(TunnelCommand::AllowLan(self.settings.allow_lan, tx));
 
+        #[cfg(not(target_os = "android"))]
+        {
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::AllowedLanNets(
+                self.settings.allowed_lan_nets.clone(),
+                tx,
+            ));
+        }
+
         let (tx, _rx) = oneshot::channel();
-        let dns = dns::addresses_from_options(&self.settings.tunnel_options.dns_options);
-        self.send_tunnel_command(TunnelCommand::Dns(dns, tx));
+        self.send_tunnel_command(TunnelCommand::Dns(self.dns_config(), tx));
 
         self.version_updater_handle
             .set_show_beta_releases(self.settings.show_beta_releases)
@@ -3066,6 +3628,95 @@ impl Daemon {
         Self::oneshot_send(tx, result, "export_json_settings response");
     }
 
+    fn on_get_leak_history(&self, tx: oneshot::Sender<Vec<LeakReport>>) {
+        Self::oneshot_send(tx, self.leak_history.reports(), "get_leak_history response");
+    }
+
+    fn on_get_learned_obfuscation(&self, tx: oneshot::Sender<Vec<LearnedObfuscation>>) {
+        Self::oneshot_send(
+            tx,
+            self.learned_obfuscation.entries(),
+            "get_learned_obfuscation response",
+        );
+    }
+
+    async fn on_clear_learned_obfuscation(&mut self, tx: ResponseTx<(), Error>) {
+        self.relay_selector.set_learned_obfuscation(None);
+        let result = self
+            .learned_obfuscation
+            .clear()
+            .await
+            .map_err(Error::LearnedObfuscation);
+        Self::oneshot_send(tx, result, "clear_learned_obfuscation response");
+    }
+
+    fn on_run_leak_test(&self, tx: ResponseTx<LeakTestReport, Error>) {
+        let TunnelState::Connected { endpoint, .. } = &self.tunnel_state else {
+            Self::oneshot_send(
+                tx,
+                Err(Error::LeakTestNotConnected),
+                "run_leak_test response",
+            );
+            return;
+        };
+        // Resolvers on the local network are reachable by design when local network sharing is
+        // enabled, so they would always be reported as leaks
+        let allowed_lan_nets = if self.settings.allow_lan {
+            self.settings.allowed_lan_nets.networks()
+        } else {
+            vec![]
+        };
+        let non_tunnel_dns = dns::addresses_from_options(&self.settings.tunnel_options.dns_options)
+            .non_tunnel_config()
+            .iter()
+            .copied()
+            .filter(|ip| !allowed_lan_nets.iter().any(|net| net.contains(*ip)))
+            .collect();
+        let leak_test = self
+            .leak_checker
+            .run_leak_test(endpoint.clone(), non_tunnel_dns);
+        tokio::spawn(async move {
+            Self::oneshot_send(tx, Ok(leak_test.await), "run_leak_test response");
+        });
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn on_get_dns_blocklist_stats(&self, tx: oneshot::Sender<DnsBlocklistStats>) {
+        Self::oneshot_send(
+            tx,
+            self.dns_blocklist.stats(),
+            "get_dns_blocklist_stats response",
+        );
+    }
+
+    fn on_get_tunnel_statistics(&self, tx: oneshot::Sender<Option<TunnelStatistics>>) {
+        // The tunnel state machine responds once the statistics have been collected
+        self.send_tunnel_command(TunnelCommand::GetStatistics(tx));
+    }
+
+    async fn on_get_metrics(&self, tx: oneshot::Sender<metrics::Metrics>) {
+        let key_age = match self.account_manager.data().await.map(|s| s.into_device()) {
+            Ok(Some(config)) => (chrono::Utc::now() - config.device.wg_data.created)
+                .to_std()
+                .ok(),
+            _ => None,
+        };
+        let mut metrics = metrics::Metrics {
+            tunnel_state: self.tunnel_state.clone(),
+            counters: self.metrics.clone(),
+            statistics: None,
+            api_availability: self.api_handle.availability.get_state(),
+            key_age,
+        };
+
+        let (statistics_tx, statistics_rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::GetStatistics(statistics_tx));
+        tokio::spawn(async move {
+            metrics.statistics = statistics_rx.await.ok().flatten();
+            Self::oneshot_send(tx, metrics, "get_metrics response");
+        });
+    }
+
     fn on_get_feature_indicators(&self, tx: oneshot::Sender<FeatureIndicators>) {
         let feature_indicators = match &self.tunnel_state {
             TunnelState::Connecting {
@@ -3082,8 +3733,13 @@ impl Daemon {
     /// Set the target state of the client. If it changed trigger the operations needed to
     /// progress towards that state.
     /// Returns a bool representing whether a state change was initiated.
+    /// Set and persist the target state. This replaces any trusted network override, so that
+    /// connecting or disconnecting manually takes precedence over trusted networks.
     async fn set_target_state(&mut self, new_state: TargetState) -> bool {
-        if new_state != *self.target_state || self.tunnel_state.is_in_error_state() {
+        if new_state != *self.target_state
+            || self.target_state.is_overridden()
+            || self.tunnel_state.is_in_error_state()
+        {
             log::debug!("Target state {:?} => {:?}", *self.target_state, new_state);
 
             self.target_state.set(new_state).await;
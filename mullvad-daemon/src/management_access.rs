@@ -0,0 +1,139 @@
+//! Role based access control for the management interface.
+//!
+//! Callers are identified by the credentials of the process on the other end of the management
+//! socket, and mapped to a [`Role`] using an [`AccessPolicy`] read from the settings directory.
+//! If there is no policy file, every caller is an admin, which is the same as having no access
+//! control at all.
+
+use mullvad_management_interface::PeerCredentials;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+use talpid_types::ErrorExt;
+use tokio::{fs, io};
+
+const ACCESS_POLICY_FILE: &str = "management-access.json";
+
+/// UID of the superuser, which is always an admin.
+const ROOT_UID: u32 = 0;
+
+/// What a caller of the management interface is allowed to do. Roles are ordered, so that a role
+/// is allowed to do everything that lesser roles are allowed to do.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// May observe the tunnel state, settings and events, but change nothing.
+    ReadOnly,
+    /// May call any RPC.
+    Admin,
+}
+
+/// Maps callers of the management interface to roles.
+///
+/// A rule for the UID of the caller takes precedence over a rule for its primary GID. Callers
+/// that match no rule get `default_role`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AccessPolicy {
+    default_role: Role,
+    #[serde(default)]
+    users: HashMap<u32, Role>,
+    #[serde(default)]
+    groups: HashMap<u32, Role>,
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        Self::with_default_role(Role::Admin)
+    }
+}
+
+impl AccessPolicy {
+    fn with_default_role(default_role: Role) -> Self {
+        AccessPolicy {
+            default_role,
+            users: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Load the policy from `settings_dir`. If the policy file exists but cannot be read, every
+    /// caller except root is given the [`Role::ReadOnly`] role.
+    pub async fn load(settings_dir: &Path) -> Self {
+        let path = settings_dir.join(ACCESS_POLICY_FILE);
+        match fs::read_to_string(&path).await {
+            Ok(contents) => serde_json::from_str(&contents)
+                .inspect(|_| log::info!("Loaded management access policy from {}", path.display()))
+                .unwrap_or_else(|error| {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to parse management access policy")
+                    );
+                    Self::with_default_role(Role::ReadOnly)
+                }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read management access policy")
+                );
+                Self::with_default_role(Role::ReadOnly)
+            }
+        }
+    }
+
+    /// Return the role of a caller. Callers whose credentials are unknown are admins, since the
+    /// management interface is then protected by other means.
+    pub fn role(&self, peer: Option<&PeerCredentials>) -> Role {
+        let Some(peer) = peer else {
+            return Role::Admin;
+        };
+        if peer.uid == ROOT_UID {
+            return Role::Admin;
+        }
+        self.users
+            .get(&peer.uid)
+            .or_else(|| self.groups.get(&peer.gid))
+            .copied()
+            .unwrap_or(self.default_role)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn peer(uid: u32, gid: u32) -> PeerCredentials {
+        PeerCredentials {
+            uid,
+            gid,
+            pid: None,
+        }
+    }
+
+    #[test]
+    fn test_role() {
+        let policy: AccessPolicy = serde_json::from_str(
+            r#"{
+                "default_role": "read_only",
+                "users": { "1000": "admin", "1001": "read_only" },
+                "groups": { "100": "admin" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(policy.role(Some(&peer(1000, 1000))), Role::Admin);
+        // User rules take precedence over group rules
+        assert_eq!(policy.role(Some(&peer(1001, 100))), Role::ReadOnly);
+        assert_eq!(policy.role(Some(&peer(1002, 100))), Role::Admin);
+        assert_eq!(policy.role(Some(&peer(1002, 1002))), Role::ReadOnly);
+        // Root and callers without credentials are always admins
+        assert_eq!(policy.role(Some(&peer(ROOT_UID, 1002))), Role::Admin);
+        assert_eq!(policy.role(None), Role::Admin);
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = AccessPolicy::default();
+        assert_eq!(policy.role(Some(&peer(1000, 1000))), Role::Admin);
+    }
+}
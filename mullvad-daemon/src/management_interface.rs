@@ -1,4 +1,8 @@
-use crate::{account_history, device, version_check, DaemonCommand, DaemonCommandSender};
+use crate::{
+    account_history, device,
+    management_access::{AccessPolicy, Role},
+    version_check, DaemonCommand, DaemonCommandSender,
+};
 use futures::{
     channel::{mpsc, oneshot},
     StreamExt,
@@ -6,10 +10,11 @@ use futures::{
 use mullvad_api::{rest::Error as RestError, StatusCode};
 use mullvad_management_interface::{
     types::{self, daemon_event, management_service_server::ManagementService},
-    Code, Request, Response, ServerJoinHandle, Status,
+    Code, PeerCredentials, Request, Response, ServerJoinHandle, Status,
 };
 use mullvad_types::{
     account::AccountNumber,
+    auto_connect::{AutoConnectRules, TrustedNetwork},
     relay_constraints::{
         BridgeSettings, BridgeState, ObfuscationSettings, RelayOverride, RelaySettings,
     },
@@ -31,6 +36,9 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const RPC_SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
 
+/// Shortest interval at which tunnel statistics may be streamed to clients.
+const MIN_TUNNEL_STATISTICS_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     // Unable to start the management interface server
@@ -41,6 +49,7 @@ pub enum Error {
 struct ManagementServiceImpl {
     daemon_tx: DaemonCommandSender,
     subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
+    access_policy: AccessPolicy,
 }
 
 pub type ServiceResult<T> = std::result::Result<Response<T>, Status>;
@@ -54,11 +63,14 @@ const USED_VOUCHER_MESSAGE: &str = "This voucher code has already been used";
 impl ManagementService for ManagementServiceImpl {
     type GetSplitTunnelProcessesStream = UnboundedReceiverStream<Result<i32, Status>>;
     type EventsListenStream = EventsListenerReceiver;
+    type TunnelStatisticsListenStream =
+        UnboundedReceiverStream<Result<types::TunnelStatistics, Status>>;
 
     // Control and get the tunnel state
     //
 
-    async fn connect_tunnel(&self, _: Request<()>) -> ServiceResult<bool> {
+    async fn connect_tunnel(&self, request: Request<()>) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("connect_tunnel");
 
         let (tx, rx) = oneshot::channel();
@@ -67,7 +79,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(connect_issued))
     }
 
-    async fn disconnect_tunnel(&self, _: Request<()>) -> ServiceResult<bool> {
+    async fn disconnect_tunnel(&self, request: Request<()>) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("disconnect_tunnel");
 
         let (tx, rx) = oneshot::channel();
@@ -76,7 +89,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(disconnect_issued))
     }
 
-    async fn reconnect_tunnel(&self, _: Request<()>) -> ServiceResult<bool> {
+    async fn reconnect_tunnel(&self, request: Request<()>) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("reconnect_tunnel");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::Reconnect(tx))?;
@@ -84,7 +98,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(reconnect_issued))
     }
 
-    async fn get_tunnel_state(&self, _: Request<()>) -> ServiceResult<types::TunnelState> {
+    async fn get_tunnel_state(&self, request: Request<()>) -> ServiceResult<types::TunnelState> {
+        self.authorize(&request, Role::ReadOnly)?;
         log::debug!("get_tunnel_state");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetState(tx))?;
@@ -95,7 +110,8 @@ impl ManagementService for ManagementServiceImpl {
     // Control the daemon and receive events
     //
 
-    async fn events_listen(&self, _: Request<()>) -> ServiceResult<Self::EventsListenStream> {
+    async fn events_listen(&self, request: Request<()>) -> ServiceResult<Self::EventsListenStream> {
+        self.authorize(&request, Role::ReadOnly)?;
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
         let mut subscriptions = self.subscriptions.lock().unwrap();
@@ -104,7 +120,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
 
-    async fn prepare_restart(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn prepare_restart(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("prepare_restart");
         // Note: The old `PrepareRestart` behavior never shutdown the daemon.
         let shutdown = false;
@@ -113,12 +130,14 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn prepare_restart_v2(&self, shutdown: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&shutdown, Role::Admin)?;
         log::debug!("prepare_restart_v2");
         self.send_command_to_daemon(DaemonCommand::PrepareRestart(shutdown.into_inner()))?;
         Ok(Response::new(()))
     }
 
-    async fn factory_reset(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn factory_reset(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         #[cfg(not(target_os = "android"))]
         {
             log::debug!("factory_reset");
@@ -135,7 +154,8 @@ impl ManagementService for ManagementServiceImpl {
         }
     }
 
-    async fn get_current_version(&self, _: Request<()>) -> ServiceResult<String> {
+    async fn get_current_version(&self, request: Request<()>) -> ServiceResult<String> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_current_version");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetCurrentVersion(tx))?;
@@ -143,7 +163,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(version))
     }
 
-    async fn get_version_info(&self, _: Request<()>) -> ServiceResult<types::AppVersionInfo> {
+    async fn get_version_info(&self, request: Request<()>) -> ServiceResult<types::AppVersionInfo> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_version_info");
 
         let (tx, rx) = oneshot::channel();
@@ -155,7 +176,8 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn is_performing_post_upgrade(&self, _: Request<()>) -> ServiceResult<bool> {
+    async fn is_performing_post_upgrade(&self, request: Request<()>) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("is_performing_post_upgrade");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::IsPerformingPostUpgrade(tx))?;
@@ -165,7 +187,8 @@ impl ManagementService for ManagementServiceImpl {
     // Relays and tunnel constraints
     //
 
-    async fn update_relay_locations(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn update_relay_locations(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("update_relay_locations");
         self.send_command_to_daemon(DaemonCommand::UpdateRelayLocations)?;
         Ok(Response::new(()))
@@ -175,6 +198,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::RelaySettings>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("set_relay_settings");
         let (tx, rx) = oneshot::channel();
         let constraints_update =
@@ -186,7 +210,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
-    async fn get_relay_locations(&self, _: Request<()>) -> ServiceResult<types::RelayList> {
+    async fn get_relay_locations(&self, request: Request<()>) -> ServiceResult<types::RelayList> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_relay_locations");
 
         let (tx, rx) = oneshot::channel();
@@ -200,6 +225,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::BridgeSettings>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let settings =
             BridgeSettings::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
 
@@ -215,6 +241,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::ObfuscationSettings>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let settings =
             ObfuscationSettings::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
         log::debug!("set_obfuscation_settings({:?})", settings);
@@ -225,6 +252,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn set_bridge_state(&self, request: Request<types::BridgeState>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let bridge_state =
             BridgeState::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
 
@@ -238,7 +266,8 @@ impl ManagementService for ManagementServiceImpl {
     // Settings
     //
 
-    async fn get_settings(&self, _: Request<()>) -> ServiceResult<types::Settings> {
+    async fn get_settings(&self, request: Request<()>) -> ServiceResult<types::Settings> {
+        self.authorize(&request, Role::ReadOnly)?;
         log::debug!("get_settings");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetSettings(tx))?;
@@ -247,7 +276,8 @@ impl ManagementService for ManagementServiceImpl {
             .map(|settings| Response::new(types::Settings::from(&settings)))
     }
 
-    async fn reset_settings(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn reset_settings(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("reset_settings");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ResetSettings(tx))?;
@@ -256,6 +286,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn set_allow_lan(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let allow_lan = request.into_inner();
         log::debug!("set_allow_lan({})", allow_lan);
         let (tx, rx) = oneshot::channel();
@@ -264,7 +295,34 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(not(target_os = "android"))]
+    async fn set_allowed_lan_nets(
+        &self,
+        request: Request<types::AllowedLanNets>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        let allowed_lan_nets = talpid_types::net::AllowedLanNets::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        log::debug!("set_allowed_lan_nets({})", allowed_lan_nets);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetAllowedLanNets(tx, allowed_lan_nets))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_allowed_lan_nets(
+        &self,
+        request: Request<types::AllowedLanNets>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        Err(Status::unimplemented(
+            "Setting custom LAN networks is not supported on Android",
+        ))
+    }
+
     async fn set_show_beta_releases(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let enabled = request.into_inner();
         log::debug!("set_show_beta_releases({})", enabled);
         let (tx, rx) = oneshot::channel();
@@ -275,6 +333,7 @@ impl ManagementService for ManagementServiceImpl {
 
     #[cfg(not(target_os = "android"))]
     async fn set_block_when_disconnected(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let block_when_disconnected = request.into_inner();
         log::debug!("set_block_when_disconnected({})", block_when_disconnected);
         let (tx, rx) = oneshot::channel();
@@ -288,6 +347,7 @@ impl ManagementService for ManagementServiceImpl {
 
     #[cfg(target_os = "android")]
     async fn set_block_when_disconnected(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let block_when_disconnected = request.into_inner();
         log::debug!("set_block_when_disconnected({})", block_when_disconnected);
         Err(Status::unimplemented(
@@ -296,6 +356,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn set_auto_connect(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let auto_connect = request.into_inner();
         log::debug!("set_auto_connect({})", auto_connect);
         let (tx, rx) = oneshot::channel();
@@ -304,7 +365,36 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_auto_connect_rules(
+        &self,
+        request: Request<types::AutoConnectRules>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        let rules =
+            AutoConnectRules::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_auto_connect_rules");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetAutoConnectRules(tx, rules))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_trusted_networks(
+        &self,
+        request: Request<types::TrustedNetworks>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        let networks =
+            Vec::<TrustedNetwork>::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_trusted_networks");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetTrustedNetworks(tx, networks))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     async fn set_openvpn_mssfix(&self, request: Request<u32>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let mssfix = request.into_inner();
         let mssfix = if mssfix != 0 {
             Some(mssfix as u16)
@@ -319,6 +409,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn set_wireguard_mtu(&self, request: Request<u32>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let mtu = request.into_inner();
         let mtu = if mtu != 0 { Some(mtu as u16) } else { None };
         log::debug!("set_wireguard_mtu({:?})", mtu);
@@ -329,6 +420,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn set_enable_ipv6(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let enable_ipv6 = request.into_inner();
         log::debug!("set_enable_ipv6({})", enable_ipv6);
         let (tx, rx) = oneshot::channel();
@@ -341,6 +433,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::QuantumResistantState>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let state = mullvad_types::wireguard::QuantumResistantState::try_from(request.into_inner())
             .map_err(map_protobuf_type_err)?;
 
@@ -353,6 +446,7 @@ impl ManagementService for ManagementServiceImpl {
 
     #[cfg(daita)]
     async fn set_enable_daita(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let daita_enabled = request.into_inner();
         log::debug!("set_enable_daita({daita_enabled})");
         let (tx, rx) = oneshot::channel();
@@ -363,6 +457,7 @@ impl ManagementService for ManagementServiceImpl {
 
     #[cfg(daita)]
     async fn set_daita_direct_only(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let direct_only_enabled = request.into_inner();
         log::debug!("set_daita_direct_only({direct_only_enabled})");
         let (tx, rx) = oneshot::channel();
@@ -379,6 +474,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::DaitaSettings>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let state = mullvad_types::wireguard::DaitaSettings::from(request.into_inner());
 
         log::debug!("set_daita_settings({state:?})");
@@ -389,21 +485,28 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     #[cfg(not(daita))]
-    async fn set_enable_daita(&self, _: Request<bool>) -> ServiceResult<()> {
+    async fn set_enable_daita(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(()))
     }
 
     #[cfg(not(daita))]
-    async fn set_daita_direct_only(&self, _: Request<bool>) -> ServiceResult<()> {
+    async fn set_daita_direct_only(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(()))
     }
 
     #[cfg(not(daita))]
-    async fn set_daita_settings(&self, _: Request<types::DaitaSettings>) -> ServiceResult<()> {
+    async fn set_daita_settings(
+        &self,
+        request: Request<types::DaitaSettings>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(()))
     }
 
     async fn set_dns_options(&self, request: Request<types::DnsOptions>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let options = DnsOptions::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
         log::debug!("set_dns_options({:?})", options);
 
@@ -417,6 +520,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::RelayOverride>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let relay_override =
             RelayOverride::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
         log::debug!("set_relay_override");
@@ -426,7 +530,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
-    async fn clear_all_relay_overrides(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn clear_all_relay_overrides(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("clear_all_relay_overrides");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ClearAllRelayOverrides(tx))?;
@@ -437,7 +542,8 @@ impl ManagementService for ManagementServiceImpl {
     // Account management
     //
 
-    async fn create_new_account(&self, _: Request<()>) -> ServiceResult<String> {
+    async fn create_new_account(&self, request: Request<()>) -> ServiceResult<String> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("create_new_account");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::CreateNewAccount(tx))?;
@@ -448,6 +554,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn login_account(&self, request: Request<AccountNumber>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("login_account");
         let account_number = request.into_inner();
         let (tx, rx) = oneshot::channel();
@@ -458,7 +565,8 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn logout_account(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn logout_account(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("logout_account");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::LogoutAccount(tx))?;
@@ -472,6 +580,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<AccountNumber>,
     ) -> ServiceResult<types::AccountData> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_account_data");
         let account_number = request.into_inner();
         let (tx, rx) = oneshot::channel();
@@ -488,7 +597,11 @@ impl ManagementService for ManagementServiceImpl {
             })
     }
 
-    async fn get_account_history(&self, _: Request<()>) -> ServiceResult<types::AccountHistory> {
+    async fn get_account_history(
+        &self,
+        request: Request<()>,
+    ) -> ServiceResult<types::AccountHistory> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_account_history");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetAccountHistory(tx))?;
@@ -497,7 +610,8 @@ impl ManagementService for ManagementServiceImpl {
             .map(|history| Response::new(types::AccountHistory { number: history }))
     }
 
-    async fn clear_account_history(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn clear_account_history(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("clear_account_history");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ClearAccountHistory(tx))?;
@@ -507,7 +621,8 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn get_www_auth_token(&self, _: Request<()>) -> ServiceResult<String> {
+    async fn get_www_auth_token(&self, request: Request<()>) -> ServiceResult<String> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_www_auth_token");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetWwwAuthToken(tx))?;
@@ -525,6 +640,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<String>,
     ) -> ServiceResult<types::VoucherSubmission> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("submit_voucher");
         let voucher = request.into_inner();
         let (tx, rx) = oneshot::channel();
@@ -536,7 +652,8 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     // Device management
-    async fn get_device(&self, _: Request<()>) -> ServiceResult<types::DeviceState> {
+    async fn get_device(&self, request: Request<()>) -> ServiceResult<types::DeviceState> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_device");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetDevice(tx))?;
@@ -544,7 +661,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(types::DeviceState::from(device)))
     }
 
-    async fn update_device(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn update_device(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("update_device");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::UpdateDevice(tx))?;
@@ -558,6 +676,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<AccountNumber>,
     ) -> ServiceResult<types::DeviceList> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("list_devices");
         let (tx, rx) = oneshot::channel();
         let token = request.into_inner();
@@ -567,6 +686,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn remove_device(&self, request: Request<types::DeviceRemoval>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("remove_device");
         let (tx, rx) = oneshot::channel();
         let removal = request.into_inner();
@@ -586,6 +706,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::Duration>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let interval: RotationInterval = Duration::try_from(request.into_inner())
             .map_err(|_| Status::invalid_argument("unexpected negative rotation interval"))?
             .try_into()
@@ -603,7 +724,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
-    async fn reset_wireguard_rotation_interval(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn reset_wireguard_rotation_interval(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("reset_wireguard_rotation_interval");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::SetWireguardRotationInterval(tx, None))?;
@@ -611,7 +733,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
-    async fn rotate_wireguard_key(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn rotate_wireguard_key(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("rotate_wireguard_key");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::RotateWireguardKey(tx))?;
@@ -621,7 +744,8 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn get_wireguard_key(&self, _: Request<()>) -> ServiceResult<types::PublicKey> {
+    async fn get_wireguard_key(&self, request: Request<()>) -> ServiceResult<types::PublicKey> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_wireguard_key");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetWireguardKey(tx))?;
@@ -636,6 +760,7 @@ impl ManagementService for ManagementServiceImpl {
     //
 
     async fn create_custom_list(&self, request: Request<String>) -> ServiceResult<String> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("create_custom_list");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::CreateCustomList(tx, request.into_inner()))?;
@@ -646,6 +771,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn delete_custom_list(&self, request: Request<String>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("delete_custom_list");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::DeleteCustomList(
@@ -660,6 +786,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn update_custom_list(&self, request: Request<types::CustomList>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("update_custom_list");
         let custom_list = mullvad_types::custom_list::CustomList::try_from(request.into_inner())?;
         let (tx, rx) = oneshot::channel();
@@ -670,7 +797,8 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn clear_custom_lists(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn clear_custom_lists(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("clear_custom_lists");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ClearCustomLists(tx))?;
@@ -680,12 +808,81 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
+    // Saved custom endpoints
+    //
+
+    async fn create_custom_endpoint(
+        &self,
+        request: Request<types::NewCustomEndpoint>,
+    ) -> ServiceResult<String> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("create_custom_endpoint");
+        let request = request.into_inner();
+        let endpoint = request
+            .endpoint
+            .ok_or(Status::invalid_argument("missing custom endpoint"))?;
+        let endpoint = mullvad_types::CustomTunnelEndpoint::try_from(endpoint)?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::CreateCustomEndpoint(
+            tx,
+            request.name,
+            endpoint,
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|response| Response::new(response.to_string()))
+            .map_err(map_daemon_error)
+    }
+
+    async fn delete_custom_endpoint(&self, request: Request<String>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("delete_custom_endpoint");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::DeleteCustomEndpoint(
+            tx,
+            mullvad_types::custom_endpoint::Id::from_str(&request.into_inner())
+                .map_err(|_| Status::invalid_argument("invalid ID"))?,
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn update_custom_endpoint(
+        &self,
+        request: Request<types::CustomEndpoint>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("update_custom_endpoint");
+        let endpoint =
+            mullvad_types::custom_endpoint::NamedCustomEndpoint::try_from(request.into_inner())?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::UpdateCustomEndpoint(tx, endpoint))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn clear_custom_endpoints(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("clear_custom_endpoints");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ClearCustomEndpoints(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
     // Access Methods
 
     async fn add_api_access_method(
         &self,
         request: Request<types::NewAccessMethodSetting>,
     ) -> ServiceResult<types::Uuid> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("add_api_access_method");
         let request = request.into_inner();
         let (tx, rx) = oneshot::channel();
@@ -706,6 +903,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn remove_api_access_method(&self, request: Request<types::Uuid>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("remove_api_access_method");
         let api_access_method = mullvad_types::access_method::Id::try_from(request.into_inner())?;
         let (tx, rx) = oneshot::channel();
@@ -717,6 +915,7 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     async fn set_api_access_method(&self, request: Request<types::Uuid>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("set_api_access_method");
         let api_access_method = mullvad_types::access_method::Id::try_from(request.into_inner())?;
         let (tx, rx) = oneshot::channel();
@@ -731,6 +930,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::AccessMethodSetting>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("update_api_access_method");
         let access_method_update =
             mullvad_types::access_method::AccessMethodSetting::try_from(request.into_inner())?;
@@ -745,7 +945,8 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn clear_custom_api_access_methods(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn clear_custom_api_access_methods(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("clear_custom_api_access_methods");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ClearCustomApiAccessMethods(tx))?;
@@ -759,8 +960,9 @@ impl ManagementService for ManagementServiceImpl {
     /// connect to the Mullvad API.
     async fn get_current_api_access_method(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::AccessMethodSetting> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_current_api_access_method");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetCurrentAccessMethod(tx))?;
@@ -775,6 +977,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         config: Request<types::CustomProxy>,
     ) -> ServiceResult<bool> {
+        self.authorize(&config, Role::Admin)?;
         log::debug!("test_custom_api_access_method");
         let (tx, rx) = oneshot::channel();
         let proxy = talpid_types::net::proxy::CustomProxy::try_from(config.into_inner())?;
@@ -789,6 +992,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::Uuid>,
     ) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("test_api_access_method_by_id");
         let (tx, rx) = oneshot::channel();
         let api_access_method = mullvad_types::access_method::Id::try_from(request.into_inner())?;
@@ -807,8 +1011,9 @@ impl ManagementService for ManagementServiceImpl {
 
     async fn get_split_tunnel_processes(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<Self::GetSplitTunnelProcessesStream> {
+        self.authorize(&request, Role::Admin)?;
         #[cfg(target_os = "linux")]
         {
             log::debug!("get_split_tunnel_processes");
@@ -837,6 +1042,7 @@ impl ManagementService for ManagementServiceImpl {
 
     #[cfg(target_os = "linux")]
     async fn add_split_tunnel_process(&self, request: Request<i32>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let pid = request.into_inner();
         log::debug!("add_split_tunnel_process");
         let (tx, rx) = oneshot::channel();
@@ -847,12 +1053,14 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
     #[cfg(not(target_os = "linux"))]
-    async fn add_split_tunnel_process(&self, _: Request<i32>) -> ServiceResult<()> {
+    async fn add_split_tunnel_process(&self, request: Request<i32>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(()))
     }
 
     #[cfg(target_os = "linux")]
     async fn remove_split_tunnel_process(&self, request: Request<i32>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         let pid = request.into_inner();
         log::debug!("remove_split_tunnel_process");
         let (tx, rx) = oneshot::channel();
@@ -863,11 +1071,13 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
     #[cfg(not(target_os = "linux"))]
-    async fn remove_split_tunnel_process(&self, _: Request<i32>) -> ServiceResult<()> {
+    async fn remove_split_tunnel_process(&self, request: Request<i32>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(()))
     }
 
-    async fn clear_split_tunnel_processes(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn clear_split_tunnel_processes(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         #[cfg(target_os = "linux")]
         {
             log::debug!("clear_split_tunnel_processes");
@@ -884,8 +1094,8 @@ impl ManagementService for ManagementServiceImpl {
         }
     }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn add_split_tunnel_app(&self, request: Request<String>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         use mullvad_types::settings::SplitApp;
         log::debug!("add_split_tunnel_app");
         let path = SplitApp::from(request.into_inner());
@@ -897,13 +1107,8 @@ impl ManagementService for ManagementServiceImpl {
             .map(Response::new)
     }
 
-    #[cfg(target_os = "linux")]
-    async fn add_split_tunnel_app(&self, _: Request<String>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
-
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn remove_split_tunnel_app(&self, request: Request<String>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         use mullvad_types::settings::SplitApp;
         log::debug!("remove_split_tunnel_app");
         let path = SplitApp::from(request.into_inner());
@@ -914,13 +1119,9 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
             .map(Response::new)
     }
-    #[cfg(target_os = "linux")]
-    async fn remove_split_tunnel_app(&self, _: Request<String>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-    async fn clear_split_tunnel_apps(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn clear_split_tunnel_apps(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("clear_split_tunnel_apps");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ClearSplitTunnelApps(tx))?;
@@ -929,13 +1130,9 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
             .map(Response::new)
     }
-    #[cfg(target_os = "linux")]
-    async fn clear_split_tunnel_apps(&self, _: Request<()>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn set_split_tunnel_state(&self, request: Request<bool>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("set_split_tunnel_state");
         let enabled = request.into_inner();
         let (tx, rx) = oneshot::channel();
@@ -945,16 +1142,13 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
             .map(Response::new)
     }
-    #[cfg(target_os = "linux")]
-    async fn set_split_tunnel_state(&self, _: Request<bool>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
 
     #[cfg(windows)]
     async fn get_excluded_processes(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::ExcludedProcessList> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_excluded_processes");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetSplitTunnelProcesses(tx))?;
@@ -974,27 +1168,31 @@ impl ManagementService for ManagementServiceImpl {
     #[cfg(not(windows))]
     async fn get_excluded_processes(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::ExcludedProcessList> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(types::ExcludedProcessList {
             processes: vec![],
         }))
     }
 
     #[cfg(target_os = "macos")]
-    async fn need_full_disk_permissions(&self, _: Request<()>) -> ServiceResult<bool> {
+    async fn need_full_disk_permissions(&self, request: Request<()>) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("need_full_disk_permissions");
         let has_access = talpid_core::split_tunnel::has_full_disk_access().await;
         Ok(Response::new(!has_access))
     }
 
     #[cfg(not(target_os = "macos"))]
-    async fn need_full_disk_permissions(&self, _: Request<()>) -> ServiceResult<bool> {
+    async fn need_full_disk_permissions(&self, request: Request<()>) -> ServiceResult<bool> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(false))
     }
 
     #[cfg(windows)]
-    async fn check_volumes(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn check_volumes(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("check_volumes");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::CheckVolumes(tx))?;
@@ -1005,11 +1203,13 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     #[cfg(not(windows))]
-    async fn check_volumes(&self, _: Request<()>) -> ServiceResult<()> {
+    async fn check_volumes(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         Ok(Response::new(()))
     }
 
     async fn apply_json_settings(&self, blob: Request<String>) -> ServiceResult<()> {
+        self.authorize(&blob, Role::Admin)?;
         log::debug!("apply_json_settings");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ApplyJsonSettings(tx, blob.into_inner()))?;
@@ -1017,7 +1217,8 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
-    async fn export_json_settings(&self, _: Request<()>) -> ServiceResult<String> {
+    async fn export_json_settings(&self, request: Request<()>) -> ServiceResult<String> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("export_json_settings");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ExportJsonSettings(tx))?;
@@ -1028,8 +1229,9 @@ impl ManagementService for ManagementServiceImpl {
     #[cfg(target_os = "android")]
     async fn init_play_purchase(
         &self,
-        _request: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::PlayPurchasePaymentToken> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("init_play_purchase");
 
         let (tx, rx) = oneshot::channel();
@@ -1048,8 +1250,9 @@ impl ManagementService for ManagementServiceImpl {
     #[cfg(not(target_os = "android"))]
     async fn init_play_purchase(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::PlayPurchasePaymentToken> {
+        self.authorize(&request, Role::Admin)?;
         log::error!("Called `init_play_purchase` on non-Android platform");
         Ok(Response::new(types::PlayPurchasePaymentToken {
             token: String::default(),
@@ -1061,6 +1264,7 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::PlayPurchase>,
     ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("verify_play_purchase");
 
         let (tx, rx) = oneshot::channel();
@@ -1074,15 +1278,20 @@ impl ManagementService for ManagementServiceImpl {
     }
 
     #[cfg(not(target_os = "android"))]
-    async fn verify_play_purchase(&self, _: Request<types::PlayPurchase>) -> ServiceResult<()> {
+    async fn verify_play_purchase(
+        &self,
+        request: Request<types::PlayPurchase>,
+    ) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
         log::error!("Called `verify_play_purchase` on non-Android platform");
         Ok(Response::new(()))
     }
 
     async fn get_feature_indicators(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::FeatureIndicators> {
+        self.authorize(&request, Role::Admin)?;
         log::debug!("get_feature_indicators");
 
         let (tx, rx) = oneshot::channel();
@@ -1095,6 +1304,149 @@ impl ManagementService for ManagementServiceImpl {
 
         Ok(Response::new(feature_indicators))
     }
+
+    async fn get_leak_history(&self, request: Request<()>) -> ServiceResult<types::LeakHistory> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("get_leak_history");
+
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetLeakHistory(tx))?;
+
+        let history = self
+            .wait_for_result(rx)
+            .await
+            .map(types::LeakHistory::from)?;
+
+        Ok(Response::new(history))
+    }
+
+    async fn get_learned_obfuscation(
+        &self,
+        request: Request<()>,
+    ) -> ServiceResult<types::LearnedObfuscationTable> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("get_learned_obfuscation");
+
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetLearnedObfuscation(tx))?;
+
+        let table = self
+            .wait_for_result(rx)
+            .await
+            .map(types::LearnedObfuscationTable::from)?;
+
+        Ok(Response::new(table))
+    }
+
+    async fn clear_learned_obfuscation(&self, request: Request<()>) -> ServiceResult<()> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("clear_learned_obfuscation");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ClearLearnedObfuscation(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn run_leak_test(&self, request: Request<()>) -> ServiceResult<types::LeakTestReport> {
+        self.authorize(&request, Role::Admin)?;
+        log::debug!("run_leak_test");
+
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RunLeakTest(tx))?;
+
+        let report = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(types::LeakTestReport::from(report)))
+    }
+
+    async fn get_tunnel_statistics(
+        &self,
+        request: Request<()>,
+    ) -> ServiceResult<types::TunnelStatistics> {
+        self.authorize(&request, Role::ReadOnly)?;
+        log::debug!("get_tunnel_statistics");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetTunnelStatistics(tx))?;
+        let statistics = self
+            .wait_for_result(rx)
+            .await?
+            .ok_or_else(|| Status::failed_precondition("not connected to a WireGuard relay"))?;
+        Ok(Response::new(types::TunnelStatistics::from(statistics)))
+    }
+
+    async fn tunnel_statistics_listen(
+        &self,
+        request: Request<types::Duration>,
+    ) -> ServiceResult<Self::TunnelStatisticsListenStream> {
+        self.authorize(&request, Role::ReadOnly)?;
+        let interval = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("unexpected negative interval"))?;
+        if interval < MIN_TUNNEL_STATISTICS_INTERVAL {
+            return Err(Status::invalid_argument(format!(
+                "the interval must be at least {} ms",
+                MIN_TUNNEL_STATISTICS_INTERVAL.as_millis()
+            )));
+        }
+        log::debug!("tunnel_statistics_listen({:?})", interval);
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let daemon_tx = self.daemon_tx.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if tx.is_closed() {
+                    break;
+                }
+                let (result_tx, result_rx) = oneshot::channel();
+                if daemon_tx
+                    .send(DaemonCommand::GetTunnelStatistics(result_tx))
+                    .is_err()
+                {
+                    break;
+                }
+                let Ok(statistics) = result_rx.await else {
+                    break;
+                };
+                if let Some(statistics) = statistics {
+                    if tx
+                        .send(Ok(types::TunnelStatistics::from(statistics)))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn get_dns_blocklist_stats(
+        &self,
+        request: Request<()>,
+    ) -> ServiceResult<types::DnsBlocklistStats> {
+        self.authorize(&request, Role::ReadOnly)?;
+        log::debug!("get_dns_blocklist_stats");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetDnsBlocklistStats(tx))?;
+        let stats = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::DnsBlocklistStats::from(stats)))
+    }
+
+    #[cfg(target_os = "android")]
+    async fn get_dns_blocklist_stats(
+        &self,
+        request: Request<()>,
+    ) -> ServiceResult<types::DnsBlocklistStats> {
+        self.authorize(&request, Role::ReadOnly)?;
+        Err(Status::unimplemented(
+            "Local DNS blocklists are not supported on Android",
+        ))
+    }
 }
 
 impl ManagementServiceImpl {
@@ -1108,6 +1460,19 @@ impl ManagementServiceImpl {
     async fn wait_for_result<T>(&self, rx: oneshot::Receiver<T>) -> Result<T, Status> {
         rx.await.map_err(|_| Status::internal("sender was dropped"))
     }
+
+    /// Fails with `PERMISSION_DENIED` unless the caller of `request` has at least the `required`
+    /// role according to the access policy.
+    fn authorize<T>(&self, request: &Request<T>, required: Role) -> Result<(), Status> {
+        let peer = PeerCredentials::of(request);
+        if self.access_policy.role(peer.as_ref()) >= required {
+            return Ok(());
+        }
+        log::warn!("Denied management interface request from {peer:?}");
+        Err(Status::permission_denied(
+            "the caller is not allowed to perform this operation",
+        ))
+    }
 }
 
 /// The running management interface serving gRPC requests.
@@ -1127,6 +1492,7 @@ impl ManagementInterfaceServer {
     pub fn start(
         daemon_tx: DaemonCommandSender,
         rpc_socket_path: impl AsRef<Path>,
+        access_policy: AccessPolicy,
     ) -> Result<ManagementInterfaceServer, Error> {
         let subscriptions = Arc::<Mutex<Vec<EventsListenerSender>>>::default();
         // NOTE: It is important that the channel buffer size is kept at 0. When sending a signal
@@ -1136,6 +1502,7 @@ impl ManagementInterfaceServer {
         let server = ManagementServiceImpl {
             daemon_tx,
             subscriptions: subscriptions.clone(),
+            access_policy,
         };
         let rpc_server_join_handle = mullvad_management_interface::spawn_rpc_server(
             server,
@@ -1280,6 +1647,16 @@ impl ManagementInterfaceEventBroadcaster {
             )),
         })
     }
+
+    /// Notify that a network leak was detected.
+    pub(crate) fn notify_leak_detected(&self, report: mullvad_types::leak::LeakReport) {
+        log::debug!("Broadcasting leak detected event");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::LeakDetected(types::LeakReport::from(
+                report,
+            ))),
+        })
+    }
 }
 
 /// Converts [`crate::Error`] into a tonic status.
@@ -1297,13 +1674,14 @@ fn map_daemon_error(error: crate::Error) -> Status {
         DaemonError::RemoveDeviceError(error) => map_device_error(&error),
         DaemonError::UpdateDeviceError(error) => map_device_error(&error),
         DaemonError::VoucherSubmission(error) => map_device_error(&error),
-        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        #[cfg(not(target_os = "android"))]
         DaemonError::SplitTunnelError(error) => map_split_tunnel_error(error),
         DaemonError::AccountHistory(error) => map_account_history_error(error),
         DaemonError::NoAccountNumber | DaemonError::NoAccountNumberHistory => {
             Status::unauthenticated(error.to_string())
         }
         DaemonError::VersionCheckError(error) => map_version_check_error(error),
+        DaemonError::LeakTestNotConnected => Status::failed_precondition(error.to_string()),
         error => Status::unknown(error.to_string()),
     }
 }
@@ -1325,7 +1703,7 @@ fn map_split_tunnel_error(error: talpid_core::split_tunnel::Error) -> Status {
     }
 }
 
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
 /// Converts [`talpid_core::split_tunnel::Error`] into a tonic status.
 fn map_split_tunnel_error(error: talpid_core::split_tunnel::Error) -> Status {
     Status::unknown(error.to_string())
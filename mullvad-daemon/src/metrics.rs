@@ -0,0 +1,505 @@
+//! Opt-in OpenMetrics exporter.
+//!
+//! If `MULLVAD_METRICS_ADDRESS` is set, the daemon serves metrics over HTTP on that address,
+//! which must be a loopback address such as `127.0.0.1:9531`. On Unix based platforms, the
+//! address may also be a Unix domain socket given as `unix:/path/to/socket`. Every `GET /metrics`
+//! request is answered with a snapshot of the metrics in the OpenMetrics text format.
+
+use crate::{DaemonCommand, DaemonCommandSender};
+use futures::channel::oneshot;
+use mullvad_types::states::TunnelState;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::{collections::BTreeMap, fmt, io, net::SocketAddr, str::FromStr, time::Duration};
+use talpid_types::{
+    net::wireguard::TunnelStatistics,
+    tunnel::{ActionAfterDisconnect, ErrorStateCause},
+    ErrorExt,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+};
+
+const METRICS_ADDRESS_VAR: &str = "MULLVAD_METRICS_ADDRESS";
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Requests with a larger head than this are rejected.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TUNNEL_STATES: [&str; 5] = [
+    "disconnected",
+    "connecting",
+    "connected",
+    "disconnecting",
+    "error",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Invalid metrics address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Metrics may only be served on a loopback address, not {0}")]
+    NotLoopback(SocketAddr),
+
+    #[error("Failed to listen on metrics address")]
+    Listen(#[source] io::Error),
+}
+
+/// Where metrics are served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Endpoint {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for Endpoint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                return Err(Error::InvalidAddress(s.to_owned()));
+            }
+            return Ok(Endpoint::Unix(path));
+        }
+
+        let addr = SocketAddr::from_str(s).map_err(|_| Error::InvalidAddress(s.to_owned()))?;
+        if !addr.ip().is_loopback() {
+            return Err(Error::NotLoopback(addr));
+        }
+        Ok(Endpoint::Tcp(addr))
+    }
+}
+
+/// Start serving metrics if `MULLVAD_METRICS_ADDRESS` is set. Failing to do so is logged, but
+/// does not prevent the daemon from starting.
+pub async fn start_exporter(daemon_tx: DaemonCommandSender) {
+    let Ok(address) = std::env::var(METRICS_ADDRESS_VAR) else {
+        return;
+    };
+    if let Err(error) = start_exporter_inner(&address, daemon_tx).await {
+        log::error!(
+            "{}",
+            error.display_chain_with_msg("Failed to start metrics exporter")
+        );
+    }
+}
+
+async fn start_exporter_inner(address: &str, daemon_tx: DaemonCommandSender) -> Result<(), Error> {
+    match address.parse()? {
+        Endpoint::Tcp(addr) => {
+            let listener = TcpListener::bind(addr).await.map_err(Error::Listen)?;
+            log::info!("Serving metrics on http://{addr}/metrics");
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => spawn_connection(stream, daemon_tx.clone()),
+                        Err(error) => log_accept_error(error),
+                    }
+                }
+            });
+        }
+        #[cfg(unix)]
+        Endpoint::Unix(path) => {
+            use std::os::unix::fs::PermissionsExt;
+
+            let _ = tokio::fs::remove_file(&path).await;
+            let listener = tokio::net::UnixListener::bind(&path).map_err(Error::Listen)?;
+            tokio::fs::set_permissions(&path, PermissionsExt::from_mode(0o766))
+                .await
+                .map_err(Error::Listen)?;
+            log::info!("Serving metrics on {}", path.display());
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => spawn_connection(stream, daemon_tx.clone()),
+                        Err(error) => log_accept_error(error),
+                    }
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+fn log_accept_error(error: io::Error) {
+    log::error!(
+        "{}",
+        error.display_chain_with_msg("Failed to accept metrics connection")
+    );
+}
+
+fn spawn_connection<S>(stream: S, daemon_tx: DaemonCommandSender)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Err(error) = serve_connection(stream, &daemon_tx).await {
+            log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to serve metrics request")
+            );
+        }
+    });
+}
+
+async fn serve_connection<S>(mut stream: S, daemon_tx: &DaemonCommandSender) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let response = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => match get_metrics(daemon_tx).await {
+            Some(metrics) => response("200 OK", CONTENT_TYPE, &metrics.to_string()),
+            None => response(
+                "503 Service Unavailable",
+                "text/plain",
+                "Daemon unavailable\n",
+            ),
+        },
+        (Some("GET"), Some(_)) => response("404 Not Found", "text/plain", "Not found\n"),
+        _ => response(
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n",
+        ),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read the request line and headers of an HTTP request. The body, if any, is ignored.
+async fn read_request_head<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Request is too large",
+            ));
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8(head).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+async fn get_metrics(daemon_tx: &DaemonCommandSender) -> Option<Metrics> {
+    let (tx, rx) = oneshot::channel();
+    daemon_tx.send(DaemonCommand::GetMetrics(tx)).ok()?;
+    rx.await.ok()
+}
+
+/// Counters that are updated by the daemon as events occur.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Counters {
+    /// Number of times the error state was entered, by cause
+    tunnel_errors: BTreeMap<String, u64>,
+    reconnects: u64,
+    leaks_detected: u64,
+}
+
+impl Counters {
+    pub fn on_tunnel_state(&mut self, tunnel_state: &TunnelState) {
+        match tunnel_state {
+            TunnelState::Error(error_state) => {
+                *self
+                    .tunnel_errors
+                    .entry(error_cause_label(error_state.cause()))
+                    .or_default() += 1;
+            }
+            TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect) => {
+                self.reconnects += 1;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn on_leak_detected(&mut self) {
+        self.leaks_detected += 1;
+    }
+}
+
+/// Snapshot of all metrics exported by the daemon.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub tunnel_state: TunnelState,
+    pub counters: Counters,
+    /// Statistics for the current WireGuard tunnel, if any
+    pub statistics: Option<TunnelStatistics>,
+    pub api_availability: mullvad_api::availability::State,
+    /// Time since the WireGuard key was last rotated, if logged in
+    pub key_age: Option<Duration>,
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let current_state = tunnel_state_label(&self.tunnel_state);
+        family(
+            f,
+            "mullvad_tunnel_state",
+            "stateset",
+            "Current state of the tunnel.",
+        )?;
+        for state in TUNNEL_STATES {
+            writeln!(
+                f,
+                "mullvad_tunnel_state{{mullvad_tunnel_state=\"{state}\"}} {}",
+                u8::from(state == current_state)
+            )?;
+        }
+
+        family(
+            f,
+            "mullvad_tunnel_errors",
+            "counter",
+            "Number of times the tunnel entered the error state, by cause.",
+        )?;
+        for (cause, count) in &self.counters.tunnel_errors {
+            writeln!(
+                f,
+                "mullvad_tunnel_errors_total{{cause=\"{}\"}} {count}",
+                escape(cause)
+            )?;
+        }
+
+        family(
+            f,
+            "mullvad_tunnel_reconnects",
+            "counter",
+            "Number of times the tunnel was reconnected.",
+        )?;
+        writeln!(
+            f,
+            "mullvad_tunnel_reconnects_total {}",
+            self.counters.reconnects
+        )?;
+
+        family(
+            f,
+            "mullvad_relay",
+            "info",
+            "Relay that the tunnel is connecting or connected to.",
+        )?;
+        if let Some(hostname) = relay_hostname(&self.tunnel_state) {
+            writeln!(
+                f,
+                "mullvad_relay_info{{hostname=\"{}\"}} 1",
+                escape(hostname)
+            )?;
+        }
+
+        family(
+            f,
+            "mullvad_tunnel_transmitted_bytes",
+            "counter",
+            "Bytes sent through the current tunnel.",
+        )?;
+        writeln!(f, "# UNIT mullvad_tunnel_transmitted_bytes bytes")?;
+        family(
+            f,
+            "mullvad_tunnel_received_bytes",
+            "counter",
+            "Bytes received through the current tunnel.",
+        )?;
+        writeln!(f, "# UNIT mullvad_tunnel_received_bytes bytes")?;
+        if let Some(statistics) = &self.statistics {
+            let tx_bytes: u64 = statistics.peers.iter().map(|peer| peer.tx_bytes).sum();
+            let rx_bytes: u64 = statistics.peers.iter().map(|peer| peer.rx_bytes).sum();
+            writeln!(f, "mullvad_tunnel_transmitted_bytes_total {tx_bytes}")?;
+            writeln!(f, "mullvad_tunnel_received_bytes_total {rx_bytes}")?;
+        }
+
+        let api = &self.api_availability;
+        family(
+            f,
+            "mullvad_api_offline",
+            "gauge",
+            "Whether the API is considered unreachable because the host is offline.",
+        )?;
+        writeln!(f, "mullvad_api_offline {}", u8::from(api.is_offline()))?;
+        family(
+            f,
+            "mullvad_api_suspended",
+            "gauge",
+            "Whether all API requests are suspended.",
+        )?;
+        writeln!(f, "mullvad_api_suspended {}", u8::from(api.is_suspended()))?;
+        family(
+            f,
+            "mullvad_api_background_paused",
+            "gauge",
+            "Whether background API requests are paused.",
+        )?;
+        writeln!(
+            f,
+            "mullvad_api_background_paused {}",
+            u8::from(api.is_background_paused())
+        )?;
+
+        family(
+            f,
+            "mullvad_wireguard_key_age_seconds",
+            "gauge",
+            "Time since the WireGuard key was last rotated.",
+        )?;
+        writeln!(f, "# UNIT mullvad_wireguard_key_age_seconds seconds")?;
+        if let Some(key_age) = self.key_age {
+            writeln!(f, "mullvad_wireguard_key_age_seconds {}", key_age.as_secs())?;
+        }
+
+        family(
+            f,
+            "mullvad_leaks_detected",
+            "counter",
+            "Number of network leaks detected since the daemon started.",
+        )?;
+        writeln!(
+            f,
+            "mullvad_leaks_detected_total {}",
+            self.counters.leaks_detected
+        )?;
+
+        writeln!(f, "# EOF")
+    }
+}
+
+fn family(f: &mut fmt::Formatter<'_>, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(f, "# TYPE {name} {kind}")?;
+    writeln!(f, "# HELP {name} {help}")
+}
+
+fn tunnel_state_label(tunnel_state: &TunnelState) -> &'static str {
+    match tunnel_state {
+        TunnelState::Disconnected { .. } => "disconnected",
+        TunnelState::Connecting { .. } => "connecting",
+        TunnelState::Connected { .. } => "connected",
+        TunnelState::Disconnecting(_) => "disconnecting",
+        TunnelState::Error(_) => "error",
+    }
+}
+
+fn relay_hostname(tunnel_state: &TunnelState) -> Option<&str> {
+    match tunnel_state {
+        TunnelState::Connecting { location, .. } | TunnelState::Connected { location, .. } => {
+            location.as_ref()?.hostname.as_deref()
+        }
+        _ => None,
+    }
+}
+
+/// Return the same name for the cause as is used when serializing the tunnel state.
+fn error_cause_label(cause: &ErrorStateCause) -> String {
+    serde_json::to_value(cause)
+        .ok()
+        .and_then(|value| Some(value.get("reason")?.as_str()?.to_owned()))
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+/// Escape a label value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::tunnel::ErrorState;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            "127.0.0.1:9531".parse::<Endpoint>().unwrap(),
+            Endpoint::Tcp("127.0.0.1:9531".parse().unwrap())
+        );
+        assert_eq!(
+            "[::1]:9531".parse::<Endpoint>().unwrap(),
+            Endpoint::Tcp("[::1]:9531".parse().unwrap())
+        );
+        assert!(matches!(
+            "0.0.0.0:9531".parse::<Endpoint>(),
+            Err(Error::NotLoopback(_))
+        ));
+        assert!(matches!(
+            "localhost".parse::<Endpoint>(),
+            Err(Error::InvalidAddress(_))
+        ));
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                "unix:/run/mullvad-metrics".parse::<Endpoint>().unwrap(),
+                Endpoint::Unix(PathBuf::from("/run/mullvad-metrics"))
+            );
+            assert!("unix:relative".parse::<Endpoint>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_encode_metrics() {
+        let mut counters = Counters::default();
+        let error_state = TunnelState::Error(ErrorState::new(ErrorStateCause::IsOffline, None));
+        counters.on_tunnel_state(&error_state);
+        counters.on_tunnel_state(&error_state);
+        counters.on_tunnel_state(&TunnelState::Disconnecting(
+            ActionAfterDisconnect::Reconnect,
+        ));
+        counters.on_leak_detected();
+
+        let metrics = Metrics {
+            tunnel_state: error_state,
+            counters,
+            statistics: None,
+            api_availability: Default::default(),
+            key_age: Some(Duration::from_secs(3600)),
+        }
+        .to_string();
+
+        assert!(metrics.contains("mullvad_tunnel_state{mullvad_tunnel_state=\"error\"} 1\n"));
+        assert!(metrics.contains("mullvad_tunnel_state{mullvad_tunnel_state=\"connected\"} 0\n"));
+        assert!(metrics.contains("mullvad_tunnel_errors_total{cause=\"is_offline\"} 2\n"));
+        assert!(metrics.contains("mullvad_tunnel_reconnects_total 1\n"));
+        assert!(metrics.contains("mullvad_leaks_detected_total 1\n"));
+        assert!(metrics.contains("mullvad_wireguard_key_age_seconds 3600\n"));
+        assert!(!metrics.contains("mullvad_relay_info"));
+        assert!(metrics.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn test_escape_label_value() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,123 @@
+//! Measures the latency to relays, so that the relay selector can pick among the fastest ones
+//! when the `fastest` selection mode is used.
+//!
+//! Relays are measured in the background, and never while connecting, since the firewall only
+//! lets traffic through to the relay that is being connected to.
+
+use mullvad_relay_selector::{latency::LatencyProber, RelaySelector, RuntimeParameters};
+use mullvad_types::relay_list::Relay;
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpStream, task::JoinHandle};
+
+/// Port that is probed on every relay. OpenVPN relays accept TCP connections on it, and so do
+/// WireGuard relays, for UDP-over-TCP.
+const PROBE_PORT: u16 = 443;
+
+/// How long to wait for a relay to accept a connection.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often to measure relays again while measuring in the background. Only relays without a
+/// valid cached measurement are probed.
+const MEASUREMENT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Measures the round-trip time to a relay by timing a TCP handshake with it.
+///
+/// Traffic to relays is blocked by the firewall in the blocked and error states, so relays
+/// cannot be probed then. They are simply considered unreachable.
+pub struct TcpHandshakeProber;
+
+impl LatencyProber for TcpHandshakeProber {
+    fn probe(&self, relay: &Relay) -> Pin<Box<dyn Future<Output = Option<Duration>> + Send>> {
+        let addr = SocketAddr::from((relay.ipv4_addr_in, PROBE_PORT));
+        Box::pin(async move {
+            let start = Instant::now();
+            match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+                Ok(Ok(_stream)) => Some(start.elapsed()),
+                Ok(Err(error)) => {
+                    log::trace!("Failed to connect to {addr}: {error}");
+                    None
+                }
+                Err(_) => {
+                    log::trace!("Timed out connecting to {addr}");
+                    None
+                }
+            }
+        })
+    }
+}
+
+/// Periodically measures the latency to the relays that may be selected, in the background.
+#[derive(Clone)]
+pub struct LatencyMeasurer {
+    relay_selector: RelaySelector,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl LatencyMeasurer {
+    pub fn new(relay_selector: RelaySelector) -> Self {
+        LatencyMeasurer {
+            relay_selector,
+            task: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Start measuring relays periodically, unless that is already being done. This should only
+    /// be done while the firewall does not block traffic to the relays.
+    pub fn start(&self) {
+        let mut task = self.task.lock().unwrap();
+        if task.is_none() {
+            *task = Some(self.spawn());
+        }
+    }
+
+    /// Stop measuring relays. Cached measurements are kept.
+    pub fn stop(&self) {
+        if let Some(task) = self.task.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+
+    /// Measure again right away if relays are being measured, e.g. because the relays that may be
+    /// selected have changed.
+    pub fn restart(&self) {
+        let mut task = self.task.lock().unwrap();
+        if let Some(running_task) = task.take() {
+            running_task.abort();
+            *task = Some(self.spawn());
+        }
+    }
+
+    /// Forget all cached measurements, e.g. because the network changed. If relays are being
+    /// measured, they are measured again right away.
+    pub fn reset(&self) {
+        let mut task = self.task.lock().unwrap();
+        let running_task = task.take();
+        if let Some(running_task) = &running_task {
+            running_task.abort();
+        }
+        self.relay_selector.clear_latencies();
+        if running_task.is_some() {
+            *task = Some(self.spawn());
+        }
+    }
+
+    fn spawn(&self) -> JoinHandle<()> {
+        let relay_selector = self.relay_selector.clone();
+        tokio::spawn(async move {
+            loop {
+                // Relays are always probed over IPv4, so only consider relays that can be used
+                // without IPv6
+                relay_selector
+                    .measure_latencies(0, RuntimeParameters { ipv6: false })
+                    .await;
+                tokio::time::sleep(MEASUREMENT_INTERVAL).await;
+            }
+        })
+    }
+}
@@ -1,5 +1,6 @@
 use futures::TryFutureExt;
 use mullvad_types::{
+    custom_endpoint::Error as CustomEndpointError,
     custom_list::Error as CustomListError,
     relay_constraints::{RelayConstraints, RelaySettings, WireguardConstraints},
     settings::{DnsState, Settings},
@@ -57,6 +58,10 @@ impl From<Error> for mullvad_management_interface::Status {
                 let custom_list_err = *err.downcast::<CustomListError>().unwrap();
                 handle_custom_list_error(custom_list_err)
             }
+            Error::UpdateFailed(err) if err.downcast_ref::<CustomEndpointError>().is_some() => {
+                let custom_endpoint_err = *err.downcast::<CustomEndpointError>().unwrap();
+                handle_custom_endpoint_error(custom_endpoint_err)
+            }
             Error::SerializeError(..) | Error::ParseError(..) | Error::UpdateFailed(..) => {
                 Status::new(Code::Internal, error.to_string())
             }
@@ -89,6 +94,32 @@ fn handle_custom_list_error(
     }
 }
 
+fn handle_custom_endpoint_error(
+    custom_endpoint_err: CustomEndpointError,
+) -> mullvad_management_interface::Status {
+    use mullvad_management_interface::{Code, Status};
+    match custom_endpoint_err {
+        error @ CustomEndpointError::EndpointExists
+        | error @ CustomEndpointError::DuplicateName => Status::with_details(
+            Code::AlreadyExists,
+            error.to_string(),
+            mullvad_management_interface::CUSTOM_ENDPOINT_EXISTS_DETAILS.into(),
+        ),
+        error @ CustomEndpointError::NameTooLong
+        | error @ CustomEndpointError::NoEndpointSelected => {
+            Status::invalid_argument(error.to_string())
+        }
+        error @ CustomEndpointError::EndpointNotFound => Status::with_details(
+            Code::NotFound,
+            error.to_string(),
+            mullvad_management_interface::CUSTOM_ENDPOINT_NOT_FOUND_DETAILS.into(),
+        ),
+        error @ CustomEndpointError::EndpointInUse => {
+            Status::failed_precondition(error.to_string())
+        }
+    }
+}
+
 pub struct SettingsPersister {
     settings: Settings,
     path: PathBuf,
@@ -605,6 +636,31 @@ mod test {
         let _ = SettingsPersister::load_from_bytes(settings).unwrap();
     }
 
+    /// Custom LAN networks that are not local should be dropped when loading the settings,
+    /// instead of discarding the whole settings file.
+    #[test]
+    #[cfg(not(any(windows, target_os = "android")))]
+    fn test_deserialize_invalid_allowed_lan_nets() {
+        let mut settings = serde_json::to_value(SettingsPersister::default_settings()).unwrap();
+        settings["auto_connect"] = serde_json::json!(true);
+        settings["allowed_lan_nets"] = serde_json::json!({
+            "custom_nets": ["100.64.0.0/10", "8.8.8.0/24"],
+            "replace_default": true,
+        });
+
+        let settings = SettingsPersister::load_from_bytes(settings.to_string().as_bytes()).unwrap();
+
+        let custom_nets: Vec<_> = settings
+            .allowed_lan_nets
+            .custom_nets()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert!(settings.auto_connect);
+        assert_eq!(custom_nets, ["100.64.0.0/10"]);
+        assert!(settings.allowed_lan_nets.replace_default());
+    }
+
     /// The [`SettingsPersister`] should always succeed when deserializing a
     /// [`Settings`] object from disk. However, there is a distinction between
     /// different error cases.
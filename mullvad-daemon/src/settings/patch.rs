@@ -1,6 +1,12 @@
 //! This module provides functionality for updating settings using a JSON string, i.e. applying a
-//! patch. It is intended to be relatively safe, preventing editing of "dangerous" settings such as
-//! custom DNS.
+//! patch, and for exporting the settings that can be patched. It is intended to be relatively safe,
+//! preventing editing of "dangerous" settings such as custom DNS.
+//!
+//! Patches may contain relay overrides, custom lists, obfuscation settings, some WireGuard tunnel
+//! options, the DNS content blockers, "Local network sharing" and its networks, and
+//! "Auto-connect". An exported patch
+//! contains all of these settings, except for relay overrides and custom lists when there are none,
+//! and can be applied as is.
 //!
 //! Patching the settings is a three-step procedure:
 //! 1. Validating the input. Only a subset of settings is allowed to be edited using this method.
@@ -10,13 +16,22 @@
 //! 3. Deserialize the resulting JSON back to a [Settings] instance, and, if valid, replace the
 //!    existing settings.
 //!
-//! Permitted settings and merge strategies are defined in the [PERMITTED_SUBKEYS] constant.
+//! Permitted settings, merge strategies and additional validation are defined in the
+//! [PERMITTED_SUBKEYS] constant.
 //!
 //! This implementation must be kept in sync with the
 //! [spec](../../../docs/settings-patch-format.md).
 
 use super::SettingsPersister;
-use mullvad_types::settings::Settings;
+use mullvad_types::{
+    custom_list::CustomList,
+    relay_constraints::{RelayOverride, SelectedObfuscation},
+    settings::Settings,
+};
+use std::ops::RangeInclusive;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::AllowedLanNets;
+use talpid_types::net::{obfuscation::WebSocketSettings, proxy::Shadowsocks};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -76,9 +91,14 @@ enum MergeStrategy {
 // TODO: Use Default trait when `const_trait_impl`` is available.
 const DEFAULT_MERGE_STRATEGY: MergeStrategy = MergeStrategy::Replace;
 
+/// Function used to validate the value of a key in a patch, in addition to checking that only
+/// permitted subkeys are present.
+type Validator = fn(&serde_json::Value) -> Result<(), Error>;
+
 struct PermittedKey {
     key_type: PermittedKeyValue,
     merge_strategy: MergeStrategy,
+    validator: Option<Validator>,
 }
 
 impl PermittedKey {
@@ -86,6 +106,7 @@ impl PermittedKey {
         Self {
             key_type: PermittedKeyValue::Object(keys),
             merge_strategy: DEFAULT_MERGE_STRATEGY,
+            validator: None,
         }
     }
 
@@ -93,6 +114,7 @@ impl PermittedKey {
         Self {
             key_type: PermittedKeyValue::Array(key),
             merge_strategy: DEFAULT_MERGE_STRATEGY,
+            validator: None,
         }
     }
 
@@ -100,6 +122,7 @@ impl PermittedKey {
         Self {
             key_type: PermittedKeyValue::Any,
             merge_strategy: DEFAULT_MERGE_STRATEGY,
+            validator: None,
         }
     }
 
@@ -107,6 +130,11 @@ impl PermittedKey {
         self.merge_strategy = merge_strategy;
         self
     }
+
+    const fn validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
 }
 
 enum PermittedKeyValue {
@@ -118,15 +146,124 @@ enum PermittedKeyValue {
     Any,
 }
 
-const PERMITTED_SUBKEYS: &PermittedKey = &PermittedKey::object(&[(
-    "relay_overrides",
-    PermittedKey::array(&PermittedKey::object(&[
-        ("hostname", PermittedKey::any()),
-        ("ipv4_addr_in", PermittedKey::any()),
-        ("ipv6_addr_in", PermittedKey::any()),
-    ]))
-    .merge_strategy(MergeStrategy::Custom(merge_relay_overrides)),
-)]);
+const PERMITTED_SUBKEYS: &PermittedKey = &PermittedKey::object(&[
+    (
+        "relay_overrides",
+        PermittedKey::array(&PermittedKey::object(&[
+            ("hostname", PermittedKey::any()),
+            ("ipv4_addr_in", PermittedKey::any()),
+            ("ipv6_addr_in", PermittedKey::any()),
+        ]))
+        .merge_strategy(MergeStrategy::Custom(merge_relay_overrides)),
+    ),
+    (
+        "custom_lists",
+        PermittedKey::array(
+            &PermittedKey::object(&[
+                ("name", PermittedKey::any()),
+                ("locations", PermittedKey::any()),
+            ])
+            .validator(validate_custom_list),
+        )
+        .merge_strategy(MergeStrategy::Custom(merge_custom_lists)),
+    ),
+    ("obfuscation_settings", OBFUSCATION_SETTINGS),
+    ("tunnel_options", TUNNEL_OPTIONS),
+    ("allow_lan", PermittedKey::any().validator(validate_bool)),
+    #[cfg(not(target_os = "android"))]
+    (
+        "allowed_lan_nets",
+        PermittedKey::object(&[
+            ("custom_nets", PermittedKey::any()),
+            (
+                "replace_default",
+                PermittedKey::any().validator(validate_bool),
+            ),
+        ])
+        .validator(validate_allowed_lan_nets),
+    ),
+    ("auto_connect", PermittedKey::any().validator(validate_bool)),
+]);
+
+const OBFUSCATION_SETTINGS: PermittedKey = PermittedKey::object(&[
+    (
+        "selected_obfuscation",
+        PermittedKey::any().validator(validate_selected_obfuscation),
+    ),
+    (
+        "udp2tcp",
+        PermittedKey::object(&[
+            ("port", PermittedKey::any()),
+            (
+                "websocket",
+                PermittedKey::any().validator(validate_websocket),
+            ),
+        ]),
+    ),
+    (
+        "shadowsocks",
+        PermittedKey::object(&[("port", PermittedKey::any())]),
+    ),
+    (
+        "custom_shadowsocks",
+        PermittedKey::any().validator(validate_custom_shadowsocks),
+    ),
+]);
+
+/// Only tunnel options that cannot weaken the security of the tunnel are permitted. For example,
+/// custom DNS servers may not be set, but the DNS content blockers may be toggled.
+const TUNNEL_OPTIONS: PermittedKey = PermittedKey::object(&[
+    (
+        "wireguard",
+        PermittedKey::object(&[
+            ("mtu", PermittedKey::any().validator(validate_wireguard_mtu)),
+            ("quantum_resistant", PermittedKey::any()),
+            (
+                "daita",
+                PermittedKey::object(&[
+                    ("enabled", PermittedKey::any().validator(validate_bool)),
+                    (
+                        "use_multihop_if_necessary",
+                        PermittedKey::any().validator(validate_bool),
+                    ),
+                ]),
+            ),
+        ]),
+    ),
+    (
+        "dns_options",
+        PermittedKey::object(&[(
+            "default_options",
+            PermittedKey::object(&[
+                ("block_ads", PermittedKey::any().validator(validate_bool)),
+                (
+                    "block_trackers",
+                    PermittedKey::any().validator(validate_bool),
+                ),
+                (
+                    "block_malware",
+                    PermittedKey::any().validator(validate_bool),
+                ),
+                (
+                    "block_adult_content",
+                    PermittedKey::any().validator(validate_bool),
+                ),
+                (
+                    "block_gambling",
+                    PermittedKey::any().validator(validate_bool),
+                ),
+                (
+                    "block_social_media",
+                    PermittedKey::any().validator(validate_bool),
+                ),
+            ]),
+        )]),
+    ),
+]);
+
+/// Valid WireGuard MTUs. This matches the range accepted by the GUI.
+const WIREGUARD_MTU_RANGE: RangeInclusive<u64> = 1280..=1420;
+
 /// Prohibit stack overflow via excessive recursion. It might be possible to forgo this when
 /// tail-call optimization can be enforced?
 const RECURSE_LIMIT: usize = 15;
@@ -139,9 +276,54 @@ pub fn export_settings(settings: &Settings) -> Result<String, Error> {
 
 fn export_settings_inner(settings: &Settings) -> Result<serde_json::Value, Error> {
     let mut out = serde_json::Map::new();
+
+    let overrides = export_relay_overrides(&settings.relay_overrides)?;
+    if !overrides.is_empty() {
+        out.insert(
+            "relay_overrides".to_owned(),
+            serde_json::Value::Array(overrides),
+        );
+    }
+
+    let custom_lists = export_custom_lists(&settings.custom_lists)?;
+    if !custom_lists.is_empty() {
+        out.insert(
+            "custom_lists".to_owned(),
+            serde_json::Value::Array(custom_lists),
+        );
+    }
+
+    out.insert(
+        "obfuscation_settings".to_owned(),
+        export_value(&OBFUSCATION_SETTINGS, &settings.obfuscation_settings)?,
+    );
+    out.insert(
+        "tunnel_options".to_owned(),
+        export_value(&TUNNEL_OPTIONS, &settings.tunnel_options)?,
+    );
+    out.insert(
+        "allow_lan".to_owned(),
+        serde_json::Value::Bool(settings.allow_lan),
+    );
+    #[cfg(not(target_os = "android"))]
+    out.insert(
+        "allowed_lan_nets".to_owned(),
+        serde_json::to_value(&settings.allowed_lan_nets).map_err(Error::SerializeValue)?,
+    );
+    out.insert(
+        "auto_connect".to_owned(),
+        serde_json::Value::Bool(settings.auto_connect),
+    );
+
+    Ok(serde_json::Value::Object(out))
+}
+
+fn export_relay_overrides(
+    relay_overrides: &[RelayOverride],
+) -> Result<Vec<serde_json::Value>, Error> {
     let mut overrides = vec![];
 
-    for relay_override in &settings.relay_overrides {
+    for relay_override in relay_overrides {
         let mut relay_override =
             serde_json::to_value(relay_override).map_err(Error::SerializeValue)?;
         if let Some(relay_overrides) = relay_override.as_object_mut() {
@@ -155,14 +337,64 @@ fn export_settings_inner(settings: &Settings) -> Result<serde_json::Value, Error
         overrides.push(relay_override);
     }
 
-    if !overrides.is_empty() {
-        out.insert(
-            "relay_overrides".to_owned(),
-            serde_json::Value::Array(overrides),
-        );
-    }
+    Ok(overrides)
+}
 
-    Ok(serde_json::Value::Object(out))
+/// Export custom lists without their IDs, since these are specific to each device. Lists are
+/// identified by their names when the patch is applied.
+fn export_custom_lists(custom_lists: &[CustomList]) -> Result<Vec<serde_json::Value>, Error> {
+    custom_lists
+        .iter()
+        .map(|list| {
+            let locations = serde_json::to_value(&list.locations).map_err(Error::SerializeValue)?;
+            let mut list_value = serde_json::Map::new();
+            list_value.insert(
+                "name".to_owned(),
+                serde_json::Value::String(list.name.clone()),
+            );
+            list_value.insert("locations".to_owned(), locations);
+            Ok(serde_json::Value::Object(list_value))
+        })
+        .collect()
+}
+
+/// Serialize `value` and remove anything not permitted by `permitted_key`.
+fn export_value(
+    permitted_key: &'static PermittedKey,
+    value: &impl serde::Serialize,
+) -> Result<serde_json::Value, Error> {
+    let value = serde_json::to_value(value).map_err(Error::SerializeValue)?;
+    Ok(prune_to_permitted(permitted_key, value))
+}
+
+/// Remove all keys from `value` that are not permitted by `permitted_key`.
+fn prune_to_permitted(
+    permitted_key: &'static PermittedKey,
+    value: serde_json::Value,
+) -> serde_json::Value {
+    match (&permitted_key.key_type, value) {
+        (PermittedKeyValue::Object(subkeys), serde_json::Value::Object(map)) => {
+            let map = map
+                .into_iter()
+                .filter_map(|(k, v)| {
+                    let (_, subkey) = subkeys
+                        .iter()
+                        .find(|(permitted_key, _)| k == *permitted_key)?;
+                    Some((k, prune_to_permitted(subkey, v)))
+                })
+                .collect();
+            serde_json::Value::Object(map)
+        }
+        (PermittedKeyValue::Array(subkey), serde_json::Value::Array(values)) => {
+            serde_json::Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| prune_to_permitted(subkey, v))
+                    .collect(),
+            )
+        }
+        (_, value) => value,
+    }
 }
 
 /// Update the settings with the supplied patch. Only settings specified in `PERMITTED_SUBKEYS` can
@@ -259,6 +491,161 @@ fn merge_relay_overrides(
     Ok(serde_json::Value::Array(new_array))
 }
 
+/// Replace the locations of existing custom lists with a matching name. Lists with names that do
+/// not exist are added.
+///
+/// Note that custom lists are stored as `{ "custom_lists": [ ... ] }` in the settings, but the
+/// patch only contains the array.
+fn merge_custom_lists(
+    current_settings: &serde_json::Value,
+    patch: &serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    let mut current_settings = current_settings.clone();
+    if current_settings.is_null() {
+        current_settings = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let current_obj = current_settings
+        .as_object_mut()
+        .ok_or(Error::InvalidOrMissingValue(
+            "existing custom lists should be an object",
+        ))?;
+    let current_array = current_obj
+        .entry("custom_lists")
+        .or_insert(serde_json::Value::Array(vec![]))
+        .as_array_mut()
+        .ok_or(Error::InvalidOrMissingValue(
+            "existing custom lists should be an array",
+        ))?;
+
+    let patch_array = patch
+        .as_array()
+        .ok_or(Error::InvalidOrMissingValue("custom lists must be array"))?;
+
+    for patch_list in patch_array {
+        let name = patch_list
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or(Error::InvalidOrMissingValue("custom list name"))?;
+        let locations = patch_list
+            .get("locations")
+            .ok_or(Error::InvalidOrMissingValue("custom list locations"))?;
+
+        let existing_list = current_array.iter_mut().find(|value| {
+            value
+                .get("name")
+                .map(|existing_name| existing_name.as_str() == Some(name))
+                .unwrap_or(false)
+        });
+
+        match existing_list {
+            Some(serde_json::Value::Object(existing_list)) => {
+                existing_list.insert("locations".to_owned(), locations.to_owned());
+            }
+            Some(_) => {
+                return Err(Error::InvalidOrMissingValue(
+                    "all custom lists must be objects",
+                ));
+            }
+            None => {
+                let new_list = CustomList::new(name.to_owned())
+                    .map_err(|_| Error::InvalidOrMissingValue("custom list name"))?;
+                let mut new_list = serde_json::to_value(new_list).map_err(Error::SerializeValue)?;
+                new_list["locations"] = locations.to_owned();
+                current_array.push(new_list);
+            }
+        }
+    }
+
+    Ok(current_settings)
+}
+
+fn validate_bool(value: &serde_json::Value) -> Result<(), Error> {
+    if !value.is_boolean() {
+        return Err(Error::InvalidOrMissingValue("expected boolean"));
+    }
+    Ok(())
+}
+
+/// The MTU must either be `null` (automatic) or within [WIREGUARD_MTU_RANGE].
+fn validate_wireguard_mtu(value: &serde_json::Value) -> Result<(), Error> {
+    if value.is_null() {
+        return Ok(());
+    }
+    match value.as_u64() {
+        Some(mtu) if WIREGUARD_MTU_RANGE.contains(&mtu) => Ok(()),
+        _ => Err(Error::InvalidOrMissingValue(
+            "WireGuard MTU must be null or between 1280 and 1420",
+        )),
+    }
+}
+
+/// The obfuscation mode must be one of the modes supported by [SelectedObfuscation], such as
+/// `quic`.
+fn validate_selected_obfuscation(value: &serde_json::Value) -> Result<(), Error> {
+    serde_json::from_value::<SelectedObfuscation>(value.clone())
+        .map(|_| ())
+        .map_err(|_| Error::InvalidOrMissingValue("obfuscation mode"))
+}
+
+/// The WebSocket settings of udp2tcp must either be `null` or have a valid server name and path.
+fn validate_websocket(value: &serde_json::Value) -> Result<(), Error> {
+    let invalid = || Error::InvalidOrMissingValue("udp2tcp WebSocket settings");
+    let websocket: Option<WebSocketSettings> =
+        serde_json::from_value(value.clone()).map_err(|_| invalid())?;
+    match websocket {
+        Some(websocket) if !websocket.is_valid() => Err(invalid()),
+        _ => Ok(()),
+    }
+}
+
+/// The self-hosted Shadowsocks server must either be `null` or use a cipher supported by the
+/// obfuscator.
+fn validate_custom_shadowsocks(value: &serde_json::Value) -> Result<(), Error> {
+    let invalid = || Error::InvalidOrMissingValue("self-hosted shadowsocks server");
+    let server: Option<Shadowsocks> =
+        serde_json::from_value(value.clone()).map_err(|_| invalid())?;
+    match server {
+        Some(server) => server.validate_for_obfuscation().map_err(|_| invalid()),
+        None => Ok(()),
+    }
+}
+
+/// Custom LAN networks must be accepted by [AllowedLanNets::new]. Since invalid networks are
+/// silently dropped when the settings are deserialized, they must be rejected here.
+#[cfg(not(target_os = "android"))]
+fn validate_allowed_lan_nets(value: &serde_json::Value) -> Result<(), Error> {
+    let invalid = || {
+        Error::InvalidOrMissingValue(
+            "custom LAN networks must be private, shared, link-local or unique local networks",
+        )
+    };
+    let custom_nets = match value.get("custom_nets") {
+        Some(custom_nets) => serde_json::from_value(custom_nets.clone()).map_err(|_| invalid())?,
+        None => vec![],
+    };
+    let replace_default = value
+        .get("replace_default")
+        .and_then(|replace_default| replace_default.as_bool())
+        .unwrap_or(false);
+    AllowedLanNets::new(custom_nets, replace_default)
+        .map(|_| ())
+        .map_err(|_| invalid())
+}
+
+/// Custom lists must have a name and a list of locations.
+fn validate_custom_list(value: &serde_json::Value) -> Result<(), Error> {
+    if !value.get("name").is_some_and(|name| name.is_string()) {
+        return Err(Error::InvalidOrMissingValue("custom list name"));
+    }
+    if !value
+        .get("locations")
+        .is_some_and(|locations| locations.is_array())
+    {
+        return Err(Error::InvalidOrMissingValue("custom list locations"));
+    }
+    Ok(())
+}
+
 fn merge_patch_to_value(
     permitted_key: &'static PermittedKey,
     current_value: &mut serde_json::Value,
@@ -317,6 +704,10 @@ fn validate_patch_value(
         return Err(Error::RecursionLimit);
     }
 
+    if let Some(validator) = permitted_key.validator {
+        validator(json_value)?;
+    }
+
     match permitted_key.key_type {
         PermittedKeyValue::Object(subkeys) => {
             let map = json_value.as_object().ok_or(Error::InvalidOrMissingValue(
@@ -452,12 +843,22 @@ fn test_valid_patch_files() {
     const OVERRIDE_PATCH: &str =
         include_str!("../../../docs/patch-examples/override-relay-ips.json");
 
+    const CENSORSHIP_PATCH: &str =
+        include_str!("../../../docs/patch-examples/censorship-circumvention.json");
+    const WORKSTATION_PATCH: &str =
+        include_str!("../../../docs/patch-examples/workstation-provisioning.json");
+
     let prev_settings = Settings::default();
     let _ = merge_validate_patch_inner(&prev_settings, OVERRIDE_PATCH)
         .expect("failed to apply relay overrides");
+    let _ = merge_validate_patch_inner(&prev_settings, CENSORSHIP_PATCH)
+        .expect("failed to apply censorship circumvention patch");
+    let _ = merge_validate_patch_inner(&prev_settings, WORKSTATION_PATCH)
+        .expect("failed to apply workstation provisioning patch");
 }
 
 #[test]
+#[cfg(not(target_os = "android"))]
 fn test_patch_export() {
     use mullvad_types::relay_constraints::RelayOverride;
 
@@ -470,12 +871,259 @@ fn test_patch_export() {
 
     let exported = export_settings_inner(&settings).expect("patch export failed");
 
-    let expected = r#"{ "relay_overrides": [ { "hostname": "test", "ipv4_addr_in": "1.2.3.4", "ipv6_addr_in": "::1" } ] }"#;
+    let expected = r#"{
+        "relay_overrides": [ { "hostname": "test", "ipv4_addr_in": "1.2.3.4", "ipv6_addr_in": "::1" } ],
+        "obfuscation_settings": {
+            "selected_obfuscation": "auto",
+            "udp2tcp": { "port": "any", "websocket": null },
+            "shadowsocks": { "port": "any" },
+            "custom_shadowsocks": null
+        },
+        "tunnel_options": {
+            "wireguard": {
+                "mtu": null,
+                "quantum_resistant": "auto",
+                "daita": { "enabled": false, "use_multihop_if_necessary": true }
+            },
+            "dns_options": {
+                "default_options": {
+                    "block_ads": false,
+                    "block_trackers": false,
+                    "block_malware": false,
+                    "block_adult_content": false,
+                    "block_gambling": false,
+                    "block_social_media": false
+                }
+            }
+        },
+        "allow_lan": false,
+        "allowed_lan_nets": { "custom_nets": [], "replace_default": false },
+        "auto_connect": false
+    }"#;
     let expected: serde_json::Value = serde_json::from_str(expected).unwrap();
 
     assert_eq!(exported, expected);
 }
 
+/// Applying an exported patch to the default settings should reproduce all exported settings
+#[test]
+fn test_patch_export_roundtrip() {
+    use mullvad_types::{
+        relay_constraints::{GeographicLocationConstraint, SelectedObfuscation},
+        wireguard::QuantumResistantState,
+    };
+
+    let mut settings = Settings::default();
+    settings.allow_lan = true;
+    settings.auto_connect = true;
+    settings.obfuscation_settings.selected_obfuscation = SelectedObfuscation::Shadowsocks;
+    settings.tunnel_options.wireguard.mtu = Some(1300);
+    settings.tunnel_options.wireguard.quantum_resistant = QuantumResistantState::On;
+    settings
+        .tunnel_options
+        .dns_options
+        .default_options
+        .block_ads = true;
+    // Custom DNS must never be exported
+    settings.tunnel_options.dns_options.custom_options.addresses = vec!["1.1.1.1".parse().unwrap()];
+    let mut custom_list = CustomList::new("test".to_owned()).unwrap();
+    custom_list
+        .locations
+        .insert(GeographicLocationConstraint::country("se"));
+    settings.custom_lists.add(custom_list).unwrap();
+
+    let exported = export_settings(&settings).expect("patch export failed");
+    assert!(!exported.contains("custom_options"));
+
+    let imported = merge_validate_patch_inner(&Settings::default(), &exported)
+        .expect("failed to apply exported patch");
+
+    assert!(imported.allow_lan);
+    assert!(imported.auto_connect);
+    assert_eq!(imported.obfuscation_settings, settings.obfuscation_settings);
+    assert_eq!(
+        imported.tunnel_options.wireguard,
+        settings.tunnel_options.wireguard
+    );
+    assert_eq!(
+        imported.tunnel_options.dns_options.default_options,
+        settings.tunnel_options.dns_options.default_options
+    );
+    assert!(imported
+        .tunnel_options
+        .dns_options
+        .custom_options
+        .addresses
+        .is_empty());
+    assert_eq!(imported.custom_lists.len(), 1);
+    assert_eq!(imported.custom_lists[0].name, "test");
+    assert_eq!(
+        imported.custom_lists[0].locations,
+        settings.custom_lists[0].locations
+    );
+}
+
+#[test]
+fn test_patch_tunnel_options() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "tunnel_options": { "wireguard": { "mtu": 1300 } } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    assert_eq!(new_settings.tunnel_options.wireguard.mtu, Some(1300));
+    // Other tunnel options should be unaffected
+    assert_eq!(
+        new_settings.tunnel_options.wireguard.quantum_resistant,
+        settings.tunnel_options.wireguard.quantum_resistant
+    );
+
+    // MTU out of range
+    let patch = r#"{ "tunnel_options": { "wireguard": { "mtu": 9000 } } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+
+    // Custom DNS is prohibited
+    let patch = r#"{ "tunnel_options": { "dns_options": { "state": "custom" } } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+
+    // Content blockers must be booleans
+    let patch =
+        r#"{ "tunnel_options": { "dns_options": { "default_options": { "block_ads": 1 } } } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+}
+
+#[test]
+fn test_patch_custom_shadowsocks() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "obfuscation_settings": { "custom_shadowsocks": {
+        "endpoint": "192.0.2.1:443", "password": "mullvad", "cipher": "aes-256-gcm"
+    } } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    let server = new_settings
+        .obfuscation_settings
+        .custom_shadowsocks
+        .unwrap();
+    assert_eq!(server.endpoint, "192.0.2.1:443".parse().unwrap());
+
+    let patch = r#"{ "obfuscation_settings": { "custom_shadowsocks": null } }"#;
+    let new_settings = merge_validate_patch_inner(&new_settings, patch).unwrap();
+    assert_eq!(new_settings.obfuscation_settings.custom_shadowsocks, None);
+
+    // Stream ciphers are not supported by the obfuscator
+    let patch = r#"{ "obfuscation_settings": { "custom_shadowsocks": {
+        "endpoint": "192.0.2.1:443", "password": "mullvad", "cipher": "aes-256-cfb"
+    } } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+}
+
+#[test]
+fn test_patch_selected_obfuscation() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "obfuscation_settings": { "selected_obfuscation": "quic" } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    assert_eq!(
+        new_settings.obfuscation_settings.selected_obfuscation,
+        SelectedObfuscation::Quic
+    );
+
+    let patch = r#"{ "obfuscation_settings": { "selected_obfuscation": "http3" } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+}
+
+#[test]
+#[cfg(not(any(windows, target_os = "android")))]
+fn test_patch_allowed_lan_nets() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "allowed_lan_nets": { "custom_nets": ["100.64.0.0/10", "fd00::/8"] } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    assert_eq!(new_settings.allowed_lan_nets.custom_nets().len(), 2);
+    assert!(!new_settings.allowed_lan_nets.replace_default());
+
+    let patch = r#"{ "allowed_lan_nets": { "custom_nets": ["10.0.0.0/24", "8.8.8.0/24"] } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+
+    let patch = r#"{ "allowed_lan_nets": { "custom_nets": ["not a network"] } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+}
+
+#[test]
+fn test_patch_udp2tcp_websocket() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "obfuscation_settings": { "udp2tcp": {
+        "websocket": { "server_name": "example.com", "path": "/ws" }
+    } } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    assert_eq!(
+        new_settings.obfuscation_settings.udp2tcp.websocket,
+        Some(WebSocketSettings {
+            server_name: "example.com".to_owned(),
+            path: "/ws".to_owned(),
+        })
+    );
+
+    let patch = r#"{ "obfuscation_settings": { "udp2tcp": { "websocket": null } } }"#;
+    let new_settings = merge_validate_patch_inner(&new_settings, patch).unwrap();
+    assert_eq!(new_settings.obfuscation_settings.udp2tcp.websocket, None);
+
+    // Server names must be valid DNS names or IP addresses, and paths must be absolute
+    let patch = r#"{ "obfuscation_settings": { "udp2tcp": {
+        "websocket": { "server_name": "not a host", "path": "/ws" }
+    } } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+    let patch = r#"{ "obfuscation_settings": { "udp2tcp": {
+        "websocket": { "server_name": "example.com", "path": "ws" }
+    } } }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+}
+
+#[test]
+fn test_patch_custom_lists() {
+    use mullvad_types::relay_constraints::GeographicLocationConstraint;
+
+    let mut settings = Settings::default();
+    let mut existing_list = CustomList::new("existing".to_owned()).unwrap();
+    existing_list
+        .locations
+        .insert(GeographicLocationConstraint::country("se"));
+    let existing_id = existing_list.id;
+    settings.custom_lists.add(existing_list).unwrap();
+
+    // Replace locations of existing lists, and add new lists
+    let patch = r#"{ "custom_lists": [
+        { "name": "existing", "locations": [ { "country": "no" } ] },
+        { "name": "new", "locations": [ { "city": ["de", "ber"] } ] }
+    ] }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+
+    assert_eq!(new_settings.custom_lists.len(), 2);
+    let existing = &new_settings.custom_lists[0];
+    assert_eq!(existing.id, existing_id);
+    assert_eq!(
+        existing.locations.iter().collect::<Vec<_>>(),
+        [&GeographicLocationConstraint::country("no")]
+    );
+    let new = &new_settings.custom_lists[1];
+    assert_eq!(new.name, "new");
+    assert_eq!(
+        new.locations.iter().collect::<Vec<_>>(),
+        [&GeographicLocationConstraint::city("de", "ber")]
+    );
+
+    // IDs may not be specified
+    let patch = r#"{ "custom_lists": [ { "id": "00000000-0000-0000-0000-000000000000", "name": "a", "locations": [] } ] }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+
+    // Names must not be too long
+    let patch = r#"{ "custom_lists": [ { "name": "this name is much too long for a custom list", "locations": [] } ] }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+
+    // Lists must have locations
+    let patch = r#"{ "custom_lists": [ { "name": "a" } ] }"#;
+    merge_validate_patch_inner(&settings, patch).unwrap_err();
+}
+
 #[test]
 fn test_patch_relay_override() {
     const PERMITTED_SUBKEYS: &PermittedKey = &PermittedKey::object(&[(
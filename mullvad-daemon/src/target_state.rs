@@ -14,6 +14,9 @@ const TARGET_START_STATE_FILE: &str = "target-start-state.json";
 /// Persists the target state to a file, which is only removed if the instance is dropped cleanly.
 pub struct PersistentTargetState {
     state: TargetState,
+    /// Target state that temporarily takes precedence over `state`. It is never persisted, so
+    /// `state` is used again after a restart.
+    override_state: Option<TargetState>,
     cache_path: PathBuf,
     locked: bool,
 }
@@ -28,6 +31,7 @@ impl PersistentTargetState {
         } = Self::read_target_state(&cache_path, fs::read_to_string).await;
         let state = PersistentTargetState {
             state,
+            override_state: None,
             cache_path,
             locked: false,
         };
@@ -99,6 +103,7 @@ impl PersistentTargetState {
         let cache_path = cache_dir.join(TARGET_START_STATE_FILE);
         let state = PersistentTargetState {
             state: TargetState::Secured,
+            override_state: None,
             cache_path,
             locked: false,
         };
@@ -106,13 +111,30 @@ impl PersistentTargetState {
         state
     }
 
+    /// Set and persist the target state. This removes any override.
     pub async fn set(&mut self, new_state: TargetState) {
+        self.override_state = None;
         if new_state != self.state {
             self.state = new_state;
             self.save().await;
         }
     }
 
+    /// Use `state` as the target state until the override is removed, without persisting it.
+    pub fn set_override(&mut self, state: TargetState) {
+        self.override_state = Some(state);
+    }
+
+    /// Remove the override, if any, and return whether there was one.
+    pub fn clear_override(&mut self) -> bool {
+        self.override_state.take().is_some()
+    }
+
+    /// Whether the target state is currently overridden.
+    pub fn is_overridden(&self) -> bool {
+        self.override_state.is_some()
+    }
+
     /// Prevent the file from being removed when the instance is dropped.
     pub fn lock(&mut self) {
         self.locked = true;
@@ -179,7 +201,7 @@ impl Deref for PersistentTargetState {
     type Target = TargetState;
 
     fn deref(&self) -> &Self::Target {
-        &self.state
+        self.override_state.as_ref().unwrap_or(&self.state)
     }
 }
 
@@ -248,4 +270,33 @@ mod test {
         // Reading back a corrupt target state cache should yield `TargetState::Secured`.
         assert_eq!(*target_state, TargetState::Secured);
     }
+
+    /// An override is not persisted. If the daemon is restarted while the target state is
+    /// overridden, e.g. on a trusted network, the persisted state is used again, and is kept when
+    /// the override is removed.
+    #[tokio::test]
+    async fn test_target_state_override_not_persisted() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("target-state-test-{}", std::process::id()));
+        fs::create_dir_all(&cache_dir).await.unwrap();
+
+        let mut target_state = PersistentTargetState::new(&cache_dir).await;
+        target_state.set(TargetState::Secured).await;
+        target_state.set_override(TargetState::Unsecured);
+        assert_eq!(*target_state, TargetState::Unsecured);
+
+        // Restart without a clean shutdown
+        target_state.lock();
+        drop(target_state);
+        let mut target_state = PersistentTargetState::new(&cache_dir).await;
+        assert_eq!(*target_state, TargetState::Secured);
+
+        // Join and then leave the trusted network
+        target_state.set_override(TargetState::Unsecured);
+        assert!(target_state.clear_override());
+        assert_eq!(*target_state, TargetState::Secured);
+
+        target_state.finalize().await;
+        let _ = fs::remove_dir_all(&cache_dir).await;
+    }
 }
@@ -103,6 +103,12 @@ impl ParametersGenerator {
         }
     }
 
+    /// Returns whether the last generated tunnel parameters were for a custom endpoint rather than
+    /// a Mullvad relay.
+    pub async fn last_relay_was_custom(&self) -> bool {
+        self.0.lock().await.last_generated_relays.is_none()
+    }
+
     /// Gets the location associated with the last generated tunnel parameters.
     pub async fn get_last_location(&self) -> Option<GeoIpLocation> {
         let inner = self.0.lock().await;
@@ -217,11 +223,14 @@ impl InnerParametersGenerator {
 
                 Ok(self.create_wireguard_tunnel_parameters(endpoint, data, obfuscator_config))
             }
-            GetRelay::Custom(custom_relay) => {
+            GetRelay::Custom {
+                endpoint: custom_relay,
+                obfuscator,
+            } => {
                 self.last_generated_relays = None;
                 custom_relay
                      // TODO: generate proxy settings for custom tunnels
-                     .to_tunnel_parameters(self.tunnel_options.clone(), None)
+                     .to_tunnel_parameters(self.tunnel_options.clone(), None, obfuscator)
                      .map_err(|e| {
                          log::error!("Failed to resolve hostname for custom tunnel config: {}", e);
                          Error::ResolveCustomHostname
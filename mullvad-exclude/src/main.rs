@@ -14,7 +14,7 @@ use std::{
 };
 
 #[cfg(target_os = "linux")]
-use talpid_types::cgroup::{find_net_cls_mount, SPLIT_TUNNEL_CGROUP_NAME};
+use talpid_types::cgroup::{find_cgroup2_mount, find_net_cls_mount, SPLIT_TUNNEL_CGROUP_NAME};
 
 #[cfg(target_os = "linux")]
 const PROGRAM_NAME: &str = "mullvad-exclude";
@@ -40,11 +40,11 @@ enum Error {
     #[error("An argument contains interior nul bytes")]
     ArgumentNul(#[source] NulError),
 
-    #[error("Failed to find net_cls controller")]
-    FindNetClsController(#[source] io::Error),
+    #[error("Failed to find cgroup mounts")]
+    FindCGroupMounts(#[source] io::Error),
 
-    #[error("No net_cls controller")]
-    NoNetClsController,
+    #[error("No split tunnel cgroup. Is the daemon running?")]
+    NoExclusionCGroup,
 }
 
 fn main() {
@@ -85,13 +85,7 @@ fn run() -> Result<Infallible, Error> {
         .collect::<Result<Vec<CString>, NulError>>()
         .map_err(Error::ArgumentNul)?;
 
-    let cgroup_dir = find_net_cls_mount()
-        .map_err(Error::FindNetClsController)?
-        .ok_or(Error::NoNetClsController)?;
-
-    let procs_path = cgroup_dir
-        .join(SPLIT_TUNNEL_CGROUP_NAME)
-        .join("cgroup.procs");
+    let procs_path = find_exclusion_cgroup()?.join("cgroup.procs");
 
     let file = fs::OpenOptions::new()
         .write(true)
@@ -113,3 +107,17 @@ fn run() -> Result<Infallible, Error> {
     // Launch the process
     execvp(&program, &args).map_err(Error::Exec)
 }
+
+/// Find the cgroup used by the daemon for excluded processes. The legacy net_cls controller is
+/// preferred over the cgroup v2 hierarchy, same as in the daemon.
+#[cfg(target_os = "linux")]
+fn find_exclusion_cgroup() -> Result<std::path::PathBuf, Error> {
+    let net_cls_dir = find_net_cls_mount().map_err(Error::FindCGroupMounts)?;
+    let cgroup2_dir = find_cgroup2_mount().map_err(Error::FindCGroupMounts)?;
+    [net_cls_dir, cgroup2_dir]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(SPLIT_TUNNEL_CGROUP_NAME))
+        .find(|dir| dir.exists())
+        .ok_or(Error::NoExclusionCGroup)
+}
@@ -20,12 +20,17 @@ impl TunnelObfuscatorRuntime {
     pub fn new(peer: SocketAddr, obfuscation_protocol: TunnelObfuscatorProtocol) -> Self {
         let settings: ObfuscationSettings = match obfuscation_protocol {
             TunnelObfuscatorProtocol::UdpOverTcp => {
-                ObfuscationSettings::Udp2Tcp(udp2tcp::Settings { peer })
+                ObfuscationSettings::Udp2Tcp(udp2tcp::Settings {
+                    peer,
+                    websocket: None,
+                })
             }
             TunnelObfuscatorProtocol::Shadowsocks => {
                 ObfuscationSettings::Shadowsocks(shadowsocks::Settings {
                     shadowsocks_endpoint: peer,
                     wireguard_endpoint: SocketAddr::from((Ipv4Addr::LOCALHOST, 51820)),
+                    cipher: shadowsocks::MULLVAD_CIPHER.to_owned(),
+                    password: shadowsocks::MULLVAD_PASSWORD.to_owned(),
                 })
             }
         };
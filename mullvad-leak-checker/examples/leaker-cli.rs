@@ -15,6 +15,9 @@ pub enum LeakMethod {
     /// Ask `am.i.mullvad.net` whether you are leaking.
     #[cfg(feature = "am-i-mullvad")]
     AmIMullvad(#[clap(flatten)] mullvad_leak_checker::am_i_mullvad::AmIMullvadOpt),
+
+    /// Check for leaks by binding to a non-tunnel interface and querying DNS resolvers.
+    Dns(#[clap(flatten)] mullvad_leak_checker::dns::DnsOpt),
 }
 
 #[tokio::main]
@@ -30,6 +33,7 @@ async fn main() -> anyhow::Result<()> {
         LeakMethod::Traceroute(opt) => mullvad_leak_checker::traceroute::run_leak_test(opt).await,
         #[cfg(feature = "am-i-mullvad")]
         LeakMethod::AmIMullvad(opt) => mullvad_leak_checker::am_i_mullvad::run_leak_test(opt).await,
+        LeakMethod::Dns(opt) => mullvad_leak_checker::dns::run_leak_test(opt).await,
     };
 
     log::info!("Leak status: {leak_status:#?}");
@@ -9,7 +9,7 @@ use crate::{LeakInfo, LeakStatus};
 pub struct AmIMullvadOpt {
     /// Try to bind to a specific interface
     #[clap(short, long)]
-    interface: Option<String>,
+    pub interface: Option<String>,
 }
 
 const AM_I_MULLVAD_URL: &str = "https://am.i.mullvad.net/json";
@@ -0,0 +1,303 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::Context;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::UdpSocket;
+
+use crate::{util::Ip, Interface, LeakInfo, LeakStatus};
+
+/// Well-known public resolvers, which should never be reachable outside the tunnel.
+pub const WELL_KNOWN_RESOLVERS: [IpAddr; 3] = [
+    IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+    IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+    IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)),
+];
+
+pub const DNS_PORT: u16 = 53;
+
+/// Name to look up. Any answer counts as a leak, so it does not matter what it resolves to.
+const QUERY_NAME: &str = "am.i.mullvad.net";
+
+#[derive(Clone, clap::Args)]
+pub struct DnsOpt {
+    /// Try to bind to a specific interface
+    #[clap(short, long)]
+    pub interface: Option<Interface>,
+
+    /// Resolver to query. May be given more than once. Defaults to well-known public resolvers.
+    #[clap(short, long = "resolver")]
+    pub resolvers: Vec<SocketAddr>,
+
+    /// How long to wait for answers, in milliseconds
+    #[clap(long, default_value_t = 2000)]
+    pub timeout_ms: u64,
+}
+
+impl DnsOpt {
+    fn resolvers(&self) -> Vec<SocketAddr> {
+        if self.resolvers.is_empty() {
+            WELL_KNOWN_RESOLVERS
+                .iter()
+                .map(|&ip| SocketAddr::new(ip, DNS_PORT))
+                .collect()
+        } else {
+            self.resolvers.clone()
+        }
+    }
+}
+
+/// [try_run_leak_test], but on an error, assume we aren't leaking.
+pub async fn run_leak_test(opt: &DnsOpt) -> LeakStatus {
+    try_run_leak_test(opt)
+        .await
+        .inspect_err(|e| log::debug!("Leak test errored, assuming no leak. {e:?}"))
+        .unwrap_or(LeakStatus::NoLeak)
+}
+
+/// Run a DNS leak test.
+///
+/// This test will send a DNS query to each resolver in `opt`, from a socket bound to `interface`,
+/// which should be a non-tunnel interface. If any resolver answers, the query was NOT blocked by
+/// the firewall, and therefore we are leaking.
+pub async fn try_run_leak_test(opt: &DnsOpt) -> anyhow::Result<LeakStatus> {
+    let (v4_resolvers, v6_resolvers): (Vec<_>, Vec<_>) =
+        opt.resolvers().into_iter().partition(SocketAddr::is_ipv4);
+    let timeout = Duration::from_millis(opt.timeout_ms);
+
+    let (mut reachable_resolvers, v6_reachable) = futures::try_join!(
+        query_resolvers(opt.interface.as_ref(), Ip::v4(), &v4_resolvers, timeout),
+        query_resolvers(opt.interface.as_ref(), Ip::v6(), &v6_resolvers, timeout),
+    )?;
+    reachable_resolvers.extend(v6_reachable);
+
+    if reachable_resolvers.is_empty() {
+        log::debug!("No DNS resolver answered");
+        return Ok(LeakStatus::NoLeak);
+    }
+    log::debug!("DNS resolvers answered: {reachable_resolvers:?}");
+    Ok(LeakStatus::LeakDetected(LeakInfo::DnsReachable {
+        interface: opt.interface.clone(),
+        reachable_resolvers,
+    }))
+}
+
+/// Send a query to each of `resolvers`, which must all be of `ip_version`, and return the
+/// resolvers that answered within `timeout`.
+async fn query_resolvers(
+    interface: Option<&Interface>,
+    ip_version: Ip,
+    resolvers: &[SocketAddr],
+    timeout: Duration,
+) -> anyhow::Result<Vec<SocketAddr>> {
+    if resolvers.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let domain = match ip_version {
+        Ip::V4(..) => Domain::IPV4,
+        Ip::V6(..) => Domain::IPV6,
+    };
+    let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))
+        .context("Failed to create UDP socket")?;
+    if let Some(interface) = interface {
+        bind_socket_to_interface(&socket, interface, ip_version)?;
+    }
+    socket
+        .set_nonblocking(true)
+        .context("Failed to make socket non-blocking")?;
+    let socket = UdpSocket::from_std(socket.into()).context("Failed to register UDP socket")?;
+
+    // A random ID makes spoofed answers unlikely to be accepted
+    let query_id = rand::random();
+    let query = build_query(query_id, QUERY_NAME);
+    for &resolver in resolvers {
+        if let Err(error) = socket.send_to(&query, resolver).await {
+            // The firewall may reject the query outright
+            log::debug!("Failed to send DNS query to {resolver}: {error}");
+        }
+    }
+
+    let mut reachable = vec![];
+    let recv_answers = async {
+        let mut buf = [0u8; 512];
+        while reachable.len() < resolvers.len() {
+            let (len, from) = match socket.recv_from(&mut buf).await {
+                Ok(received) => received,
+                Err(error) => {
+                    log::debug!("Failed to receive DNS answer: {error}");
+                    continue;
+                }
+            };
+            let Some(&resolver) = resolvers
+                .iter()
+                .find(|resolver| resolver.ip() == from.ip() && resolver.port() == from.port())
+            else {
+                continue;
+            };
+            if is_answer(&buf[..len], query_id) && !reachable.contains(&resolver) {
+                reachable.push(resolver);
+            }
+        }
+    };
+    let _ = tokio::time::timeout(timeout, recv_answers).await;
+
+    Ok(reachable)
+}
+
+/// Build a recursive DNS query for the A records of `name`.
+fn build_query(id: u16, name: &str) -> Vec<u8> {
+    let mut query = Vec::with_capacity(18 + name.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    // Flags: recursion desired
+    query.extend_from_slice(&[0x01, 0x00]);
+    // One question, no answer, authority or additional records
+    query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    // Type A, class IN
+    query.extend_from_slice(&[0, 1, 0, 1]);
+    query
+}
+
+/// Returns whether `packet` is a response to the query with ID `id`. The response code is
+/// ignored, since any response means that the resolver was reached.
+fn is_answer(packet: &[u8], id: u16) -> bool {
+    const QR_RESPONSE: u8 = 0x80;
+    packet.len() >= 12 && packet[..2] == id.to_be_bytes() && packet[2] & QR_RESPONSE != 0
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_socket_to_interface(
+    socket: &Socket,
+    interface: &Interface,
+    _ip_version: Ip,
+) -> anyhow::Result<()> {
+    log::debug!("Binding socket to {interface:?}");
+
+    let Interface::Name(interface) = interface;
+
+    socket
+        .bind_device(Some(interface.as_bytes()))
+        .context("Failed to bind socket to interface")
+}
+
+#[cfg(target_os = "macos")]
+fn bind_socket_to_interface(
+    socket: &Socket,
+    interface: &Interface,
+    ip_version: Ip,
+) -> anyhow::Result<()> {
+    use anyhow::anyhow;
+    use nix::net::if_::if_nametoindex;
+    use std::num::NonZero;
+
+    log::debug!("Binding socket to {interface:?}");
+
+    let interface_index = match interface {
+        &Interface::Index(index) => index,
+        Interface::Name(interface) => if_nametoindex(interface.as_str())
+            .map_err(anyhow::Error::from)
+            .and_then(|code| NonZero::new(code).ok_or(anyhow!("Non-zero error code")))
+            .context("Failed to get interface index")?,
+    };
+
+    match ip_version {
+        Ip::V4(..) => socket.bind_device_by_index_v4(Some(interface_index))?,
+        Ip::V6(..) => socket.bind_device_by_index_v6(Some(interface_index))?,
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn bind_socket_to_interface(
+    socket: &Socket,
+    interface: &Interface,
+    ip_version: Ip,
+) -> anyhow::Result<()> {
+    use crate::util::get_interface_ip;
+
+    log::debug!("Binding socket to {interface:?}");
+
+    let ip = get_interface_ip(interface, ip_version)?;
+    socket
+        .bind(&SocketAddr::new(ip, 0).into())
+        .context("Failed to bind socket to interface IP")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Spawn a fake DNS server on localhost, which answers every query.
+    async fn spawn_fake_dns_server() -> SocketAddr {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let address = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                let mut answer = buf[..len].to_vec();
+                answer[2] |= 0x80;
+                let _ = socket.send_to(&answer, from).await;
+            }
+        });
+        address
+    }
+
+    fn opt(resolvers: Vec<SocketAddr>) -> DnsOpt {
+        DnsOpt {
+            interface: None,
+            resolvers,
+            timeout_ms: 500,
+        }
+    }
+
+    #[test]
+    fn test_dns_query() {
+        let query = build_query(0x1234, QUERY_NAME);
+        assert_eq!(&query[..2], &[0x12, 0x34]);
+        assert_eq!(&query[12..16], b"\x02am\x01");
+        assert_eq!(query.len(), 12 + QUERY_NAME.len() + 2 + 4);
+
+        let mut answer = query.clone();
+        answer[2] |= 0x80;
+        assert!(is_answer(&answer, 0x1234));
+        assert!(!is_answer(&answer, 0x4321));
+        assert!(!is_answer(&query, 0x1234));
+    }
+
+    /// Only resolvers that answer should be reported
+    #[tokio::test]
+    async fn test_reachable_resolver() {
+        let fake_server = spawn_fake_dns_server().await;
+        let silent_socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let silent_server = silent_socket.local_addr().unwrap();
+
+        let status = try_run_leak_test(&opt(vec![silent_server, fake_server]))
+            .await
+            .unwrap();
+        let LeakStatus::LeakDetected(LeakInfo::DnsReachable {
+            reachable_resolvers,
+            ..
+        }) = status
+        else {
+            panic!("expected DNS leak, got {status:?}");
+        };
+        assert_eq!(reachable_resolvers, [fake_server]);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_resolver() {
+        let silent_server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+
+        let status = try_run_leak_test(&opt(vec![silent_server.local_addr().unwrap()]))
+            .await
+            .unwrap();
+        assert!(matches!(status, LeakStatus::NoLeak), "got {status:?}");
+    }
+}
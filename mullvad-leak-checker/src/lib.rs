@@ -1,7 +1,11 @@
-use std::{fmt, net::IpAddr};
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+};
 
 #[cfg(feature = "am-i-mullvad")]
 pub mod am_i_mullvad;
+pub mod dns;
 pub mod traceroute;
 mod util;
 
@@ -23,6 +27,12 @@ pub enum LeakInfo {
     /// Queried a <https://am.i.mullvad.net>, and was not mullvad.
     #[cfg(feature = "am-i-mullvad")]
     AmIMullvad { ip: IpAddr },
+
+    /// Got answers from DNS resolvers, bypassing firewall rules.
+    DnsReachable {
+        reachable_resolvers: Vec<SocketAddr>,
+        interface: Option<Interface>,
+    },
 }
 
 #[derive(Clone)]
@@ -36,6 +46,38 @@ pub enum Interface {
     Index(std::num::NonZeroU32),
 }
 
+impl Interface {
+    /// Return the name of the interface, looking it up if it was given by LUID or index.
+    pub fn name(&self) -> anyhow::Result<String> {
+        use anyhow::Context;
+
+        match self {
+            Interface::Name(name) => Ok(name.clone()),
+
+            #[cfg(target_os = "windows")]
+            Interface::Luid(luid) => {
+                let alias = talpid_windows::net::alias_from_luid(luid)
+                    .with_context(|| format!("Failed to get alias of iface {self:?}"))?;
+                alias
+                    .into_string()
+                    .ok()
+                    .context("Network interface name was not UTF-8")
+            }
+
+            #[cfg(target_os = "macos")]
+            &Interface::Index(index) => {
+                use std::ffi::c_uint;
+
+                let name = nix::net::if_::if_indextoname(c_uint::from(index))
+                    .with_context(|| format!("Failed to get name of iface with index {index}"))?;
+                name.into_string()
+                    .ok()
+                    .context("Network interface name was not UTF-8")
+            }
+        }
+    }
+}
+
 impl From<String> for Interface {
     fn from(name: String) -> Self {
         Interface::Name(name)
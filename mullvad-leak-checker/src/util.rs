@@ -40,27 +40,9 @@ pub fn get_interface_ip(interface: &Interface, ip_version: Ip) -> anyhow::Result
 
 #[cfg(unix)]
 pub fn get_interface_ip(interface: &Interface, ip_version: Ip) -> anyhow::Result<IpAddr> {
-    #[cfg(target_os = "macos")]
-    let interface_name;
-
-    let interface_name = match interface {
-        Interface::Name(name) => name.as_str(),
-
-        #[cfg(target_os = "macos")]
-        &Interface::Index(index) => {
-            use anyhow::{anyhow, Context};
-            use std::ffi::c_uint;
-
-            // nix getifaddrs provides no way of getting an interface by index, so we need to get
-            // the interface name
-            interface_name = nix::net::if_::if_indextoname(c_uint::from(index))
-                .with_context(|| anyhow!("Failed to get name of iface with index {index}"))?;
-
-            interface_name
-                .to_str()
-                .context("Network interface name was not UTF-8")?
-        }
-    };
+    // nix getifaddrs provides no way of getting an interface by index, so we need to get the
+    // interface name
+    let interface_name = interface.name()?;
 
     for interface_address in nix::ifaddrs::getifaddrs()? {
         if interface_address.interface_name != interface_name {
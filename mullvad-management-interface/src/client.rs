@@ -8,6 +8,9 @@ use mullvad_types::wireguard::DaitaSettings;
 use mullvad_types::{
     access_method::AccessMethodSetting,
     device::{DeviceEvent, RemoveDeviceEvent},
+    dns_blocklist::DnsBlocklistStats,
+    leak::{LeakReport, LeakTestReport},
+    learned_obfuscation::LearnedObfuscation,
     relay_list::RelayList,
     settings::Settings,
     states::TunnelState,
@@ -18,6 +21,8 @@ use mullvad_types::{
 use mullvad_types::{
     access_method::{self, AccessMethod},
     account::{AccountData, AccountNumber, VoucherSubmission},
+    auto_connect::{AutoConnectRules, TrustedNetwork},
+    custom_endpoint::{self, NamedCustomEndpoint},
     custom_list::{CustomList, Id},
     device::{Device, DeviceId, DeviceState},
     features::FeatureIndicators,
@@ -26,9 +31,12 @@ use mullvad_types::{
     },
     settings::DnsOptions,
     wireguard::{PublicKey, QuantumResistantState, RotationInterval},
+    CustomTunnelEndpoint,
 };
 #[cfg(not(target_os = "android"))]
-use std::{path::Path, str::FromStr};
+use std::{path::Path, str::FromStr, time::Duration};
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::wireguard::TunnelStatistics;
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 #[cfg(not(target_os = "android"))]
@@ -51,6 +59,7 @@ pub enum DaemonEvent {
     Device(DeviceEvent),
     RemoveDevice(RemoveDeviceEvent),
     NewAccessMethod(AccessMethodSetting),
+    LeakDetected(LeakReport),
 }
 
 impl TryFrom<types::daemon_event::Event> for DaemonEvent {
@@ -81,6 +90,9 @@ impl TryFrom<types::daemon_event::Event> for DaemonEvent {
                     .map(DaemonEvent::NewAccessMethod)
                     .map_err(Error::InvalidResponse)
             }
+            types::daemon_event::Event::LeakDetected(report) => LeakReport::try_from(report)
+                .map(DaemonEvent::LeakDetected)
+                .map_err(Error::InvalidResponse),
         }
     }
 }
@@ -278,7 +290,7 @@ impl MullvadProxyClient {
         self.0
             .set_relay_settings(update)
             .await
-            .map_err(Error::Rpc)?;
+            .map_err(map_custom_endpoint_error)?;
         Ok(())
     }
 
@@ -326,6 +338,18 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    #[cfg(not(target_os = "android"))]
+    pub async fn set_allowed_lan_nets(
+        &mut self,
+        allowed_lan_nets: &talpid_types::net::AllowedLanNets,
+    ) -> Result<()> {
+        self.0
+            .set_allowed_lan_nets(types::AllowedLanNets::from(allowed_lan_nets))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_show_beta_releases(&mut self, state: bool) -> Result<()> {
         self.0
             .set_show_beta_releases(state)
@@ -347,6 +371,22 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_auto_connect_rules(&mut self, rules: AutoConnectRules) -> Result<()> {
+        self.0
+            .set_auto_connect_rules(types::AutoConnectRules::from(rules))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_trusted_networks(&mut self, networks: Vec<TrustedNetwork>) -> Result<()> {
+        self.0
+            .set_trusted_networks(types::TrustedNetworks::from(networks))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_openvpn_mssfix(&mut self, mssfix: Option<u16>) -> Result<()> {
         self.0
             .set_openvpn_mssfix(mssfix.map(u32::from).unwrap_or(0))
@@ -605,6 +645,48 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn create_custom_endpoint(
+        &mut self,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) -> Result<custom_endpoint::Id> {
+        let id = self
+            .0
+            .create_custom_endpoint(types::NewCustomEndpoint {
+                name,
+                endpoint: Some(types::CustomRelaySettings::from(endpoint)),
+            })
+            .await
+            .map_err(map_custom_endpoint_error)?
+            .into_inner();
+        custom_endpoint::Id::from_str(&id).map_err(|_| Error::CustomEndpointNotFound)
+    }
+
+    pub async fn delete_custom_endpoint(&mut self, id: String) -> Result<()> {
+        self.0
+            .delete_custom_endpoint(id)
+            .await
+            .map_err(map_custom_endpoint_error)?;
+        Ok(())
+    }
+
+    pub async fn update_custom_endpoint(&mut self, endpoint: NamedCustomEndpoint) -> Result<()> {
+        self.0
+            .update_custom_endpoint(types::CustomEndpoint::from(endpoint))
+            .await
+            .map_err(map_custom_endpoint_error)?;
+        Ok(())
+    }
+
+    /// Remove all saved custom endpoints.
+    pub async fn clear_custom_endpoints(&mut self) -> Result<()> {
+        self.0
+            .clear_custom_endpoints(())
+            .await
+            .map_err(map_custom_endpoint_error)?;
+        Ok(())
+    }
+
     pub async fn add_access_method(
         &mut self,
         name: String,
@@ -768,6 +850,86 @@ impl MullvadProxyClient {
             .map(|response| response.into_inner())
             .map(FeatureIndicators::from)
     }
+
+    pub async fn get_leak_history(&mut self) -> Result<Vec<LeakReport>> {
+        let history = self
+            .0
+            .get_leak_history(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        Vec::try_from(history).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn run_leak_test(&mut self) -> Result<LeakTestReport> {
+        let report = self
+            .0
+            .run_leak_test(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        LeakTestReport::try_from(report).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn get_learned_obfuscation(&mut self) -> Result<Vec<LearnedObfuscation>> {
+        let table = self
+            .0
+            .get_learned_obfuscation(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        Vec::try_from(table).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn clear_learned_obfuscation(&mut self) -> Result<()> {
+        self.0
+            .clear_learned_obfuscation(())
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn get_dns_blocklist_stats(&mut self) -> Result<DnsBlocklistStats> {
+        let stats = self
+            .0
+            .get_dns_blocklist_stats(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        DnsBlocklistStats::try_from(stats).map_err(Error::InvalidResponse)
+    }
+
+    /// Return statistics about the current tunnel, or `None` unless connected to a WireGuard
+    /// relay.
+    pub async fn get_tunnel_statistics(&mut self) -> Result<Option<TunnelStatistics>> {
+        let statistics = match self.0.get_tunnel_statistics(()).await {
+            Ok(statistics) => statistics.into_inner(),
+            Err(status) if status.code() == Code::FailedPrecondition => return Ok(None),
+            Err(status) => return Err(Error::Rpc(status)),
+        };
+        TunnelStatistics::try_from(statistics)
+            .map(Some)
+            .map_err(Error::InvalidResponse)
+    }
+
+    /// Return a stream that yields statistics about the current tunnel every `interval`, for as
+    /// long as a tunnel is up.
+    pub async fn tunnel_statistics_listen<'a>(
+        &mut self,
+        interval: Duration,
+    ) -> Result<impl Stream<Item = Result<TunnelStatistics>> + 'a> {
+        let interval = types::Duration::try_from(interval).map_err(|_| Error::DurationTooLarge)?;
+        let listener = self
+            .0
+            .tunnel_statistics_listen(interval)
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+
+        Ok(listener.map(|item| {
+            TunnelStatistics::try_from(item.map_err(Error::Rpc)?).map_err(Error::InvalidResponse)
+        }))
+    }
 }
 
 #[cfg(not(target_os = "android"))]
@@ -781,6 +943,19 @@ fn map_device_error(status: Status) -> Error {
     }
 }
 
+#[cfg(not(target_os = "android"))]
+fn map_custom_endpoint_error(status: Status) -> Error {
+    match status.code() {
+        Code::NotFound if status.details() == crate::CUSTOM_ENDPOINT_NOT_FOUND_DETAILS => {
+            Error::CustomEndpointNotFound
+        }
+        Code::AlreadyExists if status.details() == crate::CUSTOM_ENDPOINT_EXISTS_DETAILS => {
+            Error::CustomEndpointExists
+        }
+        _other => Error::Rpc(status),
+    }
+}
+
 #[cfg(not(target_os = "android"))]
 fn map_custom_list_error(status: Status) -> Error {
     match status.code() {
@@ -1,6 +1,7 @@
 pub mod client;
 pub mod types;
 
+#[cfg(not(target_os = "android"))]
 use parity_tokio_ipc::Endpoint as IpcEndpoint;
 #[cfg(unix)]
 use std::{env, fs, os::unix::fs::PermissionsExt};
@@ -32,6 +33,8 @@ static MULLVAD_MANAGEMENT_SOCKET_GROUP: LazyLock<Option<String>> =
 pub const CUSTOM_LIST_LIST_NOT_FOUND_DETAILS: &[u8] = b"custom_list_list_not_found";
 pub const CUSTOM_LIST_LIST_EXISTS_DETAILS: &[u8] = b"custom_list_list_exists";
 pub const CUSTOM_LIST_LIST_NAME_TOO_LONG_DETAILS: &[u8] = b"custom_list_list_name_too_long";
+pub const CUSTOM_ENDPOINT_NOT_FOUND_DETAILS: &[u8] = b"custom_endpoint_not_found";
+pub const CUSTOM_ENDPOINT_EXISTS_DETAILS: &[u8] = b"custom_endpoint_exists";
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -107,6 +110,12 @@ pub enum Error {
     #[error("Location was not found in the custom list")]
     LocationNotFoundInCustomlist,
 
+    #[error("A custom endpoint with that name already exists")]
+    CustomEndpointExists,
+
+    #[error("The custom endpoint does not exist")]
+    CustomEndpointNotFound,
+
     #[error("Could not retrieve API access methods from settings")]
     ApiAccessMethodSettingsNotFound,
 
@@ -142,17 +151,26 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
     abort_rx: F,
     rpc_socket_path: impl AsRef<std::path::Path>,
 ) -> std::result::Result<ServerJoinHandle, Error> {
-    use futures::stream::TryStreamExt;
-    use parity_tokio_ipc::SecurityAttributes;
-
-    let mut endpoint = IpcEndpoint::new(rpc_socket_path.as_ref().to_string_lossy().to_string());
-    endpoint.set_security_attributes(
-        SecurityAttributes::allow_everyone_create()
-            .map_err(Error::SecurityAttributes)?
-            .set_mode(0o766)
-            .map_err(Error::SecurityAttributes)?,
-    );
-    let incoming = endpoint.incoming().map_err(Error::StartServerError)?;
+    #[cfg(unix)]
+    let incoming = unix_incoming(rpc_socket_path.as_ref())?;
+
+    #[cfg(windows)]
+    let incoming = {
+        use futures::stream::TryStreamExt;
+        use parity_tokio_ipc::SecurityAttributes;
+
+        let mut endpoint = IpcEndpoint::new(rpc_socket_path.as_ref().to_string_lossy().to_string());
+        endpoint.set_security_attributes(
+            SecurityAttributes::allow_everyone_create()
+                .map_err(Error::SecurityAttributes)?
+                .set_mode(0o766)
+                .map_err(Error::SecurityAttributes)?,
+        );
+        endpoint
+            .incoming()
+            .map_err(Error::StartServerError)?
+            .map_ok(|stream| StreamBox(stream, None))
+    };
 
     #[cfg(unix)]
     if let Some(group_name) = &*MULLVAD_MANAGEMENT_SOCKET_GROUP {
@@ -168,7 +186,7 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
     Ok(tokio::spawn(async move {
         if let Err(execution_error) = Server::builder()
             .add_service(ManagementServiceServer::new(service))
-            .serve_with_incoming_shutdown(incoming.map_ok(StreamBox), abort_rx)
+            .serve_with_incoming_shutdown(incoming, abort_rx)
             .await
             .map_err(Error::GrpcTransportError)
         {
@@ -178,13 +196,76 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
     }))
 }
 
+/// Listen on a Unix socket at `path`, and attach the credentials of the peer process to every
+/// accepted connection. Connections whose peer credentials cannot be read are dropped.
+#[cfg(unix)]
+fn unix_incoming(
+    path: &std::path::Path,
+) -> Result<
+    Pin<Box<impl futures::Stream<Item = io::Result<StreamBox<tokio::net::UnixStream>>>>>,
+    Error,
+> {
+    let listener = tokio::net::UnixListener::bind(path).map_err(Error::StartServerError)?;
+    fs::set_permissions(path, PermissionsExt::from_mode(0o766)).map_err(Error::PermissionsError)?;
+
+    Ok(Box::pin(futures::stream::unfold(
+        listener,
+        |listener| async move {
+            loop {
+                let (stream, _) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(error) => return Some((Err(error), listener)),
+                };
+                match stream.peer_cred() {
+                    Ok(cred) => {
+                        let peer = PeerCredentials {
+                            uid: cred.uid(),
+                            gid: cred.gid(),
+                            pid: cred.pid(),
+                        };
+                        return Some((Ok(StreamBox(stream, Some(peer))), listener));
+                    }
+                    Err(error) => {
+                        log::error!(
+                            "Failed to read peer credentials, dropping connection: {error}"
+                        );
+                    }
+                }
+            }
+        },
+    )))
+}
+
+/// Credentials of the process on the other end of a management interface connection.
+///
+/// These are attached to every request as an extension of type `Option<PeerCredentials>`. They
+/// are only available on Unix, where they are read from the socket (`SO_PEERCRED` on Linux).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    /// Primary group of the peer process. Supplementary groups are not known.
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
+impl PeerCredentials {
+    /// Return the credentials of the caller of `request`, if known.
+    pub fn of<T>(request: &Request<T>) -> Option<PeerCredentials> {
+        request
+            .extensions()
+            .get::<Option<PeerCredentials>>()
+            .copied()
+            .flatten()
+    }
+}
+
 #[derive(Debug)]
-struct StreamBox<T: AsyncRead + AsyncWrite>(pub T);
+struct StreamBox<T: AsyncRead + AsyncWrite>(pub T, Option<PeerCredentials>);
 impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
-    type ConnectInfo = Option<()>;
+    type ConnectInfo = Option<PeerCredentials>;
 
     fn connect_info(&self) -> Self::ConnectInfo {
-        None
+        self.1
     }
 }
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
@@ -69,7 +69,9 @@ mod data {
     use mullvad_types::access_method::{
         AccessMethod, AccessMethodSetting, BuiltInAccessMethod, Id,
     };
-    use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+    use talpid_types::net::proxy::{
+        CustomProxy, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote,
+    };
 
     impl TryFrom<proto::AccessMethodSetting> for AccessMethodSetting {
         type Error = FromProtobufTypeError;
@@ -188,6 +190,14 @@ mod data {
         }
     }
 
+    impl TryFrom<proto::HttpConnect> for AccessMethod {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpConnect) -> Result<Self, Self::Error> {
+            HttpConnect::try_from(value).map(AccessMethod::from)
+        }
+    }
+
     impl From<BuiltInAccessMethod> for proto::AccessMethod {
         fn from(value: BuiltInAccessMethod) -> Self {
             proto::AccessMethod {
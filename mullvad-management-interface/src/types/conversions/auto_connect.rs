@@ -0,0 +1,201 @@
+use super::arg_from_str;
+use crate::types::{proto, FromProtobufTypeError};
+use chrono::{NaiveTime, Timelike};
+use mullvad_types::{
+    auto_connect::{
+        AutoConnectAction, AutoConnectRule, AutoConnectRules, RuleConditions, TimeWindow,
+        TrustedNetwork,
+    },
+    relay_constraints::RelaySettings,
+};
+
+impl From<AutoConnectRules> for proto::AutoConnectRules {
+    fn from(rules: AutoConnectRules) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(proto::AutoConnectRule::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::AutoConnectRules> for AutoConnectRules {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(rules: proto::AutoConnectRules) -> Result<Self, Self::Error> {
+        let rules = rules
+            .rules
+            .into_iter()
+            .map(AutoConnectRule::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        AutoConnectRules::new(rules).map_err(|_| {
+            FromProtobufTypeError::InvalidArgument("auto-connect rule names must be unique")
+        })
+    }
+}
+
+impl From<AutoConnectRule> for proto::AutoConnectRule {
+    fn from(rule: AutoConnectRule) -> Self {
+        use proto::auto_connect_rule::Action;
+
+        let conditions = rule.conditions;
+        let (action, relay_settings) = match rule.action {
+            AutoConnectAction::Connect => (Action::Connect, None),
+            AutoConnectAction::Disconnect => (Action::Disconnect, None),
+            AutoConnectAction::ConnectUsing(relay_settings) => (
+                Action::Connect,
+                Some(proto::RelaySettings::from(relay_settings)),
+            ),
+        };
+
+        Self {
+            name: rule.name,
+            interface: conditions.interface,
+            gateway_ip: conditions.gateway_ip.map(|ip| ip.to_string()),
+            gateway_mac: conditions.gateway_mac.map(|mac| mac.to_string()),
+            dns_suffix: conditions.dns_suffix,
+            time_window: conditions.time_window.map(|window| proto::TimeWindow {
+                start: minutes_after_midnight(window.start),
+                end: minutes_after_midnight(window.end),
+            }),
+            action: i32::from(action),
+            relay_settings,
+        }
+    }
+}
+
+impl TryFrom<proto::AutoConnectRule> for AutoConnectRule {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(rule: proto::AutoConnectRule) -> Result<Self, Self::Error> {
+        use proto::auto_connect_rule::Action;
+
+        let action = Action::try_from(rule.action).map_err(|_| {
+            FromProtobufTypeError::InvalidArgument("invalid auto-connect rule action")
+        })?;
+        let action = match (action, rule.relay_settings) {
+            (Action::Connect, None) => AutoConnectAction::Connect,
+            (Action::Connect, Some(relay_settings)) => {
+                AutoConnectAction::ConnectUsing(RelaySettings::try_from(relay_settings)?)
+            }
+            (Action::Disconnect, _) => AutoConnectAction::Disconnect,
+        };
+
+        let time_window = rule
+            .time_window
+            .map(|window| {
+                Ok::<_, FromProtobufTypeError>(TimeWindow {
+                    start: time_from_minutes(window.start)?,
+                    end: time_from_minutes(window.end)?,
+                })
+            })
+            .transpose()?;
+
+        let conditions = RuleConditions {
+            interface: rule.interface,
+            gateway_ip: rule
+                .gateway_ip
+                .map(|ip| arg_from_str(&ip, "invalid gateway IP"))
+                .transpose()?,
+            gateway_mac: rule
+                .gateway_mac
+                .map(|mac| arg_from_str(&mac, "invalid gateway MAC address"))
+                .transpose()?,
+            dns_suffix: rule.dns_suffix,
+            time_window,
+        };
+
+        // Networks can only be identified on Linux and macOS
+        if conditions.depends_on_network()
+            && cfg!(not(any(target_os = "linux", target_os = "macos")))
+        {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "auto-connect rules can only depend on the time of day on this platform",
+            ));
+        }
+
+        Ok(AutoConnectRule {
+            name: rule.name,
+            conditions,
+            action,
+        })
+    }
+}
+
+impl From<Vec<TrustedNetwork>> for proto::TrustedNetworks {
+    fn from(networks: Vec<TrustedNetwork>) -> Self {
+        Self {
+            networks: networks
+                .into_iter()
+                .map(proto::TrustedNetwork::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::TrustedNetworks> for Vec<TrustedNetwork> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(networks: proto::TrustedNetworks) -> Result<Self, Self::Error> {
+        let networks = networks
+            .networks
+            .into_iter()
+            .map(TrustedNetwork::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        if !networks.iter().all(TrustedNetwork::is_supported) {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "trusted network cannot be recognized on this platform",
+            ));
+        }
+        Ok(networks)
+    }
+}
+
+impl From<TrustedNetwork> for proto::TrustedNetwork {
+    fn from(network: TrustedNetwork) -> Self {
+        use proto::trusted_network::Network;
+
+        let network = match network {
+            TrustedNetwork::GatewayMac(mac) => Network::GatewayMac(mac.to_string()),
+            TrustedNetwork::Ssid(ssid) => Network::Ssid(ssid),
+            TrustedNetwork::Subnet(subnet) => Network::Subnet(subnet.to_string()),
+        };
+        Self {
+            network: Some(network),
+        }
+    }
+}
+
+impl TryFrom<proto::TrustedNetwork> for TrustedNetwork {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(network: proto::TrustedNetwork) -> Result<Self, Self::Error> {
+        use proto::trusted_network::Network;
+
+        match network.network {
+            Some(Network::GatewayMac(mac)) => Ok(TrustedNetwork::GatewayMac(arg_from_str(
+                &mac,
+                "invalid gateway MAC address",
+            )?)),
+            Some(Network::Ssid(ssid)) => Ok(TrustedNetwork::Ssid(ssid)),
+            Some(Network::Subnet(subnet)) => Ok(TrustedNetwork::Subnet(arg_from_str(
+                &subnet,
+                "invalid trusted subnet",
+            )?)),
+            None => Err(FromProtobufTypeError::InvalidArgument(
+                "missing trusted network",
+            )),
+        }
+    }
+}
+
+fn minutes_after_midnight(time: NaiveTime) -> u32 {
+    time.hour() * 60 + time.minute()
+}
+
+fn time_from_minutes(minutes: u32) -> Result<NaiveTime, FromProtobufTypeError> {
+    NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0).ok_or(
+        FromProtobufTypeError::InvalidArgument("invalid time window"),
+    )
+}
@@ -0,0 +1,60 @@
+use std::str::FromStr;
+
+use crate::types::{proto, FromProtobufTypeError};
+use mullvad_types::{
+    custom_endpoint::{CustomEndpointsSettings, Id, NamedCustomEndpoint},
+    CustomTunnelEndpoint,
+};
+
+impl From<CustomEndpointsSettings> for proto::CustomEndpointSettings {
+    fn from(settings: CustomEndpointsSettings) -> Self {
+        Self {
+            custom_endpoints: settings
+                .into_iter()
+                .map(proto::CustomEndpoint::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomEndpointSettings> for CustomEndpointsSettings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::CustomEndpointSettings) -> Result<Self, Self::Error> {
+        Ok(Self::from(
+            settings
+                .custom_endpoints
+                .into_iter()
+                .map(NamedCustomEndpoint::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+        ))
+    }
+}
+
+impl From<NamedCustomEndpoint> for proto::CustomEndpoint {
+    fn from(endpoint: NamedCustomEndpoint) -> Self {
+        Self {
+            id: endpoint.id.to_string(),
+            name: endpoint.name,
+            endpoint: Some(proto::CustomRelaySettings::from(endpoint.endpoint)),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomEndpoint> for NamedCustomEndpoint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(endpoint: proto::CustomEndpoint) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: Id::from_str(&endpoint.id)
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("Invalid endpoint ID"))?,
+            name: endpoint.name,
+            endpoint: endpoint
+                .endpoint
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "missing custom endpoint",
+                ))
+                .and_then(CustomTunnelEndpoint::try_from)?,
+        })
+    }
+}
@@ -4,6 +4,44 @@ use crate::types::{
 };
 use talpid_types::net::wireguard;
 
+impl TryFrom<proto::CustomRelaySettings> for mullvad_types::CustomTunnelEndpoint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::CustomRelaySettings) -> Result<Self, Self::Error> {
+        let config = settings
+            .config
+            .ok_or(FromProtobufTypeError::InvalidArgument(
+                "missing relay connection config",
+            ))?;
+        let wireguard_psk = settings
+            .wireguard_psk
+            .map(|psk| {
+                <[u8; 32]>::try_from(psk.as_slice())
+                    .map(wireguard::PresharedKey::from)
+                    .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid preshared key"))
+            })
+            .transpose()?;
+        Ok(mullvad_types::CustomTunnelEndpoint {
+            host: settings.host,
+            config: mullvad_types::ConnectionConfig::try_from(config)?,
+            wireguard_psk,
+        })
+    }
+}
+
+impl From<mullvad_types::CustomTunnelEndpoint> for proto::CustomRelaySettings {
+    fn from(endpoint: mullvad_types::CustomTunnelEndpoint) -> Self {
+        Self {
+            host: endpoint.host,
+            config: Some(proto::ConnectionConfig::from(endpoint.config)),
+            wireguard_psk: endpoint
+                .wireguard_psk
+                .as_ref()
+                .map(|psk| psk.as_bytes().to_vec()),
+        }
+    }
+}
+
 impl TryFrom<proto::ConnectionConfig> for mullvad_types::ConnectionConfig {
     type Error = FromProtobufTypeError;
 
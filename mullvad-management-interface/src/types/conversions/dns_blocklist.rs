@@ -0,0 +1,121 @@
+use crate::types::{proto, FromProtobufTypeError};
+use mullvad_types::{
+    dns_blocklist::{BlocklistSourceStats, DnsBlocklistStats},
+    settings::{BlocklistSource, LocalBlocklistOptions},
+};
+use talpid_types::net::dns::normalize_domain;
+
+impl From<&LocalBlocklistOptions> for proto::LocalBlocklistOptions {
+    fn from(options: &LocalBlocklistOptions) -> Self {
+        proto::LocalBlocklistOptions {
+            sources: options
+                .sources
+                .iter()
+                .cloned()
+                .map(proto::BlocklistSource::from)
+                .collect(),
+            allowlist: options.allowlist.clone(),
+        }
+    }
+}
+
+impl TryFrom<proto::LocalBlocklistOptions> for LocalBlocklistOptions {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(options: proto::LocalBlocklistOptions) -> Result<Self, Self::Error> {
+        Ok(LocalBlocklistOptions {
+            sources: options
+                .sources
+                .into_iter()
+                .map(BlocklistSource::try_from)
+                .collect::<Result<_, _>>()?,
+            allowlist: options
+                .allowlist
+                .iter()
+                .map(|domain| {
+                    normalize_domain(domain).map_err(|_| {
+                        FromProtobufTypeError::InvalidArgument("invalid allowlisted domain")
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<BlocklistSource> for proto::BlocklistSource {
+    fn from(source: BlocklistSource) -> Self {
+        use proto::blocklist_source::Source;
+
+        let source = match source {
+            BlocklistSource::File(path) => Source::File(path.to_string_lossy().into_owned()),
+            BlocklistSource::Url(url) => Source::Url(url),
+        };
+        proto::BlocklistSource {
+            source: Some(source),
+        }
+    }
+}
+
+impl TryFrom<proto::BlocklistSource> for BlocklistSource {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(source: proto::BlocklistSource) -> Result<Self, Self::Error> {
+        use proto::blocklist_source::Source;
+
+        match source.source {
+            Some(Source::File(path)) if !path.is_empty() => Ok(BlocklistSource::File(path.into())),
+            Some(Source::Url(url)) if url.starts_with("https://") => Ok(BlocklistSource::Url(url)),
+            Some(Source::Url(_)) => Err(FromProtobufTypeError::InvalidArgument(
+                "blocklist URLs must use HTTPS",
+            )),
+            _ => Err(FromProtobufTypeError::InvalidArgument(
+                "missing blocklist source",
+            )),
+        }
+    }
+}
+
+impl From<DnsBlocklistStats> for proto::DnsBlocklistStats {
+    fn from(stats: DnsBlocklistStats) -> Self {
+        proto::DnsBlocklistStats {
+            allowlisted_queries: stats.allowlisted_queries,
+            sources: stats
+                .sources
+                .into_iter()
+                .map(|source| proto::BlocklistSourceStats {
+                    source: Some(proto::BlocklistSource::from(source.source)),
+                    domains: source.domains,
+                    blocked_queries: source.blocked_queries,
+                    error: source.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::DnsBlocklistStats> for DnsBlocklistStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: proto::DnsBlocklistStats) -> Result<Self, Self::Error> {
+        Ok(DnsBlocklistStats {
+            allowlisted_queries: stats.allowlisted_queries,
+            sources: stats
+                .sources
+                .into_iter()
+                .map(|source| {
+                    Ok(BlocklistSourceStats {
+                        source: source
+                            .source
+                            .ok_or(FromProtobufTypeError::InvalidArgument(
+                                "missing blocklist source",
+                            ))
+                            .and_then(BlocklistSource::try_from)?,
+                        domains: source.domains,
+                        blocked_queries: source.blocked_queries,
+                        error: source.error,
+                    })
+                })
+                .collect::<Result<_, FromProtobufTypeError>>()?,
+        })
+    }
+}
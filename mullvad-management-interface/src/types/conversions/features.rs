@@ -11,6 +11,7 @@ impl From<mullvad_types::features::FeatureIndicator> for proto::FeatureIndicator
             mullvad_types::features::FeatureIndicator::LockdownMode => LockdownMode,
             mullvad_types::features::FeatureIndicator::Udp2Tcp => Udp2Tcp,
             mullvad_types::features::FeatureIndicator::Shadowsocks => Shadowsocks,
+            mullvad_types::features::FeatureIndicator::Quic => Quic,
             mullvad_types::features::FeatureIndicator::LanSharing => LanSharing,
             mullvad_types::features::FeatureIndicator::DnsContentBlockers => DnsContentBlockers,
             mullvad_types::features::FeatureIndicator::CustomDns => CustomDns,
@@ -18,6 +19,7 @@ impl From<mullvad_types::features::FeatureIndicator> for proto::FeatureIndicator
             mullvad_types::features::FeatureIndicator::CustomMtu => CustomMtu,
             mullvad_types::features::FeatureIndicator::CustomMssFix => CustomMssFix,
             mullvad_types::features::FeatureIndicator::Daita => Daita,
+            mullvad_types::features::FeatureIndicator::TrustedNetworks => TrustedNetworks,
         }
     }
 }
@@ -32,6 +34,7 @@ impl From<proto::FeatureIndicator> for mullvad_types::features::FeatureIndicator
             proto::FeatureIndicator::LockdownMode => Self::LockdownMode,
             proto::FeatureIndicator::Udp2Tcp => Self::Udp2Tcp,
             proto::FeatureIndicator::Shadowsocks => Self::Shadowsocks,
+            proto::FeatureIndicator::Quic => Self::Quic,
             proto::FeatureIndicator::LanSharing => Self::LanSharing,
             proto::FeatureIndicator::DnsContentBlockers => Self::DnsContentBlockers,
             proto::FeatureIndicator::CustomDns => Self::CustomDns,
@@ -39,6 +42,7 @@ impl From<proto::FeatureIndicator> for mullvad_types::features::FeatureIndicator
             proto::FeatureIndicator::CustomMtu => Self::CustomMtu,
             proto::FeatureIndicator::CustomMssFix => Self::CustomMssFix,
             proto::FeatureIndicator::Daita => Self::Daita,
+            proto::FeatureIndicator::TrustedNetworks => Self::TrustedNetworks,
         }
     }
 }
@@ -0,0 +1,199 @@
+use super::arg_from_str;
+use crate::types::{proto, FromProtobufTypeError};
+use chrono::{DateTime, Utc};
+use mullvad_types::leak::{LeakInfo, LeakReport, LeakTestReport, LeakTestResult};
+use prost_types::Timestamp;
+
+pub(super) fn timestamp_to_proto(time: DateTime<Utc>) -> Timestamp {
+    Timestamp {
+        seconds: time.timestamp(),
+        nanos: 0,
+    }
+}
+
+pub(super) fn timestamp_from_proto(
+    time: Option<Timestamp>,
+    missing_msg: &'static str,
+) -> Result<DateTime<Utc>, FromProtobufTypeError> {
+    let time = time.ok_or(FromProtobufTypeError::InvalidArgument(missing_msg))?;
+    DateTime::from_timestamp(time.seconds, 0)
+        .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))
+}
+
+fn tunnel_endpoint_from_proto(
+    endpoint: Option<proto::TunnelEndpoint>,
+) -> Result<talpid_types::net::TunnelEndpoint, FromProtobufTypeError> {
+    endpoint
+        .ok_or(FromProtobufTypeError::InvalidArgument(
+            "missing tunnel endpoint",
+        ))
+        .and_then(talpid_types::net::TunnelEndpoint::try_from)
+}
+
+impl From<LeakInfo> for proto::LeakInfo {
+    fn from(info: LeakInfo) -> Self {
+        use proto::leak_info::{AmIMullvad, DnsReachable, Info, NodeReachableOnInterface};
+
+        let info = match info {
+            LeakInfo::NodeReachableOnInterface {
+                interface,
+                reachable_nodes,
+            } => Info::NodeReachableOnInterface(NodeReachableOnInterface {
+                interface,
+                reachable_nodes: reachable_nodes.iter().map(ToString::to_string).collect(),
+            }),
+            LeakInfo::AmIMullvad { ip } => Info::AmIMullvad(AmIMullvad { ip: ip.to_string() }),
+            LeakInfo::DnsReachable {
+                interface,
+                resolvers,
+            } => Info::DnsReachable(DnsReachable {
+                interface,
+                resolvers: resolvers.iter().map(ToString::to_string).collect(),
+            }),
+        };
+
+        Self { info: Some(info) }
+    }
+}
+
+impl TryFrom<proto::LeakInfo> for LeakInfo {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(info: proto::LeakInfo) -> Result<Self, Self::Error> {
+        use proto::leak_info::Info;
+
+        match info.info {
+            Some(Info::NodeReachableOnInterface(info)) => Ok(LeakInfo::NodeReachableOnInterface {
+                interface: info.interface,
+                reachable_nodes: info
+                    .reachable_nodes
+                    .iter()
+                    .map(|node| arg_from_str(node, "invalid reachable node"))
+                    .collect::<Result<_, _>>()?,
+            }),
+            Some(Info::AmIMullvad(info)) => Ok(LeakInfo::AmIMullvad {
+                ip: arg_from_str(&info.ip, "invalid leaked IP")?,
+            }),
+            Some(Info::DnsReachable(info)) => Ok(LeakInfo::DnsReachable {
+                interface: info.interface,
+                resolvers: info
+                    .resolvers
+                    .iter()
+                    .map(|resolver| arg_from_str(resolver, "invalid DNS resolver"))
+                    .collect::<Result<_, _>>()?,
+            }),
+            None => Err(FromProtobufTypeError::InvalidArgument("missing leak info")),
+        }
+    }
+}
+
+impl From<LeakReport> for proto::LeakReport {
+    fn from(report: LeakReport) -> Self {
+        Self {
+            detected_at: Some(timestamp_to_proto(report.detected_at)),
+            tunnel_endpoint: Some(proto::TunnelEndpoint::from(report.tunnel_endpoint)),
+            info: Some(proto::LeakInfo::from(report.info)),
+        }
+    }
+}
+
+impl TryFrom<proto::LeakReport> for LeakReport {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(report: proto::LeakReport) -> Result<Self, Self::Error> {
+        Ok(LeakReport {
+            detected_at: timestamp_from_proto(report.detected_at, "missing leak detection time")?,
+            tunnel_endpoint: tunnel_endpoint_from_proto(report.tunnel_endpoint)?,
+            info: report
+                .info
+                .ok_or(FromProtobufTypeError::InvalidArgument("missing leak info"))
+                .and_then(LeakInfo::try_from)?,
+        })
+    }
+}
+
+impl TryFrom<proto::LeakHistory> for Vec<LeakReport> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(history: proto::LeakHistory) -> Result<Self, Self::Error> {
+        history
+            .reports
+            .into_iter()
+            .map(LeakReport::try_from)
+            .collect()
+    }
+}
+
+impl From<Vec<LeakReport>> for proto::LeakHistory {
+    fn from(reports: Vec<LeakReport>) -> Self {
+        Self {
+            reports: reports.into_iter().map(proto::LeakReport::from).collect(),
+        }
+    }
+}
+
+impl From<LeakTestResult> for proto::LeakTestResult {
+    fn from(result: LeakTestResult) -> Self {
+        use proto::leak_test_result::Result as TestResult;
+
+        let result = match result {
+            LeakTestResult::NoLeak => TestResult::NoLeak(proto::leak_test_result::NoLeak {}),
+            LeakTestResult::Leak(info) => TestResult::Leak(proto::LeakInfo::from(info)),
+            LeakTestResult::Error(error) => TestResult::Error(error),
+        };
+        Self {
+            result: Some(result),
+        }
+    }
+}
+
+impl TryFrom<proto::LeakTestResult> for LeakTestResult {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(result: proto::LeakTestResult) -> Result<Self, Self::Error> {
+        use proto::leak_test_result::Result as TestResult;
+
+        match result.result {
+            Some(TestResult::NoLeak(_)) => Ok(LeakTestResult::NoLeak),
+            Some(TestResult::Leak(info)) => LeakInfo::try_from(info).map(LeakTestResult::Leak),
+            Some(TestResult::Error(error)) => Ok(LeakTestResult::Error(error)),
+            None => Err(FromProtobufTypeError::InvalidArgument(
+                "missing leak test result",
+            )),
+        }
+    }
+}
+
+impl From<LeakTestReport> for proto::LeakTestReport {
+    fn from(report: LeakTestReport) -> Self {
+        Self {
+            tested_at: Some(timestamp_to_proto(report.tested_at)),
+            tunnel_endpoint: Some(proto::TunnelEndpoint::from(report.tunnel_endpoint)),
+            traceroute: Some(proto::LeakTestResult::from(report.traceroute)),
+            am_i_mullvad: Some(proto::LeakTestResult::from(report.am_i_mullvad)),
+            dns: Some(proto::LeakTestResult::from(report.dns)),
+        }
+    }
+}
+
+impl TryFrom<proto::LeakTestReport> for LeakTestReport {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(report: proto::LeakTestReport) -> Result<Self, Self::Error> {
+        let result = |result: Option<proto::LeakTestResult>| {
+            result
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "missing leak test result",
+                ))
+                .and_then(LeakTestResult::try_from)
+        };
+
+        Ok(LeakTestReport {
+            tested_at: timestamp_from_proto(report.tested_at, "missing leak test time")?,
+            tunnel_endpoint: tunnel_endpoint_from_proto(report.tunnel_endpoint)?,
+            traceroute: result(report.traceroute)?,
+            am_i_mullvad: result(report.am_i_mullvad)?,
+            dns: result(report.dns)?,
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use super::{
+    arg_from_str,
+    leak::{timestamp_from_proto, timestamp_to_proto},
+};
+use crate::types::{proto, FromProtobufTypeError};
+use mullvad_types::learned_obfuscation::{LearnedObfuscation, NetworkId, WorkingObfuscation};
+use talpid_types::net::ObfuscationType;
+
+impl From<Vec<LearnedObfuscation>> for proto::LearnedObfuscationTable {
+    fn from(entries: Vec<LearnedObfuscation>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .map(proto::LearnedObfuscation::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::LearnedObfuscationTable> for Vec<LearnedObfuscation> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(table: proto::LearnedObfuscationTable) -> Result<Self, Self::Error> {
+        table
+            .entries
+            .into_iter()
+            .map(LearnedObfuscation::try_from)
+            .collect()
+    }
+}
+
+impl From<LearnedObfuscation> for proto::LearnedObfuscation {
+    fn from(entry: LearnedObfuscation) -> Self {
+        use proto::obfuscation_endpoint::ObfuscationType as ProtoObfuscationType;
+
+        let obfuscation_type = entry.obfuscation.obfuscation.map(|obfuscation| {
+            i32::from(match obfuscation {
+                ObfuscationType::Udp2Tcp => ProtoObfuscationType::Udp2tcp,
+                ObfuscationType::Shadowsocks => ProtoObfuscationType::Shadowsocks,
+                ObfuscationType::Quic => ProtoObfuscationType::Quic,
+            })
+        });
+        Self {
+            network: Some(proto::NetworkId::from(entry.network)),
+            obfuscation_type,
+            port: u32::from(entry.obfuscation.port),
+            ip_version: i32::from(proto::IpVersion::from(entry.obfuscation.ip_version)),
+            last_used: Some(timestamp_to_proto(entry.last_used)),
+        }
+    }
+}
+
+impl TryFrom<proto::LearnedObfuscation> for LearnedObfuscation {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(entry: proto::LearnedObfuscation) -> Result<Self, Self::Error> {
+        use proto::obfuscation_endpoint::ObfuscationType as ProtoObfuscationType;
+
+        let obfuscation = entry
+            .obfuscation_type
+            .map(
+                |obfuscation| match ProtoObfuscationType::try_from(obfuscation) {
+                    Ok(ProtoObfuscationType::Udp2tcp) => Ok(ObfuscationType::Udp2Tcp),
+                    Ok(ProtoObfuscationType::Shadowsocks) => Ok(ObfuscationType::Shadowsocks),
+                    Ok(ProtoObfuscationType::Quic) => Ok(ObfuscationType::Quic),
+                    Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+                        "unknown obfuscation type",
+                    )),
+                },
+            )
+            .transpose()?;
+        let ip_version = proto::IpVersion::try_from(entry.ip_version)
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid IP version"))?;
+
+        Ok(LearnedObfuscation {
+            network: entry
+                .network
+                .ok_or(FromProtobufTypeError::InvalidArgument("missing network"))
+                .and_then(NetworkId::try_from)?,
+            obfuscation: WorkingObfuscation {
+                obfuscation,
+                port: u16::try_from(entry.port)
+                    .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid port"))?,
+                ip_version: ip_version.into(),
+            },
+            last_used: timestamp_from_proto(entry.last_used, "missing last used time")?,
+        })
+    }
+}
+
+impl From<NetworkId> for proto::NetworkId {
+    fn from(network: NetworkId) -> Self {
+        use proto::network_id::Id;
+
+        let id = match network {
+            NetworkId::Ssid(ssid) => Id::Ssid(ssid),
+            NetworkId::GatewayMac(mac) => Id::GatewayMac(mac.to_string()),
+        };
+        Self { id: Some(id) }
+    }
+}
+
+impl TryFrom<proto::NetworkId> for NetworkId {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(network: proto::NetworkId) -> Result<Self, Self::Error> {
+        use proto::network_id::Id;
+
+        match network.id {
+            Some(Id::Ssid(ssid)) => Ok(NetworkId::Ssid(ssid)),
+            Some(Id::GatewayMac(mac)) => Ok(NetworkId::GatewayMac(arg_from_str(
+                &mac,
+                "invalid gateway MAC address",
+            )?)),
+            None => Err(FromProtobufTypeError::InvalidArgument("missing network")),
+        }
+    }
+}
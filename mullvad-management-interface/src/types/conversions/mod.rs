@@ -2,10 +2,15 @@ use std::str::FromStr;
 
 mod access_method;
 mod account;
+mod auto_connect;
+mod custom_endpoint;
 mod custom_list;
 mod custom_tunnel;
 mod device;
+mod dns_blocklist;
 mod features;
+mod leak;
+mod learned_obfuscation;
 mod location;
 mod net;
 pub mod relay_constraints;
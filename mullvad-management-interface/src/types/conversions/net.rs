@@ -39,6 +39,9 @@ impl From<talpid_types::net::TunnelEndpoint> for proto::TunnelEndpoint {
                         net::ObfuscationType::Shadowsocks => {
                             i32::from(proto::obfuscation_endpoint::ObfuscationType::Shadowsocks)
                         }
+                        net::ObfuscationType::Quic => {
+                            i32::from(proto::obfuscation_endpoint::ObfuscationType::Quic)
+                        }
                     },
                 }
             }),
@@ -123,6 +126,9 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
                                 Ok(proto::obfuscation_endpoint::ObfuscationType::Shadowsocks) => {
                                     talpid_net::ObfuscationType::Shadowsocks
                                 }
+                                Ok(proto::obfuscation_endpoint::ObfuscationType::Quic) => {
+                                    talpid_net::ObfuscationType::Quic
+                                }
                                 Err(_) => {
                                     return Err(FromProtobufTypeError::InvalidArgument(
                                         "unknown obfuscation type",
@@ -207,11 +213,11 @@ pub fn try_transport_protocol_from_i32(
 }
 
 mod proxy {
-    use std::net::Ipv4Addr;
+    use std::net::{IpAddr, Ipv4Addr};
 
     use crate::types::{proto, FromProtobufTypeError};
     use talpid_types::net::proxy::{
-        CustomProxy, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
+        CustomProxy, HttpAuth, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
     };
 
     impl TryFrom<proto::CustomProxy> for CustomProxy {
@@ -228,6 +234,9 @@ mod proxy {
                 Some(proto::custom_proxy::ProxyMethod::Shadowsocks(shadowsocks)) => {
                     CustomProxy::Shadowsocks(Shadowsocks::try_from(shadowsocks)?)
                 }
+                Some(proto::custom_proxy::ProxyMethod::HttpConnect(http)) => {
+                    CustomProxy::HttpConnect(HttpConnect::try_from(http)?)
+                }
                 None => {
                     return Err(FromProtobufTypeError::InvalidArgument(
                         "CustomProxy missing proxy_method field",
@@ -295,6 +304,27 @@ mod proxy {
         }
     }
 
+    impl TryFrom<proto::HttpConnect> for HttpConnect {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpConnect) -> Result<Self, Self::Error> {
+            let ip = value.ip.parse::<IpAddr>().map_err(|_| {
+                FromProtobufTypeError::InvalidArgument(
+                    "Could not parse HTTP CONNECT proxy message from protobuf",
+                )
+            })?;
+            let mut http = HttpConnect::new((ip, value.port as u16));
+            if let Some(credentials) = value.auth {
+                http = http.with_authentication(HttpAuth::try_from(credentials)?);
+            }
+            if let Some(server_name) = value.tls_server_name {
+                http = http.with_tls(server_name);
+            }
+
+            Ok(http)
+        }
+    }
+
     impl From<CustomProxy> for proto::CustomProxy {
         fn from(value: CustomProxy) -> Self {
             proto::CustomProxy {
@@ -314,6 +344,11 @@ mod proxy {
                             config,
                         ))
                     }
+                    CustomProxy::HttpConnect(config) => {
+                        proto::custom_proxy::ProxyMethod::HttpConnect(proto::HttpConnect::from(
+                            config,
+                        ))
+                    }
                 }),
             }
         }
@@ -353,6 +388,39 @@ mod proxy {
         }
     }
 
+    impl From<HttpConnect> for proto::HttpConnect {
+        fn from(value: HttpConnect) -> Self {
+            proto::HttpConnect {
+                ip: value.endpoint.ip().to_string(),
+                port: value.endpoint.port() as u32,
+                auth: value.auth.map(proto::HttpAuth::from),
+                tls_server_name: value.tls_server_name,
+            }
+        }
+    }
+
+    impl From<HttpAuth> for proto::HttpAuth {
+        fn from(value: HttpAuth) -> Self {
+            proto::HttpAuth {
+                username: value.username().to_string(),
+                password: value.password().to_string(),
+            }
+        }
+    }
+
+    impl TryFrom<proto::HttpAuth> for HttpAuth {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpAuth) -> Result<Self, Self::Error> {
+            HttpAuth::new(value.username, value.password).map_err(|_| {
+                FromProtobufTypeError::InvalidArgument(
+                    "Failed to parse HTTP CONNECT proxy with authentication. \
+                     Make sure the credentials are valid.",
+                )
+            })
+        }
+    }
+
     impl From<SocksAuth> for proto::SocksAuth {
         fn from(value: SocksAuth) -> Self {
             proto::SocksAuth {
@@ -375,3 +443,47 @@ mod proxy {
         }
     }
 }
+
+impl From<talpid_types::net::dns::EncryptedDnsUpstream> for proto::EncryptedDnsUpstream {
+    fn from(upstream: talpid_types::net::dns::EncryptedDnsUpstream) -> Self {
+        use talpid_types::net::dns::EncryptedDnsProtocol;
+
+        let protocol = match upstream.protocol {
+            EncryptedDnsProtocol::Https => proto::encrypted_dns_upstream::Protocol::Https,
+            EncryptedDnsProtocol::Tls => proto::encrypted_dns_upstream::Protocol::Tls,
+        };
+        proto::EncryptedDnsUpstream {
+            protocol: i32::from(protocol),
+            address: upstream.address.to_string(),
+            hostname: upstream.hostname,
+        }
+    }
+}
+
+impl TryFrom<proto::EncryptedDnsUpstream> for talpid_types::net::dns::EncryptedDnsUpstream {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(upstream: proto::EncryptedDnsUpstream) -> Result<Self, Self::Error> {
+        use talpid_types::net::dns::EncryptedDnsProtocol;
+
+        let protocol = match proto::encrypted_dns_upstream::Protocol::try_from(upstream.protocol) {
+            Ok(proto::encrypted_dns_upstream::Protocol::Https) => EncryptedDnsProtocol::Https,
+            Ok(proto::encrypted_dns_upstream::Protocol::Tls) => EncryptedDnsProtocol::Tls,
+            Err(_) => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid encrypted DNS protocol",
+                ))
+            }
+        };
+        if upstream.hostname.is_empty() {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "missing encrypted DNS hostname",
+            ));
+        }
+        Ok(talpid_types::net::dns::EncryptedDnsUpstream {
+            protocol,
+            address: arg_from_str(&upstream.address, "invalid encrypted DNS address")?,
+            hostname: upstream.hostname,
+        })
+    }
+}
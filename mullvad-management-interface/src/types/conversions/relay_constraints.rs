@@ -3,7 +3,7 @@ use mullvad_types::{
     constraints::Constraint, custom_list::Id, relay_constraints::GeographicLocationConstraint,
 };
 use std::str::FromStr;
-use talpid_types::net::proxy::CustomProxy;
+use talpid_types::net::proxy::{CustomProxy, Shadowsocks};
 
 impl TryFrom<&proto::WireguardConstraints>
     for mullvad_types::relay_constraints::WireguardConstraints
@@ -67,6 +67,7 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
         settings: proto::RelaySettings,
     ) -> Result<mullvad_types::relay_constraints::RelaySettings, Self::Error> {
         use mullvad_types::{relay_constraints as mullvad_constraints, CustomTunnelEndpoint};
+        use std::str::FromStr;
 
         let update_value = settings
             .endpoint
@@ -76,17 +77,20 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
 
         match update_value {
             proto::relay_settings::Endpoint::Custom(settings) => {
-                let config = settings
-                    .config
-                    .ok_or(FromProtobufTypeError::InvalidArgument(
-                        "missing relay connection config",
-                    ))?;
-                let config = mullvad_types::ConnectionConfig::try_from(config)?;
                 Ok(mullvad_constraints::RelaySettings::CustomTunnelEndpoint(
-                    CustomTunnelEndpoint {
-                        host: settings.host,
-                        config,
-                    },
+                    CustomTunnelEndpoint::try_from(settings)?,
+                ))
+            }
+
+            proto::relay_settings::Endpoint::SavedCustomEndpoints(settings) => {
+                let ids = settings
+                    .endpoint_ids
+                    .iter()
+                    .map(|id| mullvad_types::custom_endpoint::Id::from_str(id))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| FromProtobufTypeError::InvalidArgument("Invalid endpoint ID"))?;
+                Ok(mullvad_constraints::RelaySettings::SavedCustomEndpoints(
+                    ids,
                 ))
             }
 
@@ -98,6 +102,11 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
                 let providers = try_providers_constraint_from_proto(&settings.providers)?;
                 let ownership = try_ownership_constraint_from_i32(settings.ownership)?;
                 let tunnel_protocol = try_tunnel_type_from_i32(settings.tunnel_type)?;
+                let selection_mode = proto::RelaySelectionMode::try_from(settings.selection_mode)
+                    .map(mullvad_constraints::SelectionMode::from)
+                    .map_err(|_| {
+                        FromProtobufTypeError::InvalidArgument("invalid relay selection mode")
+                    })?;
 
                 let openvpn_constraints =
                     mullvad_constraints::OpenVpnConstraints::try_from(
@@ -119,6 +128,7 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
                         tunnel_protocol,
                         wireguard_constraints,
                         openvpn_constraints,
+                        selection_mode,
                     },
                 ))
             }
@@ -151,11 +161,17 @@ impl From<&mullvad_types::relay_constraints::ObfuscationSettings> for proto::Obf
             SelectedObfuscation::Shadowsocks => {
                 proto::obfuscation_settings::SelectedObfuscation::Shadowsocks
             }
+            SelectedObfuscation::Quic => proto::obfuscation_settings::SelectedObfuscation::Quic,
         });
         Self {
             selected_obfuscation,
             udp2tcp: Some(proto::Udp2TcpObfuscationSettings::from(&settings.udp2tcp)),
             shadowsocks: Some(proto::ShadowsocksSettings::from(&settings.shadowsocks)),
+            custom_shadowsocks: settings
+                .custom_shadowsocks
+                .clone()
+                .map(proto::Shadowsocks::from),
+            learn_per_network: settings.learn_per_network,
         }
     }
 }
@@ -172,6 +188,13 @@ impl From<&mullvad_types::relay_constraints::Udp2TcpObfuscationSettings>
     fn from(settings: &mullvad_types::relay_constraints::Udp2TcpObfuscationSettings) -> Self {
         Self {
             port: settings.port.map(u32::from).option(),
+            websocket: settings
+                .websocket
+                .as_ref()
+                .map(|websocket| proto::WebSocketSettings {
+                    server_name: websocket.server_name.clone(),
+                    path: websocket.path.clone(),
+                }),
         }
     }
 }
@@ -225,9 +248,11 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
 
         let endpoint = match settings {
             MullvadRelaySettings::CustomTunnelEndpoint(endpoint) => {
-                relay_settings::Endpoint::Custom(proto::CustomRelaySettings {
-                    host: endpoint.host,
-                    config: Some(proto::ConnectionConfig::from(endpoint.config)),
+                relay_settings::Endpoint::Custom(proto::CustomRelaySettings::from(endpoint))
+            }
+            MullvadRelaySettings::SavedCustomEndpoints(ids) => {
+                relay_settings::Endpoint::SavedCustomEndpoints(proto::SavedCustomEndpoints {
+                    endpoint_ids: ids.iter().map(|id| id.to_string()).collect(),
                 })
             }
             MullvadRelaySettings::Normal(constraints) => {
@@ -266,6 +291,10 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
                             .option()
                             .map(proto::TransportPort::from),
                     }),
+
+                    selection_mode: i32::from(proto::RelaySelectionMode::from(
+                        constraints.selection_mode,
+                    )),
                 })
             }
         };
@@ -276,6 +305,26 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
     }
 }
 
+impl From<mullvad_types::relay_constraints::SelectionMode> for proto::RelaySelectionMode {
+    fn from(mode: mullvad_types::relay_constraints::SelectionMode) -> Self {
+        use mullvad_types::relay_constraints::SelectionMode;
+        match mode {
+            SelectionMode::Random => proto::RelaySelectionMode::Random,
+            SelectionMode::Fastest => proto::RelaySelectionMode::Fastest,
+        }
+    }
+}
+
+impl From<proto::RelaySelectionMode> for mullvad_types::relay_constraints::SelectionMode {
+    fn from(mode: proto::RelaySelectionMode) -> Self {
+        use mullvad_types::relay_constraints::SelectionMode;
+        match mode {
+            proto::RelaySelectionMode::Random => SelectionMode::Random,
+            proto::RelaySelectionMode::Fastest => SelectionMode::Fastest,
+        }
+    }
+}
+
 impl From<mullvad_types::relay_constraints::TransportPort> for proto::TransportPort {
     fn from(port: mullvad_types::relay_constraints::TransportPort) -> Self {
         proto::TransportPort {
@@ -437,6 +486,7 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
                 Ok(IpcSelectedObfuscation::Off) => SelectedObfuscation::Off,
                 Ok(IpcSelectedObfuscation::Udp2tcp) => SelectedObfuscation::Udp2Tcp,
                 Ok(IpcSelectedObfuscation::Shadowsocks) => SelectedObfuscation::Shadowsocks,
+                Ok(IpcSelectedObfuscation::Quic) => SelectedObfuscation::Quic,
                 Err(_) => {
                     return Err(FromProtobufTypeError::InvalidArgument(
                         "invalid obfuscation settings",
@@ -465,10 +515,30 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
             }
         };
 
+        let custom_shadowsocks = settings
+            .custom_shadowsocks
+            .map(Shadowsocks::try_from)
+            .transpose()?;
+        if let Some(server) = &custom_shadowsocks {
+            server.validate_for_obfuscation().map_err(|_| {
+                FromProtobufTypeError::InvalidArgument("invalid self-hosted shadowsocks server")
+            })?;
+        }
+
+        // Networks can only be identified on Linux and macOS
+        if settings.learn_per_network && cfg!(not(any(target_os = "linux", target_os = "macos")))
+        {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "learning obfuscation per network is not supported on this platform",
+            ));
+        }
+
         Ok(Self {
             selected_obfuscation,
             udp2tcp,
             shadowsocks,
+            custom_shadowsocks,
+            learn_per_network: settings.learn_per_network,
         })
     }
 }
@@ -479,8 +549,26 @@ impl TryFrom<&proto::Udp2TcpObfuscationSettings>
     type Error = FromProtobufTypeError;
 
     fn try_from(settings: &proto::Udp2TcpObfuscationSettings) -> Result<Self, Self::Error> {
+        let websocket = settings
+            .websocket
+            .as_ref()
+            .map(|websocket| {
+                let websocket = talpid_types::net::obfuscation::WebSocketSettings {
+                    server_name: websocket.server_name.clone(),
+                    path: websocket.path.clone(),
+                };
+                if websocket.is_valid() {
+                    Ok(websocket)
+                } else {
+                    Err(FromProtobufTypeError::InvalidArgument(
+                        "invalid WebSocket settings",
+                    ))
+                }
+            })
+            .transpose()?;
         Ok(Self {
             port: Constraint::from(settings.port.map(|port| port as u16)),
+            websocket,
         })
     }
 }
@@ -140,6 +140,10 @@ impl From<mullvad_types::relay_list::Relay> for proto::Relay {
                             .iter()
                             .map(|addr| addr.to_string())
                             .collect(),
+                        quic: data.quic.map(|quic| proto::QuicEndpointData {
+                            addr_in: quic.addr_in.iter().map(|addr| addr.to_string()).collect(),
+                            hostname: quic.hostname,
+                        }),
                     },
                 )),
                 _ => None,
@@ -266,6 +270,27 @@ impl TryFrom<proto::Relay> for mullvad_types::relay_list::Relay {
                                 })
                             })
                             .collect::<Result<_, FromProtobufTypeError>>()?,
+                        quic: data
+                            .quic
+                            .map(|quic| {
+                                Ok::<_, FromProtobufTypeError>(
+                                    mullvad_types::relay_list::QuicEndpointData {
+                                        addr_in: quic
+                                            .addr_in
+                                            .iter()
+                                            .map(|addr| {
+                                                addr.parse().map_err(|_err| {
+                                                    FromProtobufTypeError::InvalidArgument(
+                                                        "invalid QUIC server address",
+                                                    )
+                                                })
+                                            })
+                                            .collect::<Result<_, _>>()?,
+                                        hostname: quic.hostname,
+                                    },
+                                )
+                            })
+                            .transpose()?,
                     },
                 )
             }
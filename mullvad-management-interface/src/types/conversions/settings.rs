@@ -1,10 +1,10 @@
+use super::arg_from_str;
 use crate::types::{proto, FromProtobufTypeError};
 use mullvad_types::settings::CURRENT_SETTINGS_VERSION;
 use talpid_types::ErrorExt;
 
 impl From<&mullvad_types::settings::Settings> for proto::Settings {
     fn from(settings: &mullvad_types::settings::Settings) -> Self {
-        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
         let split_tunnel = {
             let apps = settings
                 .split_tunnel
@@ -24,8 +24,10 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
                 apps,
             })
         };
-        #[cfg(target_os = "linux")]
-        let split_tunnel = None;
+        #[cfg(not(target_os = "android"))]
+        let allowed_lan_nets = Some(proto::AllowedLanNets::from(&settings.allowed_lan_nets));
+        #[cfg(target_os = "android")]
+        let allowed_lan_nets = None;
 
         Self {
             relay_settings: Some(proto::RelaySettings::from(settings.get_relay_settings())),
@@ -34,11 +36,18 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             )),
             bridge_state: Some(proto::BridgeState::from(settings.bridge_state)),
             allow_lan: settings.allow_lan,
+            allowed_lan_nets,
             #[cfg(not(target_os = "android"))]
             block_when_disconnected: settings.block_when_disconnected,
             #[cfg(target_os = "android")]
             block_when_disconnected: false,
             auto_connect: settings.auto_connect,
+            auto_connect_rules: Some(proto::AutoConnectRules::from(
+                settings.auto_connect_rules.clone(),
+            )),
+            trusted_networks: Some(proto::TrustedNetworks::from(
+                settings.trusted_networks.clone(),
+            )),
             tunnel_options: Some(proto::TunnelOptions::from(&settings.tunnel_options)),
             show_beta_releases: settings.show_beta_releases,
             obfuscation_settings: Some(proto::ObfuscationSettings::from(
@@ -48,6 +57,9 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             custom_lists: Some(proto::CustomListSettings::from(
                 settings.custom_lists.clone(),
             )),
+            custom_endpoints: Some(proto::CustomEndpointSettings::from(
+                settings.custom_endpoints.clone(),
+            )),
             api_access_methods: Some(proto::ApiAccessMethodSettings::from(
                 settings.api_access_methods.clone(),
             )),
@@ -85,7 +97,25 @@ impl From<&mullvad_types::settings::DnsOptions> for proto::DnsOptions {
                     .iter()
                     .map(|addr| addr.to_string())
                     .collect(),
+                encrypted_upstreams: options
+                    .custom_options
+                    .encrypted_upstreams
+                    .iter()
+                    .cloned()
+                    .map(proto::EncryptedDnsUpstream::from)
+                    .collect(),
             }),
+            split_dns: options
+                .split_dns
+                .iter()
+                .map(|(domain, resolvers)| proto::SplitDnsRoute {
+                    domain: domain.clone(),
+                    resolvers: resolvers.iter().map(|addr| addr.to_string()).collect(),
+                })
+                .collect(),
+            local_blocklists: Some(proto::LocalBlocklistOptions::from(
+                &options.local_blocklists,
+            )),
         }
     }
 }
@@ -162,7 +192,27 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
                 .ok_or(FromProtobufTypeError::InvalidArgument(
                     "missing api access methods settings",
                 ))?;
-        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+        let custom_endpoints = settings
+            .custom_endpoints
+            .map(mullvad_types::custom_endpoint::CustomEndpointsSettings::try_from)
+            .transpose()?
+            .unwrap_or_default();
+        let auto_connect_rules = settings
+            .auto_connect_rules
+            .map(mullvad_types::auto_connect::AutoConnectRules::try_from)
+            .transpose()?
+            .unwrap_or_default();
+        let trusted_networks = settings
+            .trusted_networks
+            .map(Vec::<mullvad_types::auto_connect::TrustedNetwork>::try_from)
+            .transpose()?
+            .unwrap_or_default();
+        #[cfg(not(target_os = "android"))]
+        let allowed_lan_nets = settings
+            .allowed_lan_nets
+            .map(talpid_types::net::AllowedLanNets::try_from)
+            .transpose()?
+            .unwrap_or_default();
         let split_tunnel = settings
             .split_tunnel
             .ok_or(FromProtobufTypeError::InvalidArgument(
@@ -179,8 +229,12 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             bridge_state,
             allow_lan: settings.allow_lan,
             #[cfg(not(target_os = "android"))]
+            allowed_lan_nets,
+            #[cfg(not(target_os = "android"))]
             block_when_disconnected: settings.block_when_disconnected,
             auto_connect: settings.auto_connect,
+            auto_connect_rules,
+            trusted_networks,
             tunnel_options: mullvad_types::settings::TunnelOptions::try_from(tunnel_options)?,
             relay_overrides: settings
                 .relay_overrides
@@ -188,7 +242,6 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
                 .map(mullvad_types::relay_constraints::RelayOverride::try_from)
                 .collect::<Result<Vec<_>, _>>()?,
             show_beta_releases: settings.show_beta_releases,
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             split_tunnel: mullvad_types::settings::SplitTunnelSettings::from(split_tunnel),
             obfuscation_settings: mullvad_types::relay_constraints::ObfuscationSettings::try_from(
                 obfuscation_settings,
@@ -199,6 +252,7 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             custom_lists: mullvad_types::custom_list::CustomListsSettings::try_from(
                 custom_lists_settings,
             )?,
+            custom_endpoints,
             api_access_methods: mullvad_types::access_method::Settings::try_from(
                 api_access_methods_settings,
             )?,
@@ -206,6 +260,38 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
     }
 }
 
+impl From<&talpid_types::net::AllowedLanNets> for proto::AllowedLanNets {
+    fn from(allowed_lan_nets: &talpid_types::net::AllowedLanNets) -> Self {
+        Self {
+            custom_nets: allowed_lan_nets
+                .custom_nets()
+                .iter()
+                .map(|net| net.to_string())
+                .collect(),
+            replace_default: allowed_lan_nets.replace_default(),
+        }
+    }
+}
+
+impl TryFrom<proto::AllowedLanNets> for talpid_types::net::AllowedLanNets {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(allowed_lan_nets: proto::AllowedLanNets) -> Result<Self, Self::Error> {
+        let custom_nets = allowed_lan_nets
+            .custom_nets
+            .iter()
+            .map(|net| net.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid LAN network"))?;
+        Self::new(custom_nets, allowed_lan_nets.replace_default).map_err(|error| {
+            log::error!("Rejecting custom LAN networks: {error}");
+            FromProtobufTypeError::InvalidArgument(
+                "LAN networks must be private, link-local or unique local networks",
+            )
+        })
+    }
+}
+
 pub fn try_bridge_state_from_i32(
     bridge_state: i32,
 ) -> Result<mullvad_types::relay_constraints::BridgeState, FromProtobufTypeError> {
@@ -223,7 +309,6 @@ pub fn try_bridge_state_from_i32(
     }
 }
 
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
 impl From<proto::SplitTunnelSettings> for mullvad_types::settings::SplitTunnelSettings {
     fn from(value: proto::SplitTunnelSettings) -> Self {
         use mullvad_types::settings::{SplitApp, SplitTunnelSettings};
@@ -356,7 +441,33 @@ impl TryFrom<proto::DnsOptions> for mullvad_types::settings::DnsOptions {
                         })
                     })
                     .collect::<Result<Vec<_>, _>>()?,
+                encrypted_upstreams: custom_options
+                    .encrypted_upstreams
+                    .into_iter()
+                    .map(talpid_types::net::dns::EncryptedDnsUpstream::try_from)
+                    .collect::<Result<Vec<_>, _>>()?,
             },
+            split_dns: options
+                .split_dns
+                .into_iter()
+                .map(|route| {
+                    let domain =
+                        talpid_types::net::dns::normalize_domain(&route.domain).map_err(|_| {
+                            FromProtobufTypeError::InvalidArgument("invalid split DNS domain")
+                        })?;
+                    let resolvers = route
+                        .resolvers
+                        .iter()
+                        .map(|addr| arg_from_str(addr, "invalid split DNS resolver"))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok((domain, resolvers))
+                })
+                .collect::<Result<_, _>>()?,
+            local_blocklists: options
+                .local_blocklists
+                .map(mullvad_types::settings::LocalBlocklistOptions::try_from)
+                .transpose()?
+                .unwrap_or_default(),
         })
     }
 }
@@ -2,6 +2,7 @@ use super::FromProtobufTypeError;
 use crate::types::proto;
 use chrono::DateTime;
 use prost_types::Timestamp;
+use std::time::{Duration, SystemTime};
 
 impl From<mullvad_types::wireguard::PublicKey> for proto::PublicKey {
     fn from(public_key: mullvad_types::wireguard::PublicKey) -> Self {
@@ -92,3 +93,92 @@ impl From<proto::DaitaSettings> for mullvad_types::wireguard::DaitaSettings {
         }
     }
 }
+
+impl From<talpid_types::net::wireguard::TunnelStatistics> for proto::TunnelStatistics {
+    fn from(statistics: talpid_types::net::wireguard::TunnelStatistics) -> Self {
+        proto::TunnelStatistics {
+            peers: statistics
+                .peers
+                .into_iter()
+                .map(proto::PeerStatistics::from)
+                .collect(),
+            tx_packets: statistics.tx_packets,
+            rx_packets: statistics.rx_packets,
+            mtu: u32::from(statistics.mtu),
+            ping_rtts: statistics
+                .ping_rtts
+                .into_iter()
+                .map(duration_to_proto)
+                .collect(),
+            uptime: Some(duration_to_proto(statistics.uptime)),
+        }
+    }
+}
+
+impl From<talpid_types::net::wireguard::PeerStatistics> for proto::PeerStatistics {
+    fn from(statistics: talpid_types::net::wireguard::PeerStatistics) -> Self {
+        proto::PeerStatistics {
+            public_key: statistics.public_key.as_bytes().to_vec(),
+            tx_bytes: statistics.tx_bytes,
+            rx_bytes: statistics.rx_bytes,
+            last_handshake: statistics.last_handshake.map(Timestamp::from),
+            handshakes: statistics.handshakes,
+        }
+    }
+}
+
+impl TryFrom<proto::TunnelStatistics> for talpid_types::net::wireguard::TunnelStatistics {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(statistics: proto::TunnelStatistics) -> Result<Self, Self::Error> {
+        Ok(talpid_types::net::wireguard::TunnelStatistics {
+            peers: statistics
+                .peers
+                .into_iter()
+                .map(talpid_types::net::wireguard::PeerStatistics::try_from)
+                .collect::<Result<_, _>>()?,
+            tx_packets: statistics.tx_packets,
+            rx_packets: statistics.rx_packets,
+            mtu: u16::try_from(statistics.mtu)
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid MTU"))?,
+            ping_rtts: statistics
+                .ping_rtts
+                .into_iter()
+                .map(duration_from_proto)
+                .collect::<Result<_, _>>()?,
+            uptime: duration_from_proto(statistics.uptime.ok_or(
+                FromProtobufTypeError::InvalidArgument("missing tunnel uptime"),
+            )?)?,
+        })
+    }
+}
+
+impl TryFrom<proto::PeerStatistics> for talpid_types::net::wireguard::PeerStatistics {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(statistics: proto::PeerStatistics) -> Result<Self, Self::Error> {
+        Ok(talpid_types::net::wireguard::PeerStatistics {
+            public_key: talpid_types::net::wireguard::PublicKey::try_from(
+                statistics.public_key.as_slice(),
+            )
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid wireguard key"))?,
+            tx_bytes: statistics.tx_bytes,
+            rx_bytes: statistics.rx_bytes,
+            last_handshake: statistics
+                .last_handshake
+                .map(SystemTime::try_from)
+                .transpose()
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid timestamp"))?,
+            handshakes: statistics.handshakes,
+        })
+    }
+}
+
+fn duration_to_proto(duration: Duration) -> prost_types::Duration {
+    prost_types::Duration::try_from(duration).expect("Duration is too large for protobuf")
+}
+
+fn duration_from_proto(duration: prost_types::Duration) -> Result<Duration, FromProtobufTypeError> {
+    Duration::try_from(duration)
+        .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid duration"))
+}
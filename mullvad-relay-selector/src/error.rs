@@ -22,6 +22,9 @@ pub enum Error {
     #[error("No bridges matching current constraints")]
     NoBridge,
 
+    #[error("None of the selected custom endpoints exist")]
+    NoCustomEndpoint,
+
     #[error("No obfuscators matching current constraints")]
     NoObfuscator(#[source] Box<dyn std::error::Error + Send + Sync>),
 
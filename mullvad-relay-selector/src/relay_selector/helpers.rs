@@ -8,14 +8,16 @@ use std::{
 use mullvad_types::{
     constraints::Constraint,
     endpoint::MullvadWireguardEndpoint,
-    relay_constraints::{ShadowsocksSettings, Udp2TcpObfuscationSettings},
-    relay_list::Relay,
+    relay_constraints::{
+        ObfuscationSettings, SelectedObfuscation, ShadowsocksSettings, Udp2TcpObfuscationSettings,
+    },
+    relay_list::{QuicEndpointData, Relay, RelayEndpointData},
 };
 use rand::{
     seq::{IteratorRandom, SliceRandom},
     thread_rng, Rng,
 };
-use talpid_types::net::obfuscation::ObfuscatorConfig;
+use talpid_types::net::{obfuscation::ObfuscatorConfig, proxy};
 
 use crate::SelectedObfuscator;
 
@@ -27,6 +29,10 @@ const SHADOWSOCKS_EXTRA_PORT_RANGES: &[RangeInclusive<u16>] = &[1..=u16::MAX];
 pub enum Error {
     #[error("Found no valid port matching the selected settings")]
     NoMatchingPort,
+    #[error("The relay has no QUIC server for the selected IP version")]
+    NoQuicEndpoint,
+    #[error("Invalid self-hosted Shadowsocks server")]
+    InvalidCustomShadowsocks(#[source] proxy::Error),
 }
 
 /// Picks a relay at random from `relays`, but don't pick `exclude`.
@@ -93,6 +99,7 @@ pub fn get_udp2tcp_obfuscator(
         get_udp2tcp_obfuscator_port(obfuscation_settings_constraint, udp2tcp_ports)?;
     let config = ObfuscatorConfig::Udp2Tcp {
         endpoint: SocketAddr::new(endpoint.peer.endpoint.ip(), udp2tcp_endpoint_port),
+        websocket: obfuscation_settings_constraint.websocket.clone(),
     };
 
     Ok(SelectedObfuscator { config, relay })
@@ -141,6 +148,53 @@ pub fn get_shadowsocks_obfuscator(
     })
 }
 
+pub fn get_quic_obfuscator(
+    relay: Relay,
+    endpoint: &MullvadWireguardEndpoint,
+) -> Result<SelectedObfuscator, Error> {
+    let quic = match &relay.endpoint_data {
+        RelayEndpointData::Wireguard(wg) => wg.quic.as_ref().ok_or(Error::NoQuicEndpoint)?,
+        _ => panic!("expected wireguard relay"),
+    };
+
+    // Use a server of the same address family as the WireGuard endpoint
+    let wg_in_addr = endpoint.peer.endpoint.ip();
+    let in_ip = quic
+        .addr_in
+        .iter()
+        .filter(|addr| addr.is_ipv4() == wg_in_addr.is_ipv4())
+        .choose(&mut thread_rng())
+        .copied()
+        .ok_or(Error::NoQuicEndpoint)?;
+
+    let config = ObfuscatorConfig::Quic {
+        endpoint: SocketAddr::new(in_ip, QuicEndpointData::PORT),
+        hostname: quic.hostname.clone(),
+    };
+
+    Ok(SelectedObfuscator { config, relay })
+}
+
+/// Returns the obfuscator to use for a custom WireGuard endpoint, if any.
+///
+/// Only self-hosted obfuscators are applicable here, since Mullvad operated ones cannot reach the
+/// custom peer.
+pub fn get_custom_obfuscator(
+    settings: &ObfuscationSettings,
+) -> Result<Option<ObfuscatorConfig>, Error> {
+    match (&settings.selected_obfuscation, &settings.custom_shadowsocks) {
+        (SelectedObfuscation::Shadowsocks, Some(server)) => {
+            server
+                .validate_for_obfuscation()
+                .map_err(Error::InvalidCustomShadowsocks)?;
+            Ok(Some(ObfuscatorConfig::CustomShadowsocks {
+                server: server.clone(),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
 /// Return an obfuscation config for the wireguard server at `wg_in_addr` or one of `extra_in_addrs`
 /// (unless empty). `wg_in_addr_port_ranges` contains all valid ports for `wg_in_addr`, and
 /// `SHADOWSOCKS_EXTRA_PORT_RANGES` contains valid ports for `extra_in_addrs`.
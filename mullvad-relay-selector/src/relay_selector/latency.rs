@@ -0,0 +1,379 @@
+//! Latency-aware relay selection.
+//!
+//! By default, the relay selector picks a relay at random among the relays that match a
+//! [`RelayQuery`], using the relay weights. When the [`SelectionMode::Fastest`] mode is set, the
+//! matching relays are first ranked by their measured latency, and a relay is only picked among
+//! the ones that are (roughly) the fastest.
+//!
+//! Selecting a relay never probes anything. Latencies are measured ahead of time by a
+//! [`LatencyRanker`], which probes many relays concurrently using a [`LatencyProber`], and caches
+//! the results for a while, so that successive measurements do not have to probe the same relays
+//! again. Failed probes are not cached, since a relay may only have been unreachable because of
+//! e.g. the firewall or a network change, so such relays are probed again by the next
+//! measurement.
+//!
+//! [`RelayQuery`]: crate::query::RelayQuery
+//! [`SelectionMode::Fastest`]: mullvad_types::relay_constraints::SelectionMode::Fastest
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use itertools::Itertools;
+use mullvad_types::relay_list::Relay;
+
+/// Default amount of time that a latency measurement is considered valid.
+pub const DEFAULT_LATENCY_EXPIRY: Duration = Duration::from_secs(10 * 60);
+
+/// Relays whose latency is within this margin of the fastest relay are considered equally fast.
+/// This keeps some variety in the selection instead of always picking the very same relay.
+pub const DEFAULT_LATENCY_TOLERANCE: Duration = Duration::from_millis(5);
+
+/// Maximum number of relays that are probed at the same time.
+const MAX_CONCURRENT_PROBES: usize = 32;
+
+/// Measures the latency to a relay, e.g. by sending ICMP echo requests or by timing a TCP
+/// handshake.
+///
+/// Implementations should enforce a reasonable timeout of their own, since a relay that never
+/// answers would otherwise hold up the measurement of all the others.
+pub trait LatencyProber: Send + Sync {
+    /// Return the round-trip time to `relay`, or `None` if the relay could not be reached.
+    fn probe(&self, relay: &Relay) -> Pin<Box<dyn Future<Output = Option<Duration>> + Send>>;
+}
+
+/// How the relay selector picks among the relays that match a query, for a single selection.
+#[derive(Clone, Default)]
+pub(crate) enum SelectionMode {
+    /// Pick a random relay, weighted by the relay weights.
+    #[default]
+    Random,
+    /// Prefer the relays with the lowest latency, as ranked by a [`LatencyRanker`].
+    Fastest(Arc<LatencyRanker>),
+}
+
+impl SelectionMode {
+    /// Narrow down `candidates` according to the selection mode. The returned relays still have
+    /// to be picked from at random.
+    pub(crate) fn candidates(&self, candidates: Vec<Relay>) -> Vec<Relay> {
+        self.fastest(&candidates).unwrap_or(candidates)
+    }
+
+    /// Return the fastest relays in `candidates`, or `None` if relays should not be picked based
+    /// on latency, or no relay in `candidates` is known to be reachable.
+    pub(crate) fn fastest(&self, candidates: &[Relay]) -> Option<Vec<Relay>> {
+        match self {
+            SelectionMode::Random => None,
+            SelectionMode::Fastest(ranker) => ranker.fastest(candidates),
+        }
+    }
+}
+
+/// Ranks relays by latency using a [`LatencyProber`], caching the results.
+pub struct LatencyRanker {
+    prober: Box<dyn LatencyProber>,
+    cache: Mutex<LatencyCache>,
+    tolerance: Duration,
+}
+
+impl LatencyRanker {
+    /// Create a ranker using [`DEFAULT_LATENCY_EXPIRY`] and [`DEFAULT_LATENCY_TOLERANCE`].
+    pub fn new(prober: impl LatencyProber + 'static) -> Self {
+        Self::with_parameters(prober, DEFAULT_LATENCY_EXPIRY, DEFAULT_LATENCY_TOLERANCE)
+    }
+
+    /// Create a ranker which caches measurements for `expiry`, and treats relays whose latency is
+    /// within `tolerance` of the fastest relay as equally fast.
+    pub fn with_parameters(
+        prober: impl LatencyProber + 'static,
+        expiry: Duration,
+        tolerance: Duration,
+    ) -> Self {
+        LatencyRanker {
+            prober: Box::new(prober),
+            cache: Mutex::new(LatencyCache::new(expiry)),
+            tolerance,
+        }
+    }
+
+    /// Probe every relay in `relays` that has no valid cached measurement, and cache the latency
+    /// to the relays that could be reached. Up to
+    /// [`MAX_CONCURRENT_PROBES`] relays are probed at the same time, and the cache is not locked
+    /// while waiting for a probe.
+    pub async fn measure(&self, relays: Vec<Relay>) {
+        let now = Instant::now();
+        let unmeasured: Vec<Relay> = {
+            let mut cache = self.cache.lock().unwrap();
+            relays
+                .into_iter()
+                .unique_by(|relay| relay.hostname.clone())
+                .filter(|relay| cache.get(&relay.hostname, now).is_none())
+                .collect()
+        };
+        if unmeasured.is_empty() {
+            return;
+        }
+        log::debug!("Measuring latency to {} relays", unmeasured.len());
+
+        futures::stream::iter(unmeasured)
+            .map(|relay| {
+                let probe = self.prober.probe(&relay);
+                async move { (relay.hostname, probe.await) }
+            })
+            .buffer_unordered(MAX_CONCURRENT_PROBES)
+            .for_each(|(hostname, latency)| {
+                log::trace!("Measured latency to {hostname}: {latency:?}");
+                if let Some(latency) = latency {
+                    self.cache
+                        .lock()
+                        .unwrap()
+                        .insert(hostname, latency, Instant::now());
+                }
+                std::future::ready(())
+            })
+            .await;
+    }
+
+    /// Return the cached latency to `relay`, or `None` if it has not been measured successfully.
+    pub fn latency(&self, relay: &Relay) -> Option<Duration> {
+        self.cache
+            .lock()
+            .unwrap()
+            .get(&relay.hostname, Instant::now())
+    }
+
+    /// Forget all cached measurements, e.g. because the network has changed.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Return the relays in `candidates` that are the fastest ones, ordered by latency.
+    ///
+    /// Only cached measurements are used. Relays that have not been measured successfully are
+    /// discarded. If no relay in `candidates` is known to be reachable, `None` is returned.
+    pub fn fastest(&self, candidates: &[Relay]) -> Option<Vec<Relay>> {
+        let now = Instant::now();
+        let mut measured: Vec<(Duration, &Relay)> = {
+            let mut cache = self.cache.lock().unwrap();
+            candidates
+                .iter()
+                .filter_map(|relay| Some((cache.get(&relay.hostname, now)?, relay)))
+                .collect()
+        };
+        if measured.is_empty() {
+            log::debug!("No latency measurements available for the matching relays");
+            return None;
+        }
+        measured.sort_by_key(|(latency, _)| *latency);
+
+        let threshold = measured[0].0.saturating_add(self.tolerance);
+        let fastest = measured
+            .into_iter()
+            .take_while(|(latency, _)| *latency <= threshold)
+            .map(|(_, relay)| relay.clone())
+            .collect();
+        Some(fastest)
+    }
+}
+
+/// Latency measurements keyed by relay hostname. Each entry expires after a fixed duration.
+struct LatencyCache {
+    entries: HashMap<String, CachedLatency>,
+    expiry: Duration,
+}
+
+struct CachedLatency {
+    latency: Duration,
+    measured_at: Instant,
+}
+
+impl LatencyCache {
+    fn new(expiry: Duration) -> Self {
+        LatencyCache {
+            entries: HashMap::new(),
+            expiry,
+        }
+    }
+
+    /// Return the cached measurement for `hostname`, unless it is missing or has expired.
+    fn get(&mut self, hostname: &str, now: Instant) -> Option<Duration> {
+        let entry = self.entries.get(hostname)?;
+        if now.saturating_duration_since(entry.measured_at) >= self.expiry {
+            self.entries.remove(hostname);
+            return None;
+        }
+        Some(entry.latency)
+    }
+
+    fn insert(&mut self, hostname: String, latency: Duration, now: Instant) {
+        self.entries.insert(
+            hostname,
+            CachedLatency {
+                latency,
+                measured_at: now,
+            },
+        );
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mullvad_types::{
+        location::Location,
+        relay_list::{RelayEndpointData, WireguardRelayEndpointData},
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use talpid_types::net::wireguard::PublicKey;
+
+    /// Prober which returns fixed latencies and counts how many probes were made.
+    struct MockProber {
+        latencies: HashMap<String, Duration>,
+        probes: Arc<AtomicUsize>,
+    }
+
+    impl MockProber {
+        fn new(latencies: &[(&str, u64)]) -> (Self, Arc<AtomicUsize>) {
+            let probes = Arc::new(AtomicUsize::new(0));
+            let prober = MockProber {
+                latencies: latencies
+                    .iter()
+                    .map(|(hostname, ms)| (hostname.to_string(), Duration::from_millis(*ms)))
+                    .collect(),
+                probes: probes.clone(),
+            };
+            (prober, probes)
+        }
+    }
+
+    impl LatencyProber for MockProber {
+        fn probe(&self, relay: &Relay) -> Pin<Box<dyn Future<Output = Option<Duration>> + Send>> {
+            self.probes.fetch_add(1, Ordering::SeqCst);
+            Box::pin(std::future::ready(
+                self.latencies.get(&relay.hostname).copied(),
+            ))
+        }
+    }
+
+    fn relay(hostname: &str) -> Relay {
+        Relay {
+            hostname: hostname.to_string(),
+            ipv4_addr_in: "10.0.0.1".parse().unwrap(),
+            ipv6_addr_in: None,
+            overridden_ipv4: false,
+            overridden_ipv6: false,
+            include_in_country: true,
+            active: true,
+            owned: true,
+            provider: "provider".to_string(),
+            weight: 1,
+            endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
+                public_key: PublicKey::from_base64("BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=")
+                    .unwrap(),
+                daita: false,
+                shadowsocks_extra_addr_in: vec![],
+                quic: None,
+            }),
+            location: Location {
+                country: "Sweden".to_string(),
+                country_code: "se".to_string(),
+                city: "Gothenburg".to_string(),
+                city_code: "got".to_string(),
+                latitude: 57.71,
+                longitude: 11.97,
+            },
+        }
+    }
+
+    fn hostnames(relays: &[Relay]) -> Vec<&str> {
+        relays.iter().map(|relay| relay.hostname.as_str()).collect()
+    }
+
+    /// The fastest relays should be returned in order, and unreachable or unmeasured relays
+    /// discarded
+    #[test]
+    fn test_rank_by_latency() {
+        let (prober, _) = MockProber::new(&[("a", 40), ("b", 10), ("c", 12), ("d", 100)]);
+        let ranker = LatencyRanker::with_parameters(
+            prober,
+            DEFAULT_LATENCY_EXPIRY,
+            Duration::from_millis(5),
+        );
+
+        let candidates = vec![relay("a"), relay("b"), relay("c"), relay("d"), relay("e")];
+        futures::executor::block_on(ranker.measure(candidates.clone()));
+
+        let mut candidates = candidates;
+        candidates.push(relay("f"));
+        assert_eq!(hostnames(&ranker.fastest(&candidates).unwrap()), ["b", "c"]);
+    }
+
+    /// If no relay is known to be reachable, no ranking should be returned
+    #[test]
+    fn test_no_ranking_if_unreachable() {
+        let (prober, _) = MockProber::new(&[]);
+        let ranker = LatencyRanker::new(prober);
+        let candidates = vec![relay("a"), relay("b")];
+
+        assert!(ranker.fastest(&candidates).is_none());
+
+        futures::executor::block_on(ranker.measure(candidates.clone()));
+        assert!(ranker.fastest(&candidates).is_none());
+        assert_eq!(ranker.latency(&candidates[0]), None);
+    }
+
+    /// Failed probes should not be cached, so that unreachable relays are probed again
+    #[test]
+    fn test_failed_probes_not_cached() {
+        let (prober, probes) = MockProber::new(&[("a", 10)]);
+        let ranker = LatencyRanker::new(prober);
+        let candidates = vec![relay("a"), relay("b")];
+
+        futures::executor::block_on(ranker.measure(candidates.clone()));
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+
+        futures::executor::block_on(ranker.measure(candidates));
+        assert_eq!(probes.load(Ordering::SeqCst), 3);
+    }
+
+    /// Measurements should be reused until they expire
+    #[test]
+    fn test_cache_expiry() {
+        const EXPIRY: Duration = Duration::from_secs(60);
+        let mut cache = LatencyCache::new(EXPIRY);
+        let start = Instant::now();
+        let latency = Duration::from_millis(10);
+
+        cache.insert("a".to_owned(), latency, start);
+        assert_eq!(cache.get("a", start + EXPIRY / 2), Some(latency));
+        assert_eq!(cache.get("a", start + EXPIRY), None);
+        assert_eq!(cache.get("a", start), None);
+    }
+
+    /// Only relays without a cached measurement should be probed, each one only once
+    #[test]
+    fn test_measure_only_unmeasured() {
+        let (prober, probes) = MockProber::new(&[("a", 10), ("b", 20)]);
+        let ranker = LatencyRanker::new(prober);
+
+        futures::executor::block_on(ranker.measure(vec![relay("a"), relay("a")]));
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+
+        futures::executor::block_on(ranker.measure(vec![relay("a"), relay("b")]));
+        assert_eq!(probes.load(Ordering::SeqCst), 2);
+        assert_eq!(ranker.latency(&relay("b")), Some(Duration::from_millis(20)));
+
+        ranker.clear();
+        futures::executor::block_on(ranker.measure(vec![relay("a"), relay("b")]));
+        assert_eq!(probes.load(Ordering::SeqCst), 4);
+    }
+}
@@ -145,11 +145,29 @@ fn filter_on_obfuscation(
             )
         }
 
-        // If Shadowsocks is not a requirement, then there are no relay-specific constraints
+        // QUIC is only available on relays that run a QUIC server
+        ObfuscationQuery::Quic => filter_on_quic(&query.ip_version, relay),
+
+        // Otherwise, there are no relay-specific constraints
         _ => true,
     }
 }
 
+/// Returns whether `relay` has a QUIC server reachable using the IP version posed by `ip_version`.
+fn filter_on_quic(ip_version: &Constraint<IpVersion>, relay: &Relay) -> bool {
+    let ip_version = super::detailer::resolve_ip_version(*ip_version);
+
+    match &relay.endpoint_data {
+        RelayEndpointData::Wireguard(WireguardRelayEndpointData {
+            quic: Some(quic), ..
+        }) => quic
+            .addr_in
+            .iter()
+            .any(|&addr| IpVersion::from(addr) == ip_version),
+        _ => false,
+    }
+}
+
 /// Returns whether `relay` satisfies the Shadowsocks filter posed by `port`.
 fn filter_on_shadowsocks(
     port_ranges: &[RangeInclusive<u16>],
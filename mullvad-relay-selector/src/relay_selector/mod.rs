@@ -2,11 +2,13 @@
 
 pub mod detailer;
 mod helpers;
+pub mod latency;
 pub mod matcher;
 mod parsed_relays;
 pub mod query;
 pub mod relays;
 
+use latency::{LatencyRanker, SelectionMode};
 use matcher::{filter_matching_bridges, filter_matching_relay_list};
 use parsed_relays::ParsedRelays;
 use relays::{Multihop, Singlehop, WireguardConfig};
@@ -31,8 +33,10 @@ use itertools::Itertools;
 
 use mullvad_types::{
     constraints::Constraint,
+    custom_endpoint::CustomEndpointsSettings,
     custom_list::CustomListsSettings,
     endpoint::MullvadWireguardEndpoint,
+    learned_obfuscation::WorkingObfuscation,
     location::{Coordinates, Location},
     relay_constraints::{
         BridgeSettings, BridgeState, InternalBridgeConstraints, ObfuscationSettings,
@@ -42,13 +46,13 @@ use mullvad_types::{
     relay_list::{Relay, RelayEndpointData, RelayList},
     settings::Settings,
     wireguard::QuantumResistantState,
-    CustomTunnelEndpoint, Intersection,
+    ConnectionConfig, CustomTunnelEndpoint, Intersection,
 };
 use talpid_types::{
     net::{
         obfuscation::ObfuscatorConfig,
         proxy::{CustomProxy, Shadowsocks},
-        Endpoint, TransportProtocol, TunnelType,
+        Endpoint, ObfuscationType, TransportProtocol, TunnelType,
     },
     ErrorExt,
 };
@@ -83,6 +87,24 @@ pub static WIREGUARD_RETRY_ORDER: LazyLock<Vec<RelayQuery>> = LazyLock::new(|| {
     ]
 });
 
+/// Build a query which picks the obfuscation, port and IP version that last worked on the current
+/// network. It is tried before [`WIREGUARD_RETRY_ORDER`].
+fn learned_obfuscation_query(learned: WorkingObfuscation) -> RelayQuery {
+    use query::builder::RelayQueryBuilder;
+    let builder = RelayQueryBuilder::new()
+        .wireguard()
+        .ip_version(learned.ip_version);
+    match learned.obfuscation {
+        None => builder.port(learned.port).build(),
+        Some(ObfuscationType::Udp2Tcp) => builder.udp2tcp().udp2tcp_port(learned.port).build(),
+        Some(ObfuscationType::Shadowsocks) => {
+            builder.shadowsocks().shadowsocks_port(learned.port).build()
+        }
+        // QUIC is always served on the same port
+        Some(ObfuscationType::Quic) => builder.quic().build(),
+    }
+}
+
 /// [`OPENVPN_RETRY_ORDER`] defines an ordered set of relay parameters which the relay selector should
 /// prioritize on successive connection attempts. Note that these will *never* override user
 /// preferences. See [the documentation on `RelayQuery`][RelayQuery] for further details.
@@ -112,6 +134,9 @@ pub static OPENVPN_RETRY_ORDER: LazyLock<Vec<RelayQuery>> = LazyLock::new(|| {
 pub struct RelaySelector {
     config: Arc<Mutex<SelectorConfig>>,
     parsed_relays: Arc<Mutex<ParsedRelays>>,
+    latency_ranker: Arc<Mutex<Option<Arc<LatencyRanker>>>>,
+    learned_obfuscation: Arc<Mutex<Option<WorkingObfuscation>>>,
+    relay_settings_override: Arc<Mutex<Option<RelaySettings>>>,
 }
 
 #[derive(Clone)]
@@ -120,6 +145,7 @@ pub struct SelectorConfig {
     pub relay_settings: RelaySettings,
     pub additional_constraints: AdditionalRelayConstraints,
     pub custom_lists: CustomListsSettings,
+    pub custom_endpoints: CustomEndpointsSettings,
     pub relay_overrides: Vec<RelayOverride>,
     // Wireguard specific data
     pub obfuscation_settings: ObfuscationSettings,
@@ -157,6 +183,7 @@ impl SelectorConfig {
             bridge_settings: settings.bridge_settings.clone(),
             obfuscation_settings: settings.obfuscation_settings.clone(),
             custom_lists: settings.custom_lists.clone(),
+            custom_endpoints: settings.custom_endpoints.clone(),
             relay_overrides: settings.relay_overrides.clone(),
         }
     }
@@ -227,9 +254,10 @@ impl Default for RuntimeParameters {
 /// seemingly useless derivates of [`SelectorConfig`].
 ///
 /// The second one is a custom config, where [`SelectorConfig::relay_settings`] is
-/// [`RelaySettings::CustomTunnelEndpoint`]. For this variant, the endpoint where the client should
-/// connect to is already specified inside of the variant, so in practice the relay selector becomes
-/// superfluous. Also, there exists no mapping to [`RelayQueryBuilder`].
+/// [`RelaySettings::CustomTunnelEndpoint`] or [`RelaySettings::SavedCustomEndpoints`]. For this
+/// variant, the endpoints where the client should connect to are already specified, in order of
+/// preference, so in practice the relay selector becomes superfluous. Also, there exists no
+/// mapping to [`RelayQueryBuilder`].
 ///
 /// [`RelayQueryBuilder`]: query::builder::RelayQueryBuilder
 #[derive(Debug, Clone)]
@@ -237,7 +265,17 @@ enum SpecializedSelectorConfig<'a> {
     // This variant implements `From<NormalSelectorConfig> for RelayQuery`
     Normal(NormalSelectorConfig<'a>),
     // This variant does not
-    Custom(&'a CustomTunnelEndpoint),
+    Custom(CustomSelectorConfig<'a>),
+}
+
+/// A special-cased variant of [`SelectorConfig`] for custom tunnel endpoints.
+///
+/// For context, see [`SpecializedSelectorConfig`].
+#[derive(Debug, Clone)]
+struct CustomSelectorConfig<'a> {
+    /// Custom endpoints in order of preference.
+    endpoints: Vec<&'a CustomTunnelEndpoint>,
+    obfuscation_settings: &'a ObfuscationSettings,
 }
 
 /// A special-cased variant of [`SelectorConfig`].
@@ -269,7 +307,11 @@ pub enum GetRelay {
         exit: Relay,
         bridge: Option<SelectedBridge>,
     },
-    Custom(CustomTunnelEndpoint),
+    Custom {
+        endpoint: CustomTunnelEndpoint,
+        /// Self-hosted obfuscator to connect through, if any.
+        obfuscator: Option<ObfuscatorConfig>,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -317,6 +359,7 @@ impl Default for SelectorConfig {
             obfuscation_settings: default_settings.obfuscation_settings,
             bridge_state: default_settings.bridge_state,
             custom_lists: default_settings.custom_lists,
+            custom_endpoints: default_settings.custom_endpoints,
             relay_overrides: default_settings.relay_overrides,
         }
     }
@@ -341,7 +384,20 @@ impl<'a> From<&'a SelectorConfig> for SpecializedSelectorConfig<'a> {
     fn from(value: &'a SelectorConfig) -> SpecializedSelectorConfig<'a> {
         match &value.relay_settings {
             RelaySettings::CustomTunnelEndpoint(custom_tunnel_endpoint) => {
-                SpecializedSelectorConfig::Custom(custom_tunnel_endpoint)
+                SpecializedSelectorConfig::Custom(CustomSelectorConfig {
+                    endpoints: vec![custom_tunnel_endpoint],
+                    obfuscation_settings: &value.obfuscation_settings,
+                })
+            }
+            RelaySettings::SavedCustomEndpoints(ids) => {
+                SpecializedSelectorConfig::Custom(CustomSelectorConfig {
+                    endpoints: value
+                        .custom_endpoints
+                        .resolve(ids)
+                        .map(|saved| &saved.endpoint)
+                        .collect(),
+                    obfuscation_settings: &value.obfuscation_settings,
+                })
             }
             RelaySettings::Normal(user_preferences) => {
                 SpecializedSelectorConfig::Normal(NormalSelectorConfig {
@@ -462,6 +518,9 @@ impl RelaySelector {
         RelaySelector {
             config: Arc::new(Mutex::new(config)),
             parsed_relays: Arc::new(Mutex::new(unsynchronized_parsed_relays)),
+            latency_ranker: Arc::new(Mutex::new(None)),
+            learned_obfuscation: Arc::new(Mutex::new(None)),
+            relay_settings_override: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -473,6 +532,9 @@ impl RelaySelector {
                 &config.relay_overrides,
             ))),
             config: Arc::new(Mutex::new(config)),
+            latency_ranker: Arc::new(Mutex::new(None)),
+            learned_obfuscation: Arc::new(Mutex::new(None)),
+            relay_settings_override: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -487,6 +549,143 @@ impl RelaySelector {
         parsed_relays.update(relays);
     }
 
+    /// Set the ranker used to pick among the fastest relays, when the
+    /// [`SelectionMode::Fastest`] mode is set in the relay constraints. Without a ranker, relays are
+    /// always picked at random.
+    ///
+    /// [`SelectionMode::Fastest`]: mullvad_types::relay_constraints::SelectionMode::Fastest
+    pub fn set_latency_ranker(&self, ranker: Arc<LatencyRanker>) {
+        *self.latency_ranker.lock().unwrap() = Some(ranker);
+    }
+
+    /// Forget all measured latencies, e.g. because the device moved to a different network.
+    pub fn clear_latencies(&self) {
+        if let Some(ranker) = &*self.latency_ranker.lock().unwrap() {
+            ranker.clear();
+        }
+    }
+
+    /// Returns how to pick among the relays that match the current constraints.
+    fn selection_mode(&self, user_preferences: &RelayConstraints) -> SelectionMode {
+        use mullvad_types::relay_constraints::SelectionMode as UserSelectionMode;
+
+        match user_preferences.selection_mode {
+            UserSelectionMode::Random => SelectionMode::Random,
+            UserSelectionMode::Fastest => match self.latency_ranker.lock().unwrap().clone() {
+                Some(ranker) => SelectionMode::Fastest(ranker),
+                None => {
+                    log::warn!("No latency ranker available. Picking a relay at random");
+                    SelectionMode::Random
+                }
+            },
+        }
+    }
+
+    /// Measure the latency to the relays that may be picked for `retry_attempt`, unless recent
+    /// measurements exist. This does nothing unless the [`SelectionMode::Fastest`] mode is set.
+    ///
+    /// Relay selection only uses measurements that have already been made, so that it never has
+    /// to wait for the network. This should therefore be called ahead of time, in the background,
+    /// while relays are reachable.
+    ///
+    /// [`SelectionMode::Fastest`]: mullvad_types::relay_constraints::SelectionMode::Fastest
+    pub async fn measure_latencies(&self, retry_attempt: usize, runtime_params: RuntimeParameters) {
+        let (ranker, relays) = match self.latency_candidates(retry_attempt, runtime_params) {
+            Ok(Some(candidates)) => candidates,
+            Ok(None) => return,
+            Err(error) => {
+                log::debug!("Not measuring relay latencies: {error}");
+                return;
+            }
+        };
+        ranker.measure(relays).await;
+    }
+
+    /// Returns the relays whose latency matters for `retry_attempt`, along with the ranker to
+    /// measure them with, or `None` if relays are not picked based on latency.
+    fn latency_candidates(
+        &self,
+        retry_attempt: usize,
+        runtime_params: RuntimeParameters,
+    ) -> Result<Option<(Arc<LatencyRanker>, Vec<Relay>)>, Error> {
+        let selector_config = self.config();
+        let SpecializedSelectorConfig::Normal(normal_config) =
+            SpecializedSelectorConfig::from(&selector_config)
+        else {
+            return Ok(None);
+        };
+        let SelectionMode::Fastest(ranker) = self.selection_mode(normal_config.user_preferences)
+        else {
+            return Ok(None);
+        };
+
+        let retry_order = match normal_config.user_preferences.tunnel_protocol {
+            TunnelType::Wireguard => self.wireguard_retry_order(),
+            TunnelType::OpenVpn => OPENVPN_RETRY_ORDER.clone(),
+        };
+        let relay_list = self.parsed_relays.lock().unwrap().parsed_list().clone();
+        let query = Self::pick_and_merge_query(
+            retry_attempt,
+            &retry_order,
+            runtime_params,
+            &normal_config,
+            &relay_list,
+        )?;
+
+        // The client connects directly to the entry relay, so that is the one to measure when
+        // multihop is used
+        let candidate_query = match query.tunnel_protocol() {
+            TunnelType::Wireguard if query.using_daita() && query.use_multihop_if_necessary() => {
+                // The automatically chosen entry relay may be any DAITA relay. This includes the
+                // relays that may be picked when multihop turns out not to be necessary.
+                Self::auto_multihop_entry_query(&query)?
+            }
+            TunnelType::Wireguard if !query.singlehop() => Self::multihop_entry_query(&query)?,
+            TunnelType::Wireguard | TunnelType::OpenVpn => query,
+        };
+        let relays =
+            filter_matching_relay_list(&candidate_query, &relay_list, normal_config.custom_lists);
+        Ok(Some((ranker, relays)))
+    }
+
+    /// Set the obfuscation that last worked on the current network, if any. It is tried first
+    /// when connecting to a WireGuard relay.
+    pub fn set_learned_obfuscation(&self, learned: Option<WorkingObfuscation>) {
+        *self.learned_obfuscation.lock().unwrap() = learned;
+    }
+
+    /// Returns [`WIREGUARD_RETRY_ORDER`], preceded by the obfuscation that last worked on the
+    /// current network.
+    fn wireguard_retry_order(&self) -> Vec<RelayQuery> {
+        let learned = *self.learned_obfuscation.lock().unwrap();
+        learned
+            .map(learned_obfuscation_query)
+            .into_iter()
+            .chain(WIREGUARD_RETRY_ORDER.iter().cloned())
+            .collect()
+    }
+
+    /// Temporarily use `relay_settings` instead of the relay settings in [`SelectorConfig`], or
+    /// stop doing so if `None`. The override survives [`RelaySelector::set_config`]. Returns
+    /// whether the relay settings in use changed.
+    pub fn set_relay_settings_override(&self, relay_settings: Option<RelaySettings>) -> bool {
+        let mut relay_settings_override = self.relay_settings_override.lock().unwrap();
+        if *relay_settings_override == relay_settings {
+            return false;
+        }
+        *relay_settings_override = relay_settings;
+        true
+    }
+
+    /// Returns the current config, with the relay settings replaced by the override, if any.
+    fn config(&self) -> SelectorConfig {
+        let mut config = self.config.lock().unwrap().clone();
+        if let Some(relay_settings) = self.relay_settings_override.lock().unwrap().clone() {
+            config.relay_settings = relay_settings;
+        }
+        config
+    }
+
     fn set_overrides(&mut self, relay_overrides: &[RelayOverride]) {
         let mut parsed_relays = self.parsed_relays.lock().unwrap();
         parsed_relays.set_overrides(relay_overrides);
@@ -511,8 +710,8 @@ impl RelaySelector {
     /// state.
     pub fn get_bridge_forced(&self) -> Option<Shadowsocks> {
         let parsed_relays = &self.parsed_relays.lock().unwrap().parsed_list().clone();
-        let config = self.config.lock().unwrap();
-        let specialized_config = SpecializedSelectorConfig::from(&*config);
+        let config = self.config();
+        let specialized_config = SpecializedSelectorConfig::from(&config);
 
         let near_location = match specialized_config {
             SpecializedSelectorConfig::Normal(config) => RelayQuery::try_from(config.clone())
@@ -548,15 +747,16 @@ impl RelaySelector {
 
     /// Returns random relay and relay endpoint matching `query`.
     pub fn get_relay_by_query(&self, query: RelayQuery) -> Result<GetRelay, Error> {
-        let config_guard = self.config.lock().unwrap();
-        let config = SpecializedSelectorConfig::from(&*config_guard);
+        let selector_config = self.config();
+        let config = SpecializedSelectorConfig::from(&selector_config);
         match config {
             SpecializedSelectorConfig::Custom(custom_config) => {
-                Ok(GetRelay::Custom(custom_config.clone()))
+                Self::get_custom_endpoint(&custom_config, 0)
             }
             SpecializedSelectorConfig::Normal(normal_config) => {
                 let relay_list = &self.parsed_relays.lock().unwrap().parsed_list().clone();
-                Self::get_relay_inner(&query, relay_list, normal_config.custom_lists)
+                let mode = self.selection_mode(normal_config.user_preferences);
+                Self::get_relay_inner(&query, relay_list, normal_config.custom_lists, &mode)
             }
         }
     }
@@ -569,20 +769,19 @@ impl RelaySelector {
         retry_attempt: usize,
         runtime_params: RuntimeParameters,
     ) -> Result<GetRelay, Error> {
-        let config_guard = self.config.lock().unwrap();
-        let config = SpecializedSelectorConfig::from(&*config_guard);
+        let selector_config = self.config();
+        let config = SpecializedSelectorConfig::from(&selector_config);
         match config {
             SpecializedSelectorConfig::Custom(custom_config) => {
-                Ok(GetRelay::Custom(custom_config.clone()))
+                Self::get_custom_endpoint(&custom_config, retry_attempt)
             }
             SpecializedSelectorConfig::Normal(normal_config) => {
                 let tunnel_protocol = normal_config.user_preferences.tunnel_protocol;
-                drop(config_guard);
 
                 match tunnel_protocol {
                     TunnelType::Wireguard => self.get_relay_with_custom_params(
                         retry_attempt,
-                        &WIREGUARD_RETRY_ORDER,
+                        &self.wireguard_retry_order(),
                         runtime_params,
                     ),
                     TunnelType::OpenVpn => self.get_relay_with_custom_params(
@@ -603,14 +802,14 @@ impl RelaySelector {
         retry_order: &[RelayQuery],
         runtime_params: RuntimeParameters,
     ) -> Result<GetRelay, Error> {
-        let config_guard = self.config.lock().unwrap();
-        let config = SpecializedSelectorConfig::from(&*config_guard);
+        let selector_config = self.config();
+        let config = SpecializedSelectorConfig::from(&selector_config);
 
         // Short-circuit if a custom tunnel endpoint is to be used - don't have to involve the
         // relay selector further!
         match config {
             SpecializedSelectorConfig::Custom(custom_config) => {
-                Ok(GetRelay::Custom(custom_config.clone()))
+                Self::get_custom_endpoint(&custom_config, retry_attempt)
             }
             SpecializedSelectorConfig::Normal(normal_config) => {
                 let relay_list = self.parsed_relays.lock().unwrap().parsed_list().clone();
@@ -622,11 +821,40 @@ impl RelaySelector {
                     &normal_config,
                     &relay_list,
                 )?;
-                Self::get_relay_inner(&query, &relay_list, normal_config.custom_lists)
+                let mode = self.selection_mode(normal_config.user_preferences);
+                Self::get_relay_inner(&query, &relay_list, normal_config.custom_lists, &mode)
             }
         }
     }
 
+    /// Pick the custom endpoint to use for `retry_attempt`. Every failed attempt moves on to the
+    /// next endpoint in `config`, looping back to the first one after the last.
+    ///
+    /// WireGuard endpoints are obfuscated using the self-hosted Shadowsocks server if Shadowsocks
+    /// obfuscation is selected and such a server has been configured.
+    fn get_custom_endpoint(
+        config: &CustomSelectorConfig<'_>,
+        retry_attempt: usize,
+    ) -> Result<GetRelay, Error> {
+        let endpoint = config
+            .endpoints
+            .iter()
+            .cycle()
+            .nth(retry_attempt)
+            .ok_or(Error::NoCustomEndpoint)?;
+        let obfuscator = match endpoint.config {
+            ConnectionConfig::Wireguard(_) => {
+                helpers::get_custom_obfuscator(config.obfuscation_settings)
+                    .map_err(|error| Error::NoObfuscator(Box::new(error)))?
+            }
+            ConnectionConfig::OpenVpn(_) => None,
+        };
+        Ok(GetRelay::Custom {
+            endpoint: (*endpoint).clone(),
+            obfuscator,
+        })
+    }
+
     /// This function defines the merge between a set of pre-defined queries and `user_preferences`
     /// for the given `retry_attempt`.
     ///
@@ -656,7 +884,8 @@ impl RelaySelector {
             // settings
             .filter(|query| runtime_params.compatible(query))
             .filter_map(|query| query.clone().intersection(user_query.clone()))
-            .filter(|query| Self::get_relay_inner(query, parsed_relays, user_config.custom_lists).is_ok())
+            // Only check whether a relay can be found. There is no need to rank relays by latency here.
+            .filter(|query| Self::get_relay_inner(query, parsed_relays, user_config.custom_lists, &SelectionMode::Random).is_ok())
             .cycle() // If the above filters remove all relays, cycle will also return an empty iterator
             .nth(retry_attempt)
             .ok_or(Error::NoRelay)
@@ -671,6 +900,7 @@ impl RelaySelector {
     /// - `config`: Configuration settings that influence relay selection, including bridge state
     ///   and custom lists.
     /// - `parsed_relays`: The complete set of parsed relays available for selection.
+    /// - `mode`: How to pick among the relays that match `query`.
     ///
     /// # Returns
     /// * A randomly selected relay that meets the specified constraints (and a random bridge/entry
@@ -682,12 +912,15 @@ impl RelaySelector {
         query: &RelayQuery,
         parsed_relays: &RelayList,
         custom_lists: &CustomListsSettings,
+        mode: &SelectionMode,
     ) -> Result<GetRelay, Error> {
         match query.tunnel_protocol() {
             TunnelType::Wireguard => {
-                Self::get_wireguard_relay_inner(query, custom_lists, parsed_relays)
+                Self::get_wireguard_relay_inner(query, custom_lists, parsed_relays, mode)
+            }
+            TunnelType::OpenVpn => {
+                Self::get_openvpn_relay(query, custom_lists, parsed_relays, mode)
             }
-            TunnelType::OpenVpn => Self::get_openvpn_relay(query, custom_lists, parsed_relays),
         }
     }
 
@@ -696,6 +929,7 @@ impl RelaySelector {
         query: &RelayQuery,
         parsed_relays: &RelayList,
         custom_lists: &CustomListsSettings,
+        mode: &SelectionMode,
     ) -> Result<GetRelay, Error> {
         // FIXME: A bit of defensive programming - calling `get_wireguard_relay_inner` with a query that
         // doesn't specify Wireguard as the desired tunnel type is not valid and will lead
@@ -703,7 +937,7 @@ impl RelaySelector {
         // to lift this invariant to be checked by the type system instead.
         let mut query = query.clone();
         query.set_tunnel_protocol(TunnelType::Wireguard)?;
-        Self::get_wireguard_relay_inner(&query, custom_lists, parsed_relays)
+        Self::get_wireguard_relay_inner(&query, custom_lists, parsed_relays, mode)
     }
 
     /// Derive a valid relay configuration from `query`.
@@ -723,9 +957,10 @@ impl RelaySelector {
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Result<GetRelay, Error> {
         assert_eq!(query.tunnel_protocol(), TunnelType::Wireguard);
-        let inner = Self::get_wireguard_relay_config(query, custom_lists, parsed_relays)?;
+        let inner = Self::get_wireguard_relay_config(query, custom_lists, parsed_relays, mode)?;
         let endpoint = Self::get_wireguard_endpoint(query, parsed_relays, &inner)?;
         let obfuscator =
             Self::get_wireguard_obfuscator(query, inner.clone(), &endpoint, parsed_relays)?;
@@ -747,9 +982,10 @@ impl RelaySelector {
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Result<WireguardConfig, Error> {
         let inner = if query.singlehop() {
-            match Self::get_wireguard_singlehop_config(query, custom_lists, parsed_relays) {
+            match Self::get_wireguard_singlehop_config(query, custom_lists, parsed_relays, mode) {
                 Some(exit) => WireguardConfig::from(exit),
                 None => {
                     // If we found no matching relays because DAITA was enabled, and
@@ -761,6 +997,7 @@ impl RelaySelector {
                             query,
                             custom_lists,
                             parsed_relays,
+                            mode,
                         )?;
                         WireguardConfig::from(multihop)
                     } else {
@@ -774,9 +1011,9 @@ impl RelaySelector {
             // entry relay with smarting routing enabled, even if multihop is turned on
             // Also implied: Multihop is enabled.
             let multihop = if query.using_daita() && query.use_multihop_if_necessary() {
-                Self::get_wireguard_auto_multihop_config(query, custom_lists, parsed_relays)?
+                Self::get_wireguard_auto_multihop_config(query, custom_lists, parsed_relays, mode)?
             } else {
-                Self::get_wireguard_multihop_config(query, custom_lists, parsed_relays)?
+                Self::get_wireguard_multihop_config(query, custom_lists, parsed_relays, mode)?
             };
             WireguardConfig::from(multihop)
         };
@@ -793,8 +1030,13 @@ impl RelaySelector {
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Option<Singlehop> {
-        let candidates = filter_matching_relay_list(query, parsed_relays, custom_lists);
+        let candidates = mode.candidates(filter_matching_relay_list(
+            query,
+            parsed_relays,
+            custom_lists,
+        ));
         helpers::pick_random_relay(&candidates)
             .cloned()
             .map(Singlehop::new)
@@ -805,10 +1047,14 @@ impl RelaySelector {
     /// # Returns
     /// * An `Err` if no entry/exit relay can be chosen
     /// * `Ok(Multihop)` otherwise
+    ///
+    /// The entry relay is picked among the ones closest to the exit relay, unless `mode` ranks
+    /// the entry candidates by latency. In that case, it is picked among the fastest ones.
     fn get_wireguard_auto_multihop_config(
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Result<Multihop, Error> {
         let mut exit_relay_query = query.clone();
 
@@ -822,13 +1068,19 @@ impl RelaySelector {
         let exit = helpers::pick_random_relay(&exit_candidates).ok_or(Error::NoRelay)?;
 
         // generate a list of potential entry relays, disregarding any location constraint
-        let mut entry_query = query.clone();
-        entry_query.set_location(Constraint::Any)?;
-        let mut entry_candidates =
-            filter_matching_relay_list(&entry_query, parsed_relays, custom_lists)
-                .into_iter()
-                .map(|entry| RelayWithDistance::new_with_distance_from(entry, &exit.location))
-                .collect_vec();
+        let entry_query = Self::auto_multihop_entry_query(query)?;
+        let entry_candidates =
+            filter_matching_relay_list(&entry_query, parsed_relays, custom_lists);
+        let fastest_entry = mode.fastest(&entry_candidates).and_then(|fastest_entries| {
+            helpers::pick_random_relay_excluding(&fastest_entries, exit).cloned()
+        });
+        if let Some(entry) = fastest_entry {
+            return Ok(Multihop::new(entry, exit.clone()));
+        }
+        let mut entry_candidates = entry_candidates
+            .into_iter()
+            .map(|entry| RelayWithDistance::new_with_distance_from(entry, &exit.location))
+            .collect_vec();
 
         // sort entry relay candidates by distance, and pick one from those that are closest
         entry_candidates.sort_unstable_by(|a, b| a.distance.total_cmp(&b.distance));
@@ -856,17 +1108,16 @@ impl RelaySelector {
     /// * An `Err` if no entry relay can be chosen
     /// * An `Err` if the chosen entry and exit relays are the same
     /// * `Ok(WireguardConfig::Multihop)` otherwise
+    ///
+    /// Since the client connects directly to the entry relay, only the entry relay candidates are
+    /// narrowed down by `mode`.
     fn get_wireguard_multihop_config(
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Result<Multihop, Error> {
-        // Here, we modify the original query just a bit.
-        // The actual query for an entry relay is identical as for an exit relay, with the
-        // exception that the location is different. It is simply the location as dictated by
-        // the query's multihop constraint.
-        let mut entry_relay_query = query.clone();
-        entry_relay_query.set_location(query.wireguard_constraints().entry_location.clone())?;
+        let entry_relay_query = Self::multihop_entry_query(query)?;
         // After we have our two queries (one for the exit relay & one for the entry relay),
         // we can query for all exit & entry candidates! All candidates are needed for the next
         // step.
@@ -879,8 +1130,11 @@ impl RelaySelector {
 
         let exit_candidates =
             filter_matching_relay_list(&exit_relay_query, parsed_relays, custom_lists);
-        let entry_candidates =
-            filter_matching_relay_list(&entry_relay_query, parsed_relays, custom_lists);
+        let entry_candidates = mode.candidates(filter_matching_relay_list(
+            &entry_relay_query,
+            parsed_relays,
+            custom_lists,
+        ));
 
         // We avoid picking the same relay for entry and exit by choosing one and excluding it when
         // choosing the other.
@@ -903,6 +1157,25 @@ impl RelaySelector {
         Ok(Multihop::new(entry.clone(), exit.clone()))
     }
 
+    /// Returns the query for the entry relay when multihop is used.
+    ///
+    /// The query for an entry relay is identical to the one for an exit relay, with the exception
+    /// that the location is different. It is simply the location as dictated by the query's
+    /// multihop constraint.
+    fn multihop_entry_query(query: &RelayQuery) -> Result<RelayQuery, Error> {
+        let mut entry_relay_query = query.clone();
+        entry_relay_query.set_location(query.wireguard_constraints().entry_location.clone())?;
+        Ok(entry_relay_query)
+    }
+
+    /// Returns the query for the entry relay when multihop is enabled automatically to use DAITA.
+    /// Any location is accepted for the entry relay.
+    fn auto_multihop_entry_query(query: &RelayQuery) -> Result<RelayQuery, Error> {
+        let mut entry_query = query.clone();
+        entry_query.set_location(Constraint::Any)?;
+        Ok(entry_query)
+    }
+
     /// Constructs a [`MullvadEndpoint`] with details for how to connect to `relay`.
     ///
     /// [`MullvadEndpoint`]: mullvad_types::endpoint::MullvadEndpoint
@@ -955,6 +1228,9 @@ impl RelaySelector {
 
                 Ok(Some(obfuscation))
             }
+            ObfuscationQuery::Quic => helpers::get_quic_obfuscator(obfuscator_relay, endpoint)
+                .map(Some)
+                .map_err(box_obfsucation_error),
         }
     }
 
@@ -976,10 +1252,11 @@ impl RelaySelector {
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Result<GetRelay, Error> {
         assert_eq!(query.tunnel_protocol(), TunnelType::OpenVpn);
-        let exit =
-            Self::choose_openvpn_relay(query, custom_lists, parsed_relays).ok_or(Error::NoRelay)?;
+        let exit = Self::choose_openvpn_relay(query, custom_lists, parsed_relays, mode)
+            .ok_or(Error::NoRelay)?;
         let endpoint = Self::get_openvpn_endpoint(query, &exit, parsed_relays)?;
         let bridge = Self::get_openvpn_bridge(
             query,
@@ -1179,9 +1456,14 @@ impl RelaySelector {
         query: &RelayQuery,
         custom_lists: &CustomListsSettings,
         parsed_relays: &RelayList,
+        mode: &SelectionMode,
     ) -> Option<Relay> {
         // Filter among all valid relays
-        let candidates = filter_matching_relay_list(query, parsed_relays, custom_lists);
+        let candidates = mode.candidates(filter_matching_relay_list(
+            query,
+            parsed_relays,
+            custom_lists,
+        ));
         // Pick one of the valid relays.
         helpers::pick_random_relay(&candidates).cloned()
     }
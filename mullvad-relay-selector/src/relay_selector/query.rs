@@ -217,6 +217,7 @@ impl RelayQuery {
             tunnel_protocol: self.tunnel_protocol,
             wireguard_constraints: self.wireguard_constraints.into_constraints(),
             openvpn_constraints: self.openvpn_constraints.into_constraints(),
+            selection_mode: Default::default(),
         };
 
         (constraints, bridge_state, bridge_settings, obfuscation)
@@ -284,6 +285,7 @@ pub enum ObfuscationQuery {
     Auto,
     Udp2tcp(Udp2TcpObfuscationSettings),
     Shadowsocks(ShadowsocksSettings),
+    Quic,
 }
 
 impl ObfuscationQuery {
@@ -307,6 +309,10 @@ impl ObfuscationQuery {
                 shadowsocks: settings,
                 ..Default::default()
             },
+            ObfuscationQuery::Quic => ObfuscationSettings {
+                selected_obfuscation: SelectedObfuscation::Quic,
+                ..Default::default()
+            },
         }
     }
 }
@@ -324,6 +330,7 @@ impl From<ObfuscationSettings> for ObfuscationQuery {
             SelectedObfuscation::Shadowsocks => {
                 ObfuscationQuery::Shadowsocks(obfuscation.shadowsocks)
             }
+            SelectedObfuscation::Quic => ObfuscationQuery::Quic,
         }
     }
 }
@@ -339,6 +346,7 @@ impl Intersection for ObfuscationQuery {
             (ObfuscationQuery::Shadowsocks(a), ObfuscationQuery::Shadowsocks(b)) => {
                 Some(ObfuscationQuery::Shadowsocks(a.intersection(b)?))
             }
+            (ObfuscationQuery::Quic, ObfuscationQuery::Quic) => Some(ObfuscationQuery::Quic),
             _ => None,
         }
     }
@@ -755,6 +763,7 @@ pub mod builder {
         > {
             let obfuscation = Udp2TcpObfuscationSettings {
                 port: Constraint::Any,
+                websocket: None,
             };
             let protocol = Wireguard {
                 multihop: self.protocol.multihop,
@@ -791,6 +800,19 @@ pub mod builder {
                 protocol,
             }
         }
+
+        /// Enable QUIC obfuscation. QUIC obfuscation has no further settings.
+        pub fn quic(self) -> RelayQueryBuilder<Wireguard<Multihop, bool, Daita, QuantumResistant>> {
+            let protocol = Wireguard {
+                multihop: self.protocol.multihop,
+                obfuscation: true,
+                daita: self.protocol.daita,
+                quantum_resistant: self.protocol.quantum_resistant,
+            };
+            let mut query = self.query;
+            query.wireguard_constraints.obfuscation = ObfuscationQuery::Quic;
+            RelayQueryBuilder { query, protocol }
+        }
     }
 
     impl<Multihop, Daita, QuantumResistant>
@@ -806,6 +828,19 @@ pub mod builder {
         }
     }
 
+    impl<Multihop, Daita, QuantumResistant>
+        RelayQueryBuilder<Wireguard<Multihop, ShadowsocksSettings, Daita, QuantumResistant>>
+    {
+        /// Set the Shadowsocks port. This is the UDP port which the Shadowsocks obfuscation
+        /// protocol should use to connect to a relay.
+        pub fn shadowsocks_port(mut self, port: u16) -> Self {
+            self.protocol.obfuscation.port = Constraint::Only(port);
+            self.query.wireguard_constraints.obfuscation =
+                ObfuscationQuery::Shadowsocks(self.protocol.obfuscation.clone());
+            self
+        }
+    }
+
     // Type-safe builder pattern for OpenVPN relay constraints.
 
     /// Internal builder state for a [`OpenVpnRelayQuery`] configuration.
@@ -1042,10 +1077,13 @@ mod test {
                 selected_obfuscation: SelectedObfuscation::Auto,
                 udp2tcp: Udp2TcpObfuscationSettings {
                     port: port1,
+                    websocket: None,
                 },
                 shadowsocks: ShadowsocksSettings {
                     port: port2,
                 },
+                custom_shadowsocks: None,
+                learn_per_network: false,
             });
             assert_eq!(query, ObfuscationQuery::Auto);
         }
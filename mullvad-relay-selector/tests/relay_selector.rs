@@ -2,35 +2,46 @@
 
 use std::{
     collections::HashSet,
+    future::Future,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
-    sync::LazyLock,
+    pin::Pin,
+    sync::{Arc, LazyLock},
+    time::Duration,
 };
 use talpid_types::net::{
-    obfuscation::ObfuscatorConfig,
+    obfuscation::{ObfuscatorConfig, WebSocketSettings},
+    openvpn,
+    proxy::Shadowsocks,
     wireguard::PublicKey,
-    Endpoint, IpVersion,
+    Endpoint, IpVersion, ObfuscationType,
     TransportProtocol::{Tcp, Udp},
     TunnelType,
 };
 
 use mullvad_relay_selector::{
+    latency::{LatencyProber, LatencyRanker},
     query::{builder::RelayQueryBuilder, BridgeQuery, ObfuscationQuery, OpenVpnRelayQuery},
     Error, GetRelay, RelaySelector, RuntimeParameters, SelectedObfuscator, SelectorConfig,
     WireguardConfig, OPENVPN_RETRY_ORDER, WIREGUARD_RETRY_ORDER,
 };
 use mullvad_types::{
     constraints::Constraint,
+    custom_endpoint::{CustomEndpointsSettings, NamedCustomEndpoint},
     endpoint::MullvadEndpoint,
+    learned_obfuscation::WorkingObfuscation,
     location::Location,
     relay_constraints::{
-        BridgeConstraints, BridgeState, GeographicLocationConstraint, Ownership, Providers,
-        RelayConstraints, RelayOverride, RelaySettings, TransportPort,
+        BridgeConstraints, BridgeState, GeographicLocationConstraint, ObfuscationSettings,
+        Ownership, Providers, RelayConstraints, RelayOverride, RelaySettings, SelectedObfuscation,
+        SelectionMode, TransportPort, Udp2TcpObfuscationSettings,
     },
     relay_list::{
-        BridgeEndpointData, OpenVpnEndpoint, OpenVpnEndpointData, Relay, RelayEndpointData,
-        RelayList, RelayListCity, RelayListCountry, ShadowsocksEndpointData, WireguardEndpointData,
-        WireguardRelayEndpointData,
+        BridgeEndpointData, OpenVpnEndpoint, OpenVpnEndpointData, QuicEndpointData, Relay,
+        RelayEndpointData, RelayList, RelayListCity, RelayListCountry, ShadowsocksEndpointData,
+        WireguardEndpointData, WireguardRelayEndpointData,
     },
+    wg_quick::WgQuickConfig,
+    ConnectionConfig, CustomTunnelEndpoint,
 };
 
 static DUMMY_LOCATION: LazyLock<Location> = LazyLock::new(|| Location {
@@ -71,6 +82,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                         .unwrap(),
                         daita: true,
                         shadowsocks_extra_addr_in: vec![],
+                        quic: None,
                     }),
                     location: DUMMY_LOCATION.clone(),
                 },
@@ -92,6 +104,10 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                         .unwrap(),
                         daita: false,
                         shadowsocks_extra_addr_in: vec![],
+                        quic: Some(QuicEndpointData {
+                            addr_in: QUIC_RELAY_ADDRS.to_vec(),
+                            hostname: QUIC_RELAY_HOSTNAME.to_string(),
+                        }),
                     }),
                     location: DUMMY_LOCATION.clone(),
                 },
@@ -113,6 +129,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                         .unwrap(),
                         daita: true,
                         shadowsocks_extra_addr_in: vec![],
+                        quic: None,
                     }),
                     location: DUMMY_LOCATION.clone(),
                 },
@@ -239,6 +256,7 @@ static SHADOWSOCKS_RELAY: LazyLock<Relay> = LazyLock::new(|| Relay {
         public_key: PublicKey::from_base64("eaNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=").unwrap(),
         daita: false,
         shadowsocks_extra_addr_in: SHADOWSOCKS_RELAY_EXTRA_ADDRS.to_vec(),
+        quic: None,
     }),
     location: DUMMY_LOCATION.clone(),
 });
@@ -251,6 +269,13 @@ const SHADOWSOCKS_RELAY_EXTRA_ADDRS: &[IpAddr; 2] = &[
 static SHADOWSOCKS_RELAY_LOCATION: LazyLock<GeographicLocationConstraint> =
     LazyLock::new(|| GeographicLocationConstraint::hostname("se", "got", "se1337-wireguard"));
 
+/// Addresses of the QUIC server running on `se10-wireguard`
+const QUIC_RELAY_ADDRS: &[IpAddr; 2] = &[
+    IpAddr::V4(Ipv4Addr::new(185, 213, 154, 70)),
+    IpAddr::V6(Ipv6Addr::new(0x2a03, 0x1b20, 5, 0xf011, 0, 0, 0, 0xa10e)),
+];
+const QUIC_RELAY_HOSTNAME: &str = "se10-wireguard.example.com";
+
 // Helper functions
 fn unwrap_relay(get_result: GetRelay) -> Relay {
     match get_result {
@@ -259,7 +284,9 @@ fn unwrap_relay(get_result: GetRelay) -> Relay {
             crate::WireguardConfig::Multihop { exit, .. } => exit,
         },
         GetRelay::OpenVpn { exit, .. } => exit,
-        GetRelay::Custom(custom) => {
+        GetRelay::Custom {
+            endpoint: custom, ..
+        } => {
             panic!("Can not extract regular relay from custom relay: {custom}")
         }
     }
@@ -272,7 +299,9 @@ fn unwrap_entry_relay(get_result: GetRelay) -> Relay {
             crate::WireguardConfig::Multihop { entry, .. } => entry,
         },
         GetRelay::OpenVpn { exit, .. } => exit,
-        GetRelay::Custom(custom) => {
+        GetRelay::Custom {
+            endpoint: custom, ..
+        } => {
             panic!("Can not extract regular relay from custom relay: {custom}")
         }
     }
@@ -282,7 +311,9 @@ fn unwrap_endpoint(get_result: GetRelay) -> MullvadEndpoint {
     match get_result {
         GetRelay::Wireguard { endpoint, .. } => MullvadEndpoint::Wireguard(endpoint),
         GetRelay::OpenVpn { endpoint, .. } => MullvadEndpoint::OpenVpn(endpoint),
-        GetRelay::Custom(custom) => {
+        GetRelay::Custom {
+            endpoint: custom, ..
+        } => {
             panic!("Can not extract Mullvad endpoint from custom relay: {custom}")
         }
     }
@@ -424,8 +455,9 @@ fn test_wireguard_retry_order() {
                 assert!(match &query.wireguard_constraints().obfuscation {
                     ObfuscationQuery::Auto => true,
                     ObfuscationQuery::Off => obfuscator.is_none(),
-                    ObfuscationQuery::Udp2tcp(_) | ObfuscationQuery::Shadowsocks(_) =>
-                        obfuscator.is_some(),
+                    ObfuscationQuery::Udp2tcp(_)
+                    | ObfuscationQuery::Shadowsocks(_)
+                    | ObfuscationQuery::Quic => obfuscator.is_some(),
                 });
             }
             _ => unreachable!(),
@@ -433,6 +465,47 @@ fn test_wireguard_retry_order() {
     }
 }
 
+/// Test that the obfuscation that last worked on the current network is tried before
+/// [`WIREGUARD_RETRY_ORDER`], and that the retry order is otherwise unchanged.
+#[test]
+fn test_learned_obfuscation_is_tried_first() {
+    let relay_selector = default_relay_selector();
+    relay_selector.set_learned_obfuscation(Some(WorkingObfuscation {
+        obfuscation: Some(ObfuscationType::Shadowsocks),
+        port: 150,
+        ip_version: IpVersion::V4,
+    }));
+    let runtime_params = RuntimeParameters { ipv6: true };
+
+    match relay_selector.get_relay(0, runtime_params.clone()).unwrap() {
+        GetRelay::Wireguard {
+            obfuscator:
+                Some(SelectedObfuscator {
+                    config: ObfuscatorConfig::Shadowsocks { endpoint },
+                    ..
+                }),
+            ..
+        } => assert_eq!(endpoint.port(), 150),
+        wrong_relay => panic!(
+            "Relay selector should have picked the learned Shadowsocks port, instead chose {wrong_relay:?}"
+        ),
+    }
+    match relay_selector.get_relay(1, runtime_params.clone()).unwrap() {
+        GetRelay::Wireguard { obfuscator, .. } => assert!(obfuscator.is_none()),
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard relay, instead chose {wrong_relay:?}"
+        ),
+    }
+
+    relay_selector.set_learned_obfuscation(None);
+    match relay_selector.get_relay(0, runtime_params.clone()).unwrap() {
+        GetRelay::Wireguard { obfuscator, .. } => assert!(obfuscator.is_none()),
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard relay, instead chose {wrong_relay:?}"
+        ),
+    }
+}
+
 /// Test whether the relay selector seems to respect the order as defined by [`OPENVPN_RETRY_ORDER`].
 #[test]
 fn test_openvpn_retry_order() {
@@ -586,6 +659,7 @@ fn test_wireguard_entry() {
                             .unwrap(),
                             daita: false,
                             shadowsocks_extra_addr_in: vec![],
+                            quic: None,
                         }),
                         location: DUMMY_LOCATION.clone(),
                     },
@@ -607,6 +681,7 @@ fn test_wireguard_entry() {
                             .unwrap(),
                             daita: false,
                             shadowsocks_extra_addr_in: vec![],
+                            quic: None,
                         }),
                         location: DUMMY_LOCATION.clone(),
                     },
@@ -870,6 +945,43 @@ fn test_selecting_wireguard_over_shadowsocks() {
     }
 }
 
+/// Test whether only relays running a QUIC server are selected when QUIC is selected, and that
+/// the obfuscator connects to the QUIC server rather than the WireGuard endpoint.
+#[test]
+fn test_selecting_wireguard_over_quic() {
+    let relay_selector = RelaySelector::from_list(SelectorConfig::default(), RELAYS.clone());
+
+    for ip_version in [IpVersion::V4, IpVersion::V6] {
+        let query = RelayQueryBuilder::new()
+            .wireguard()
+            .ip_version(ip_version)
+            .quic()
+            .build();
+
+        let relay = relay_selector.get_relay_by_query(query).unwrap();
+        match relay {
+            GetRelay::Wireguard {
+                obfuscator:
+                    Some(SelectedObfuscator {
+                        config: ObfuscatorConfig::Quic { endpoint, hostname },
+                        ..
+                    }),
+                inner: WireguardConfig::Singlehop { exit },
+                ..
+            } => {
+                assert_eq!(exit.hostname, "se10-wireguard");
+                assert!(QUIC_RELAY_ADDRS.contains(&endpoint.ip()));
+                assert_eq!(IpVersion::from(endpoint.ip()), ip_version);
+                assert_eq!(endpoint.port(), QuicEndpointData::PORT);
+                assert_eq!(hostname, QUIC_RELAY_HOSTNAME);
+            }
+            wrong_relay => panic!(
+                "Relay selector should have picked a Wireguard relay with QUIC, instead chose {wrong_relay:?}"
+            ),
+        }
+    }
+}
+
 /// Test whether extra Shadowsocks IPs are selected when available
 #[test]
 fn test_selecting_wireguard_over_shadowsocks_extra_ips() {
@@ -1014,6 +1126,45 @@ fn test_selecting_wireguard_endpoint_with_udp2tcp_obfuscation() {
     }
 }
 
+/// Assert that WebSocket settings for UDP2TCP obfuscation are passed on to the obfuscator
+/// configuration.
+#[test]
+fn test_selecting_wireguard_endpoint_with_udp2tcp_websocket_obfuscation() {
+    let relay_selector = default_relay_selector();
+    let websocket = WebSocketSettings {
+        server_name: "cdn.example.com".to_string(),
+        path: "/ws".to_string(),
+    };
+    let mut query = RelayQueryBuilder::new().wireguard().udp2tcp().build();
+    let mut wireguard_constraints = query.wireguard_constraints().clone();
+    wireguard_constraints.obfuscation = ObfuscationQuery::Udp2tcp(Udp2TcpObfuscationSettings {
+        port: Constraint::Any,
+        websocket: Some(websocket.clone()),
+    });
+    query
+        .set_wireguard_constraints(wireguard_constraints)
+        .unwrap();
+
+    let relay = relay_selector.get_relay_by_query(query).unwrap();
+    match relay {
+        GetRelay::Wireguard {
+            obfuscator:
+                Some(SelectedObfuscator {
+                    config:
+                        ObfuscatorConfig::Udp2Tcp {
+                            websocket: selected_websocket,
+                            ..
+                        },
+                    ..
+                }),
+            ..
+        } => assert_eq!(selected_websocket, Some(websocket)),
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard relay with UDP2TCP, instead chose {wrong_relay:?}"
+        ),
+    }
+}
+
 /// Construct a query for a Wireguard configuration where obfuscation is set to "Auto" and
 /// multihop is explicitly turned off. Assert that the relay selector does *not* return an
 /// obfuscator config.
@@ -1063,7 +1214,7 @@ fn test_selected_wireguard_endpoints_use_correct_port_ranges() {
                     panic!("Relay selector should have picked an obfuscator")
                 };
                 assert!(matches!(obfuscator.config,
-                    ObfuscatorConfig::Udp2Tcp { endpoint } if
+                    ObfuscatorConfig::Udp2Tcp { endpoint, .. } if
                         TCP2UDP_PORTS.contains(&endpoint.port()),
                 ))
             }
@@ -1247,6 +1398,7 @@ fn test_include_in_country() {
                             )
                             .unwrap(),
                             shadowsocks_extra_addr_in: vec![],
+                            quic: None,
                             daita: false,
                         }),
                         location: DUMMY_LOCATION.clone(),
@@ -1268,6 +1420,7 @@ fn test_include_in_country() {
                             )
                             .unwrap(),
                             shadowsocks_extra_addr_in: vec![],
+                            quic: None,
                             daita: false,
                         }),
                         location: DUMMY_LOCATION.clone(),
@@ -1671,3 +1824,219 @@ fn valid_user_setting_should_yield_relay() {
         }
     }
 }
+
+/// Prober which reports fixed latencies for some of the relays in [`RELAYS`], and treats the
+/// other relays as unreachable.
+struct MockProber;
+
+impl LatencyProber for MockProber {
+    fn probe(&self, relay: &Relay) -> Pin<Box<dyn Future<Output = Option<Duration>> + Send>> {
+        let latency = match relay.hostname.as_str() {
+            "se9-wireguard" => Some(Duration::from_millis(80)),
+            "se10-wireguard" => Some(Duration::from_millis(15)),
+            "se11-wireguard" => Some(Duration::from_millis(50)),
+            _ => None,
+        };
+        Box::pin(std::future::ready(latency))
+    }
+}
+
+/// Returns a relay selector in the [`SelectionMode::Fastest`] mode, along with its ranker.
+fn fastest_relay_selector() -> (RelaySelector, Arc<LatencyRanker>) {
+    let config = SelectorConfig {
+        relay_settings: RelaySettings::Normal(RelayConstraints {
+            selection_mode: SelectionMode::Fastest,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+    let ranker = Arc::new(LatencyRanker::new(MockProber));
+    relay_selector.set_latency_ranker(ranker.clone());
+    (relay_selector, ranker)
+}
+
+/// In [`SelectionMode::Fastest`], the relay with the lowest latency should always be picked once
+/// latencies have been measured, and relays that cannot be reached should be avoided.
+#[test]
+fn test_fastest_selection_mode() {
+    let (relay_selector, _ranker) = fastest_relay_selector();
+    let runtime_params = RuntimeParameters { ipv6: false };
+
+    futures::executor::block_on(relay_selector.measure_latencies(0, runtime_params.clone()));
+    for _ in 0..100 {
+        let relay = unwrap_relay(relay_selector.get_relay(0, runtime_params.clone()).unwrap());
+        assert_eq!(relay.hostname, "se10-wireguard");
+    }
+
+    // With multihop, the latency to the entry relay is what matters
+    let query = RelayQueryBuilder::new().wireguard().multihop().build();
+    for _ in 0..100 {
+        let relay = unwrap_entry_relay(relay_selector.get_relay_by_query(query.clone()).unwrap());
+        assert_eq!(relay.hostname, "se10-wireguard");
+    }
+}
+
+/// Without latency measurements, [`SelectionMode::Fastest`] should fall back on picking any
+/// matching relay.
+#[test]
+fn test_fastest_selection_mode_unmeasured() {
+    let (relay_selector, _ranker) = fastest_relay_selector();
+    let query = RelayQueryBuilder::new()
+        .wireguard()
+        .location(NON_DAITA_RELAY_LOCATION.clone())
+        .build();
+    let relay = unwrap_relay(relay_selector.get_relay_by_query(query).unwrap());
+    assert_eq!(relay.hostname, "se10-wireguard");
+}
+
+/// When multihop is enabled automatically to use DAITA, the entry relay should be the fastest
+/// DAITA relay in [`SelectionMode::Fastest`].
+#[test]
+fn test_fastest_selection_mode_daita_auto_multihop() {
+    let (relay_selector, ranker) = fastest_relay_selector();
+    futures::executor::block_on(ranker.measure(RELAYS.relays().cloned().collect()));
+
+    let query = RelayQueryBuilder::new()
+        .wireguard()
+        .daita()
+        .daita_use_multihop_if_necessary(true)
+        .location(NON_DAITA_RELAY_LOCATION.clone())
+        .build();
+    for _ in 0..100 {
+        let relay = relay_selector.get_relay_by_query(query.clone()).unwrap();
+        let GetRelay::Wireguard {
+            inner: WireguardConfig::Multihop { entry, exit },
+            ..
+        } = relay
+        else {
+            panic!("Expected a multihop configuration, got {relay:?}");
+        };
+        assert_eq!(entry.hostname, "se11-wireguard");
+        assert_eq!(exit.hostname, "se10-wireguard");
+    }
+}
+
+/// Saved custom endpoints should be tried in order, moving on to the next endpoint for every
+/// failed attempt. Endpoints that no longer exist are skipped.
+#[test]
+fn test_saved_custom_endpoints_failover() {
+    let custom_endpoint = |name: &str| {
+        let config = ConnectionConfig::OpenVpn(openvpn::ConnectionConfig {
+            endpoint: Endpoint::new(Ipv4Addr::UNSPECIFIED, 1194, Udp),
+            username: "user".to_string(),
+            password: "pass".to_string(),
+        });
+        NamedCustomEndpoint::new(
+            name.to_string(),
+            CustomTunnelEndpoint::new(format!("{name}.example.com"), config),
+        )
+        .unwrap()
+    };
+    let (first, second, removed) = (
+        custom_endpoint("first"),
+        custom_endpoint("second"),
+        custom_endpoint("removed"),
+    );
+
+    let config = SelectorConfig {
+        relay_settings: RelaySettings::SavedCustomEndpoints(vec![first.id, removed.id, second.id]),
+        custom_endpoints: CustomEndpointsSettings::from(vec![first.clone(), second.clone()]),
+        ..SelectorConfig::default()
+    };
+    let mut relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+
+    for retry_attempt in 0..10 {
+        let expected = if retry_attempt % 2 == 0 {
+            &first
+        } else {
+            &second
+        };
+        match relay_selector
+            .get_relay(retry_attempt, RuntimeParameters::default())
+            .unwrap()
+        {
+            GetRelay::Custom { endpoint, .. } => assert_eq!(endpoint, expected.endpoint),
+            wrong_relay => panic!("Expected a custom endpoint, instead chose {wrong_relay:?}"),
+        }
+    }
+
+    // Fail if none of the endpoints exist
+    relay_selector.set_config(SelectorConfig {
+        relay_settings: RelaySettings::SavedCustomEndpoints(vec![removed.id]),
+        ..SelectorConfig::default()
+    });
+    assert!(matches!(
+        relay_selector.get_relay(0, RuntimeParameters::default()),
+        Err(Error::NoCustomEndpoint)
+    ));
+}
+
+/// Custom WireGuard endpoints should be obfuscated using the self-hosted Shadowsocks server when
+/// Shadowsocks obfuscation is selected, and never through a Mullvad operated obfuscator.
+#[test]
+fn test_custom_endpoint_shadowsocks() {
+    let wg_quick: WgQuickConfig = "[Interface]
+PrivateKey = mPue6Xt0pdz4NRAhfQSp/SLKo7kV7DW+2zvBq0N9iUI=
+Address = 10.8.0.2/24
+
+[Peer]
+PublicKey = BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=
+AllowedIPs = 0.0.0.0/0
+Endpoint = 192.0.2.1:51820
+"
+    .parse()
+    .unwrap();
+    let server = Shadowsocks::new(
+        ([192, 0, 2, 1], 8388),
+        "2022-blake3-aes-256-gcm".to_string(),
+        "qw2dI8Vh8Q5GmcSmyBbfHgUm+XoCgG7HRgTUmgx5Wqs=".to_string(),
+    );
+    let config_with_obfuscation = |obfuscation_settings| SelectorConfig {
+        relay_settings: RelaySettings::CustomTunnelEndpoint(wg_quick.custom_tunnel_endpoint()),
+        obfuscation_settings,
+        ..SelectorConfig::default()
+    };
+    let get_obfuscator = |relay_selector: &RelaySelector| match relay_selector
+        .get_relay(0, RuntimeParameters::default())
+        .unwrap()
+    {
+        GetRelay::Custom { obfuscator, .. } => obfuscator,
+        wrong_relay => panic!("Expected a custom endpoint, instead chose {wrong_relay:?}"),
+    };
+
+    let mut relay_selector = RelaySelector::from_list(
+        config_with_obfuscation(ObfuscationSettings {
+            selected_obfuscation: SelectedObfuscation::Shadowsocks,
+            custom_shadowsocks: Some(server.clone()),
+            ..ObfuscationSettings::default()
+        }),
+        RELAYS.clone(),
+    );
+    assert_eq!(
+        get_obfuscator(&relay_selector),
+        Some(ObfuscatorConfig::CustomShadowsocks { server })
+    );
+
+    // Without a self-hosted server, the endpoint is used without obfuscation
+    relay_selector.set_config(config_with_obfuscation(ObfuscationSettings {
+        selected_obfuscation: SelectedObfuscation::Shadowsocks,
+        ..ObfuscationSettings::default()
+    }));
+    assert_eq!(get_obfuscator(&relay_selector), None);
+
+    // Reject servers that cannot be used
+    relay_selector.set_config(config_with_obfuscation(ObfuscationSettings {
+        selected_obfuscation: SelectedObfuscation::Shadowsocks,
+        custom_shadowsocks: Some(Shadowsocks::new(
+            ([192, 0, 2, 1], 8388),
+            "2022-blake3-aes-256-gcm".to_string(),
+            "too short".to_string(),
+        )),
+        ..ObfuscationSettings::default()
+    }));
+    assert!(matches!(
+        relay_selector.get_relay(0, RuntimeParameters::default()),
+        Err(Error::NoObfuscator(_))
+    ));
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+use talpid_types::net::proxy::{CustomProxy, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote};
 
 /// Settings for API access methods.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -344,3 +344,9 @@ impl From<Shadowsocks> for AccessMethod {
         CustomProxy::Shadowsocks(value).into()
     }
 }
+
+impl From<HttpConnect> for AccessMethod {
+    fn from(value: HttpConnect) -> Self {
+        CustomProxy::HttpConnect(value).into()
+    }
+}
@@ -0,0 +1,479 @@
+//! Rules for connecting or disconnecting automatically depending on the network that the device is
+//! connected to and the time of day.
+
+use crate::relay_constraints::RelaySettings;
+use chrono::NaiveTime;
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashSet, fmt, net::IpAddr, ops::Deref, str::FromStr};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Auto-connect rule name must not be empty")]
+    EmptyName,
+    #[error("Auto-connect rule with name already exists: {0}")]
+    DuplicateName(String),
+    #[error("Invalid MAC address")]
+    InvalidMacAddress,
+    #[error("Invalid time window. Expected a window such as 08:00-17:00")]
+    InvalidTimeWindow,
+}
+
+/// Ordered list of auto-connect rules. The first rule whose conditions match is applied.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoConnectRules {
+    rules: Vec<AutoConnectRule>,
+}
+
+impl AutoConnectRules {
+    /// Create a set of rules. Returns an error if a name is empty or used by more than one rule.
+    pub fn new(rules: Vec<AutoConnectRule>) -> Result<Self, Error> {
+        let mut names = HashSet::new();
+        for rule in &rules {
+            if rule.name.is_empty() {
+                return Err(Error::EmptyName);
+            }
+            if !names.insert(rule.name.as_str()) {
+                return Err(Error::DuplicateName(rule.name.clone()));
+            }
+        }
+        Ok(Self { rules })
+    }
+
+    /// Return the first rule that matches `network` at the local time `time`.
+    pub fn first_match(
+        &self,
+        network: &NetworkDetails,
+        time: NaiveTime,
+    ) -> Option<&AutoConnectRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.conditions.matches(network, time))
+    }
+}
+
+impl IntoIterator for AutoConnectRules {
+    type Item = AutoConnectRule;
+    type IntoIter = <Vec<AutoConnectRule> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rules.into_iter()
+    }
+}
+
+impl Deref for AutoConnectRules {
+    type Target = [AutoConnectRule];
+
+    fn deref(&self) -> &Self::Target {
+        &self.rules
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoConnectRule {
+    pub name: String,
+    pub conditions: RuleConditions,
+    pub action: AutoConnectAction,
+}
+
+/// What to do when a rule starts matching.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoConnectAction {
+    Connect,
+    Disconnect,
+    /// Connect using these relay settings instead of the saved ones. They are not saved, and only
+    /// apply until a different rule matches or the user changes the relay settings.
+    ConnectUsing(RelaySettings),
+}
+
+impl fmt::Display for AutoConnectAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoConnectAction::Connect => f.write_str("connect"),
+            AutoConnectAction::Disconnect => f.write_str("disconnect"),
+            AutoConnectAction::ConnectUsing(_) => f.write_str("connect using relay preset"),
+        }
+    }
+}
+
+/// Conditions that must all be met for a rule to match. A rule without conditions always matches.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleConditions {
+    /// Name of the interface used by the default route
+    pub interface: Option<String>,
+    /// IP address of the default gateway
+    pub gateway_ip: Option<IpAddr>,
+    /// MAC address of the default gateway
+    pub gateway_mac: Option<MacAddress>,
+    /// DNS search domain of the current network
+    pub dns_suffix: Option<String>,
+    /// Local time of day
+    pub time_window: Option<TimeWindow>,
+}
+
+impl RuleConditions {
+    /// Returns whether any condition depends on the network, rather than only on the time of day.
+    pub fn depends_on_network(&self) -> bool {
+        self.interface.is_some()
+            || self.gateway_ip.is_some()
+            || self.gateway_mac.is_some()
+            || self.dns_suffix.is_some()
+    }
+
+    pub fn matches(&self, network: &NetworkDetails, time: NaiveTime) -> bool {
+        let interface_matches = self
+            .interface
+            .as_ref()
+            .map(|interface| network.interface.as_ref() == Some(interface))
+            .unwrap_or(true);
+        let gateway_ip_matches = self
+            .gateway_ip
+            .map(|ip| network.gateway_ip == Some(ip))
+            .unwrap_or(true);
+        let gateway_mac_matches = self
+            .gateway_mac
+            .map(|mac| network.gateway_mac == Some(mac))
+            .unwrap_or(true);
+        let dns_suffix_matches = self
+            .dns_suffix
+            .as_ref()
+            .map(|suffix| {
+                network
+                    .dns_suffixes
+                    .iter()
+                    .any(|network_suffix| same_domain(suffix, network_suffix))
+            })
+            .unwrap_or(true);
+        let time_matches = self
+            .time_window
+            .map(|window| window.contains(time))
+            .unwrap_or(true);
+
+        interface_matches
+            && gateway_ip_matches
+            && gateway_mac_matches
+            && dns_suffix_matches
+            && time_matches
+    }
+}
+
+fn same_domain(a: &str, b: &str) -> bool {
+    a.trim_end_matches('.')
+        .eq_ignore_ascii_case(b.trim_end_matches('.'))
+}
+
+/// A network on which the tunnel is disconnected automatically, unless lockdown mode is enabled.
+/// The tunnel is reconnected when leaving the network.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustedNetwork {
+    /// Network whose default gateway has this MAC address
+    GatewayMac(MacAddress),
+    /// Wi-Fi network with this SSID
+    Ssid(String),
+    /// Network whose default gateway is in this subnet
+    Subnet(IpNetwork),
+}
+
+impl TrustedNetwork {
+    /// Returns whether the network can be recognized on this platform. Networks can only be
+    /// identified on Linux and macOS, and Wi-Fi SSIDs only on Linux.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            TrustedNetwork::Ssid(_) => cfg!(target_os = "linux"),
+            TrustedNetwork::GatewayMac(_) | TrustedNetwork::Subnet(_) => {
+                cfg!(any(target_os = "linux", target_os = "macos"))
+            }
+        }
+    }
+
+    pub fn matches(&self, network: &NetworkDetails) -> bool {
+        match self {
+            TrustedNetwork::GatewayMac(mac) => network.gateway_mac == Some(*mac),
+            TrustedNetwork::Ssid(ssid) => network.ssid.as_ref() == Some(ssid),
+            TrustedNetwork::Subnet(subnet) => network
+                .gateway_ip
+                .is_some_and(|gateway| subnet.contains(gateway)),
+        }
+    }
+}
+
+impl fmt::Display for TrustedNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrustedNetwork::GatewayMac(mac) => write!(f, "gateway MAC {mac}"),
+            TrustedNetwork::Ssid(ssid) => write!(f, "SSID \"{ssid}\""),
+            TrustedNetwork::Subnet(subnet) => write!(f, "subnet {subnet}"),
+        }
+    }
+}
+
+/// Details about the network that the device is connected to, used to evaluate rules.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct NetworkDetails {
+    /// Name of the interface used by the default route
+    pub interface: Option<String>,
+    pub gateway_ip: Option<IpAddr>,
+    pub gateway_mac: Option<MacAddress>,
+    pub dns_suffixes: Vec<String>,
+    /// SSID of the Wi-Fi network, if the default route uses a wireless interface
+    pub ssid: Option<String>,
+}
+
+/// A window of local time. If `end` is earlier than `start`, the window spans midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').ok_or(Error::InvalidTimeWindow)?;
+        let parse = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| Error::InvalidTimeWindow)
+        };
+        Ok(TimeWindow {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// Link layer address, formatted as `aa:bb:cc:dd:ee:ff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl FromStr for MacAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut address = [0u8; 6];
+        let mut parts = s.split([':', '-']);
+        for byte in &mut address {
+            let part = parts.next().ok_or(Error::InvalidMacAddress)?;
+            if part.len() != 2 {
+                return Err(Error::InvalidMacAddress);
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| Error::InvalidMacAddress)?;
+        }
+        if parts.next().is_some() {
+            return Err(Error::InvalidMacAddress);
+        }
+        Ok(MacAddress(address))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        address.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn time(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_time_window() {
+        let office_hours: TimeWindow = "08:00-17:00".parse().unwrap();
+        assert!(office_hours.contains(time("08:00")));
+        assert!(office_hours.contains(time("12:30")));
+        assert!(!office_hours.contains(time("17:00")));
+
+        let night: TimeWindow = "22:00-06:00".parse().unwrap();
+        assert!(night.contains(time("23:00")));
+        assert!(night.contains(time("05:59")));
+        assert!(!night.contains(time("12:00")));
+
+        assert!("08:00".parse::<TimeWindow>().is_err());
+    }
+
+    #[test]
+    fn test_mac_address() {
+        let mac: MacAddress = "AA:bb:cc:00:11:22".parse().unwrap();
+        assert_eq!(mac.to_string(), "aa:bb:cc:00:11:22");
+        assert_eq!("aa-bb-cc-00-11-22".parse::<MacAddress>().unwrap(), mac);
+        assert!("aa:bb:cc:00:11".parse::<MacAddress>().is_err());
+        assert!("aa:bb:cc:00:11:22:33".parse::<MacAddress>().is_err());
+    }
+
+    /// The first rule whose conditions all match should be picked
+    #[test]
+    fn test_first_match() {
+        let office = AutoConnectRule {
+            name: "office".to_owned(),
+            conditions: RuleConditions {
+                dns_suffix: Some("corp.example.com".to_owned()),
+                time_window: Some("08:00-17:00".parse().unwrap()),
+                ..Default::default()
+            },
+            action: AutoConnectAction::Disconnect,
+        };
+        let fallback = AutoConnectRule {
+            name: "anywhere else".to_owned(),
+            conditions: RuleConditions::default(),
+            action: AutoConnectAction::Connect,
+        };
+        let rules = AutoConnectRules::new(vec![office, fallback]).unwrap();
+
+        let office_network = NetworkDetails {
+            interface: Some("wlan0".to_owned()),
+            dns_suffixes: vec!["CORP.example.com.".to_owned()],
+            ..Default::default()
+        };
+        let rule_name = |network: &NetworkDetails, at: &str| {
+            rules
+                .first_match(network, time(at))
+                .map(|rule| rule.name.clone())
+        };
+        assert_eq!(rule_name(&office_network, "09:00").unwrap(), "office");
+        assert_eq!(
+            rule_name(&office_network, "20:00").unwrap(),
+            "anywhere else"
+        );
+        assert_eq!(
+            rule_name(&NetworkDetails::default(), "09:00").unwrap(),
+            "anywhere else"
+        );
+    }
+
+    /// Every network condition must match
+    #[test]
+    fn test_network_conditions() {
+        let network = NetworkDetails {
+            interface: Some("eth0".to_owned()),
+            gateway_ip: Some("192.168.1.1".parse().unwrap()),
+            gateway_mac: Some("aa:bb:cc:00:11:22".parse().unwrap()),
+            dns_suffixes: vec!["lan".to_owned()],
+            ssid: None,
+        };
+        let conditions = RuleConditions {
+            interface: Some("eth0".to_owned()),
+            gateway_ip: Some("192.168.1.1".parse().unwrap()),
+            gateway_mac: Some("aa:bb:cc:00:11:22".parse().unwrap()),
+            dns_suffix: Some("LAN.".to_owned()),
+            time_window: None,
+        };
+        assert!(conditions.depends_on_network());
+        assert!(conditions.matches(&network, time("12:00")));
+        assert!(!conditions.matches(&NetworkDetails::default(), time("12:00")));
+
+        let mismatches = [
+            RuleConditions {
+                interface: Some("wlan0".to_owned()),
+                ..conditions.clone()
+            },
+            RuleConditions {
+                gateway_ip: Some("192.168.1.2".parse().unwrap()),
+                ..conditions.clone()
+            },
+            RuleConditions {
+                gateway_mac: Some("aa:bb:cc:00:11:23".parse().unwrap()),
+                ..conditions.clone()
+            },
+            RuleConditions {
+                dns_suffix: Some("corp.example.com".to_owned()),
+                ..conditions.clone()
+            },
+            RuleConditions {
+                time_window: Some("08:00-09:00".parse().unwrap()),
+                ..conditions.clone()
+            },
+        ];
+        for conditions in mismatches {
+            assert!(
+                !conditions.matches(&network, time("12:00")),
+                "{conditions:?}"
+            );
+        }
+
+        let time_only = RuleConditions {
+            time_window: Some("08:00-17:00".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(!time_only.depends_on_network());
+    }
+
+    #[test]
+    fn test_trusted_network() {
+        let network = NetworkDetails {
+            gateway_ip: Some("192.168.1.1".parse().unwrap()),
+            gateway_mac: Some("aa:bb:cc:00:11:22".parse().unwrap()),
+            ssid: Some("home".to_owned()),
+            ..Default::default()
+        };
+        let mac = |mac: &str| TrustedNetwork::GatewayMac(mac.parse().unwrap());
+        let ssid = |ssid: &str| TrustedNetwork::Ssid(ssid.to_owned());
+        let subnet = |subnet: &str| TrustedNetwork::Subnet(subnet.parse().unwrap());
+
+        assert!(mac("aa:bb:cc:00:11:22").matches(&network));
+        assert!(!mac("aa:bb:cc:00:11:23").matches(&network));
+        assert!(ssid("home").matches(&network));
+        assert!(!ssid("Home").matches(&network));
+        assert!(subnet("192.168.0.0/16").matches(&network));
+        assert!(!subnet("10.0.0.0/8").matches(&network));
+        assert!(!subnet("192.168.0.0/16").matches(&NetworkDetails::default()));
+
+        let identifiable = cfg!(any(target_os = "linux", target_os = "macos"));
+        assert_eq!(mac("aa:bb:cc:00:11:22").is_supported(), identifiable);
+        assert_eq!(subnet("192.168.0.0/16").is_supported(), identifiable);
+        assert_eq!(ssid("home").is_supported(), cfg!(target_os = "linux"));
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let rule = AutoConnectRule {
+            name: "rule".to_owned(),
+            conditions: RuleConditions::default(),
+            action: AutoConnectAction::Connect,
+        };
+        assert!(matches!(
+            AutoConnectRules::new(vec![rule.clone(), rule]),
+            Err(Error::DuplicateName(_))
+        ));
+    }
+}
@@ -142,3 +142,4 @@ impl_intersection_partialeq!(relay_constraints::Ownership);
 impl_intersection_partialeq!(talpid_types::net::TransportProtocol);
 impl_intersection_partialeq!(talpid_types::net::TunnelType);
 impl_intersection_partialeq!(talpid_types::net::IpVersion);
+impl_intersection_partialeq!(Option<talpid_types::net::obfuscation::WebSocketSettings>);
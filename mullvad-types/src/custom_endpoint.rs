@@ -0,0 +1,242 @@
+use crate::CustomTunnelEndpoint;
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
+
+const CUSTOM_ENDPOINT_NAME_MAX_SIZE: usize = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Custom endpoint name too long")]
+    NameTooLong,
+    #[error("Custom endpoint with name already exists")]
+    DuplicateName,
+    #[error("Custom endpoint not found")]
+    EndpointNotFound,
+    #[error("Custom endpoint with given ID already exists")]
+    EndpointExists,
+    #[error("At least one custom endpoint must be selected")]
+    NoEndpointSelected,
+    #[error("Custom endpoint is selected in the relay settings")]
+    EndpointInUse,
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Id(uuid::Uuid);
+
+impl Deref for Id {
+    type Target = uuid::Uuid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Id {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromStr for Id {
+    type Err = <uuid::Uuid as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        uuid::Uuid::from_str(s).map(Id)
+    }
+}
+
+/// Custom tunnel endpoints saved by the user. These can be selected using
+/// [`RelaySettings::SavedCustomEndpoints`].
+///
+/// [`RelaySettings::SavedCustomEndpoints`]: crate::relay_constraints::RelaySettings::SavedCustomEndpoints
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomEndpointsSettings {
+    custom_endpoints: Vec<NamedCustomEndpoint>,
+}
+
+impl From<Vec<NamedCustomEndpoint>> for CustomEndpointsSettings {
+    fn from(custom_endpoints: Vec<NamedCustomEndpoint>) -> Self {
+        Self { custom_endpoints }
+    }
+}
+
+impl CustomEndpointsSettings {
+    pub fn add(&mut self, new_endpoint: NamedCustomEndpoint) -> Result<(), Error> {
+        if self.find_index(&new_endpoint.id).is_some() {
+            return Err(Error::EndpointExists);
+        }
+        self.check_name_is_unique(&new_endpoint)?;
+        self.custom_endpoints.push(new_endpoint);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, id: &Id) -> Result<(), Error> {
+        let index = self.find_index(id).ok_or(Error::EndpointNotFound)?;
+        self.custom_endpoints.remove(index);
+        Ok(())
+    }
+
+    /// Remove all custom endpoints
+    pub fn clear(&mut self) {
+        self.custom_endpoints.clear();
+    }
+
+    pub fn update(&mut self, new_endpoint: NamedCustomEndpoint) -> Result<(), Error> {
+        let index = self
+            .find_index(&new_endpoint.id)
+            .ok_or(Error::EndpointNotFound)?;
+        check_name_length(&new_endpoint.name)?;
+        self.check_name_is_unique(&new_endpoint)?;
+        self.custom_endpoints[index] = new_endpoint;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &Id) -> Option<&NamedCustomEndpoint> {
+        self.custom_endpoints
+            .iter()
+            .find(|endpoint| endpoint.id == *id)
+    }
+
+    /// Check that `ids` is a valid selection of saved endpoints.
+    pub fn validate_selection(&self, ids: &[Id]) -> Result<(), Error> {
+        if ids.is_empty() {
+            return Err(Error::NoEndpointSelected);
+        }
+        if ids.iter().any(|id| self.get(id).is_none()) {
+            return Err(Error::EndpointNotFound);
+        }
+        Ok(())
+    }
+
+    /// Return the endpoints referenced by `ids`, in the same order. IDs that do not refer to a
+    /// saved endpoint are skipped.
+    pub fn resolve<'a>(&'a self, ids: &'a [Id]) -> impl Iterator<Item = &'a NamedCustomEndpoint> {
+        ids.iter().filter_map(|id| self.get(id))
+    }
+
+    fn check_name_is_unique(&self, new_endpoint: &NamedCustomEndpoint) -> Result<(), Error> {
+        if self
+            .custom_endpoints
+            .iter()
+            .any(|endpoint| endpoint.name == new_endpoint.name && endpoint.id != new_endpoint.id)
+        {
+            return Err(Error::DuplicateName);
+        }
+        Ok(())
+    }
+
+    fn find_index(&self, id: &Id) -> Option<usize> {
+        self.custom_endpoints
+            .iter()
+            .position(|endpoint| endpoint.id == *id)
+    }
+}
+
+impl IntoIterator for CustomEndpointsSettings {
+    type Item = NamedCustomEndpoint;
+    type IntoIter = <Vec<NamedCustomEndpoint> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.custom_endpoints.into_iter()
+    }
+}
+
+impl Deref for CustomEndpointsSettings {
+    type Target = [NamedCustomEndpoint];
+
+    fn deref(&self) -> &Self::Target {
+        &self.custom_endpoints
+    }
+}
+
+/// A saved custom tunnel endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NamedCustomEndpoint {
+    pub id: Id,
+    pub name: String,
+    pub endpoint: CustomTunnelEndpoint,
+}
+
+impl NamedCustomEndpoint {
+    pub fn new(name: String, endpoint: CustomTunnelEndpoint) -> Result<Self, Error> {
+        check_name_length(&name)?;
+
+        Ok(NamedCustomEndpoint {
+            id: Id(uuid::Uuid::new_v4()),
+            name,
+            endpoint,
+        })
+    }
+}
+
+fn check_name_length(name: &str) -> Result<(), Error> {
+    if name.chars().count() > CUSTOM_ENDPOINT_NAME_MAX_SIZE {
+        return Err(Error::NameTooLong);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ConnectionConfig;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use talpid_types::net::{openvpn, Endpoint, TransportProtocol};
+
+    fn endpoint(name: &str) -> NamedCustomEndpoint {
+        let config = ConnectionConfig::OpenVpn(openvpn::ConnectionConfig {
+            endpoint: Endpoint::from_socket_address(
+                SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 1194),
+                TransportProtocol::Udp,
+            ),
+            username: "user".to_owned(),
+            password: "pass".to_owned(),
+        });
+        NamedCustomEndpoint::new(
+            name.to_owned(),
+            CustomTunnelEndpoint::new(format!("{name}.example.com"), config),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let mut settings = CustomEndpointsSettings::default();
+        settings.add(endpoint("a")).unwrap();
+        assert!(matches!(
+            settings.add(endpoint("a")),
+            Err(Error::DuplicateName)
+        ));
+
+        let mut renamed = endpoint("b");
+        settings.add(renamed.clone()).unwrap();
+        renamed.name = "a".to_owned();
+        assert!(matches!(
+            settings.update(renamed.clone()),
+            Err(Error::DuplicateName)
+        ));
+
+        renamed.name = "b".repeat(CUSTOM_ENDPOINT_NAME_MAX_SIZE + 1);
+        assert!(matches!(settings.update(renamed), Err(Error::NameTooLong)));
+    }
+
+    /// Endpoints should be resolved in the given order, skipping unknown IDs
+    #[test]
+    fn test_resolve_failover_order() {
+        let (a, b) = (endpoint("a"), endpoint("b"));
+        let mut settings = CustomEndpointsSettings::default();
+        settings.add(a.clone()).unwrap();
+        settings.add(b.clone()).unwrap();
+
+        let removed = endpoint("c").id;
+        let ids = [b.id, removed, a.id];
+        let names: Vec<_> = settings
+            .resolve(&ids)
+            .map(|endpoint| endpoint.name.as_str())
+            .collect();
+        assert_eq!(names, ["b", "a"]);
+    }
+}
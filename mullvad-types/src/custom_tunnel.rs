@@ -4,7 +4,12 @@ use std::{
     fmt, io,
     net::{IpAddr, SocketAddr, ToSocketAddrs},
 };
-use talpid_types::net::{openvpn, proxy::CustomProxy, wireguard, Endpoint, TunnelParameters};
+use talpid_types::net::{
+    obfuscation::ObfuscatorConfig, openvpn, proxy::CustomProxy, wireguard, Endpoint,
+    TunnelParameters,
+};
+
+pub mod wg_quick;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -19,11 +24,19 @@ pub enum Error {
 pub struct CustomTunnelEndpoint {
     pub host: String,
     pub config: ConnectionConfig,
+    /// Preshared key of the WireGuard peer. It is stored here rather than in
+    /// [`wireguard::PeerConfig`], since PSKs in tunnel configs are ephemeral and never persisted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wireguard_psk: Option<wireguard::PresharedKey>,
 }
 
 impl CustomTunnelEndpoint {
     pub fn new(host: String, config: ConnectionConfig) -> Self {
-        Self { host, config }
+        Self {
+            host,
+            config,
+            wireguard_psk: None,
+        }
     }
 
     pub fn endpoint(&self) -> Endpoint {
@@ -33,10 +46,13 @@ impl CustomTunnelEndpoint {
         }
     }
 
+    /// Create tunnel parameters for this endpoint. `proxy` only applies to OpenVPN and
+    /// `obfuscation` only to WireGuard.
     pub fn to_tunnel_parameters(
         &self,
         tunnel_options: TunnelOptions,
         proxy: Option<CustomProxy>,
+        obfuscation: Option<ObfuscatorConfig>,
     ) -> Result<TunnelParameters, Error> {
         let ip = resolve_to_ip(&self.host)?;
         let mut config = self.config.clone();
@@ -52,7 +68,8 @@ impl CustomTunnelEndpoint {
                 fwmark: crate::TUNNEL_FWMARK,
             }
             .into(),
-            ConnectionConfig::Wireguard(connection) => {
+            ConnectionConfig::Wireguard(mut connection) => {
+                connection.peer.psk = self.wireguard_psk.clone();
                 let mut options = tunnel_options.wireguard.into_talpid_tunnel_options();
                 if options.quantum_resistant {
                     options.quantum_resistant = false;
@@ -62,7 +79,7 @@ impl CustomTunnelEndpoint {
                     connection,
                     options,
                     generic_options: tunnel_options.generic,
-                    obfuscation: None,
+                    obfuscation,
                 }
                 .into()
             }
@@ -0,0 +1,809 @@
+//! Import and export of custom WireGuard endpoints as `wg-quick` configuration files.
+//!
+//! Only the subset of the format that maps onto a [`CustomTunnelEndpoint`] is supported: a
+//! single `[Interface]` section and a single `[Peer]` section. Keys that would have no effect,
+//! such as `PostUp` or `Table`, are rejected instead of being silently ignored. The exception is
+//! `PersistentKeepalive`, which is common in configuration files and harmless to leave out. It is
+//! ignored, and reported in [`WgQuickConfig::ignored_keys`].
+//!
+//! `DNS` and `MTU` are not part of the endpoint itself. They are returned alongside it, so that
+//! the caller can decide whether to apply them to the global DNS and tunnel settings.
+//!
+//! The format has no notion of a gateway, so it is derived from the subnet of the tunnel address.
+//! When exporting, the prefix length of the address is chosen so that the gateway can be derived
+//! from it again. If that is not possible, the gateway is written as a `# Gateway = <address>`
+//! comment in the `[Interface]` section, which `wg-quick` ignores.
+
+use super::{ConnectionConfig, CustomTunnelEndpoint};
+use ipnetwork::{IpNetwork, Ipv4Network, Ipv6Network};
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+use talpid_types::net::wireguard;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Line {line}: Expected a section header or \"Key = Value\"")]
+    InvalidLine { line: usize },
+
+    #[error("Line {line}: Unsupported section [{section}]")]
+    UnsupportedSection { line: usize, section: String },
+
+    #[error("Line {line}: \"{key}\" is not inside a section")]
+    KeyOutsideSection { line: usize, key: String },
+
+    #[error("Line {line}: \"{key}\" is not supported in [{section}]")]
+    UnsupportedKey {
+        line: usize,
+        section: Section,
+        key: String,
+    },
+
+    #[error("Line {line}: \"{key}\" is specified more than once")]
+    DuplicateKey { line: usize, key: String },
+
+    #[error("Line {line}: Invalid value for \"{key}\": {reason}")]
+    InvalidValue {
+        line: usize,
+        key: String,
+        reason: String,
+    },
+
+    #[error("Missing [{0}] section")]
+    MissingSection(Section),
+
+    #[error("Line {line}: Only a single [{section}] section is supported")]
+    DuplicateSection { line: usize, section: Section },
+
+    #[error("Missing \"{key}\" in [{section}]")]
+    MissingKey { section: Section, key: &'static str },
+
+    #[error("Cannot determine the IPv4 gateway. Specify an IPv4 \"Address\" with a subnet prefix, or an IPv4 \"DNS\" server")]
+    NoIpv4Gateway,
+
+    #[error("Only WireGuard endpoints can be exported")]
+    NotWireguard,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Interface,
+    Peer,
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Section::Interface => f.write_str("Interface"),
+            Section::Peer => f.write_str("Peer"),
+        }
+    }
+}
+
+/// A custom WireGuard endpoint, along with the settings from a `wg-quick` configuration file
+/// which do not belong to the endpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgQuickConfig {
+    /// Hostname or IP address of the peer.
+    pub host: String,
+    pub config: wireguard::ConnectionConfig,
+    /// Value of `PresharedKey`.
+    pub psk: Option<wireguard::PresharedKey>,
+    /// Servers listed in `DNS`.
+    pub dns_servers: Vec<IpAddr>,
+    /// Value of `MTU`.
+    pub mtu: Option<u16>,
+    /// Keys that are not supported but were ignored instead of rejected.
+    pub ignored_keys: Vec<String>,
+}
+
+impl WgQuickConfig {
+    /// Create a configuration that can be exported from a custom endpoint. `dns_servers` should
+    /// contain the DNS servers used in the tunnel. If it is empty, the gateways are used.
+    pub fn from_endpoint(
+        endpoint: CustomTunnelEndpoint,
+        mut dns_servers: Vec<IpAddr>,
+        mtu: Option<u16>,
+    ) -> Result<Self, Error> {
+        let ConnectionConfig::Wireguard(config) = endpoint.config else {
+            return Err(Error::NotWireguard);
+        };
+        if dns_servers.is_empty() {
+            dns_servers.push(IpAddr::V4(config.ipv4_gateway));
+            dns_servers.extend(config.ipv6_gateway.map(IpAddr::V6));
+        }
+        Ok(WgQuickConfig {
+            host: endpoint.host,
+            config,
+            psk: endpoint.wireguard_psk,
+            dns_servers,
+            mtu,
+            ignored_keys: vec![],
+        })
+    }
+
+    pub fn custom_tunnel_endpoint(&self) -> CustomTunnelEndpoint {
+        CustomTunnelEndpoint {
+            host: self.host.clone(),
+            config: ConnectionConfig::Wireguard(self.config.clone()),
+            wireguard_psk: self.psk.clone(),
+        }
+    }
+
+    /// Return the DNS servers, unless they are just the tunnel gateways, which the daemon uses
+    /// by default anyway.
+    pub fn custom_dns_servers(&self) -> Option<&[IpAddr]> {
+        let only_gateways = self.dns_servers.iter().all(|server| match server {
+            IpAddr::V4(server) => *server == self.config.ipv4_gateway,
+            IpAddr::V6(server) => Some(*server) == self.config.ipv6_gateway,
+        });
+        if only_gateways {
+            None
+        } else {
+            Some(&self.dns_servers)
+        }
+    }
+}
+
+impl FromStr for WgQuickConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser::default().parse(s)
+    }
+}
+
+/// Values collected while parsing a configuration file.
+#[derive(Default)]
+struct Parser {
+    section: Option<Section>,
+    seen_interface: bool,
+    seen_peer: bool,
+
+    private_key: Option<wireguard::PrivateKey>,
+    addresses: Option<Vec<IpNetwork>>,
+    /// Gateways given in `# Gateway = <address>` comments.
+    gateways: Vec<IpAddr>,
+    dns_servers: Option<Vec<IpAddr>>,
+    mtu: Option<u16>,
+
+    public_key: Option<wireguard::PublicKey>,
+    psk: Option<wireguard::PresharedKey>,
+    endpoint: Option<(String, u16)>,
+    allowed_ips: Option<Vec<IpNetwork>>,
+
+    ignored_keys: Vec<String>,
+}
+
+impl Parser {
+    fn parse(mut self, s: &str) -> Result<WgQuickConfig, Error> {
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            if let Some(gateway) = parse_gateway_comment(line) {
+                if self.section == Some(Section::Interface) {
+                    self.gateways.push(gateway);
+                }
+                continue;
+            }
+            let line = match line.split_once('#') {
+                Some((line, _comment)) => line,
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(section) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                self.enter_section(line_number, section.trim())?;
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(Error::InvalidLine { line: line_number })?;
+            self.parse_key(line_number, key.trim(), value.trim())?;
+        }
+
+        self.finish()
+    }
+
+    fn enter_section(&mut self, line: usize, section: &str) -> Result<(), Error> {
+        let section = if section.eq_ignore_ascii_case("interface") {
+            Section::Interface
+        } else if section.eq_ignore_ascii_case("peer") {
+            Section::Peer
+        } else {
+            return Err(Error::UnsupportedSection {
+                line,
+                section: section.to_owned(),
+            });
+        };
+        let seen = match section {
+            Section::Interface => &mut self.seen_interface,
+            Section::Peer => &mut self.seen_peer,
+        };
+        if *seen {
+            return Err(Error::DuplicateSection { line, section });
+        }
+        *seen = true;
+        self.section = Some(section);
+        Ok(())
+    }
+
+    fn parse_key(&mut self, line: usize, key: &str, value: &str) -> Result<(), Error> {
+        let Some(section) = self.section else {
+            return Err(Error::KeyOutsideSection {
+                line,
+                key: key.to_owned(),
+            });
+        };
+        let invalid = |reason: &dyn fmt::Display| Error::InvalidValue {
+            line,
+            key: key.to_owned(),
+            reason: reason.to_string(),
+        };
+        let duplicate = || Error::DuplicateKey {
+            line,
+            key: key.to_owned(),
+        };
+
+        match (section, key.to_ascii_lowercase().as_str()) {
+            (Section::Interface, "privatekey") => {
+                let private_key =
+                    wireguard::PrivateKey::from_base64(value).map_err(|error| invalid(&error))?;
+                set_once(&mut self.private_key, private_key, duplicate)
+            }
+            (Section::Interface, "address") => {
+                let addresses = self.addresses.get_or_insert_with(Vec::new);
+                for address in split_list(value) {
+                    addresses.push(
+                        address
+                            .parse::<IpNetwork>()
+                            .map_err(|error| invalid(&error))?,
+                    );
+                }
+                Ok(())
+            }
+            (Section::Interface, "dns") => {
+                let servers = self.dns_servers.get_or_insert_with(Vec::new);
+                for server in split_list(value) {
+                    // wg-quick treats anything that is not an IP address as a search domain
+                    let server = server.parse().map_err(|_| {
+                        invalid(&format_args!(
+                            "DNS search domains are not supported: {server}"
+                        ))
+                    })?;
+                    servers.push(server);
+                }
+                Ok(())
+            }
+            (Section::Interface, "mtu") => {
+                let mtu = value.parse().map_err(|error| invalid(&error))?;
+                set_once(&mut self.mtu, mtu, duplicate)
+            }
+            (Section::Peer, "publickey") => {
+                let public_key =
+                    wireguard::PublicKey::from_base64(value).map_err(|error| invalid(&error))?;
+                set_once(&mut self.public_key, public_key, duplicate)
+            }
+            (Section::Peer, "presharedkey") => {
+                let psk =
+                    wireguard::PresharedKey::from_base64(value).map_err(|error| invalid(&error))?;
+                set_once(&mut self.psk, psk, duplicate)
+            }
+            (Section::Peer, "endpoint") => {
+                let endpoint = parse_endpoint(value).map_err(|error| invalid(&error))?;
+                set_once(&mut self.endpoint, endpoint, duplicate)
+            }
+            (Section::Peer, "allowedips") => {
+                let allowed_ips = self.allowed_ips.get_or_insert_with(Vec::new);
+                for network in split_list(value) {
+                    allowed_ips.push(
+                        network
+                            .parse::<IpNetwork>()
+                            .map_err(|error| invalid(&error))?,
+                    );
+                }
+                Ok(())
+            }
+            (Section::Peer, "persistentkeepalive") => {
+                self.ignored_keys.push(key.to_owned());
+                Ok(())
+            }
+            _ => Err(Error::UnsupportedKey {
+                line,
+                section,
+                key: key.to_owned(),
+            }),
+        }
+    }
+
+    fn finish(self) -> Result<WgQuickConfig, Error> {
+        if !self.seen_interface {
+            return Err(Error::MissingSection(Section::Interface));
+        }
+        if !self.seen_peer {
+            return Err(Error::MissingSection(Section::Peer));
+        }
+        let missing = |section, key| Error::MissingKey { section, key };
+
+        let private_key = self
+            .private_key
+            .ok_or(missing(Section::Interface, "PrivateKey"))?;
+        let addresses = self
+            .addresses
+            .ok_or(missing(Section::Interface, "Address"))?;
+        let public_key = self.public_key.ok_or(missing(Section::Peer, "PublicKey"))?;
+        let (host, port) = self.endpoint.ok_or(missing(Section::Peer, "Endpoint"))?;
+        let allowed_ips = self
+            .allowed_ips
+            .ok_or(missing(Section::Peer, "AllowedIPs"))?;
+        let dns_servers = self.dns_servers.unwrap_or_default();
+
+        let ipv4_gateway =
+            ipv4_gateway(&addresses, &self.gateways, &dns_servers).ok_or(Error::NoIpv4Gateway)?;
+        let ipv6_gateway = ipv6_gateway(&addresses, &self.gateways, &dns_servers);
+
+        let config = wireguard::ConnectionConfig {
+            tunnel: wireguard::TunnelConfig {
+                private_key,
+                addresses: addresses.iter().map(IpNetwork::ip).collect(),
+            },
+            peer: wireguard::PeerConfig {
+                public_key,
+                allowed_ips,
+                // The host is resolved when connecting
+                endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
+                psk: None,
+                #[cfg(daita)]
+                constant_packet_size: false,
+            },
+            exit_peer: None,
+            ipv4_gateway,
+            ipv6_gateway,
+            #[cfg(target_os = "linux")]
+            fwmark: Some(crate::TUNNEL_FWMARK),
+        };
+
+        Ok(WgQuickConfig {
+            host,
+            config,
+            psk: self.psk,
+            dns_servers,
+            mtu: self.mtu,
+            ignored_keys: self.ignored_keys,
+        })
+    }
+}
+
+fn set_once<T>(
+    field: &mut Option<T>,
+    value: T,
+    duplicate: impl Fn() -> Error,
+) -> Result<(), Error> {
+    if field.is_some() {
+        return Err(duplicate());
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Parse `host:port`, where an IPv6 host is enclosed in brackets.
+fn parse_endpoint(value: &str) -> Result<(String, u16), String> {
+    let (host, port) = value
+        .rsplit_once(':')
+        .ok_or_else(|| "expected \"host:port\"".to_owned())?;
+    let port = port.parse().map_err(|_| format!("invalid port: {port}"))?;
+    let host = match host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+    {
+        Some(host) => {
+            host.parse::<Ipv6Addr>()
+                .map_err(|_| format!("invalid IPv6 address: {host}"))?;
+            host
+        }
+        None if host.contains(':') => {
+            return Err("IPv6 addresses must be enclosed in brackets".to_owned());
+        }
+        None => host,
+    };
+    if host.is_empty() {
+        return Err("missing host".to_owned());
+    }
+    Ok((host.to_owned(), port))
+}
+
+/// Parse a `# Gateway = <address>` comment, as written by the exporter when the gateway cannot
+/// be derived from the tunnel address.
+fn parse_gateway_comment(line: &str) -> Option<IpAddr> {
+    let (key, value) = line.trim().strip_prefix('#')?.split_once('=')?;
+    if !key.trim().eq_ignore_ascii_case("gateway") {
+        return None;
+    }
+    value.trim().parse().ok()
+}
+
+/// wg-quick files do not specify a gateway. Use the gateway given in a comment if there is one,
+/// then the first address in the subnet of the tunnel address, and otherwise fall back on the
+/// DNS server.
+fn ipv4_gateway(
+    addresses: &[IpNetwork],
+    gateways: &[IpAddr],
+    dns_servers: &[IpAddr],
+) -> Option<Ipv4Addr> {
+    let explicit = gateways.iter().find_map(|gateway| match gateway {
+        IpAddr::V4(gateway) => Some(*gateway),
+        IpAddr::V6(_) => None,
+    });
+    let from_subnet = || {
+        addresses.iter().find_map(|address| match address {
+            IpNetwork::V4(address) => ipv4_subnet_gateway(address),
+            IpNetwork::V6(_) => None,
+        })
+    };
+    explicit.or_else(from_subnet).or_else(|| {
+        dns_servers.iter().find_map(|server| match server {
+            IpAddr::V4(server) => Some(*server),
+            IpAddr::V6(_) => None,
+        })
+    })
+}
+
+/// See [`ipv4_gateway`].
+fn ipv6_gateway(
+    addresses: &[IpNetwork],
+    gateways: &[IpAddr],
+    dns_servers: &[IpAddr],
+) -> Option<Ipv6Addr> {
+    let explicit = gateways.iter().find_map(|gateway| match gateway {
+        IpAddr::V6(gateway) => Some(*gateway),
+        IpAddr::V4(_) => None,
+    });
+    let from_subnet = || {
+        addresses.iter().find_map(|address| match address {
+            IpNetwork::V6(address) => ipv6_subnet_gateway(address),
+            IpNetwork::V4(_) => None,
+        })
+    };
+    explicit.or_else(from_subnet).or_else(|| {
+        dns_servers.iter().find_map(|server| match server {
+            IpAddr::V6(server) => Some(*server),
+            IpAddr::V4(_) => None,
+        })
+    })
+}
+
+/// Return the first address in the subnet of `address`, unless the subnet is too small to have
+/// one besides `address` itself.
+fn ipv4_subnet_gateway(address: &Ipv4Network) -> Option<Ipv4Addr> {
+    if address.prefix() >= 31 {
+        return None;
+    }
+    let gateway = Ipv4Addr::from(u32::from(address.network()) + 1);
+    (gateway != address.ip()).then_some(gateway)
+}
+
+/// See [`ipv4_subnet_gateway`].
+fn ipv6_subnet_gateway(address: &Ipv6Network) -> Option<Ipv6Addr> {
+    if address.prefix() >= 127 {
+        return None;
+    }
+    let gateway = Ipv6Addr::from(u128::from(address.network()) + 1);
+    (gateway != address.ip()).then_some(gateway)
+}
+
+/// Return the tunnel addresses to export, along with the gateways that cannot be derived from
+/// them. The first address of each family is given the longest prefix from which its gateway is
+/// derived on import. The other addresses, and addresses whose gateway cannot be derived from any
+/// prefix, are exported as single hosts.
+fn export_addresses(config: &wireguard::ConnectionConfig) -> (Vec<IpNetwork>, Vec<IpAddr>) {
+    let mut ipv4_gateway = Some(config.ipv4_gateway);
+    let mut ipv6_gateway = config.ipv6_gateway;
+
+    let addresses = config
+        .tunnel
+        .addresses
+        .iter()
+        .map(|address| match *address {
+            IpAddr::V4(address) => {
+                let prefix = ipv4_gateway
+                    .and_then(|gateway| {
+                        (0..31).rev().find(|&prefix| {
+                            let network = Ipv4Network::new(address, prefix).unwrap();
+                            ipv4_subnet_gateway(&network) == Some(gateway)
+                        })
+                    })
+                    .inspect(|_| ipv4_gateway = None)
+                    .unwrap_or(32);
+                IpNetwork::V4(Ipv4Network::new(address, prefix).unwrap())
+            }
+            IpAddr::V6(address) => {
+                let prefix = ipv6_gateway
+                    .and_then(|gateway| {
+                        (0..127).rev().find(|&prefix| {
+                            let network = Ipv6Network::new(address, prefix).unwrap();
+                            ipv6_subnet_gateway(&network) == Some(gateway)
+                        })
+                    })
+                    .inspect(|_| ipv6_gateway = None)
+                    .unwrap_or(128);
+                IpNetwork::V6(Ipv6Network::new(address, prefix).unwrap())
+            }
+        })
+        .collect();
+
+    let gateways = ipv4_gateway
+        .map(IpAddr::V4)
+        .into_iter()
+        .chain(ipv6_gateway.map(IpAddr::V6))
+        .collect();
+    (addresses, gateways)
+}
+
+fn write_list<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    key: &str,
+    items: impl IntoIterator<Item = T>,
+) -> fmt::Result {
+    let items: Vec<String> = items.into_iter().map(|item| item.to_string()).collect();
+    if !items.is_empty() {
+        writeln!(f, "{key} = {}", items.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Export the configuration in the `wg-quick` format. Note that the output contains the private
+/// key.
+impl fmt::Display for WgQuickConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = &self.config;
+
+        let (addresses, gateways) = export_addresses(config);
+
+        writeln!(f, "[Interface]")?;
+        writeln!(f, "PrivateKey = {}", config.tunnel.private_key.to_base64())?;
+        write_list(f, "Address", &addresses)?;
+        for gateway in gateways {
+            writeln!(f, "# Gateway = {gateway}")?;
+        }
+        write_list(f, "DNS", &self.dns_servers)?;
+        if let Some(mtu) = self.mtu {
+            writeln!(f, "MTU = {mtu}")?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "[Peer]")?;
+        writeln!(f, "PublicKey = {}", config.peer.public_key.to_base64())?;
+        if let Some(psk) = &self.psk {
+            writeln!(f, "PresharedKey = {}", psk.to_base64())?;
+        }
+        write_list(f, "AllowedIPs", &config.peer.allowed_ips)?;
+        let port = config.peer.endpoint.port();
+        if self.host.parse::<Ipv6Addr>().is_ok() {
+            writeln!(f, "Endpoint = [{}]:{port}", self.host)
+        } else {
+            writeln!(f, "Endpoint = {}:{port}", self.host)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "mPue6Xt0pdz4NRAhfQSp/SLKo7kV7DW+2zvBq0N9iUI=";
+    const PUBLIC_KEY: &str = "BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=";
+    const PSK: &str = "4ksnhM2TPmU3fWGYXMxo8sYcEZMkOvsgC6DDQj3r6mc=";
+
+    #[test]
+    fn test_parse() {
+        let config: WgQuickConfig = format!(
+            "# Self-hosted exit
+[Interface]
+PrivateKey = {PRIVATE_KEY}
+Address = 10.8.0.2/24, fd00::2/64
+DNS = 1.1.1.1
+MTU = 1380
+
+[peer]
+publickey = {PUBLIC_KEY}
+PresharedKey = {PSK}
+AllowedIPs = 0.0.0.0/0
+AllowedIPs = ::/0 # appended to the previous line
+Endpoint = vpn.example.com:51820
+"
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(config.host, "vpn.example.com");
+        assert_eq!(config.dns_servers, ["1.1.1.1".parse::<IpAddr>().unwrap()]);
+        assert_eq!(config.mtu, Some(1380));
+
+        let wireguard = &config.config;
+        assert_eq!(wireguard.tunnel.private_key.to_base64(), PRIVATE_KEY);
+        assert_eq!(
+            wireguard.tunnel.addresses,
+            [
+                "10.8.0.2".parse::<IpAddr>().unwrap(),
+                "fd00::2".parse().unwrap()
+            ]
+        );
+        assert_eq!(wireguard.peer.public_key.to_base64(), PUBLIC_KEY);
+        assert_eq!(config.psk.as_ref().unwrap().to_base64(), PSK);
+        assert_eq!(wireguard.peer.psk, None);
+        assert_eq!(wireguard.peer.allowed_ips.len(), 2);
+        assert_eq!(wireguard.peer.endpoint.port(), 51820);
+        assert_eq!(wireguard.ipv4_gateway, Ipv4Addr::new(10, 8, 0, 1));
+        assert_eq!(wireguard.ipv6_gateway, Some("fd00::1".parse().unwrap()));
+    }
+
+    /// Exporting and importing a configuration should not change it
+    #[test]
+    fn test_round_trip() {
+        let config: WgQuickConfig = format!(
+            "[Interface]
+PrivateKey = {PRIVATE_KEY}
+Address = 10.64.0.2/32
+DNS = 10.64.0.1
+
+[Peer]
+PublicKey = {PUBLIC_KEY}
+PresharedKey = {PSK}
+AllowedIPs = 0.0.0.0/0, ::/0
+Endpoint = [2001:db8::1]:51820
+"
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(config.config.ipv4_gateway, Ipv4Addr::new(10, 64, 0, 1));
+        assert_eq!(config.custom_dns_servers(), None);
+
+        let endpoint = config.custom_tunnel_endpoint();
+        assert_eq!(endpoint.wireguard_psk, config.psk);
+        let from_endpoint = WgQuickConfig::from_endpoint(endpoint, vec![], None).unwrap();
+        assert_eq!(from_endpoint, config);
+
+        let exported = config.to_string();
+        assert!(exported.contains("Endpoint = [2001:db8::1]:51820"));
+        assert_eq!(exported.parse::<WgQuickConfig>().unwrap(), config);
+    }
+
+    /// The gateway should survive a round trip when the DNS server is not the gateway
+    #[test]
+    fn test_round_trip_custom_dns() {
+        let config: WgQuickConfig = format!(
+            "[Interface]
+PrivateKey = {PRIVATE_KEY}
+Address = 10.64.0.2/24, fc00:bbbb:bbbb:bb01::2/64
+DNS = 1.1.1.1
+
+[Peer]
+PublicKey = {PUBLIC_KEY}
+AllowedIPs = 0.0.0.0/0, ::/0
+Endpoint = 192.0.2.1:51820
+"
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(config.config.ipv4_gateway, Ipv4Addr::new(10, 64, 0, 1));
+
+        let exported = config.to_string();
+        assert!(exported.contains("Address = 10.64.0.2/30, fc00:bbbb:bbbb:bb01::2/126"));
+        assert!(!exported.contains("Gateway"));
+        assert_eq!(exported.parse::<WgQuickConfig>().unwrap(), config);
+
+        // A gateway that is not the first address of any subnet of the tunnel address has to be
+        // exported explicitly
+        let mut config = config;
+        config.config.ipv4_gateway = Ipv4Addr::new(10, 64, 0, 5);
+        config.config.ipv6_gateway = Some("fc00:bbbb:bbbb:bb01::5".parse().unwrap());
+
+        let exported = config.to_string();
+        assert!(exported.contains("Address = 10.64.0.2/32, fc00:bbbb:bbbb:bb01::2/128"));
+        assert!(exported.contains("# Gateway = 10.64.0.5\n# Gateway = fc00:bbbb:bbbb:bb01::5\n"));
+        assert_eq!(exported.parse::<WgQuickConfig>().unwrap(), config);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let config = |interface: &str, peer: &str| {
+            format!(
+                "[Interface]
+PrivateKey = {PRIVATE_KEY}
+Address = 10.8.0.2/24
+{interface}
+[Peer]
+PublicKey = {PUBLIC_KEY}
+AllowedIPs = 0.0.0.0/0
+Endpoint = 192.0.2.1:51820
+{peer}"
+            )
+            .parse::<WgQuickConfig>()
+        };
+
+        assert!(config("", "").is_ok());
+        assert_eq!(
+            config("PostUp = iptables -A FORWARD", ""),
+            Err(Error::UnsupportedKey {
+                line: 4,
+                section: Section::Interface,
+                key: "PostUp".to_owned()
+            })
+        );
+        assert_eq!(
+            config("", "PersistentKeepalive = 25").unwrap().ignored_keys,
+            ["PersistentKeepalive"]
+        );
+        assert!(matches!(
+            config("DNS = 1.1.1.1, example.com", ""),
+            Err(Error::InvalidValue { line: 4, .. })
+        ));
+        assert!(matches!(
+            config("", "[Peer]"),
+            Err(Error::DuplicateSection {
+                line: 9,
+                section: Section::Peer
+            })
+        ));
+        assert!(matches!(
+            config("", "Endpoint = 192.0.2.2:51820"),
+            Err(Error::DuplicateKey { line: 9, .. })
+        ));
+        assert!(matches!(
+            config("MTU = big", ""),
+            Err(Error::InvalidValue { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_keys() {
+        let result = format!(
+            "[Interface]
+PrivateKey = {PRIVATE_KEY}
+Address = 10.8.0.2/32
+
+[Peer]
+PublicKey = {PUBLIC_KEY}
+AllowedIPs = 0.0.0.0/0
+"
+        )
+        .parse::<WgQuickConfig>();
+        assert_eq!(
+            result,
+            Err(Error::MissingKey {
+                section: Section::Peer,
+                key: "Endpoint"
+            })
+        );
+
+        // A /32 address without a DNS server does not reveal the gateway
+        let result = format!(
+            "[Interface]
+PrivateKey = {PRIVATE_KEY}
+Address = 10.8.0.2/32
+
+[Peer]
+PublicKey = {PUBLIC_KEY}
+AllowedIPs = 0.0.0.0/0
+Endpoint = 192.0.2.1:51820
+"
+        )
+        .parse::<WgQuickConfig>();
+        assert_eq!(result, Err(Error::NoIpv4Gateway));
+    }
+}
@@ -0,0 +1,34 @@
+//! Statistics for the local DNS blocklists.
+
+use crate::settings::BlocklistSource;
+use serde::{Deserialize, Serialize};
+
+/// Counters for the local DNS blocklists. The counters are reset whenever the lists are reloaded.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsBlocklistStats {
+    /// Number of queries which matched a blocklist, but were allowed by the allowlist
+    pub allowlisted_queries: u64,
+    pub sources: Vec<BlocklistSourceStats>,
+}
+
+/// Status and counters of a single blocklist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlocklistSourceStats {
+    pub source: BlocklistSource,
+    /// Number of domains loaded from the list
+    pub domains: u64,
+    /// Number of queries blocked because of the list
+    pub blocked_queries: u64,
+    /// Set if the list could not be loaded the last time it was tried
+    pub error: Option<String>,
+}
+
+impl DnsBlocklistStats {
+    /// Total number of blocked queries.
+    pub fn blocked_queries(&self) -> u64 {
+        self.sources
+            .iter()
+            .map(|source| source.blocked_queries)
+            .sum()
+    }
+}
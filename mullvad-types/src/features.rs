@@ -3,7 +3,10 @@ use std::{
     fmt::{Debug, Display},
 };
 
-use crate::settings::{DnsState, Settings};
+use crate::{
+    auto_connect::TrustedNetwork,
+    settings::{DnsState, Settings},
+};
 use serde::{Deserialize, Serialize};
 use talpid_types::net::{ObfuscationType, TunnelEndpoint, TunnelType};
 
@@ -80,6 +83,8 @@ pub enum FeatureIndicator {
     CustomMtu,
     CustomMssFix,
     Daita,
+    TrustedNetworks,
+    Quic,
 }
 
 impl FeatureIndicator {
@@ -99,6 +104,8 @@ impl FeatureIndicator {
             FeatureIndicator::CustomMtu => "Custom MTU",
             FeatureIndicator::CustomMssFix => "Custom MSS",
             FeatureIndicator::Daita => "DAITA",
+            FeatureIndicator::TrustedNetworks => "Trusted Networks",
+            FeatureIndicator::Quic => "QUIC",
         }
     }
 }
@@ -122,10 +129,7 @@ pub fn compute_feature_indicators(
     endpoint: &TunnelEndpoint,
     server_ip_override: bool,
 ) -> FeatureIndicators {
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     let split_tunneling = settings.split_tunnel.enable_exclusions;
-    #[cfg(not(any(windows, target_os = "android", target_os = "macos")))]
-    let split_tunneling = false;
 
     #[cfg(not(target_os = "android"))]
     let lockdown_mode = settings.block_when_disconnected;
@@ -136,6 +140,13 @@ pub fn compute_feature_indicators(
         .default_options
         .any_blockers_enabled();
     let custom_dns = settings.tunnel_options.dns_options.state == DnsState::Custom;
+    // Trusted networks have no effect in lockdown mode
+    let trusted_networks = settings
+        .trusted_networks
+        .iter()
+        .any(TrustedNetwork::is_supported);
+    #[cfg(not(target_os = "android"))]
+    let trusted_networks = trusted_networks && !lockdown_mode;
 
     let generic_features = [
         (split_tunneling, FeatureIndicator::SplitTunneling),
@@ -143,6 +154,7 @@ pub fn compute_feature_indicators(
         (dns_content_blockers, FeatureIndicator::DnsContentBlockers),
         (custom_dns, FeatureIndicator::CustomDns),
         (server_ip_override, FeatureIndicator::ServerIpOverride),
+        (trusted_networks, FeatureIndicator::TrustedNetworks),
         #[cfg(not(target_os = "android"))]
         (lockdown_mode, FeatureIndicator::LockdownMode),
     ];
@@ -170,6 +182,11 @@ pub fn compute_feature_indicators(
                 .as_ref()
                 .filter(|obfuscation| obfuscation.obfuscation_type == ObfuscationType::Shadowsocks)
                 .is_some();
+            let quic = endpoint
+                .obfuscation
+                .as_ref()
+                .filter(|obfuscation| obfuscation.obfuscation_type == ObfuscationType::Quic)
+                .is_some();
 
             let mtu = settings.tunnel_options.wireguard.mtu.is_some();
 
@@ -186,6 +203,7 @@ pub fn compute_feature_indicators(
                 (multihop, FeatureIndicator::Multihop),
                 (udp_tcp, FeatureIndicator::Udp2Tcp),
                 (shadowsocks, FeatureIndicator::Shadowsocks),
+                (quic, FeatureIndicator::Quic),
                 (mtu, FeatureIndicator::CustomMtu),
                 (daita, FeatureIndicator::Daita),
             ]
@@ -271,6 +289,29 @@ mod tests {
             expected_indicators
         );
 
+        let trusted_network = TrustedNetwork::GatewayMac("aa:bb:cc:00:11:22".parse().unwrap());
+        settings.trusted_networks.push(trusted_network.clone());
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected_indicators,
+            "Trusted networks should not be indicated in lockdown mode"
+        );
+
+        settings.block_when_disconnected = false;
+        expected_indicators
+            .0
+            .remove(&FeatureIndicator::LockdownMode);
+        // Networks that cannot be recognized on this platform are never indicated
+        if trusted_network.is_supported() {
+            expected_indicators
+                .0
+                .insert(FeatureIndicator::TrustedNetworks);
+        }
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected_indicators
+        );
+
         settings.tunnel_options.openvpn.mssfix = Some(1300);
         assert_eq!(
             compute_feature_indicators(&settings, &endpoint, false),
@@ -356,6 +397,13 @@ mod tests {
             compute_feature_indicators(&settings, &endpoint, false),
             expected_indicators
         );
+        endpoint.obfuscation.as_mut().unwrap().obfuscation_type = ObfuscationType::Quic;
+        expected_indicators.0.remove(&FeatureIndicator::Shadowsocks);
+        expected_indicators.0.insert(FeatureIndicator::Quic);
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected_indicators
+        );
 
         settings.tunnel_options.wireguard.mtu = Some(1300);
         expected_indicators.0.insert(FeatureIndicator::CustomMtu);
@@ -437,6 +485,8 @@ mod tests {
             FeatureIndicator::CustomMtu => {}
             FeatureIndicator::CustomMssFix => {}
             FeatureIndicator::Daita => {}
+            FeatureIndicator::TrustedNetworks => {}
+            FeatureIndicator::Quic => {}
         }
     }
 }
@@ -0,0 +1,103 @@
+//! Network leaks detected by the daemon while the tunnel is up.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::IpAddr};
+use talpid_types::net::TunnelEndpoint;
+
+/// A leak that was detected while connected to `tunnel_endpoint`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeakReport {
+    pub detected_at: DateTime<Utc>,
+    pub tunnel_endpoint: TunnelEndpoint,
+    pub info: LeakInfo,
+}
+
+/// Details about how traffic leaked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeakInfo {
+    /// Managed to reach other network nodes on the physical interface, bypassing firewall rules.
+    NodeReachableOnInterface {
+        interface: String,
+        reachable_nodes: Vec<IpAddr>,
+    },
+    /// <https://am.i.mullvad.net> saw traffic from an IP that does not belong to Mullvad.
+    AmIMullvad { ip: IpAddr },
+    /// DNS resolvers answered queries sent on the physical interface, outside the tunnel.
+    DnsReachable {
+        interface: String,
+        resolvers: Vec<IpAddr>,
+    },
+}
+
+impl fmt::Display for LeakInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeakInfo::NodeReachableOnInterface {
+                interface,
+                reachable_nodes,
+            } => {
+                let nodes: Vec<_> = reachable_nodes.iter().map(IpAddr::to_string).collect();
+                write!(f, "reached {} on interface {interface}", nodes.join(", "))
+            }
+            LeakInfo::AmIMullvad { ip } => write!(f, "am.i.mullvad.net saw non-Mullvad IP {ip}"),
+            LeakInfo::DnsReachable {
+                interface,
+                resolvers,
+            } => {
+                let resolvers: Vec<_> = resolvers.iter().map(IpAddr::to_string).collect();
+                write!(
+                    f,
+                    "DNS resolvers {} answered on interface {interface}",
+                    resolvers.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Results of an on-demand leak test against the current tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeakTestReport {
+    pub tested_at: DateTime<Utc>,
+    pub tunnel_endpoint: TunnelEndpoint,
+    /// Whether other network nodes could be reached on the physical interface.
+    pub traceroute: LeakTestResult,
+    /// Whether <https://am.i.mullvad.net> sees traffic from a Mullvad IP.
+    pub am_i_mullvad: LeakTestResult,
+    /// Whether DNS resolvers could be reached on the physical interface.
+    pub dns: LeakTestResult,
+}
+
+impl LeakTestReport {
+    /// Returns all leaks found by the test.
+    pub fn leaks(&self) -> impl Iterator<Item = &LeakInfo> {
+        [&self.traceroute, &self.am_i_mullvad, &self.dns]
+            .into_iter()
+            .filter_map(|result| match result {
+                LeakTestResult::Leak(info) => Some(info),
+                LeakTestResult::NoLeak | LeakTestResult::Error(_) => None,
+            })
+    }
+}
+
+/// Outcome of a single leak test.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeakTestResult {
+    NoLeak,
+    Leak(LeakInfo),
+    /// The test could not be completed.
+    Error(String),
+}
+
+impl fmt::Display for LeakTestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeakTestResult::NoLeak => write!(f, "no leak"),
+            LeakTestResult::Leak(info) => write!(f, "leak: {info}"),
+            LeakTestResult::Error(error) => write!(f, "failed: {error}"),
+        }
+    }
+}
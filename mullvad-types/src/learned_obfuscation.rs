@@ -0,0 +1,158 @@
+//! Obfuscation settings that are known to work on the networks that the device has used.
+
+use crate::auto_connect::{MacAddress, NetworkDetails};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use talpid_types::net::{IpVersion, ObfuscationType, TunnelEndpoint, TunnelType};
+
+/// Identifies a network that the device has been connected to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkId {
+    /// Wi-Fi network with this SSID
+    Ssid(String),
+    /// Network whose default gateway has this MAC address
+    GatewayMac(MacAddress),
+}
+
+impl NetworkId {
+    /// Identify the network described by `network`. The SSID is preferred over the gateway MAC,
+    /// since access points of the same Wi-Fi network may use different gateways.
+    pub fn from_details(network: &NetworkDetails) -> Option<Self> {
+        match (&network.ssid, network.gateway_mac) {
+            (Some(ssid), _) => Some(NetworkId::Ssid(ssid.clone())),
+            (None, Some(mac)) => Some(NetworkId::GatewayMac(mac)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl fmt::Display for NetworkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkId::Ssid(ssid) => write!(f, "SSID \"{ssid}\""),
+            NetworkId::GatewayMac(mac) => write!(f, "gateway MAC {mac}"),
+        }
+    }
+}
+
+/// Obfuscation, port and IP version that a WireGuard tunnel was successfully established with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingObfuscation {
+    /// `None` if the tunnel was not obfuscated
+    pub obfuscation: Option<ObfuscationType>,
+    /// Port of the obfuscation server, or of the WireGuard relay if the tunnel was not obfuscated
+    pub port: u16,
+    pub ip_version: IpVersion,
+}
+
+impl WorkingObfuscation {
+    /// Return the obfuscation used to reach `endpoint`, or `None` if it is not a WireGuard tunnel.
+    pub fn from_endpoint(endpoint: &TunnelEndpoint) -> Option<Self> {
+        if endpoint.tunnel_type != TunnelType::Wireguard {
+            return None;
+        }
+        let (obfuscation, address) = match &endpoint.obfuscation {
+            Some(obfuscation) => (
+                Some(obfuscation.obfuscation_type),
+                obfuscation.endpoint.address,
+            ),
+            None => (
+                None,
+                endpoint.entry_endpoint.unwrap_or(endpoint.endpoint).address,
+            ),
+        };
+        Some(WorkingObfuscation {
+            obfuscation,
+            port: address.port(),
+            ip_version: IpVersion::from(address.ip()),
+        })
+    }
+}
+
+impl fmt::Display for WorkingObfuscation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.obfuscation {
+            Some(obfuscation) => write!(f, "{obfuscation}")?,
+            None => write!(f, "no obfuscation")?,
+        }
+        write!(f, ", port {} over {}", self.port, self.ip_version)
+    }
+}
+
+/// The obfuscation that most recently worked on `network`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LearnedObfuscation {
+    pub network: NetworkId,
+    pub obfuscation: WorkingObfuscation,
+    pub last_used: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::{
+        obfuscation::ObfuscatorConfig, Endpoint, ObfuscationEndpoint, TransportProtocol,
+    };
+
+    #[test]
+    fn test_network_id_prefers_ssid() {
+        let mut network = NetworkDetails {
+            gateway_mac: Some("aa:bb:cc:dd:ee:ff".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            NetworkId::from_details(&network),
+            Some(NetworkId::GatewayMac("aa:bb:cc:dd:ee:ff".parse().unwrap()))
+        );
+
+        network.ssid = Some("Hotel Wi-Fi".to_owned());
+        assert_eq!(
+            NetworkId::from_details(&network),
+            Some(NetworkId::Ssid("Hotel Wi-Fi".to_owned()))
+        );
+
+        assert_eq!(NetworkId::from_details(&NetworkDetails::default()), None);
+    }
+
+    #[test]
+    fn test_working_obfuscation_from_endpoint() {
+        let mut endpoint = TunnelEndpoint {
+            endpoint: Endpoint::new([10, 0, 0, 1], 51820, TransportProtocol::Udp),
+            tunnel_type: TunnelType::Wireguard,
+            quantum_resistant: false,
+            proxy: None,
+            obfuscation: None,
+            entry_endpoint: None,
+            tunnel_interface: None,
+            #[cfg(daita)]
+            daita: false,
+        };
+        assert_eq!(
+            WorkingObfuscation::from_endpoint(&endpoint),
+            Some(WorkingObfuscation {
+                obfuscation: None,
+                port: 51820,
+                ip_version: IpVersion::V4,
+            })
+        );
+
+        let config = ObfuscatorConfig::Udp2Tcp {
+            endpoint: "[2001:db8::1]:443".parse().unwrap(),
+            websocket: None,
+        };
+        endpoint.obfuscation = Some(ObfuscationEndpoint::from(&config));
+        assert_eq!(
+            WorkingObfuscation::from_endpoint(&endpoint),
+            Some(WorkingObfuscation {
+                obfuscation: Some(ObfuscationType::Udp2Tcp),
+                port: 443,
+                ip_version: IpVersion::V6,
+            })
+        );
+
+        endpoint.tunnel_type = TunnelType::OpenVpn;
+        assert_eq!(WorkingObfuscation::from_endpoint(&endpoint), None);
+    }
+}
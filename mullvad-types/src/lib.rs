@@ -1,11 +1,16 @@
 pub mod access_method;
 pub mod account;
 pub mod auth_failed;
+pub mod auto_connect;
 pub mod constraints;
+pub mod custom_endpoint;
 pub mod custom_list;
 pub mod device;
+pub mod dns_blocklist;
 pub mod endpoint;
 pub mod features;
+pub mod leak;
+pub mod learned_obfuscation;
 pub mod location;
 pub mod relay_constraints;
 pub mod relay_list;
@@ -3,6 +3,7 @@
 
 use crate::{
     constraints::{Constraint, Match},
+    custom_endpoint::{self, CustomEndpointsSettings},
     custom_list::{CustomListsSettings, Id},
     location::{CityCode, CountryCode, Hostname},
     relay_list::{Relay, RelayEndpointData},
@@ -15,7 +16,9 @@ use std::{
     net::{Ipv4Addr, Ipv6Addr},
     str::FromStr,
 };
-use talpid_types::net::{proxy::CustomProxy, IpVersion, TransportProtocol, TunnelType};
+use talpid_types::net::{
+    obfuscation::WebSocketSettings, proxy::CustomProxy, IpVersion, TransportProtocol, TunnelType,
+};
 
 /// Specifies a specific endpoint or [`RelayConstraints`] to use when `mullvad-daemon` selects a
 /// relay.
@@ -23,6 +26,9 @@ use talpid_types::net::{proxy::CustomProxy, IpVersion, TransportProtocol, Tunnel
 #[serde(rename_all = "snake_case")]
 pub enum RelaySettings {
     CustomTunnelEndpoint(CustomTunnelEndpoint),
+    /// Saved custom endpoints, in order of preference. If connecting to an endpoint fails, the
+    /// next one is tried.
+    SavedCustomEndpoints(Vec<custom_endpoint::Id>),
     Normal(RelayConstraints),
 }
 
@@ -34,6 +40,8 @@ impl RelaySettings {
             RelaySettings::CustomTunnelEndpoint(endpoint) => {
                 endpoint.endpoint().protocol == TransportProtocol::Tcp
             }
+            // Any endpoint in the group may end up being used
+            RelaySettings::SavedCustomEndpoints(_) => false,
             RelaySettings::Normal(update) => !matches!(
                 &update.openvpn_constraints,
                 OpenVpnConstraints {
@@ -62,6 +70,7 @@ impl From<RelayConstraints> for RelaySettings {
 pub struct RelaySettingsFormatter<'a> {
     pub settings: &'a RelaySettings,
     pub custom_lists: &'a CustomListsSettings,
+    pub custom_endpoints: &'a CustomEndpointsSettings,
 }
 
 impl fmt::Display for RelaySettingsFormatter<'_> {
@@ -70,6 +79,16 @@ impl fmt::Display for RelaySettingsFormatter<'_> {
             RelaySettings::CustomTunnelEndpoint(endpoint) => {
                 write!(f, "custom endpoint {endpoint}")
             }
+            RelaySettings::SavedCustomEndpoints(ids) => {
+                write!(f, "saved custom endpoints ")?;
+                let names = ids.iter().map(|id| {
+                    self.custom_endpoints
+                        .get(id)
+                        .map(|endpoint| endpoint.name.clone())
+                        .unwrap_or_else(|| format!("<unknown endpoint {}>", **id))
+                });
+                write!(f, "{}", names.collect::<Vec<_>>().join(", "))
+            }
             RelaySettings::Normal(constraints) => {
                 write!(
                     f,
@@ -126,6 +145,7 @@ pub struct RelayConstraints {
     pub tunnel_protocol: TunnelType,
     pub wireguard_constraints: WireguardConstraints,
     pub openvpn_constraints: OpenVpnConstraints,
+    pub selection_mode: SelectionMode,
 }
 
 pub struct RelayConstraintsFormatter<'a> {
@@ -157,7 +177,8 @@ impl fmt::Display for RelayConstraintsFormatter<'_> {
                 })
         )?;
         writeln!(f, "Provider(s): {}", self.constraints.providers)?;
-        write!(f, "Ownership: {}", self.constraints.ownership)
+        writeln!(f, "Ownership: {}", self.constraints.ownership)?;
+        write!(f, "Selection mode: {}", self.constraints.selection_mode)
     }
 }
 
@@ -254,6 +275,28 @@ impl FromStr for GeographicLocationConstraint {
     }
 }
 
+/// How a relay is picked among the relays that match the constraints.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SelectionMode {
+    /// Pick a random relay, weighted by the relay weights.
+    #[default]
+    Random,
+    /// Prefer the relays with the lowest measured latency. Only the entry relay is picked this way
+    /// when multihop is used.
+    Fastest,
+}
+
+impl fmt::Display for SelectionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            SelectionMode::Random => write!(f, "random"),
+            SelectionMode::Fastest => write!(f, "fastest"),
+        }
+    }
+}
+
 /// Limits the set of servers to choose based on ownership.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
@@ -509,6 +552,7 @@ pub enum SelectedObfuscation {
     #[cfg_attr(feature = "clap", clap(name = "udp2tcp"))]
     Udp2Tcp,
     Shadowsocks,
+    Quic,
 }
 
 impl Intersection for SelectedObfuscation {
@@ -533,6 +577,7 @@ impl fmt::Display for SelectedObfuscation {
             SelectedObfuscation::Off => "off".fmt(f),
             SelectedObfuscation::Udp2Tcp => "udp2tcp".fmt(f),
             SelectedObfuscation::Shadowsocks => "shadowsocks".fmt(f),
+            SelectedObfuscation::Quic => "quic".fmt(f),
         }
     }
 }
@@ -541,14 +586,22 @@ impl fmt::Display for SelectedObfuscation {
 #[serde(rename_all = "snake_case")]
 pub struct Udp2TcpObfuscationSettings {
     pub port: Constraint<u16>,
+    /// Frame datagrams as WebSocket messages over TLS, rather than writing them directly to the
+    /// TCP stream.
+    #[serde(default)]
+    pub websocket: Option<WebSocketSettings>,
 }
 
 impl fmt::Display for Udp2TcpObfuscationSettings {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.port {
-            Constraint::Any => write!(f, "any port"),
-            Constraint::Only(port) => write!(f, "port {port}"),
+            Constraint::Any => write!(f, "any port")?,
+            Constraint::Only(port) => write!(f, "port {port}")?,
         }
+        if let Some(websocket) = &self.websocket {
+            write!(f, ", WebSocket over TLS ({websocket})")?;
+        }
+        Ok(())
     }
 }
 
@@ -575,6 +628,12 @@ pub struct ObfuscationSettings {
     pub selected_obfuscation: SelectedObfuscation,
     pub udp2tcp: Udp2TcpObfuscationSettings,
     pub shadowsocks: ShadowsocksSettings,
+    /// Self-hosted Shadowsocks server used when connecting to a custom WireGuard endpoint with
+    /// Shadowsocks obfuscation. It must forward traffic to the endpoint of the custom peer.
+    pub custom_shadowsocks: Option<talpid_types::net::proxy::Shadowsocks>,
+    /// Remember which obfuscation worked on each network, and try it first when connecting on
+    /// that network again. Only supported on Linux and macOS.
+    pub learn_per_network: bool,
 }
 
 /// Limits the set of bridge servers to use in `mullvad-daemon`.
@@ -694,13 +753,16 @@ impl RelayOverride {
 
         // Additional IPs should be ignored when overrides are present
         if let RelayEndpointData::Wireguard(data) = &mut relay.endpoint_data {
-            data.shadowsocks_extra_addr_in.retain(|addr| {
+            // Keep address if it's not overridden
+            let not_overridden = |addr: &std::net::IpAddr| {
                 let not_overridden_v4 = self.ipv4_addr_in.is_none() && addr.is_ipv4();
                 let not_overridden_v6 = self.ipv6_addr_in.is_none() && addr.is_ipv6();
-
-                // Keep address if it's not overridden
                 not_overridden_v4 || not_overridden_v6
-            });
+            };
+            data.shadowsocks_extra_addr_in.retain(not_overridden);
+            if let Some(quic) = &mut data.quic {
+                quic.addr_in.retain(not_overridden);
+            }
         }
     }
 }
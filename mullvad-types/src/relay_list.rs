@@ -130,6 +130,7 @@ impl PartialEq for Relay {
     ///     #   .unwrap(),
     ///     #   daita: false,
     ///     #   shadowsocks_extra_addr_in: vec![],
+    ///     #   quic: None,
     ///     # }),
     ///     # location: mullvad_types::location::Location {
     ///     #   country: "Sweden".to_string(),
@@ -226,6 +227,24 @@ pub struct WireguardRelayEndpointData {
     /// Optional IP addresses used by Shadowsocks
     #[serde(default)]
     pub shadowsocks_extra_addr_in: Vec<IpAddr>,
+    /// QUIC obfuscation endpoint, if the relay supports it
+    #[serde(default)]
+    pub quic: Option<QuicEndpointData>,
+}
+
+/// Data needed to connect to the QUIC obfuscation endpoint of a WireGuard relay.
+#[derive(Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Debug)]
+pub struct QuicEndpointData {
+    /// IP addresses that the QUIC server listens on. These are separate from the WireGuard
+    /// addresses, since WireGuard may also listen on UDP port 443.
+    pub addr_in: Vec<IpAddr>,
+    /// Server name used for SNI and to verify the certificate of the server
+    pub hostname: String,
+}
+
+impl QuicEndpointData {
+    /// Port that QUIC obfuscation servers listen on.
+    pub const PORT: u16 = 443;
 }
 
 #[derive(Debug, Default, Clone, Deserialize, Serialize)]
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use std::{fmt, net::IpAddr, path::PathBuf};
+use talpid_types::net::dns::{EncryptedDnsUpstream, SplitDnsRoutes};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -16,6 +17,11 @@ pub struct DnsOptions {
     pub state: DnsState,
     pub default_options: DefaultDnsOptions,
     pub custom_options: CustomDnsOptions,
+    /// Resolvers to use for specific domains, regardless of `state`. The resolvers must be
+    /// reachable on the local network.
+    pub split_dns: SplitDnsRoutes,
+    /// Domain blocklists enforced by a local resolver, regardless of `state`.
+    pub local_blocklists: LocalBlocklistOptions,
 }
 
 /// Default DNS config
@@ -34,6 +40,10 @@ pub struct DefaultDnsOptions {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct CustomDnsOptions {
     pub addresses: Vec<IpAddr>,
+    /// Resolvers reached using DNS-over-HTTPS or DNS-over-TLS. If there are any, queries are sent
+    /// to these through a local forwarder, and `addresses` are not used.
+    #[serde(default)]
+    pub encrypted_upstreams: Vec<EncryptedDnsUpstream>,
 }
 
 impl DefaultDnsOptions {
@@ -56,3 +66,34 @@ impl DefaultDnsOptions {
             || block_social_media
     }
 }
+
+/// Domain blocklists maintained by the user. Queries for blocked domains are answered with
+/// NXDOMAIN by a local resolver.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(default)]
+pub struct LocalBlocklistOptions {
+    /// Lists in hosts, adblock or plain format
+    pub sources: Vec<BlocklistSource>,
+    /// Domains that are never blocked, including their subdomains
+    pub allowlist: Vec<String>,
+}
+
+/// Where to load a blocklist from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BlocklistSource {
+    /// A local file. The file and its parent directories must be owned by root and not be
+    /// writable by other users. Not supported on Windows.
+    File(PathBuf),
+    /// An HTTPS URL. The list is downloaded through the tunnel. Other schemes are not supported.
+    Url(String),
+}
+
+impl fmt::Display for BlocklistSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlocklistSource::File(path) => write!(f, "{}", path.display()),
+            BlocklistSource::Url(url) => f.write_str(url),
+        }
+    }
+}
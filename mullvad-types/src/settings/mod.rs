@@ -1,6 +1,8 @@
 use crate::{
     access_method,
+    auto_connect::{AutoConnectRules, TrustedNetwork},
     constraints::Constraint,
+    custom_endpoint::CustomEndpointsSettings,
     custom_list::CustomListsSettings,
     relay_constraints::{
         BridgeSettings, BridgeState, GeographicLocationConstraint, LocationConstraint,
@@ -10,8 +12,9 @@ use crate::{
     wireguard,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use std::collections::HashSet;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::AllowedLanNets;
 use talpid_types::net::{openvpn, GenericTunnelOptions};
 
 mod dns;
@@ -79,16 +82,27 @@ pub struct Settings {
     pub bridge_state: BridgeState,
     /// All of the custom relay lists
     pub custom_lists: CustomListsSettings,
+    /// Saved custom tunnel endpoints
+    pub custom_endpoints: CustomEndpointsSettings,
     /// API access methods
     pub api_access_methods: access_method::Settings,
     /// If the daemon should allow communication with private (LAN) networks.
     pub allow_lan: bool,
+    /// Networks that are considered to be private (LAN) networks when `allow_lan` is enabled.
+    #[cfg(not(target_os = "android"))]
+    pub allowed_lan_nets: AllowedLanNets,
     /// Extra level of kill switch. When this setting is on, the disconnected state will block
     /// the firewall to not allow any traffic in or out.
     #[cfg(not(target_os = "android"))]
     pub block_when_disconnected: bool,
     /// If the daemon should connect the VPN tunnel directly on start or not.
     pub auto_connect: bool,
+    /// Rules for connecting or disconnecting automatically when the network or time of day
+    /// changes.
+    pub auto_connect_rules: AutoConnectRules,
+    /// Networks on which the tunnel is disconnected automatically, unless lockdown mode is
+    /// enabled.
+    pub trusted_networks: Vec<TrustedNetwork>,
     /// Options that should be applied to tunnels of a specific type regardless of where the relays
     /// might be located.
     pub tunnel_options: TunnelOptions,
@@ -97,13 +111,11 @@ pub struct Settings {
     /// Whether to notify users of beta updates.
     pub show_beta_releases: bool,
     /// Split tunneling settings
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     pub split_tunnel: SplitTunnelSettings,
     /// Specifies settings schema version
     pub settings_version: SettingsVersion,
 }
 
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
 #[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 pub struct SplitTunnelSettings {
     /// Toggles split tunneling on or off
@@ -113,7 +125,7 @@ pub struct SplitTunnelSettings {
 }
 
 /// An application whose traffic should be excluded from any active tunnel.
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(not(target_os = "android"))]
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct SplitApp(std::path::PathBuf);
 
@@ -122,7 +134,7 @@ pub struct SplitApp(std::path::PathBuf);
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct SplitApp(String);
 
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(not(target_os = "android"))]
 impl SplitApp {
     /// Convert the underlying path to a [`String`].
     /// This function will fail if the underlying path string is not valid UTF-8. See
@@ -158,14 +170,14 @@ impl SplitApp {
     }
 }
 
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(not(target_os = "android"))]
 impl From<String> for SplitApp {
     fn from(value: String) -> Self {
         SplitApp::from(std::path::PathBuf::from(value))
     }
 }
 
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(not(target_os = "android"))]
 impl From<std::path::PathBuf> for SplitApp {
     fn from(value: std::path::PathBuf) -> Self {
         SplitApp(value)
@@ -201,15 +213,19 @@ impl Default for Settings {
             },
             bridge_state: BridgeState::Auto,
             custom_lists: CustomListsSettings::default(),
+            custom_endpoints: CustomEndpointsSettings::default(),
             api_access_methods: access_method::Settings::default(),
             allow_lan: false,
             #[cfg(not(target_os = "android"))]
+            allowed_lan_nets: AllowedLanNets::default(),
+            #[cfg(not(target_os = "android"))]
             block_when_disconnected: false,
             auto_connect: false,
+            auto_connect_rules: AutoConnectRules::default(),
+            trusted_networks: vec![],
             tunnel_options: TunnelOptions::default(),
             relay_overrides: vec![],
             show_beta_releases: false,
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             split_tunnel: SplitTunnelSettings::default(),
             settings_version: CURRENT_SETTINGS_VERSION,
         }
@@ -232,10 +248,12 @@ impl Settings {
                 RelaySettingsFormatter {
                     settings: &self.relay_settings,
                     custom_lists: &self.custom_lists,
+                    custom_endpoints: &self.custom_endpoints,
                 },
                 RelaySettingsFormatter {
                     settings: &new_settings,
                     custom_lists: &self.custom_lists,
+                    custom_endpoints: &self.custom_endpoints,
                 },
             );
 
@@ -276,7 +294,10 @@ pub struct TunnelOptions {
     pub dns_options: DnsOptions,
 }
 
-pub use dns::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
+pub use dns::{
+    BlocklistSource, CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState,
+    LocalBlocklistOptions,
+};
 
 impl Default for TunnelOptions {
     fn default() -> Self {
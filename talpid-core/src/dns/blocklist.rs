@@ -0,0 +1,351 @@
+//! Domain blocklists enforced by the local DNS forwarder. Queries for blocked domains, or any of
+//! their subdomains, are answered with NXDOMAIN unless the domain is allowlisted.
+//!
+//! Lists are parsed from hosts files (`0.0.0.0 ads.example`), adblock-style filter lists
+//! (`||ads.example^`, and `@@||ads.example^` for exceptions), or plain lists of domains.
+use std::{
+    collections::HashMap,
+    fmt,
+    net::IpAddr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use talpid_types::net::dns::normalize_domain;
+
+/// Names that commonly appear in hosts files, but which must not be blocked.
+const LOCAL_HOSTNAMES: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-localnet",
+    "ip6-mcastprefix",
+    "ip6-allnodes",
+    "ip6-allrouters",
+    "ip6-allhosts",
+];
+
+/// Domains parsed from a single blocklist.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParsedBlocklist {
+    /// Domains to block, including their subdomains
+    pub blocked: Vec<String>,
+    /// Domains that must not be blocked, including their subdomains
+    pub allowed: Vec<String>,
+}
+
+/// Parse a blocklist in hosts, adblock or plain format. Lines that cannot be parsed, and adblock
+/// rules that cannot be enforced using DNS, are ignored.
+pub fn parse(contents: &str) -> ParsedBlocklist {
+    let mut list = ParsedBlocklist::default();
+    for line in contents.lines() {
+        parse_line(line, &mut list);
+    }
+    list
+}
+
+fn parse_line(line: &str, list: &mut ParsedBlocklist) {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with(['#', '!', '[']) {
+        return;
+    }
+
+    if let Some(rule) = line.strip_prefix("@@||") {
+        list.allowed.extend(adblock_domain(rule));
+        return;
+    }
+    if let Some(rule) = line.strip_prefix("||") {
+        list.blocked.extend(adblock_domain(rule));
+        return;
+    }
+
+    let line = line.split('#').next().unwrap_or_default();
+    let mut fields = line.split_whitespace().peekable();
+    // Hosts files map an address to one or more names
+    fields.next_if(|field| field.parse::<IpAddr>().is_ok());
+    for name in fields {
+        if LOCAL_HOSTNAMES.contains(&name) || name.parse::<IpAddr>().is_ok() {
+            continue;
+        }
+        list.blocked.extend(normalize_domain(name).ok());
+    }
+}
+
+/// Return the domain of an adblock rule such as `ads.example^`, with the leading `||` removed.
+/// Rules with a path, or with modifiers other than `$important`, only apply to some requests and
+/// are skipped.
+fn adblock_domain(rule: &str) -> Option<String> {
+    let (domain, rest) = rule.split_once('^')?;
+    if !matches!(rest, "" | "$important") {
+        return None;
+    }
+    normalize_domain(domain).ok()
+}
+
+/// Compiled blocklists, and counters of how many queries they have blocked.
+pub struct DomainBlocklist {
+    /// Blocked domains, mapped to the index of the list that they came from
+    blocked: DomainTrie,
+    allowed: DomainTrie,
+    /// Number of domains added from each list
+    domains: Vec<u64>,
+    blocked_queries: Vec<AtomicU64>,
+    allowlisted_queries: AtomicU64,
+}
+
+/// Counters for a [DomainBlocklist].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlocklistStats {
+    /// Number of queries which matched a blocklist, but were allowed by an allowlist
+    pub allowlisted_queries: u64,
+    /// Counters for each list, in the order that the lists were given
+    pub lists: Vec<ListStats>,
+}
+
+/// Counters for a single list in a [DomainBlocklist].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListStats {
+    /// Number of domains added from the list. Domains that are already covered by an earlier
+    /// entry are not counted.
+    pub domains: u64,
+    /// Number of queries blocked because of the list
+    pub blocked_queries: u64,
+}
+
+impl DomainBlocklist {
+    /// Compile `lists` into a single blocklist. Domains in `allowlist`, or in the allowlist part
+    /// of any of the lists, are never blocked.
+    pub fn new(lists: &[ParsedBlocklist], allowlist: &[String]) -> Self {
+        let mut blocked = DomainTrie::default();
+        let mut allowed = DomainTrie::default();
+        let mut domains = vec![0; lists.len()];
+
+        for (index, list) in lists.iter().enumerate() {
+            for domain in &list.blocked {
+                if blocked.insert(domain, index) {
+                    domains[index] += 1;
+                }
+            }
+            for domain in &list.allowed {
+                allowed.insert(domain, index);
+            }
+        }
+        for domain in allowlist {
+            allowed.insert(domain, lists.len());
+        }
+
+        DomainBlocklist {
+            blocked,
+            allowed,
+            blocked_queries: domains.iter().map(|_| AtomicU64::new(0)).collect(),
+            domains,
+            allowlisted_queries: AtomicU64::new(0),
+        }
+    }
+
+    /// Return whether queries for `name` should be blocked, and update the counters.
+    pub fn check(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        let Some(list) = self.blocked.get(&name) else {
+            return false;
+        };
+        if self.allowed.get(&name).is_some() {
+            self.allowlisted_queries.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        self.blocked_queries[list].fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Return the current counters.
+    pub fn stats(&self) -> BlocklistStats {
+        BlocklistStats {
+            allowlisted_queries: self.allowlisted_queries.load(Ordering::Relaxed),
+            lists: self
+                .domains
+                .iter()
+                .zip(&self.blocked_queries)
+                .map(|(domains, blocked_queries)| ListStats {
+                    domains: *domains,
+                    blocked_queries: blocked_queries.load(Ordering::Relaxed),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Blocklists are equal if they block the same domains. The counters are ignored.
+impl PartialEq for DomainBlocklist {
+    fn eq(&self, other: &Self) -> bool {
+        self.blocked == other.blocked
+            && self.allowed == other.allowed
+            && self.domains == other.domains
+    }
+}
+
+impl Eq for DomainBlocklist {}
+
+impl fmt::Debug for DomainBlocklist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DomainBlocklist")
+            .field("domains", &self.domains)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A set of domains, keyed by their labels from right to left. Each domain also covers its
+/// subdomains.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DomainTrie {
+    root: TrieNode,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TrieNode {
+    children: HashMap<Box<str>, TrieNode>,
+    /// Set if the domain ending at this node is in the set
+    value: Option<usize>,
+}
+
+impl DomainTrie {
+    /// Add `domain` with the associated `value`. Returns `false` if the domain is already covered
+    /// by the set.
+    fn insert(&mut self, domain: &str, value: usize) -> bool {
+        let mut node = &mut self.root;
+        for label in domain.rsplit('.') {
+            if node.value.is_some() {
+                return false;
+            }
+            node = node.children.entry(label.into()).or_default();
+        }
+        if node.value.is_some() {
+            return false;
+        }
+        node.value = Some(value);
+        // Subdomains are covered by this node from now on
+        node.children = HashMap::new();
+        true
+    }
+
+    /// Return the value of the domain that covers `name`, if any.
+    fn get(&self, name: &str) -> Option<usize> {
+        let mut node = &self.root;
+        for label in name.rsplit('.') {
+            node = node.children.get(label)?;
+            if node.value.is_some() {
+                return node.value;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HOSTS: &str = r#"
+# Comment
+127.0.0.1 localhost
+::1 localhost ip6-localhost
+0.0.0.0 0.0.0.0
+0.0.0.0 ads.example tracker.example # trailing comment
+0.0.0.0 Metrics.Example.
+0.0.0.0 not..valid
+"#;
+
+    const ADBLOCK: &str = r#"
+[Adblock Plus 2.0]
+! Title: Test list
+||ads.example^
+||cdn.example^$third-party
+||example.org/banner.png
+||tracking.example^$important
+@@||good.ads.example^
+"#;
+
+    #[test]
+    fn test_parse_hosts() {
+        assert_eq!(
+            parse(HOSTS),
+            ParsedBlocklist {
+                blocked: vec![
+                    "ads.example".to_owned(),
+                    "tracker.example".to_owned(),
+                    "metrics.example".to_owned(),
+                ],
+                allowed: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_adblock() {
+        assert_eq!(
+            parse(ADBLOCK),
+            ParsedBlocklist {
+                blocked: vec!["ads.example".to_owned(), "tracking.example".to_owned()],
+                allowed: vec!["good.ads.example".to_owned()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_plain() {
+        assert_eq!(
+            parse("ads.example\n*.tracker.example\n"),
+            ParsedBlocklist {
+                blocked: vec!["ads.example".to_owned(), "tracker.example".to_owned()],
+                allowed: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let blocklist = DomainBlocklist::new(
+            &[parse(ADBLOCK), parse("sub.ads.example\nmetrics.example\n")],
+            &["metrics.example".to_owned()],
+        );
+
+        assert!(blocklist.check("ads.example."));
+        assert!(blocklist.check("sub.ads.example."));
+        assert!(blocklist.check("Deep.Sub.ADS.example."));
+        assert!(!blocklist.check("good.ads.example."));
+        assert!(!blocklist.check("host.good.ads.example."));
+        assert!(!blocklist.check("badads.example."));
+        assert!(!blocklist.check("example."));
+        assert!(!blocklist.check("metrics.example."));
+
+        assert_eq!(
+            blocklist.stats(),
+            BlocklistStats {
+                allowlisted_queries: 3,
+                lists: vec![
+                    ListStats {
+                        domains: 2,
+                        blocked_queries: 3,
+                    },
+                    // `sub.ads.example` is covered by the first list
+                    ListStats {
+                        domains: 1,
+                        blocked_queries: 0,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_insert_parent_domain() {
+        let mut trie = DomainTrie::default();
+        assert!(trie.insert("sub.ads.example", 0));
+        assert!(trie.insert("ads.example", 1));
+        assert!(!trie.insert("other.ads.example", 2));
+        assert_eq!(trie.get("sub.ads.example"), Some(1));
+        assert_eq!(trie.get("ads.example"), Some(1));
+        assert_eq!(trie.get("example"), None);
+    }
+}
@@ -0,0 +1,490 @@
+//! A local DNS forwarder, which is registered as the system resolver and forwards queries to
+//! upstream resolvers. It is used for two things:
+//!
+//! * DNS-over-HTTPS or DNS-over-TLS. Since the upstream resolvers are reached through the tunnel,
+//!   neither the queries nor the responses are ever sent in plaintext.
+//! * Split DNS, on systems where the system resolver cannot route queries for specific domains
+//!   to specific resolvers. Queries for such domains are sent to resolvers on the local network,
+//!   and all other queries are sent to the regular upstream resolvers.
+//! * Local domain blocklists. Queries for blocked domains are answered with NXDOMAIN, without
+//!   being forwarded.
+//!
+//! See [DnsForwarder::start].
+use std::{
+    cmp::Reverse,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hickory_proto::{
+    op::{Header, MessageType, OpCode, ResponseCode},
+    rr::Name,
+};
+use hickory_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    error::ResolveErrorKind,
+    TokioAsyncResolver,
+};
+use hickory_server::{
+    authority::MessageResponseBuilder,
+    server::{Request, RequestHandler, ResponseHandler, ResponseInfo},
+    ServerFuture,
+};
+use talpid_types::net::dns::{EncryptedDnsProtocol, EncryptedDnsUpstream, SplitDnsRoutes};
+
+use super::blocklist::DomainBlocklist;
+
+/// Address that the forwarder listens on.
+///
+/// The system resolver cannot be configured to use a non-standard port on most platforms, so a
+/// dedicated loopback address is used to avoid conflicts with other local resolvers. On macOS,
+/// only 127.0.0.1 is assigned to the loopback interface by default.
+#[cfg(not(target_os = "macos"))]
+pub const LISTEN_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 66, 0, 53));
+#[cfg(target_os = "macos")]
+pub const LISTEN_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Port that the forwarder listens on.
+pub const LISTEN_PORT: u16 = 53;
+
+/// Port used to reach plain DNS resolvers.
+const DNS_PORT: u16 = 53;
+
+/// How long to wait for a DNS query on an idle TCP connection.
+const TCP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Forwarder errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to bind UDP socket
+    #[error("Failed to bind UDP socket")]
+    UdpBindError(#[source] io::Error),
+
+    /// Failed to bind TCP socket
+    #[error("Failed to bind TCP socket")]
+    TcpBindError(#[source] io::Error),
+
+    /// Failed to get local address of a bound socket
+    #[error("Failed to get local address of a bound socket")]
+    GetSocketAddrError(#[source] io::Error),
+}
+
+/// Where the forwarder sends queries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwarderConfig {
+    /// Resolvers for names that match no split DNS route
+    pub upstreams: Upstreams,
+    /// Plain resolvers on the local network, to use for specific domains
+    pub split_dns: SplitDnsRoutes,
+    /// Domains to answer with NXDOMAIN
+    pub blocklist: Option<Arc<DomainBlocklist>>,
+}
+
+/// Resolvers that the forwarder sends queries to by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Upstreams {
+    /// DNS-over-HTTPS or DNS-over-TLS resolvers
+    Encrypted(Vec<EncryptedDnsUpstream>),
+    /// Plain DNS resolvers
+    Plain(Vec<IpAddr>),
+}
+
+/// A running DNS forwarder. The forwarder stops when this is dropped.
+pub struct DnsForwarder {
+    config: ForwarderConfig,
+    resolvers: Arc<Mutex<Resolvers>>,
+    server: tokio::task::JoinHandle<()>,
+    local_addr: SocketAddr,
+}
+
+impl DnsForwarder {
+    /// Start forwarding queries received on [LISTEN_ADDR] according to `config`.
+    pub async fn start(config: ForwarderConfig) -> Result<Self, Error> {
+        Self::bind(SocketAddr::new(LISTEN_ADDR, LISTEN_PORT), config).await
+    }
+
+    async fn bind(addr: SocketAddr, config: ForwarderConfig) -> Result<Self, Error> {
+        let udp_socket = tokio::net::UdpSocket::bind(addr)
+            .await
+            .map_err(Error::UdpBindError)?;
+        let local_addr = udp_socket.local_addr().map_err(Error::GetSocketAddrError)?;
+        let tcp_listener = tokio::net::TcpListener::bind(local_addr)
+            .await
+            .map_err(Error::TcpBindError)?;
+
+        let resolvers = Arc::new(Mutex::new(Resolvers::new(&config)));
+        let mut server = ServerFuture::new(ForwardingHandler {
+            resolvers: resolvers.clone(),
+        });
+        server.register_socket(udp_socket);
+        server.register_listener(tcp_listener, TCP_TIMEOUT);
+
+        let server = tokio::spawn(async move {
+            if let Err(error) = server.block_until_done().await {
+                log::error!("DNS forwarder unexpectedly stopped: {error}");
+            }
+        });
+
+        log::debug!("Started DNS forwarder on {local_addr}");
+
+        Ok(DnsForwarder {
+            config,
+            resolvers,
+            server,
+            local_addr,
+        })
+    }
+
+    /// Where queries are forwarded.
+    pub fn config(&self) -> &ForwarderConfig {
+        &self.config
+    }
+
+    /// Forward queries according to `config` instead, without restarting the forwarder.
+    pub fn set_config(&mut self, config: ForwarderConfig) {
+        *self.resolvers.lock().unwrap() = Resolvers::new(&config);
+        self.config = config;
+    }
+
+    /// Stop the forwarder and wait for it to shut down.
+    pub async fn stop(mut self) {
+        self.server.abort();
+        let _ = (&mut self.server).await;
+    }
+}
+
+impl Drop for DnsForwarder {
+    fn drop(&mut self) {
+        log::debug!("Stopping DNS forwarder on {}", self.local_addr);
+        self.server.abort();
+    }
+}
+
+/// Resolvers for a [ForwarderConfig].
+struct Resolvers {
+    default: TokioAsyncResolver,
+    /// Resolvers for split DNS domains, with the most specific domains first
+    split: Vec<(Name, TokioAsyncResolver)>,
+    blocklist: Option<Arc<DomainBlocklist>>,
+}
+
+impl Resolvers {
+    fn new(config: &ForwarderConfig) -> Self {
+        let default = match &config.upstreams {
+            Upstreams::Encrypted(upstreams) => encrypted_resolver(upstreams),
+            Upstreams::Plain(servers) => plain_resolver(servers),
+        };
+        let mut split: Vec<_> = config
+            .split_dns
+            .iter()
+            .filter_map(
+                |(domain, servers)| match Name::from_ascii(format!("{domain}.")) {
+                    Ok(name) => Some((name, plain_resolver(servers))),
+                    Err(error) => {
+                        log::error!("Ignoring invalid split DNS domain {domain}: {error}");
+                        None
+                    }
+                },
+            )
+            .collect();
+        split.sort_by_key(|(name, _)| Reverse(name.num_labels()));
+
+        Resolvers {
+            default,
+            split,
+            blocklist: config.blocklist.clone(),
+        }
+    }
+
+    /// Return the resolver to send queries for `name` to, or `None` if the name is blocked.
+    fn for_query(&self, name: &Name) -> Option<TokioAsyncResolver> {
+        if let Some(blocklist) = &self.blocklist {
+            if blocklist.check(&name.to_ascii()) {
+                return None;
+            }
+        }
+        Some(self.for_name(name).clone())
+    }
+
+    /// Return the resolver to send queries for `name` to.
+    fn for_name(&self, name: &Name) -> &TokioAsyncResolver {
+        self.split
+            .iter()
+            .find(|(domain, _)| domain.zone_of(name))
+            .map(|(_, resolver)| resolver)
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Create a resolver which sends plaintext queries to `servers`.
+fn plain_resolver(servers: &[IpAddr]) -> TokioAsyncResolver {
+    let config = ResolverConfig::from_parts(
+        None,
+        vec![],
+        NameServerConfigGroup::from_ips_clear(servers, DNS_PORT, true),
+    );
+    TokioAsyncResolver::tokio(config, ResolverOpts::default())
+}
+
+/// Create a resolver which sends queries to `upstreams`, and verifies their certificates using
+/// the bundled root certificates.
+fn encrypted_resolver(upstreams: &[EncryptedDnsUpstream]) -> TokioAsyncResolver {
+    let mut config = ResolverConfig::new();
+    for upstream in upstreams {
+        let ips = [upstream.address.ip()];
+        let port = upstream.address.port();
+        let hostname = upstream.hostname.clone();
+        let group = match upstream.protocol {
+            EncryptedDnsProtocol::Https => {
+                NameServerConfigGroup::from_ips_https(&ips, port, hostname, true)
+            }
+            EncryptedDnsProtocol::Tls => {
+                NameServerConfigGroup::from_ips_tls(&ips, port, hostname, true)
+            }
+        };
+        for name_server in group.into_inner() {
+            config.add_name_server(name_server);
+        }
+    }
+    config.set_tls_client_config(Arc::new(tls_client_config()));
+
+    TokioAsyncResolver::tokio(config, ResolverOpts::default())
+}
+
+fn tls_client_config() -> rustls::ClientConfig {
+    let mut root_store = rustls::RootCertStore::empty();
+    root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+
+    rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(root_store)
+        .with_no_client_auth()
+}
+
+/// An implementation of [hickory_server::server::RequestHandler] that resolves queries using the
+/// upstream resolvers.
+struct ForwardingHandler {
+    resolvers: Arc<Mutex<Resolvers>>,
+}
+
+impl ForwardingHandler {
+    async fn forward<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        mut response_handle: R,
+    ) -> io::Result<ResponseInfo> {
+        let builder = MessageResponseBuilder::from_message_request(request);
+        let query = request.query().original();
+        let Some(resolver) = self.resolvers.lock().unwrap().for_query(query.name()) else {
+            let response = builder.error_msg(request.header(), ResponseCode::NXDomain);
+            return response_handle.send_response(response).await;
+        };
+
+        match resolver
+            .lookup(query.name().clone(), query.query_type())
+            .await
+        {
+            Ok(lookup) => {
+                let mut header = Header::new();
+                header.set_id(request.id());
+                header.set_op_code(OpCode::Query);
+                header.set_message_type(MessageType::Response);
+                header.set_recursion_desired(request.header().recursion_desired());
+                header.set_recursion_available(true);
+                let response = builder.build(
+                    header,
+                    lookup.record_iter(),
+                    std::iter::empty(),
+                    std::iter::empty(),
+                    std::iter::empty(),
+                );
+                response_handle.send_response(response).await
+            }
+            Err(error) => {
+                let response_code = match error.kind() {
+                    ResolveErrorKind::NoRecordsFound { response_code, .. } => *response_code,
+                    _ => {
+                        log::debug!("Failed to forward DNS query: {error}");
+                        ResponseCode::ServFail
+                    }
+                };
+                let response = builder.error_msg(request.header(), response_code);
+                response_handle.send_response(response).await
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RequestHandler for ForwardingHandler {
+    async fn handle_request<R: ResponseHandler>(
+        &self,
+        request: &Request,
+        mut response_handle: R,
+    ) -> ResponseInfo {
+        if !request.src().ip().is_loopback() {
+            log::error!("Dropping a stray request from outside: {}", request.src());
+            return Header::new().into();
+        }
+        if request.message_type() != MessageType::Query || request.op_code() != OpCode::Query {
+            let response = MessageResponseBuilder::from_message_request(request)
+                .error_msg(request.header(), ResponseCode::NotImp);
+            return response_handle
+                .send_response(response)
+                .await
+                .unwrap_or_else(|_| Header::new().into());
+        }
+
+        self.forward(request, response_handle)
+            .await
+            .unwrap_or_else(|error| {
+                log::error!("Failed to send DNS response: {error}");
+                Header::new().into()
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encrypted_config(upstreams: Vec<EncryptedDnsUpstream>) -> ForwarderConfig {
+        ForwarderConfig {
+            upstreams: Upstreams::Encrypted(upstreams),
+            split_dns: SplitDnsRoutes::new(),
+            blocklist: None,
+        }
+    }
+
+    async fn start_forwarder(upstreams: Vec<EncryptedDnsUpstream>) -> DnsForwarder {
+        DnsForwarder::bind((Ipv4Addr::LOCALHOST, 0).into(), encrypted_config(upstreams))
+            .await
+            .unwrap()
+    }
+
+    fn get_test_resolver(port: u16) -> TokioAsyncResolver {
+        let resolver_config = ResolverConfig::from_parts(
+            None,
+            vec![],
+            NameServerConfigGroup::from_ips_clear(&[Ipv4Addr::LOCALHOST.into()], port, true),
+        );
+        let mut opts = ResolverOpts::default();
+        opts.attempts = 1;
+        TokioAsyncResolver::tokio(resolver_config, opts)
+    }
+
+    /// Queries should fail, rather than be sent in plaintext, if no upstream can be reached
+    #[tokio::test]
+    async fn test_unreachable_upstream() {
+        let forwarder = start_forwarder(vec![EncryptedDnsUpstream {
+            protocol: EncryptedDnsProtocol::Tls,
+            // Nothing listens on the discard port
+            address: (Ipv4Addr::LOCALHOST, 9).into(),
+            hostname: "dns.test".to_owned(),
+        }])
+        .await;
+        let test_resolver = get_test_resolver(forwarder.local_addr.port());
+
+        let result = test_resolver.lookup_ip("mullvad.net").await;
+        assert!(result.is_err(), "lookup should fail: {result:?}");
+    }
+
+    #[tokio::test]
+    async fn test_set_config() {
+        let mut forwarder = start_forwarder(vec![]).await;
+        let upstream =
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Https, "192.0.2.1#dns.test").unwrap();
+        forwarder.set_config(encrypted_config(vec![upstream.clone()]));
+        assert_eq!(forwarder.config(), &encrypted_config(vec![upstream]));
+    }
+
+    /// Queries should be sent to the resolver of the most specific matching split DNS domain
+    #[tokio::test]
+    async fn test_split_dns_routing() {
+        let resolvers = Resolvers::new(&ForwarderConfig {
+            upstreams: Upstreams::Plain(vec!["10.64.0.1".parse().unwrap()]),
+            split_dns: SplitDnsRoutes::from([
+                (
+                    "corp.example".to_owned(),
+                    vec!["192.168.1.53".parse().unwrap()],
+                ),
+                (
+                    "dev.corp.example".to_owned(),
+                    vec!["192.168.2.53".parse().unwrap()],
+                ),
+            ]),
+            blocklist: None,
+        });
+        let resolver_for = |name: &str| resolvers.for_name(&Name::from_ascii(name).unwrap());
+
+        assert!(std::ptr::eq(
+            resolver_for("host.dev.corp.example."),
+            &resolvers.split[0].1
+        ));
+        assert!(std::ptr::eq(
+            resolver_for("corp.example."),
+            &resolvers.split[1].1
+        ));
+        assert!(std::ptr::eq(
+            resolver_for("CORP.example."),
+            &resolvers.split[1].1
+        ));
+        assert!(std::ptr::eq(
+            resolver_for("notcorp.example."),
+            &resolvers.default
+        ));
+        assert!(std::ptr::eq(
+            resolver_for("mullvad.net."),
+            &resolvers.default
+        ));
+    }
+
+    /// Queries for blocked domains should be answered with NXDOMAIN without being forwarded
+    #[tokio::test]
+    async fn test_blocklist() {
+        let blocklist = Arc::new(DomainBlocklist::new(
+            &[crate::dns::blocklist::parse("ads.example\n")],
+            &[],
+        ));
+        let mut forwarder = start_forwarder(vec![]).await;
+        forwarder.set_config(ForwarderConfig {
+            blocklist: Some(blocklist.clone()),
+            ..encrypted_config(vec![])
+        });
+        let test_resolver = get_test_resolver(forwarder.local_addr.port());
+
+        let error = test_resolver
+            .lookup_ip("tracker.ads.example.")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                error.kind(),
+                ResolveErrorKind::NoRecordsFound {
+                    response_code: ResponseCode::NXDomain,
+                    ..
+                }
+            ),
+            "unexpected error: {error}"
+        );
+        assert_eq!(blocklist.stats().lists[0].blocked_queries, 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let forwarder = start_forwarder(vec![]).await;
+        let addr = forwarder.local_addr;
+        forwarder.stop().await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        std::net::UdpSocket::bind(addr)
+            .expect("Failed to bind to a port that should have been released");
+    }
+}
@@ -1,5 +1,10 @@
 use std::fmt;
 use std::net::IpAddr;
+#[cfg(not(target_os = "android"))]
+use std::sync::Arc;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::dns::EncryptedDnsUpstream;
+use talpid_types::net::dns::SplitDnsRoutes;
 
 #[cfg(target_os = "linux")]
 use talpid_routing::RouteManagerHandle;
@@ -25,16 +30,34 @@ mod imp;
 
 pub use self::imp::Error;
 
+/// Local forwarder for encrypted DNS
+#[cfg(not(target_os = "android"))]
+pub mod forwarder;
+
+/// Domain blocklists enforced by the local forwarder
+#[cfg(not(target_os = "android"))]
+pub mod blocklist;
+
 /// DNS configuration
 #[derive(Debug, Clone, PartialEq)]
 pub struct DnsConfig {
     config: InnerDnsConfig,
+    /// Resolvers on the local network to use for specific domains. Split DNS is not supported on
+    /// Android.
+    #[cfg_attr(target_os = "android", allow(dead_code))]
+    split_dns: SplitDnsRoutes,
+    /// Domains to block using the local forwarder
+    #[cfg(not(target_os = "android"))]
+    blocklist: Option<Arc<blocklist::DomainBlocklist>>,
 }
 
 impl Default for DnsConfig {
     fn default() -> Self {
         Self {
             config: InnerDnsConfig::Default,
+            split_dns: SplitDnsRoutes::new(),
+            #[cfg(not(target_os = "android"))]
+            blocklist: None,
         }
     }
 }
@@ -47,7 +70,61 @@ impl DnsConfig {
                 tunnel_config: tunnel_config.to_owned(),
                 non_tunnel_config: non_tunnel_config.to_owned(),
             },
+            split_dns: SplitDnsRoutes::new(),
+            #[cfg(not(target_os = "android"))]
+            blocklist: None,
+        }
+    }
+
+    /// Send all queries to the given encrypted resolvers, through a local forwarder.
+    #[cfg(not(target_os = "android"))]
+    pub fn from_encrypted_upstreams(upstreams: &[EncryptedDnsUpstream]) -> Self {
+        DnsConfig {
+            config: InnerDnsConfig::Encrypted {
+                upstreams: upstreams.to_owned(),
+            },
+            split_dns: SplitDnsRoutes::new(),
+            blocklist: None,
+        }
+    }
+
+    /// Send queries for the domains in `split_dns` to their resolvers, instead of to the regular
+    /// DNS servers. The resolvers are reached outside the tunnel.
+    pub fn with_split_dns(mut self, split_dns: SplitDnsRoutes) -> Self {
+        self.split_dns = split_dns;
+        self
+    }
+
+    /// Answer queries for domains in `blocklist` with NXDOMAIN. This makes the system resolver
+    /// use the local forwarder.
+    #[cfg(not(target_os = "android"))]
+    pub fn with_blocklist(mut self, blocklist: Option<Arc<blocklist::DomainBlocklist>>) -> Self {
+        self.blocklist = blocklist;
+        self
+    }
+
+    /// Addresses of custom DNS servers to allow on non-tunnel interface. The
+    /// [ResolvedDnsConfig] also allows the resolvers of split DNS routes.
+    pub fn non_tunnel_config(&self) -> &[IpAddr] {
+        match &self.config {
+            InnerDnsConfig::Default => &[],
+            InnerDnsConfig::Override {
+                non_tunnel_config, ..
+            } => non_tunnel_config,
+            #[cfg(not(target_os = "android"))]
+            InnerDnsConfig::Encrypted { .. } => &[],
+        }
+    }
+
+    /// Add the resolvers of all split DNS routes to `addrs`, skipping duplicates.
+    #[cfg(not(target_os = "android"))]
+    fn with_split_dns_resolvers(&self, mut addrs: Vec<IpAddr>) -> Vec<IpAddr> {
+        for resolver in self.split_dns.values().flatten() {
+            if !addrs.contains(resolver) {
+                addrs.push(*resolver);
+            }
         }
+        addrs
     }
 }
 
@@ -64,6 +141,11 @@ enum InnerDnsConfig {
         /// on non-tunnel interface, only allow them in the firewall.
         non_tunnel_config: Vec<IpAddr>,
     },
+    /// Use the local forwarder, which sends queries to encrypted resolvers
+    #[cfg(not(target_os = "android"))]
+    Encrypted {
+        upstreams: Vec<EncryptedDnsUpstream>,
+    },
 }
 
 impl DnsConfig {
@@ -72,22 +154,69 @@ impl DnsConfig {
         default_tun_config: &[IpAddr],
         #[cfg(target_os = "macos")] port: u16,
     ) -> ResolvedDnsConfig {
-        match &self.config {
-            InnerDnsConfig::Default => ResolvedDnsConfig {
-                tunnel_config: default_tun_config.to_owned(),
-                non_tunnel_config: vec![],
-                #[cfg(target_os = "macos")]
-                port,
-            },
+        let (tunnel_config, non_tunnel_config) = match &self.config {
+            InnerDnsConfig::Default => (default_tun_config.to_owned(), vec![]),
             InnerDnsConfig::Override {
                 tunnel_config,
                 non_tunnel_config,
-            } => ResolvedDnsConfig {
-                tunnel_config: tunnel_config.to_owned(),
-                non_tunnel_config: non_tunnel_config.to_owned(),
-                #[cfg(target_os = "macos")]
-                port,
-            },
+            } => (tunnel_config.to_owned(), non_tunnel_config.to_owned()),
+            #[cfg(not(target_os = "android"))]
+            InnerDnsConfig::Encrypted { .. } => (vec![], vec![]),
+        };
+
+        // Split DNS is not supported on Android
+        #[cfg(target_os = "android")]
+        {
+            ResolvedDnsConfig {
+                tunnel_config,
+                non_tunnel_config,
+            }
+        }
+
+        #[cfg(not(target_os = "android"))]
+        {
+            let upstreams = match &self.config {
+                InnerDnsConfig::Encrypted { upstreams } => {
+                    Some(forwarder::Upstreams::Encrypted(upstreams.to_owned()))
+                }
+                // Split DNS is handled by the forwarder so that only the DNS config of the tunnel
+                // interface is changed, and never that of the physical interfaces.
+                _ if self.blocklist.is_some() || !self.split_dns.is_empty() => {
+                    Some(forwarder::Upstreams::Plain(
+                        tunnel_config
+                            .iter()
+                            .chain(&non_tunnel_config)
+                            .copied()
+                            .collect(),
+                    ))
+                }
+                _ => None,
+            };
+            let non_tunnel_config = self.with_split_dns_resolvers(non_tunnel_config);
+
+            match upstreams {
+                Some(upstreams) => ResolvedDnsConfig {
+                    // The forwarder is allowed as well as the upstream resolvers that it uses
+                    tunnel_config: std::iter::once(forwarder::LISTEN_ADDR)
+                        .chain(tunnel_config)
+                        .collect(),
+                    non_tunnel_config,
+                    forwarder: Some(forwarder::ForwarderConfig {
+                        upstreams,
+                        split_dns: self.split_dns.clone(),
+                        blocklist: self.blocklist.clone(),
+                    }),
+                    #[cfg(target_os = "macos")]
+                    port: forwarder::LISTEN_PORT,
+                },
+                None => ResolvedDnsConfig {
+                    tunnel_config,
+                    non_tunnel_config,
+                    forwarder: None,
+                    #[cfg(target_os = "macos")]
+                    port,
+                },
+            }
         }
     }
 }
@@ -95,12 +224,15 @@ impl DnsConfig {
 /// DNS configuration with `DnsConfig::Default` resolved
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResolvedDnsConfig {
-    /// Addresses to configure on the tunnel interface
+    /// Addresses to allow on the tunnel interface
     tunnel_config: Vec<IpAddr>,
     /// Addresses to allow on non-tunnel interface.
     /// For the most part, the tunnel state machine will not handle any of this configuration
     /// on non-tunnel interface, only allow them in the firewall.
     non_tunnel_config: Vec<IpAddr>,
+    /// If set, the system resolver should use the local forwarder, configured like this.
+    #[cfg(not(target_os = "android"))]
+    forwarder: Option<forwarder::ForwarderConfig>,
     /// Port to use
     #[cfg(target_os = "macos")]
     port: u16,
@@ -114,6 +246,11 @@ impl fmt::Display for ResolvedDnsConfig {
         f.write_str(" Non-tunnel DNS: ")?;
         Self::fmt_addr_set(f, &self.non_tunnel_config)?;
 
+        #[cfg(not(target_os = "android"))]
+        if let Some(forwarder) = &self.forwarder {
+            write!(f, " Forwarder: {:?}", forwarder)?;
+        }
+
         #[cfg(target_os = "macos")]
         write!(f, " Port: {}", self.port)?;
 
@@ -133,11 +270,27 @@ impl ResolvedDnsConfig {
         f.write_str("}")
     }
 
-    /// Addresses to configure on the tunnel interface
+    /// Addresses to allow on the tunnel interface
     pub fn tunnel_config(&self) -> &[IpAddr] {
         &self.tunnel_config
     }
 
+    /// Addresses to configure the system resolver with. These are the same as
+    /// [Self::tunnel_config], unless the local forwarder is used.
+    pub fn system_config(&self) -> &[IpAddr] {
+        #[cfg(not(target_os = "android"))]
+        if self.forwarder.is_some() {
+            return std::slice::from_ref(&forwarder::LISTEN_ADDR);
+        }
+        &self.tunnel_config
+    }
+
+    /// Configuration of the local forwarder, if the system resolver should use it
+    #[cfg(not(target_os = "android"))]
+    pub fn forwarder_config(&self) -> Option<&forwarder::ForwarderConfig> {
+        self.forwarder.as_ref()
+    }
+
     /// Addresses to allow on non-tunnel interface.
     /// For the most part, the tunnel state machine will not handle any of this configuration
     /// on non-tunnel interface, only allow them in the firewall.
@@ -145,19 +298,26 @@ impl ResolvedDnsConfig {
         &self.non_tunnel_config
     }
 
-    /// Consume `self` and return a vector of all addresses
+    /// Consume `self` and return a vector of all addresses that the system resolver should use
     pub fn addresses(self) -> impl Iterator<Item = IpAddr> {
-        self.non_tunnel_config.into_iter().chain(self.tunnel_config)
+        #[cfg(not(target_os = "android"))]
+        let (non_tunnel_config, tunnel_config) = if self.forwarder.is_some() {
+            (vec![], vec![forwarder::LISTEN_ADDR])
+        } else {
+            (self.non_tunnel_config, self.tunnel_config)
+        };
+        #[cfg(target_os = "android")]
+        let (non_tunnel_config, tunnel_config) = (self.non_tunnel_config, self.tunnel_config);
+
+        non_tunnel_config.into_iter().chain(tunnel_config)
     }
 
-    /// Return whether the config contains only (and at least one) loopback addresses, and zero
-    /// non-loopback addresses
+    /// Return whether the system resolver should use only (and at least one) loopback addresses,
+    /// and zero non-loopback addresses
     pub fn is_loopback(&self) -> bool {
         let (loopback_addrs, non_loopback_addrs) = self
-            .tunnel_config
-            .iter()
-            .chain(self.non_tunnel_config.iter())
-            .copied()
+            .clone()
+            .addresses()
             .partition::<Vec<_>, _>(|ip| ip.is_loopback());
 
         !loopback_addrs.is_empty() && non_loopback_addrs.is_empty()
@@ -223,3 +383,36 @@ trait DnsMonitorT: Sized {
         self.reset()
     }
 }
+
+#[cfg(all(test, not(target_os = "android")))]
+mod test {
+    use super::*;
+
+    /// Split DNS routes should be handled by the forwarder on the tunnel interface, also when
+    /// systemd-resolved is used, so that the DNS config of other links is left alone.
+    #[test]
+    fn test_split_dns_uses_forwarder() {
+        let tunnel_dns: IpAddr = "10.64.0.1".parse().unwrap();
+        let resolver: IpAddr = "192.168.1.53".parse().unwrap();
+        let split_dns = SplitDnsRoutes::from([("corp.example".to_owned(), vec![resolver])]);
+
+        let config = DnsConfig::default()
+            .with_split_dns(split_dns.clone())
+            .resolve(
+                &[tunnel_dns],
+                #[cfg(target_os = "macos")]
+                53,
+            );
+
+        assert_eq!(config.system_config(), &[forwarder::LISTEN_ADDR]);
+        assert_eq!(config.non_tunnel_config(), &[resolver]);
+        assert_eq!(
+            config.forwarder_config(),
+            Some(&forwarder::ForwarderConfig {
+                upstreams: forwarder::Upstreams::Plain(vec![tunnel_dns]),
+                split_dns,
+                blocklist: None,
+            })
+        );
+    }
+}
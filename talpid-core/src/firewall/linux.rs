@@ -1,5 +1,8 @@
 use super::{FirewallArguments, FirewallPolicy};
-use crate::{split_tunnel, tunnel};
+use crate::{
+    split_tunnel::{self, CGroupVersion},
+    tunnel,
+};
 use ipnetwork::IpNetwork;
 use nftnl::{
     expr::{self, IcmpCode, Payload, RejectionType, Verdict},
@@ -12,9 +15,12 @@ use std::{
     net::{IpAddr, Ipv4Addr},
     sync::LazyLock,
 };
-use talpid_types::net::{
-    AllowedEndpoint, AllowedTunnelTraffic, Endpoint, TransportProtocol, ALLOWED_LAN_MULTICAST_NETS,
-    ALLOWED_LAN_NETS,
+use talpid_types::{
+    net::{
+        AllowedEndpoint, AllowedLanNets, AllowedTunnelTraffic, Endpoint, TransportProtocol,
+        ALLOWED_LAN_MULTICAST_NETS,
+    },
+    ErrorExt,
 };
 
 /// Priority for rules that tag split tunneling packets. Equals NF_IP_PRI_MANGLE.
@@ -98,20 +104,30 @@ enum End {
 /// The Linux implementation for the firewall and DNS.
 pub struct Firewall {
     fwmark: u32,
+    split_tunnel_cgroup: CGroupVersion,
 }
 
 impl Firewall {
     pub fn from_args(args: FirewallArguments) -> Result<Self> {
-        Firewall::new(args.fwmark)
+        Ok(Firewall {
+            fwmark: args.fwmark,
+            split_tunnel_cgroup: args.split_tunnel_cgroup,
+        })
     }
 
+    /// Create a firewall that only identifies split tunneled processes by their `net_cls` class
+    /// ID.
     pub fn new(fwmark: u32) -> Result<Self> {
-        Ok(Firewall { fwmark })
+        Ok(Firewall {
+            fwmark,
+            split_tunnel_cgroup: CGroupVersion::V1,
+        })
     }
 
     pub fn apply_policy(&mut self, policy: FirewallPolicy) -> Result<()> {
         let table = Table::new(&TABLE_NAME, ProtoFamily::Inet);
-        let batch = PolicyBatch::new(&table).finalize(&policy, self.fwmark)?;
+        let batch =
+            PolicyBatch::new(&table).finalize(&policy, self.fwmark, self.split_tunnel_cgroup)?;
         Self::send_and_process(&batch)?;
         Self::apply_kernel_config(&policy);
         self.verify_tables(&[TABLE_NAME])
@@ -301,9 +317,14 @@ impl<'a> PolicyBatch<'a> {
 
     /// Finalize the nftnl message batch by adding every firewall rule needed to satisfy the given
     /// policy.
-    pub fn finalize(mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<FinalizedBatch> {
+    pub fn finalize(
+        mut self,
+        policy: &FirewallPolicy,
+        fwmark: u32,
+        split_tunnel_cgroup: CGroupVersion,
+    ) -> Result<FinalizedBatch> {
         self.add_loopback_rules()?;
-        self.add_split_tunneling_rules(policy, fwmark)?;
+        self.add_split_tunneling_rules(policy, fwmark, split_tunnel_cgroup)?;
         self.add_dhcp_client_rules();
         self.add_ndp_rules();
         self.add_policy_specific_rules(policy, fwmark)?;
@@ -311,7 +332,12 @@ impl<'a> PolicyBatch<'a> {
         Ok(self.batch.finalize())
     }
 
-    fn add_split_tunneling_rules(&mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<()> {
+    fn add_split_tunneling_rules(
+        &mut self,
+        policy: &FirewallPolicy,
+        fwmark: u32,
+        split_tunnel_cgroup: CGroupVersion,
+    ) -> Result<()> {
         // Send select DNS requests in the tunnel
         if let FirewallPolicy::Connected {
             tunnel, dns_config, ..
@@ -344,16 +370,32 @@ impl<'a> PolicyBatch<'a> {
         let mut rule = Rule::new(&self.mangle_chain);
         rule.add_expr(&nft_expr!(meta cgroup));
         rule.add_expr(&nft_expr!(cmp == split_tunnel::NET_CLS_CLASSID));
-        // Loads `split_tunnel::MARK` into first nftnl register
-        rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
-        // Sets `split_tunnel::MARK` as connection tracker mark
-        rule.add_expr(&nft_expr!(ct mark set));
-        // Loads `fwmark` into first nftnl register
-        rule.add_expr(&nft_expr!(immediate data fwmark));
-        // Sets `fwmark` as metadata mark for packet
-        rule.add_expr(&nft_expr!(meta mark set));
+        add_split_tunnel_marks(&mut rule, fwmark);
         self.batch.add(&rule, nftnl::MsgType::Add);
 
+        // If there is no net_cls controller, split tunneled processes are instead added to a
+        // cgroup in the v2 hierarchy. Packets are matched on the cgroup of the sending socket.
+        // The split tunnel manager only uses the v2 hierarchy if the kernel supports this match,
+        // so the rule must not be added in any other mode.
+        if split_tunnel_cgroup == CGroupVersion::V2 {
+            match split_tunnel::cgroup2_exclusion_id() {
+                Ok(Some(cgroup_id)) => {
+                    let mut rule = Rule::new(&self.mangle_chain);
+                    rule.add_expr(&SocketCgroupV2 {
+                        level: split_tunnel::CGROUP2_EXCLUSION_LEVEL,
+                    });
+                    rule.add_expr(&nft_expr!(cmp == &cgroup_id.to_ne_bytes()[..]));
+                    add_split_tunnel_marks(&mut rule, fwmark);
+                    self.batch.add(&rule, nftnl::MsgType::Add);
+                }
+                Ok(None) => (),
+                Err(error) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to look up split tunnel cgroup")
+                ),
+            }
+        }
+
         for chain in &[&self.in_chain, &self.out_chain, &self.forward_chain] {
             let mut rule = Rule::new(chain);
             rule.add_expr(&nft_expr!(ct mark));
@@ -536,11 +578,12 @@ impl<'a> PolicyBatch<'a> {
     }
 
     fn add_policy_specific_rules(&mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<()> {
-        let allow_lan = match policy {
+        let allowed_lan_nets = match policy {
             FirewallPolicy::Connecting {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
+                allowed_lan_nets,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
             } => {
@@ -569,12 +612,13 @@ impl<'a> PolicyBatch<'a> {
                         self.add_block_cve_2019_14899(tunnel);
                     }
                 }
-                *allow_lan
+                allow_lan.then_some(allowed_lan_nets)
             }
             FirewallPolicy::Connected {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
+                allowed_lan_nets,
                 dns_config,
             } => {
                 self.add_allow_tunnel_endpoint_rules(peer_endpoint, fwmark);
@@ -611,10 +655,11 @@ impl<'a> PolicyBatch<'a> {
                 if *allow_lan {
                     self.add_block_cve_2019_14899(tunnel);
                 }
-                *allow_lan
+                allow_lan.then_some(allowed_lan_nets)
             }
             FirewallPolicy::Blocked {
                 allow_lan,
+                allowed_lan_nets,
                 allowed_endpoint,
             } => {
                 if let Some(endpoint) = allowed_endpoint {
@@ -623,12 +668,12 @@ impl<'a> PolicyBatch<'a> {
 
                 // Important to drop DNS before allowing LAN (to stop DNS leaking to the LAN)
                 self.add_drop_dns_rule();
-                *allow_lan
+                allow_lan.then_some(allowed_lan_nets)
             }
         };
 
-        if allow_lan {
-            self.add_allow_lan_rules();
+        if let Some(allowed_lan_nets) = allowed_lan_nets {
+            self.add_allow_lan_rules(allowed_lan_nets);
         }
 
         // Reject any remaining outgoing traffic
@@ -857,11 +902,13 @@ impl<'a> PolicyBatch<'a> {
         }
     }
 
-    fn add_allow_lan_rules(&mut self) {
+    fn add_allow_lan_rules(&mut self, allowed_lan_nets: &AllowedLanNets) {
+        let lan_nets = allowed_lan_nets.networks();
+
         // Output and forward chains
         for chain in &[&self.out_chain, &self.forward_chain] {
             // LAN -> LAN
-            for net in &*ALLOWED_LAN_NETS {
+            for net in &lan_nets {
                 let mut out_rule = Rule::new(chain);
                 check_net(&mut out_rule, End::Dst, *net);
                 add_verdict(&mut out_rule, &Verdict::Accept);
@@ -879,7 +926,7 @@ impl<'a> PolicyBatch<'a> {
 
         // Input chain
         // LAN -> LAN
-        for net in &*ALLOWED_LAN_NETS {
+        for net in &lan_nets {
             let mut in_rule = Rule::new(&self.in_chain);
             check_net(&mut in_rule, End::Src, *net);
             add_verdict(&mut in_rule, &Verdict::Accept);
@@ -1071,6 +1118,47 @@ fn add_verdict(rule: &mut Rule<'_>, verdict: &expr::Verdict) {
     rule.add_expr(verdict);
 }
 
+/// Apply `split_tunnel::MARK` as the connection tracking mark and `fwmark` as the packet mark.
+fn add_split_tunnel_marks(rule: &mut Rule<'_>, fwmark: u32) {
+    // Loads `split_tunnel::MARK` into first nftnl register
+    rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
+    // Sets `split_tunnel::MARK` as connection tracker mark
+    rule.add_expr(&nft_expr!(ct mark set));
+    // Loads `fwmark` into first nftnl register
+    rule.add_expr(&nft_expr!(immediate data fwmark));
+    // Sets `fwmark` as metadata mark for packet
+    rule.add_expr(&nft_expr!(meta mark set));
+}
+
+/// Loads the ID of the cgroup v2 ancestor at `level` of the socket that sent the packet into the
+/// first nftnl register. Equivalent to `socket cgroupv2 level <level>`. This requires Linux 5.13
+/// or later.
+struct SocketCgroupV2 {
+    level: u32,
+}
+
+// Attributes of the socket expression, from `enum nftnl_expr_socket_attr` in `libnftnl/expr.h`
+const NFTNL_EXPR_SOCKET_KEY: u16 = 1;
+const NFTNL_EXPR_SOCKET_DREG: u16 = 2;
+const NFTNL_EXPR_SOCKET_LEVEL: u16 = 3;
+// `NFT_SOCKET_CGROUPV2` from `linux/netfilter/nf_tables.h`
+const NFT_SOCKET_CGROUPV2: u32 = 3;
+
+impl expr::Expression for SocketCgroupV2 {
+    fn to_expr(&self, _rule: &Rule<'_>) -> *mut nftnl::nftnl_sys::nftnl_expr {
+        use nftnl::nftnl_sys as sys;
+
+        // SAFETY: The name is a valid C string, and the attributes are all u32 values
+        unsafe {
+            let expr = sys::nftnl_expr_alloc(c"socket".as_ptr());
+            sys::nftnl_expr_set_u32(expr, NFTNL_EXPR_SOCKET_KEY, NFT_SOCKET_CGROUPV2);
+            sys::nftnl_expr_set_u32(expr, NFTNL_EXPR_SOCKET_DREG, libc::NFT_REG_1 as u32);
+            sys::nftnl_expr_set_u32(expr, NFTNL_EXPR_SOCKET_LEVEL, self.level);
+            expr
+        }
+    }
+}
+
 fn set_src_valid_mark_sysctl() -> io::Result<()> {
     fs::write(PROC_SYS_NET_IPV4_CONF_SRC_VALID_MARK, b"1")
 }
@@ -1105,3 +1193,66 @@ fn batch_deprecated_tables(batch: &mut Batch) {
         batch.add(table, nftnl::MsgType::Del);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nftnl::{expr::Expression, nftnl_sys as sys};
+
+    /// Check that no `socket cgroupv2` expression is added when split tunneled processes are
+    /// identified by their `net_cls` class ID.
+    #[test]
+    fn test_no_socket_cgroupv2_expr_in_v1_mode() {
+        let table = Table::new(&TABLE_NAME, ProtoFamily::Inet);
+        let policy = FirewallPolicy::Blocked {
+            allow_lan: false,
+            allowed_lan_nets: AllowedLanNets::default(),
+            allowed_endpoint: None,
+        };
+        let batch = PolicyBatch::new(&table)
+            .finalize(&policy, 0x6d6f6c65, CGroupVersion::V1)
+            .unwrap();
+
+        let socket_expr_name = c"socket".to_bytes_with_nul();
+        for message in &batch {
+            assert!(
+                !message
+                    .windows(socket_expr_name.len())
+                    .any(|window| window == socket_expr_name),
+                "unexpected socket expression in batch"
+            );
+        }
+    }
+
+    /// Check that the `socket cgroupv2` expression is built with the attributes libnftnl expects.
+    #[test]
+    fn test_socket_cgroupv2_expr() {
+        let table = Table::new(&TABLE_NAME, ProtoFamily::Inet);
+        let chain = Chain::new(&MANGLE_CHAIN_NAME, &table);
+        let rule = Rule::new(&chain);
+        let expr = SocketCgroupV2 { level: 2 }.to_expr(&rule);
+
+        // SAFETY: `expr` is a valid socket expression, and `buf` outlives the calls using it
+        unsafe {
+            assert_eq!(
+                sys::nftnl_expr_get_u32(expr, NFTNL_EXPR_SOCKET_KEY),
+                NFT_SOCKET_CGROUPV2
+            );
+            assert_eq!(
+                sys::nftnl_expr_get_u32(expr, NFTNL_EXPR_SOCKET_DREG),
+                libc::NFT_REG_1 as u32
+            );
+            assert_eq!(sys::nftnl_expr_get_u32(expr, NFTNL_EXPR_SOCKET_LEVEL), 2);
+
+            let mut buf = [0u8; 256];
+            sys::nftnl_expr_snprintf(buf.as_mut_ptr().cast(), buf.len(), expr, 0, 0);
+            let printed = CStr::from_bytes_until_nul(&buf).unwrap().to_str().unwrap();
+            assert!(
+                printed.contains("=> reg 1 "),
+                "unexpected expression: {printed}"
+            );
+
+            sys::nftnl_expr_free(expr);
+        }
+    }
+}
@@ -8,8 +8,8 @@ use ipnetwork::IpNetwork;
 use libc::{c_int, sysctlbyname};
 use pfctl::{DropAction, FilterRuleAction, Ip, RedirectRule, Uid};
 use talpid_types::net::{
-    AllowedEndpoint, AllowedTunnelTraffic, TransportProtocol, ALLOWED_LAN_MULTICAST_NETS,
-    ALLOWED_LAN_NETS,
+    AllowedEndpoint, AllowedLanNets, AllowedTunnelTraffic, TransportProtocol,
+    ALLOWED_LAN_MULTICAST_NETS,
 };
 
 use super::{FirewallArguments, FirewallPolicy};
@@ -133,7 +133,9 @@ impl Firewall {
         }
 
         if policy.allow_lan() {
-            let net_is_lan = ALLOWED_LAN_NETS
+            let net_is_lan = policy
+                .allowed_lan_nets()
+                .networks()
                 .iter()
                 .chain(ALLOWED_LAN_MULTICAST_NETS.iter())
                 .any(|net| net.contains(remote_address.ip()));
@@ -315,7 +317,9 @@ impl Firewall {
         rules.push(no_nat_localhost);
 
         // no nat to LAN nets
-        for net in ALLOWED_LAN_NETS
+        for net in policy
+            .allowed_lan_nets()
+            .networks()
             .iter()
             .chain(ALLOWED_LAN_MULTICAST_NETS.iter())
         {
@@ -367,6 +371,7 @@ impl Firewall {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
+                allowed_lan_nets,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
                 redirect_interface,
@@ -404,7 +409,7 @@ impl Firewall {
                 }
 
                 if *allow_lan {
-                    rules.append(&mut self.get_allow_lan_rules()?);
+                    rules.append(&mut self.get_allow_lan_rules(allowed_lan_nets)?);
                 }
 
                 Ok(rules)
@@ -413,6 +418,7 @@ impl Firewall {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
+                allowed_lan_nets,
                 dns_config,
                 redirect_interface,
                 dns_redirect_port: _,
@@ -437,7 +443,7 @@ impl Firewall {
                 rules.append(&mut self.get_block_dns_rules()?);
 
                 if *allow_lan {
-                    rules.append(&mut self.get_allow_lan_rules()?);
+                    rules.append(&mut self.get_allow_lan_rules(allowed_lan_nets)?);
                 }
 
                 if let Some(redirect_interface) = redirect_interface {
@@ -460,6 +466,7 @@ impl Firewall {
             }
             FirewallPolicy::Blocked {
                 allow_lan,
+                allowed_lan_nets,
                 allowed_endpoint,
                 ..
             } => {
@@ -471,7 +478,7 @@ impl Firewall {
                 if *allow_lan {
                     // Important to block DNS before allow LAN (so DNS does not leak to the LAN)
                     rules.append(&mut self.get_block_dns_rules()?);
-                    rules.append(&mut self.get_allow_lan_rules()?);
+                    rules.append(&mut self.get_allow_lan_rules(allowed_lan_nets)?);
                 }
 
                 Ok(rules)
@@ -696,9 +703,12 @@ impl Firewall {
         Ok(vec![lo0_rule])
     }
 
-    fn get_allow_lan_rules(&self) -> Result<Vec<pfctl::FilterRule>> {
+    fn get_allow_lan_rules(
+        &self,
+        allowed_lan_nets: &AllowedLanNets,
+    ) -> Result<Vec<pfctl::FilterRule>> {
         let mut rules = vec![];
-        for net in &*ALLOWED_LAN_NETS {
+        for net in &allowed_lan_nets.networks() {
             let mut rule_builder = self.create_rule_builder(FilterRuleAction::Pass);
             rule_builder.quick(true);
             let allow_out = rule_builder
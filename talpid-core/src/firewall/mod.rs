@@ -6,7 +6,7 @@ use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::LazyLock,
 };
-use talpid_types::net::{AllowedEndpoint, AllowedTunnelTraffic, ALLOWED_LAN_NETS};
+use talpid_types::net::{AllowedEndpoint, AllowedLanNets, AllowedTunnelTraffic, ALLOWED_LAN_NETS};
 
 #[cfg(target_os = "macos")]
 #[path = "macos.rs"]
@@ -87,6 +87,8 @@ pub enum FirewallPolicy {
         tunnel: Option<crate::tunnel::TunnelMetadata>,
         /// Flag setting if communication with LAN networks should be possible.
         allow_lan: bool,
+        /// Networks considered to be LAN networks when `allow_lan` is set.
+        allowed_lan_nets: AllowedLanNets,
         /// Host that should be reachable while connecting.
         allowed_endpoint: AllowedEndpoint,
         /// Networks for which to permit in-tunnel traffic.
@@ -108,6 +110,8 @@ pub enum FirewallPolicy {
         tunnel: crate::tunnel::TunnelMetadata,
         /// Flag setting if communication with LAN networks should be possible.
         allow_lan: bool,
+        /// Networks considered to be LAN networks when `allow_lan` is set.
+        allowed_lan_nets: AllowedLanNets,
         /// Servers that are allowed to respond to DNS requests.
         #[cfg(not(target_os = "android"))]
         dns_config: ResolvedDnsConfig,
@@ -124,6 +128,8 @@ pub enum FirewallPolicy {
     Blocked {
         /// Flag setting if communication with LAN networks should be possible.
         allow_lan: bool,
+        /// Networks considered to be LAN networks when `allow_lan` is set.
+        allowed_lan_nets: AllowedLanNets,
         /// Host that should be reachable while in the blocked state.
         allowed_endpoint: Option<AllowedEndpoint>,
         /// Destination port for DNS traffic redirection. Traffic destined to `127.0.0.1:53` will
@@ -189,6 +195,21 @@ impl FirewallPolicy {
             | FirewallPolicy::Blocked { allow_lan, .. } => *allow_lan,
         }
     }
+
+    /// Return the networks that are allowed if LAN traffic is allowed
+    pub fn allowed_lan_nets(&self) -> &AllowedLanNets {
+        match self {
+            FirewallPolicy::Connecting {
+                allowed_lan_nets, ..
+            }
+            | FirewallPolicy::Connected {
+                allowed_lan_nets, ..
+            }
+            | FirewallPolicy::Blocked {
+                allowed_lan_nets, ..
+            } => allowed_lan_nets,
+        }
+    }
 }
 
 impl fmt::Display for FirewallPolicy {
@@ -283,6 +304,9 @@ pub struct FirewallArguments {
     /// the tunnel and _leaked_ during blocked states.
     #[cfg(target_os = "linux")]
     pub fwmark: u32,
+    /// Kind of cgroup that split tunneled processes are added to.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_cgroup: crate::split_tunnel::CGroupVersion,
 }
 
 /// State to enter during firewall init.
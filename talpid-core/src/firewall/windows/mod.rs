@@ -142,6 +142,8 @@ impl Firewall {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
+                // Custom LAN networks are rejected on Windows by `AllowedLanNets::new`
+                allowed_lan_nets: _,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
             } => {
@@ -159,6 +161,7 @@ impl Firewall {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
+                allowed_lan_nets: _,
                 dns_config,
             } => {
                 let cfg = &WinFwSettings::new(allow_lan);
@@ -166,6 +169,7 @@ impl Firewall {
             }
             FirewallPolicy::Blocked {
                 allow_lan,
+                allowed_lan_nets: _,
                 allowed_endpoint,
             } => {
                 let cfg = &WinFwSettings::new(allow_lan);
@@ -0,0 +1,374 @@
+//! Automatically exclude processes from the tunnel based on the path of their executable.
+//!
+//! The kernel notifies us of every `exec` using the process events connector. When a process
+//! starts executing a binary that is in the set of excluded paths, it is added to the exclusion
+//! cgroup. Since cgroup membership is inherited on `fork`, any child processes are excluded as well.
+
+use super::{Error, PidManager};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs, io, mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Multicast group of the process events connector.
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+/// Subscribe to process events.
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+/// `what` value of `struct proc_event` for `exec` events.
+const PROC_EVENT_EXEC: u32 = 2;
+
+/// Size of `struct nlmsghdr`.
+const NLMSG_HDRLEN: usize = mem::size_of::<libc::nlmsghdr>();
+/// Size of `struct cn_msg`, excluding the payload.
+const CN_MSG_LEN: usize = 20;
+
+/// How often the monitor thread checks whether it should stop.
+const RECV_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Moves processes into the exclusion cgroup when they execute one of the excluded applications.
+pub struct AppMonitor {
+    paths: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Processes excluded by the monitor. Processes excluded in other ways, e.g. manually by the
+    /// user, are not in this set and are never removed from the cgroup by the monitor.
+    excluded_pids: Arc<Mutex<HashSet<i32>>>,
+    pid_manager: PidManager,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl AppMonitor {
+    /// Start listening for new processes. No process is excluded until [`AppMonitor::set_paths`]
+    /// is called.
+    pub fn spawn(pid_manager: PidManager) -> Result<Self, Error> {
+        let connector = ProcConnector::open().map_err(Error::InitProcessMonitor)?;
+
+        let paths = Arc::new(Mutex::new(HashSet::new()));
+        let excluded_pids = Arc::new(Mutex::new(HashSet::new()));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread_paths = paths.clone();
+        let thread_excluded_pids = excluded_pids.clone();
+        let thread_pid_manager = pid_manager.clone();
+        let thread_shutdown = shutdown.clone();
+        thread::Builder::new()
+            .name("split-tunnel-app-monitor".to_owned())
+            .spawn(move || {
+                monitor_processes(
+                    connector,
+                    &thread_paths,
+                    &thread_excluded_pids,
+                    &thread_pid_manager,
+                    &thread_shutdown,
+                )
+            })
+            .map_err(Error::InitProcessMonitor)?;
+
+        Ok(AppMonitor {
+            paths,
+            excluded_pids,
+            pid_manager,
+            shutdown,
+        })
+    }
+
+    /// Set the executables to exclude from the tunnel. Running processes that match any of the
+    /// new paths are excluded immediately, and processes that the monitor excluded because they
+    /// matched a removed path are no longer excluded.
+    pub fn set_paths(&self, paths: &[OsString]) -> Result<(), Error> {
+        // `/proc/<pid>/exe` is always a canonical path, so symlinks must be resolved
+        let new_paths: HashSet<PathBuf> = paths
+            .iter()
+            .map(PathBuf::from)
+            .map(|path| fs::canonicalize(&path).unwrap_or(path))
+            .collect();
+
+        let removed_paths: HashSet<PathBuf> = {
+            let mut current_paths = self.paths.lock().unwrap();
+            let removed = current_paths.difference(&new_paths).cloned().collect();
+            *current_paths = new_paths.clone();
+            removed
+        };
+
+        if !removed_paths.is_empty() {
+            let current_pids = self.pid_manager.list()?;
+            let mut excluded_pids = self.excluded_pids.lock().unwrap();
+            // Forget processes that have exited or been moved out of the cgroup
+            excluded_pids.retain(|pid| current_pids.contains(pid));
+            for pid in excluded_pids.clone() {
+                if process_path(pid).is_some_and(|path| removed_paths.contains(&path)) {
+                    self.pid_manager.remove(pid)?;
+                    excluded_pids.remove(&pid);
+                }
+            }
+        }
+
+        exclude_matching_processes(&new_paths, &self.excluded_pids, &self.pid_manager)
+    }
+}
+
+impl Drop for AppMonitor {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+}
+
+fn monitor_processes(
+    connector: ProcConnector,
+    paths: &Mutex<HashSet<PathBuf>>,
+    excluded_pids: &Mutex<HashSet<i32>>,
+    pid_manager: &PidManager,
+    shutdown: &AtomicBool,
+) {
+    let mut buffer = [0u8; 256];
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let pid = match connector.recv(&mut buffer) {
+            Ok(len) => match parse_exec_event(&buffer[..len]) {
+                Some(pid) => pid,
+                None => continue,
+            },
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+                ) =>
+            {
+                continue
+            }
+            Err(error) if error.raw_os_error() == Some(libc::ENOBUFS) => {
+                // Events were dropped, so look for processes we may have missed
+                log::warn!("Process events were lost. Rescanning all processes");
+                let paths = paths.lock().unwrap().clone();
+                if let Err(error) = exclude_matching_processes(&paths, excluded_pids, pid_manager) {
+                    log::error!("Failed to exclude processes: {error}");
+                }
+                continue;
+            }
+            Err(error) => {
+                log::error!("Stopping split tunnel app monitor: {error}");
+                return;
+            }
+        };
+
+        let paths = paths.lock().unwrap();
+        if paths.is_empty() {
+            continue;
+        }
+        let Some(path) = process_path(pid) else {
+            continue;
+        };
+        if paths.contains(&path) {
+            log::debug!("Excluding process {pid} ({})", path.display());
+            if let Err(error) = exclude_process(pid, excluded_pids, pid_manager) {
+                log::error!("Failed to exclude process {pid}: {error}");
+            }
+        }
+    }
+}
+
+/// Exclude all running processes whose executable is in `paths`.
+fn exclude_matching_processes(
+    paths: &HashSet<PathBuf>,
+    excluded_pids: &Mutex<HashSet<i32>>,
+    pid_manager: &PidManager,
+) -> Result<(), Error> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let entries = fs::read_dir("/proc").map_err(Error::ListProcesses)?;
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        else {
+            continue;
+        };
+        if process_path(pid).is_some_and(|path| paths.contains(&path)) {
+            // The process may have exited since it was listed
+            if let Err(error) = exclude_process(pid, excluded_pids, pid_manager) {
+                log::debug!("Failed to exclude process {pid}: {error}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Exclude a process and remember that the monitor excluded it. Processes that are already
+/// excluded are left alone, so that they remain excluded when their path is removed.
+fn exclude_process(
+    pid: i32,
+    excluded_pids: &Mutex<HashSet<i32>>,
+    pid_manager: &PidManager,
+) -> Result<(), Error> {
+    if pid_manager.list()?.contains(&pid) {
+        return Ok(());
+    }
+    pid_manager.add(pid)?;
+    excluded_pids.lock().unwrap().insert(pid);
+    Ok(())
+}
+
+/// Return the path of the executable that the process is running, if it still exists.
+fn process_path(pid: i32) -> Option<PathBuf> {
+    fs::read_link(Path::new("/proc").join(pid.to_string()).join("exe")).ok()
+}
+
+/// Return the PID of the process (thread group) that called `exec`, if `message` is an exec event.
+fn parse_exec_event(message: &[u8]) -> Option<i32> {
+    let event = message.get(NLMSG_HDRLEN + CN_MSG_LEN..)?;
+    let what = u32::from_ne_bytes(event.get(0..4)?.try_into().ok()?);
+    if what != PROC_EVENT_EXEC {
+        return None;
+    }
+    // `struct exec_proc_event` follows `what`, `cpu` and `timestamp_ns`
+    let tgid = i32::from_ne_bytes(event.get(20..24)?.try_into().ok()?);
+    Some(tgid)
+}
+
+/// Netlink socket subscribed to the process events connector.
+struct ProcConnector {
+    socket: OwnedFd,
+}
+
+impl ProcConnector {
+    fn open() -> io::Result<Self> {
+        // SAFETY: Calling socket has no safety requirements
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fd` is a valid socket that is owned by nothing else
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: `sockaddr_nl` is valid when zeroed
+        let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = CN_IDX_PROC;
+        // SAFETY: `address` is a valid `sockaddr_nl` of the given size
+        let result = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let timeout = libc::timeval {
+            tv_sec: RECV_TIMEOUT.as_secs() as libc::time_t,
+            tv_usec: 0,
+        };
+        // SAFETY: `timeout` is a valid `timeval` of the given size
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                &timeout as *const libc::timeval as *const libc::c_void,
+                mem::size_of::<libc::timeval>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let connector = ProcConnector { socket };
+        connector.send_op(PROC_CN_MCAST_LISTEN)?;
+        Ok(connector)
+    }
+
+    /// Send a `PROC_CN_MCAST_*` operation to the kernel.
+    fn send_op(&self, op: u32) -> io::Result<()> {
+        let op = op.to_ne_bytes();
+        let total_len = NLMSG_HDRLEN + CN_MSG_LEN + op.len();
+
+        let mut message = Vec::with_capacity(total_len);
+        // struct nlmsghdr
+        message.extend_from_slice(&(total_len as u32).to_ne_bytes());
+        message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&std::process::id().to_ne_bytes());
+        // struct cn_msg
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&(op.len() as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&op);
+
+        // SAFETY: `message` is a valid buffer of the given length
+        let result = unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn recv(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        // SAFETY: `buffer` is a valid, writable buffer of the given length
+        let result = unsafe {
+            libc::recv(
+                self.socket.as_raw_fd(),
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                buffer.len(),
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(result as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(what: u32, tgid: i32) -> Vec<u8> {
+        let mut message = vec![0u8; NLMSG_HDRLEN + CN_MSG_LEN];
+        message.extend_from_slice(&what.to_ne_bytes());
+        // cpu and timestamp_ns
+        message.extend_from_slice(&[0u8; 12]);
+        // process_pid and process_tgid
+        message.extend_from_slice(&(tgid + 1).to_ne_bytes());
+        message.extend_from_slice(&tgid.to_ne_bytes());
+        message
+    }
+
+    #[test]
+    fn test_parse_exec_event() {
+        assert_eq!(parse_exec_event(&event(PROC_EVENT_EXEC, 1234)), Some(1234));
+        // PROC_EVENT_FORK
+        assert_eq!(parse_exec_event(&event(1, 1234)), None);
+        // Truncated message
+        assert_eq!(parse_exec_event(&event(PROC_EVENT_EXEC, 1234)[..40]), None);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+};
+use talpid_types::cgroup::{find_cgroup2_mount, find_net_cls_mount, SPLIT_TUNNEL_CGROUP_NAME};
+
+mod app_monitor;
+
+pub use app_monitor::AppMonitor;
+
+const DEFAULT_NET_CLS_DIR: &str = "/sys/fs/cgroup/net_cls";
+const NET_CLS_DIR_OVERRIDE_ENV_VAR: &str = "TALPID_NET_CLS_MOUNT_DIR";
+/// Set this variable to `1` to never use the cgroup v2 hierarchy for split tunneling.
+const DISABLE_CGROUP2_ENV_VAR: &str = "TALPID_DISABLE_CGROUP2_SPLIT_TUNNEL";
+
+/// Oldest kernel version that can match sockets on their cgroup v2 ancestors.
+const CGROUP2_SOCKET_MIN_KERNEL_VERSION: (u32, u32) = (5, 13);
+const PROC_SYS_KERNEL_OSRELEASE: &str = "/proc/sys/kernel/osrelease";
+
+/// Depth of the exclusion cgroup in the cgroup v2 hierarchy, i.e. the number of path components
+/// below the root of the hierarchy.
+pub const CGROUP2_EXCLUSION_LEVEL: u32 = 1;
+
+/// Identifies packets coming from the cgroup.
+/// This should be an arbitrary but unique integer.
+pub const NET_CLS_CLASSID: u32 = 0x4d9f41;
+/// Value used to mark packets and associated connections.
+/// This should be an arbitrary but unique integer.
+pub const MARK: i32 = 0xf41;
+
+/// Errors related to split tunneling.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Unable to create cgroup.
+    #[error("Unable to initialize net_cls cgroup instance")]
+    InitNetClsCGroup(#[source] nix::Error),
+
+    /// Unable to create cgroup.
+    #[error("Unable to create cgroup for excluded processes")]
+    CreateCGroup(#[source] io::Error),
+
+    /// Unable to set class ID for cgroup.
+    #[error("Unable to set cgroup class ID")]
+    SetCGroupClassId(#[source] io::Error),
+
+    /// Unable to add PID to cgroup.procs.
+    #[error("Unable to add PID to cgroup.procs")]
+    AddCGroupPid(#[source] io::Error),
+
+    /// Unable to remove PID to cgroup.procs.
+    #[error("Unable to remove PID from cgroup")]
+    RemoveCGroupPid(#[source] io::Error),
+
+    /// Unable to read cgroup.procs.
+    #[error("Unable to obtain PIDs from cgroup.procs")]
+    ListCGroupPids(#[source] io::Error),
+
+    /// Unable to read /proc/mounts
+    #[error("Failed to read /proc/mounts")]
+    ListMounts(#[source] io::Error),
+
+    /// Unable to find the cgroup for excluded processes.
+    #[error("Unable to look up the cgroup for excluded processes")]
+    FindCGroup(#[source] io::Error),
+
+    /// Unable to subscribe to process events.
+    #[error("Unable to start monitoring processes")]
+    InitProcessMonitor(#[source] io::Error),
+
+    /// Unable to list running processes.
+    #[error("Unable to list running processes")]
+    ListProcesses(#[source] io::Error),
+}
+
+/// Kind of cgroup hierarchy used to identify excluded processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CGroupVersion {
+    /// The legacy `net_cls` controller. Packets from excluded processes are tagged with
+    /// [`NET_CLS_CLASSID`].
+    V1,
+    /// The unified hierarchy. Excluded sockets are identified by the exclusion cgroup, using the
+    /// nftables `socket cgroupv2` expression.
+    V2,
+}
+
+/// Manages PIDs in the Linux Cgroup excluded from the VPN tunnel.
+#[derive(Clone)]
+pub struct PidManager {
+    /// Root of the cgroup hierarchy that contains the exclusion cgroup.
+    root_path: PathBuf,
+    version: CGroupVersion,
+}
+
+impl PidManager {
+    /// Creates a new PID Cgroup manager.
+    ///
+    /// Finds the corresponding Cgroup to use. A `net_cls` controller is preferred if one is
+    /// mounted. Otherwise, the cgroup v2 hierarchy is used. If neither exists, a `net_cls`
+    /// filesystem is mounted.
+    pub fn new() -> Result<PidManager, Error> {
+        let (root_path, version) = Self::create_cgroup()?;
+        log::debug!(
+            "Using {version:?} cgroup at {} for split tunneling",
+            root_path.display()
+        );
+        let manager = PidManager { root_path, version };
+        manager.setup_exclusion_group()?;
+        Ok(manager)
+    }
+
+    /// Set up cgroup used to track PIDs for split tunneling.
+    fn create_cgroup() -> Result<(PathBuf, CGroupVersion), Error> {
+        if let Some(net_cls_path) = find_net_cls_mount().map_err(Error::ListMounts)? {
+            return Ok((net_cls_path, CGroupVersion::V1));
+        }
+
+        if env::var(DISABLE_CGROUP2_ENV_VAR).map_or(true, |value| value != "1") {
+            if let Some(cgroup2_path) = find_cgroup2_mount().map_err(Error::ListMounts)? {
+                if cgroup2_socket_match_supported() {
+                    return Ok((cgroup2_path, CGroupVersion::V2));
+                }
+                log::debug!("The kernel cannot match sockets on their cgroup. Using net_cls");
+            }
+        }
+
+        let net_cls_dir = env::var(NET_CLS_DIR_OVERRIDE_ENV_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(DEFAULT_NET_CLS_DIR));
+
+        if !net_cls_dir.exists() {
+            fs::create_dir_all(&net_cls_dir).map_err(Error::CreateCGroup)?;
+        }
+
+        // https://www.kernel.org/doc/Documentation/cgroup-v1/net_cls.txt
+        nix::mount::mount(
+            Some("net_cls"),
+            &net_cls_dir,
+            Some("cgroup"),
+            nix::mount::MsFlags::empty(),
+            Some("net_cls"),
+        )
+        .map_err(Error::InitNetClsCGroup)?;
+
+        Ok((net_cls_dir, CGroupVersion::V1))
+    }
+
+    /// Return the kind of cgroup hierarchy that excluded processes are added to.
+    pub fn version(&self) -> CGroupVersion {
+        self.version
+    }
+
+    fn setup_exclusion_group(&self) -> Result<(), Error> {
+        let exclusions_dir = self.root_path.join(SPLIT_TUNNEL_CGROUP_NAME);
+        if !exclusions_dir.exists() {
+            fs::create_dir(exclusions_dir.clone()).map_err(Error::CreateCGroup)?;
+        }
+
+        match self.version {
+            CGroupVersion::V1 => {
+                let classid_path = exclusions_dir.join("net_cls.classid");
+                fs::write(classid_path, NET_CLS_CLASSID.to_string().as_bytes())
+                    .map_err(Error::SetCGroupClassId)
+            }
+            // The cgroup is matched by its ID, so there is nothing to configure
+            CGroupVersion::V2 => Ok(()),
+        }
+    }
+
+    /// Add a PID to the Cgroup to have it excluded from the tunnel.
+    pub fn add(&self, pid: i32) -> Result<(), Error> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.exclusion_procs_path())
+            .map_err(Error::AddCGroupPid)?;
+
+        file.write_all(pid.to_string().as_bytes())
+            .map_err(Error::AddCGroupPid)
+    }
+
+    /// Remove a PID from the Cgroup to have it included in the tunnel.
+    pub fn remove(&self, pid: i32) -> Result<(), Error> {
+        // FIXME: We remove PIDs from our cgroup here by adding
+        //        them to the parent cgroup. This seems wrong.
+        let mut file = self
+            .open_parent_cgroup_handle()
+            .map_err(Error::RemoveCGroupPid)?;
+
+        file.write_all(pid.to_string().as_bytes())
+            .map_err(Error::RemoveCGroupPid)
+    }
+
+    /// Return a list of all PIDs currently in the Cgroup excluded from the tunnel.
+    pub fn list(&self) -> Result<Vec<i32>, Error> {
+        let file = fs::File::open(self.exclusion_procs_path()).map_err(Error::ListCGroupPids)?;
+
+        let result: Result<Vec<i32>, io::Error> = BufReader::new(file)
+            .lines()
+            .map(|line| {
+                line.and_then(|v| {
+                    v.parse()
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                })
+            })
+            .collect();
+        result.map_err(Error::ListCGroupPids)
+    }
+
+    /// Removes all PIDs from the Cgroup.
+    pub fn clear(&self) -> Result<(), Error> {
+        let pids = self.list()?;
+
+        let mut file = self
+            .open_parent_cgroup_handle()
+            .map_err(Error::RemoveCGroupPid)?;
+        for pid in pids {
+            file.write_all(pid.to_string().as_bytes())
+                .map_err(Error::RemoveCGroupPid)?;
+        }
+
+        Ok(())
+    }
+
+    /// Path of the file listing the processes in the exclusion cgroup. Both the `net_cls`
+    /// controller and the unified hierarchy expose it as `cgroup.procs`.
+    fn exclusion_procs_path(&self) -> PathBuf {
+        self.root_path
+            .join(SPLIT_TUNNEL_CGROUP_NAME)
+            .join("cgroup.procs")
+    }
+
+    fn open_parent_cgroup_handle(&self) -> io::Result<fs::File> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.root_path.join("cgroup.procs"))
+    }
+}
+
+/// Return the ID of the cgroup v2 exclusion group, if it exists. nftables identifies a cgroup by
+/// the inode number of its directory.
+pub fn cgroup2_exclusion_id() -> Result<Option<u64>, Error> {
+    let Some(cgroup2_path) = find_cgroup2_mount().map_err(Error::ListMounts)? else {
+        return Ok(None);
+    };
+    match fs::metadata(cgroup2_path.join(SPLIT_TUNNEL_CGROUP_NAME)) {
+        Ok(metadata) => Ok(Some(metadata.ino())),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(Error::FindCGroup(error)),
+    }
+}
+
+/// Return whether the running kernel supports the nftables `socket cgroupv2` expression.
+fn cgroup2_socket_match_supported() -> bool {
+    match fs::read_to_string(PROC_SYS_KERNEL_OSRELEASE) {
+        Ok(release) => parse_kernel_version(&release)
+            .is_some_and(|version| version >= CGROUP2_SOCKET_MIN_KERNEL_VERSION),
+        Err(error) => {
+            log::error!("Failed to read kernel version: {error}");
+            false
+        }
+    }
+}
+
+/// Parse the major and minor version from a kernel release string, e.g. `6.1.0-13-amd64`.
+fn parse_kernel_version(release: &str) -> Option<(u32, u32)> {
+    let mut components = release.trim().split(['.', '-']);
+    let major = components.next()?.parse().ok()?;
+    let minor = components.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_kernel_version() {
+        assert_eq!(parse_kernel_version("6.1.0-13-amd64\n"), Some((6, 1)));
+        assert_eq!(parse_kernel_version("5.13.0"), Some((5, 13)));
+        assert_eq!(parse_kernel_version("5.4-rc1"), Some((5, 4)));
+        assert_eq!(parse_kernel_version("unknown"), None);
+    }
+}
@@ -168,7 +168,9 @@ impl TunnelMonitor {
             TunnelParameters::OpenVpn(params) => match &params.proxy {
                 Some(CustomProxy::Shadowsocks(_)) => Some(std::env::current_exe().unwrap()),
                 Some(CustomProxy::Socks5Local(_)) => None,
-                Some(CustomProxy::Socks5Remote(_)) | None => Some(resource_dir.join("openvpn.exe")),
+                Some(CustomProxy::Socks5Remote(_)) | Some(CustomProxy::HttpConnect(_)) | None => {
+                    Some(resource_dir.join("openvpn.exe"))
+                }
             },
             _ => Some(std::env::current_exe().unwrap()),
         }
@@ -268,6 +270,16 @@ impl TunnelMonitor {
         }
     }
 
+    /// Return a handle that can be used to query statistics about the tunnel. Statistics are only
+    /// available for WireGuard tunnels.
+    pub fn stats_handle(&self) -> Option<talpid_wireguard::TunnelStatsHandle> {
+        match &self.monitor {
+            #[cfg(not(target_os = "android"))]
+            InternalTunnelMonitor::OpenVpn(_) => None,
+            InternalTunnelMonitor::Wireguard(monitor) => Some(monitor.stats_handle()),
+        }
+    }
+
     /// Consumes the monitor and blocks until the tunnel exits or there is an error.
     pub fn wait(self) -> Result<()> {
         self.monitor.wait()
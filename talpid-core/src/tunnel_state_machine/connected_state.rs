@@ -1,3 +1,5 @@
+use std::sync::{Arc, OnceLock};
+
 use futures::channel::{mpsc, oneshot};
 use futures::stream::Fuse;
 use futures::StreamExt;
@@ -5,6 +7,7 @@ use futures::StreamExt;
 use talpid_types::net::{AllowedClients, AllowedEndpoint, TunnelParameters};
 use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
 use talpid_types::{BoxedError, ErrorExt};
+use talpid_wireguard::TunnelStatsHandle;
 
 #[cfg(target_os = "macos")]
 use crate::dns::DnsConfig;
@@ -33,6 +36,7 @@ pub struct ConnectedState {
     tunnel_parameters: TunnelParameters,
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
+    stats_handle: Arc<OnceLock<TunnelStatsHandle>>,
 }
 
 impl ConnectedState {
@@ -44,6 +48,7 @@ impl ConnectedState {
         tunnel_parameters: TunnelParameters,
         tunnel_close_event: TunnelCloseEvent,
         tunnel_close_tx: oneshot::Sender<()>,
+        stats_handle: Arc<OnceLock<TunnelStatsHandle>>,
     ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
         let connected_state = ConnectedState {
             metadata,
@@ -51,6 +56,7 @@ impl ConnectedState {
             tunnel_parameters,
             tunnel_close_event,
             tunnel_close_tx,
+            stats_handle,
         };
 
         let tunnel_interface = Some(connected_state.metadata.interface.clone());
@@ -137,6 +143,7 @@ impl ConnectedState {
             peer_endpoint,
             tunnel: self.metadata.clone(),
             allow_lan: shared_values.allow_lan,
+            allowed_lan_nets: shared_values.allowed_lan_nets.clone(),
             #[cfg(not(target_os = "android"))]
             dns_config: Self::resolve_dns(&self.metadata, shared_values),
             #[cfg(target_os = "macos")]
@@ -160,6 +167,11 @@ impl ConnectedState {
     fn set_dns(&self, shared_values: &mut SharedTunnelStateValues) -> Result<(), BoxedError> {
         let dns_config: ResolvedDnsConfig = Self::resolve_dns(&self.metadata, shared_values);
 
+        #[cfg(not(target_os = "android"))]
+        shared_values
+            .update_dns_forwarder(dns_config.forwarder_config().cloned())
+            .map_err(BoxedError::new)?;
+
         #[cfg(not(target_os = "macos"))]
         shared_values
             .dns_monitor
@@ -205,6 +217,9 @@ impl ConnectedState {
             log::error!("{}", error.display_chain_with_msg("Unable to reset DNS"));
         }
 
+        #[cfg(not(target_os = "android"))]
+        shared_values.stop_dns_forwarder();
+
         // On macOS, configure only the local DNS resolver
         #[cfg(target_os = "macos")]
         shared_values
@@ -280,6 +295,22 @@ impl ConnectedState {
                 consequence
             }
             #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowedLanNets(allowed_lan_nets, complete_tx)) => {
+                let consequence = if shared_values.set_allowed_lan_nets(allowed_lan_nets) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
             Some(TunnelCommand::AllowEndpoint(endpoint, tx)) => {
                 shared_values.allowed_endpoint = endpoint;
                 let _ = tx.send(());
@@ -348,6 +379,17 @@ impl ConnectedState {
             Some(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            Some(TunnelCommand::GetStatistics(tx)) => {
+                let stats_handle = self.stats_handle.get().cloned();
+                shared_values.runtime.spawn(async move {
+                    let statistics = match stats_handle {
+                        Some(stats_handle) => stats_handle.get().await,
+                        None => None,
+                    };
+                    let _ = tx.send(statistics);
+                });
+                SameState(self)
+            }
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
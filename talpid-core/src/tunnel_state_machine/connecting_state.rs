@@ -1,5 +1,5 @@
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -12,6 +12,7 @@ use talpid_tunnel::{EventHook, TunnelArgs, TunnelEvent, TunnelMetadata};
 use talpid_types::net::{AllowedClients, AllowedEndpoint, AllowedTunnelTraffic, TunnelParameters};
 use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
 use talpid_types::ErrorExt;
+use talpid_wireguard::TunnelStatsHandle;
 
 use super::connected_state::TunnelEventsReceiver;
 use super::{
@@ -46,6 +47,8 @@ pub struct ConnectingState {
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
     retry_attempt: u32,
+    /// Set once the tunnel monitor has been started
+    stats_handle: Arc<OnceLock<TunnelStatsHandle>>,
 }
 
 impl ConnectingState {
@@ -189,6 +192,7 @@ impl ConnectingState {
             peer_endpoint,
             tunnel: tunnel_metadata.clone(),
             allow_lan: shared_values.allow_lan,
+            allowed_lan_nets: shared_values.allowed_lan_nets.clone(),
             allowed_endpoint: shared_values.allowed_endpoint.clone(),
             allowed_tunnel_traffic,
             #[cfg(target_os = "macos")]
@@ -234,6 +238,8 @@ impl ConnectingState {
         let (tunnel_close_event_tx, tunnel_close_event_rx) = oneshot::channel();
 
         let tunnel_parameters = parameters.clone();
+        let stats_handle = Arc::new(OnceLock::new());
+        let monitor_stats_handle = stats_handle.clone();
 
         tokio::task::spawn_blocking(move || {
             let start = Instant::now();
@@ -250,6 +256,9 @@ impl ConnectingState {
 
             let block_reason = match TunnelMonitor::start(&tunnel_parameters, &log_dir, args) {
                 Ok(monitor) => {
+                    if let Some(handle) = monitor.stats_handle() {
+                        let _ = monitor_stats_handle.set(handle);
+                    }
                     let reason = Self::wait_for_tunnel_monitor(monitor, retry_attempt);
                     log::debug!("Tunnel monitor exited with block reason: {:?}", reason);
                     reason
@@ -290,6 +299,7 @@ impl ConnectingState {
             tunnel_close_event: tunnel_close_event_rx.fuse(),
             tunnel_close_tx,
             retry_attempt,
+            stats_handle,
         }
     }
 
@@ -401,6 +411,16 @@ impl ConnectingState {
                 consequence
             }
             #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowedLanNets(allowed_lan_nets, complete_tx)) => {
+                let consequence = if shared_values.set_allowed_lan_nets(allowed_lan_nets) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
             Some(TunnelCommand::AllowEndpoint(endpoint, tx)) => {
                 if shared_values.allowed_endpoint != endpoint {
                     shared_values.allowed_endpoint = endpoint;
@@ -461,6 +481,10 @@ impl ConnectingState {
             Some(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            Some(TunnelCommand::GetStatistics(tx)) => {
+                let _ = tx.send(None);
+                SameState(self)
+            }
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
@@ -572,6 +596,7 @@ impl ConnectingState {
                 self.tunnel_parameters,
                 self.tunnel_close_event,
                 self.tunnel_close_tx,
+                self.stats_handle,
             )),
             Some((TunnelEvent::Down, _)) => {
                 // It is important to reset this before the tunnel device is down,
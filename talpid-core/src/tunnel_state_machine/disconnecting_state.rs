@@ -42,6 +42,11 @@ impl DisconnectingState {
                 let _ = complete_tx.send(());
             }
             #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowedLanNets(allowed_lan_nets, complete_tx)) => {
+                let _ = shared_values.set_allowed_lan_nets(allowed_lan_nets);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(not(target_os = "android"))]
             Some(TunnelCommand::AllowEndpoint(endpoint, tx)) => {
                 shared_values.allowed_endpoint = endpoint;
                 let _ = tx.send(());
@@ -87,6 +92,9 @@ impl DisconnectingState {
                     _ => AfterDisconnect::Block(reason),
                 }
             }
+            Some(TunnelCommand::GetStatistics(tx)) => {
+                let _ = tx.send(None);
+            }
             None => {
                 if let AfterDisconnect::Reconnect(_) = self.after_disconnect {
                     self.after_disconnect = AfterDisconnect::Nothing;
@@ -11,13 +11,11 @@ use self::{
     disconnecting_state::{AfterDisconnect, DisconnectingState},
     error_state::ErrorState,
 };
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
-use crate::split_tunnel;
 use crate::{
     dns::{DnsConfig, DnsMonitor},
     firewall::{Firewall, FirewallArguments, InitialFirewallState},
     mpsc::Sender,
-    offline,
+    offline, split_tunnel,
 };
 #[cfg(any(target_os = "windows", target_os = "macos"))]
 use std::ffi::OsString;
@@ -46,7 +44,10 @@ use std::{
 #[cfg(target_os = "android")]
 use talpid_types::{android::AndroidContext, ErrorExt};
 use talpid_types::{
-    net::{AllowedEndpoint, Connectivity, TunnelParameters},
+    net::{
+        wireguard::TunnelStatistics, AllowedEndpoint, AllowedLanNets, Connectivity,
+        TunnelParameters,
+    },
     tunnel::{ErrorStateCause, ParameterGenerationError, TunnelStateTransition},
 };
 
@@ -93,6 +94,8 @@ pub enum Error {
 pub struct InitialTunnelState {
     /// Whether to allow LAN traffic when not in the (non-blocking) disconnected state.
     pub allow_lan: bool,
+    /// Networks to consider LAN networks when `allow_lan` is enabled.
+    pub allowed_lan_nets: AllowedLanNets,
     /// Block traffic unless connected to the VPN.
     #[cfg(not(target_os = "android"))]
     pub block_when_disconnected: bool,
@@ -109,6 +112,9 @@ pub struct InitialTunnelState {
     /// Apps to exclude from the tunnel.
     #[cfg(target_os = "android")]
     pub exclude_paths: Vec<String>,
+    /// Kind of cgroup that split tunneled processes are added to.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_cgroup: split_tunnel::CGroupVersion,
 }
 
 /// Identifiers for various network resources that should be unique to a given instance of a tunnel
@@ -195,6 +201,9 @@ pub async fn spawn(
 pub enum TunnelCommand {
     /// Enable or disable LAN access in the firewall.
     AllowLan(bool, oneshot::Sender<()>),
+    /// Set the networks that are considered to be LAN networks.
+    #[cfg(not(target_os = "android"))]
+    AllowedLanNets(AllowedLanNets, oneshot::Sender<()>),
     /// Endpoint that should never be blocked. `()` is sent to the
     /// channel after attempting to set the firewall policy, regardless
     /// of whether it succeeded.
@@ -213,6 +222,8 @@ pub enum TunnelCommand {
     Disconnect,
     /// Block all network access unless tunnel is disconnecting or disconnected
     Block(ErrorStateCause),
+    /// Return statistics about the tunnel, if it is connected.
+    GetStatistics(oneshot::Sender<Option<TunnelStatistics>>),
     /// Bypass a socket, allowing traffic to flow through outside the tunnel.
     #[cfg(target_os = "android")]
     BypassSocket(RawFd, oneshot::Sender<()>),
@@ -312,6 +323,8 @@ impl TunnelStateMachine {
             allow_lan: args.settings.allow_lan,
             #[cfg(target_os = "linux")]
             fwmark: args.linux_ids.fwmark,
+            #[cfg(target_os = "linux")]
+            split_tunnel_cgroup: args.settings.split_tunnel_cgroup,
         };
 
         let firewall = Firewall::from_args(fw_args).map_err(Error::InitFirewallError)?;
@@ -382,6 +395,7 @@ impl TunnelStateMachine {
             route_manager: args.route_manager,
             _offline_monitor: offline_monitor,
             allow_lan: args.settings.allow_lan,
+            allowed_lan_nets: args.settings.allowed_lan_nets,
             #[cfg(not(target_os = "android"))]
             block_when_disconnected: args.settings.block_when_disconnected,
             connectivity,
@@ -395,6 +409,8 @@ impl TunnelStateMachine {
             connectivity_check_was_enabled: None,
             #[cfg(target_os = "macos")]
             filtering_resolver,
+            #[cfg(not(target_os = "android"))]
+            dns_forwarder: None,
         };
 
         tokio::task::spawn_blocking(move || {
@@ -474,6 +490,8 @@ struct SharedTunnelStateValues {
     _offline_monitor: offline::MonitorHandle,
     /// Should LAN access be allowed outside the tunnel.
     allow_lan: bool,
+    /// Networks considered to be LAN networks.
+    allowed_lan_nets: AllowedLanNets,
     /// Should network access be allowed when in the disconnected state.
     #[cfg(not(target_os = "android"))]
     block_when_disconnected: bool,
@@ -499,6 +517,10 @@ struct SharedTunnelStateValues {
     /// Filtering resolver handle
     #[cfg(target_os = "macos")]
     filtering_resolver: crate::resolver::ResolverHandle,
+
+    /// Local forwarder for encrypted DNS. Only running while connected with encrypted DNS.
+    #[cfg(not(target_os = "android"))]
+    dns_forwarder: Option<crate::dns::forwarder::DnsForwarder>,
 }
 
 impl SharedTunnelStateValues {
@@ -569,6 +591,15 @@ impl SharedTunnelStateValues {
         }
     }
 
+    pub fn set_allowed_lan_nets(&mut self, allowed_lan_nets: AllowedLanNets) -> bool {
+        if self.allowed_lan_nets != allowed_lan_nets {
+            self.allowed_lan_nets = allowed_lan_nets;
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn set_dns_config(&mut self, dns_config: DnsConfig) -> bool {
         if self.dns_config != dns_config {
             self.dns_config = dns_config;
@@ -578,6 +609,38 @@ impl SharedTunnelStateValues {
         }
     }
 
+    /// Start, reconfigure or stop the local DNS forwarder, depending on whether the resolved DNS
+    /// config uses it.
+    #[cfg(not(target_os = "android"))]
+    pub fn update_dns_forwarder(
+        &mut self,
+        config: Option<crate::dns::forwarder::ForwarderConfig>,
+    ) -> Result<(), crate::dns::forwarder::Error> {
+        let Some(config) = config else {
+            self.stop_dns_forwarder();
+            return Ok(());
+        };
+        if let Some(forwarder) = &mut self.dns_forwarder {
+            if forwarder.config() != &config {
+                forwarder.set_config(config);
+            }
+            return Ok(());
+        }
+        let forwarder = self
+            .runtime
+            .block_on(crate::dns::forwarder::DnsForwarder::start(config))?;
+        self.dns_forwarder = Some(forwarder);
+        Ok(())
+    }
+
+    /// Stop the local DNS forwarder, if it is running.
+    #[cfg(not(target_os = "android"))]
+    pub fn stop_dns_forwarder(&mut self) {
+        if let Some(forwarder) = self.dns_forwarder.take() {
+            self.runtime.block_on(forwarder.stop());
+        }
+    }
+
     /// NetworkManager's connectivity check can get hung when DNS requests fail, thus the TSM
     /// should always disable it before applying firewall rules. The connectivity check should be
     /// reset whenever the firewall is cleared.
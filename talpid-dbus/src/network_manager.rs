@@ -27,6 +27,8 @@ const CONNECTIVITY_CHECK_KEY: &str = "ConnectivityCheckEnabled";
 const NM_DNS_MANAGER: &str = "org.freedesktop.NetworkManager.DnsManager";
 const NM_DNS_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager/DnsManager";
 const NM_DEVICE: &str = "org.freedesktop.NetworkManager.Device";
+const NM_DEVICE_WIRELESS: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const NM_ACCESS_POINT: &str = "org.freedesktop.NetworkManager.AccessPoint";
 
 const NM_IP4_CONFIG: &str = "org.freedesktop.NetworkManager.IP4Config";
 const NM_IP6_CONFIG: &str = "org.freedesktop.NetworkManager.IP6Config";
@@ -39,6 +41,8 @@ const NM_DEVICE_STATE_IP_CHECK: u32 = 80;
 const NM_DEVICE_STATE_SECONDARY: u32 = 90;
 const NM_DEVICE_STATE_ACTIVATED: u32 = 100;
 
+const NM_DEVICE_TYPE_WIFI: u32 = 2;
+
 const NM_SETTINGS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
 const NM_SETTINGS_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
 const NM_SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
@@ -643,6 +647,26 @@ impl NetworkManager {
         Err(Error::DeviceNotFound)
     }
 
+    /// Returns the SSID of the access point that `interface_name` is associated with, or `None`
+    /// if the interface is not a Wi-Fi interface or is not associated.
+    pub fn get_wireless_ssid(&self, interface_name: &str) -> Result<Option<Vec<u8>>> {
+        let device_path = self.fetch_device(interface_name)?;
+        let device = self.as_path(&device_path);
+
+        let device_type: u32 = device.get(NM_DEVICE, "DeviceType")?;
+        if device_type != NM_DEVICE_TYPE_WIFI {
+            return Ok(None);
+        }
+
+        let access_point: dbus::Path<'static> =
+            device.get(NM_DEVICE_WIRELESS, "ActiveAccessPoint")?;
+        if &*access_point == "/" {
+            return Ok(None);
+        }
+        let ssid: Vec<u8> = self.as_path(&access_point).get(NM_ACCESS_POINT, "Ssid")?;
+        Ok(Some(ssid))
+    }
+
     pub fn convert_address_to_dbus(address: &IpAddr) -> VariantMap {
         let mut map: VariantMap = HashMap::new();
         map.insert(
@@ -543,15 +543,20 @@ impl<C: OpenVpnBuilder + Send + 'static> OpenVpnMonitor<C> {
     fn create_proxy_auth_file(
         proxy_settings: &Option<CustomProxy>,
     ) -> std::result::Result<Option<mktemp::TempFile>, io::Error> {
-        if let Some(CustomProxy::Socks5Remote(remote_proxy)) = proxy_settings {
-            if let Some(ref proxy_auth) = remote_proxy.auth {
-                return Ok(Some(Self::create_credentials_file(
-                    proxy_auth.username(),
-                    proxy_auth.password(),
-                )?));
-            }
-        }
-        Ok(None)
+        let credentials = match proxy_settings {
+            Some(CustomProxy::Socks5Remote(remote_proxy)) => remote_proxy
+                .auth
+                .as_ref()
+                .map(|auth| (auth.username(), auth.password())),
+            Some(CustomProxy::HttpConnect(http_proxy)) => http_proxy
+                .auth
+                .as_ref()
+                .map(|auth| (auth.username(), auth.password())),
+            _ => None,
+        };
+        credentials
+            .map(|(username, password)| Self::create_credentials_file(username, password))
+            .transpose()
     }
 
     /// Starts a proxy service, as applicable.
@@ -13,6 +13,9 @@ pub enum Error {
 
     #[error("I/O error")]
     Io(io::Error),
+
+    #[error("Unsupported proxy configuration: {0}")]
+    Unsupported(&'static str),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -56,6 +59,17 @@ pub async fn start_proxy(
                 remote_settings.endpoint.port(),
             )?))
         }
+        CustomProxy::HttpConnect(http_settings) => {
+            // OpenVPN connects to the proxy itself, but does not support TLS to the proxy.
+            if http_settings.tls_server_name.is_some() {
+                return Err(Error::Unsupported(
+                    "OpenVPN does not support connecting to an HTTP proxy over TLS",
+                ));
+            }
+            Ok(Box::new(noop::NoopProxyMonitor::start(
+                http_settings.endpoint.port(),
+            )?))
+        }
         CustomProxy::Shadowsocks(ss_settings) => Ok(Box::new(
             ShadowsocksProxyMonitor::start(
                 ss_settings,
@@ -58,6 +58,10 @@ impl ShadowsocksProxyMonitor {
             .local
             .push(LocalInstanceConfig::with_local_config(local));
 
+        // `ServerConfig::new` panics if the key is invalid for the cipher
+        settings
+            .validate()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
         let server = ServerConfig::new(
             settings.endpoint,
             settings.password.clone(),
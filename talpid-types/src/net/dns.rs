@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    net::{IpAddr, SocketAddr},
+};
+
+/// Protocol used to reach an encrypted DNS resolver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptedDnsProtocol {
+    /// DNS-over-HTTPS (RFC 8484)
+    Https,
+    /// DNS-over-TLS (RFC 7858)
+    Tls,
+}
+
+impl EncryptedDnsProtocol {
+    /// The port used by the protocol unless something else is specified.
+    pub const fn default_port(self) -> u16 {
+        match self {
+            EncryptedDnsProtocol::Https => 443,
+            EncryptedDnsProtocol::Tls => 853,
+        }
+    }
+}
+
+impl fmt::Display for EncryptedDnsProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptedDnsProtocol::Https => f.write_str("DNS-over-HTTPS"),
+            EncryptedDnsProtocol::Tls => f.write_str("DNS-over-TLS"),
+        }
+    }
+}
+
+/// A DNS resolver that is reached using an encrypted protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EncryptedDnsUpstream {
+    pub protocol: EncryptedDnsProtocol,
+    pub address: SocketAddr,
+    /// Name that the certificate of the resolver must be valid for.
+    pub hostname: String,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum EncryptedDnsUpstreamParseError {
+    #[error("Missing hostname, expected ADDRESS[:PORT]#HOSTNAME")]
+    MissingHostname,
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+}
+
+impl EncryptedDnsUpstream {
+    /// Parse a resolver given as `ADDRESS[:PORT]#HOSTNAME`. IPv6 addresses must be enclosed in
+    /// brackets if a port is given. The port defaults to the standard port of `protocol`.
+    pub fn parse(
+        protocol: EncryptedDnsProtocol,
+        s: &str,
+    ) -> Result<Self, EncryptedDnsUpstreamParseError> {
+        let (address, hostname) = s
+            .split_once('#')
+            .filter(|(_, hostname)| !hostname.is_empty())
+            .ok_or(EncryptedDnsUpstreamParseError::MissingHostname)?;
+        let address = match address.parse::<SocketAddr>() {
+            Ok(address) => address,
+            Err(_) => address
+                .parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, protocol.default_port()))
+                .map_err(|_| EncryptedDnsUpstreamParseError::InvalidAddress(address.to_owned()))?,
+        };
+        Ok(EncryptedDnsUpstream {
+            protocol,
+            address,
+            hostname: hostname.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for EncryptedDnsUpstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{} ({})", self.address, self.hostname, self.protocol)
+    }
+}
+
+/// Resolvers to use for specific domains, keyed by domain. Queries for a domain or any of its
+/// subdomains are sent to its resolvers instead of the regular DNS servers. If several domains
+/// match, the longest one wins.
+pub type SplitDnsRoutes = BTreeMap<String, Vec<IpAddr>>;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Invalid domain: {0}")]
+pub struct InvalidDomainError(pub String);
+
+/// Normalize a domain for use in [SplitDnsRoutes]. A leading `*.` and a trailing `.` are removed,
+/// and the domain is converted to lowercase.
+pub fn normalize_domain(domain: &str) -> Result<String, InvalidDomainError> {
+    let normalized = domain
+        .trim_start_matches("*.")
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    let valid_label = |label: &str| {
+        (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if normalized.is_empty() || normalized.len() > 253 || !normalized.split('.').all(valid_label) {
+        return Err(InvalidDomainError(domain.to_owned()));
+    }
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_upstream() {
+        let upstream =
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Https, "192.0.2.1#dns.example.com")
+                .unwrap();
+        assert_eq!(upstream.address, "192.0.2.1:443".parse().unwrap());
+        assert_eq!(upstream.hostname, "dns.example.com");
+
+        let upstream =
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Tls, "[2001:db8::1]:8853#dns.test")
+                .unwrap();
+        assert_eq!(upstream.address, "[2001:db8::1]:8853".parse().unwrap());
+
+        let upstream =
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Tls, "2001:db8::1#dns.test").unwrap();
+        assert_eq!(upstream.address, "[2001:db8::1]:853".parse().unwrap());
+
+        assert_eq!(
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Tls, "192.0.2.1"),
+            Err(EncryptedDnsUpstreamParseError::MissingHostname)
+        );
+        assert_eq!(
+            EncryptedDnsUpstream::parse(EncryptedDnsProtocol::Tls, "dns.test#dns.test"),
+            Err(EncryptedDnsUpstreamParseError::InvalidAddress(
+                "dns.test".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_normalize_domain() {
+        assert_eq!(normalize_domain("corp.example").unwrap(), "corp.example");
+        assert_eq!(normalize_domain("*.Corp.Example.").unwrap(), "corp.example");
+        assert!(normalize_domain("").is_err());
+        assert!(normalize_domain("corp..example").is_err());
+        assert!(normalize_domain("-corp.example").is_err());
+        assert!(normalize_domain("corp example").is_err());
+    }
+}
@@ -14,6 +14,7 @@ use std::{
 
 use self::proxy::{CustomProxy, Socks5Local};
 
+pub mod dns;
 pub mod obfuscation;
 pub mod openvpn;
 pub mod proxy;
@@ -52,6 +53,161 @@ pub static ALLOWED_LAN_MULTICAST_NETS: LazyLock<[IpNetwork; 8]> = LazyLock::new(
     ]
 });
 
+/// Address ranges that custom LAN networks must lie within. In addition to the private and
+/// link-local ranges, this includes the RFC 6598 shared address space, which is used for
+/// carrier-grade NAT and by overlay networks such as Tailscale, and IPv6 unique local addresses.
+static CUSTOM_LAN_NET_RANGES: LazyLock<[IpNetwork; 7]> = LazyLock::new(|| {
+    [
+        IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(10, 0, 0, 0), 8).unwrap()),
+        IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(172, 16, 0, 0), 12).unwrap()),
+        IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(192, 168, 0, 0), 16).unwrap()),
+        IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(169, 254, 0, 0), 16).unwrap()),
+        IpNetwork::V4(Ipv4Network::new(Ipv4Addr::new(100, 64, 0, 0), 10).unwrap()),
+        IpNetwork::V6(Ipv6Network::new(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0), 10).unwrap()),
+        IpNetwork::V6(Ipv6Network::new(Ipv6Addr::new(0xfc00, 0, 0, 0, 0, 0, 0, 0), 7).unwrap()),
+    ]
+});
+
+/// User-defined networks that are allowed when "allow local network" is enabled, in addition to or
+/// instead of [`ALLOWED_LAN_NETS`]. Multicast and broadcast traffic to
+/// [`ALLOWED_LAN_MULTICAST_NETS`] is allowed regardless.
+///
+/// Custom networks must lie within local address ranges, since anything else would let traffic
+/// bypass the tunnel. Use [`AllowedLanNets::new`] to construct a validated instance. When
+/// deserializing, networks that are not local are dropped.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "RawAllowedLanNets")]
+pub struct AllowedLanNets {
+    /// Networks to allow.
+    custom_nets: Vec<IpNetwork>,
+    /// If true, only `custom_nets` are allowed. Otherwise, `custom_nets` are allowed in addition to
+    /// [`ALLOWED_LAN_NETS`].
+    replace_default: bool,
+}
+
+/// Unvalidated form of [`AllowedLanNets`], used when deserializing.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RawAllowedLanNets {
+    custom_nets: Vec<IpNetwork>,
+    replace_default: bool,
+}
+
+impl From<RawAllowedLanNets> for AllowedLanNets {
+    /// Drop anything that [`AllowedLanNets::new`] would reject, so that a single invalid network
+    /// does not prevent the remaining settings from being loaded.
+    fn from(raw: RawAllowedLanNets) -> Self {
+        if cfg!(windows) && (!raw.custom_nets.is_empty() || raw.replace_default) {
+            log::warn!(
+                "Ignoring custom local networks: {}",
+                InvalidAllowedLanNets::Unsupported
+            );
+            return Self::default();
+        }
+        let (custom_nets, rejected): (Vec<_>, Vec<_>) = raw
+            .custom_nets
+            .into_iter()
+            .partition(|net| Self::is_local_net(net));
+        for net in rejected {
+            log::warn!(
+                "Ignoring custom local network: {}",
+                InvalidAllowedLanNets::NotLocal(net)
+            );
+        }
+        Self {
+            custom_nets,
+            replace_default: raw.replace_default,
+        }
+    }
+}
+
+/// Error returned when custom LAN networks are rejected by [`AllowedLanNets::new`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidAllowedLanNets {
+    /// The network is not a private, shared, link-local or unique local network.
+    #[error("{0} is not a private, shared, link-local or unique local network")]
+    NotLocal(IpNetwork),
+    /// Custom LAN networks are not supported on this platform.
+    #[error("Custom local networks are not supported on this platform")]
+    Unsupported,
+}
+
+impl AllowedLanNets {
+    /// Create a new set of allowed LAN networks. Every network in `custom_nets` must be contained
+    /// in a private, shared (RFC 6598), link-local or unique local address range.
+    pub fn new(
+        custom_nets: Vec<IpNetwork>,
+        replace_default: bool,
+    ) -> Result<Self, InvalidAllowedLanNets> {
+        if cfg!(windows) && (!custom_nets.is_empty() || replace_default) {
+            return Err(InvalidAllowedLanNets::Unsupported);
+        }
+        if let Some(net) = custom_nets.iter().find(|net| !Self::is_local_net(net)) {
+            return Err(InvalidAllowedLanNets::NotLocal(*net));
+        }
+        Ok(Self {
+            custom_nets,
+            replace_default,
+        })
+    }
+
+    fn is_local_net(net: &IpNetwork) -> bool {
+        net.prefix() > 0
+            && CUSTOM_LAN_NET_RANGES.iter().any(|allowed| {
+                net.is_ipv4() == allowed.is_ipv4()
+                    && net.prefix() >= allowed.prefix()
+                    && allowed.contains(net.network())
+            })
+    }
+
+    /// User-defined networks to allow.
+    pub fn custom_nets(&self) -> &[IpNetwork] {
+        &self.custom_nets
+    }
+
+    /// Whether only [`Self::custom_nets`] are allowed, instead of also [`ALLOWED_LAN_NETS`].
+    pub fn replace_default(&self) -> bool {
+        self.replace_default
+    }
+
+    /// Return all unicast networks that should be allowed.
+    pub fn networks(&self) -> Vec<IpNetwork> {
+        let default_nets: &[IpNetwork] = if self.replace_default {
+            &[]
+        } else {
+            &*ALLOWED_LAN_NETS
+        };
+        let mut networks = default_nets.to_vec();
+        for net in &self.custom_nets {
+            if !networks.contains(net) {
+                networks.push(*net);
+            }
+        }
+        networks
+    }
+}
+
+impl fmt::Display for AllowedLanNets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.replace_default {
+            f.write_str("default private networks")?;
+            if self.custom_nets.is_empty() {
+                return Ok(());
+            }
+            f.write_str(", ")?;
+        } else if self.custom_nets.is_empty() {
+            return f.write_str("none");
+        }
+        let custom_nets = self
+            .custom_nets
+            .iter()
+            .map(|net| net.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        f.write_str(&custom_nets)
+    }
+}
+
 /// TunnelParameters are used to encapsulate all the data needed to start a tunnel. This is enum
 /// should be generated by implementations of the trait
 /// `talpid-core::tunnel_state_machine::TunnelParametersGenerator`
@@ -240,6 +396,7 @@ pub enum ObfuscationType {
     #[serde(rename = "udp2tcp")]
     Udp2Tcp,
     Shadowsocks,
+    Quic,
 }
 
 impl fmt::Display for ObfuscationType {
@@ -247,6 +404,7 @@ impl fmt::Display for ObfuscationType {
         match self {
             ObfuscationType::Udp2Tcp => "Udp2Tcp".fmt(f),
             ObfuscationType::Shadowsocks => "Shadowsocks".fmt(f),
+            ObfuscationType::Quic => "QUIC".fmt(f),
         }
     }
 }
@@ -261,7 +419,7 @@ pub struct ObfuscationEndpoint {
 impl From<&ObfuscatorConfig> for ObfuscationEndpoint {
     fn from(config: &ObfuscatorConfig) -> ObfuscationEndpoint {
         let (endpoint, obfuscation_type) = match config {
-            ObfuscatorConfig::Udp2Tcp { endpoint } => (
+            ObfuscatorConfig::Udp2Tcp { endpoint, .. } => (
                 Endpoint {
                     address: *endpoint,
                     protocol: TransportProtocol::Tcp,
@@ -275,6 +433,20 @@ impl From<&ObfuscatorConfig> for ObfuscationEndpoint {
                 },
                 ObfuscationType::Shadowsocks,
             ),
+            ObfuscatorConfig::CustomShadowsocks { server } => (
+                Endpoint {
+                    address: server.endpoint,
+                    protocol: TransportProtocol::Udp,
+                },
+                ObfuscationType::Shadowsocks,
+            ),
+            ObfuscatorConfig::Quic { endpoint, .. } => (
+                Endpoint {
+                    address: *endpoint,
+                    protocol: TransportProtocol::Udp,
+                },
+                ObfuscationType::Quic,
+            ),
         };
 
         ObfuscationEndpoint {
@@ -607,3 +779,39 @@ impl Connectivity {
         matches!(self, Connectivity::Status { ipv6: true, .. })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[cfg(not(windows))]
+    fn test_allowed_lan_nets_validation() {
+        let valid = [
+            "10.1.0.0/16",
+            "192.168.1.0/24",
+            "169.254.0.0/16",
+            "100.64.0.0/10",
+            "100.100.100.0/24",
+            "fc00::/7",
+            "fd00::/8",
+        ];
+        let valid: Vec<IpNetwork> = valid.iter().map(|net| net.parse().unwrap()).collect();
+        assert!(AllowedLanNets::new(valid, true).is_ok());
+
+        for invalid in [
+            "0.0.0.0/0",
+            "::/0",
+            "8.8.8.0/24",
+            "10.0.0.0/7",
+            "100.0.0.0/8",
+            "2001:db8::/32",
+        ] {
+            let net: IpNetwork = invalid.parse().unwrap();
+            assert_eq!(
+                AllowedLanNets::new(vec![net], false),
+                Err(InvalidAllowedLanNets::NotLocal(net)),
+            );
+        }
+    }
+}
@@ -1,18 +1,61 @@
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::{fmt, net::SocketAddr};
 
-use super::{Endpoint, TransportProtocol};
+use super::{proxy::Shadowsocks, Endpoint, TransportProtocol};
 
 #[derive(Clone, Eq, PartialEq, Deserialize, Serialize, Debug)]
 pub enum ObfuscatorConfig {
-    Udp2Tcp { endpoint: SocketAddr },
-    Shadowsocks { endpoint: SocketAddr },
+    Udp2Tcp {
+        endpoint: SocketAddr,
+        /// Frame datagrams as WebSocket messages over TLS.
+        #[serde(default)]
+        websocket: Option<WebSocketSettings>,
+    },
+    Shadowsocks {
+        endpoint: SocketAddr,
+    },
+    /// Shadowsocks obfuscation using a server not operated by Mullvad, which forwards traffic
+    /// to the WireGuard endpoint of the peer.
+    CustomShadowsocks {
+        server: Shadowsocks,
+    },
+    /// WireGuard packets are sent as QUIC datagrams, to look like HTTP/3.
+    Quic {
+        endpoint: SocketAddr,
+        /// Server name used for SNI and to verify the certificate of the server.
+        hostname: String,
+    },
+}
+
+/// Settings for carrying udp2tcp traffic as WebSocket messages over TLS, so that it looks like
+/// ordinary HTTPS WebSocket traffic.
+#[derive(Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Debug)]
+pub struct WebSocketSettings {
+    /// Server name used for SNI, the `Host` header and to verify the certificate of the server.
+    pub server_name: String,
+    /// Path of the WebSocket endpoint. Must start with `/`.
+    pub path: String,
+}
+
+impl WebSocketSettings {
+    /// Returns whether the server name is a valid DNS name or IP address, and the path is
+    /// absolute.
+    pub fn is_valid(&self) -> bool {
+        rustls_pki_types::ServerName::try_from(self.server_name.as_str()).is_ok()
+            && self.path.starts_with('/')
+    }
+}
+
+impl fmt::Display for WebSocketSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "wss://{}{}", self.server_name, self.path)
+    }
 }
 
 impl ObfuscatorConfig {
     pub fn get_obfuscator_endpoint(&self) -> Endpoint {
         match self {
-            ObfuscatorConfig::Udp2Tcp { endpoint } => Endpoint {
+            ObfuscatorConfig::Udp2Tcp { endpoint, .. } => Endpoint {
                 address: *endpoint,
                 protocol: TransportProtocol::Tcp,
             },
@@ -20,6 +63,14 @@ impl ObfuscatorConfig {
                 address: *endpoint,
                 protocol: TransportProtocol::Udp,
             },
+            ObfuscatorConfig::CustomShadowsocks { server } => Endpoint {
+                address: server.endpoint,
+                protocol: TransportProtocol::Udp,
+            },
+            ObfuscatorConfig::Quic { endpoint, .. } => Endpoint {
+                address: *endpoint,
+                protocol: TransportProtocol::Udp,
+            },
         }
     }
 }
@@ -1,4 +1,5 @@
 use crate::net::Endpoint;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::{Deserialize, Serialize};
 use std::{fmt, net::SocketAddr};
 
@@ -9,6 +10,14 @@ pub enum Error {
     /// Validation of SOCKS5 username or password failed.
     #[error("Invalid SOCKS5 authentication credentials: {0}")]
     InvalidSocksAuthValues(&'static str),
+
+    /// Validation of a Shadowsocks cipher or password failed.
+    #[error("Invalid Shadowsocks configuration: {0}")]
+    InvalidShadowsocksConfig(&'static str),
+
+    /// Validation of HTTP proxy username or password failed.
+    #[error("Invalid HTTP proxy authentication credentials: {0}")]
+    InvalidHttpAuthValues(&'static str),
 }
 
 /// Types of bridges that can be used to proxy a connection to a tunnel
@@ -44,6 +53,7 @@ pub enum CustomProxy {
     Shadowsocks(Shadowsocks),
     Socks5Local(Socks5Local),
     Socks5Remote(Socks5Remote),
+    HttpConnect(HttpConnect),
 }
 
 impl CustomProxy {
@@ -61,6 +71,10 @@ impl CustomProxy {
                 endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
                 proxy_type: ProxyType::Shadowsocks,
             },
+            CustomProxy::HttpConnect(settings) => ProxyEndpoint {
+                endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
+                proxy_type: ProxyType::Custom,
+            },
         }
     }
 }
@@ -83,6 +97,12 @@ impl From<Shadowsocks> for CustomProxy {
     }
 }
 
+impl From<HttpConnect> for CustomProxy {
+    fn from(value: HttpConnect) -> Self {
+        CustomProxy::HttpConnect(value)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Shadowsocks {
     pub endpoint: SocketAddr,
@@ -105,6 +125,94 @@ pub struct Socks5Remote {
     pub auth: Option<SocksAuth>,
 }
 
+/// A proxy server which tunnels TCP connections using the HTTP `CONNECT` method,
+/// as described in RFC 9110: <https://datatracker.ietf.org/doc/html/rfc9110#section-9.3.6>.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpConnect {
+    pub endpoint: SocketAddr,
+    pub auth: Option<HttpAuth>,
+    /// If set, the connection to the proxy itself is made using TLS, and the
+    /// certificate presented by the proxy is verified against this server name.
+    pub tls_server_name: Option<String>,
+}
+
+/// A valid HTTP basic authentication according to
+/// RFC 7617: <https://datatracker.ietf.org/doc/html/rfc7617>.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpAuth {
+    username: String,
+    password: String,
+}
+
+impl HttpAuth {
+    /// Validate an HTTP proxy username/password based authentication.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::HttpAuth;
+    ///
+    /// let valid_auth = HttpAuth::new("FooBar".to_string(), "hunter2".to_string());
+    /// assert!(valid_auth.is_ok());
+    /// ```
+    ///
+    /// The username may not be empty, and may not contain a colon.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::HttpAuth;
+    ///
+    /// let empty_username = HttpAuth::new("".to_string(), "hunter2".to_string());
+    /// assert!(empty_username.is_err());
+    ///
+    /// let colon_username = HttpAuth::new("Foo:Bar".to_string(), "hunter2".to_string());
+    /// assert!(colon_username.is_err());
+    /// ```
+    pub fn new(username: String, password: String) -> Result<Self, Error> {
+        if username.is_empty() {
+            return Err(Error::InvalidHttpAuthValues("Username may not be empty"));
+        }
+        if username.contains(':') {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username may not contain a colon",
+            ));
+        }
+        if username
+            .chars()
+            .chain(password.chars())
+            .any(char::is_control)
+        {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username and password may not contain control characters",
+            ));
+        }
+
+        Ok(HttpAuth { username, password })
+    }
+
+    /// Read the username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Read the password.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// Encode the credentials as the value of a `Proxy-Authorization` header.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::HttpAuth;
+    ///
+    /// let auth = HttpAuth::new("Aladdin".to_string(), "open sesame".to_string()).unwrap();
+    /// assert_eq!(auth.to_header_value(), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+    /// ```
+    pub fn to_header_value(&self) -> String {
+        let credentials = format!("{}:{}", self.username, self.password);
+        format!("Basic {}", STANDARD.encode(credentials))
+    }
+}
+
 /// A valid SOCKS5 username/password authentication according to
 /// RFC 1929: <https://datatracker.ietf.org/doc/html/rfc1929>.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -192,6 +300,109 @@ impl Shadowsocks {
             cipher,
         }
     }
+
+    /// Validate the cipher and password.
+    ///
+    /// # Examples
+    ///
+    /// The cipher has to be one of [`SHADOWSOCKS_CIPHERS`].
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::Shadowsocks;
+    ///
+    /// let endpoint = ([192, 0, 2, 1], 443);
+    /// let valid = Shadowsocks::new(endpoint, "aes-256-gcm".to_string(), "mullvad".to_string());
+    /// assert!(valid.validate().is_ok());
+    ///
+    /// let invalid_cipher = Shadowsocks::new(endpoint, "rot13".to_string(), "mullvad".to_string());
+    /// assert!(invalid_cipher.validate().is_err());
+    /// ```
+    ///
+    /// The 2022 edition ciphers do not take a password, but a base64 encoded key of the same
+    /// length as the key used by the cipher.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::Shadowsocks;
+    ///
+    /// let endpoint = ([192, 0, 2, 1], 443);
+    /// let cipher = "2022-blake3-aes-128-gcm".to_string();
+    ///
+    /// let key_128 = "AAAAAAAAAAAAAAAAAAAAAA==".to_string();
+    /// let valid = Shadowsocks::new(endpoint, cipher.clone(), key_128);
+    /// assert!(valid.validate().is_ok());
+    ///
+    /// let not_a_key = Shadowsocks::new(endpoint, cipher.clone(), "mullvad".to_string());
+    /// assert!(not_a_key.validate().is_err());
+    ///
+    /// let key_256 = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string();
+    /// let wrong_key_length = Shadowsocks::new(endpoint, cipher, key_256);
+    /// assert!(wrong_key_length.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        if !SHADOWSOCKS_CIPHERS.contains(&self.cipher.as_str()) {
+            return Err(Error::InvalidShadowsocksConfig("Unsupported cipher"));
+        }
+        self.validate_2022_key()
+    }
+
+    /// Validate the cipher and password for use as WireGuard obfuscation. Only the ciphers in
+    /// [`SHADOWSOCKS_AEAD_CIPHERS`] and [`SHADOWSOCKS_2022_CIPHERS`] are supported, since the
+    /// obfuscator does not implement stream ciphers.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::Shadowsocks;
+    ///
+    /// let endpoint = ([192, 0, 2, 1], 443);
+    /// let aead = Shadowsocks::new(endpoint, "aes-256-gcm".to_string(), "mullvad".to_string());
+    /// assert!(aead.validate_for_obfuscation().is_ok());
+    ///
+    /// let key_256 = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string();
+    /// let aead_2022 = Shadowsocks::new(endpoint, "2022-blake3-aes-256-gcm".to_string(), key_256);
+    /// assert!(aead_2022.validate_for_obfuscation().is_ok());
+    ///
+    /// let stream = Shadowsocks::new(endpoint, "aes-256-cfb".to_string(), "mullvad".to_string());
+    /// assert!(stream.validate().is_ok());
+    /// assert!(stream.validate_for_obfuscation().is_err());
+    /// ```
+    pub fn validate_for_obfuscation(&self) -> Result<(), Error> {
+        let cipher = self.cipher.as_str();
+        if SHADOWSOCKS_2022_CIPHERS.contains(&cipher) {
+            self.validate_2022_key()
+        } else if SHADOWSOCKS_AEAD_CIPHERS.contains(&cipher) {
+            Ok(())
+        } else {
+            Err(Error::InvalidShadowsocksConfig(
+                "Only AEAD ciphers are supported for obfuscation",
+            ))
+        }
+    }
+
+    /// Check that the password of a 2022 edition cipher is a key of the right length. Other
+    /// ciphers accept any password.
+    fn validate_2022_key(&self) -> Result<(), Error> {
+        if let Some(key_len) = shadowsocks_2022_key_len(&self.cipher) {
+            let key = STANDARD.decode(&self.password).map_err(|_| {
+                Error::InvalidShadowsocksConfig(
+                    "The password of a 2022 cipher should be a base64 encoded key",
+                )
+            })?;
+            if key.len() != key_len {
+                return Err(Error::InvalidShadowsocksConfig(
+                    "The key length does not match the cipher",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Key length in bytes of the 2022 edition Shadowsocks ciphers.
+fn shadowsocks_2022_key_len(cipher: &str) -> Option<usize> {
+    match cipher {
+        "2022-blake3-aes-128-gcm" => Some(16),
+        "2022-blake3-aes-256-gcm" | "2022-blake3-chacha20-poly1305" => Some(32),
+        _ => None,
+    }
 }
 
 impl Socks5Local {
@@ -233,8 +444,30 @@ impl Socks5Remote {
     }
 }
 
+impl HttpConnect {
+    pub fn new<I: Into<SocketAddr>>(endpoint: I) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            auth: None,
+            tls_server_name: None,
+        }
+    }
+
+    /// Authenticate against the proxy using HTTP basic authentication.
+    pub fn with_authentication(mut self, authentication: HttpAuth) -> Self {
+        self.auth = Some(authentication);
+        self
+    }
+
+    /// Connect to the proxy using TLS, verifying it as `server_name`.
+    pub fn with_tls(mut self, server_name: String) -> Self {
+        self.tls_server_name = Some(server_name);
+        self
+    }
+}
+
 /// List of ciphers usable by a Shadowsocks proxy.
-pub const SHADOWSOCKS_CIPHERS: [&str; 19] = [
+pub const SHADOWSOCKS_CIPHERS: [&str; 22] = [
     // Stream ciphers.
     "aes-128-cfb",
     "aes-128-cfb1",
@@ -256,4 +489,26 @@ pub const SHADOWSOCKS_CIPHERS: [&str; 19] = [
     "xchacha20-ietf-poly1305",
     "aes-128-pmac-siv",
     "aes-256-pmac-siv",
+    // AEAD ciphers from the 2022 edition of the protocol.
+    "2022-blake3-aes-128-gcm",
+    "2022-blake3-aes-256-gcm",
+    "2022-blake3-chacha20-poly1305",
+];
+
+/// AEAD ciphers from [`SHADOWSOCKS_CIPHERS`], excluding the 2022 edition ones.
+pub const SHADOWSOCKS_AEAD_CIPHERS: [&str; 6] = [
+    "aes-128-gcm",
+    "aes-256-gcm",
+    "chacha20-ietf-poly1305",
+    "xchacha20-ietf-poly1305",
+    "aes-128-pmac-siv",
+    "aes-256-pmac-siv",
+];
+
+/// AEAD ciphers from the 2022 edition of the protocol. These take a base64 encoded key instead
+/// of a password.
+pub const SHADOWSOCKS_2022_CIPHERS: [&str; 3] = [
+    "2022-blake3-aes-128-gcm",
+    "2022-blake3-aes-256-gcm",
+    "2022-blake3-chacha20-poly1305",
 ];
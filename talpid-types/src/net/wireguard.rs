@@ -6,6 +6,7 @@ use std::{
     cmp, fmt,
     hash::{Hash, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, SystemTime},
 };
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -96,6 +97,34 @@ pub struct TunnelOptions {
     pub daita: bool,
 }
 
+/// Statistics about a running WireGuard tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TunnelStatistics {
+    pub peers: Vec<PeerStatistics>,
+    /// Packets sent on the tunnel interface, if reported by the OS
+    pub tx_packets: Option<u64>,
+    /// Packets received on the tunnel interface, if reported by the OS
+    pub rx_packets: Option<u64>,
+    /// Current MTU of the tunnel interface
+    pub mtu: u16,
+    /// Round-trip times of the most recent pings sent by the connectivity check, oldest first
+    pub ping_rtts: Vec<Duration>,
+    /// Time since the tunnel was established
+    pub uptime: Duration,
+}
+
+/// Statistics about a single peer in a WireGuard tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PeerStatistics {
+    pub public_key: PublicKey,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    /// Time of the most recent handshake, if any
+    pub last_handshake: Option<SystemTime>,
+    /// Number of handshakes observed since the tunnel was started
+    pub handshakes: u64,
+}
+
 /// Wireguard x25519 private key
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct PrivateKey(x25519_dalek::StaticSecret);
@@ -262,7 +291,8 @@ impl fmt::Display for PublicKey {
     }
 }
 
-/// A WireGuard preshared key (PSK). Used to make the tunnel quantum-resistant.
+/// A WireGuard preshared key (PSK). Used to make the tunnel quantum-resistant, or provided by
+/// the user as part of a custom tunnel endpoint.
 #[derive(Clone, PartialEq, Eq, Hash, Zeroize, ZeroizeOnDrop)]
 pub struct PresharedKey(Box<[u8; 32]>);
 
@@ -273,6 +303,20 @@ impl PresharedKey {
     pub fn as_bytes(&self) -> &[u8; 32] {
         &self.0
     }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.as_bytes())
+    }
+
+    pub fn from_base64(key: &str) -> Result<Self, InvalidKey> {
+        key_from_base64(key)
+    }
+}
+
+impl From<[u8; 32]> for PresharedKey {
+    fn from(key: [u8; 32]) -> PresharedKey {
+        PresharedKey(Box::new(key))
+    }
 }
 
 impl From<Box<[u8; 32]>> for PresharedKey {
@@ -287,6 +331,24 @@ impl fmt::Debug for PresharedKey {
     }
 }
 
+impl Serialize for PresharedKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_key(self.as_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PresharedKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_key(deserializer)
+    }
+}
+
 fn serialize_key<S>(key: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -1,6 +1,6 @@
 use std::net::Ipv4Addr;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio::time::Instant;
@@ -9,7 +9,7 @@ use super::constants::*;
 use super::error::Error;
 use super::pinger;
 
-use crate::stats::StatsMap;
+use crate::stats::{StatsMap, StatsRecorder};
 #[cfg(target_os = "android")]
 use crate::Tunnel;
 use crate::{TunnelError, TunnelType};
@@ -42,6 +42,7 @@ pub struct Check {
     ping_state: PingState,
     cancel_receiver: CancelReceiver,
     retry_attempt: u32,
+    stats_recorder: Arc<Mutex<StatsRecorder>>,
 }
 
 /// A handle that can be used to shut down the connectivity monitor.
@@ -100,6 +101,7 @@ impl Check {
         #[cfg(any(target_os = "macos", target_os = "linux"))] interface: String,
         retry_attempt: u32,
         cancel_receiver: CancelReceiver,
+        stats_recorder: Arc<Mutex<StatsRecorder>>,
     ) -> Result<Check, Error> {
         Ok(Check {
             conn_state: ConnState::new(Instant::now(), Default::default()),
@@ -110,6 +112,7 @@ impl Check {
             )?,
             retry_attempt,
             cancel_receiver,
+            stats_recorder,
         })
     }
 
@@ -123,6 +126,7 @@ impl Check {
                 ping_state,
                 retry_attempt: 0,
                 cancel_receiver,
+                stats_recorder: Arc::new(Mutex::new(StatsRecorder::new(0))),
             },
             cancel_token,
         )
@@ -176,6 +180,7 @@ impl Check {
                 if Self::check_connectivity_interval(
                     &mut self.conn_state,
                     &mut self.ping_state,
+                    &self.stats_recorder,
                     Instant::now(),
                     check_timeout,
                     tunnel_handle,
@@ -231,6 +236,7 @@ impl Check {
         Self::check_connectivity_interval(
             &mut self.conn_state,
             &mut self.ping_state,
+            &self.stats_recorder,
             now,
             PING_TIMEOUT,
             tunnel_handle,
@@ -242,6 +248,7 @@ impl Check {
     async fn check_connectivity_interval(
         conn_state: &mut ConnState,
         ping_state: &mut PingState,
+        stats_recorder: &Mutex<StatsRecorder>,
         now: Instant,
         timeout: Duration,
         tunnel_handle: &TunnelType,
@@ -252,6 +259,14 @@ impl Check {
         {
             None => Ok(false),
             Some(new_stats) => {
+                {
+                    let mut stats_recorder = stats_recorder.lock().unwrap();
+                    stats_recorder.record_stats(&new_stats);
+                    for rtt in ping_state.pinger.take_rtts() {
+                        stats_recorder.record_ping_rtt(rtt);
+                    }
+                }
+
                 if conn_state.update(now, new_stats) {
                     ping_state.reset().await;
                     return Ok(true);
@@ -517,6 +532,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake: None,
             },
         );
         conn_state.update(Instant::now(), stats);
@@ -542,6 +558,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake: None,
             },
         );
         conn_state.update(connect_time, stats);
@@ -566,6 +583,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake: None,
             },
         );
         conn_state.update(start, stats);
@@ -577,6 +595,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 1,
+                last_handshake: None,
             },
         );
         conn_state.update(update_time, stats);
@@ -658,6 +677,7 @@ mod test {
                     Stats {
                         tx_bytes: 0,
                         rx_bytes: 0,
+                        last_handshake: None,
                     },
                 );
                 MockTunnel::new(move || Ok(tunnel_stats.clone())).boxed()
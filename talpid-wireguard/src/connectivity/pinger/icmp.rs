@@ -4,12 +4,15 @@ use socket2::{Domain, Protocol, Socket, Type};
 use tokio::net::UdpSocket;
 
 use std::{
+    collections::VecDeque,
     io::{self, Write},
     net::{Ipv4Addr, SocketAddr},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 const SEND_RETRY_ATTEMPTS: u32 = 10;
+/// Number of sent pings to remember when matching replies.
+const MAX_PENDING_PINGS: usize = 16;
 
 /// Pinger errors
 #[derive(thiserror::Error, Debug)]
@@ -56,6 +59,8 @@ pub struct Pinger {
     addr: SocketAddr,
     id: u16,
     seq: u16,
+    /// Sequence numbers and send times of pings that have not been answered
+    pending: VecDeque<(u16, Instant)>,
 }
 
 impl Pinger {
@@ -92,6 +97,7 @@ impl Pinger {
             addr,
             id: rand::random(),
             seq: 0,
+            pending: VecDeque::with_capacity(MAX_PENDING_PINGS),
         })
     }
 
@@ -147,11 +153,67 @@ fn should_retry_send(_err: &io::Error) -> bool {
 impl super::Pinger for Pinger {
     async fn send_icmp(&mut self) -> Result<()> {
         let mut message = [0u8; 50];
+        let seq = self.seq;
         self.construct_icmpv4_packet(&mut message)?;
-        self.send_ping_request(&message, self.addr).await
+        self.send_ping_request(&message, self.addr).await?;
+
+        if self.pending.len() == MAX_PENDING_PINGS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((seq, Instant::now()));
+        Ok(())
+    }
+
+    fn take_rtts(&mut self) -> Vec<Duration> {
+        let mut rtts = vec![];
+        let mut buffer = [0u8; 1500];
+        loop {
+            let (len, source) = match self.sock.try_recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error) => {
+                    if error.kind() != io::ErrorKind::WouldBlock {
+                        log::trace!("Failed to read ICMP socket: {error}");
+                    }
+                    break;
+                }
+            };
+            if source.ip() != self.addr.ip() {
+                continue;
+            }
+            let Some((id, seq)) = parse_echo_reply(&buffer[..len]) else {
+                continue;
+            };
+            // Datagram ICMP sockets replace the identifier with their own
+            if !cfg!(target_os = "android") && id != self.id {
+                continue;
+            }
+            if let Some(index) = self.pending.iter().position(|(sent, _)| *sent == seq) {
+                if let Some((_, sent_at)) = self.pending.remove(index) {
+                    rtts.push(sent_at.elapsed());
+                }
+            }
+        }
+        rtts
     }
 }
 
+/// Return the identifier and sequence number of an ICMP echo reply. Packets read from raw sockets
+/// begin with an IPv4 header, which is skipped.
+fn parse_echo_reply(packet: &[u8]) -> Option<(u16, u16)> {
+    let icmp = match packet.first()? >> 4 {
+        4 => packet.get(usize::from(packet[0] & 0x0f) * 4..)?,
+        _ => packet,
+    };
+    // ICMP type - Echo (ping) reply, code 0
+    if icmp.len() < 8 || icmp[0] != 0x00 || icmp[1] != 0x00 {
+        return None;
+    }
+    Some((
+        u16::from_be_bytes([icmp[4], icmp[5]]),
+        u16::from_be_bytes([icmp[6], icmp[7]]),
+    ))
+}
+
 trait PayloadWriter {
     fn packet_id(&mut self) -> u16;
     fn sequence_num(&mut self) -> u16;
@@ -258,6 +320,34 @@ mod test {
         assert_eq!(buffer, expected_packet);
     }
 
+    #[test]
+    fn test_parse_echo_reply() {
+        let reply = [
+            // ICMP type - echo reply
+            0x00, // Code 0
+            0x00, // checksum
+            0x44, 0x70, // packet ID
+            0x1d, 0xcd, // sequence number
+            0x00, 0x01, // payload
+            0xb6, 0xe0,
+        ];
+        assert_eq!(parse_echo_reply(&reply), Some((0x1dcd, 0x0001)));
+
+        // Raw sockets also return the IPv4 header
+        let mut packet = vec![
+            0x45, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00, 0x00, 0x40, 0x01, 0x00, 0x00, 0x0a, 0x40,
+            0x00, 0x01, 0x0a, 0x40, 0x00, 0x02,
+        ];
+        packet.extend_from_slice(&reply);
+        assert_eq!(parse_echo_reply(&packet), Some((0x1dcd, 0x0001)));
+
+        // Echo requests are ignored
+        let mut request = reply;
+        request[0] = 0x08;
+        assert_eq!(parse_echo_reply(&request), None);
+        assert_eq!(parse_echo_reply(&reply[..6]), None);
+    }
+
     #[test]
     fn test_icmpv4_packet_too_short() {
         assert!(!construct_icmpv4_packet_inner(
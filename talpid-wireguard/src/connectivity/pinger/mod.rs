@@ -1,5 +1,7 @@
 mod icmp;
 
+use std::time::Duration;
+
 pub use icmp::Error;
 
 /// Trait for sending ICMP requests to get some traffic from a remote server
@@ -9,6 +11,11 @@ pub trait Pinger: Send {
     async fn send_icmp(&mut self) -> Result<(), Error>;
     /// Clears all resources used by the pinger.
     async fn reset(&mut self) {}
+    /// Returns the round-trip times of replies received since the last call, without waiting
+    /// for more replies.
+    fn take_rtts(&mut self) -> Vec<Duration> {
+        Vec::new()
+    }
 }
 
 /// Create a new pinger
@@ -7,6 +7,7 @@ use self::config::Config;
 use futures::channel::mpsc;
 use futures::future::Future;
 use obfuscation::ObfuscatorHandle;
+use stats::StatsRecorder;
 #[cfg(target_os = "android")]
 use std::borrow::Cow;
 #[cfg(windows)]
@@ -45,6 +46,7 @@ mod ephemeral;
 mod logging;
 mod obfuscation;
 mod stats;
+pub use stats::TunnelStatsHandle;
 #[cfg(wireguard_go)]
 mod wireguard_go;
 #[cfg(target_os = "linux")]
@@ -149,6 +151,7 @@ pub struct WireguardMonitor {
     close_msg_receiver: sync_mpsc::Receiver<CloseMsg>,
     pinger_stop_sender: connectivity::CancelToken,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
+    stats: TunnelStatsHandle,
 }
 
 #[cfg(any(target_os = "linux", target_os = "windows"))]
@@ -160,6 +163,11 @@ static FORCE_USERSPACE_WIREGUARD: LazyLock<bool> = LazyLock::new(|| {
 });
 
 impl WireguardMonitor {
+    /// Return a handle that can be used to query statistics about the tunnel.
+    pub fn stats_handle(&self) -> TunnelStatsHandle {
+        self.stats.clone()
+    }
+
     /// Starts a WireGuard tunnel with the given config
     #[cfg(not(target_os = "android"))]
     pub fn start(
@@ -212,6 +220,7 @@ impl WireguardMonitor {
 
         let obfuscator = Arc::new(AsyncMutex::new(obfuscator));
 
+        let stats_recorder = Arc::new(Mutex::new(StatsRecorder::new(config.mtu)));
         let gateway = config.ipv4_gateway;
         let (cancel_token, cancel_receiver) = connectivity::CancelToken::new();
         let mut connectivity_monitor = connectivity::Check::new(
@@ -220,12 +229,15 @@ impl WireguardMonitor {
             iface_name.clone(),
             args.retry_attempt,
             cancel_receiver,
+            stats_recorder.clone(),
         )
         .map_err(Error::ConnectivityMonitorError)?;
 
+        let tunnel = Arc::new(AsyncMutex::new(Some(tunnel)));
         let monitor = WireguardMonitor {
             runtime: args.runtime.clone(),
-            tunnel: Arc::new(AsyncMutex::new(Some(tunnel))),
+            stats: TunnelStatsHandle::new(&tunnel, stats_recorder.clone(), iface_name.clone()),
+            tunnel,
             event_hook: args.event_hook.clone(),
             close_msg_receiver: close_obfs_listener,
             pinger_stop_sender: cancel_token,
@@ -300,6 +312,7 @@ impl WireguardMonitor {
             if detect_mtu {
                 let config = config.clone();
                 let iface_name = iface_name.clone();
+                let stats_recorder = stats_recorder.clone();
                 tokio::task::spawn(async move {
                     if config.daita {
                         // TODO: For now, we assume the MTU during the tunnel lifetime.
@@ -308,7 +321,7 @@ impl WireguardMonitor {
                         return;
                     }
 
-                    if let Err(e) = mtu_detection::automatic_mtu_correction(
+                    match mtu_detection::automatic_mtu_correction(
                         gateway,
                         iface_name,
                         config.mtu,
@@ -317,12 +330,15 @@ impl WireguardMonitor {
                     )
                     .await
                     {
-                        log::error!(
-                            "{}",
-                            e.display_chain_with_msg(
-                                "Failed to automatically adjust MTU based on dropped packets"
-                            )
-                        );
+                        Ok(mtu) => stats_recorder.lock().unwrap().set_mtu(mtu),
+                        Err(e) => {
+                            log::error!(
+                                "{}",
+                                e.display_chain_with_msg(
+                                    "Failed to automatically adjust MTU based on dropped packets"
+                                )
+                            );
+                        }
                     };
                 });
             }
@@ -355,6 +371,7 @@ impl WireguardMonitor {
                 .map_err(Error::SetupRoutingError)
                 .map_err(CloseMsg::SetupError)?;
 
+            stats_recorder.lock().unwrap().set_up();
             let metadata = Self::tunnel_metadata(&iface_name, &config);
             event_hook.on_event(TunnelEvent::Up(metadata)).await;
 
@@ -424,11 +441,13 @@ impl WireguardMonitor {
 
         let should_negotiate_ephemeral_peer = config.quantum_resistant || config.daita;
 
+        let stats_recorder = Arc::new(Mutex::new(StatsRecorder::new(config.mtu)));
         let (cancel_token, cancel_receiver) = connectivity::CancelToken::new();
         let connectivity_check = connectivity::Check::new(
             config.ipv4_gateway,
             args.retry_attempt,
             cancel_receiver.clone(),
+            stats_recorder.clone(),
         )
         .map_err(Error::ConnectivityMonitorError)?;
 
@@ -449,6 +468,7 @@ impl WireguardMonitor {
         let mut event_hook = args.event_hook;
         let monitor = WireguardMonitor {
             runtime: args.runtime.clone(),
+            stats: TunnelStatsHandle::new(&tunnel, stats_recorder.clone(), iface_name.clone()),
             tunnel: Arc::clone(&tunnel),
             event_hook: event_hook.clone(),
             close_msg_receiver: close_obfs_listener,
@@ -498,6 +518,7 @@ impl WireguardMonitor {
                     .await;
             }
 
+            stats_recorder.lock().unwrap().set_up();
             let metadata = Self::tunnel_metadata(&iface_name, &config);
             event_hook.on_event(TunnelEvent::Up(metadata)).await;
 
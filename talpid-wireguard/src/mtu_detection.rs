@@ -37,7 +37,7 @@ const PING_OFFSET_TIMEOUT: Duration = Duration::from_secs(2);
 const MTU_STEP_SIZE: u16 = 20;
 
 /// Verify that the current MTU doesn't cause dropped packets, otherwise lower it to the
-/// largest value which doesn't.
+/// largest value which doesn't. Returns the resulting MTU.
 ///
 /// Note: This does not take fragmentation into account, so it should only be used as an extra
 /// safety measure after the normal MTU calculation using header sizes and safety margins.
@@ -46,7 +46,7 @@ pub async fn automatic_mtu_correction(
     iface_name: String,
     current_tunnel_mtu: u16,
     #[cfg(windows)] ipv6: bool,
-) -> Result<(), Error> {
+) -> Result<u16, Error> {
     log::debug!("Starting MTU detection");
     let verified_mtu = detect_mtu(
         gateway,
@@ -66,7 +66,7 @@ pub async fn automatic_mtu_correction(
     } else {
         log::debug!("MTU {verified_mtu} verified to not drop packets");
     };
-    Ok(())
+    Ok(verified_mtu)
 }
 
 #[cfg(windows)]
@@ -13,7 +13,7 @@ use talpid_tunnel::tun_provider::TunProvider;
 use talpid_types::{net::obfuscation::ObfuscatorConfig, ErrorExt};
 
 use tunnel_obfuscation::{
-    create_obfuscator, shadowsocks, udp2tcp, Settings as ObfuscationSettings,
+    create_obfuscator, quic, shadowsocks, udp2tcp, Settings as ObfuscationSettings,
 };
 
 /// Begin running obfuscation machine, if configured. This function will patch `config`'s endpoint
@@ -29,6 +29,7 @@ pub async fn apply_obfuscation_config(
 
     let settings = settings_from_config(
         obfuscator_config,
+        config.entry_peer.endpoint,
         #[cfg(target_os = "linux")]
         config.fwmark,
     );
@@ -76,15 +77,27 @@ fn patch_endpoint(config: &mut Config, endpoint: SocketAddr) {
 
 fn settings_from_config(
     config: &ObfuscatorConfig,
+    peer_endpoint: SocketAddr,
     #[cfg(target_os = "linux")] fwmark: Option<u32>,
 ) -> ObfuscationSettings {
     match config {
-        ObfuscatorConfig::Udp2Tcp { endpoint } => ObfuscationSettings::Udp2Tcp(udp2tcp::Settings {
+        ObfuscatorConfig::Udp2Tcp {
+            endpoint,
+            websocket,
+        } => ObfuscationSettings::Udp2Tcp(udp2tcp::Settings {
             peer: *endpoint,
+            websocket: websocket
+                .as_ref()
+                .map(|websocket| udp2tcp::websocket::Settings {
+                    server_name: websocket.server_name.clone(),
+                    path: websocket.path.clone(),
+                    extra_root_certificate: None,
+                }),
             #[cfg(target_os = "linux")]
             fwmark,
         }),
         ObfuscatorConfig::Shadowsocks { endpoint } => {
+            // Mullvad's Shadowsocks servers forward traffic to WireGuard on the same host
             ObfuscationSettings::Shadowsocks(shadowsocks::Settings {
                 shadowsocks_endpoint: *endpoint,
                 wireguard_endpoint: if endpoint.is_ipv4() {
@@ -92,6 +105,27 @@ fn settings_from_config(
                 } else {
                     SocketAddr::from((Ipv6Addr::LOCALHOST, 51820))
                 },
+                cipher: shadowsocks::MULLVAD_CIPHER.to_owned(),
+                password: shadowsocks::MULLVAD_PASSWORD.to_owned(),
+                #[cfg(target_os = "linux")]
+                fwmark,
+            })
+        }
+        ObfuscatorConfig::CustomShadowsocks { server } => {
+            ObfuscationSettings::Shadowsocks(shadowsocks::Settings {
+                shadowsocks_endpoint: server.endpoint,
+                wireguard_endpoint: peer_endpoint,
+                cipher: server.cipher.clone(),
+                password: server.password.clone(),
+                #[cfg(target_os = "linux")]
+                fwmark,
+            })
+        }
+        ObfuscatorConfig::Quic { endpoint, hostname } => {
+            ObfuscationSettings::Quic(quic::Settings {
+                peer: *endpoint,
+                hostname: hostname.clone(),
+                extra_root_certificate: None,
                 #[cfg(target_os = "linux")]
                 fwmark,
             })
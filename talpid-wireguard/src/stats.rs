@@ -1,9 +1,298 @@
-/// Contains bytes sent and received through a tunnel
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    io,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant, SystemTime},
+};
+
+use talpid_types::{
+    net::wireguard::{PeerStatistics, TunnelStatistics},
+    ErrorExt,
+};
+use tokio::sync::Mutex as AsyncMutex;
+
+#[cfg(target_os = "android")]
+use crate::Tunnel;
+use crate::TunnelType;
+
+/// Number of connectivity check ping round-trip times to remember.
+const MAX_PING_RTTS: usize = 10;
+
+/// Contains bytes sent and received through a tunnel, and the time of the latest handshake
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Stats {
     pub tx_bytes: u64,
     pub rx_bytes: u64,
+    pub last_handshake: Option<SystemTime>,
 }
 
 /// A map from peer pubkeys to peer stats.
-pub type StatsMap = std::collections::HashMap<[u8; 32], Stats>;
+pub type StatsMap = HashMap<[u8; 32], Stats>;
+
+/// Tunnel statistics that the WireGuard implementations do not keep track of themselves. They are
+/// recorded by the tunnel monitor while the tunnel is running.
+#[derive(Debug)]
+pub struct StatsRecorder {
+    /// Latest handshake time and number of handshakes seen for each peer
+    handshakes: HashMap<[u8; 32], (SystemTime, u64)>,
+    ping_rtts: VecDeque<Duration>,
+    mtu: u16,
+    /// When the tunnel was established
+    up_since: Option<Instant>,
+}
+
+impl StatsRecorder {
+    pub fn new(mtu: u16) -> Self {
+        StatsRecorder {
+            handshakes: HashMap::new(),
+            ping_rtts: VecDeque::with_capacity(MAX_PING_RTTS),
+            mtu,
+            up_since: None,
+        }
+    }
+
+    /// Count the handshakes that have occurred since `record_stats` was last called. Handshakes
+    /// are only counted if this is called more often than handshakes occur.
+    pub fn record_stats(&mut self, stats: &StatsMap) {
+        for (peer, stats) in stats {
+            let Some(last_handshake) = stats.last_handshake else {
+                continue;
+            };
+            match self.handshakes.entry(*peer) {
+                Entry::Occupied(mut entry) => {
+                    let (time, count) = entry.get_mut();
+                    if *time != last_handshake {
+                        *time = last_handshake;
+                        *count += 1;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert((last_handshake, 1));
+                }
+            }
+        }
+    }
+
+    pub fn record_ping_rtt(&mut self, rtt: Duration) {
+        if self.ping_rtts.len() == MAX_PING_RTTS {
+            self.ping_rtts.pop_front();
+        }
+        self.ping_rtts.push_back(rtt);
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub fn set_mtu(&mut self, mtu: u16) {
+        self.mtu = mtu;
+    }
+
+    /// Mark the tunnel as established.
+    pub fn set_up(&mut self) {
+        self.up_since = Some(Instant::now());
+    }
+
+    fn statistics(
+        &self,
+        stats: &StatsMap,
+        packets: Option<(u64, u64)>,
+    ) -> Option<TunnelStatistics> {
+        let up_since = self.up_since?;
+        let peers = stats
+            .iter()
+            .map(|(peer, stats)| PeerStatistics {
+                public_key: (*peer).into(),
+                tx_bytes: stats.tx_bytes,
+                rx_bytes: stats.rx_bytes,
+                last_handshake: stats.last_handshake,
+                handshakes: self.handshakes.get(peer).map_or(0, |(_, count)| *count),
+            })
+            .collect();
+        Some(TunnelStatistics {
+            peers,
+            tx_packets: packets.map(|(tx_packets, _)| tx_packets),
+            rx_packets: packets.map(|(_, rx_packets)| rx_packets),
+            mtu: self.mtu,
+            ping_rtts: self.ping_rtts.iter().copied().collect(),
+            uptime: up_since.elapsed(),
+        })
+    }
+}
+
+/// Handle used to query statistics about a running tunnel.
+#[derive(Clone)]
+pub struct TunnelStatsHandle {
+    tunnel: Weak<AsyncMutex<Option<TunnelType>>>,
+    recorder: Arc<Mutex<StatsRecorder>>,
+    interface_name: String,
+}
+
+impl TunnelStatsHandle {
+    pub(crate) fn new(
+        tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+        recorder: Arc<Mutex<StatsRecorder>>,
+        interface_name: String,
+    ) -> Self {
+        TunnelStatsHandle {
+            tunnel: Arc::downgrade(tunnel),
+            recorder,
+            interface_name,
+        }
+    }
+
+    /// Return statistics about the tunnel, or `None` if the tunnel is not up.
+    pub async fn get(&self) -> Option<TunnelStatistics> {
+        if self.recorder.lock().unwrap().up_since.is_none() {
+            return None;
+        }
+
+        let tunnel = self.tunnel.upgrade()?;
+        let stats = {
+            let lock = tunnel.lock().await;
+            lock.as_ref()?
+                .get_tunnel_stats()
+                .await
+                .inspect_err(|error| {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to obtain tunnel stats")
+                    );
+                })
+                .ok()?
+        };
+
+        let packets = interface_packets(&self.interface_name)
+            .inspect_err(|error| {
+                log::debug!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to obtain tunnel packet counters")
+                );
+            })
+            .ok();
+
+        let mut recorder = self.recorder.lock().unwrap();
+        recorder.record_stats(&stats);
+        recorder.statistics(&stats, packets)
+    }
+}
+
+/// Return the number of packets sent and received on an interface.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn interface_packets(interface: &str) -> io::Result<(u64, u64)> {
+    let read_counter = |counter: &str| -> io::Result<u64> {
+        let path = format!("/sys/class/net/{interface}/statistics/{counter}");
+        std::fs::read_to_string(path)?
+            .trim()
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    };
+    Ok((read_counter("tx_packets")?, read_counter("rx_packets")?))
+}
+
+/// Return the number of packets sent and received on an interface.
+#[cfg(target_os = "macos")]
+fn interface_packets(interface: &str) -> io::Result<(u64, u64)> {
+    use std::ffi::CStr;
+
+    let mut addrs = std::ptr::null_mut();
+    // SAFETY: `addrs` is a valid pointer to store the list in
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut result = Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "Interface not found",
+    ));
+    let mut current = addrs;
+    while !current.is_null() {
+        // SAFETY: `current` is an entry in the list returned by `getifaddrs`
+        let entry = unsafe { &*current };
+        current = entry.ifa_next;
+
+        if entry.ifa_addr.is_null() || entry.ifa_data.is_null() {
+            continue;
+        }
+        // SAFETY: `ifa_addr` is not null
+        if i32::from(unsafe { (*entry.ifa_addr).sa_family }) != libc::AF_LINK {
+            continue;
+        }
+        // SAFETY: `ifa_name` is a null-terminated string
+        if unsafe { CStr::from_ptr(entry.ifa_name) }.to_bytes() != interface.as_bytes() {
+            continue;
+        }
+        // SAFETY: `ifa_data` points to an `if_data` struct for `AF_LINK` entries
+        let data = unsafe { &*(entry.ifa_data as *const libc::if_data) };
+        result = Ok((u64::from(data.ifi_opackets), u64::from(data.ifi_ipackets)));
+        break;
+    }
+
+    // SAFETY: `addrs` was returned by `getifaddrs` and is not used after this
+    unsafe { libc::freeifaddrs(addrs) };
+    result
+}
+
+/// Return the number of packets sent and received on an interface.
+#[cfg(windows)]
+fn interface_packets(interface: &str) -> io::Result<(u64, u64)> {
+    use talpid_types::win32_err;
+    use windows_sys::Win32::NetworkManagement::IpHelper::{GetIfEntry2, MIB_IF_ROW2};
+
+    // SAFETY: MIB_IF_ROW2 is made up entirely of types for which all zeros is a valid value.
+    let mut row: MIB_IF_ROW2 = unsafe { std::mem::zeroed() };
+    row.InterfaceLuid = talpid_windows::net::luid_from_alias(interface)?;
+
+    // SAFETY: GetIfEntry2 reads `row.InterfaceLuid`, which is valid, and populates the struct.
+    win32_err!(unsafe { GetIfEntry2(&mut row) })?;
+
+    Ok((
+        row.OutUcastPkts + row.OutNUcastPkts,
+        row.InUcastPkts + row.InNUcastPkts,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_count_handshakes() {
+        let peer = [0u8; 32];
+        let first = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        let second = SystemTime::UNIX_EPOCH + Duration::from_secs(121);
+        let stats = |last_handshake| {
+            StatsMap::from([(
+                peer,
+                Stats {
+                    tx_bytes: 0,
+                    rx_bytes: 0,
+                    last_handshake,
+                },
+            )])
+        };
+
+        let mut recorder = StatsRecorder::new(1380);
+        assert_eq!(recorder.statistics(&stats(None), None), None);
+        recorder.set_up();
+
+        recorder.record_stats(&stats(None));
+        recorder.record_stats(&stats(Some(first)));
+        recorder.record_stats(&stats(Some(first)));
+        recorder.record_stats(&stats(Some(second)));
+
+        let statistics = recorder.statistics(&stats(Some(second)), None).unwrap();
+        assert_eq!(statistics.peers[0].handshakes, 2);
+        assert_eq!(statistics.peers[0].last_handshake, Some(second));
+    }
+
+    #[test]
+    fn test_ping_rtts() {
+        let mut recorder = StatsRecorder::new(1380);
+        recorder.set_up();
+        for millis in 0..(MAX_PING_RTTS as u64 + 2) {
+            recorder.record_ping_rtt(Duration::from_millis(millis));
+        }
+
+        let statistics = recorder.statistics(&StatsMap::new(), None).unwrap();
+        assert_eq!(statistics.ping_rtts.len(), MAX_PING_RTTS);
+        assert_eq!(statistics.ping_rtts[0], Duration::from_millis(2));
+    }
+}
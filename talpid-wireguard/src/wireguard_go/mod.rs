@@ -8,9 +8,9 @@ use super::{
 };
 #[cfg(target_os = "linux")]
 use crate::config::MULLVAD_INTERFACE_NAME;
-#[cfg(target_os = "android")]
-use crate::connectivity;
 use crate::logging::{clean_up_logging, initialize_logging};
+#[cfg(target_os = "android")]
+use crate::{connectivity, stats::StatsRecorder};
 #[cfg(all(unix, not(target_os = "android")))]
 use ipnetwork::IpNetwork;
 #[cfg(daita)]
@@ -581,7 +581,8 @@ impl WgGoTunnel {
         let state = self.as_state();
         let addr = state.config.ipv4_gateway;
         let cancel_receiver = state.cancel_receiver.clone();
-        let mut check = connectivity::Check::new(addr, 0, cancel_receiver)
+        let stats_recorder = Arc::new(Mutex::new(StatsRecorder::new(state.config.mtu)));
+        let mut check = connectivity::Check::new(addr, 0, cancel_receiver, stats_recorder)
             .map_err(|err| TunnelError::RecoverableStartWireguardError(Box::new(err)))?;
 
         // TODO: retry attempt?
@@ -658,6 +659,11 @@ impl Tunnel for WgGoTunnel {
 
 mod stats {
     use super::{Stats, StatsMap};
+    use std::{
+        num::ParseIntError,
+        str::FromStr,
+        time::{Duration, SystemTime},
+    };
 
     #[derive(thiserror::Error, Debug, PartialEq)]
     pub enum Error {
@@ -665,7 +671,7 @@ mod stats {
         PubKeyParse(String, #[source] hex::FromHexError),
 
         #[error("Failed to parse integer from string \"{0}\"")]
-        IntParse(String, #[source] std::num::ParseIntError),
+        IntParse(String, #[source] ParseIntError),
     }
 
     impl Stats {
@@ -675,6 +681,8 @@ mod stats {
             let mut peer = None;
             let mut tx_bytes = None;
             let mut rx_bytes = None;
+            let mut handshake_sec = 0;
+            let mut handshake_nsec = 0;
 
             // parts iterates over keys and values
             let parts = config.split('\n').filter_map(|line| {
@@ -693,23 +701,13 @@ mod stats {
                         peer = Some(buffer);
                         tx_bytes = None;
                         rx_bytes = None;
+                        handshake_sec = 0;
+                        handshake_nsec = 0;
                     }
-                    "rx_bytes" => {
-                        rx_bytes = Some(
-                            value
-                                .trim()
-                                .parse()
-                                .map_err(|err| Error::IntParse(value.to_string(), err))?,
-                        );
-                    }
-                    "tx_bytes" => {
-                        tx_bytes = Some(
-                            value
-                                .trim()
-                                .parse()
-                                .map_err(|err| Error::IntParse(value.to_string(), err))?,
-                        );
-                    }
+                    "rx_bytes" => rx_bytes = Some(parse_int(value)?),
+                    "tx_bytes" => tx_bytes = Some(parse_int(value)?),
+                    "last_handshake_time_sec" => handshake_sec = parse_int(value)?,
+                    "last_handshake_time_nsec" => handshake_nsec = parse_int(value)?,
 
                     _ => continue,
                 }
@@ -717,11 +715,16 @@ mod stats {
                 if let (Some(peer_val), Some(tx_bytes_val), Some(rx_bytes_val)) =
                     (peer, tx_bytes, rx_bytes)
                 {
+                    // A zero timestamp means that no handshake has occurred
+                    let last_handshake = (handshake_sec != 0 || handshake_nsec != 0).then(|| {
+                        SystemTime::UNIX_EPOCH + Duration::new(handshake_sec, handshake_nsec)
+                    });
                     map.insert(
                         peer_val,
                         Self {
                             tx_bytes: tx_bytes_val,
                             rx_bytes: rx_bytes_val,
+                            last_handshake,
                         },
                     );
                     peer = None;
@@ -733,9 +736,17 @@ mod stats {
         }
     }
 
+    fn parse_int<T: FromStr<Err = ParseIntError>>(value: &str) -> Result<T, Error> {
+        value
+            .trim()
+            .parse()
+            .map_err(|err| Error::IntParse(value.to_string(), err))
+    }
+
     #[cfg(test)]
     mod test {
         use super::super::stats::{Error, Stats};
+        use std::time::{Duration, SystemTime};
 
         #[test]
         fn test_parsing() {
@@ -748,6 +759,10 @@ mod stats {
             assert_eq!(actual_keys, [pubkey]);
             assert_eq!(stats[&pubkey].rx_bytes, 2396);
             assert_eq!(stats[&pubkey].tx_bytes, 2740);
+            assert_eq!(
+                stats[&pubkey].last_handshake,
+                Some(SystemTime::UNIX_EPOCH + Duration::new(1578420649, 369416131))
+            );
         }
 
         #[test]
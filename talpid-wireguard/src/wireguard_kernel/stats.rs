@@ -1,3 +1,7 @@
+use std::time::{Duration, SystemTime};
+
+use nix::sys::time::TimeSpec;
+
 use super::wg_message::{DeviceMessage, DeviceNla, PeerNla};
 use crate::stats::{Stats, StatsMap};
 
@@ -10,18 +14,29 @@ impl Stats {
                 for msg in peers {
                     let mut tx_bytes = 0;
                     let mut rx_bytes = 0;
+                    let mut last_handshake = None;
                     let mut pub_key = None;
 
                     for nla in &msg.0 {
                         match nla {
                             PeerNla::TxBytes(bytes) => tx_bytes = *bytes,
                             PeerNla::RxBytes(bytes) => rx_bytes = *bytes,
+                            PeerNla::LastHandshakeTime(time) => {
+                                last_handshake = parse_handshake_time(time)
+                            }
                             PeerNla::PublicKey(key) => pub_key = Some(*key),
                             _ => continue,
                         }
                     }
                     if let Some(key) = pub_key {
-                        map.insert(key, Stats { tx_bytes, rx_bytes });
+                        map.insert(
+                            key,
+                            Stats {
+                                tx_bytes,
+                                rx_bytes,
+                                last_handshake,
+                            },
+                        );
                     }
                 }
             }
@@ -30,3 +45,13 @@ impl Stats {
         map
     }
 }
+
+/// A zero timestamp means that no handshake has occurred.
+fn parse_handshake_time(time: &TimeSpec) -> Option<SystemTime> {
+    let secs = u64::try_from(time.tv_sec()).ok()?;
+    let nanos = u32::try_from(time.tv_nsec()).ok()?;
+    if secs == 0 && nanos == 0 {
+        return None;
+    }
+    Some(SystemTime::UNIX_EPOCH + Duration::new(secs, nanos))
+}
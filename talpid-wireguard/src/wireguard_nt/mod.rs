@@ -24,6 +24,7 @@ use std::{
     pin::Pin,
     ptr,
     sync::{Arc, LazyLock, Mutex},
+    time::{Duration, SystemTime},
 };
 use talpid_types::{BoxedError, ErrorExt};
 use talpid_windows::net;
@@ -1044,6 +1045,19 @@ unsafe fn deserialize_config(
     Ok((interface, peers))
 }
 
+/// Convert a handshake time in 100 ns intervals since 1601-01-01 to a [SystemTime]. A zero
+/// timestamp means that no handshake has occurred.
+fn parse_handshake_time(time: u64) -> Option<SystemTime> {
+    /// Number of 100 ns intervals between 1601-01-01 and 1970-01-01
+    const UNIX_EPOCH_OFFSET: u64 = 116_444_736_000_000_000;
+
+    if time == 0 {
+        return None;
+    }
+    let since_epoch = time.checked_sub(UNIX_EPOCH_OFFSET)?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(since_epoch.saturating_mul(100)))
+}
+
 #[async_trait::async_trait]
 impl Tunnel for WgNtTunnel {
     fn get_interface_name(&self) -> String {
@@ -1072,6 +1086,7 @@ impl Tunnel for WgNtTunnel {
                     Stats {
                         tx_bytes: peer.tx_bytes,
                         rx_bytes: peer.rx_bytes,
+                        last_handshake: parse_handshake_time(peer.last_handshake),
                     },
                 );
             }
@@ -84,8 +84,11 @@ pub async fn test_dns_leak_custom_public_ip(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![CONFIG_IP],
+                encrypted_upstreams: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns: Default::default(),
+            local_blocklists: Default::default(),
         })
         .await
         .expect("failed to configure DNS server");
@@ -116,8 +119,11 @@ pub async fn test_dns_leak_custom_private_ip(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![CONFIG_IP],
+                encrypted_upstreams: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns: Default::default(),
+            local_blocklists: Default::default(),
         })
         .await
         .expect("failed to configure DNS server");
@@ -367,8 +373,11 @@ pub async fn test_dns_config_custom_private(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![IpAddr::V4(TEST_CONFIG.host_bridge_ip)],
+                encrypted_upstreams: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns: Default::default(),
+            local_blocklists: Default::default(),
         })
         .await
         .context("failed to configure DNS server")?;
@@ -401,8 +410,11 @@ pub async fn test_dns_config_custom_public(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![custom_ip],
+                encrypted_upstreams: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns: Default::default(),
+            local_blocklists: Default::default(),
         })
         .await
         .context("failed to configure DNS server")?;
@@ -507,6 +519,8 @@ pub async fn test_content_blockers(
                 default_options: test_opts,
                 custom_options: settings::CustomDnsOptions::default(),
                 state: settings::DnsState::Default,
+                split_dns: Default::default(),
+                local_blocklists: Default::default(),
             })
             .await
             .context("failed to configure DNS server")?;
@@ -677,6 +691,7 @@ async fn connect_local_wg_relay(mullvad_client: &mut MullvadProxyClient) -> Resu
             fwmark: None,
             ipv6_gateway: None,
         }),
+        wireguard_psk: None,
     };
     set_custom_endpoint(mullvad_client, custom_tunnel_endpoint)
         .await
@@ -191,6 +191,7 @@ pub async fn test_connecting_state(
         CustomTunnelEndpoint {
             host: "1.3.3.7".to_owned(),
             config: mullvad_types::ConnectionConfig::Wireguard(unreachable_wireguard_tunnel()),
+            wireguard_psk: None,
         },
     )
     .await
@@ -0,0 +1,109 @@
+//! Helpers shared by the obfuscators, and by the test servers of the QUIC and WebSocket
+//! transports.
+
+use rustls::{pki_types::CertificateDer, RootCertStore};
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use tokio::net::UdpSocket;
+
+/// Wait for a client to connect to `udp_listener` and connect the socket to that address
+pub(crate) async fn wait_for_local_udp_client(udp_listener: &UdpSocket) -> io::Result<()> {
+    log::trace!("Waiting for UDP socket client");
+    let client_addr = udp_listener.peek_sender().await?;
+
+    log::trace!("UDP connection from {client_addr}");
+    udp_listener.connect(client_addr).await
+}
+
+/// Return whether retrying is a lost cause
+pub(crate) fn is_fatal_socket_io_error(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::NotConnected
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
+
+/// Return the Web PKI roots, and `extra_root_certificate` if any.
+pub(crate) fn root_certificates(
+    extra_root_certificate: Option<CertificateDer<'static>>,
+) -> Result<RootCertStore, rustls::Error> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(certificate) = extra_root_certificate {
+        roots.add(certificate)?;
+    }
+    Ok(roots)
+}
+
+/// Create the UDP socket that a test server forwards the datagrams of one client with. Every
+/// client is given its own socket, so that replies can be routed back.
+pub(crate) async fn connect_forward_socket(forward_addr: SocketAddr) -> io::Result<UdpSocket> {
+    let bind_addr = if forward_addr.is_ipv4() {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+    } else {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+    };
+    let udp = UdpSocket::bind(bind_addr).await?;
+    udp.connect(forward_addr).await?;
+    Ok(udp)
+}
+
+#[cfg(test)]
+pub(crate) mod test_harness {
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        time::Duration,
+    };
+    use tokio::net::UdpSocket;
+
+    /// Server name that [`self_signed_certificate`] is valid for.
+    pub const SERVER_NAME: &str = "localhost";
+
+    /// Generate a certificate and private key for [`SERVER_NAME`].
+    pub fn self_signed_certificate() -> (CertificateDer<'static>, PrivateKeyDer<'static>) {
+        let certified_key = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])
+            .expect("failed to generate certificate");
+        let certificate = certified_key.cert.der().clone();
+        let private_key = PrivatePkcs8KeyDer::from(certified_key.key_pair.serialize_der()).into();
+        (certificate, private_key)
+    }
+
+    /// Spawn a UDP echo server, which stands in for the WireGuard endpoint, and return its
+    /// address.
+    pub async fn spawn_echo_server() -> SocketAddr {
+        let echo = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let addr = echo.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buffer = vec![0u8; usize::from(u16::MAX)];
+            loop {
+                let (read_n, sender) = echo.recv_from(&mut buffer).await.unwrap();
+                echo.send_to(&buffer[..read_n], sender).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    /// Send every packet to the obfuscator at `obfuscator_addr` and check that it is echoed back
+    /// unchanged.
+    pub async fn assert_echoed(obfuscator_addr: SocketAddr, packets: &[&[u8]]) {
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        client.connect(obfuscator_addr).await.unwrap();
+
+        let mut buffer = vec![0u8; usize::from(u16::MAX)];
+        for packet in packets {
+            client.send(packet).await.unwrap();
+            let read_n = tokio::time::timeout(Duration::from_secs(10), client.recv(&mut buffer))
+                .await
+                .expect("timed out waiting for echo")
+                .unwrap();
+            assert_eq!(&buffer[..read_n], *packet);
+        }
+    }
+}
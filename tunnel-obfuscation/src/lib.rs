@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 use std::net::SocketAddr;
 
+mod common;
+pub mod quic;
 pub mod shadowsocks;
 pub mod udp2tcp;
 
@@ -19,6 +21,12 @@ pub enum Error {
 
     #[error("Failed to run Shadowsocks")]
     RunShadowsocksObfuscator(#[source] shadowsocks::Error),
+
+    #[error("Failed to initialize QUIC obfuscator")]
+    CreateQuicObfuscator(#[source] quic::Error),
+
+    #[error("Failed to run QUIC obfuscator")]
+    RunQuicObfuscator(#[source] quic::Error),
 }
 
 #[async_trait]
@@ -42,6 +50,7 @@ pub trait Obfuscator: Send {
 pub enum Settings {
     Udp2Tcp(udp2tcp::Settings),
     Shadowsocks(shadowsocks::Settings),
+    Quic(quic::Settings),
 }
 
 pub async fn create_obfuscator(settings: &Settings) -> Result<Box<dyn Obfuscator>> {
@@ -54,6 +63,10 @@ pub async fn create_obfuscator(settings: &Settings) -> Result<Box<dyn Obfuscator
             .await
             .map(box_obfuscator)
             .map_err(Error::CreateShadowsocksObfuscator),
+        Settings::Quic(s) => quic::Quic::new(s)
+            .await
+            .map(box_obfuscator)
+            .map_err(Error::CreateQuicObfuscator),
     }
 }
 
@@ -1,13 +1,42 @@
+//! Test harness for the obfuscators.
+//!
+//! Usage:
+//! - `tunnel-obfuscation udp2tcp`
+//! - `tunnel-obfuscation udp2tcp-ws <server address> <server name> <path> [CA certificate PEM
+//!   file]`
+//! - `tunnel-obfuscation udp2tcp-ws-server <listen address> <WireGuard address> <path>
+//!   <certificate chain PEM file> <private key PEM file>`
+//! - `tunnel-obfuscation quic <server address> <hostname> [CA certificate PEM file]`
+//! - `tunnel-obfuscation quic-server <listen address> <WireGuard address> <certificate chain PEM
+//!   file> <private key PEM file>`
+//!
+//! The WebSocket and QUIC clients and servers can be combined to test obfuscation locally, using a
+//! self-signed certificate.
+
+use rustls::pki_types::pem::PemObject;
 use std::{env::args, net::SocketAddr};
-use tunnel_obfuscation::{create_obfuscator, udp2tcp, Obfuscator, Settings};
+use tunnel_obfuscation::{
+    create_obfuscator,
+    quic::{self, CertificateDer, PrivateKeyDer},
+    udp2tcp::{self, websocket},
+    Obfuscator, Settings,
+};
 
 #[tokio::main]
 async fn main() {
-    if args().len() != 2 {
+    let args: Vec<String> = args().skip(1).collect();
+    let Some((obfuscator_type, args)) = args.split_first() else {
         println!("Missing arguments");
+        return;
+    };
+
+    match obfuscator_type.as_str() {
+        "udp2tcp-ws-server" => return run_websocket_server(args).await,
+        "quic-server" => return run_quic_server(args).await,
+        _ => (),
     }
 
-    let obfuscator = instantiate_requested(&args().last().unwrap()).await;
+    let obfuscator = instantiate_requested(obfuscator_type, args).await;
 
     println!("endpoint() returns {:?}", obfuscator.endpoint());
 
@@ -16,11 +45,12 @@ async fn main() {
     }
 }
 
-async fn instantiate_requested(obfuscator_type: &str) -> Box<dyn Obfuscator> {
+async fn instantiate_requested(obfuscator_type: &str, args: &[String]) -> Box<dyn Obfuscator> {
     match obfuscator_type {
         "udp2tcp" => {
             let settings = udp2tcp::Settings {
                 peer: SocketAddr::new("127.0.0.1".parse().unwrap(), 3030),
+                websocket: None,
                 #[cfg(target_os = "linux")]
                 fwmark: Some(1337),
             };
@@ -29,8 +59,101 @@ async fn instantiate_requested(obfuscator_type: &str) -> Box<dyn Obfuscator> {
                 .await
                 .expect("Creating obfuscator failed")
         }
+        "udp2tcp-ws" => {
+            let [peer, server_name, path, rest @ ..] = args else {
+                panic!("Expected a server address, server name and path");
+            };
+            let settings = udp2tcp::Settings {
+                peer: peer.parse().expect("Invalid server address"),
+                websocket: Some(websocket::Settings {
+                    server_name: server_name.clone(),
+                    path: path.clone(),
+                    extra_root_certificate: rest.first().map(|path| read_certificate(path)),
+                }),
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+            };
+
+            create_obfuscator(&Settings::Udp2Tcp(settings))
+                .await
+                .expect("Creating obfuscator failed")
+        }
+        "quic" => {
+            let [peer, hostname, rest @ ..] = args else {
+                panic!("Expected a server address and hostname");
+            };
+            let settings = quic::Settings {
+                peer: peer.parse().expect("Invalid server address"),
+                hostname: hostname.clone(),
+                extra_root_certificate: rest.first().map(|path| read_certificate(path)),
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+            };
+
+            create_obfuscator(&Settings::Quic(settings))
+                .await
+                .expect("Creating obfuscator failed")
+        }
         _ => {
             unimplemented!()
         }
     }
 }
+
+async fn run_websocket_server(args: &[String]) {
+    let [listen_addr, forward_addr, path, certificate_chain, private_key] = args else {
+        panic!(
+            "Expected a listen address, WireGuard address, path, certificate chain and private key"
+        );
+    };
+
+    let server = websocket::Server::new(websocket::ServerSettings {
+        listen_addr: listen_addr.parse().expect("Invalid listen address"),
+        forward_addr: forward_addr.parse().expect("Invalid WireGuard address"),
+        path: path.clone(),
+        certificate_chain: read_certificate_chain(certificate_chain),
+        private_key: read_private_key(private_key),
+    })
+    .await
+    .expect("Creating WebSocket server failed");
+
+    println!("Listening on {:?}", server.local_addr());
+
+    if let Err(err) = server.run().await {
+        println!("server.run() failed: {err:?}");
+    }
+}
+
+async fn run_quic_server(args: &[String]) {
+    let [listen_addr, forward_addr, certificate_chain, private_key] = args else {
+        panic!("Expected a listen address, WireGuard address, certificate chain and private key");
+    };
+
+    let server = quic::Server::new(quic::ServerSettings {
+        listen_addr: listen_addr.parse().expect("Invalid listen address"),
+        forward_addr: forward_addr.parse().expect("Invalid WireGuard address"),
+        certificate_chain: read_certificate_chain(certificate_chain),
+        private_key: read_private_key(private_key),
+    })
+    .expect("Creating QUIC server failed");
+
+    println!("Listening on {:?}", server.local_addr());
+
+    if let Err(err) = server.run().await {
+        println!("server.run() failed: {err:?}");
+    }
+}
+
+fn read_certificate(path: &str) -> CertificateDer<'static> {
+    CertificateDer::from_pem_file(path).expect("Failed to read CA certificate")
+}
+
+fn read_certificate_chain(path: &str) -> Vec<CertificateDer<'static>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect())
+        .expect("Failed to read certificate chain")
+}
+
+fn read_private_key(path: &str) -> PrivateKeyDer<'static> {
+    PrivateKeyDer::from_pem_file(path).expect("Failed to read private key")
+}
@@ -0,0 +1,471 @@
+//! QUIC obfuscation
+//!
+//! WireGuard packets are sent as unreliable QUIC datagrams (RFC 9221). The connection uses the
+//! HTTP/3 ALPN identifier and is usually made to UDP port 443, so that it looks like ordinary
+//! HTTP/3 traffic. The server forwards every datagram to its WireGuard endpoint.
+//!
+//! Note: It is important not to connect to the QUIC endpoint right away. The remote socket must
+//! be protected in `VpnService` so that the socket is not routed through the tunnel.
+
+use super::{
+    common::{
+        connect_forward_socket, is_fatal_socket_io_error, root_certificates,
+        wait_for_local_udp_client,
+    },
+    Obfuscator,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+#[cfg(target_os = "linux")]
+use nix::sys::socket::{setsockopt, sockopt};
+use quinn::{
+    crypto::rustls::{QuicClientConfig, QuicServerConfig},
+    ClientConfig, Connection, Endpoint, EndpointConfig, MtuDiscoveryConfig, SendDatagramError,
+    ServerConfig, TokioRuntime, TransportConfig,
+};
+pub use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{crypto::ring, version::TLS13};
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use std::os::fd::AsRawFd;
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{net::UdpSocket, sync::oneshot};
+
+/// ALPN identifier of HTTP/3.
+const ALPN_H3: &[u8] = b"h3";
+
+/// Keep the connection alive well within the idle timeout of common NATs and middleboxes.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Largest UDP payload that fits in a 1500 byte Ethernet frame over IPv6. QUIC would otherwise
+/// start out at 1200 bytes, which is too small for full-sized WireGuard packets, and only grow
+/// once path MTU discovery has completed. If the path cannot carry this much, black hole
+/// detection brings the MTU back down.
+const INITIAL_MTU: u16 = 1500 - 40 - 8;
+
+/// Upper bound on the number of bytes added to each WireGuard packet by QUIC. A packet carrying
+/// a single DATAGRAM frame looks like this:
+/// [flags][connection id][packet number][frame type][length][payload][tag]
+///
+/// See <https://www.rfc-editor.org/rfc/rfc9000#section-17.3.1> and
+/// <https://www.rfc-editor.org/rfc/rfc9221#section-4>.
+const PACKET_OVERHEAD: u16 = 1 + 20 + 4 + 1 + 2 + 16;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to bind local UDP socket
+    #[error("Failed to bind local UDP socket")]
+    BindUdp(#[source] io::Error),
+    /// Failed to get local UDP socket address
+    #[error("Failed to get local UDP socket address")]
+    GetUdpLocalAddress(#[source] io::Error),
+    /// Failed to bind remote UDP socket
+    #[error("Failed to bind remote UDP socket")]
+    BindRemoteUdp(#[source] io::Error),
+    /// Failed to set fwmark on remote socket
+    #[cfg(target_os = "linux")]
+    #[error("Failed to set fwmark on remote socket")]
+    SetFwmark(#[source] nix::Error),
+    /// Failed to create QUIC endpoint
+    #[error("Failed to create QUIC endpoint")]
+    CreateEndpoint(#[source] io::Error),
+    /// Invalid TLS configuration
+    #[error("Invalid TLS configuration")]
+    TlsConfig(#[source] rustls::Error),
+    /// The TLS configuration has no cipher suite usable with QUIC
+    #[error("No cipher suite usable with QUIC")]
+    NoInitialCipherSuite(#[source] quinn::crypto::rustls::NoInitialCipherSuite),
+    /// Failed to wait for UDP client
+    #[error("Failed to wait for UDP client")]
+    WaitForUdpClient(#[source] io::Error),
+    /// Failed to start connecting to the QUIC endpoint
+    #[error("Failed to connect to QUIC endpoint")]
+    Connect(#[source] quinn::ConnectError),
+    /// The QUIC connection failed
+    #[error("QUIC connection failed")]
+    Connection(#[source] quinn::ConnectionError),
+    /// The server does not support QUIC datagrams
+    #[error("The server does not support QUIC datagrams")]
+    DatagramsUnsupported,
+}
+
+/// Settings for the QUIC obfuscation client
+#[derive(Debug)]
+pub struct Settings {
+    /// Remote QUIC endpoint
+    pub peer: SocketAddr,
+    /// Server name used for SNI and to verify the certificate of the server
+    pub hostname: String,
+    /// Certificate to trust in addition to the Web PKI roots. This is mainly useful when testing
+    /// against a local server with a self-signed certificate.
+    pub extra_root_certificate: Option<CertificateDer<'static>>,
+    #[cfg(target_os = "linux")]
+    pub fwmark: Option<u32>,
+}
+
+pub struct Quic {
+    udp_client_addr: SocketAddr,
+    server: tokio::task::JoinHandle<Result<()>>,
+    // The receiver will implicitly shut down when this is dropped
+    _shutdown_tx: oneshot::Sender<()>,
+    #[cfg(target_os = "android")]
+    outbound_fd: i32,
+}
+
+impl Quic {
+    pub(crate) async fn new(settings: &Settings) -> Result<Self> {
+        let client_config = client_config(settings.extra_root_certificate.clone())?;
+
+        let local_addr = if settings.peer.is_ipv4() {
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0))
+        } else {
+            SocketAddr::from((Ipv6Addr::LOCALHOST, 0))
+        };
+        let local_udp_socket = UdpSocket::bind(local_addr).await.map_err(Error::BindUdp)?;
+        let udp_client_addr = local_udp_socket
+            .local_addr()
+            .map_err(Error::GetUdpLocalAddress)?;
+
+        let remote_socket = create_remote_socket(
+            settings.peer.is_ipv4(),
+            #[cfg(target_os = "linux")]
+            settings.fwmark,
+        )?;
+
+        #[cfg(target_os = "android")]
+        let outbound_fd = remote_socket.as_raw_fd();
+
+        // Nothing is sent until `connect_with` is called
+        let endpoint = Endpoint::new(
+            EndpointConfig::default(),
+            None,
+            remote_socket,
+            Arc::new(TokioRuntime),
+        )
+        .map_err(Error::CreateEndpoint)?;
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+
+        let server = tokio::spawn(run_forwarding(
+            endpoint,
+            client_config,
+            settings.peer,
+            settings.hostname.clone(),
+            local_udp_socket,
+            shutdown_rx,
+        ));
+
+        Ok(Quic {
+            udp_client_addr,
+            server,
+            _shutdown_tx: shutdown_tx,
+            #[cfg(target_os = "android")]
+            outbound_fd,
+        })
+    }
+}
+
+fn client_config(extra_root_certificate: Option<CertificateDer<'static>>) -> Result<ClientConfig> {
+    let roots = root_certificates(extra_root_certificate).map_err(Error::TlsConfig)?;
+
+    let mut tls_config =
+        rustls::ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_protocol_versions(&[&TLS13])
+            .map_err(Error::TlsConfig)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    tls_config.alpn_protocols = vec![ALPN_H3.to_vec()];
+
+    let quic_config =
+        QuicClientConfig::try_from(tls_config).map_err(Error::NoInitialCipherSuite)?;
+    let mut config = ClientConfig::new(Arc::new(quic_config));
+    config.transport_config(Arc::new(transport_config()));
+    Ok(config)
+}
+
+fn transport_config() -> TransportConfig {
+    let mut mtu_discovery = MtuDiscoveryConfig::default();
+    mtu_discovery.upper_bound(INITIAL_MTU);
+
+    let mut config = TransportConfig::default();
+    config.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+    config.initial_mtu(INITIAL_MTU);
+    config.mtu_discovery_config(Some(mtu_discovery));
+    config
+}
+
+fn create_remote_socket(
+    ipv4: bool,
+    #[cfg(target_os = "linux")] fwmark: Option<u32>,
+) -> Result<std::net::UdpSocket> {
+    let random_bind_addr = if ipv4 {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+    } else {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+    };
+    let socket = std::net::UdpSocket::bind(random_bind_addr).map_err(Error::BindRemoteUdp)?;
+    socket.set_nonblocking(true).map_err(Error::BindRemoteUdp)?;
+    #[cfg(target_os = "linux")]
+    if let Some(fwmark) = fwmark {
+        setsockopt(socket.as_raw_fd(), sockopt::Mark, &fwmark).map_err(Error::SetFwmark)?;
+    }
+
+    Ok(socket)
+}
+
+async fn run_forwarding(
+    endpoint: Endpoint,
+    client_config: ClientConfig,
+    peer: SocketAddr,
+    hostname: String,
+    local_udp_socket: UdpSocket,
+    shutdown_rx: oneshot::Receiver<()>,
+) -> Result<()> {
+    wait_for_local_udp_client(&local_udp_socket)
+        .await
+        .map_err(Error::WaitForUdpClient)?;
+
+    let connection = endpoint
+        .connect_with(client_config, peer, &hostname)
+        .map_err(Error::Connect)?
+        .await
+        .map_err(Error::Connection)?;
+    let Some(max_datagram_size) = connection.max_datagram_size() else {
+        return Err(Error::DatagramsUnsupported);
+    };
+    log::trace!("QUIC connection established to {peer}. Max datagram size: {max_datagram_size}");
+
+    let local_udp = Arc::new(local_udp_socket);
+
+    let mut client = tokio::spawn(forward_udp_to_quic(connection.clone(), local_udp.clone()));
+    let mut server = tokio::spawn(forward_quic_to_udp(connection.clone(), local_udp));
+
+    tokio::select! {
+        _ = shutdown_rx => {
+            log::trace!("Stopping QUIC obfuscation");
+        }
+        _result = &mut server => log::trace!("QUIC connection closed"),
+        _result = &mut client => log::trace!("Local UDP client closed"),
+    }
+
+    client.abort();
+    server.abort();
+    connection.close(0u32.into(), b"");
+
+    Ok(())
+}
+
+/// Send every datagram received on `udp` to the other end of `connection`.
+async fn forward_udp_to_quic(connection: Connection, udp: Arc<UdpSocket>) {
+    let mut rx_buffer = vec![0u8; u16::MAX as usize];
+
+    loop {
+        let read_n = match udp.recv(&mut rx_buffer).await {
+            Ok(read_n) => read_n,
+            Err(error) => {
+                log::error!("Failed to read from UDP socket: {error}");
+                break;
+            }
+        };
+
+        match connection.send_datagram(Bytes::copy_from_slice(&rx_buffer[..read_n])) {
+            Ok(()) => (),
+            // The path cannot carry this packet. Drop it and let WireGuard deal with the loss.
+            Err(SendDatagramError::TooLarge) => {
+                log::warn!(
+                    "Dropping datagram of {read_n} bytes. The path only fits {} bytes",
+                    connection.max_datagram_size().unwrap_or(0)
+                );
+            }
+            Err(error) => {
+                log::error!("Failed to send QUIC datagram: {error}");
+                break;
+            }
+        }
+    }
+}
+
+/// Send every datagram received on `connection` to `udp`.
+async fn forward_quic_to_udp(connection: Connection, udp: Arc<UdpSocket>) {
+    loop {
+        let datagram = match connection.read_datagram().await {
+            Ok(datagram) => datagram,
+            Err(error) => {
+                log::debug!("Failed to read QUIC datagram: {error}");
+                break;
+            }
+        };
+
+        if let Err(error) = udp.send(&datagram).await {
+            log::error!("Failed to write to UDP socket: {error}");
+            if is_fatal_socket_io_error(&error) {
+                break;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Obfuscator for Quic {
+    fn endpoint(&self) -> SocketAddr {
+        self.udp_client_addr
+    }
+
+    async fn run(self: Box<Self>) -> crate::Result<()> {
+        match self.server.await {
+            Ok(result) => result.map_err(crate::Error::RunQuicObfuscator),
+            Err(_err) if _err.is_cancelled() => Ok(()),
+            Err(_err) => panic!("server handle panicked"),
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    fn remote_socket_fd(&self) -> std::os::unix::io::RawFd {
+        self.outbound_fd
+    }
+
+    fn packet_overhead(&self) -> u16 {
+        PACKET_OVERHEAD
+    }
+}
+
+/// Settings for a QUIC obfuscation server
+#[derive(Debug)]
+pub struct ServerSettings {
+    /// Address to accept QUIC connections on
+    pub listen_addr: SocketAddr,
+    /// WireGuard endpoint that datagrams are forwarded to
+    pub forward_addr: SocketAddr,
+    /// Certificate chain presented to clients
+    pub certificate_chain: Vec<CertificateDer<'static>>,
+    /// Private key of the first certificate in `certificate_chain`
+    pub private_key: PrivateKeyDer<'static>,
+}
+
+/// A QUIC obfuscation server, which forwards the datagrams of every client to a WireGuard
+/// endpoint.
+///
+/// This is meant for testing the client locally, and not for production use.
+pub struct Server {
+    endpoint: Endpoint,
+    forward_addr: SocketAddr,
+}
+
+impl Server {
+    pub fn new(settings: ServerSettings) -> Result<Self> {
+        let mut tls_config =
+            rustls::ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+                .with_protocol_versions(&[&TLS13])
+                .map_err(Error::TlsConfig)?
+                .with_no_client_auth()
+                .with_single_cert(settings.certificate_chain, settings.private_key)
+                .map_err(Error::TlsConfig)?;
+        tls_config.alpn_protocols = vec![ALPN_H3.to_vec()];
+
+        let quic_config =
+            QuicServerConfig::try_from(tls_config).map_err(Error::NoInitialCipherSuite)?;
+        let mut config = ServerConfig::with_crypto(Arc::new(quic_config));
+        config.transport_config(Arc::new(transport_config()));
+
+        let endpoint =
+            Endpoint::server(config, settings.listen_addr).map_err(Error::CreateEndpoint)?;
+
+        Ok(Server {
+            endpoint,
+            forward_addr: settings.forward_addr,
+        })
+    }
+
+    /// Returns the address that QUIC connections are accepted on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.endpoint
+            .local_addr()
+            .map_err(Error::GetUdpLocalAddress)
+    }
+
+    /// Accept and serve clients until the endpoint is closed.
+    pub async fn run(self) -> Result<()> {
+        while let Some(incoming) = self.endpoint.accept().await {
+            let forward_addr = self.forward_addr;
+            tokio::spawn(async move {
+                let remote_addr = incoming.remote_address();
+                if let Err(error) = serve_client(incoming, forward_addr).await {
+                    log::error!("Failed to serve QUIC client {remote_addr}: {error}");
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+async fn serve_client(incoming: quinn::Incoming, forward_addr: SocketAddr) -> Result<()> {
+    let connection = incoming.await.map_err(Error::Connection)?;
+    log::debug!("Accepted QUIC client {}", connection.remote_address());
+
+    let udp = connect_forward_socket(forward_addr)
+        .await
+        .map_err(Error::BindRemoteUdp)?;
+    let udp = Arc::new(udp);
+
+    tokio::select! {
+        _ = forward_quic_to_udp(connection.clone(), udp.clone()) => (),
+        _ = forward_udp_to_quic(connection.clone(), udp) => (),
+    }
+    log::debug!("QUIC client {} disconnected", connection.remote_address());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        common::test_harness::{
+            assert_echoed, self_signed_certificate, spawn_echo_server, SERVER_NAME,
+        },
+        create_obfuscator, Settings as ObfuscationSettings,
+    };
+
+    /// Send datagrams through a local client and server to a UDP echo server, and back.
+    #[tokio::test]
+    async fn test_forwarding() {
+        let (certificate, private_key) = self_signed_certificate();
+        let forward_addr = spawn_echo_server().await;
+
+        let server = Server::new(ServerSettings {
+            listen_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            forward_addr,
+            certificate_chain: vec![certificate.clone()],
+            private_key,
+        })
+        .unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let obfuscator = create_obfuscator(&ObfuscationSettings::Quic(Settings {
+            peer: server_addr,
+            hostname: SERVER_NAME.to_string(),
+            extra_root_certificate: Some(certificate),
+            #[cfg(target_os = "linux")]
+            fwmark: None,
+        }))
+        .await
+        .unwrap();
+        let obfuscator_addr = obfuscator.endpoint();
+        tokio::spawn(obfuscator.run());
+
+        // Full-sized WireGuard packets must fit before path MTU discovery has completed
+        let full_sized_packet = vec![1u8; usize::from(INITIAL_MTU - PACKET_OVERHEAD)];
+        assert_echoed(
+            obfuscator_addr,
+            &[b"handshake initiation", &full_sized_packet],
+        )
+        .await;
+    }
+}
@@ -3,7 +3,10 @@
 //! Note: It is important not to connect to the shadowsocks endpoint right away. The remote socket
 //! must be protected in `VpnService` so that the socket is not routed through the tunnel.
 
-use super::Obfuscator;
+use super::{
+    common::{is_fatal_socket_io_error, wait_for_local_udp_client},
+    Obfuscator,
+};
 use async_trait::async_trait;
 #[cfg(target_os = "linux")]
 use nix::sys::socket::{setsockopt, sockopt};
@@ -19,11 +22,13 @@ use shadowsocks::{
 };
 #[cfg(any(target_os = "android", target_os = "linux"))]
 use std::os::fd::AsRawFd;
-use std::{io, net::SocketAddr, sync::Arc};
+use std::{io, net::SocketAddr, str::FromStr, sync::Arc};
 use tokio::{net::UdpSocket, sync::oneshot};
 
-const SHADOWSOCKS_CIPHER: CipherKind = CipherKind::AES_256_GCM;
-const SHADOWSOCKS_PASSWORD: &str = "mullvad";
+/// Cipher used by Mullvad's Shadowsocks servers.
+pub const MULLVAD_CIPHER: &str = "aes-256-gcm";
+/// Password used by Mullvad's Shadowsocks servers.
+pub const MULLVAD_PASSWORD: &str = "mullvad";
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -54,11 +59,15 @@ pub enum Error {
     /// Failed to receive remote socket descriptor
     #[error("Failed to receive remote socket descriptor")]
     ReceiveRemoteFd,
+    /// Unknown or unsupported cipher
+    #[error("Unsupported Shadowsocks cipher: {0}")]
+    UnsupportedCipher(String),
 }
 
 pub struct Shadowsocks {
     udp_client_addr: SocketAddr,
     wireguard_endpoint: SocketAddr,
+    cipher: CipherKind,
     server: tokio::task::JoinHandle<Result<()>>,
     // The receiver will implicitly shut down when this is dropped
     _shutdown_tx: oneshot::Sender<()>,
@@ -72,12 +81,28 @@ pub struct Settings {
     pub shadowsocks_endpoint: SocketAddr,
     /// Remote WireGuard endpoint
     pub wireguard_endpoint: SocketAddr,
+    /// Cipher used by the Shadowsocks server. Only AEAD ciphers are supported, including the
+    /// ones from the 2022 edition of the protocol.
+    pub cipher: String,
+    /// Password used by the Shadowsocks server. For the 2022 edition ciphers, this is a base64
+    /// encoded key, and it is assumed to be of the correct length.
+    pub password: String,
     #[cfg(target_os = "linux")]
     pub fwmark: Option<u32>,
 }
 
 impl Shadowsocks {
     pub(crate) async fn new(settings: &Settings) -> Result<Self> {
+        let cipher = CipherKind::from_str(&settings.cipher)
+            .ok()
+            .filter(|cipher| cipher.is_aead() || cipher.is_aead_2022())
+            .ok_or_else(|| Error::UnsupportedCipher(settings.cipher.clone()))?;
+        let server_config = ServerConfig::new(
+            settings.shadowsocks_endpoint,
+            settings.password.clone(),
+            cipher,
+        );
+
         let (local_udp_socket, udp_client_addr) =
             create_local_udp_socket(settings.shadowsocks_endpoint.is_ipv4()).await?;
 
@@ -95,6 +120,7 @@ impl Shadowsocks {
 
         let server = tokio::spawn(run_forwarding(
             settings.shadowsocks_endpoint,
+            server_config,
             remote_socket,
             local_udp_socket,
             settings.wireguard_endpoint,
@@ -104,6 +130,7 @@ impl Shadowsocks {
         Ok(Shadowsocks {
             udp_client_addr,
             wireguard_endpoint: settings.wireguard_endpoint,
+            cipher,
             server,
             _shutdown_tx: shutdown_tx,
             #[cfg(target_os = "android")]
@@ -114,6 +141,7 @@ impl Shadowsocks {
 
 async fn run_forwarding(
     shadowsocks_endpoint: SocketAddr,
+    server_config: ServerConfig,
     remote_socket: UdpSocket,
     local_udp_socket: UdpSocket,
     wireguard_endpoint: SocketAddr,
@@ -123,7 +151,7 @@ async fn run_forwarding(
         .await
         .map_err(Error::WaitForUdpClient)?;
 
-    let shadowsocks = connect_shadowsocks(remote_socket, shadowsocks_endpoint);
+    let shadowsocks = connect_shadowsocks(remote_socket, &server_config);
     let shadowsocks = Arc::new(shadowsocks);
 
     let local_udp = Arc::new(local_udp_socket);
@@ -157,14 +185,14 @@ async fn run_forwarding(
     Ok(())
 }
 
-fn connect_shadowsocks(remote_socket: UdpSocket, shadowsocks_endpoint: SocketAddr) -> ProxySocket {
+fn connect_shadowsocks(remote_socket: UdpSocket, server_config: &ServerConfig) -> ProxySocket {
     let ss_context = Context::new_shared(ServerType::Local);
-    let ss_config: ServerConfig = ServerConfig::new(
-        shadowsocks_endpoint,
-        SHADOWSOCKS_PASSWORD,
-        SHADOWSOCKS_CIPHER,
-    );
-    ProxySocket::from_socket(UdpSocketType::Client, ss_context, &ss_config, remote_socket)
+    ProxySocket::from_socket(
+        UdpSocketType::Client,
+        ss_context,
+        server_config,
+        remote_socket,
+    )
 }
 
 async fn create_shadowsocks_socket(
@@ -203,15 +231,6 @@ async fn create_local_udp_socket(ipv4: bool) -> Result<(UdpSocket, SocketAddr)>
     Ok((local_udp_socket, udp_client_addr))
 }
 
-/// Wait for a client to connect to `udp_listener` and connect the socket to that address
-async fn wait_for_local_udp_client(udp_listener: &UdpSocket) -> io::Result<()> {
-    log::trace!("Waiting for UDP socket client");
-    let client_addr = udp_listener.peek_sender().await?;
-
-    log::trace!("UDP connection from {client_addr}");
-    udp_listener.connect(client_addr).await
-}
-
 async fn handle_outgoing(
     ss_write: Arc<ProxySocket>,
     local_udp_read: Arc<UdpSocket>,
@@ -297,16 +316,49 @@ impl Obfuscator for Shadowsocks {
     }
 
     fn packet_overhead(&self) -> u16 {
+        let overhead = packet_overhead(self.cipher, &Address::from(self.wireguard_endpoint));
+        u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
+    }
+}
+
+/// Shadowsocks 2022 clients pad datagrams sent to port 53 with random padding, so that the payload
+/// and the padding together are at most this many bytes. Larger payloads are not padded.
+const MAX_PADDING_SIZE: usize = 900;
+
+/// Size of the smallest WireGuard datagram that fills the tunnel MTU: the minimum IPv4 MTU, plus
+/// the WireGuard header and authentication tag.
+const MIN_FULL_SIZE_PAYLOAD: usize = 576 + 32;
+
+/// Number of bytes added to each UDP datagram by Shadowsocks, when sending to `target`.
+fn packet_overhead(cipher: CipherKind, target: &Address) -> usize {
+    if cipher.is_aead_2022() {
+        // This math relies on the packet structure of Shadowsocks 2022 UDP packets.
+        // https://github.com/Shadowsocks-NET/shadowsocks-specs/blob/main/2022-1-shadowsocks-2022-edition.md
+        // Those packets look like this:
+        // [nonce][session id][packet id][type][timestamp][padding length][padding][address]
+        // [payload][tag]
+        // The nonce is only sent when using ChaCha20-Poly1305.
+        const HEADER_LEN: usize = 8 + 8 + 1 + 8 + 2;
+        let nonce_len = if cipher == CipherKind::AEAD2022_BLAKE3_CHACHA20_POLY1305 {
+            24
+        } else {
+            0
+        };
+        // Padding is only added when sending to port 53. A padded datagram may be larger than a
+        // full size one without padding if the tunnel MTU is small, so reserve room for the
+        // padding that can be added on top of the smallest full size payload.
+        let padding_len = if target.port() == 53 {
+            MAX_PADDING_SIZE - MIN_FULL_SIZE_PAYLOAD
+        } else {
+            0
+        };
+        nonce_len + HEADER_LEN + padding_len + target.serialized_len() + cipher.tag_len()
+    } else {
         // This math relies on the packet structure of Shadowsocks AEAD UDP packets.
         // https://shadowsocks.org/doc/aead.html
         // Those packets look like this: [salt][address][payload][tag]
-        debug_assert!(SHADOWSOCKS_CIPHER.is_aead());
-
-        let overhead = SHADOWSOCKS_CIPHER.salt_len()
-            + Address::from(self.wireguard_endpoint).serialized_len()
-            + SHADOWSOCKS_CIPHER.tag_len();
-
-        u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
+        debug_assert!(cipher.is_aead());
+        cipher.salt_len() + target.serialized_len() + cipher.tag_len()
     }
 }
 
@@ -315,13 +367,42 @@ fn is_fatal_socket_error(error: &ProxySocketError) -> bool {
     matches!(error, ProxySocketError::IoError(e) if is_fatal_socket_io_error(e))
 }
 
-fn is_fatal_socket_io_error(error: &io::Error) -> bool {
-    matches!(
-        error.kind(),
-        io::ErrorKind::NotConnected
-            | io::ErrorKind::ConnectionReset
-            | io::ErrorKind::ConnectionRefused
-            | io::ErrorKind::ConnectionAborted
-            | io::ErrorKind::BrokenPipe
-    )
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_packet_overhead() {
+        let ipv4 = Address::from(SocketAddr::from((Ipv4Addr::LOCALHOST, 51820)));
+        let ipv6 = Address::from(SocketAddr::from((Ipv6Addr::LOCALHOST, 51820)));
+        let dns_port = Address::from(SocketAddr::from((Ipv4Addr::LOCALHOST, 53)));
+
+        // [salt][address][payload][tag]
+        assert_eq!(packet_overhead(CipherKind::AES_256_GCM, &ipv4), 32 + 7 + 16);
+        assert_eq!(
+            packet_overhead(CipherKind::AES_128_GCM, &ipv6),
+            16 + 19 + 16
+        );
+        // Only 2022 clients pad datagrams
+        assert_eq!(
+            packet_overhead(CipherKind::AES_256_GCM, &dns_port),
+            32 + 7 + 16
+        );
+
+        // [session id][packet id][type][timestamp][padding length][address][payload][tag]
+        let aes_2022 = CipherKind::AEAD2022_BLAKE3_AES_256_GCM;
+        assert_eq!(packet_overhead(aes_2022, &ipv4), 27 + 7 + 16);
+        assert_eq!(packet_overhead(aes_2022, &ipv6), 27 + 19 + 16);
+        let chacha_2022 = CipherKind::AEAD2022_BLAKE3_CHACHA20_POLY1305;
+        assert_eq!(packet_overhead(chacha_2022, &ipv4), 24 + 27 + 7 + 16);
+
+        // A padded datagram carrying a small payload must not be larger than an unpadded one
+        // carrying a full size payload at the minimum MTU
+        let padded_datagram = 27 + MAX_PADDING_SIZE + 7 + 16;
+        assert_eq!(
+            packet_overhead(aes_2022, &dns_port) + MIN_FULL_SIZE_PAYLOAD,
+            padded_datagram
+        );
+    }
 }
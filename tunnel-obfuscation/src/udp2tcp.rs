@@ -6,9 +6,14 @@ use udp_over_tcp::{
     TcpOptions,
 };
 
+pub mod websocket;
+
 #[derive(Debug)]
 pub struct Settings {
     pub peer: SocketAddr,
+    /// Frame datagrams as WebSocket messages over TLS, rather than writing them directly to the
+    /// TCP stream.
+    pub websocket: Option<websocket::Settings>,
     #[cfg(target_os = "linux")]
     pub fwmark: Option<u32>,
 }
@@ -28,15 +33,43 @@ pub enum Error {
     /// Failed to run obfuscator
     #[error("Failed to run obfuscator")]
     RunObfuscator(#[source] udp2tcp::Error),
+
+    /// Failed to create WebSocket obfuscator
+    #[error("Failed to create WebSocket obfuscator")]
+    CreateWebSocketObfuscator(#[source] websocket::Error),
+
+    /// Failed to run WebSocket obfuscator
+    #[error("Failed to run WebSocket obfuscator")]
+    RunWebSocketObfuscator(#[source] websocket::Error),
 }
 
 pub struct Udp2Tcp {
     local_addr: SocketAddr,
-    instance: Udp2TcpImpl,
+    instance: Instance,
+}
+
+enum Instance {
+    Tcp(Udp2TcpImpl),
+    WebSocket(websocket::WebSocket),
 }
 
 impl Udp2Tcp {
     pub(crate) async fn new(settings: &Settings) -> Result<Self> {
+        if let Some(websocket_settings) = &settings.websocket {
+            let instance = websocket::WebSocket::new(
+                settings.peer,
+                websocket_settings,
+                #[cfg(target_os = "linux")]
+                settings.fwmark,
+            )
+            .await
+            .map_err(Error::CreateWebSocketObfuscator)?;
+            return Ok(Self {
+                local_addr: instance.endpoint(),
+                instance: Instance::WebSocket(instance),
+            });
+        }
+
         let listen_addr = if settings.peer.is_ipv4() {
             SocketAddr::new("127.0.0.1".parse().unwrap(), 0)
         } else {
@@ -62,7 +95,7 @@ impl Udp2Tcp {
 
         Ok(Self {
             local_addr,
-            instance,
+            instance: Instance::Tcp(instance),
         })
     }
 }
@@ -74,26 +107,34 @@ impl Obfuscator for Udp2Tcp {
     }
 
     async fn run(self: Box<Self>) -> crate::Result<()> {
-        self.instance
-            .run()
-            .await
-            .map_err(Error::RunObfuscator)
-            .map_err(crate::Error::RunUdp2TcpObfuscator)
+        match self.instance {
+            Instance::Tcp(instance) => instance.run().await.map_err(Error::RunObfuscator),
+            Instance::WebSocket(instance) => {
+                instance.run().await.map_err(Error::RunWebSocketObfuscator)
+            }
+        }
+        .map_err(crate::Error::RunUdp2TcpObfuscator)
     }
 
     #[cfg(target_os = "android")]
     fn remote_socket_fd(&self) -> std::os::unix::io::RawFd {
-        self.instance.remote_tcp_fd()
+        match &self.instance {
+            Instance::Tcp(instance) => instance.remote_tcp_fd(),
+            Instance::WebSocket(instance) => instance.remote_socket_fd(),
+        }
     }
 
     fn packet_overhead(&self) -> u16 {
         let max_tcp_header_len = 60; // https://datatracker.ietf.org/doc/html/rfc9293#section-3.1-6.22.1
         let udp_header_len = 8; // https://datatracker.ietf.org/doc/html/rfc768
 
-        // TODO: Make `HEADER_LEN` constant public in udp-over-tcp lib and use it instead
-        let udp_over_tcp_header_len = size_of::<u16>();
+        let framing_overhead = match &self.instance {
+            // TODO: Make `HEADER_LEN` constant public in udp-over-tcp lib and use it instead
+            Instance::Tcp(_) => size_of::<u16>(),
+            Instance::WebSocket(instance) => usize::from(instance.framing_overhead()),
+        };
 
-        let overhead = max_tcp_header_len - udp_header_len + udp_over_tcp_header_len;
+        let overhead = max_tcp_header_len - udp_header_len + framing_overhead;
 
         u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
     }
@@ -0,0 +1,480 @@
+//! WebSocket over TLS transport for udp2tcp
+//!
+//! Instead of writing length-prefixed datagrams directly to a TCP stream, every WireGuard packet
+//! is sent as a binary WebSocket message over TLS. The stream then looks like an ordinary HTTPS
+//! WebSocket connection, which can also pass through CDNs and TLS-terminating proxies.
+//!
+//! Note: It is important not to connect to the server right away. The TCP socket must be
+//! protected in `VpnService` so that the socket is not routed through the tunnel.
+
+use crate::common::{
+    connect_forward_socket, is_fatal_socket_io_error, root_certificates, wait_for_local_udp_client,
+};
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+#[cfg(target_os = "linux")]
+use nix::sys::socket::{setsockopt, sockopt};
+pub use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{crypto::ring, pki_types::ServerName};
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use std::os::fd::AsRawFd;
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpSocket, UdpSocket},
+    sync::oneshot,
+};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        handshake::{
+            client::Request,
+            server::{ErrorResponse, Request as ServerRequest, Response},
+        },
+        http::StatusCode,
+        Message,
+    },
+    WebSocketStream,
+};
+
+/// ALPN identifier of HTTP/1.1, which WebSocket handshakes are made over.
+const ALPN_HTTP_1_1: &[u8] = b"http/1.1";
+
+/// Upper bound on the number of bytes added to a datagram by TLS and WebSocket framing, on top of
+/// the TCP header. TLS records are at most 29 bytes larger than their payload with the supported
+/// AEAD ciphers (TLS 1.2 with an explicit nonce), and client frames carry a 2 byte header, a 2 byte
+/// extended length and a 4 byte mask.
+///
+/// See <https://www.rfc-editor.org/rfc/rfc5246#section-6.2.3.3> and
+/// <https://www.rfc-editor.org/rfc/rfc6455#section-5.2>.
+const FRAMING_OVERHEAD: u16 = (5 + 8 + 16) + (2 + 2 + 4);
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to bind local UDP socket
+    #[error("Failed to bind local UDP socket")]
+    BindUdp(#[source] io::Error),
+    /// Failed to get local UDP socket address
+    #[error("Failed to get local UDP socket address")]
+    GetUdpLocalAddress(#[source] io::Error),
+    /// Failed to create TCP socket
+    #[error("Failed to create TCP socket")]
+    CreateTcpSocket(#[source] io::Error),
+    /// Failed to set fwmark on remote socket
+    #[cfg(target_os = "linux")]
+    #[error("Failed to set fwmark on remote socket")]
+    SetFwmark(#[source] nix::Error),
+    /// Invalid TLS configuration
+    #[error("Invalid TLS configuration")]
+    TlsConfig(#[source] rustls::Error),
+    /// The server name is not a valid DNS name or IP address
+    #[error("Invalid server name: {0}")]
+    InvalidServerName(String),
+    /// The path must be absolute
+    #[error("Invalid WebSocket path: {0}")]
+    InvalidPath(String),
+    /// Failed to create the WebSocket handshake request
+    #[error("Invalid WebSocket request")]
+    InvalidRequest(#[source] tungstenite::Error),
+    /// Failed to wait for UDP client
+    #[error("Failed to wait for UDP client")]
+    WaitForUdpClient(#[source] io::Error),
+    /// Failed to connect to the server
+    #[error("Failed to connect to server")]
+    Connect(#[source] io::Error),
+    /// Failed to bind the TCP listener of the server
+    #[error("Failed to bind TCP listener")]
+    BindTcp(#[source] io::Error),
+    /// Failed to bind the UDP socket that the server forwards datagrams with
+    #[error("Failed to bind remote UDP socket")]
+    BindRemoteUdp(#[source] io::Error),
+    /// The TLS handshake failed
+    #[error("TLS handshake failed")]
+    TlsHandshake(#[source] io::Error),
+    /// The WebSocket handshake failed
+    #[error("WebSocket handshake failed")]
+    WebSocketHandshake(#[source] tungstenite::Error),
+}
+
+/// Settings for the WebSocket over TLS transport
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Server name used for SNI, the `Host` header and to verify the certificate of the server
+    pub server_name: String,
+    /// Path of the WebSocket endpoint, such as `/ws`
+    pub path: String,
+    /// Certificate to trust in addition to the Web PKI roots, as for
+    /// [`crate::quic::Settings::extra_root_certificate`].
+    pub extra_root_certificate: Option<CertificateDer<'static>>,
+}
+
+pub struct WebSocket {
+    udp_client_addr: SocketAddr,
+    server: tokio::task::JoinHandle<Result<()>>,
+    // The receiver will implicitly shut down when this is dropped
+    _shutdown_tx: oneshot::Sender<()>,
+    #[cfg(target_os = "android")]
+    outbound_fd: i32,
+}
+
+impl WebSocket {
+    pub(crate) async fn new(
+        peer: SocketAddr,
+        settings: &Settings,
+        #[cfg(target_os = "linux")] fwmark: Option<u32>,
+    ) -> Result<Self> {
+        let connector = tls_connector(settings.extra_root_certificate.clone())?;
+        let server_name = ServerName::try_from(settings.server_name.clone())
+            .map_err(|_| Error::InvalidServerName(settings.server_name.clone()))?;
+        if !settings.path.starts_with('/') {
+            return Err(Error::InvalidPath(settings.path.clone()));
+        }
+        let request = format!("wss://{}{}", settings.server_name, settings.path)
+            .into_client_request()
+            .map_err(Error::InvalidRequest)?;
+
+        let local_addr = if peer.is_ipv4() {
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 0))
+        } else {
+            SocketAddr::from((Ipv6Addr::LOCALHOST, 0))
+        };
+        let local_udp_socket = UdpSocket::bind(local_addr).await.map_err(Error::BindUdp)?;
+        let udp_client_addr = local_udp_socket
+            .local_addr()
+            .map_err(Error::GetUdpLocalAddress)?;
+
+        let tcp_socket = if peer.is_ipv4() {
+            TcpSocket::new_v4()
+        } else {
+            TcpSocket::new_v6()
+        }
+        .map_err(Error::CreateTcpSocket)?;
+        #[cfg(target_os = "linux")]
+        if let Some(fwmark) = fwmark {
+            setsockopt(tcp_socket.as_raw_fd(), sockopt::Mark, &fwmark).map_err(Error::SetFwmark)?;
+        }
+
+        #[cfg(target_os = "android")]
+        let outbound_fd = tcp_socket.as_raw_fd();
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+
+        let server = tokio::spawn(run_forwarding(
+            tcp_socket,
+            peer,
+            connector,
+            server_name,
+            request,
+            local_udp_socket,
+            shutdown_rx,
+        ));
+
+        Ok(WebSocket {
+            udp_client_addr,
+            server,
+            _shutdown_tx: shutdown_tx,
+            #[cfg(target_os = "android")]
+            outbound_fd,
+        })
+    }
+
+    pub(crate) fn endpoint(&self) -> SocketAddr {
+        self.udp_client_addr
+    }
+
+    pub(crate) async fn run(self) -> Result<()> {
+        match self.server.await {
+            Ok(result) => result,
+            Err(_err) if _err.is_cancelled() => Ok(()),
+            Err(_err) => panic!("server handle panicked"),
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    pub(crate) fn remote_socket_fd(&self) -> std::os::unix::io::RawFd {
+        self.outbound_fd
+    }
+
+    /// The overhead of TLS and WebSocket framing, in addition to that of TCP.
+    pub(crate) fn framing_overhead(&self) -> u16 {
+        FRAMING_OVERHEAD
+    }
+}
+
+fn tls_connector(extra_root_certificate: Option<CertificateDer<'static>>) -> Result<TlsConnector> {
+    let roots = root_certificates(extra_root_certificate).map_err(Error::TlsConfig)?;
+
+    let mut config =
+        rustls::ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(Error::TlsConfig)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    config.alpn_protocols = vec![ALPN_HTTP_1_1.to_vec()];
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+async fn run_forwarding(
+    tcp_socket: TcpSocket,
+    peer: SocketAddr,
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+    request: Request,
+    local_udp_socket: UdpSocket,
+    shutdown_rx: oneshot::Receiver<()>,
+) -> Result<()> {
+    wait_for_local_udp_client(&local_udp_socket)
+        .await
+        .map_err(Error::WaitForUdpClient)?;
+
+    let tcp_stream = tcp_socket.connect(peer).await.map_err(Error::Connect)?;
+    // Disables the Nagle algorithm on the TCP socket. Improves performance
+    if let Err(error) = tcp_stream.set_nodelay(true) {
+        log::warn!("Failed to set TCP_NODELAY: {error}");
+    }
+    let tls_stream = connector
+        .connect(server_name, tcp_stream)
+        .await
+        .map_err(Error::TlsHandshake)?;
+    let (websocket, _response) = tokio_tungstenite::client_async(request, tls_stream)
+        .await
+        .map_err(Error::WebSocketHandshake)?;
+    log::trace!("WebSocket connection established to {peer}");
+
+    let local_udp = Arc::new(local_udp_socket);
+    let (sink, stream) = websocket.split();
+
+    let mut client = tokio::spawn(forward_udp_to_websocket(sink, local_udp.clone()));
+    let mut server = tokio::spawn(forward_websocket_to_udp(stream, local_udp));
+
+    tokio::select! {
+        _ = shutdown_rx => {
+            log::trace!("Stopping WebSocket obfuscation");
+        }
+        _result = &mut server => log::trace!("WebSocket connection closed"),
+        _result = &mut client => log::trace!("Local UDP client closed"),
+    }
+
+    client.abort();
+    server.abort();
+
+    Ok(())
+}
+
+/// Send every datagram received on `udp` as a binary message to `sink`.
+async fn forward_udp_to_websocket<S>(
+    mut sink: SplitSink<WebSocketStream<S>, Message>,
+    udp: Arc<UdpSocket>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut rx_buffer = vec![0u8; u16::MAX as usize];
+
+    loop {
+        let read_n = match udp.recv(&mut rx_buffer).await {
+            Ok(read_n) => read_n,
+            Err(error) => {
+                log::error!("Failed to read from UDP socket: {error}");
+                break;
+            }
+        };
+
+        if let Err(error) = sink
+            .send(Message::Binary(rx_buffer[..read_n].to_vec()))
+            .await
+        {
+            log::error!("Failed to send WebSocket message: {error}");
+            break;
+        }
+    }
+}
+
+/// Send the payload of every binary message received on `stream` to `udp`.
+async fn forward_websocket_to_udp<S>(
+    mut stream: SplitStream<WebSocketStream<S>>,
+    udp: Arc<UdpSocket>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    while let Some(message) = stream.next().await {
+        let datagram = match message {
+            Ok(Message::Binary(datagram)) => datagram,
+            Ok(Message::Close(_)) => break,
+            // Control frames are handled by tungstenite, and text messages are not used
+            Ok(_) => continue,
+            Err(error) => {
+                log::debug!("Failed to read WebSocket message: {error}");
+                break;
+            }
+        };
+
+        if let Err(error) = udp.send(&datagram).await {
+            log::error!("Failed to write to UDP socket: {error}");
+            if is_fatal_socket_io_error(&error) {
+                break;
+            }
+        }
+    }
+}
+
+/// Settings for a WebSocket over TLS server
+#[derive(Debug)]
+pub struct ServerSettings {
+    /// Address to accept TCP connections on
+    pub listen_addr: SocketAddr,
+    /// WireGuard endpoint that datagrams are forwarded to
+    pub forward_addr: SocketAddr,
+    /// Path that WebSocket connections are accepted on. Other paths are rejected with 404.
+    pub path: String,
+    /// Certificate chain presented to clients
+    pub certificate_chain: Vec<CertificateDer<'static>>,
+    /// Private key of the first certificate in `certificate_chain`
+    pub private_key: PrivateKeyDer<'static>,
+}
+
+/// The WebSocket counterpart of [`crate::quic::Server`], for testing the client locally.
+pub struct Server {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    forward_addr: SocketAddr,
+    path: Arc<str>,
+}
+
+impl Server {
+    pub async fn new(settings: ServerSettings) -> Result<Self> {
+        let mut config =
+            rustls::ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+                .with_safe_default_protocol_versions()
+                .map_err(Error::TlsConfig)?
+                .with_no_client_auth()
+                .with_single_cert(settings.certificate_chain, settings.private_key)
+                .map_err(Error::TlsConfig)?;
+        config.alpn_protocols = vec![ALPN_HTTP_1_1.to_vec()];
+
+        let listener = TcpListener::bind(settings.listen_addr)
+            .await
+            .map_err(Error::BindTcp)?;
+
+        Ok(Server {
+            listener,
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+            forward_addr: settings.forward_addr,
+            path: settings.path.into(),
+        })
+    }
+
+    /// Returns the address that TCP connections are accepted on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(Error::BindTcp)
+    }
+
+    /// Accept and serve clients until accepting fails.
+    pub async fn run(self) -> Result<()> {
+        loop {
+            let (tcp_stream, remote_addr) = self.listener.accept().await.map_err(Error::Connect)?;
+            let acceptor = self.acceptor.clone();
+            let forward_addr = self.forward_addr;
+            let path = self.path.clone();
+            tokio::spawn(async move {
+                if let Err(error) = serve_client(tcp_stream, acceptor, path, forward_addr).await {
+                    log::error!("Failed to serve WebSocket client {remote_addr}: {error}");
+                }
+            });
+        }
+    }
+}
+
+async fn serve_client(
+    tcp_stream: tokio::net::TcpStream,
+    acceptor: TlsAcceptor,
+    path: Arc<str>,
+    forward_addr: SocketAddr,
+) -> Result<()> {
+    let tls_stream = acceptor
+        .accept(tcp_stream)
+        .await
+        .map_err(Error::TlsHandshake)?;
+    let check_path = |request: &ServerRequest, response: Response| {
+        if request.uri().path() == &*path {
+            Ok(response)
+        } else {
+            let mut response = ErrorResponse::new(None);
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            Err(response)
+        }
+    };
+    let websocket = tokio_tungstenite::accept_hdr_async(tls_stream, check_path)
+        .await
+        .map_err(Error::WebSocketHandshake)?;
+
+    let udp = connect_forward_socket(forward_addr)
+        .await
+        .map_err(Error::BindRemoteUdp)?;
+    let udp = Arc::new(udp);
+
+    let (sink, stream) = websocket.split();
+    tokio::select! {
+        _ = forward_websocket_to_udp(stream, udp.clone()) => (),
+        _ = forward_udp_to_websocket(sink, udp) => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        common::test_harness::{
+            assert_echoed, self_signed_certificate, spawn_echo_server, SERVER_NAME,
+        },
+        create_obfuscator, udp2tcp, Settings as ObfuscationSettings,
+    };
+
+    /// Send a datagram through a local client and server to a UDP echo server, and back.
+    #[tokio::test]
+    async fn test_forwarding() {
+        let (certificate, private_key) = self_signed_certificate();
+        let forward_addr = spawn_echo_server().await;
+
+        let server = Server::new(ServerSettings {
+            listen_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            forward_addr,
+            path: "/wg".to_string(),
+            certificate_chain: vec![certificate.clone()],
+            private_key,
+        })
+        .await
+        .unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let obfuscator = create_obfuscator(&ObfuscationSettings::Udp2Tcp(udp2tcp::Settings {
+            peer: server_addr,
+            websocket: Some(Settings {
+                server_name: SERVER_NAME.to_string(),
+                path: "/wg".to_string(),
+                extra_root_certificate: Some(certificate),
+            }),
+            #[cfg(target_os = "linux")]
+            fwmark: None,
+        }))
+        .await
+        .unwrap();
+        let obfuscator_addr = obfuscator.endpoint();
+        tokio::spawn(obfuscator.run());
+
+        assert_echoed(obfuscator_addr, &[b"handshake initiation"]).await;
+    }
+}